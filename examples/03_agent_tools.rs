@@ -62,6 +62,12 @@ impl WeatherTools {
     }
 }
 
+impl Default for WeatherTools {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 // We implement `ServerHandler` to provide server metadata and capabilities.
 #[tool_handler]
 impl ServerHandler for WeatherTools {
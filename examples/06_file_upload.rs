@@ -0,0 +1,82 @@
+use base64::{engine::general_purpose, Engine as _};
+use unia::{
+    files::FileUpload,
+    model::{MediaType, Message, Part},
+    providers::{gemini::Gemini, Provider},
+    Client,
+};
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // ============================================================================================
+    // Step 1: Setup Provider
+    // ============================================================================================
+    // Gemini limits requests to 20 MB, so large media such as videos or long PDFs cannot be
+    // sent inline. The Files API stores them for 48 hours and lets requests reference them.
+    let api_key = std::env::var("GEMINI_API_KEY").expect("GEMINI_API_KEY must be set");
+    let client = Gemini::create(api_key, "gemini-2.5-flash".to_string());
+
+    // ============================================================================================
+    // Step 2: Upload a File Explicitly
+    // ============================================================================================
+    // `upload_file` from the `FileUpload` trait takes the raw bytes and returns a `FileRef`.
+    // Its `to_part` is a `Part::Media` with empty `data` and the file's `uri`, pointing at the
+    // upload. OpenAI and Anthropic clients implement the same trait.
+    let pdf_url = "https://www.w3.org/WAI/ER/tests/xhtml/testfiles/resources/pdf/dummy.pdf";
+    println!("Fetching document from {}...", pdf_url);
+    let pdf_bytes = reqwest::get(pdf_url).await?.bytes().await?;
+
+    let file = client
+        .upload_file(pdf_bytes.to_vec(), "application/pdf", Some("dummy.pdf"))
+        .await?;
+    println!("Uploaded {} as {}", file.id, file.uri);
+
+    let message = Message::User(vec![
+        Part::Text {
+            content: "What does this document contain?".to_string(),
+            finished: true,
+        },
+        file.to_part(),
+    ]);
+
+    let response = client.request(vec![message], vec![]).await?;
+    let content = response
+        .data
+        .first()
+        .and_then(|m| m.content())
+        .unwrap_or_default();
+    println!("Response: {}", content);
+
+    // ============================================================================================
+    // Step 3: Let the Client Upload Automatically
+    // ============================================================================================
+    // Media above `GeminiModel::upload_threshold` (15 MB of base64 by default) is uploaded
+    // transparently, so regular inline parts work for large payloads as well. Each payload is
+    // only uploaded once per client, even when the conversation is sent again.
+    let message = Message::User(vec![
+        Part::Text {
+            content: "Summarize this document in one sentence.".to_string(),
+            finished: true,
+        },
+        Part::Media {
+            media_type: MediaType::Document,
+            data: general_purpose::STANDARD.encode(&pdf_bytes),
+            mime_type: "application/pdf".to_string(),
+            uri: Some(pdf_url.to_string()),
+            finished: true,
+        },
+    ]);
+
+    let response = client.request(vec![message], vec![]).await?;
+    let content = response
+        .data
+        .first()
+        .and_then(|m| m.content())
+        .unwrap_or_default();
+    println!("Response: {}", content);
+
+    // Uploaded files expire on their own, but can be deleted right away.
+    client.delete_file(&file).await?;
+
+    Ok(())
+}
@@ -0,0 +1,243 @@
+use futures::StreamExt;
+use serde_json::json;
+use std::future::Future;
+use unia::{
+    model::{MediaType, Message, Part},
+    providers::{
+        Anthropic, DeepSeek, Fireworks, Gemini, Groq, Mistral, OpenAI, OpenRouter, Provider,
+        Together, XAI,
+    },
+    stream::StreamDelta,
+    BoxedClient, Client, StreamingClient,
+};
+
+// A 16x16 red PNG, small enough to embed.
+const RED_SQUARE: &str = "iVBORw0KGgoAAAANSUhEUgAAABAAAAAQCAIAAACQkWg2AAAAFklEQVR42mP4z8BAEmIY1TCqYfhqAACQ+f8B8u7oVwAAAABJRU5ErkJggg==";
+
+// ============================================================================================
+// Step 1: Configure Providers
+// ============================================================================================
+// Every provider whose API key is set in the environment is tested. The model can be
+// overridden with `<PREFIX>_MODEL`, e.g. `OPENAI_MODEL=gpt-5-mini`.
+fn configured_clients() -> Vec<(&'static str, BoxedClient)> {
+    fn client<P>(prefix: &str, default_model: &str) -> Option<BoxedClient>
+    where
+        P: Provider,
+        P::Client: StreamingClient + 'static,
+    {
+        let key = std::env::var(format!("{}_API_KEY", prefix)).ok()?;
+        let model = std::env::var(format!("{}_MODEL", prefix))
+            .unwrap_or_else(|_| default_model.to_string());
+        Some(BoxedClient::new(P::create(key, model)))
+    }
+
+    [
+        ("OpenAI", client::<OpenAI>("OPENAI", "gpt-5-mini")),
+        (
+            "Anthropic",
+            client::<Anthropic>("ANTHROPIC", "claude-haiku-4-5"),
+        ),
+        ("Gemini", client::<Gemini>("GEMINI", "gemini-2.5-flash")),
+        (
+            "Mistral",
+            client::<Mistral>("MISTRAL", "mistral-small-latest"),
+        ),
+        (
+            "Groq",
+            client::<Groq>("GROQ", "meta-llama/llama-4-scout-17b-16e-instruct"),
+        ),
+        ("DeepSeek", client::<DeepSeek>("DEEPSEEK", "deepseek-chat")),
+        ("xAI", client::<XAI>("XAI", "grok-4-fast")),
+        (
+            "Together",
+            client::<Together>("TOGETHER", "meta-llama/Llama-4-Scout-17B-16E-Instruct"),
+        ),
+        (
+            "Fireworks",
+            client::<Fireworks>(
+                "FIREWORKS",
+                "accounts/fireworks/models/llama4-scout-instruct-basic",
+            ),
+        ),
+        (
+            "OpenRouter",
+            client::<OpenRouter>("OPENROUTER", "openai/gpt-5-mini"),
+        ),
+    ]
+    .into_iter()
+    .filter_map(|(name, client)| Some((name, client?)))
+    .collect()
+}
+
+fn user(content: &str) -> Message {
+    Message::User(vec![Part::Text {
+        content: content.to_string(),
+        finished: true,
+    }])
+}
+
+fn reply_text(messages: &[Message]) -> String {
+    messages
+        .iter()
+        .filter_map(|message| message.content())
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+// ============================================================================================
+// Step 2: Define the Scenarios
+// ============================================================================================
+// Each scenario sends the same request to every provider and checks the answer, so a
+// failure points at a difference the unified abstractions do not cover.
+async fn chat(client: &BoxedClient) -> Result<(), String> {
+    let response = client
+        .request(vec![user("Reply with the single word: pong")], vec![])
+        .await
+        .map_err(|e| e.to_string())?;
+    let text = reply_text(&response.data);
+    text.contains("pong")
+        .then_some(())
+        .ok_or_else(|| format!("unexpected reply {:?}", text))
+}
+
+async fn tools(client: &BoxedClient) -> Result<(), String> {
+    let schema = json!({
+        "type": "object",
+        "properties": { "city": { "type": "string" } },
+        "required": ["city"]
+    });
+    let tool = rmcp::model::Tool::new(
+        "get_weather",
+        "Get the current weather in a city",
+        schema.as_object().cloned().unwrap_or_default(),
+    );
+    let response = client
+        .request(vec![user("What is the weather in Paris?")], vec![tool])
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let call = response
+        .data
+        .iter()
+        .flat_map(|m| m.parts())
+        .find_map(|part| match part {
+            Part::FunctionCall {
+                name, arguments, ..
+            } => Some((name.clone(), arguments.clone())),
+            _ => None,
+        });
+    match call {
+        Some((name, arguments)) if name == "get_weather" && arguments["city"].is_string() => Ok(()),
+        Some((name, arguments)) => Err(format!("unexpected call {}({})", name, arguments)),
+        None => Err("no tool call".to_string()),
+    }
+}
+
+async fn streaming(client: &BoxedClient) -> Result<(), String> {
+    let mut stream = client
+        .request_stream_deltas(
+            vec![user("Count from 1 to 5, separated by spaces.")],
+            vec![],
+        )
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let mut text = String::new();
+    let mut deltas = 0;
+    while let Some(delta) = stream.next().await {
+        if let StreamDelta::TextDelta { text: fragment, .. } = delta.map_err(|e| e.to_string())? {
+            text.push_str(&fragment);
+            deltas += 1;
+        }
+    }
+    if !text.contains('5') {
+        return Err(format!("unexpected reply {:?}", text));
+    }
+    if deltas < 2 {
+        return Err("reply arrived in a single delta".to_string());
+    }
+    Ok(())
+}
+
+async fn vision(client: &BoxedClient) -> Result<(), String> {
+    let message = Message::User(vec![
+        Part::Text {
+            content: "What color is this image? Answer with one word.".to_string(),
+            finished: true,
+        },
+        Part::Media {
+            media_type: MediaType::Image,
+            data: RED_SQUARE.to_string(),
+            mime_type: "image/png".to_string(),
+            uri: None,
+            finished: true,
+        },
+    ]);
+    let response = client
+        .request(vec![message], vec![])
+        .await
+        .map_err(|e| e.to_string())?;
+    let text = reply_text(&response.data);
+    text.contains("red")
+        .then_some(())
+        .ok_or_else(|| format!("unexpected reply {:?}", text))
+}
+
+async fn check<F: Future<Output = Result<(), String>>>(
+    provider: &str,
+    scenario: &str,
+    future: F,
+    failures: &mut Vec<String>,
+) -> &'static str {
+    match future.await {
+        Ok(()) => "ok",
+        Err(e) => {
+            failures.push(format!("{} / {}: {}", provider, scenario, e));
+            "FAIL"
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let clients = configured_clients();
+    if clients.is_empty() {
+        eprintln!("No provider configured. Set e.g. OPENAI_API_KEY or GEMINI_API_KEY.");
+        return Ok(());
+    }
+
+    // ============================================================================================
+    // Step 3: Run the Matrix
+    // ============================================================================================
+    // Scenarios run one after another, so the output is not interleaved and rate limits
+    // are not an issue.
+    println!(
+        "{:<12} {:<40} {:<6} {:<6} {:<10} {:<6}",
+        "provider", "model", "chat", "tools", "streaming", "vision"
+    );
+    let mut failures = Vec::new();
+    for (name, client) in &clients {
+        let model = client.model_options().model.clone();
+        let chat = check(name, "chat", chat(client), &mut failures).await;
+        let tools = check(name, "tools", tools(client), &mut failures).await;
+        let streaming = check(name, "streaming", streaming(client), &mut failures).await;
+        let vision = check(name, "vision", vision(client), &mut failures).await;
+        println!(
+            "{:<12} {:<40} {:<6} {:<6} {:<10} {:<6}",
+            name, model, chat, tools, streaming, vision
+        );
+    }
+
+    // ============================================================================================
+    // Step 4: Report Failures
+    // ============================================================================================
+    if !failures.is_empty() {
+        println!("\nFailures:");
+        for failure in &failures {
+            println!("- {}", failure);
+        }
+        std::process::exit(1);
+    }
+    Ok(())
+}
@@ -1,12 +1,38 @@
 //! Agent struct for automatic tool execution with LLM providers.
 
-use crate::client::{Client, ClientError};
-use crate::model::{FinishReason, Message, Part, Response, Usage};
-use serde_json::json;
-use std::collections::HashMap;
+use crate::batch::DEFAULT_CONCURRENCY;
+use crate::client::{BoxedClient, Client, ClientError};
+use crate::model::{breakdown, FinishReason, IterationUsage, Message, Part, Response, Usage};
+use async_trait::async_trait;
+use futures::future::{BoxFuture, Either};
+use futures::stream::FuturesUnordered;
+use futures::{Stream, StreamExt, TryStreamExt};
+use rmcp::model::Tool;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::future::Future;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::Duration;
+use thiserror::Error;
+use tokio::time::Instant;
+use tokio_util::sync::CancellationToken;
 use tracing::{debug, info, warn};
 
-use crate::mcp::MCPServer;
+use crate::cost::CostTracker;
+use crate::mcp::{with_timeout, ListChanged, ListChanges, MCPError, MCPServer, MCPTimeouts};
+use crate::memory::Conversation;
+use crate::moderation::{SafetyPolicy, SafetyStage, SafetyVerdict};
+use crate::prompt::prompt_version;
+use crate::ratelimit::RateLimiter;
+use crate::schema::RUN_FORMAT_VERSION;
+use crate::stream::{diff_snapshots, StreamDelta};
+use crate::tokenize::{count_tokens, estimate_tokens, CHARS_PER_TOKEN};
+use crate::tools::canonicalize;
 
 /// Agent that automatically executes tools in a loop.
 ///
@@ -35,10 +61,89 @@ use crate::mcp::MCPServer;
 ///
 /// let response = agent.chat(messages).await?;
 /// ```
-pub struct Agent<C: Client> {
+///
+/// The client type defaults to [`BoxedClient`], so agents of different providers
+/// can be stored together as `Agent`:
+///
+/// ```ignore
+/// let agents: Vec<Agent> = vec![
+///     Agent::new(BoxedClient::new(openai_client)),
+///     Agent::new(BoxedClient::new(gemini_client)),
+/// ];
+/// ```
+pub struct Agent<C: Client = BoxedClient> {
     client: C,
     max_iterations: usize,
+    on_max_iterations: OnMaxIterations,
+    force_final_answer: bool,
     server: Option<Box<dyn MCPServer>>,
+    strict_tool_arguments: bool,
+    hooks: Vec<Box<dyn AgentHooks>>,
+    rate_limiter: Option<Arc<RateLimiter>>,
+    tool_concurrency: usize,
+    eager_tools: bool,
+    timeout: Option<Duration>,
+    cost_tracker: Option<Arc<CostTracker>>,
+    variant: Option<String>,
+    safety_policy: Option<Box<dyn SafetyPolicy>>,
+    tool_result_policy: Option<ToolResultPolicy>,
+    tool_cache: Option<ToolCache>,
+    titles: Mutex<HashMap<u64, String>>,
+    mcp_timeouts: MCPTimeouts,
+}
+
+/// Number of leading messages included in the prompt for [`Agent::title`].
+const TITLE_MESSAGES: usize = 6;
+
+/// Maximum number of characters per message included in the title prompt.
+const TITLE_MESSAGE_CHARS: usize = 500;
+
+const TITLE_INSTRUCTION: &str = "Write a short title of at most six words for the \
+following conversation. Reply with the title only, without quotes or trailing punctuation.";
+
+/// Callbacks for observing the agent loop.
+///
+/// All methods default to doing nothing, so implementations only override the
+/// events they care about. Hooks are called in the order they were added.
+///
+/// ```ignore
+/// struct AuditLog;
+///
+/// #[async_trait]
+/// impl AgentHooks for AuditLog {
+///     async fn on_tool_call(&self, name: &str, arguments: &mut Value) {
+///         println!("calling {} with {}", name, arguments);
+///     }
+/// }
+///
+/// let agent = Agent::new(client).with_server(server).with_hooks(AuditLog);
+/// ```
+#[async_trait]
+pub trait AgentHooks: Send + Sync {
+    /// Called at the start of every iteration of the agent loop, counting from 0.
+    async fn on_iteration(&self, _iteration: usize) {}
+
+    /// Called before a request is sent to the model.
+    async fn on_llm_request(&self, _messages: &[Message], _tools: &[Tool]) {}
+
+    /// Called with the complete model response of an iteration.
+    async fn on_llm_response(&self, _response: &Response) {}
+
+    /// Called before a tool is executed. The arguments may be modified.
+    async fn on_tool_call(&self, _name: &str, _arguments: &mut Value) {}
+
+    /// Called with the result of a tool call, including errors reported to the model.
+    async fn on_tool_result(&self, _name: &str, _result: &Part) {}
+
+    /// Called before the agent waits for rate limit quota to free up.
+    async fn on_rate_limit(&self, _delay: Duration) {}
+
+    /// Called when a [`chat_stream`](Agent::chat_stream) is dropped before it
+    /// finished, with the messages and usage of the run so far and finish reason
+    /// [`FinishReason::Error`]. Tool calls without a response were cancelled.
+    ///
+    /// Called synchronously while the stream is dropped, so it must not block.
+    fn on_abort(&self, _partial: &Response) {}
 }
 
 impl<C: Client> Agent<C> {
@@ -52,7 +157,22 @@ impl<C: Client> Agent<C> {
         Self {
             client,
             max_iterations: 10,
+            on_max_iterations: OnMaxIterations::default(),
+            force_final_answer: false,
             server: None,
+            strict_tool_arguments: false,
+            hooks: Vec::new(),
+            rate_limiter: None,
+            tool_concurrency: DEFAULT_CONCURRENCY,
+            eager_tools: false,
+            timeout: None,
+            cost_tracker: None,
+            variant: None,
+            safety_policy: None,
+            tool_result_policy: None,
+            tool_cache: None,
+            titles: Mutex::new(HashMap::new()),
+            mcp_timeouts: MCPTimeouts::default(),
         }
     }
 
@@ -62,12 +182,631 @@ impl<C: Client> Agent<C> {
         self
     }
 
+    /// Set the timeouts for listing tools and calling them on the MCP server.
+    ///
+    /// A tool call that times out is cancelled and reported to the model as an
+    /// error. Defaults to [`MCPTimeouts::default`].
+    pub fn with_mcp_timeouts(mut self, timeouts: MCPTimeouts) -> Self {
+        self.mcp_timeouts = timeouts;
+        self
+    }
+
     /// Set the maximum number of iterations for the agentic loop.
     pub fn with_max_iterations(mut self, max: usize) -> Self {
         self.max_iterations = max;
         self
     }
 
+    /// Set what happens when the agentic loop reaches its maximum number of
+    /// iterations. Defaults to [`OnMaxIterations::ReturnPartial`].
+    pub fn on_max_iterations(mut self, behavior: OnMaxIterations) -> Self {
+        self.on_max_iterations = behavior;
+        self
+    }
+
+    /// Withhold the tools on the last permitted iteration and ask the model to
+    /// answer with the information gathered so far.
+    ///
+    /// The run then ends with an answer for the user instead of a tool call
+    /// that is never executed. This is [`OnMaxIterations::FinalAnswer`] with the
+    /// final turn counted against [`with_max_iterations`](Self::with_max_iterations),
+    /// and overrides [`on_max_iterations`](Self::on_max_iterations). Disabled by
+    /// default.
+    pub fn with_forced_final_answer(mut self, enabled: bool) -> Self {
+        self.force_final_answer = enabled;
+        self
+    }
+
+    /// Reject tool calls whose arguments were not valid JSON instead of running
+    /// them with repaired arguments.
+    ///
+    /// Rejected calls are answered with an error so the model can retry.
+    pub fn with_strict_tool_arguments(mut self, strict: bool) -> Self {
+        self.strict_tool_arguments = strict;
+        self
+    }
+
+    /// Add hooks observing the agent loop.
+    pub fn with_hooks<H: AgentHooks + 'static>(mut self, hooks: H) -> Self {
+        self.hooks.push(Box::new(hooks));
+        self
+    }
+
+    /// Set how many tool calls of a single response are executed concurrently.
+    ///
+    /// Results are always returned to the model in the order of the calls. Set to
+    /// 1 to execute tools one after another.
+    pub fn with_tool_concurrency(mut self, concurrency: usize) -> Self {
+        self.tool_concurrency = concurrency;
+        self
+    }
+
+    /// Start executing tool calls while the response is still streaming.
+    ///
+    /// In [`chat_stream`](Self::chat_stream), a call is executed as soon as the
+    /// provider marks its arguments complete, so slow tools overlap with the rest
+    /// of the generation. Calls may then run even if the stream fails later in
+    /// the turn. Disabled by default.
+    pub fn with_eager_tool_execution(mut self, eager: bool) -> Self {
+        self.eager_tools = eager;
+        self
+    }
+
+    /// Limit the total duration of a run.
+    ///
+    /// When the deadline passes, the pending model request is dropped, in-flight
+    /// tool calls are cancelled on their servers and the run fails with
+    /// [`ClientError::DeadlineExceeded`].
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Pace requests with a rate limiter.
+    ///
+    /// Before every iteration the agent estimates the prompt size and waits until
+    /// the limiter has quota for it, instead of sending a request that would be
+    /// rejected. The limiter can be shared between agents using the same API key.
+    pub fn with_rate_limiter(mut self, limiter: Arc<RateLimiter>) -> Self {
+        self.rate_limiter = Some(limiter);
+        self
+    }
+
+    /// Record the usage and cost of every model response in a tracker.
+    ///
+    /// Responses of [`chat_in`](Self::chat_in) are attributed to the conversation
+    /// they belong to. The tracker can be shared between agents.
+    pub fn with_cost_tracker(mut self, tracker: Arc<CostTracker>) -> Self {
+        self.cost_tracker = Some(tracker);
+        self
+    }
+
+    /// Tag the runs of the agent with an experiment variant.
+    ///
+    /// The variant is recorded in the cost tracker, in [`AgentRun`] records and
+    /// on the `invoke_agent` span. See [`Experiment`](crate::experiment::Experiment).
+    pub fn with_variant(mut self, variant: impl Into<String>) -> Self {
+        self.variant = Some(variant.into());
+        self
+    }
+
+    /// Screen the messages of every run with a safety policy.
+    ///
+    /// The trailing user messages passed to a run are screened before the first
+    /// request, tool results before they are sent back to the model, and model
+    /// messages before tool calls are executed. Redacted messages replace the
+    /// original ones, in the response as well, and a rejection fails the run
+    /// with [`ClientError::PolicyViolation`]. In
+    /// [`chat_stream`](Self::chat_stream), model messages are screened once
+    /// their turn is complete, so earlier snapshots are not screened, and with
+    /// [eager tool execution](Self::with_eager_tool_execution) their tool calls
+    /// may already be running.
+    pub fn with_safety_policy<P: SafetyPolicy + 'static>(mut self, policy: P) -> Self {
+        self.safety_policy = Some(Box::new(policy));
+        self
+    }
+
+    /// Limit the size of tool results before they are added to the conversation.
+    ///
+    /// Results are unlimited by default, so a single large result can fill the
+    /// model's context window.
+    pub fn with_tool_result_policy(mut self, policy: ToolResultPolicy) -> Self {
+        self.tool_result_policy = Some(policy);
+        self
+    }
+
+    /// Answer repeated tool calls from a cache instead of calling the MCP server
+    /// again.
+    ///
+    /// Successful results are kept for `ttl`, keyed on the tool name and its
+    /// arguments, within the conversation they were made in: a single run, or
+    /// all runs of a [`chat_in`](Self::chat_in) conversation. A model stuck
+    /// repeating the same call then gets the same answer without further load
+    /// on the server. Only suitable for tools without side effects.
+    pub fn with_tool_cache(mut self, ttl: Duration) -> Self {
+        self.tool_cache = Some(ToolCache::new(ttl));
+        self
+    }
+
+    /// Screen a message with the safety policy, returning the message to use.
+    async fn screen(&self, message: Message, stage: SafetyStage) -> Result<Message, ClientError> {
+        let Some(policy) = &self.safety_policy else {
+            return Ok(message);
+        };
+        match policy.screen(&message, stage).await? {
+            SafetyVerdict::Allow => Ok(message),
+            SafetyVerdict::Redact(redacted) => {
+                info!("Safety policy redacted a {:?} message", stage);
+                Ok(redacted)
+            }
+            SafetyVerdict::Reject(reason) => {
+                warn!("Safety policy rejected a {:?} message: {}", stage, reason);
+                Err(ClientError::PolicyViolation(reason))
+            }
+        }
+    }
+
+    /// Screen the user messages after the last model message, i.e. the new
+    /// input of a run.
+    async fn screen_input(&self, messages: &mut [Message]) -> Result<(), AgentError> {
+        if self.safety_policy.is_none() {
+            return Ok(());
+        }
+        let start = messages
+            .iter()
+            .rposition(|message| matches!(message, Message::Assistant(_)))
+            .map_or(0, |index| index + 1);
+        for message in &mut messages[start..] {
+            let screened = self.screen(message.clone(), SafetyStage::Input).await;
+            *message = screened.map_err(|source| AgentError {
+                partial: Response {
+                    data: Vec::new(),
+                    usage: Usage::default(),
+                    finish: FinishReason::Error,
+                    alternatives: Vec::new(),
+                    model: None,
+                    iterations: Vec::new(),
+                },
+                source,
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Observe a model response: run the hooks and record its cost.
+    async fn observe_response(&self, response: &Response, conversation: Option<&str>) {
+        for hooks in &self.hooks {
+            hooks.on_llm_response(response).await;
+        }
+        if let Some(tracker) = &self.cost_tracker {
+            // Price the model that served the request, which may be a snapshot
+            // with its own price rather than the requested alias.
+            let model = match &response.model {
+                Some(model) => model,
+                None => &self.client.model_options().model,
+            };
+            let variant = self.variant.as_deref();
+            if let Some(cost) =
+                tracker.record_variant(conversation, variant, model, &response.usage)
+            {
+                debug!("Request cost: ${:.6}", cost);
+            }
+        }
+    }
+
+    /// Wait for rate limit quota for the next request, if a limiter is configured.
+    async fn pace(&self, messages: &[Message], tools: &[Tool]) {
+        let Some(limiter) = &self.rate_limiter else {
+            return;
+        };
+
+        let model = &self.client.model_options().model;
+        let tokens = count_tokens(messages, model) + estimate_tokens(&[], tools);
+        let delay = limiter.delay_for(tokens);
+        if !delay.is_zero() {
+            info!(
+                "Rate limit nearly exhausted, delaying next request by {:?}",
+                delay
+            );
+            for hooks in &self.hooks {
+                hooks.on_rate_limit(delay).await;
+            }
+        }
+        limiter.acquire(tokens).await;
+    }
+
+    /// The `invoke_agent` span of a run.
+    #[cfg(feature = "otel")]
+    fn otel_span(&self) -> tracing::Span {
+        let model_options = self.client.model_options();
+        crate::otel::agent_span(
+            self.client.provider(),
+            &model_options.model,
+            self.variant.as_deref(),
+            model_options
+                .system
+                .as_deref()
+                .map(prompt_version)
+                .as_deref(),
+        )
+    }
+
+    /// Tools of the MCP server, with the ID of the server providing each tool.
+    async fn list_tools(&self) -> Result<(Vec<Tool>, HashMap<String, Option<String>>), MCPError> {
+        let Some(server) = &self.server else {
+            return Ok((Vec::new(), HashMap::new()));
+        };
+        let tools = with_timeout("list_tools", self.mcp_timeouts.list, server.list_tools()).await?;
+        let map = tools
+            .iter()
+            .map(|t| (t.value.name.to_string(), t.server_id.clone()))
+            .collect();
+        Ok((tools.into_iter().map(|t| t.value).collect(), map))
+    }
+
+    /// Subscribe to the list changes of the MCP server. Subscribing before the
+    /// tools are listed ensures no change is missed.
+    fn subscribe(&self) -> ListChanges {
+        self.server
+            .as_ref()
+            .map(|server| server.subscribe())
+            .unwrap_or_default()
+    }
+
+    /// List the tools again if the server announced that they changed, so
+    /// tools added or removed during a run are seen by the next request.
+    async fn refresh_tools(
+        &self,
+        changes: &mut ListChanges,
+        tools: &mut Vec<Tool>,
+        tool_map: &mut HashMap<String, Option<String>>,
+    ) {
+        if !changes.pending().contains(&ListChanged::Tools) {
+            return;
+        }
+        debug!("MCP tool list changed, listing tools again");
+        match self.list_tools().await {
+            Ok((new_tools, new_map)) => {
+                *tools = new_tools;
+                *tool_map = new_map;
+            }
+            Err(e) => warn!("Failed to list changed tools from MCP server: {}", e),
+        }
+    }
+
+    /// Iterations that may run tools, and what happens once they are used up.
+    ///
+    /// With [`with_forced_final_answer`](Self::with_forced_final_answer), the last
+    /// iteration is kept for the turn of [`OnMaxIterations::FinalAnswer`].
+    fn iteration_budget(&self) -> (usize, OnMaxIterations) {
+        if self.force_final_answer && self.max_iterations > 0 {
+            (self.max_iterations - 1, OnMaxIterations::FinalAnswer)
+        } else {
+            (self.max_iterations, self.on_max_iterations)
+        }
+    }
+
+    /// Execute tool calls concurrently, returning the response parts in call order.
+    async fn execute_tools(
+        &self,
+        tool_map: &HashMap<String, Option<String>>,
+        calls: &[&Part],
+        control: &RunControl,
+    ) -> Result<Vec<Part>, ClientError> {
+        let cancel = control.cancel.child_token();
+        let mut pending = Vec::with_capacity(calls.len());
+        for part in calls {
+            if let Part::FunctionCall {
+                id,
+                name,
+                arguments,
+                raw_arguments,
+                ..
+            } = part
+            {
+                info!("Tool call requested: {}", name);
+                debug!("Tool arguments: {}", arguments);
+                pending.push(self.execute_tool(
+                    tool_map,
+                    id,
+                    name,
+                    arguments,
+                    raw_arguments.as_deref(),
+                    control,
+                    &cancel,
+                ));
+            }
+        }
+
+        let results = futures::stream::iter(pending)
+            .buffered(self.tool_concurrency.max(1))
+            .try_collect();
+        tokio::pin!(results);
+
+        tokio::select! {
+            results = &mut results => results,
+            err = control.stopped() => {
+                cancel.cancel();
+                // Keep polling so in-flight calls can notify their servers.
+                let _ = results.await;
+                Err(err)
+            }
+        }
+    }
+
+    /// Execute a single tool call and build the response part for it.
+    ///
+    /// Calls with unparseable arguments (or repaired ones in strict mode) are not
+    /// executed. If a call with repaired arguments fails, the original text is
+    /// included in the error so the model can see what went wrong.
+    #[allow(clippy::too_many_arguments)]
+    async fn execute_tool(
+        &self,
+        tool_map: &HashMap<String, Option<String>>,
+        id: &Option<String>,
+        name: &String,
+        arguments: &Value,
+        raw_arguments: Option<&str>,
+        control: &RunControl,
+        cancel: &CancellationToken,
+    ) -> Result<Part, ClientError> {
+        let call = self.call_tool(
+            tool_map,
+            id,
+            name,
+            arguments,
+            raw_arguments,
+            &control.scope,
+            cancel,
+        );
+        #[cfg(feature = "otel")]
+        let span = crate::otel::tool_span(name, id.as_deref());
+        #[cfg(feature = "otel")]
+        let call = tracing::Instrument::instrument(call, span.clone());
+        let result = call.await;
+        #[cfg(feature = "otel")]
+        if let Err(e) = &result {
+            crate::otel::record_error(&span, e);
+        }
+        let part = self.limit_tool_result(result?, control).await;
+        for hooks in &self.hooks {
+            hooks.on_tool_result(name, &part).await;
+        }
+        if control.events.is_some() {
+            // Screened on its own, as the event is sent before the results of
+            // the turn are screened together.
+            let screened = self
+                .screen(Message::User(vec![part.clone()]), SafetyStage::Input)
+                .await;
+            // A result the policy rejects, or does not return as a single part, is
+            // reported as withheld rather than left out.
+            let withheld = |reason: String| Part::FunctionResponse {
+                id: id.clone(),
+                name: name.clone(),
+                response: json!({ "error": format!("Tool result withheld: {}", reason) }),
+                parts: vec![],
+                finished: true,
+            };
+            let result = match screened {
+                Ok(Message::User(mut parts)) if parts.len() == 1 => parts.remove(0),
+                Ok(_) => withheld("redacted by the safety policy".to_string()),
+                Err(e) => withheld(e.to_string()),
+            };
+            control.emit(AgentEvent::ToolCallFinished {
+                id: id.clone(),
+                name: name.clone(),
+                result,
+            });
+        }
+        Ok(part)
+    }
+
+    /// Shorten a tool result over the limit of the tool result policy.
+    async fn limit_tool_result(&self, mut part: Part, control: &RunControl) -> Part {
+        let Some(policy) = &self.tool_result_policy else {
+            return part;
+        };
+        let Part::FunctionResponse { name, response, .. } = &mut part else {
+            return part;
+        };
+        let text = match &*response {
+            Value::String(text) => text.clone(),
+            response => response.to_string(),
+        };
+        let Some(limit) = policy.limit().filter(|limit| text.len() > *limit) else {
+            return part;
+        };
+
+        info!(
+            "Tool {} result of {} bytes exceeds the limit of {} bytes",
+            name,
+            text.len(),
+            limit
+        );
+        let summary = match policy.strategy {
+            TruncationStrategy::Summarize => {
+                match self
+                    .summarize_tool_result(name, &text, limit, control)
+                    .await
+                {
+                    Ok(summary) => Some(summary),
+                    Err(e) => {
+                        warn!("Failed to summarize the result of tool {}: {}", name, e);
+                        None
+                    }
+                }
+            }
+            _ => None,
+        };
+        *response = match summary {
+            Some(summary) => json!({
+                "summary": summary,
+                "note": format!("Summary of a result of {} bytes", text.len()),
+            }),
+            None if policy.strategy == TruncationStrategy::Tail => {
+                let kept = tail(&text, limit);
+                json!({
+                    "content": kept,
+                    "note": format!("Truncated to the last {} of {} bytes", kept.len(), text.len()),
+                })
+            }
+            None => {
+                let kept = head(&text, limit);
+                json!({
+                    "content": kept,
+                    "note": format!("Truncated to the first {} of {} bytes", kept.len(), text.len()),
+                })
+            }
+        };
+        part
+    }
+
+    /// Summarize a tool result with the agent's model in at most `limit` bytes.
+    async fn summarize_tool_result(
+        &self,
+        name: &str,
+        text: &str,
+        limit: usize,
+        control: &RunControl,
+    ) -> Result<String, ClientError> {
+        let prompt = Message::User(vec![Part::Text {
+            content: format!(
+                "{}\n\nTool: {}\nMaximum length: {} characters\n\n{}",
+                SUMMARY_INSTRUCTION,
+                name,
+                limit,
+                head(text, SUMMARY_INPUT_BYTES)
+            ),
+            finished: true,
+        }]);
+        let response = self.side_request(vec![prompt], control).await?;
+        let summary = response
+            .data
+            .iter()
+            .map(message_text)
+            .collect::<Vec<_>>()
+            .join("\n");
+        if summary.trim().is_empty() {
+            return Err(ClientError::ProviderError(
+                "Model returned an empty summary".to_string(),
+            ));
+        }
+        Ok(head(summary.trim(), limit).to_string())
+    }
+
+    /// Send a request of a run besides its turns, with the hooks, pacing, cost
+    /// tracking and deadline of a turn. Its usage is added to the run total by
+    /// the next [`RunControl::take_usage`].
+    async fn side_request(
+        &self,
+        messages: Vec<Message>,
+        control: &RunControl,
+    ) -> Result<Response, ClientError> {
+        for hooks in &self.hooks {
+            hooks.on_llm_request(&messages, &[]).await;
+        }
+        control.run(self.pace(&messages, &[])).await?;
+
+        let response = control
+            .run(self.client.request(messages, Vec::new()))
+            .await??;
+        self.observe_response(&response, control.conversation.as_deref())
+            .await;
+        *control.usage.lock().unwrap() += &response.usage;
+        Ok(response)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn call_tool(
+        &self,
+        tool_map: &HashMap<String, Option<String>>,
+        id: &Option<String>,
+        name: &String,
+        arguments: &Value,
+        raw_arguments: Option<&str>,
+        scope: &str,
+        cancel: &CancellationToken,
+    ) -> Result<Part, ClientError> {
+        let error_response = |message: String| Part::FunctionResponse {
+            id: id.clone(),
+            name: name.clone(),
+            response: json!({ "error": message }),
+            parts: vec![],
+            finished: true,
+        };
+
+        if let Some(raw) = raw_arguments {
+            if arguments.is_null() || self.strict_tool_arguments {
+                warn!("Rejected tool {} call with malformed arguments", name);
+                return Ok(error_response(format!(
+                    "Error: tool arguments are not valid JSON: {}",
+                    raw
+                )));
+            }
+        }
+
+        let mut arguments = arguments.clone();
+        for hooks in &self.hooks {
+            hooks.on_tool_call(name, &mut arguments).await;
+        }
+
+        let with_id = |mut part: Part| {
+            if let Part::FunctionResponse {
+                id: ref mut pid, ..
+            } = part
+            {
+                *pid = id.clone();
+            }
+            part
+        };
+        let cached = self
+            .tool_cache
+            .as_ref()
+            .map(|cache| (cache, ToolCache::key(scope, name, &arguments)));
+        if let Some(part) = cached.as_ref().and_then(|(cache, key)| cache.get(key)) {
+            info!("Tool {} answered from cache", name);
+            return Ok(with_id(part));
+        }
+
+        let server = self
+            .server
+            .as_ref()
+            .ok_or_else(|| ClientError::Config("No MCP server configured".to_string()))?;
+        let server_id = tool_map.get(name).cloned().flatten();
+        let result = if cancel.is_cancelled() {
+            Err(MCPError::Cancelled)
+        } else {
+            with_timeout(
+                "call_tool",
+                self.mcp_timeouts.call_tool,
+                server.call_tool_cancellable(name.clone(), arguments, server_id, cancel.clone()),
+            )
+            .await
+        };
+
+        Ok(match result {
+            Ok(part) => {
+                info!("Tool {} executed successfully", name);
+                debug!("Tool result: {:?}", part);
+                if let Some((cache, key)) = cached {
+                    cache.put(key, part.clone());
+                }
+                with_id(part)
+            }
+            Err(e) => {
+                warn!("Tool {} execution failed: {}", name, e);
+                let mut message = format!("Error: {}", e);
+                if let Some(raw) = raw_arguments {
+                    message.push_str(&format!(
+                        " (arguments were repaired from malformed JSON: {})",
+                        raw
+                    ));
+                }
+                error_response(message)
+            }
+        })
+    }
+
     /// Send a chat request with automatic tool execution.
     ///
     /// This method automatically handles the tool execution loop:
@@ -79,116 +818,325 @@ impl<C: Client> Agent<C> {
     /// - `messages`: Conversation messages
     ///
     /// # Returns
-    /// The response containing all new messages generated during the execution (including tool calls and results)
-    pub async fn chat(&self, mut messages: Vec<Message>) -> Result<Response, ClientError> {
-        debug!(
-            "Starting agent chat loop with {} initial messages",
-            messages.len()
-        );
+    /// The response containing all new messages generated during the execution (including tool calls and results).
+    /// On failure, the returned [`AgentError`] carries the messages and usage accumulated before the error.
+    pub async fn chat(&self, messages: Vec<Message>) -> Result<Response, AgentError> {
+        self.chat_with_cancellation(messages, CancellationToken::new())
+            .await
+    }
 
+    /// Like [`chat`](Self::chat), but aborts when `cancel` is triggered.
+    ///
+    /// On cancellation the in-flight model request is dropped, running tool calls
+    /// are cancelled and [`ClientError::StreamCancelled`] is returned.
+    pub async fn chat_with_cancellation(
+        &self,
+        mut messages: Vec<Message>,
+        cancel: CancellationToken,
+    ) -> Result<Response, AgentError> {
+        self.screen_input(&mut messages).await?;
+        let control = RunControl::new(cancel, self.timeout);
+        self.chat_until_stopped(messages, None, &control, None)
+            .await
+    }
+
+    /// Preview what the agent would do, without executing any tool.
+    ///
+    /// The model is called as in [`chat`](Self::chat), but tool calls are
+    /// answered by `responder` instead of the MCP server, and the loop continues
+    /// with its stub results. The returned [`DryRun`] lists the planned calls of
+    /// every iteration. Hooks observing tool execution are not called.
+    ///
+    /// ```ignore
+    /// let preview = agent
+    ///     .dry_run(messages, |_name: &str, _arguments: &Value| json!({ "status": "ok" }))
+    ///     .await?;
+    /// for step in &preview.steps {
+    ///     println!("iteration {}: {:?}", step.iteration, step.calls);
+    /// }
+    /// ```
+    pub async fn dry_run<R: DryRunResponder>(
+        &self,
+        mut messages: Vec<Message>,
+        responder: R,
+    ) -> Result<DryRun, AgentError> {
+        self.screen_input(&mut messages).await?;
+        let mut state = DryRunState {
+            responder: &responder,
+            steps: Vec::new(),
+        };
+        let control = RunControl::new(CancellationToken::new(), self.timeout);
+        let response = self
+            .chat_until_stopped(messages, None, &control, Some(&mut state))
+            .await?;
+        Ok(DryRun {
+            steps: state.steps,
+            response,
+        })
+    }
+
+    async fn chat_until_stopped(
+        &self,
+        messages: Vec<Message>,
+        conversation: Option<&str>,
+        control: &RunControl,
+        dry_run: Option<&mut DryRunState<'_>>,
+    ) -> Result<Response, AgentError> {
         let mut current_response = Response {
             data: Vec::new(),
             usage: Usage::default(),
             finish: FinishReason::Unfinished,
+            alternatives: Vec::new(),
+            model: None,
+            iterations: Vec::new(),
         };
+        let result = self.chat_loop(
+            messages,
+            conversation,
+            control,
+            &mut current_response,
+            dry_run,
+        );
+        #[cfg(feature = "otel")]
+        let span = self.otel_span();
+        #[cfg(feature = "otel")]
+        let result = tracing::Instrument::instrument(result, span.clone());
+        let result = result.await;
 
-        let (tools, tool_map) = if let Some(server) = &self.server {
-            match server.list_tools().await {
-                Ok(tools) => {
-                    let map: HashMap<String, Option<String>> = tools
-                        .iter()
-                        .map(|t| (t.value.name.to_string(), t.server_id.clone()))
-                        .collect();
-                    (tools.into_iter().map(|t| t.value).collect(), map)
-                }
-                Err(e) => {
-                    return Err(ClientError::ProviderError(format!(
-                        "Failed to list tools from MCP server: {}",
-                        e
-                    )));
-                }
+        #[cfg(feature = "otel")]
+        match &result {
+            Ok(()) => crate::otel::record_response(
+                &span,
+                &current_response.usage,
+                &current_response.finish,
+            ),
+            Err(e) => crate::otel::record_error(&span, e),
+        }
+        match result {
+            Ok(()) => Ok(current_response),
+            Err(source) => {
+                current_response.finish = FinishReason::Error;
+                Err(AgentError {
+                    partial: current_response,
+                    source,
+                })
             }
-        } else {
-            (Vec::new(), HashMap::new())
-        };
+        }
+    }
 
-        for iteration in 0..self.max_iterations {
-            debug!("Agent iteration {}/{}", iteration + 1, self.max_iterations);
+    /// Run the tool loop, accumulating generated messages into `current_response`.
+    ///
+    /// In a dry run, tool calls are answered by the stub responder.
+    async fn chat_loop(
+        &self,
+        mut messages: Vec<Message>,
+        conversation: Option<&str>,
+        control: &RunControl,
+        current_response: &mut Response,
+        mut dry_run: Option<&mut DryRunState<'_>>,
+    ) -> Result<(), ClientError> {
+        debug!(
+            "Starting agent chat loop with {} initial messages",
+            messages.len()
+        );
 
-            let response = self.client.request(messages.clone(), tools.clone()).await?;
+        let mut changes = self.subscribe();
+        let (mut tools, mut tool_map) = self.list_tools().await.map_err(|e| {
+            ClientError::ProviderError(format!("Failed to list tools from MCP server: {}", e))
+        })?;
+
+        let (max_iterations, on_max_iterations) = self.iteration_budget();
+        for iteration in 0..max_iterations {
+            debug!("Agent iteration {}/{}", iteration + 1, max_iterations);
+            self.refresh_tools(&mut changes, &mut tools, &mut tool_map)
+                .await;
+            for hooks in &self.hooks {
+                hooks.on_iteration(iteration).await;
+                hooks.on_llm_request(&messages, &tools).await;
+            }
+            control.run(self.pace(&messages, &tools)).await?;
+
+            let response = control
+                .run(self.client.request(messages.clone(), tools.clone()))
+                .await??;
+            self.observe_response(&response, conversation).await;
+            current_response.iterations.push((&response).into());
             current_response.usage += response.usage;
             current_response.finish = response.finish.clone();
+            current_response.model = response.model.clone().or(current_response.model.take());
 
             let mut tool_calls_executed = false;
 
             for msg in response.data {
+                let msg = self.screen(msg, SafetyStage::Output).await?;
                 messages.push(msg.clone());
                 current_response.data.push(msg.clone());
 
-                for part in msg.parts() {
-                    if let Part::FunctionCall {
-                        id,
-                        name,
-                        arguments,
-                        ..
-                    } = part
-                    {
-                        tool_calls_executed = true;
-                        info!("Tool call requested: {}", name);
-                        debug!("Tool arguments: {}", arguments);
-
-                        let server = self.server.as_ref().ok_or_else(|| {
-                            ClientError::Config("No MCP server configured".to_string())
-                        })?;
-                        let server_id = tool_map.get(name).cloned().flatten();
-                        let result = server
-                            .call_tool(name.clone(), arguments.clone(), server_id)
-                            .await;
-
-                        let response_part = match result {
-                            Ok(mut part) => {
-                                info!("Tool {} executed successfully", name);
-                                debug!("Tool result: {:?}", part);
-                                if let Part::FunctionResponse {
-                                    id: ref mut pid, ..
-                                } = part
-                                {
-                                    *pid = id.clone();
-                                }
-                                part
-                            }
-                            Err(e) => {
-                                warn!("Tool {} execution failed: {}", name, e);
-                                Part::FunctionResponse {
-                                    id: id.clone(),
-                                    name: name.clone(),
-                                    response: json!({ "error": format!("Error: {}", e) }),
-                                    parts: vec![],
-                                    finished: true,
-                                }
-                            }
-                        };
+                let calls: Vec<&Part> = msg
+                    .parts()
+                    .iter()
+                    .filter(|part| matches!(part, Part::FunctionCall { .. }))
+                    .collect();
+                if calls.is_empty() {
+                    continue;
+                }
+                tool_calls_executed = true;
 
-                        let response_msg = Message::User(vec![response_part]);
-                        messages.push(response_msg.clone());
-                        current_response.data.push(response_msg);
-                    }
+                let results = match dry_run.as_deref_mut() {
+                    Some(dry_run) => dry_run.respond(iteration, &calls).await,
+                    None => self.execute_tools(&tool_map, &calls, control).await?,
+                };
+                current_response.usage += control.take_usage();
+                for response_part in results {
+                    let response_msg = self
+                        .screen(Message::User(vec![response_part]), SafetyStage::Input)
+                        .await?;
+                    messages.push(response_msg.clone());
+                    current_response.data.push(response_msg);
                 }
             }
 
             if !tool_calls_executed {
                 debug!("No more function calls, agent loop complete");
-                return Ok(current_response);
+                return Ok(());
             }
         }
 
-        warn!(
-            "Max iterations ({}) reached in agent loop",
-            self.max_iterations
-        );
-        Err(ClientError::Config(
-            "Max iterations reached in agent loop".to_string(),
-        ))
+        warn!("Max iterations ({}) reached in agent loop", max_iterations);
+        match on_max_iterations {
+            OnMaxIterations::Error => Err(ClientError::Config(
+                "Max iterations reached in agent loop".to_string(),
+            )),
+            OnMaxIterations::ReturnPartial => {
+                current_response.finish = FinishReason::MaxIterations;
+                Ok(())
+            }
+            OnMaxIterations::FinalAnswer => {
+                messages.push(wrap_up_message());
+                for hooks in &self.hooks {
+                    hooks.on_llm_request(&messages, &[]).await;
+                }
+                control.run(self.pace(&messages, &[])).await?;
+
+                let response = control
+                    .run(self.client.request(messages, Vec::new()))
+                    .await??;
+                self.observe_response(&response, conversation).await;
+                current_response.iterations.push((&response).into());
+                current_response.usage += response.usage;
+                current_response.finish = response.finish;
+                current_response.model = response.model.or(current_response.model.take());
+                current_response.data.extend(response.data);
+                Ok(())
+            }
+        }
+    }
+
+    /// Send a message within a conversation.
+    ///
+    /// The message is appended to the conversation and the whole history is sent.
+    /// On success, all generated messages are appended as well; on failure the
+    /// conversation is left unchanged.
+    pub async fn chat_in(
+        &self,
+        conversation: &mut Conversation,
+        mut message: Message,
+    ) -> Result<Response, AgentError> {
+        // Screened here so the conversation keeps the message the model saw.
+        self.screen_input(std::slice::from_mut(&mut message))
+            .await?;
+        let mut messages = conversation.messages().to_vec();
+        messages.push(message.clone());
+
+        let mut control = RunControl::new(CancellationToken::new(), self.timeout);
+        control.scope = conversation.id().to_string();
+        control.conversation = Some(conversation.id().to_string());
+        let response = self
+            .chat_until_stopped(messages, Some(conversation.id()), &control, None)
+            .await?;
+        conversation.push(message);
+        conversation.extend(response.data.clone());
+        Ok(response)
+    }
+
+    /// Generate a short title for a conversation.
+    ///
+    /// Only the text of the first few messages is sent, in a single request
+    /// without tools, so the call stays cheap. Titles are cached per transcript,
+    /// so asking again for the same conversation does not make another request.
+    pub async fn title(&self, messages: &[Message]) -> Result<String, ClientError> {
+        let transcript = title_transcript(messages);
+        if transcript.is_empty() {
+            return Err(ClientError::Config(
+                "Cannot generate a title for a conversation without text".to_string(),
+            ));
+        }
+
+        let mut hasher = DefaultHasher::new();
+        transcript.hash(&mut hasher);
+        let key = hasher.finish();
+        if let Some(title) = self.titles.lock().unwrap().get(&key) {
+            return Ok(title.clone());
+        }
+
+        let prompt = Message::User(vec![Part::Text {
+            content: format!("{}\n\n{}", TITLE_INSTRUCTION, transcript),
+            finished: true,
+        }]);
+        let response = self.client.request(vec![prompt], Vec::new()).await?;
+        let title = response
+            .data
+            .iter()
+            .map(|message| clean_title(&message_text(message)))
+            .find(|title| !title.is_empty())
+            .ok_or_else(|| ClientError::ProviderError("Model returned no title".to_string()))?;
+
+        self.titles.lock().unwrap().insert(key, title.clone());
+        Ok(title)
+    }
+
+    /// Run the agent and record the outcome as an [`AgentRun`].
+    ///
+    /// Unlike [`chat`](Self::chat), failures are captured in the returned record
+    /// instead of being returned as an error.
+    pub async fn run(&self, messages: Vec<Message>) -> AgentRun {
+        let model_options = self.client.model_options();
+        let mut run = AgentRun {
+            version: RUN_FORMAT_VERSION,
+            id: uuid::Uuid::new_v4().to_string(),
+            provider: self.client.provider().to_string(),
+            model: model_options.model.clone(),
+            served_model: None,
+            system: model_options.system.clone(),
+            prompt_version: model_options.system.as_deref().map(prompt_version),
+            variant: self.variant.clone(),
+            input: messages.clone(),
+            output: Vec::new(),
+            usage: Usage::default(),
+            iterations: Vec::new(),
+            finish: FinishReason::Unfinished,
+            error: None,
+        };
+
+        match self.chat(messages).await {
+            Ok(response) => {
+                run.served_model = response.model;
+                run.output = response.data;
+                run.usage = response.usage;
+                run.iterations = response.iterations;
+                run.finish = response.finish;
+            }
+            Err(e) => {
+                run.served_model = e.partial.model;
+                run.output = e.partial.data;
+                run.usage = e.partial.usage;
+                run.iterations = e.partial.iterations;
+                run.finish = FinishReason::Error;
+                run.error = Some(e.source.to_string());
+            }
+        }
+
+        run
     }
 
     /// Send a streaming chat request with automatic tool execution.
@@ -205,67 +1153,202 @@ impl<C: Client> Agent<C> {
     /// A stream of chunks for the final response after all tool executions complete
     pub fn chat_stream<'a>(
         &'a self,
-        mut messages: Vec<Message>,
+        messages: Vec<Message>,
     ) -> std::pin::Pin<Box<dyn futures::Stream<Item = Result<Response, ClientError>> + Send + 'a>>
     where
         C: crate::client::StreamingClient,
     {
+        self.chat_stream_with_cancellation(messages, CancellationToken::new())
+    }
+
+    /// Stream the progress of a run as [`AgentEvent`]s.
+    ///
+    /// Unlike the snapshots of [`chat_stream`](Self::chat_stream), the events
+    /// mark where each model request starts and when tool calls start and
+    /// finish, so a UI can show progress while tools run. The stream ends with
+    /// [`AgentEvent::Finished`], or with the error of the run. Dropping it
+    /// aborts the run like dropping the stream of `chat_stream`.
+    pub fn chat_events<'a>(
+        &'a self,
+        messages: Vec<Message>,
+    ) -> std::pin::Pin<Box<dyn futures::Stream<Item = Result<AgentEvent, ClientError>> + Send + 'a>>
+    where
+        C: crate::client::StreamingClient,
+    {
+        let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+        let mut snapshots = self.run_stream(messages, CancellationToken::new(), Some(sender));
         Box::pin(async_stream::try_stream! {
+            let mut tracker = EventTracker::default();
+            let mut last = None;
+            loop {
+                let snapshot = tokio::select! {
+                    biased;
+                    Some(event) = receiver.recv() => {
+                        yield event;
+                        continue;
+                    }
+                    snapshot = snapshots.next() => snapshot,
+                };
+                // Events sent by the run come before the snapshot that follows them.
+                while let Ok(event) = receiver.try_recv() {
+                    yield event;
+                }
+                let Some(snapshot) = snapshot else {
+                    break;
+                };
+                let snapshot = snapshot?;
+                for event in tracker.events(&snapshot) {
+                    yield event;
+                }
+                last = Some(snapshot);
+            }
+            if let Some(response) = last {
+                yield AgentEvent::Finished {
+                    usage: response.usage.clone(),
+                    response,
+                };
+            }
+        })
+    }
+
+    /// Like [`chat_stream`](Self::chat_stream), but aborts when `cancel` is triggered.
+    ///
+    /// On cancellation the in-flight model stream is dropped, running tool calls
+    /// are cancelled and the stream ends with [`ClientError::StreamCancelled`].
+    ///
+    /// Dropping the stream before it ends aborts the run the same way, without
+    /// cancelling `cancel` itself. The transcript so far is passed to
+    /// [`AgentHooks::on_abort`] and the abort is logged, and recorded on the
+    /// `invoke_agent` span with the `otel` feature.
+    pub fn chat_stream_with_cancellation<'a>(
+        &'a self,
+        messages: Vec<Message>,
+        cancel: CancellationToken,
+    ) -> std::pin::Pin<Box<dyn futures::Stream<Item = Result<Response, ClientError>> + Send + 'a>>
+    where
+        C: crate::client::StreamingClient,
+    {
+        self.run_stream(messages, cancel, None)
+    }
+
+    /// Streamed run, sending the events that are not derived from snapshots
+    /// to `events` when set.
+    fn run_stream<'a>(
+        &'a self,
+        mut messages: Vec<Message>,
+        cancel: CancellationToken,
+        events: Option<EventSender>,
+    ) -> std::pin::Pin<Box<dyn futures::Stream<Item = Result<Response, ClientError>> + Send + 'a>>
+    where
+        C: crate::client::StreamingClient,
+    {
+        let cancel = cancel.child_token();
+        let run_cancel = cancel.clone();
+        let stream = Box::pin(async_stream::try_stream! {
             debug!("Starting agent streaming chat loop");
+            let mut control = RunControl::new(cancel, self.timeout);
+            control.events = events;
             use futures::StreamExt;
+            self.screen_input(&mut messages).await?;
 
             let mut current_response = Response {
                 data: Vec::new(),
                 usage: Usage::default(),
                 finish: FinishReason::Unfinished,
+                alternatives: Vec::new(),
+                model: None,
+                iterations: Vec::new(),
             };
 
-            let (tools, tool_map) = if let Some(server) = &self.server {
-                match server.list_tools().await {
-                    Ok(tools) => {
-                        let map: HashMap<String, Option<String>> = tools
-                            .iter()
-                            .map(|t| (t.value.name.to_string(), t.server_id.clone()))
-                            .collect();
-                        (tools.into_iter().map(|t| t.value).collect(), map)
-                    }
-                    Err(e) => {
-                        warn!("Failed to list tools from MCP server: {}", e);
-                        (Vec::new(), HashMap::new())
-                    }
+            let mut changes = self.subscribe();
+            let (mut tools, mut tool_map) = match self.list_tools().await {
+                Ok(tools) => tools,
+                Err(e) => {
+                    warn!("Failed to list tools from MCP server: {}", e);
+                    (Vec::new(), HashMap::new())
                 }
-            } else {
-                (Vec::new(), HashMap::new())
             };
 
-            for iteration in 0..self.max_iterations {
+            let (max_iterations, on_max_iterations) = self.iteration_budget();
+            for iteration in 0..max_iterations {
                 debug!(
                     "Agent streaming iteration {}/{}",
                     iteration + 1,
-                    self.max_iterations
+                    max_iterations
                 );
+                self.refresh_tools(&mut changes, &mut tools, &mut tool_map).await;
 
-                let mut stream = self.client.request_stream(messages.clone(), tools.clone()).await?;
+                for hooks in &self.hooks {
+                    hooks.on_iteration(iteration).await;
+                    hooks.on_llm_request(&messages, &tools).await;
+                }
+                control.run(self.pace(&messages, &tools)).await?;
+
+                control.emit(AgentEvent::IterationStart { iteration });
+                let mut stream = control
+                    .run(self.client.request_stream(messages.clone(), tools.clone()))
+                    .await??;
 
                 // Snapshot of state before this turn
                 let base_data_len = current_response.data.len();
                 let base_usage = current_response.usage.clone();
+                let base_iterations = current_response.iterations.len();
 
-                while let Some(response_result) = stream.next().await {
-                    let response = response_result?;
+                let mut eager = self
+                    .eager_tools
+                    .then(|| EagerTools::new(self, &tool_map, &control, control.cancel.child_token()));
+
+                let mut turn_response = None;
+                while let Some(response_result) = match &mut eager {
+                    Some(eager) => eager.next_response(&mut stream, &control).await?,
+                    None => control.run(stream.next()).await?,
+                } {
+                    let response = match response_result {
+                        Ok(response) => response,
+                        Err(e) => {
+                            if let Some(eager) = &mut eager {
+                                eager.abort().await;
+                            }
+                            Err(e)?
+                        }
+                    };
+                    if let Some(eager) = &mut eager {
+                        eager.discover(response.data.last());
+                    }
+                    turn_response = Some(response.clone());
 
                     // Update current_response
                     // Truncate to base length to remove previous partials of this turn
                     current_response.data.truncate(base_data_len);
                     current_response.data.extend(response.data.clone());
 
+                    current_response.iterations.truncate(base_iterations);
+                    current_response.iterations.push((&response).into());
                     current_response.usage = base_usage.clone();
                     current_response.usage += response.usage;
                     current_response.finish = response.finish;
+                    current_response.model = response.model.or(current_response.model.take());
 
                     yield current_response.clone();
                 }
 
+                if let Some(response) = &turn_response {
+                    self.observe_response(response, None).await;
+                }
+
+                if self.safety_policy.is_some() {
+                    let mut redacted = false;
+                    for i in base_data_len..current_response.data.len() {
+                        let msg = current_response.data[i].clone();
+                        let screened = self.screen(msg, SafetyStage::Output).await?;
+                        redacted |= screened != current_response.data[i];
+                        current_response.data[i] = screened;
+                    }
+                    if redacted {
+                        yield current_response.clone();
+                    }
+                }
+
                 // After stream, current_response contains the full assistant message for this turn.
                 // Update messages history
                 if current_response.data.len() > base_data_len {
@@ -276,48 +1359,31 @@ impl<C: Client> Agent<C> {
                 }
 
                 // Check for tool calls
-                let mut tool_calls_executed = false;
-                let mut tool_responses = Vec::new();
-
                 // We only check the LAST message for tool calls, which should be the assistant's message
-                if let Some(msg) = current_response.data.last() {
-                    for part in msg.parts() {
-                        if let Part::FunctionCall { id, name, arguments, finished, .. } = part {
-                            if *finished {
-                                tool_calls_executed = true;
-                                info!("Executing tool: {}", name);
-
-                                let server = self.server.as_ref().ok_or_else(|| ClientError::Config("No MCP server configured".to_string()))?;
-                                let server_id = tool_map.get(name).cloned().flatten();
-                                let result = server
-                                    .call_tool(name.clone(), arguments.clone(), server_id)
-                                    .await;
-
-                                let response_part = match result {
-                                    Ok(mut part) => {
-                                        if let Part::FunctionResponse { id: ref mut pid, .. } = part {
-                                            *pid = id.clone();
-                                        }
-                                        part
-                                    }
-                                    Err(e) => {
-                                        Part::FunctionResponse {
-                                            id: id.clone(),
-                                            name: name.clone(),
-                                            response: json!({ "error": format!("Error: {}", e) }),
-                                            parts: vec![],
-                                            finished: true,
-                                        }
-                                    },
-                                };
-                                tool_responses.push(response_part);
-                            }
-                        }
+                let calls: Vec<&Part> = current_response
+                    .data
+                    .last()
+                    .map(|msg| {
+                        msg.parts()
+                            .iter()
+                            .filter(|part| matches!(part, Part::FunctionCall { finished: true, .. }))
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                let tool_calls_executed = !calls.is_empty();
+                let tool_responses = match &mut eager {
+                    Some(eager) => {
+                        eager.discover(current_response.data.last());
+                        eager.finish(&control).await?
                     }
-                }
+                    None => self.execute_tools(&tool_map, &calls, &control).await?,
+                };
+                current_response.usage += control.take_usage();
 
                 if tool_calls_executed {
-                    let tool_msg = Message::User(tool_responses);
+                    let tool_msg = self
+                        .screen(Message::User(tool_responses), SafetyStage::Input)
+                        .await?;
                     messages.push(tool_msg.clone());
                     current_response.data.push(tool_msg);
 
@@ -330,11 +1396,794 @@ impl<C: Client> Agent<C> {
 
             warn!(
                 "Max iterations ({}) reached in streaming agent loop",
-                self.max_iterations
+                max_iterations
             );
-            Err(ClientError::Config(
-                "Max iterations reached in agent loop".to_string(),
-            ))?;
+            match on_max_iterations {
+                OnMaxIterations::Error => Err(ClientError::Config(
+                    "Max iterations reached in agent loop".to_string(),
+                ))?,
+                OnMaxIterations::ReturnPartial => {
+                    current_response.finish = FinishReason::MaxIterations;
+                    yield current_response;
+                }
+                OnMaxIterations::FinalAnswer => {
+                    messages.push(wrap_up_message());
+                    for hooks in &self.hooks {
+                        hooks.on_llm_request(&messages, &[]).await;
+                    }
+                    control.run(self.pace(&messages, &[])).await?;
+
+                    control.emit(AgentEvent::IterationStart {
+                        iteration: max_iterations,
+                    });
+                    let mut stream = control
+                        .run(self.client.request_stream(messages, Vec::new()))
+                        .await??;
+                    let base_data_len = current_response.data.len();
+                    let base_usage = current_response.usage.clone();
+                    let base_iterations = current_response.iterations.len();
+                    let mut turn_response = None;
+                    while let Some(response) = control.run(stream.next()).await? {
+                        let response = response?;
+                        current_response.data.truncate(base_data_len);
+                        current_response.data.extend(response.data.clone());
+                        current_response.iterations.truncate(base_iterations);
+                        current_response.iterations.push((&response).into());
+                        current_response.usage = base_usage.clone();
+                        current_response.usage += response.usage.clone();
+                        current_response.finish = response.finish.clone();
+                        current_response.model =
+                            response.model.clone().or(current_response.model.take());
+                        turn_response = Some(response);
+
+                        yield current_response.clone();
+                    }
+                    if let Some(response) = &turn_response {
+                        self.observe_response(response, None).await;
+                    }
+                }
+            }
+        });
+        #[cfg(feature = "otel")]
+        let span = self.otel_span();
+        #[cfg(feature = "otel")]
+        let stream = crate::otel::instrument_responses(stream, span.clone());
+        Box::pin(RunStream {
+            inner: stream,
+            hooks: &self.hooks,
+            cancel: run_cancel,
+            last: None,
+            finished: false,
+            #[cfg(feature = "otel")]
+            span,
         })
     }
 }
+
+/// Progress of a streamed agent run, see [`Agent::chat_events`].
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AgentEvent {
+    /// A model request starts. Iterations are counted from 0.
+    IterationStart { iteration: usize },
+    /// A change to the message the model is generating.
+    LlmDelta {
+        iteration: usize,
+        delta: StreamDelta,
+    },
+    /// The model finished a tool call, which is executed next.
+    ToolCallStarted {
+        id: Option<String>,
+        name: String,
+        arguments: Value,
+    },
+    /// A tool call returned. `result` is its function response part, as screened
+    /// by the safety policy; a result the policy rejects is replaced by an error.
+    ToolCallFinished {
+        id: Option<String>,
+        name: String,
+        result: Part,
+    },
+    /// The run ended. `usage` is the total of all model requests.
+    Finished { usage: Usage, response: Response },
+}
+
+/// Events of an agent run, derived from consecutive snapshots of its stream.
+///
+/// Iteration starts and tool results are sent by the run itself when they
+/// happen, see [`RunControl::emit`].
+#[derive(Default)]
+struct EventTracker {
+    previous: Option<Response>,
+}
+
+impl EventTracker {
+    fn events(&mut self, snapshot: &Response) -> Vec<AgentEvent> {
+        let empty = Response {
+            data: Vec::new(),
+            usage: Usage::default(),
+            finish: FinishReason::Unfinished,
+            alternatives: Vec::new(),
+            model: None,
+            iterations: Vec::new(),
+        };
+        let previous = self.previous.as_ref().unwrap_or(&empty);
+        let mut events = Vec::new();
+        let iteration = snapshot.iterations.len().saturating_sub(1);
+
+        for (index, message) in snapshot.data.iter().enumerate() {
+            let old = previous.data.get(index);
+            match message {
+                Message::Assistant(parts) => {
+                    let single = |message: Option<&Message>| Response {
+                        data: message.into_iter().cloned().collect(),
+                        ..empty.clone()
+                    };
+                    let old = old.filter(|m| matches!(m, Message::Assistant(_)));
+                    for delta in diff_snapshots(&single(old), &single(Some(message))) {
+                        events.push(AgentEvent::LlmDelta { iteration, delta });
+                    }
+                    let old_parts = old.map(|m| m.parts().as_slice()).unwrap_or_default();
+                    for (i, part) in parts.iter().enumerate() {
+                        let Part::FunctionCall {
+                            id,
+                            name,
+                            arguments,
+                            finished: true,
+                            ..
+                        } = part
+                        else {
+                            continue;
+                        };
+                        if !matches!(
+                            old_parts.get(i),
+                            Some(Part::FunctionCall { finished: true, .. })
+                        ) {
+                            events.push(AgentEvent::ToolCallStarted {
+                                id: id.clone(),
+                                name: name.clone(),
+                                arguments: arguments.clone(),
+                            });
+                        }
+                    }
+                }
+                Message::User(_) => {}
+            }
+        }
+        self.previous = Some(snapshot.clone());
+        events
+    }
+}
+
+/// Stream of a streamed agent run that aborts the run when dropped early.
+struct RunStream<'a> {
+    inner: Pin<Box<dyn Stream<Item = Result<Response, ClientError>> + Send + 'a>>,
+    hooks: &'a [Box<dyn AgentHooks>],
+    cancel: CancellationToken,
+    /// Latest snapshot, passed to [`AgentHooks::on_abort`].
+    last: Option<Response>,
+    finished: bool,
+    #[cfg(feature = "otel")]
+    span: tracing::Span,
+}
+
+impl Stream for RunStream<'_> {
+    type Item = Result<Response, ClientError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let poll = self.inner.poll_next_unpin(cx);
+        match &poll {
+            Poll::Ready(Some(Ok(response))) => self.last = Some(response.clone()),
+            // The run ends with its first error.
+            Poll::Ready(Some(Err(_)) | None) => self.finished = true,
+            Poll::Pending => {}
+        }
+        poll
+    }
+}
+
+impl Drop for RunStream<'_> {
+    fn drop(&mut self) {
+        if self.finished {
+            return;
+        }
+        // Tool calls observing the token stop before their futures are dropped
+        // with the inner stream.
+        self.cancel.cancel();
+        #[cfg(feature = "otel")]
+        crate::otel::record_error(&self.span, &ClientError::StreamCancelled);
+        #[cfg(feature = "otel")]
+        let _entered = self.span.enter();
+        warn!("Agent stream dropped before the run finished, aborting it");
+
+        let mut partial = self.last.take().unwrap_or_else(|| Response {
+            data: Vec::new(),
+            usage: Usage::default(),
+            finish: FinishReason::Unfinished,
+            alternatives: Vec::new(),
+            model: None,
+            iterations: Vec::new(),
+        });
+        partial.finish = FinishReason::Error;
+        for hooks in self.hooks {
+            hooks.on_abort(&partial);
+        }
+    }
+}
+
+/// What an [`Agent`] does when its loop reaches the maximum number of iterations
+/// with tool calls still pending.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OnMaxIterations {
+    /// Fail with [`ClientError::Config`]. The transcript so far is available
+    /// from the [`AgentError`].
+    Error,
+    /// Return the transcript so far with [`FinishReason::MaxIterations`].
+    #[default]
+    ReturnPartial,
+    /// Ask the model for one more response without tools, telling it to answer
+    /// with the information it has.
+    FinalAnswer,
+}
+
+/// How a [`ToolResultPolicy`] shortens a result over its limit.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TruncationStrategy {
+    /// Keep the beginning of the result.
+    #[default]
+    Head,
+    /// Keep the end of the result, e.g. for logs.
+    Tail,
+    /// Replace the result with a summary written by the agent's model. The
+    /// request is made like a turn of the run, whose usage and deadline it
+    /// counts towards. Falls back to [`Head`](Self::Head) if the request fails.
+    Summarize,
+}
+
+/// Size limit of tool results, applied before they are added to the
+/// conversation.
+///
+/// The limit applies to the `response` of a result, as JSON text. A result over
+/// the limit is replaced with an object holding the shortened content and a
+/// note about the truncation, so the model knows data is missing. Media parts
+/// are kept as they are.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ToolResultPolicy {
+    /// Maximum size in bytes.
+    pub max_bytes: Option<usize>,
+    /// Maximum size in tokens, estimated at four characters per token.
+    pub max_tokens: Option<usize>,
+    /// How results over the limit are shortened, [`Head`](TruncationStrategy::Head) by default.
+    pub strategy: TruncationStrategy,
+}
+
+impl ToolResultPolicy {
+    /// Create a policy without limits that keeps the head of results.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the maximum size in bytes.
+    pub fn with_max_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_bytes = Some(max_bytes);
+        self
+    }
+
+    /// Set the maximum size in tokens.
+    pub fn with_max_tokens(mut self, max_tokens: usize) -> Self {
+        self.max_tokens = Some(max_tokens);
+        self
+    }
+
+    /// Set how results over the limit are shortened.
+    pub fn with_strategy(mut self, strategy: TruncationStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Maximum size of a result in bytes, if limited.
+    fn limit(&self) -> Option<usize> {
+        let tokens = self
+            .max_tokens
+            .map(|tokens| tokens.saturating_mul(CHARS_PER_TOKEN));
+        match (self.max_bytes, tokens) {
+            (Some(bytes), Some(tokens)) => Some(bytes.min(tokens)),
+            (bytes, tokens) => bytes.or(tokens),
+        }
+    }
+}
+
+/// Longest prefix of `text` of at most `limit` bytes.
+fn head(text: &str, limit: usize) -> &str {
+    let mut end = limit.min(text.len());
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    &text[..end]
+}
+
+/// Longest suffix of `text` of at most `limit` bytes.
+fn tail(text: &str, limit: usize) -> &str {
+    let mut start = text.len().saturating_sub(limit);
+    while !text.is_char_boundary(start) {
+        start += 1;
+    }
+    &text[start..]
+}
+
+/// Maximum number of bytes of a tool result sent to be summarized.
+const SUMMARY_INPUT_BYTES: usize = 200_000;
+
+const SUMMARY_INSTRUCTION: &str = "The following tool result is too long to use as is. \
+Summarize it within the maximum length, keeping the facts, identifiers and numbers a caller \
+of the tool is most likely to need. Reply with the summary only.";
+
+/// Instruction sent for the final turn of [`OnMaxIterations::FinalAnswer`].
+const WRAP_UP_INSTRUCTION: &str = "You have reached the limit of tool calls for this request. \
+Do not call any more tools. Answer now using the information gathered so far, \
+and say what is missing if it is incomplete.";
+
+fn wrap_up_message() -> Message {
+    Message::User(vec![Part::Text {
+        content: WRAP_UP_INSTRUCTION.to_string(),
+        finished: true,
+    }])
+}
+
+/// Stub answering tool calls in [`Agent::dry_run`].
+///
+/// Implemented for closures taking the tool name and arguments.
+#[async_trait]
+pub trait DryRunResponder: Send + Sync {
+    /// Result the tool is assumed to return for these arguments.
+    async fn respond(&self, name: &str, arguments: &Value) -> Value;
+}
+
+#[async_trait]
+impl<F> DryRunResponder for F
+where
+    F: Fn(&str, &Value) -> Value + Send + Sync,
+{
+    async fn respond(&self, name: &str, arguments: &Value) -> Value {
+        self(name, arguments)
+    }
+}
+
+/// Outcome of [`Agent::dry_run`].
+#[derive(Debug, Clone)]
+pub struct DryRun {
+    /// Tool calls the model planned, in order.
+    pub steps: Vec<PlannedStep>,
+    /// Messages generated during the run, with stub tool results.
+    pub response: Response,
+}
+
+/// Tool calls planned in one model response of a dry run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlannedStep {
+    /// Iteration of the agent loop, counting from 0.
+    pub iteration: usize,
+    /// The planned `Part::FunctionCall` parts.
+    pub calls: Vec<Part>,
+    /// Stub results sent back to the model, in call order.
+    pub results: Vec<Part>,
+}
+
+struct DryRunState<'a> {
+    responder: &'a dyn DryRunResponder,
+    steps: Vec<PlannedStep>,
+}
+
+impl DryRunState<'_> {
+    /// Answer tool calls with the stub responder and record them.
+    async fn respond(&mut self, iteration: usize, calls: &[&Part]) -> Vec<Part> {
+        let mut results = Vec::with_capacity(calls.len());
+        for part in calls {
+            if let Part::FunctionCall {
+                id,
+                name,
+                arguments,
+                ..
+            } = part
+            {
+                info!("Dry run, not executing tool {}", name);
+                results.push(Part::FunctionResponse {
+                    id: id.clone(),
+                    name: name.clone(),
+                    response: self.responder.respond(name, arguments).await,
+                    parts: vec![],
+                    finished: true,
+                });
+            }
+        }
+        self.steps.push(PlannedStep {
+            iteration,
+            calls: calls.iter().map(|&part| part.clone()).collect(),
+            results: results.clone(),
+        });
+        results
+    }
+}
+
+/// Tool calls of a streamed turn, executed as soon as their arguments are complete.
+struct EagerTools<'a, C: Client> {
+    agent: &'a Agent<C>,
+    tool_map: &'a HashMap<String, Option<String>>,
+    control: &'a RunControl,
+    cancel: CancellationToken,
+    /// Part indices of the calls seen so far.
+    seen: HashSet<usize>,
+    queued: VecDeque<(usize, Part)>,
+    running: FuturesUnordered<BoxFuture<'a, (usize, Result<Part, ClientError>)>>,
+    /// Tool responses by the part index of their call.
+    results: BTreeMap<usize, Part>,
+}
+
+impl<'a, C: Client> EagerTools<'a, C> {
+    fn new(
+        agent: &'a Agent<C>,
+        tool_map: &'a HashMap<String, Option<String>>,
+        control: &'a RunControl,
+        cancel: CancellationToken,
+    ) -> Self {
+        Self {
+            agent,
+            tool_map,
+            control,
+            cancel,
+            seen: HashSet::new(),
+            queued: VecDeque::new(),
+            running: FuturesUnordered::new(),
+            results: BTreeMap::new(),
+        }
+    }
+
+    /// Queue the finished calls of the assistant message that were not seen before.
+    fn discover(&mut self, message: Option<&Message>) {
+        for (index, part) in message
+            .into_iter()
+            .flat_map(|m| m.parts().iter().enumerate())
+        {
+            if matches!(part, Part::FunctionCall { finished: true, .. }) && self.seen.insert(index)
+            {
+                self.queued.push_back((index, part.clone()));
+            }
+        }
+        self.fill();
+    }
+
+    /// Start queued calls up to the agent's tool concurrency.
+    fn fill(&mut self) {
+        while self.running.len() < self.agent.tool_concurrency.max(1) {
+            let Some((index, call)) = self.queued.pop_front() else {
+                break;
+            };
+            let Part::FunctionCall {
+                id,
+                name,
+                arguments,
+                raw_arguments,
+                ..
+            } = call
+            else {
+                continue;
+            };
+            info!("Tool call requested: {}", name);
+            debug!("Tool arguments: {}", arguments);
+
+            let (agent, tool_map, control) = (self.agent, self.tool_map, self.control);
+            let cancel = self.cancel.clone();
+            self.running.push(Box::pin(async move {
+                let result = agent
+                    .execute_tool(
+                        tool_map,
+                        &id,
+                        &name,
+                        &arguments,
+                        raw_arguments.as_deref(),
+                        control,
+                        &cancel,
+                    )
+                    .await;
+                (index, result)
+            }));
+        }
+    }
+
+    fn complete(
+        &mut self,
+        index: usize,
+        result: Result<Part, ClientError>,
+    ) -> Result<(), ClientError> {
+        self.results.insert(index, result?);
+        self.fill();
+        Ok(())
+    }
+
+    /// Wait for the next stream item while driving the running calls.
+    async fn next_response<S>(
+        &mut self,
+        stream: &mut S,
+        control: &RunControl,
+    ) -> Result<Option<S::Item>, ClientError>
+    where
+        S: Stream + Unpin,
+    {
+        loop {
+            let running = &mut self.running;
+            let next = control
+                .run(async {
+                    tokio::select! {
+                        item = stream.next() => Either::Left(item),
+                        Some(done) = running.next() => Either::Right(done),
+                    }
+                })
+                .await;
+            let result = match next {
+                Ok(Either::Left(item)) => return Ok(item),
+                Ok(Either::Right((index, result))) => self.complete(index, result),
+                Err(e) => Err(e),
+            };
+            if let Err(e) = result {
+                self.abort().await;
+                return Err(e);
+            }
+        }
+    }
+
+    /// Wait for all calls and return their responses in call order.
+    async fn finish(&mut self, control: &RunControl) -> Result<Vec<Part>, ClientError> {
+        self.fill();
+        loop {
+            let result = match control.run(self.running.next()).await {
+                Ok(Some((index, result))) => self.complete(index, result),
+                Ok(None) => break,
+                Err(e) => Err(e),
+            };
+            if let Err(e) = result {
+                self.abort().await;
+                return Err(e);
+            }
+        }
+        Ok(std::mem::take(&mut self.results).into_values().collect())
+    }
+
+    /// Cancel the running calls and wait for them to notify their servers.
+    async fn abort(&mut self) {
+        self.cancel.cancel();
+        self.queued.clear();
+        while self.running.next().await.is_some() {}
+    }
+}
+
+/// Results of tool calls, see [`Agent::with_tool_cache`].
+struct ToolCache {
+    ttl: Duration,
+    /// Results with their expiry, by conversation, tool name and arguments.
+    entries: Mutex<HashMap<String, (Part, Instant)>>,
+}
+
+impl ToolCache {
+    fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn key(scope: &str, name: &str, arguments: &Value) -> String {
+        // Sorted keys, so arguments differing only in key order share an entry.
+        format!("{}\0{}\0{}", scope, name, canonicalize(arguments))
+    }
+
+    fn get(&self, key: &str) -> Option<Part> {
+        let entries = self.entries.lock().unwrap();
+        let (part, expiry) = entries.get(key)?;
+        (*expiry > Instant::now()).then(|| part.clone())
+    }
+
+    /// Store a result, dropping expired ones.
+    fn put(&self, key: String, part: Part) {
+        let now = Instant::now();
+        let mut entries = self.entries.lock().unwrap();
+        entries.retain(|_, (_, expiry)| *expiry > now);
+        entries.insert(key, (part, now + self.ttl));
+    }
+}
+
+/// Render the leading text messages of a conversation for the title prompt.
+fn title_transcript(messages: &[Message]) -> String {
+    messages
+        .iter()
+        .filter_map(|message| {
+            let content = message_text(message);
+            let content = content.trim();
+            if content.is_empty() {
+                return None;
+            }
+            let speaker = match message {
+                Message::User(_) => "User",
+                Message::Assistant(_) => "Assistant",
+            };
+            let content: String = content.chars().take(TITLE_MESSAGE_CHARS).collect();
+            Some(format!("{}: {}", speaker, content))
+        })
+        .take(TITLE_MESSAGES)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Text parts of a message, without reasoning.
+fn message_text(message: &Message) -> String {
+    message
+        .parts()
+        .iter()
+        .filter_map(|part| match part {
+            Part::Text { content, .. } => Some(content.as_str()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Reduce a model reply to a bare title.
+fn clean_title(text: &str) -> String {
+    let line = text.trim().lines().next().unwrap_or_default();
+    let line = line.strip_prefix("Title:").unwrap_or(line);
+    line.trim()
+        .trim_matches(|c: char| matches!(c, '"' | '\'' | '*' | '#' | '`'))
+        .trim_end_matches(['.', '!'])
+        .trim()
+        .to_string()
+}
+
+/// Cancellation state of a single agent run.
+struct RunControl {
+    cancel: CancellationToken,
+    deadline: Option<Instant>,
+    /// Conversation of the run, which cached tool results are scoped to. A
+    /// random ID for runs outside a conversation.
+    scope: String,
+    /// Conversation the costs of the run are attributed to.
+    conversation: Option<String>,
+    /// Receiver of the events of [`Agent::chat_events`].
+    events: Option<EventSender>,
+    /// Usage of requests besides the turns, such as tool result summaries, not
+    /// yet added to the run total.
+    usage: Mutex<Usage>,
+}
+
+type EventSender = tokio::sync::mpsc::UnboundedSender<AgentEvent>;
+
+impl RunControl {
+    fn new(cancel: CancellationToken, timeout: Option<Duration>) -> Self {
+        Self {
+            cancel,
+            deadline: timeout.map(|timeout| Instant::now() + timeout),
+            scope: uuid::Uuid::new_v4().to_string(),
+            conversation: None,
+            events: None,
+            usage: Mutex::new(Usage::default()),
+        }
+    }
+
+    /// Take the usage of requests besides the turns made since the last call.
+    fn take_usage(&self) -> Usage {
+        std::mem::take(&mut *self.usage.lock().unwrap())
+    }
+
+    /// Send an event of the run, if anyone is listening.
+    fn emit(&self, event: AgentEvent) {
+        if let Some(events) = &self.events {
+            let _ = events.send(event);
+        }
+    }
+
+    /// Resolve once the run is cancelled or its deadline has passed.
+    async fn stopped(&self) -> ClientError {
+        match self.deadline {
+            Some(deadline) => tokio::select! {
+                _ = self.cancel.cancelled() => ClientError::StreamCancelled,
+                _ = tokio::time::sleep_until(deadline) => ClientError::DeadlineExceeded,
+            },
+            None => {
+                self.cancel.cancelled().await;
+                ClientError::StreamCancelled
+            }
+        }
+    }
+
+    /// Drive a future to completion unless the run is stopped first.
+    async fn run<T>(&self, future: impl Future<Output = T>) -> Result<T, ClientError> {
+        tokio::select! {
+            biased;
+            err = self.stopped() => Err(err),
+            value = future => Ok(value),
+        }
+    }
+}
+
+/// Error of an agent run, with the work done before it failed.
+///
+/// Tool calls and model responses generated before the error have already been
+/// paid for, so they are kept in [`partial`](Self::partial) rather than discarded.
+/// Converts into the underlying [`ClientError`] with `?`.
+#[derive(Error, Debug)]
+#[error("{source}")]
+pub struct AgentError {
+    /// Messages and usage accumulated before the error. Its finish reason is
+    /// [`FinishReason::Error`].
+    pub partial: Response,
+    #[source]
+    pub source: ClientError,
+}
+
+impl From<AgentError> for ClientError {
+    fn from(error: AgentError) -> Self {
+        error.source
+    }
+}
+
+/// Record of a single agent run, suitable for persisting and later analysis.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct AgentRun {
+    /// Version of the record format. Records written before the format was
+    /// versioned have none and are read as version 0; see
+    /// [`load_run`](crate::schema::load_run).
+    #[serde(default)]
+    pub version: u32,
+    /// Unique run identifier.
+    pub id: String,
+    /// Provider identifier of the client used.
+    pub provider: String,
+    /// Model identifier.
+    pub model: String,
+    /// Model the provider reported serving the run, such as a dated snapshot of
+    /// `model`, if known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub served_model: Option<String>,
+    /// System prompt the run was made with.
+    pub system: Option<String>,
+    /// [Version](crate::prompt::prompt_version) of the system prompt.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompt_version: Option<String>,
+    /// Experiment variant of the agent, if tagged.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub variant: Option<String>,
+    /// Conversation passed to the agent.
+    pub input: Vec<Message>,
+    /// Messages generated during the run, including tool calls and results.
+    pub output: Vec<Message>,
+    /// Accumulated token usage.
+    pub usage: Usage,
+    /// Usage of each model request of the run, in order.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub iterations: Vec<IterationUsage>,
+    /// Finish reason of the last response.
+    pub finish: FinishReason,
+    /// Error message if the run failed.
+    pub error: Option<String>,
+}
+
+impl AgentRun {
+    /// Check whether the run completed without error.
+    pub fn is_success(&self) -> bool {
+        self.error.is_none() && self.finish == FinishReason::Stop
+    }
+
+    /// Usage per model that served the run. Requests whose model was not
+    /// reported are attributed to `model`.
+    pub fn usage_by_model(&self) -> BTreeMap<String, Usage> {
+        breakdown(
+            &self.iterations,
+            self.served_model.as_deref(),
+            &self.usage,
+            &self.model,
+        )
+    }
+
+    /// The full conversation: input followed by output.
+    pub fn messages(&self) -> Vec<Message> {
+        self.input.iter().chain(&self.output).cloned().collect()
+    }
+}
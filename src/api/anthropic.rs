@@ -3,21 +3,166 @@
 use async_trait::async_trait;
 use base64::prelude::*;
 use futures::{Stream, StreamExt};
+use itertools::Itertools;
 use reqwest::header::{HeaderMap, HeaderValue, CONTENT_TYPE};
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 use serde_with::skip_serializing_none;
+use std::borrow::Cow;
 use std::collections::HashMap;
 use std::pin::Pin;
 
-use crate::client::{Client, ClientError, StreamingClient};
-use crate::http::{add_extra_headers, build_http_client, RequestBuilderExt, ResponseExt};
-use crate::model::{FinishReason, MediaType, Message, Part, Response, Usage};
-use crate::options::{ModelOptions, TransportOptions};
-use crate::sse::SSEResponseExt;
+use crate::client::{Capabilities, Client, ClientError, Modality, ModelInfo, StreamingClient};
+use crate::computer::{ComputerAction, ComputerDisplay, Coordinate, MouseButton};
+use crate::files::{file_reference, FileRef, FileUpload};
+use crate::hosted::{BuiltInTool, Citation, CodeOutput, HostedToolCall, SearchSource};
+use crate::http::{
+    add_extra_headers, build_http_client, send_with_retry, MultipartForm, RequestBuilderExt,
+    ResponseExt,
+};
+use crate::markdown::{strip_delta_markdown, strip_response_markdown, strip_stream_markdown};
+use crate::model::{correct_media_types, FinishReason, MediaType, Message, Part, Response, Usage};
+use crate::options::{ModelOptions, ReasoningVisibility, ToolChoice, TransportOptions};
+use crate::sse::{error_event, SSEResponseExt};
+use crate::stream::{accumulate, with_delta_reasoning_visibility, StreamDelta};
+use crate::tokenize::TokenCounter;
+use crate::tools::canonical_tools;
 
 const ANTHROPIC_VERSION: &str = "2023-06-01";
 
+/// Name and type of the provider-defined computer-use tool.
+const COMPUTER_TOOL: &str = "computer";
+const COMPUTER_TOOL_TYPE: &str = "computer_20250124";
+const WEB_SEARCH_TOOL: &str = "web_search";
+const WEB_SEARCH_TOOL_TYPE: &str = "web_search_20250305";
+const CODE_EXECUTION_TOOL: &str = "code_execution";
+const CODE_EXECUTION_TOOL_TYPE: &str = "code_execution_20250522";
+
+/// Convert a call of the computer tool into a [`Part::ComputerCall`].
+fn computer_call(name: &str, id: &Option<String>, input: &Value) -> Option<Part> {
+    if name != COMPUTER_TOOL {
+        return None;
+    }
+    Some(Part::ComputerCall {
+        id: id.clone(),
+        action: computer_action(input)?,
+        safety_checks: Vec::new(),
+        signature: None,
+        finished: true,
+    })
+}
+
+/// Parse the input of the computer tool.
+///
+/// Returns `None` for actions without a [`ComputerAction`] equivalent, such as
+/// `triple_click` or `hold_key`.
+fn computer_action(input: &Value) -> Option<ComputerAction> {
+    let point = |field: &str| -> Option<Coordinate> {
+        let coordinate = input.get(field)?.as_array()?;
+        match coordinate.as_slice() {
+            [x, y] => Some(Coordinate {
+                x: i32::try_from(x.as_i64()?).ok()?,
+                y: i32::try_from(y.as_i64()?).ok()?,
+            }),
+            _ => None,
+        }
+    };
+    let click = |button: MouseButton| {
+        point("coordinate").map(|Coordinate { x, y }| ComputerAction::Click { x, y, button })
+    };
+    let text = || {
+        input
+            .get("text")
+            .and_then(Value::as_str)
+            .map(str::to_string)
+    };
+
+    match input.get("action")?.as_str()? {
+        "screenshot" => Some(ComputerAction::Screenshot),
+        "left_click" => click(MouseButton::Left),
+        "right_click" => click(MouseButton::Right),
+        "middle_click" => click(MouseButton::Wheel),
+        "double_click" => {
+            point("coordinate").map(|Coordinate { x, y }| ComputerAction::DoubleClick { x, y })
+        }
+        "mouse_move" => {
+            point("coordinate").map(|Coordinate { x, y }| ComputerAction::Move { x, y })
+        }
+        "left_click_drag" => Some(ComputerAction::Drag {
+            path: vec![point("start_coordinate")?, point("coordinate")?],
+        }),
+        "scroll" => {
+            let Coordinate { x, y } = point("coordinate")?;
+            let amount = i32::try_from(input.get("scroll_amount")?.as_i64()?).ok()?;
+            let (scroll_x, scroll_y) = match input.get("scroll_direction")?.as_str()? {
+                "up" => (0, -amount),
+                "down" => (0, amount),
+                "left" => (-amount, 0),
+                "right" => (amount, 0),
+                _ => return None,
+            };
+            Some(ComputerAction::Scroll {
+                x,
+                y,
+                scroll_x,
+                scroll_y,
+            })
+        }
+        "type" => Some(ComputerAction::Type { text: text()? }),
+        "key" => Some(ComputerAction::Keypress {
+            keys: text()?.split('+').map(str::to_string).collect(),
+        }),
+        "wait" => Some(ComputerAction::Wait),
+        _ => None,
+    }
+}
+
+/// Format an action as input of the computer tool.
+fn computer_action_input(action: &ComputerAction) -> Value {
+    match action {
+        ComputerAction::Screenshot => json!({ "action": "screenshot" }),
+        ComputerAction::Click { x, y, button } => {
+            let action = match button {
+                MouseButton::Right => "right_click",
+                MouseButton::Wheel => "middle_click",
+                MouseButton::Left | MouseButton::Back | MouseButton::Forward => "left_click",
+            };
+            json!({ "action": action, "coordinate": [x, y] })
+        }
+        ComputerAction::DoubleClick { x, y } => {
+            json!({ "action": "double_click", "coordinate": [x, y] })
+        }
+        ComputerAction::Move { x, y } => json!({ "action": "mouse_move", "coordinate": [x, y] }),
+        ComputerAction::Drag { path } => {
+            let start = path.first().map(|c| [c.x, c.y]).unwrap_or_default();
+            let end = path.last().map(|c| [c.x, c.y]).unwrap_or_default();
+            json!({ "action": "left_click_drag", "start_coordinate": start, "coordinate": end })
+        }
+        ComputerAction::Scroll {
+            x,
+            y,
+            scroll_x,
+            scroll_y,
+        } => {
+            let (direction, amount) = match (*scroll_x, *scroll_y) {
+                (_, dy) if dy < 0 => ("up", -dy),
+                (_, dy) if dy > 0 => ("down", dy),
+                (dx, _) if dx < 0 => ("left", -dx),
+                (dx, _) => ("right", dx),
+            };
+            json!({
+                "action": "scroll",
+                "coordinate": [x, y],
+                "scroll_direction": direction,
+                "scroll_amount": amount,
+            })
+        }
+        ComputerAction::Type { text } => json!({ "action": "type", "text": text }),
+        ComputerAction::Keypress { keys } => json!({ "action": "key", "text": keys.join("+") }),
+        ComputerAction::Wait => json!({ "action": "wait", "duration": 1 }),
+    }
+}
+
 /// Anthropic model options.
 #[skip_serializing_none]
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -28,6 +173,145 @@ pub struct AnthropicModel {
     pub service_tier: Option<ServiceTier>,
     pub thinking_budget: Option<u32>,
     pub tool_choice: Option<AnthropicToolChoice>,
+    /// Mark the system prompt as a prompt cache breakpoint.
+    pub cache_system_prompt: Option<bool>,
+    /// Mark the tool definitions as a prompt cache breakpoint.
+    pub cache_tools: Option<bool>,
+    /// Mark the end of the conversation as a prompt cache breakpoint, so the
+    /// history is read from the cache on the next turn.
+    pub cache_conversation: Option<bool>,
+    /// Lifetime of the prompt cache breakpoints. The one hour TTL requires
+    /// [`AnthropicBeta::ExtendedCacheTtl`].
+    pub cache_ttl: Option<AnthropicCacheTtl>,
+    /// Beta features to enable, sent in the `anthropic-beta` header.
+    pub betas: Option<Vec<AnthropicBeta>>,
+    /// Offer the computer-use tool for the given display. Requires
+    /// [`AnthropicBeta::ComputerUse`].
+    pub computer_use: Option<ComputerDisplay>,
+}
+
+impl AnthropicModel {
+    /// Enable a beta feature.
+    pub fn with_beta(mut self, beta: AnthropicBeta) -> Self {
+        let betas = self.betas.get_or_insert_with(Vec::new);
+        if !betas.contains(&beta) {
+            betas.push(beta);
+        }
+        self
+    }
+
+    /// Whether a beta feature is enabled.
+    pub fn has_beta(&self, beta: &AnthropicBeta) -> bool {
+        self.betas
+            .as_ref()
+            .is_some_and(|betas| betas.contains(beta))
+    }
+
+    /// Value of the `anthropic-beta` header, if any beta is enabled.
+    fn beta_header(&self) -> Option<String> {
+        let betas = self.betas.as_ref().filter(|betas| !betas.is_empty())?;
+        Some(
+            betas
+                .iter()
+                .map(AnthropicBeta::header_value)
+                .unique()
+                .join(","),
+        )
+    }
+
+    /// Reject options that need a beta feature which is not enabled.
+    fn check_betas(&self) -> Result<(), ClientError> {
+        if self.cache_ttl == Some(AnthropicCacheTtl::OneHour)
+            && !self.has_beta(&AnthropicBeta::ExtendedCacheTtl)
+        {
+            return Err(ClientError::Config(
+                "A one hour cache TTL requires AnthropicBeta::ExtendedCacheTtl".to_string(),
+            ));
+        }
+        if self.computer_use.is_some() && !self.has_beta(&AnthropicBeta::ComputerUse) {
+            return Err(ClientError::Config(
+                "Computer use requires AnthropicBeta::ComputerUse".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Anthropic beta features.
+///
+/// Each variant maps to a dated `anthropic-beta` header value. Use
+/// [`Custom`](AnthropicBeta::Custom) for betas not listed here.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub enum AnthropicBeta {
+    /// Up to 128k output tokens on Claude 3.7 Sonnet.
+    Output128k,
+    /// Thinking between tool calls.
+    InterleavedThinking,
+    /// Token-efficient tool use on Claude 3.7 Sonnet.
+    TokenEfficientTools,
+    /// Computer use tools.
+    ComputerUse,
+    /// Files API.
+    FilesApi,
+    /// One million token context window.
+    Context1m,
+    /// One hour prompt cache TTL.
+    ExtendedCacheTtl,
+    /// Hosted code execution tool.
+    CodeExecution,
+    /// Any other beta, by its header value.
+    Custom(String),
+}
+
+impl AnthropicBeta {
+    /// Value of this beta in the `anthropic-beta` header.
+    pub fn header_value(&self) -> &str {
+        match self {
+            AnthropicBeta::Output128k => "output-128k-2025-02-19",
+            AnthropicBeta::InterleavedThinking => "interleaved-thinking-2025-05-14",
+            AnthropicBeta::TokenEfficientTools => "token-efficient-tools-2025-02-19",
+            AnthropicBeta::ComputerUse => "computer-use-2025-01-24",
+            AnthropicBeta::FilesApi => "files-api-2025-04-14",
+            AnthropicBeta::Context1m => "context-1m-2025-08-07",
+            AnthropicBeta::ExtendedCacheTtl => "extended-cache-ttl-2025-04-11",
+            AnthropicBeta::CodeExecution => "code-execution-2025-05-22",
+            AnthropicBeta::Custom(value) => value,
+        }
+    }
+}
+
+impl From<String> for AnthropicBeta {
+    fn from(value: String) -> Self {
+        [
+            AnthropicBeta::Output128k,
+            AnthropicBeta::InterleavedThinking,
+            AnthropicBeta::TokenEfficientTools,
+            AnthropicBeta::ComputerUse,
+            AnthropicBeta::FilesApi,
+            AnthropicBeta::Context1m,
+            AnthropicBeta::ExtendedCacheTtl,
+            AnthropicBeta::CodeExecution,
+        ]
+        .into_iter()
+        .find(|beta| beta.header_value() == value)
+        .unwrap_or(AnthropicBeta::Custom(value))
+    }
+}
+
+impl From<AnthropicBeta> for String {
+    fn from(beta: AnthropicBeta) -> Self {
+        beta.header_value().to_string()
+    }
+}
+
+/// Lifetime of a prompt cache entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AnthropicCacheTtl {
+    #[serde(rename = "5m")]
+    FiveMinutes,
+    #[serde(rename = "1h")]
+    OneHour,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -42,6 +326,7 @@ pub enum ServiceTier {
     StandardOnly,
 }
 
+#[skip_serializing_none]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum AnthropicToolChoice {
@@ -78,18 +363,21 @@ impl AnthropicClient {
             api_key,
             base_url,
             model_options,
-            transport_options,
+            transport_options: transport_options.for_provider("anthropic"),
         }
     }
 
     fn handle_error_response(status: reqwest::StatusCode, body: &str) -> ClientError {
-        if let Ok(error_resp) = serde_json::from_str::<AnthropicErrorResponse>(body) {
-            ClientError::ProviderError(format!(
+        let message = match serde_json::from_str::<AnthropicErrorResponse>(body) {
+            Ok(error_resp) => format!(
                 "Anthropic error ({}): {}",
                 error_resp.error.error_type, error_resp.error.message
-            ))
-        } else {
-            ClientError::ProviderError(format!("HTTP {}: {}", status, body))
+            ),
+            Err(_) => body.to_string(),
+        };
+        ClientError::Status {
+            status: status.as_u16(),
+            message,
         }
     }
 
@@ -102,10 +390,104 @@ impl AnthropicClient {
         let url = format!("{}/messages", self.base_url);
 
         let model = self.model_options.model.clone();
+        let provider = self.provider_for(&messages);
+        let req = self.post(&provider, &url)?;
 
         let request_body =
             AnthropicRequest::new(messages, &self.model_options, model, tools, stream);
 
+        Ok(req.json_logged(&request_body, &self.transport_options))
+    }
+
+    async fn send_stream(
+        &self,
+        messages: Vec<Message>,
+        tools: Vec<rmcp::model::Tool>,
+    ) -> Result<reqwest::Response, ClientError> {
+        let req = self.build_request(messages, tools, true)?;
+        let response = send_with_retry(req, &self.transport_options).await?;
+        let status = response.status();
+
+        if !status.is_success() {
+            let body = response
+                .text_logged(&self.transport_options)
+                .await
+                .unwrap_or_default();
+            return Err(Self::handle_error_response(status, &body));
+        }
+
+        Ok(response)
+    }
+
+    fn computer_use(&self) -> bool {
+        self.model_options.provider.computer_use.is_some()
+    }
+
+    /// Model options for a request, enabling the Files API beta if the
+    /// messages reference uploaded files, and the code execution beta if the
+    /// hosted tool is offered.
+    fn provider_for(&self, messages: &[Message]) -> Cow<'_, AnthropicModel> {
+        let mut provider = Cow::Borrowed(&self.model_options.provider);
+        let references_files = messages.iter().flat_map(Message::parts).any(|part| {
+            matches!(part, Part::Media { data, uri, .. } if file_reference(data, uri).is_some())
+        });
+        if references_files && !provider.has_beta(&AnthropicBeta::FilesApi) {
+            provider = Cow::Owned(provider.into_owned().with_beta(AnthropicBeta::FilesApi));
+        }
+        let executes_code = self
+            .model_options
+            .built_in_tools
+            .iter()
+            .flatten()
+            .any(|tool| *tool == BuiltInTool::CodeExecution);
+        if executes_code && !provider.has_beta(&AnthropicBeta::CodeExecution) {
+            provider = Cow::Owned(
+                provider
+                    .into_owned()
+                    .with_beta(AnthropicBeta::CodeExecution),
+            );
+        }
+        provider
+    }
+
+    fn post(
+        &self,
+        provider: &AnthropicModel,
+        url: &str,
+    ) -> Result<reqwest::RequestBuilder, ClientError> {
+        Ok(self
+            .authorized_as(provider, reqwest::Method::POST, url)?
+            .header(CONTENT_TYPE, HeaderValue::from_static("application/json")))
+    }
+
+    fn authorized(
+        &self,
+        method: reqwest::Method,
+        url: &str,
+    ) -> Result<reqwest::RequestBuilder, ClientError> {
+        self.authorized_as(&self.model_options.provider, method, url)
+    }
+
+    /// Requests to the Files API, which is in beta.
+    fn files_request(
+        &self,
+        method: reqwest::Method,
+        url: &str,
+    ) -> Result<reqwest::RequestBuilder, ClientError> {
+        let provider = self
+            .model_options
+            .provider
+            .clone()
+            .with_beta(AnthropicBeta::FilesApi);
+        self.authorized_as(&provider, method, url)
+    }
+
+    fn authorized_as(
+        &self,
+        provider: &AnthropicModel,
+        method: reqwest::Method,
+        url: &str,
+    ) -> Result<reqwest::RequestBuilder, ClientError> {
         let http_client = build_http_client(&self.transport_options)?;
 
         let mut headers = HeaderMap::new();
@@ -118,12 +500,18 @@ impl AnthropicClient {
             "anthropic-version",
             HeaderValue::from_static(ANTHROPIC_VERSION),
         );
-        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
-
-        let mut req = http_client.post(&url).headers(headers);
-        req = add_extra_headers(req, &self.transport_options);
+        provider.check_betas()?;
+        if let Some(betas) = provider.beta_header() {
+            headers.insert(
+                "anthropic-beta",
+                HeaderValue::from_str(&betas).map_err(|_| {
+                    ClientError::Config("Invalid anthropic-beta header".to_string())
+                })?,
+            );
+        }
 
-        Ok(req.json_logged(&request_body))
+        let req = http_client.request(method, url).headers(headers);
+        Ok(add_extra_headers(req, &self.transport_options))
     }
 }
 
@@ -138,16 +526,40 @@ impl Client for AnthropicClient {
     ) -> Result<Response, ClientError> {
         let req = self.build_request(messages, tools, false)?;
 
-        let response = req.send().await?;
+        let response = send_with_retry(req, &self.transport_options).await?;
         let status = response.status();
 
         if !status.is_success() {
-            let body = response.text_logged().await.unwrap_or_default();
+            let body = response
+                .text_logged(&self.transport_options)
+                .await
+                .unwrap_or_default();
             return Err(Self::handle_error_response(status, &body));
         }
 
-        let anthropic_response: AnthropicResponse = response.json_logged().await?;
-        Ok(anthropic_response.into())
+        let anthropic_response: AnthropicResponse =
+            response.json_logged(&self.transport_options).await?;
+        let mut response: Response = anthropic_response.into();
+        if self.model_options.provider.computer_use.is_some() {
+            for part in response.data.iter_mut().flat_map(Message::parts_mut) {
+                if let Part::FunctionCall {
+                    id,
+                    name,
+                    arguments,
+                    ..
+                } = part
+                {
+                    if let Some(call) = computer_call(name, id, arguments) {
+                        *part = call;
+                    }
+                }
+            }
+        }
+        hide_reasoning(&mut response, self.model_options.reasoning_visibility);
+        if self.model_options.plain_text.unwrap_or(false) {
+            strip_response_markdown(&mut response);
+        }
+        Ok(response)
     }
 
     fn model_options(&self) -> &ModelOptions<Self::ModelProvider> {
@@ -157,6 +569,58 @@ impl Client for AnthropicClient {
     fn transport_options(&self) -> &TransportOptions {
         &self.transport_options
     }
+
+    fn provider(&self) -> &'static str {
+        "anthropic"
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            supports_tools: true,
+            supports_vision: true,
+            supports_streaming: true,
+            supports_system_prompt: true,
+            max_context_tokens: Some(200_000),
+            supports_json_mode: false,
+        }
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>, ClientError> {
+        let mut models = Vec::new();
+        let mut after_id: Option<String> = None;
+
+        loop {
+            let mut req = self
+                .authorized(reqwest::Method::GET, &format!("{}/models", self.base_url))?
+                .query(&[("limit", "1000")]);
+            if let Some(after_id) = &after_id {
+                req = req.query(&[("after_id", after_id)]);
+            }
+
+            let response = send_with_retry(req, &self.transport_options).await?;
+            let status = response.status();
+            if !status.is_success() {
+                let body = response
+                    .text_logged(&self.transport_options)
+                    .await
+                    .unwrap_or_default();
+                return Err(Self::handle_error_response(status, &body));
+            }
+
+            let page: AnthropicModelList = response.json_logged(&self.transport_options).await?;
+            models.extend(page.data.into_iter().map(|model| ModelInfo {
+                id: model.id,
+                display_name: model.display_name,
+                input_modalities: vec![Modality::Text, Modality::Image, Modality::Document],
+                ..Default::default()
+            }));
+
+            match page.last_id {
+                Some(last_id) if page.has_more => after_id = Some(last_id),
+                _ => return Ok(models),
+            }
+        }
+    }
 }
 
 #[async_trait]
@@ -167,16 +631,132 @@ impl StreamingClient for AnthropicClient {
         tools: Vec<rmcp::model::Tool>,
     ) -> Result<Pin<Box<dyn Stream<Item = Result<Response, ClientError>> + Send>>, ClientError>
     {
-        let req = self.build_request(messages, tools, true)?;
-        let response = req.send().await?;
+        let response = self.send_stream(messages, tools).await?;
+        let stream = AnthropicStream::create_stream(response, self.computer_use());
+        let visibility = self.model_options.reasoning_visibility;
+        let stream = stream.map(move |response| {
+            response.map(|mut response| {
+                hide_reasoning(&mut response, visibility);
+                response
+            })
+        });
+        if self.model_options.plain_text.unwrap_or(false) {
+            return Ok(Box::pin(strip_stream_markdown(stream)));
+        }
+        Ok(Box::pin(stream))
+    }
+
+    async fn request_stream_deltas(
+        &self,
+        messages: Vec<Message>,
+        tools: Vec<rmcp::model::Tool>,
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<StreamDelta, ClientError>> + Send>>, ClientError>
+    {
+        let response = self.send_stream(messages, tools).await?;
+        let deltas = with_delta_reasoning_visibility(
+            AnthropicStream::deltas(response, self.computer_use()),
+            self.model_options.reasoning_visibility,
+            seal_hidden_thinking,
+        );
+        if self.model_options.plain_text.unwrap_or(false) {
+            return Ok(Box::pin(strip_delta_markdown(deltas)));
+        }
+        Ok(Box::pin(deltas))
+    }
+}
+
+#[async_trait]
+impl FileUpload for AnthropicClient {
+    async fn upload_file(
+        &self,
+        data: Vec<u8>,
+        mime_type: &str,
+        name: Option<&str>,
+    ) -> Result<FileRef, ClientError> {
+        let url = format!("{}/files", self.base_url);
+        let form = MultipartForm::new().file("file", data, mime_type, name.unwrap_or("file"));
+
+        let req = self
+            .files_request(reqwest::Method::POST, &url)?
+            .multipart_logged(&form, &self.transport_options);
+        let response = send_with_retry(req, &self.transport_options).await?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response
+                .text_logged(&self.transport_options)
+                .await
+                .unwrap_or_default();
+            return Err(Self::handle_error_response(status, &body));
+        }
+
+        let file: AnthropicFile = response.json_logged(&self.transport_options).await?;
+        Ok(FileRef {
+            uri: file.id.clone(),
+            id: file.id,
+            mime_type: file.mime_type,
+            name: Some(file.filename),
+            size_bytes: Some(file.size_bytes),
+        })
+    }
+
+    async fn delete_file(&self, file: &FileRef) -> Result<(), ClientError> {
+        let url = format!("{}/files/{}", self.base_url, file.id);
+        let req = self.files_request(reqwest::Method::DELETE, &url)?;
+        let response = send_with_retry(req, &self.transport_options).await?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response
+                .text_logged(&self.transport_options)
+                .await
+                .unwrap_or_default();
+            return Err(Self::handle_error_response(status, &body));
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl TokenCounter for AnthropicClient {
+    async fn count_tokens_exact(
+        &self,
+        messages: &[Message],
+        tools: &[rmcp::model::Tool],
+    ) -> Result<Option<usize>, ClientError> {
+        let url = format!("{}/messages/count_tokens", self.base_url);
+
+        let request = AnthropicRequest::new(
+            messages.to_vec(),
+            &self.model_options,
+            self.model_options.model.clone(),
+            tools.to_vec(),
+            false,
+        );
+        let request_body = AnthropicCountTokensRequest {
+            model: request.model,
+            messages: request.messages,
+            system: request.system,
+            tools: request.tools,
+            tool_choice: request.tool_choice,
+            thinking: request.thinking,
+        };
+
+        let req = self
+            .post(&self.provider_for(messages), &url)?
+            .json_logged(&request_body, &self.transport_options);
+        let response = send_with_retry(req, &self.transport_options).await?;
         let status = response.status();
 
         if !status.is_success() {
-            let body = response.text_logged().await.unwrap_or_default();
+            let body = response
+                .text_logged(&self.transport_options)
+                .await
+                .unwrap_or_default();
             return Err(Self::handle_error_response(status, &body));
         }
 
-        Ok(Box::pin(AnthropicStream::create_stream(response)))
+        let count: AnthropicCountTokensResponse =
+            response.json_logged(&self.transport_options).await?;
+        Ok(Some(count.input_tokens as usize))
     }
 }
 
@@ -187,126 +767,210 @@ struct AnthropicStream;
 impl AnthropicStream {
     fn create_stream(
         response: reqwest::Response,
+        computer_use: bool,
     ) -> impl Stream<Item = Result<Response, ClientError>> + Send {
+        accumulate(Self::deltas(response, computer_use))
+    }
+
+    /// With `computer_use`, calls of the computer tool are buffered and emitted
+    /// whole as [`StreamDelta::ComputerCall`] once their input is complete.
+    fn deltas(
+        response: reqwest::Response,
+        computer_use: bool,
+    ) -> impl Stream<Item = Result<StreamDelta, ClientError>> + Send {
         let sse_stream = response.sse();
 
         Box::pin(async_stream::try_stream! {
             let mut stream = Box::pin(sse_stream);
-            let mut current_response = Response {
-                data: vec![Message::Assistant(vec![])],
-                usage: Usage::default(),
-                finish: FinishReason::Unfinished,
-            };
 
-            let mut tool_buffers: HashMap<u32, (String, String, String)> = HashMap::new();
+            // Maps content block indices to part indices. Unsupported blocks are skipped,
+            // so the two can diverge.
+            let mut block_parts: HashMap<u32, usize> = HashMap::new();
+            // Buffered id and input of computer tool calls, by content block index.
+            let mut computer_calls: HashMap<u32, (String, String)> = HashMap::new();
+            // Buffered id and input of server tool calls, by content block index, and
+            // their inputs, by call id, until their results arrive.
+            let mut server_calls: HashMap<u32, (String, String)> = HashMap::new();
+            let mut server_inputs: HashMap<String, Value> = HashMap::new();
+            // Length and citations of text blocks, by content block index. Citations
+            // follow their block once it is complete; having no block of their own,
+            // their parts are counted apart.
+            let mut text_blocks: HashMap<u32, (usize, Vec<AnthropicCitation>)> = HashMap::new();
+            let mut citation_parts = 0;
 
             while let Some(event_result) = stream.next().await {
                 let event_str = event_result?;
 
+                // Error events without a type are not part of the enum.
                 let chunk_result: AnthropicStreamEvent = serde_json::from_str(&event_str)
-                    .map_err(|e| ClientError::ProviderError(format!("JSON parse error: {}", e)))?;
+                    .map_err(|e| error_event(&event_str).unwrap_or_else(|| ClientError::ProviderError(format!("JSON parse error: {}", e))))?;
 
                 match chunk_result {
                     AnthropicStreamEvent::MessageStart { message } => {
-                        current_response.usage.prompt_tokens = Some(message.usage.input_tokens);
-                        current_response.usage.completion_tokens = Some(message.usage.output_tokens);
-                        yield current_response.clone();
+                        yield StreamDelta::Model { model: message.model };
+                        yield StreamDelta::Usage(message.usage.into());
                     },
                     AnthropicStreamEvent::ContentBlockStart { index, content_block } => {
-                        let parts = current_response.data[0].parts_mut();
+                        let part_index = block_parts.len() + citation_parts;
 
                         match content_block {
-                            AnthropicContentBlock::Text { text, .. } => {
-                                parts.push(Part::Text { content: text, finished: false });
+                            AnthropicContentBlock::Text { text, citations, .. } => {
+                                block_parts.insert(index, part_index);
+                                text_blocks.insert(index, (text.chars().count(), citations));
+                                yield StreamDelta::TextDelta { index: part_index, text };
+                            },
+                            AnthropicContentBlock::ToolUse { id, name, .. } if computer_use && name == COMPUTER_TOOL => {
+                                block_parts.insert(index, part_index);
+                                computer_calls.insert(index, (id, String::new()));
                             },
                             AnthropicContentBlock::ToolUse { id, name, .. } => {
-                                tool_buffers.insert(index, (id.clone(), name.clone(), String::new()));
-                                parts.push(Part::FunctionCall {
+                                block_parts.insert(index, part_index);
+                                yield StreamDelta::ToolCallDelta {
+                                    index: part_index,
                                     id: Some(id),
-                                    name,
-                                    arguments: Value::Null,
+                                    name: Some(name),
+                                    arguments: String::new(),
                                     signature: None,
-                                    finished: false,
-                                });
+                                };
                             },
                             AnthropicContentBlock::Thinking { thinking, signature } => {
-                                parts.push(Part::Reasoning {
-                                    content: thinking,
-                                    summary: None,
+                                block_parts.insert(index, part_index);
+                                yield StreamDelta::ReasoningDelta {
+                                    index: part_index,
+                                    text: thinking,
                                     signature: Some(signature),
-                                    finished: false,
-                                });
+                                };
+                            },
+                            AnthropicContentBlock::RedactedThinking { data } => {
+                                block_parts.insert(index, part_index);
+                                yield StreamDelta::ReasoningDelta {
+                                    index: part_index,
+                                    text: String::new(),
+                                    signature: Some(redacted_signature(&data)),
+                                };
+                            },
+                            AnthropicContentBlock::ServerToolUse { id, input, .. } => {
+                                let input = if input == json!({}) { String::new() } else { input.to_string() };
+                                server_calls.insert(index, (id, input));
+                            },
+                            AnthropicContentBlock::WebSearchToolResult { tool_use_id, content } => {
+                                block_parts.insert(index, part_index);
+                                yield StreamDelta::HostedToolCall {
+                                    index: part_index,
+                                    call: HostedToolCall::WebSearch {
+                                        query: server_inputs.remove(&tool_use_id).and_then(|input| input["query"].as_str().map(str::to_string)),
+                                        sources: web_search_sources(&content),
+                                    },
+                                    id: Some(tool_use_id),
+                                };
+                            },
+                            AnthropicContentBlock::CodeExecutionToolResult { tool_use_id, content } => {
+                                block_parts.insert(index, part_index);
+                                yield StreamDelta::HostedToolCall {
+                                    index: part_index,
+                                    call: code_execution_call(server_inputs.remove(&tool_use_id), &content),
+                                    id: Some(tool_use_id),
+                                };
                             },
                             _ => {},
                         }
-                        yield current_response.clone();
                     },
                     AnthropicStreamEvent::ContentBlockDelta { index, delta } => {
-                        let parts = current_response.data[0].parts_mut();
-                        if let Some(part) = parts.get_mut(index as usize) {
-                            match delta {
+                        if let Some((_, input)) = computer_calls.get_mut(&index) {
+                            if let AnthropicDelta::InputJson { partial_json } = delta {
+                                input.push_str(&partial_json);
+                            }
+                        } else if let Some((_, input)) = server_calls.get_mut(&index) {
+                            if let AnthropicDelta::InputJson { partial_json } = delta {
+                                input.push_str(&partial_json);
+                            }
+                        } else if let Some(&part_index) = block_parts.get(&index) {
+                            yield match delta {
                                 AnthropicDelta::Text { text } => {
-                                    if let Part::Text { content: current_text, .. } = part {
-                                        current_text.push_str(&text);
+                                    if let Some((length, _)) = text_blocks.get_mut(&index) {
+                                        *length += text.chars().count();
                                     }
+                                    StreamDelta::TextDelta { index: part_index, text }
                                 },
-                                AnthropicDelta::InputJson { partial_json } => {
-                                    if let Some(buffer) = tool_buffers.get_mut(&index) {
-                                        buffer.2.push_str(&partial_json);
+                                AnthropicDelta::Citations { citation } => {
+                                    if let Some((_, citations)) = text_blocks.get_mut(&index) {
+                                        citations.push(citation);
                                     }
+                                    continue;
                                 },
-                                AnthropicDelta::Thinking { thinking } => {
-                                    if let Part::Reasoning { content, .. } = part {
-                                        content.push_str(&thinking);
-                                    }
+                                AnthropicDelta::InputJson { partial_json } => StreamDelta::ToolCallDelta {
+                                    index: part_index,
+                                    id: None,
+                                    name: None,
+                                    arguments: partial_json,
+                                    signature: None,
                                 },
-                                AnthropicDelta::Signature { signature } => {
-                                     if let Part::Reasoning { signature: sig, .. } = part {
-                                        *sig = Some(signature);
-                                    }
-                                }
-                            }
+                                AnthropicDelta::Thinking { thinking } => StreamDelta::ReasoningDelta {
+                                    index: part_index,
+                                    text: thinking,
+                                    signature: None,
+                                },
+                                AnthropicDelta::Signature { signature } => StreamDelta::ReasoningDelta {
+                                    index: part_index,
+                                    text: String::new(),
+                                    signature: Some(signature),
+                                },
+                            };
                         }
-                        yield current_response.clone();
                     },
                     AnthropicStreamEvent::ContentBlockStop { index } => {
-                        let parts = current_response.data[0].parts_mut();
-                        if let Some(part) = parts.get_mut(index as usize) {
-                            match part {
-                                Part::Text { finished, .. } => *finished = true,
-                                Part::Reasoning { finished, .. } => *finished = true,
-                                Part::FunctionCall { finished, arguments, .. } => {
-                                    *finished = true;
-                                    if let Some((_, _, json_str)) = tool_buffers.remove(&index) {
-                                        if let Ok(json_val) = serde_json::from_str(&json_str) {
-                                            *arguments = json_val;
-                                        }
-                                    }
-                                },
-                                Part::FunctionResponse { finished, .. } => *finished = true,
-                                Part::Media { finished, .. } => *finished = true,
+                        if let Some((id, input)) = server_calls.remove(&index) {
+                            server_inputs.insert(id, serde_json::from_str(&input).unwrap_or_default());
+                        }
+                        if let Some(&part_index) = block_parts.get(&index) {
+                            if let Some((id, input)) = computer_calls.remove(&index) {
+                                let arguments: Value = serde_json::from_str(&input).unwrap_or_default();
+                                yield match computer_action(&arguments) {
+                                    Some(action) => StreamDelta::ComputerCall {
+                                        index: part_index,
+                                        id: Some(id),
+                                        action,
+                                        safety_checks: Vec::new(),
+                                        signature: None,
+                                    },
+                                    // Actions without a common equivalent stay regular tool calls.
+                                    None => StreamDelta::ToolCallDelta {
+                                        index: part_index,
+                                        id: Some(id),
+                                        name: Some(COMPUTER_TOOL.to_string()),
+                                        arguments: input,
+                                        signature: None,
+                                    },
+                                };
+                            }
+                            yield StreamDelta::PartFinished { index: part_index };
+                        }
+                        if let Some((length, citations)) = text_blocks.remove(&index) {
+                            for citation in citations {
+                                let part_index = block_parts.len() + citation_parts;
+                                citation_parts += 1;
+                                yield StreamDelta::Citation { index: part_index, citation: citation.citation(length) };
                             }
                         }
-                        yield current_response.clone();
                     },
                     AnthropicStreamEvent::MessageDelta { delta, usage } => {
+                        if let Some(usage_delta) = usage {
+                            yield StreamDelta::Usage(Usage {
+                                completion_tokens: Some(usage_delta.output_tokens),
+                                ..Default::default()
+                            });
+                        }
                         if let Some(stop_reason) = delta.stop_reason {
-                            current_response.finish = match stop_reason.as_str() {
+                            yield StreamDelta::Finish(match stop_reason.as_str() {
                                 "end_turn" => FinishReason::Stop,
                                 "max_tokens" => FinishReason::OutputTokens,
                                 "stop_sequence" => FinishReason::Stop,
                                 "tool_use" => FinishReason::ToolCalls,
                                 _ => FinishReason::Stop,
-                            };
-                        }
-                        if let Some(usage_delta) = usage {
-                            current_response.usage.completion_tokens = Some(usage_delta.output_tokens);
+                            });
                         }
-                        yield current_response.clone();
-                    },
-                    AnthropicStreamEvent::MessageStop => {
-                        yield current_response.clone();
                     },
+                    AnthropicStreamEvent::MessageStop => {},
                     AnthropicStreamEvent::Ping => {},
                     AnthropicStreamEvent::Error { error } => {
                         Err(ClientError::ProviderError(format!("Stream error ({}): {}", error.error_type, error.message)))?;
@@ -339,6 +1003,18 @@ struct AnthropicRequest {
     thinking: Option<AnthropicThinkingConfig>,
 }
 
+#[skip_serializing_none]
+#[derive(Debug, Serialize)]
+struct AnthropicCountTokensRequest {
+    model: String,
+    messages: Vec<AnthropicMessage>,
+    system: Option<Vec<AnthropicSystemBlock>>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tools: Vec<AnthropicTool>,
+    tool_choice: Option<AnthropicToolChoice>,
+    thinking: Option<AnthropicThinkingConfig>,
+}
+
 #[derive(Debug, Serialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
 #[allow(dead_code)]
@@ -347,15 +1023,42 @@ enum AnthropicThinkingConfig {
     Disabled,
 }
 
+#[skip_serializing_none]
 #[derive(Debug, Serialize)]
 struct AnthropicTool {
+    /// Type of a provider-defined tool; `None` for custom tools.
+    #[serde(rename = "type")]
+    tool_type: Option<&'static str>,
     name: String,
     description: Option<String>,
-    input_schema: serde_json::Value,
-    #[serde(skip_serializing_if = "Option::is_none")]
+    input_schema: Option<serde_json::Value>,
+    display_width_px: Option<u32>,
+    display_height_px: Option<u32>,
     cache_control: Option<AnthropicCacheControl>,
 }
 
+#[derive(Debug, Deserialize)]
+struct AnthropicFile {
+    id: String,
+    filename: String,
+    mime_type: String,
+    size_bytes: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct AnthropicModelList {
+    data: Vec<AnthropicModelEntry>,
+    #[serde(default)]
+    has_more: bool,
+    last_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AnthropicModelEntry {
+    id: String,
+    display_name: Option<String>,
+}
+
 #[derive(Debug, Serialize)]
 struct AnthropicMessage {
     role: String,
@@ -375,14 +1078,17 @@ enum AnthropicSystemBlock {
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
 enum AnthropicCacheControl {
-    Ephemeral,
+    Ephemeral {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        ttl: Option<AnthropicCacheTtl>,
+    },
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
 enum AnthropicToolResultBlock {
     Text { text: String },
-    Image { source: AnthropicImageSource },
+    Image { source: AnthropicSource },
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -397,16 +1103,19 @@ enum AnthropicToolResultContent {
 enum AnthropicContentBlock {
     Text {
         text: String,
+        /// Sources of the text, which are not sent back.
+        #[serde(default, skip_serializing)]
+        citations: Vec<AnthropicCitation>,
         #[serde(skip_serializing_if = "Option::is_none")]
         cache_control: Option<AnthropicCacheControl>,
     },
     Image {
-        source: AnthropicImageSource,
+        source: AnthropicSource,
         #[serde(skip_serializing_if = "Option::is_none")]
         cache_control: Option<AnthropicCacheControl>,
     },
     Document {
-        source: AnthropicDocumentSource,
+        source: AnthropicSource,
         #[serde(skip_serializing_if = "Option::is_none")]
         cache_control: Option<AnthropicCacheControl>,
     },
@@ -432,22 +1141,208 @@ enum AnthropicContentBlock {
     RedactedThinking {
         data: String,
     },
+    /// Call of a tool run by Anthropic, such as web search.
+    ServerToolUse {
+        id: String,
+        name: String,
+        input: Value,
+    },
+    /// Results of a web search, or an error object if it failed.
+    WebSearchToolResult {
+        tool_use_id: String,
+        content: Value,
+    },
+    /// Output of code run by Anthropic, or an error object if it failed.
+    CodeExecutionToolResult {
+        tool_use_id: String,
+        content: Value,
+    },
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct AnthropicImageSource {
-    #[serde(rename = "type")]
-    source_type: String,
-    media_type: String,
-    data: String,
+/// Source of a text block, such as a web search result or a passage of a
+/// document.
+#[derive(Debug, Deserialize)]
+struct AnthropicCitation {
+    url: Option<String>,
+    title: Option<String>,
+    document_title: Option<String>,
+}
+
+impl AnthropicCitation {
+    /// Citation of a text block `length` characters long. Anthropic splits text
+    /// into blocks by the sources backing it, so a citation covers its block.
+    fn citation(self, length: usize) -> Citation {
+        Citation {
+            url: self.url,
+            title: self.title.or(self.document_title),
+            start_index: Some(0),
+            end_index: Some(length),
+            ..Default::default()
+        }
+    }
+}
+
+/// Pages of a web search result block, empty if the search failed.
+fn web_search_sources(content: &Value) -> Vec<SearchSource> {
+    content
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|result| {
+            Some(SearchSource {
+                url: result.get("url")?.as_str()?.to_string(),
+                title: result
+                    .get("title")
+                    .and_then(Value::as_str)
+                    .map(str::to_string),
+            })
+        })
+        .collect()
+}
+
+/// Convert a code execution result into a [`HostedToolCall::CodeInterpreter`],
+/// given the input of its call.
+fn code_execution_call(input: Option<Value>, content: &Value) -> HostedToolCall {
+    let logs = ["stdout", "stderr"]
+        .iter()
+        .filter_map(|stream| content.get(stream)?.as_str())
+        .filter(|logs| !logs.is_empty())
+        .join("\n");
+    HostedToolCall::CodeInterpreter {
+        code: input
+            .as_ref()
+            .and_then(|input| input["code"].as_str())
+            .map(str::to_string),
+        container_id: None,
+        outputs: if logs.is_empty() {
+            vec![]
+        } else {
+            vec![CodeOutput::Logs { logs }]
+        },
+    }
+}
+
+/// Prefix of the signature of a [`Part::Reasoning`] holding a redacted thinking
+/// block, whose encrypted data is kept in the rest of the signature.
+const REDACTED_THINKING: &str = "redacted:";
+
+fn redacted_signature(data: &str) -> String {
+    format!("{}{}", REDACTED_THINKING, data)
+}
+
+/// Prefix of the signature of a [`Part::Reasoning`] whose thinking was hidden by a
+/// [`ReasoningVisibility`]. Thinking is only accepted back unmodified, so the
+/// signature keeps it base64-encoded, followed by `:` and the block signature.
+const HIDDEN_THINKING: &str = "hidden:";
+
+/// Apply a reasoning visibility, keeping hidden thinking in the signature so the
+/// response can still be sent back.
+fn hide_reasoning(response: &mut Response, visibility: Option<ReasoningVisibility>) {
+    let visibility = visibility.unwrap_or_default();
+    if visibility == ReasoningVisibility::Full {
+        return;
+    }
+    for part in response.data.iter_mut().flat_map(Message::parts_mut) {
+        if let Part::Reasoning {
+            content,
+            signature: Some(signature),
+            ..
+        } = part
+        {
+            *signature = seal_hidden_thinking(content, std::mem::take(signature));
+        }
+    }
+    response.set_reasoning_visibility(visibility);
+}
+
+/// Carry the thinking text in its signature, so it can be sent back once hidden.
+fn seal_hidden_thinking(content: &str, signature: String) -> String {
+    let kept = signature.starts_with(REDACTED_THINKING) || signature.starts_with(HIDDEN_THINKING);
+    if content.is_empty() || kept {
+        return signature;
+    }
+    format!(
+        "{}{}:{}",
+        HIDDEN_THINKING,
+        BASE64_STANDARD.encode(content.as_bytes()),
+        signature
+    )
+}
+
+impl AnthropicContentBlock {
+    /// Block sending a reasoning part back. Thinking blocks are only accepted with
+    /// the signature they were returned with, so unsigned reasoning, such as that of
+    /// other providers, is dropped.
+    fn from_reasoning(content: &str, signature: Option<&str>) -> Option<Self> {
+        let signature = signature.filter(|signature| !signature.is_empty())?;
+        if let Some(hidden) = signature.strip_prefix(HIDDEN_THINKING) {
+            let (thinking, signature) = hidden.split_once(':')?;
+            return Some(AnthropicContentBlock::Thinking {
+                thinking: String::from_utf8(BASE64_STANDARD.decode(thinking).ok()?).ok()?,
+                signature: signature.to_string(),
+            });
+        }
+        Some(match signature.strip_prefix(REDACTED_THINKING) {
+            Some(data) => AnthropicContentBlock::RedactedThinking {
+                data: data.to_string(),
+            },
+            None => AnthropicContentBlock::Thinking {
+                thinking: content.to_string(),
+                signature: signature.to_string(),
+            },
+        })
+    }
+
+    /// Cache control slot of the block, if the block type accepts one.
+    fn cache_control(&mut self) -> Option<&mut Option<AnthropicCacheControl>> {
+        match self {
+            AnthropicContentBlock::Text { cache_control, .. }
+            | AnthropicContentBlock::Image { cache_control, .. }
+            | AnthropicContentBlock::Document { cache_control, .. }
+            | AnthropicContentBlock::ToolUse { cache_control, .. }
+            | AnthropicContentBlock::ToolResult { cache_control, .. } => Some(cache_control),
+            AnthropicContentBlock::Thinking { .. }
+            | AnthropicContentBlock::RedactedThinking { .. }
+            | AnthropicContentBlock::ServerToolUse { .. }
+            | AnthropicContentBlock::WebSearchToolResult { .. }
+            | AnthropicContentBlock::CodeExecutionToolResult { .. } => None,
+        }
+    }
 }
 
+/// Source of an image or document block: inline base64 data or an uploaded file.
+#[skip_serializing_none]
 #[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct AnthropicDocumentSource {
+pub struct AnthropicSource {
     #[serde(rename = "type")]
     pub source_type: String,
-    pub media_type: String,
-    pub data: String,
+    pub media_type: Option<String>,
+    pub data: Option<String>,
+    pub file_id: Option<String>,
+}
+
+impl AnthropicSource {
+    fn base64(media_type: &str, data: &str) -> Self {
+        Self {
+            source_type: "base64".to_string(),
+            media_type: Some(media_type.to_string()),
+            data: Some(data.to_string()),
+            file_id: None,
+        }
+    }
+
+    /// Source of a media part, referencing the uploaded file if it has no data.
+    fn media(data: &str, mime_type: &str, uri: &Option<String>) -> Self {
+        match file_reference(data, uri) {
+            Some(file_id) => Self {
+                source_type: "file".to_string(),
+                media_type: None,
+                data: None,
+                file_id: Some(file_id.to_string()),
+            },
+            None => Self::base64(mime_type, data),
+        }
+    }
 }
 
 impl AnthropicRequest {
@@ -460,7 +1355,7 @@ impl AnthropicRequest {
     ) -> Self {
         let mut messages = Vec::new();
 
-        for msg in messages_in {
+        for msg in correct_media_types(messages_in) {
             let role = match msg {
                 Message::User(_) => "user",
                 Message::Assistant(_) => "assistant",
@@ -472,6 +1367,7 @@ impl AnthropicRequest {
                     Part::Text { content: t, .. } => {
                         content_blocks.push(AnthropicContentBlock::Text {
                             text: t.clone(),
+                            citations: Vec::new(),
                             cache_control: None,
                         })
                     }
@@ -479,34 +1375,43 @@ impl AnthropicRequest {
                         media_type,
                         data,
                         mime_type,
+                        uri,
                         ..
                     } => {
                         content_blocks.push(AnthropicContentBlock::Text {
                             text: part.anchor_media(),
+                            citations: Vec::new(),
                             cache_control: None,
                         });
 
                         match media_type {
                             MediaType::Image => {
                                 content_blocks.push(AnthropicContentBlock::Image {
-                                    source: AnthropicImageSource {
-                                        source_type: "base64".to_string(),
-                                        media_type: mime_type.clone(),
-                                        data: data.clone(),
-                                    },
+                                    source: AnthropicSource::media(data, mime_type, uri),
                                     cache_control: None,
                                 });
                             }
                             MediaType::Document => {
                                 content_blocks.push(AnthropicContentBlock::Document {
-                                    source: AnthropicDocumentSource {
-                                        source_type: "base64".to_string(),
-                                        media_type: mime_type.clone(),
-                                        data: data.clone(),
-                                    },
+                                    source: AnthropicSource::media(data, mime_type, uri),
+                                    cache_control: None,
+                                });
+                            }
+                            // Uploaded files are read as documents whatever their type.
+                            MediaType::Text | MediaType::Audio | MediaType::Binary
+                                if file_reference(data, uri).is_some() =>
+                            {
+                                content_blocks.push(AnthropicContentBlock::Document {
+                                    source: AnthropicSource::media(data, mime_type, uri),
                                     cache_control: None,
                                 });
                             }
+                            MediaType::Audio => {
+                                tracing::warn!(
+                                    "Anthropic does not accept audio, sending only the anchor of {}",
+                                    mime_type
+                                );
+                            }
                             MediaType::Text | MediaType::Binary => {
                                 let content = match BASE64_STANDARD.decode(data) {
                                     Ok(bytes) => String::from_utf8(bytes).unwrap_or(data.clone()),
@@ -514,6 +1419,7 @@ impl AnthropicRequest {
                                 };
                                 content_blocks.push(AnthropicContentBlock::Text {
                                     text: content,
+                                    citations: Vec::new(),
                                     cache_control: None,
                                 });
                             }
@@ -534,6 +1440,39 @@ impl AnthropicRequest {
                             });
                         }
                     }
+                    Part::ComputerCall {
+                        id: Some(call_id),
+                        action,
+                        ..
+                    } => {
+                        content_blocks.push(AnthropicContentBlock::ToolUse {
+                            id: call_id.clone(),
+                            name: COMPUTER_TOOL.to_string(),
+                            input: computer_action_input(action),
+                            cache_control: None,
+                        });
+                    }
+                    Part::ComputerResult {
+                        id: Some(call_id),
+                        screenshot,
+                        mime_type,
+                        ..
+                    } => {
+                        content_blocks.push(AnthropicContentBlock::ToolResult {
+                            tool_use_id: call_id.clone(),
+                            content: AnthropicToolResultContent::Blocks(vec![
+                                AnthropicToolResultBlock::Image {
+                                    source: AnthropicSource::base64(mime_type, screenshot),
+                                },
+                            ]),
+                            is_error: None,
+                            cache_control: None,
+                        });
+                    }
+                    Part::ComputerCall { .. }
+                    | Part::ComputerResult { .. }
+                    | Part::HostedToolCall { .. }
+                    | Part::Citation { .. } => {}
                     Part::FunctionResponse {
                         id,
                         response,
@@ -554,6 +1493,7 @@ impl AnthropicRequest {
                                     media_type,
                                     data,
                                     mime_type,
+                                    uri,
                                     ..
                                 } = part
                                 {
@@ -564,11 +1504,9 @@ impl AnthropicRequest {
                                     match media_type {
                                         MediaType::Image => {
                                             blocks.push(AnthropicToolResultBlock::Image {
-                                                source: AnthropicImageSource {
-                                                    source_type: "base64".to_string(),
-                                                    media_type: mime_type.clone(),
-                                                    data: data.clone(),
-                                                },
+                                                source: AnthropicSource::media(
+                                                    data, mime_type, uri,
+                                                ),
                                             });
                                         }
                                         _ => {
@@ -597,10 +1535,10 @@ impl AnthropicRequest {
                     Part::Reasoning {
                         content, signature, ..
                     } => {
-                        content_blocks.push(AnthropicContentBlock::Thinking {
-                            thinking: content.clone(),
-                            signature: signature.clone().unwrap_or_default(),
-                        });
+                        content_blocks.extend(AnthropicContentBlock::from_reasoning(
+                            content,
+                            signature.as_deref(),
+                        ));
                     }
                 }
             }
@@ -613,15 +1551,69 @@ impl AnthropicRequest {
             }
         }
 
-        let tools = tool_defs
+        let provider = &model_options.provider;
+        let ephemeral = || AnthropicCacheControl::Ephemeral {
+            ttl: provider.cache_ttl,
+        };
+        if provider.cache_conversation.unwrap_or(false) {
+            if let Some(block) = messages
+                .last_mut()
+                .and_then(|m| m.content.iter_mut().rev().find_map(|b| b.cache_control()))
+            {
+                *block = Some(ephemeral());
+            }
+        }
+
+        let mut tools: Vec<AnthropicTool> = canonical_tools(tool_defs)
             .into_iter()
             .map(|t| AnthropicTool {
+                tool_type: None,
                 name: t.name.into_owned(),
                 description: t.description.map(|d| d.into_owned()),
-                input_schema: serde_json::Value::Object((*t.input_schema).clone()),
+                input_schema: Some(serde_json::Value::Object((*t.input_schema).clone())),
+                display_width_px: None,
+                display_height_px: None,
                 cache_control: None,
             })
             .collect();
+        if let Some(display) = &provider.computer_use {
+            tools.push(AnthropicTool {
+                tool_type: Some(COMPUTER_TOOL_TYPE),
+                name: COMPUTER_TOOL.to_string(),
+                description: None,
+                input_schema: None,
+                display_width_px: Some(display.width),
+                display_height_px: Some(display.height),
+                cache_control: None,
+            });
+        }
+        for tool in model_options.built_in_tools.iter().flatten() {
+            match tool {
+                BuiltInTool::WebSearch => tools.push(AnthropicTool {
+                    tool_type: Some(WEB_SEARCH_TOOL_TYPE),
+                    name: WEB_SEARCH_TOOL.to_string(),
+                    description: None,
+                    input_schema: None,
+                    display_width_px: None,
+                    display_height_px: None,
+                    cache_control: None,
+                }),
+                BuiltInTool::CodeExecution => tools.push(AnthropicTool {
+                    tool_type: Some(CODE_EXECUTION_TOOL_TYPE),
+                    name: CODE_EXECUTION_TOOL.to_string(),
+                    description: None,
+                    input_schema: None,
+                    display_width_px: None,
+                    display_height_px: None,
+                    cache_control: None,
+                }),
+            }
+        }
+        if provider.cache_tools.unwrap_or(false) {
+            if let Some(tool) = tools.last_mut() {
+                tool.cache_control = Some(ephemeral());
+            }
+        }
 
         let thinking = if model_options.reasoning.unwrap_or(false) {
             if let Some(budget) = model_options.provider.thinking_budget {
@@ -637,13 +1629,36 @@ impl AnthropicRequest {
             None
         };
 
-        let system = model_options.system.as_ref().map(|s| {
+        let system = model_options.system_prompt().map(|s| {
             vec![AnthropicSystemBlock::Text {
-                text: s.clone(),
-                cache_control: None,
+                text: s,
+                cache_control: provider
+                    .cache_system_prompt
+                    .unwrap_or(false)
+                    .then(ephemeral),
             }]
         });
 
+        let tool_choice = model_options.provider.tool_choice.clone().or_else(|| {
+            let choice = model_options
+                .tool_choice
+                .as_ref()
+                .filter(|_| !tools.is_empty())?;
+            Some(match choice {
+                ToolChoice::Auto => AnthropicToolChoice::Auto {
+                    disable_parallel_tool_use: None,
+                },
+                ToolChoice::None => AnthropicToolChoice::None,
+                ToolChoice::Required => AnthropicToolChoice::Any {
+                    disable_parallel_tool_use: None,
+                },
+                ToolChoice::Specific(name) => AnthropicToolChoice::Tool {
+                    name: name.clone(),
+                    disable_parallel_tool_use: None,
+                },
+            })
+        });
+
         AnthropicRequest {
             model,
             messages,
@@ -654,9 +1669,13 @@ impl AnthropicRequest {
             top_k: model_options.provider.top_k,
             stream: if stream { Some(true) } else { None },
             tools,
-            tool_choice: model_options.provider.tool_choice.clone(),
+            tool_choice,
             metadata: model_options.provider.metadata.clone(),
-            stop_sequences: model_options.provider.stop_sequences.clone(),
+            stop_sequences: model_options
+                .provider
+                .stop_sequences
+                .clone()
+                .or_else(|| model_options.stop.clone()),
             service_tier: model_options.provider.service_tier.clone(),
             thinking,
         }
@@ -690,6 +1709,27 @@ struct AnthropicUsage {
     cache_read_input_tokens: Option<u32>,
 }
 
+impl From<AnthropicUsage> for Usage {
+    /// Anthropic reports cache reads and writes separately from `input_tokens`;
+    /// they are folded into `prompt_tokens` to match the other providers.
+    fn from(usage: AnthropicUsage) -> Self {
+        let cache_read = usage.cache_read_input_tokens.unwrap_or(0);
+        let cache_creation = usage.cache_creation_input_tokens.unwrap_or(0);
+        Usage {
+            prompt_tokens: Some(usage.input_tokens + cache_read + cache_creation),
+            completion_tokens: Some(usage.output_tokens),
+            cached_tokens: usage.cache_read_input_tokens,
+            cache_creation_tokens: usage.cache_creation_input_tokens,
+            reasoning_tokens: None,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct AnthropicCountTokensResponse {
+    input_tokens: u32,
+}
+
 #[derive(Debug, Deserialize)]
 struct AnthropicErrorResponse {
     error: AnthropicError,
@@ -705,14 +1745,23 @@ struct AnthropicError {
 impl From<AnthropicResponse> for Response {
     fn from(resp: AnthropicResponse) -> Self {
         let mut parts = Vec::new();
+        // Inputs of server tool calls, by call id, until their results arrive.
+        let mut server_inputs: HashMap<String, Value> = HashMap::new();
 
         for content in resp.content {
             match content {
-                AnthropicContentBlock::Text { text, .. } => {
+                AnthropicContentBlock::Text {
+                    text, citations, ..
+                } => {
+                    let length = text.chars().count();
                     parts.push(Part::Text {
                         content: text,
                         finished: true,
                     });
+                    parts.extend(citations.into_iter().map(|citation| Part::Citation {
+                        citation: citation.citation(length),
+                        finished: true,
+                    }));
                 }
                 AnthropicContentBlock::ToolUse {
                     id, name, input, ..
@@ -722,6 +1771,7 @@ impl From<AnthropicResponse> for Response {
                         name,
                         arguments: input,
                         signature: None,
+                        raw_arguments: None,
                         finished: true,
                     });
                 }
@@ -736,7 +1786,42 @@ impl From<AnthropicResponse> for Response {
                         finished: true,
                     });
                 }
-                AnthropicContentBlock::RedactedThinking { .. } => {}
+                AnthropicContentBlock::RedactedThinking { data } => {
+                    parts.push(Part::Reasoning {
+                        content: String::new(),
+                        summary: None,
+                        signature: Some(redacted_signature(&data)),
+                        finished: true,
+                    });
+                }
+                AnthropicContentBlock::ServerToolUse { id, input, .. } => {
+                    server_inputs.insert(id, input);
+                }
+                AnthropicContentBlock::WebSearchToolResult {
+                    tool_use_id,
+                    content,
+                } => {
+                    parts.push(Part::HostedToolCall {
+                        call: HostedToolCall::WebSearch {
+                            query: server_inputs
+                                .remove(&tool_use_id)
+                                .and_then(|input| input["query"].as_str().map(str::to_string)),
+                            sources: web_search_sources(&content),
+                        },
+                        id: Some(tool_use_id),
+                        finished: true,
+                    });
+                }
+                AnthropicContentBlock::CodeExecutionToolResult {
+                    tool_use_id,
+                    content,
+                } => {
+                    parts.push(Part::HostedToolCall {
+                        call: code_execution_call(server_inputs.remove(&tool_use_id), &content),
+                        id: Some(tool_use_id),
+                        finished: true,
+                    });
+                }
                 _ => {}
             }
         }
@@ -751,11 +1836,11 @@ impl From<AnthropicResponse> for Response {
 
         Response {
             data: vec![Message::Assistant(parts)],
-            usage: Usage {
-                prompt_tokens: Some(resp.usage.input_tokens),
-                completion_tokens: Some(resp.usage.output_tokens),
-            },
+            usage: resp.usage.into(),
             finish: finish_reason,
+            alternatives: Vec::new(),
+            model: Some(resp.model),
+            iterations: Vec::new(),
         }
     }
 }
@@ -801,6 +1886,8 @@ enum AnthropicDelta {
     Thinking { thinking: String },
     #[serde(rename = "signature_delta")]
     Signature { signature: String },
+    #[serde(rename = "citations_delta")]
+    Citations { citation: AnthropicCitation },
 }
 
 #[derive(Debug, Deserialize)]
@@ -809,3 +1896,515 @@ struct AnthropicMessageDelta {
     stop_reason: Option<String>,
     stop_sequence: Option<String>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rmcp::model::Tool;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_cache_breakpoints() {
+        let mut options = ModelOptions::<AnthropicModel>::new("claude-sonnet-4-5");
+        options.system = Some("Long instructions".to_string());
+        options.provider.cache_system_prompt = Some(true);
+        options.provider.cache_tools = Some(true);
+        options.provider.cache_conversation = Some(true);
+
+        let tools = ["b", "a"]
+            .into_iter()
+            .map(|name| Tool::new(name, "", Arc::new(serde_json::Map::new())))
+            .collect();
+        let messages = vec![Message::User(vec![
+            Part::Text {
+                content: "first".to_string(),
+                finished: true,
+            },
+            Part::Text {
+                content: "second".to_string(),
+                finished: true,
+            },
+        ])];
+        let request =
+            AnthropicRequest::new(messages, &options, options.model.clone(), tools, false);
+        let json = serde_json::to_value(request).unwrap();
+
+        let ephemeral = json!({ "type": "ephemeral" });
+        assert_eq!(json["system"][0]["cache_control"], ephemeral);
+        assert!(json["tools"][0].get("cache_control").is_none());
+        assert_eq!(json["tools"][1]["cache_control"], ephemeral);
+        assert!(json["messages"][0]["content"][0]
+            .get("cache_control")
+            .is_none());
+        assert_eq!(
+            json["messages"][0]["content"][1]["cache_control"],
+            ephemeral
+        );
+    }
+
+    #[test]
+    fn test_file_references() {
+        let client = AnthropicClient::new(
+            "key".to_string(),
+            "https://api.anthropic.com/v1".to_string(),
+            ModelOptions::new("claude-sonnet-4-5"),
+            TransportOptions::default(),
+        );
+        let file = FileRef {
+            id: "file_011".to_string(),
+            uri: "file_011".to_string(),
+            mime_type: "application/pdf".to_string(),
+            name: Some("report.pdf".to_string()),
+            size_bytes: Some(1024),
+        };
+        let messages = vec![Message::User(vec![file.to_part()])];
+
+        let req = client
+            .build_request(messages.clone(), vec![], false)
+            .unwrap()
+            .build()
+            .unwrap();
+        assert_eq!(req.headers()["anthropic-beta"], "files-api-2025-04-14");
+        let json: Value = serde_json::from_slice(req.body().unwrap().as_bytes().unwrap()).unwrap();
+        assert_eq!(
+            json["messages"][0]["content"][1]["source"],
+            json!({ "type": "file", "file_id": "file_011" })
+        );
+    }
+
+    #[test]
+    fn test_tool_choice() {
+        let schema = json!({ "type": "object" }).as_object().unwrap().clone();
+        let tools = vec![rmcp::model::Tool::new("extract", "Extract fields", schema)];
+        let mut options = ModelOptions::<AnthropicModel>::new("claude-sonnet-4-5");
+        options.tool_choice = Some(ToolChoice::Required);
+
+        let request = AnthropicRequest::new(
+            vec![],
+            &options,
+            options.model.clone(),
+            tools.clone(),
+            false,
+        );
+        assert_eq!(
+            serde_json::to_value(request).unwrap()["tool_choice"],
+            json!({ "type": "any" })
+        );
+
+        // The provider-specific choice takes precedence.
+        options.provider.tool_choice = Some(AnthropicToolChoice::Auto {
+            disable_parallel_tool_use: Some(true),
+        });
+        let request = AnthropicRequest::new(vec![], &options, options.model.clone(), tools, false);
+        assert_eq!(
+            serde_json::to_value(request).unwrap()["tool_choice"],
+            json!({ "type": "auto", "disable_parallel_tool_use": true })
+        );
+    }
+
+    #[test]
+    fn test_betas() {
+        let client = AnthropicClient::new(
+            "key".to_string(),
+            "https://api.anthropic.com/v1".to_string(),
+            ModelOptions::new("claude-sonnet-4-5"),
+            TransportOptions::default(),
+        );
+        let headers = |client: &AnthropicClient| {
+            client
+                .post(&client.model_options.provider, "http://localhost")
+                .map(|req| req.build().unwrap().headers().clone())
+        };
+        assert!(headers(&client).unwrap().get("anthropic-beta").is_none());
+
+        let mut client = client;
+        client.model_options.provider.cache_ttl = Some(AnthropicCacheTtl::OneHour);
+        assert!(matches!(headers(&client), Err(ClientError::Config(_))));
+
+        client.model_options.provider = client
+            .model_options
+            .provider
+            .with_beta(AnthropicBeta::ExtendedCacheTtl)
+            .with_beta(AnthropicBeta::Custom("new-beta-2025-10-01".to_string()))
+            .with_beta(AnthropicBeta::ExtendedCacheTtl);
+        assert_eq!(
+            headers(&client).unwrap()["anthropic-beta"],
+            "extended-cache-ttl-2025-04-11,new-beta-2025-10-01"
+        );
+
+        let betas: Vec<AnthropicBeta> =
+            serde_json::from_value(json!(["context-1m-2025-08-07", "other"])).unwrap();
+        assert_eq!(
+            betas,
+            vec![
+                AnthropicBeta::Context1m,
+                AnthropicBeta::Custom("other".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_code_execution() {
+        let mut options = ModelOptions::<AnthropicModel>::new("claude-sonnet-4-5");
+        options.built_in_tools = Some(vec![BuiltInTool::CodeExecution]);
+        let client = AnthropicClient::new(
+            "key".to_string(),
+            "https://api.anthropic.com/v1".to_string(),
+            options,
+            TransportOptions::default(),
+        );
+        let request = client
+            .build_request(vec![], vec![], false)
+            .unwrap()
+            .build()
+            .unwrap();
+        assert_eq!(
+            request.headers()["anthropic-beta"],
+            "code-execution-2025-05-22"
+        );
+        assert_eq!(
+            serde_json::from_slice::<Value>(request.body().unwrap().as_bytes().unwrap()).unwrap()
+                ["tools"],
+            json!([{ "type": "code_execution_20250522", "name": "code_execution" }])
+        );
+
+        let response: AnthropicResponse = serde_json::from_value(json!({
+            "id": "msg_01",
+            "type": "message",
+            "role": "assistant",
+            "model": "claude-sonnet-4-5",
+            "content": [
+                { "type": "server_tool_use", "id": "srvtoolu_01", "name": "code_execution", "input": { "code": "print(6 * 7)" } },
+                {
+                    "type": "code_execution_tool_result",
+                    "tool_use_id": "srvtoolu_01",
+                    "content": { "type": "code_execution_result", "stdout": "42\n", "stderr": "", "return_code": 0, "content": [] }
+                },
+                { "type": "text", "text": "The answer is 42." }
+            ],
+            "stop_reason": "end_turn",
+            "usage": { "input_tokens": 10, "output_tokens": 20 }
+        }))
+        .unwrap();
+        let response = Response::from(response);
+        assert_eq!(
+            response.data[0].parts()[0],
+            Part::HostedToolCall {
+                id: Some("srvtoolu_01".to_string()),
+                call: HostedToolCall::CodeInterpreter {
+                    code: Some("print(6 * 7)".to_string()),
+                    container_id: None,
+                    outputs: vec![CodeOutput::Logs {
+                        logs: "42\n".to_string()
+                    }],
+                },
+                finished: true,
+            }
+        );
+    }
+
+    #[test]
+    fn test_computer_use() {
+        for input in [
+            json!({ "action": "screenshot" }),
+            json!({ "action": "right_click", "coordinate": [10, 20] }),
+            json!({ "action": "left_click_drag", "start_coordinate": [1, 2], "coordinate": [3, 4] }),
+            json!({ "action": "scroll", "coordinate": [5, 6], "scroll_direction": "up", "scroll_amount": 3 }),
+            json!({ "action": "key", "text": "ctrl+s" }),
+        ] {
+            let action = computer_action(&input).unwrap();
+            assert_eq!(computer_action_input(&action), input);
+        }
+        assert!(
+            computer_action(&json!({ "action": "triple_click", "coordinate": [0, 0] })).is_none()
+        );
+
+        let mut options = ModelOptions::<AnthropicModel>::new("claude-sonnet-4-5");
+        options.provider.computer_use = Some(ComputerDisplay::new(1024, 768));
+        let messages = vec![
+            Message::Assistant(vec![Part::ComputerCall {
+                id: Some("toolu_1".to_string()),
+                action: ComputerAction::Type {
+                    text: "hello".to_string(),
+                },
+                safety_checks: vec![],
+                signature: None,
+                finished: true,
+            }]),
+            Message::User(vec![Part::ComputerResult {
+                id: Some("toolu_1".to_string()),
+                screenshot: "aW1n".to_string(),
+                mime_type: "image/png".to_string(),
+                finished: true,
+            }]),
+        ];
+        let request =
+            AnthropicRequest::new(messages, &options, options.model.clone(), vec![], false);
+        let json = serde_json::to_value(request).unwrap();
+
+        assert_eq!(
+            json["tools"],
+            json!([{
+                "type": "computer_20250124",
+                "name": "computer",
+                "display_width_px": 1024,
+                "display_height_px": 768
+            }])
+        );
+        assert_eq!(json["messages"][0]["content"][0]["name"], "computer");
+        assert_eq!(
+            json["messages"][0]["content"][0]["input"],
+            json!({ "action": "type", "text": "hello" })
+        );
+        let result = &json["messages"][1]["content"][0];
+        assert_eq!(result["type"], "tool_result");
+        assert_eq!(result["tool_use_id"], "toolu_1");
+        assert_eq!(result["content"][0]["type"], "image");
+        assert_eq!(result["content"][0]["source"]["data"], "aW1n");
+
+        assert!(matches!(
+            options.provider.check_betas(),
+            Err(ClientError::Config(_))
+        ));
+        options.provider = options.provider.with_beta(AnthropicBeta::ComputerUse);
+        assert!(options.provider.check_betas().is_ok());
+    }
+
+    #[test]
+    fn test_web_search() {
+        let mut options = ModelOptions::<AnthropicModel>::new("claude-sonnet-4-5");
+        options.built_in_tools = Some(vec![BuiltInTool::WebSearch]);
+        let request = AnthropicRequest::new(vec![], &options, options.model.clone(), vec![], false);
+        assert_eq!(
+            serde_json::to_value(request).unwrap()["tools"],
+            json!([{ "type": "web_search_20250305", "name": "web_search" }])
+        );
+
+        let response: AnthropicResponse = serde_json::from_value(json!({
+            "id": "msg_01",
+            "type": "message",
+            "role": "assistant",
+            "model": "claude-sonnet-4-5",
+            "content": [
+                { "type": "server_tool_use", "id": "srvtoolu_01", "name": "web_search", "input": { "query": "rust release" } },
+                {
+                    "type": "web_search_tool_result",
+                    "tool_use_id": "srvtoolu_01",
+                    "content": [
+                        { "type": "web_search_result", "url": "https://blog.rust-lang.org", "title": "Rust Blog", "encrypted_content": "Eq" }
+                    ]
+                },
+                {
+                    "type": "text",
+                    "text": "Rust 1.90 is out.",
+                    "citations": [{
+                        "type": "web_search_result_location",
+                        "url": "https://blog.rust-lang.org",
+                        "title": "Rust Blog",
+                        "encrypted_index": "Eo",
+                        "cited_text": "Rust 1.90.0 is released"
+                    }]
+                }
+            ],
+            "stop_reason": "end_turn",
+            "usage": { "input_tokens": 10, "output_tokens": 20 }
+        }))
+        .unwrap();
+        let response = Response::from(response);
+        assert_eq!(
+            response.data[0].parts()[0],
+            Part::HostedToolCall {
+                id: Some("srvtoolu_01".to_string()),
+                call: HostedToolCall::WebSearch {
+                    query: Some("rust release".to_string()),
+                    sources: vec![SearchSource {
+                        url: "https://blog.rust-lang.org".to_string(),
+                        title: Some("Rust Blog".to_string()),
+                    }],
+                },
+                finished: true,
+            }
+        );
+        assert_eq!(
+            response.data[0].parts()[2],
+            Part::Citation {
+                citation: Citation {
+                    url: Some("https://blog.rust-lang.org".to_string()),
+                    title: Some("Rust Blog".to_string()),
+                    start_index: Some(0),
+                    end_index: Some(17),
+                    ..Default::default()
+                },
+                finished: true,
+            }
+        );
+    }
+
+    #[test]
+    fn test_thinking_round_trip() {
+        let response: AnthropicResponse = serde_json::from_value(json!({
+            "id": "msg_01",
+            "type": "message",
+            "role": "assistant",
+            "model": "claude-sonnet-4-5",
+            "content": [
+                { "type": "thinking", "thinking": "Check the weather.", "signature": "EqQB" },
+                { "type": "redacted_thinking", "data": "EmwKAhgB" },
+                { "type": "tool_use", "id": "toolu_01", "name": "get_weather", "input": { "city": "Paris" } }
+            ],
+            "stop_reason": "tool_use",
+            "usage": { "input_tokens": 10, "output_tokens": 20 }
+        }))
+        .unwrap();
+        let response = Response::from(response);
+        assert!(matches!(
+            &response.data[0].parts()[..2],
+            [
+                Part::Reasoning { signature: Some(a), .. },
+                Part::Reasoning { signature: Some(b), .. },
+            ] if a == "EqQB" && b == "redacted:EmwKAhgB"
+        ));
+
+        let mut messages = response.data;
+        messages.push(Message::Assistant(vec![Part::Reasoning {
+            content: "Unsigned reasoning from another provider.".to_string(),
+            summary: None,
+            signature: None,
+            finished: true,
+        }]));
+        let options = ModelOptions::<AnthropicModel>::new("claude-sonnet-4-5");
+        let request =
+            AnthropicRequest::new(messages, &options, options.model.clone(), vec![], false);
+        let json = serde_json::to_value(request).unwrap();
+        assert_eq!(json["messages"].as_array().unwrap().len(), 1);
+        assert_eq!(
+            json["messages"][0]["content"].as_array().unwrap()[..2],
+            [
+                json!({ "type": "thinking", "thinking": "Check the weather.", "signature": "EqQB" }),
+                json!({ "type": "redacted_thinking", "data": "EmwKAhgB" }),
+            ]
+        );
+        assert_eq!(json["messages"][0]["content"][2]["type"], "tool_use");
+    }
+
+    #[test]
+    fn test_hidden_thinking_round_trip() {
+        let mut response = Response {
+            data: vec![Message::Assistant(vec![Part::Reasoning {
+                content: "Check the weather.".to_string(),
+                summary: None,
+                signature: Some("EqQB".to_string()),
+                finished: true,
+            }])],
+            usage: Usage::default(),
+            finish: FinishReason::Stop,
+            model: None,
+            alternatives: Vec::new(),
+            iterations: Vec::new(),
+        };
+        hide_reasoning(&mut response, Some(ReasoningVisibility::Hidden));
+        assert!(matches!(
+            &response.data[0].parts()[0],
+            Part::Reasoning { content, signature: Some(signature), .. }
+                if content.is_empty() && signature.starts_with(HIDDEN_THINKING)
+        ));
+
+        let options = ModelOptions::<AnthropicModel>::new("claude-sonnet-4-5");
+        let request = AnthropicRequest::new(
+            response.data,
+            &options,
+            options.model.clone(),
+            vec![],
+            false,
+        );
+        let json = serde_json::to_value(request).unwrap();
+        assert_eq!(
+            json["messages"][0]["content"][0],
+            json!({ "type": "thinking", "thinking": "Check the weather.", "signature": "EqQB" })
+        );
+    }
+
+    #[test]
+    fn test_usage_includes_cache_tokens() {
+        let usage: Usage = AnthropicUsage {
+            input_tokens: 10,
+            output_tokens: 5,
+            cache_creation_input_tokens: Some(100),
+            cache_read_input_tokens: Some(1000),
+        }
+        .into();
+
+        assert_eq!(usage.prompt_tokens, Some(1110));
+        assert_eq!(usage.cached_tokens, Some(1000));
+        assert_eq!(usage.cache_creation_tokens, Some(100));
+    }
+
+    #[tokio::test]
+    async fn test_streamed_deltas_hide_reasoning() {
+        use crate::testing::server::{TestResponse, TestServer};
+        use futures::TryStreamExt;
+
+        let events = [
+            json!({ "type": "message_start", "message": {
+                "id": "msg_1", "type": "message", "role": "assistant", "model": "claude-sonnet-4-5",
+                "content": [], "stop_reason": null, "usage": { "input_tokens": 5, "output_tokens": 1 } } }),
+            json!({ "type": "content_block_start", "index": 0,
+                "content_block": { "type": "thinking", "thinking": "", "signature": "" } }),
+            json!({ "type": "content_block_delta", "index": 0,
+                "delta": { "type": "thinking_delta", "thinking": "Let me think." } }),
+            json!({ "type": "content_block_delta", "index": 0,
+                "delta": { "type": "signature_delta", "signature": "sig" } }),
+            json!({ "type": "content_block_stop", "index": 0 }),
+            json!({ "type": "content_block_start", "index": 1,
+                "content_block": { "type": "text", "text": "" } }),
+            json!({ "type": "content_block_delta", "index": 1,
+                "delta": { "type": "text_delta", "text": "Done." } }),
+            json!({ "type": "content_block_stop", "index": 1 }),
+            json!({ "type": "message_stop" }),
+        ];
+        let body: String = events
+            .iter()
+            .map(|event| format!("data: {}\n\n", event))
+            .collect();
+        let server = TestServer::start(move |_, _| {
+            TestResponse::new(200, body.clone()).with_header("content-type", "text/event-stream")
+        })
+        .await;
+        let mut options = ModelOptions::new("claude-sonnet-4-5");
+        options.reasoning_visibility = Some(ReasoningVisibility::Hidden);
+        let client = AnthropicClient::new(
+            "key".to_string(),
+            server.url().to_string(),
+            options,
+            TransportOptions::default(),
+        );
+
+        let deltas: Vec<StreamDelta> = client
+            .request_stream_deltas(vec![], vec![])
+            .await
+            .unwrap()
+            .try_collect()
+            .await
+            .unwrap();
+
+        let reasoning: Vec<_> = deltas
+            .iter()
+            .filter_map(|delta| match delta {
+                StreamDelta::ReasoningDelta {
+                    text, signature, ..
+                } => Some((text.as_str(), signature.as_deref())),
+                _ => None,
+            })
+            .collect();
+        // The thinking is dropped, but kept in the signature to send it back.
+        let sealed = format!("hidden:{}:sig", BASE64_STANDARD.encode("Let me think."));
+        assert_eq!(
+            reasoning,
+            vec![("", Some("")), ("", None), ("", Some(sealed.as_str()))]
+        );
+        assert!(deltas.contains(&StreamDelta::TextDelta {
+            index: 1,
+            text: "Done.".to_string()
+        }));
+    }
+}
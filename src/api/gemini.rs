@@ -1,18 +1,36 @@
 //! Google Gemini API client implementation.
 
 use async_trait::async_trait;
+use base64::prelude::*;
+use chrono::{DateTime, Utc};
 use futures::{Stream, StreamExt};
 use reqwest::header::{HeaderMap, HeaderValue, CONTENT_TYPE};
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
+use serde_json::{json, Value};
 use serde_with::skip_serializing_none;
+use std::collections::HashMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
 use std::pin::Pin;
-
-use crate::client::{Client, ClientError, StreamingClient};
-use crate::http::{add_extra_headers, build_http_client, RequestBuilderExt, ResponseExt};
-use crate::model::{FinishReason, MediaType, Message, Part, Response, Usage};
-use crate::options::{ModelOptions, TransportOptions};
-use crate::sse::SSEResponseExt;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::client::{Capabilities, Client, ClientError, ModelInfo, StreamingClient};
+use crate::embeddings::EmbeddingsClient;
+use crate::files::{FileRef, FileUpload};
+use crate::hosted::{BuiltInTool, Citation, CodeOutput, HostedToolCall, SearchSource};
+use crate::http::{
+    add_extra_headers, build_http_client, send_with_retry, RequestBuilderExt, ResponseExt,
+};
+use crate::images::{ImageGenerationClient, ImageOptions};
+use crate::markdown::{strip_delta_markdown, strip_response_markdown, strip_stream_markdown};
+use crate::model::{correct_media_types, FinishReason, MediaType, Message, Part, Response, Usage};
+use crate::options::{ModelOptions, ToolChoice, TransportOptions};
+use crate::sse::{error_event, SSEResponseExt};
+use crate::stream::{
+    accumulate, with_delta_reasoning_visibility, with_reasoning_visibility, StreamDelta,
+};
+use crate::tokenize::TokenCounter;
+use crate::tools::canonical_tools;
 
 /// Gemini model options.
 #[skip_serializing_none]
@@ -25,6 +43,56 @@ pub struct GeminiModel {
     pub thinking_budget: Option<u32>,
     pub thinking_level: Option<GeminiThinkingLevel>,
     pub include_thoughts: Option<bool>,
+    /// Kinds of output to generate, e.g. `["TEXT", "IMAGE"]` for image models.
+    pub response_modalities: Option<Vec<String>>,
+    /// Media parts whose base64 data is larger than this many bytes are uploaded
+    /// through the Files API and referenced by URI instead of being inlined.
+    /// Defaults to [`DEFAULT_UPLOAD_THRESHOLD`].
+    pub upload_threshold: Option<usize>,
+}
+
+/// Default size above which media is uploaded instead of inlined. Gemini rejects
+/// requests larger than 20 MB.
+pub const DEFAULT_UPLOAD_THRESHOLD: usize = 15 * 1024 * 1024;
+
+/// Interval between state checks while an uploaded file is being processed.
+const FILE_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Uploaded files expiring sooner than this are uploaded again rather than
+/// referenced, so they do not expire while a request is in flight.
+const UPLOAD_EXPIRY_MARGIN: Duration = Duration::from_secs(60 * 60);
+
+/// Maximum number of state checks before giving up on an uploaded file.
+const FILE_POLL_ATTEMPTS: usize = 150;
+
+/// A file stored with the Gemini Files API.
+#[skip_serializing_none]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GeminiFile {
+    /// Resource name, e.g. `files/abc-123`.
+    pub name: String,
+    pub display_name: Option<String>,
+    pub mime_type: String,
+    pub size_bytes: Option<String>,
+    /// URI used to reference the file in requests.
+    pub uri: String,
+    /// Processing state: `PROCESSING`, `ACTIVE` or `FAILED`.
+    pub state: Option<String>,
+    /// Time after which the file is deleted, typically 48 hours after upload.
+    pub expiration_time: Option<String>,
+}
+
+impl From<GeminiFile> for FileRef {
+    fn from(file: GeminiFile) -> Self {
+        Self {
+            id: file.name,
+            uri: file.uri,
+            mime_type: file.mime_type,
+            name: file.display_name,
+            size_bytes: file.size_bytes.and_then(|size| size.parse().ok()),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -47,6 +115,29 @@ pub struct GeminiClient {
     base_url: String,
     model_options: ModelOptions<GeminiModel>,
     transport_options: TransportOptions,
+    /// Media uploaded by this client, keyed by a hash of their data.
+    uploads: Arc<Mutex<HashMap<u64, Upload>>>,
+}
+
+/// A media payload uploaded by a [`GeminiClient`].
+#[derive(Debug, Clone)]
+struct Upload {
+    uri: String,
+    /// When Gemini deletes the file, if it said so.
+    expires: Option<DateTime<Utc>>,
+}
+
+impl From<&GeminiFile> for Upload {
+    fn from(file: &GeminiFile) -> Self {
+        Self {
+            uri: file.uri.clone(),
+            expires: file
+                .expiration_time
+                .as_deref()
+                .and_then(|time| DateTime::parse_from_rfc3339(time).ok())
+                .map(|time| time.with_timezone(&Utc)),
+        }
+    }
 }
 
 impl GeminiClient {
@@ -60,18 +151,182 @@ impl GeminiClient {
             api_key,
             base_url,
             model_options,
-            transport_options,
+            transport_options: transport_options.for_provider("gemini"),
+            uploads: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Upload a file with the Files API using the resumable upload protocol.
+    ///
+    /// Videos are processed after the upload; the file can only be used once
+    /// its state is `ACTIVE`.
+    async fn upload(
+        &self,
+        data: Vec<u8>,
+        mime_type: &str,
+        display_name: Option<&str>,
+    ) -> Result<GeminiFile, ClientError> {
+        let url = format!(
+            "{}/files?key={}",
+            upload_base_url(&self.base_url),
+            self.api_key
+        );
+        let req = self
+            .post(&url)?
+            .header("X-Goog-Upload-Protocol", "resumable")
+            .header("X-Goog-Upload-Command", "start")
+            .header(
+                "X-Goog-Upload-Header-Content-Length",
+                data.len().to_string(),
+            )
+            .header("X-Goog-Upload-Header-Content-Type", mime_type)
+            .json_logged(
+                &json!({ "file": { "display_name": display_name } }),
+                &self.transport_options,
+            );
+        let response = send_with_retry(req, &self.transport_options).await?;
+        let status = response.status();
+
+        if !status.is_success() {
+            let body = response
+                .text_logged(&self.transport_options)
+                .await
+                .unwrap_or_default();
+            return Err(Self::handle_error_response(status, &body));
         }
+
+        let upload_url = response
+            .headers()
+            .get("x-goog-upload-url")
+            .and_then(|v| v.to_str().ok())
+            .ok_or_else(|| {
+                ClientError::ProviderError("Gemini did not return an upload URL".to_string())
+            })?
+            .to_string();
+
+        let http_client = build_http_client(&self.transport_options)?;
+        let req = add_extra_headers(http_client.post(&upload_url), &self.transport_options)
+            .header("X-Goog-Upload-Offset", "0")
+            .header("X-Goog-Upload-Command", "upload, finalize")
+            .body(data);
+        let uploaded: GeminiFileResponse = self.send_json(req).await?;
+        Ok(uploaded.file)
+    }
+
+    /// Get the metadata of an uploaded file by its resource name.
+    pub async fn get_file(&self, name: &str) -> Result<GeminiFile, ClientError> {
+        let url = format!("{}/{}?key={}", self.base_url, name, self.api_key);
+        let http_client = build_http_client(&self.transport_options)?;
+        let req = add_extra_headers(http_client.get(&url), &self.transport_options);
+        self.send_json(req).await
+    }
+
+    /// Wait until an uploaded file has been processed.
+    async fn wait_until_active(&self, mut file: GeminiFile) -> Result<GeminiFile, ClientError> {
+        for _ in 0..FILE_POLL_ATTEMPTS {
+            match file.state.as_deref() {
+                Some("PROCESSING") => {
+                    tokio::time::sleep(FILE_POLL_INTERVAL).await;
+                    file = self.get_file(&file.name).await?;
+                }
+                Some("FAILED") => {
+                    return Err(ClientError::ProviderError(format!(
+                        "Gemini failed to process uploaded file {}",
+                        file.name
+                    )))
+                }
+                _ => return Ok(file),
+            }
+        }
+        Err(ClientError::ProviderError(format!(
+            "Uploaded file {} is still processing",
+            file.name
+        )))
+    }
+
+    /// URI of an earlier upload of a payload, unless the file has expired or
+    /// is about to.
+    fn cached_upload(&self, key: u64) -> Option<String> {
+        let mut uploads = self.uploads.lock().unwrap();
+        let upload = uploads.get(&key)?;
+        let margin = chrono::Duration::from_std(UPLOAD_EXPIRY_MARGIN).unwrap_or_default();
+        match upload.expires {
+            Some(expires) if expires - margin <= Utc::now() => {
+                uploads.remove(&key);
+                None
+            }
+            _ => Some(upload.uri.clone()),
+        }
+    }
+
+    /// Replace media parts above the upload threshold with references to
+    /// uploaded files. Each payload is only uploaded once per client, and
+    /// uploaded again when Gemini is about to delete the file.
+    async fn upload_large_media(
+        &self,
+        messages: Vec<Message>,
+    ) -> Result<Vec<Message>, ClientError> {
+        let mut messages = correct_media_types(messages);
+        let threshold = self
+            .model_options
+            .provider
+            .upload_threshold
+            .unwrap_or(DEFAULT_UPLOAD_THRESHOLD);
+
+        for message in &mut messages {
+            for part in message.parts_mut() {
+                let Part::Media {
+                    data,
+                    mime_type,
+                    uri,
+                    ..
+                } = part
+                else {
+                    continue;
+                };
+                if data.len() <= threshold {
+                    continue;
+                }
+
+                let mut hasher = DefaultHasher::new();
+                data.hash(&mut hasher);
+                let key = hasher.finish();
+
+                let file_uri = match self.cached_upload(key) {
+                    Some(file_uri) => file_uri,
+                    None => {
+                        let bytes = BASE64_STANDARD.decode(data.as_bytes()).map_err(|e| {
+                            ClientError::Config(format!("Invalid base64 media data: {}", e))
+                        })?;
+                        let file = self.upload(bytes, mime_type, uri.as_deref()).await?;
+                        let file = self.wait_until_active(file).await?;
+                        self.uploads
+                            .lock()
+                            .unwrap()
+                            .insert(key, Upload::from(&file));
+                        file.uri
+                    }
+                };
+
+                data.clear();
+                *uri = Some(file_uri);
+            }
+        }
+
+        Ok(messages)
     }
 
     fn handle_error_response(status: reqwest::StatusCode, body: &str) -> ClientError {
-        if let Ok(error_resp) = serde_json::from_str::<GeminiErrorResponse>(body) {
-            ClientError::ProviderError(format!(
+        let message = match serde_json::from_str::<GeminiErrorResponse>(body) {
+            Ok(error_resp) => format!(
                 "Gemini error ({}): {}",
                 error_resp.error.code, error_resp.error.message
-            ))
-        } else {
-            ClientError::ProviderError(format!("HTTP {}: {}", status, body))
+            ),
+            Err(_) => body.to_string(),
+        };
+        ClientError::Status {
+            status: status.as_u16(),
+            message,
         }
     }
 
@@ -95,18 +350,79 @@ impl GeminiClient {
 
         let request_body = GeminiRequest::new(messages, &self.model_options, tools)?;
 
+        Ok(self
+            .post(&url)?
+            .json_logged(&request_body, &self.transport_options))
+    }
+
+    async fn send_stream(
+        &self,
+        messages: Vec<Message>,
+        tools: Vec<rmcp::model::Tool>,
+    ) -> Result<reqwest::Response, ClientError> {
+        let messages = self.upload_large_media(messages).await?;
+        let req = self.build_request(messages, tools, true)?;
+        let response = send_with_retry(req, &self.transport_options).await?;
+        let status = response.status();
+
+        if !status.is_success() {
+            let body = response
+                .text_logged(&self.transport_options)
+                .await
+                .unwrap_or_default();
+            return Err(Self::handle_error_response(status, &body));
+        }
+
+        Ok(response)
+    }
+
+    fn post(&self, url: &str) -> Result<reqwest::RequestBuilder, ClientError> {
         let http_client = build_http_client(&self.transport_options)?;
 
         let mut headers = HeaderMap::new();
         headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
 
-        let mut req = http_client.post(&url).headers(headers);
-        req = add_extra_headers(req, &self.transport_options);
+        let req = http_client.post(url).headers(headers);
+        Ok(add_extra_headers(req, &self.transport_options))
+    }
+
+    async fn send_json<T: serde::de::DeserializeOwned>(
+        &self,
+        req: reqwest::RequestBuilder,
+    ) -> Result<T, ClientError> {
+        let response = send_with_retry(req, &self.transport_options).await?;
+        let status = response.status();
+
+        if !status.is_success() {
+            let body = response
+                .text_logged(&self.transport_options)
+                .await
+                .unwrap_or_default();
+            return Err(Self::handle_error_response(status, &body));
+        }
 
-        Ok(req.json_logged(&request_body))
+        response.json_logged(&self.transport_options).await
     }
 }
 
+/// Derive the upload endpoint from the API base URL, e.g.
+/// `https://host/v1beta` becomes `https://host/upload/v1beta`.
+fn upload_base_url(base_url: &str) -> String {
+    let path_start = base_url
+        .find("://")
+        .and_then(|scheme_end| {
+            base_url[scheme_end + 3..]
+                .find('/')
+                .map(|i| scheme_end + 3 + i)
+        })
+        .unwrap_or(base_url.len());
+    format!(
+        "{}/upload{}",
+        &base_url[..path_start],
+        &base_url[path_start..]
+    )
+}
+
 #[async_trait]
 impl Client for GeminiClient {
     type ModelProvider = GeminiModel;
@@ -116,18 +432,29 @@ impl Client for GeminiClient {
         messages: Vec<Message>,
         tools: Vec<rmcp::model::Tool>,
     ) -> Result<Response, ClientError> {
+        let messages = self.upload_large_media(messages).await?;
         let req = self.build_request(messages, tools, false)?;
 
-        let response = req.send().await?;
+        let response = send_with_retry(req, &self.transport_options).await?;
         let status = response.status();
 
         if !status.is_success() {
-            let body = response.text_logged().await.unwrap_or_default();
+            let body = response
+                .text_logged(&self.transport_options)
+                .await
+                .unwrap_or_default();
             return Err(Self::handle_error_response(status, &body));
         }
 
-        let gemini_response: GeminiResponse = response.json_logged().await?;
-        Ok(gemini_response.into())
+        let gemini_response: GeminiResponse = response.json_logged(&self.transport_options).await?;
+        let mut response: Response = gemini_response.into();
+        if let Some(visibility) = self.model_options.reasoning_visibility {
+            response.set_reasoning_visibility(visibility);
+        }
+        if self.model_options.plain_text.unwrap_or(false) {
+            strip_response_markdown(&mut response);
+        }
+        Ok(response)
     }
 
     fn model_options(&self) -> &ModelOptions<Self::ModelProvider> {
@@ -137,6 +464,59 @@ impl Client for GeminiClient {
     fn transport_options(&self) -> &TransportOptions {
         &self.transport_options
     }
+
+    fn provider(&self) -> &'static str {
+        "gemini"
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            supports_tools: true,
+            supports_vision: true,
+            supports_streaming: true,
+            supports_system_prompt: true,
+            max_context_tokens: Some(1_048_576),
+            supports_json_mode: true,
+        }
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>, ClientError> {
+        let http_client = build_http_client(&self.transport_options)?;
+        let mut models = Vec::new();
+        let mut page_token: Option<String> = None;
+
+        loop {
+            let url = format!("{}/models", self.base_url);
+            let mut req = http_client
+                .get(&url)
+                .query(&[("key", self.api_key.as_str()), ("pageSize", "1000")]);
+            if let Some(page_token) = &page_token {
+                req = req.query(&[("pageToken", page_token)]);
+            }
+            let req = add_extra_headers(req, &self.transport_options);
+
+            let page: GeminiModelList = self.send_json(req).await?;
+            models.extend(page.models.into_iter().map(|model| {
+                ModelInfo {
+                    id: model
+                        .name
+                        .strip_prefix("models/")
+                        .unwrap_or(&model.name)
+                        .to_string(),
+                    display_name: model.display_name,
+                    context_window: model.input_token_limit,
+                    max_output_tokens: model.output_token_limit,
+                    input_modalities: Vec::new(),
+                    deprecation_date: None,
+                }
+            }));
+
+            match page.next_page_token {
+                Some(token) if !token.is_empty() => page_token = Some(token),
+                _ => return Ok(models),
+            }
+        }
+    }
 }
 
 #[async_trait]
@@ -147,16 +527,215 @@ impl StreamingClient for GeminiClient {
         tools: Vec<rmcp::model::Tool>,
     ) -> Result<Pin<Box<dyn Stream<Item = Result<Response, ClientError>> + Send>>, ClientError>
     {
-        let req = self.build_request(messages, tools, true)?;
-        let response = req.send().await?;
+        let response = self.send_stream(messages, tools).await?;
+        let stream = GeminiStream::create(response);
+        let stream = with_reasoning_visibility(stream, self.model_options.reasoning_visibility);
+        if self.model_options.plain_text.unwrap_or(false) {
+            return Ok(Box::pin(strip_stream_markdown(stream)));
+        }
+        Ok(Box::pin(stream))
+    }
+
+    async fn request_stream_deltas(
+        &self,
+        messages: Vec<Message>,
+        tools: Vec<rmcp::model::Tool>,
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<StreamDelta, ClientError>> + Send>>, ClientError>
+    {
+        let response = self.send_stream(messages, tools).await?;
+        let deltas = with_delta_reasoning_visibility(
+            GeminiStream::deltas(response),
+            self.model_options.reasoning_visibility,
+            |_, signature| signature,
+        );
+        if self.model_options.plain_text.unwrap_or(false) {
+            return Ok(Box::pin(strip_delta_markdown(deltas)));
+        }
+        Ok(Box::pin(deltas))
+    }
+}
+
+#[async_trait]
+impl EmbeddingsClient for GeminiClient {
+    async fn embed(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>, ClientError> {
+        let model = &self.model_options.model;
+        let url = format!(
+            "{}/models/{}:batchEmbedContents?key={}",
+            self.base_url, model, self.api_key
+        );
+
+        let request_body = GeminiBatchEmbedRequest {
+            requests: texts
+                .into_iter()
+                .map(|text| GeminiEmbedContentRequest {
+                    model: format!("models/{}", model),
+                    content: GeminiContent {
+                        role: "user".to_string(),
+                        parts: vec![GeminiPart::Text {
+                            text,
+                            thought: None,
+                        }],
+                    },
+                })
+                .collect(),
+        };
+
+        let req = self
+            .post(&url)?
+            .json_logged(&request_body, &self.transport_options);
+        let response = send_with_retry(req, &self.transport_options).await?;
+        let status = response.status();
+
+        if !status.is_success() {
+            let body = response
+                .text_logged(&self.transport_options)
+                .await
+                .unwrap_or_default();
+            return Err(Self::handle_error_response(status, &body));
+        }
+
+        let embed_response: GeminiBatchEmbedResponse =
+            response.json_logged(&self.transport_options).await?;
+        Ok(embed_response
+            .embeddings
+            .into_iter()
+            .map(|e| e.values)
+            .collect())
+    }
+
+    fn max_batch_size(&self) -> usize {
+        100
+    }
+}
+
+#[async_trait]
+impl FileUpload for GeminiClient {
+    /// Upload a file, waiting until Gemini has processed it.
+    async fn upload_file(
+        &self,
+        data: Vec<u8>,
+        mime_type: &str,
+        name: Option<&str>,
+    ) -> Result<FileRef, ClientError> {
+        let file = self.upload(data, mime_type, name).await?;
+        Ok(self.wait_until_active(file).await?.into())
+    }
+
+    async fn delete_file(&self, file: &FileRef) -> Result<(), ClientError> {
+        let url = format!("{}/{}?key={}", self.base_url, file.id, self.api_key);
+        let http_client = build_http_client(&self.transport_options)?;
+        let req = add_extra_headers(http_client.delete(&url), &self.transport_options);
+        let response = send_with_retry(req, &self.transport_options).await?;
+        let status = response.status();
+
+        if !status.is_success() {
+            let body = response
+                .text_logged(&self.transport_options)
+                .await
+                .unwrap_or_default();
+            return Err(Self::handle_error_response(status, &body));
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl ImageGenerationClient for GeminiClient {
+    async fn generate_images(
+        &self,
+        prompt: &str,
+        options: ImageOptions,
+    ) -> Result<Vec<Part>, ClientError> {
+        let mut model_options = self.model_options.clone();
+        if let Some(model) = options.model {
+            model_options.model = model;
+        }
+        model_options.provider.response_modalities =
+            Some(vec!["TEXT".to_string(), "IMAGE".to_string()]);
+        let url = format!(
+            "{}/models/{}:generateContent?key={}",
+            self.base_url, model_options.model, self.api_key
+        );
+        let messages = vec![Message::User(vec![Part::Text {
+            content: prompt.to_string(),
+            finished: true,
+        }])];
+        let request_body = GeminiRequest::new(messages, &model_options, vec![])?;
+
+        // Image models generate a single candidate, so each image is a request.
+        let requests = (0..options.count.unwrap_or(1).max(1)).map(|_| async {
+            let req = self
+                .post(&url)?
+                .json_logged(&request_body, &self.transport_options);
+            let response: GeminiResponse = self.send_json(req).await?;
+            Ok::<_, ClientError>(Response::from(response))
+        });
+        let images: Vec<Part> = futures::future::try_join_all(requests)
+            .await?
+            .into_iter()
+            .flat_map(|response| response.data)
+            .flat_map(|message| message.parts().clone())
+            .filter(|part| {
+                matches!(
+                    part,
+                    Part::Media {
+                        media_type: MediaType::Image,
+                        ..
+                    }
+                )
+            })
+            .collect();
+
+        if images.is_empty() {
+            return Err(ClientError::ProviderError(
+                "Model returned no image".to_string(),
+            ));
+        }
+        Ok(images)
+    }
+}
+
+#[async_trait]
+impl TokenCounter for GeminiClient {
+    async fn count_tokens_exact(
+        &self,
+        messages: &[Message],
+        tools: &[rmcp::model::Tool],
+    ) -> Result<Option<usize>, ClientError> {
+        let model = &self.model_options.model;
+        let url = format!(
+            "{}/models/{}:countTokens?key={}",
+            self.base_url, model, self.api_key
+        );
+
+        let request_body = GeminiCountTokensRequest {
+            generate_content_request: GeminiGenerateContentRequest {
+                model: format!("models/{}", model),
+                request: GeminiRequest::new(
+                    messages.to_vec(),
+                    &self.model_options,
+                    tools.to_vec(),
+                )?,
+            },
+        };
+
+        let req = self
+            .post(&url)?
+            .json_logged(&request_body, &self.transport_options);
+        let response = send_with_retry(req, &self.transport_options).await?;
         let status = response.status();
 
         if !status.is_success() {
-            let body = response.text_logged().await.unwrap_or_default();
+            let body = response
+                .text_logged(&self.transport_options)
+                .await
+                .unwrap_or_default();
             return Err(Self::handle_error_response(status, &body));
         }
 
-        Ok(Box::pin(GeminiStream::create(response)))
+        let count: GeminiCountTokensResponse =
+            response.json_logged(&self.transport_options).await?;
+        Ok(Some(count.total_tokens as usize))
     }
 }
 
@@ -168,142 +747,192 @@ impl GeminiStream {
     fn create(
         response: reqwest::Response,
     ) -> impl Stream<Item = Result<Response, ClientError>> + Send {
+        accumulate(Self::deltas(response))
+    }
+
+    fn deltas(
+        response: reqwest::Response,
+    ) -> impl Stream<Item = Result<StreamDelta, ClientError>> + Send {
         let sse_stream = response.sse();
 
         Box::pin(async_stream::try_stream! {
             let mut stream = Box::pin(sse_stream);
-            let mut current_response = Response {
-                data: vec![Message::Assistant(vec![])],
-                usage: Usage::default(),
-                finish: FinishReason::Unfinished,
-            };
 
             #[derive(PartialEq)]
-            enum PartType { Text, Reasoning, FunctionCall }
-            let mut last_part_type: Option<PartType> = None;
+            enum PartType { Text, Reasoning, FunctionCall, Media, HostedToolCall }
+            let mut last_part: Option<(PartType, usize)> = None;
+            // Part index and code of the last code execution, completed by its result.
+            let mut code_call: Option<(usize, Option<String>)> = None;
+            // Index and text of the last answer part, which grounding offsets refer to.
+            let mut answer: Option<(usize, String)> = None;
+            // Search results are reported with the last chunks and emitted at the end,
+            // followed by the finish reason.
+            let mut grounding: Option<GeminiGroundingMetadata> = None;
+            let mut finish = None;
+            let mut part_count: usize = 0;
+            let mut model_reported = false;
 
             while let Some(event_result) = stream.next().await {
                 let event_str = event_result?;
+                if let Some(error) = error_event(&event_str) {
+                    Err(error)?;
+                }
 
                 let chunk_result: GeminiResponse = serde_json::from_str(&event_str)
                     .map_err(|e| ClientError::ProviderError(format!("JSON parse error: {}", e)))?;
 
+                if let Some(model) = chunk_result.model_version.filter(|_| !model_reported) {
+                    model_reported = true;
+                    yield StreamDelta::Model { model };
+                }
+
                 if let Some(usage_meta) = chunk_result.usage_metadata {
-                    current_response.usage.prompt_tokens = Some(usage_meta.prompt_token_count);
-                    current_response.usage.completion_tokens = Some(usage_meta.candidates_token_count.unwrap_or(0) + usage_meta.thoughts_token_count.unwrap_or(0));
+                    yield StreamDelta::Usage(usage_meta.into());
                 }
 
-                if let Some(candidates) = chunk_result.candidates {
-                    if let Some(candidate) = candidates.first() {
-                        if let Some(content) = &candidate.content {
-                            let parts = current_response.data[0].parts_mut();
-
-                            for part in &content.parts {
-                                match part {
-                                    GeminiPart::Text { text, thought } => {
-                                        let is_thought = thought.unwrap_or(false);
-                                        let current_type = if is_thought { PartType::Reasoning } else { PartType::Text };
-
-                                        if last_part_type
-                                            .as_ref()
-                                            .is_some_and(|last_type| *last_type != current_type)
-                                        {
-                                            if let Some(last_part) = parts.last_mut() {
-                                                match last_part {
-                                                    Part::Text { finished, .. } => *finished = true,
-                                                    Part::Reasoning { finished, .. } => *finished = true,
-                                                    Part::FunctionCall { finished, .. } => {
-                                                        *finished = true
-                                                    }
-                                                    _ => {}
-                                                }
-                                            }
-                                        }
-                                        last_part_type = Some(current_type);
-
-                                        let should_append = if let Some(last_part) = parts.last_mut() {
-                                            matches!(
-                                                (last_part, is_thought),
-                                                (Part::Text { finished: false, .. }, false)
-                                                    | (Part::Reasoning { finished: false, .. }, true)
-                                            )
-                                        } else {
-                                            false
-                                        };
-
-                                        if should_append {
-                                            if let Some(last_part) = parts.last_mut() {
-                                                match last_part {
-                                                    Part::Text { content: t, .. } => t.push_str(text),
-                                                    Part::Reasoning { content: c, .. } => c.push_str(text),
-                                                    _ => {}
-                                                }
-                                            }
-                                        } else if is_thought {
-                                            parts.push(Part::Reasoning {
-                                                content: text.clone(),
-                                                summary: None,
-                                                signature: None,
-                                                finished: false,
-                                            });
-                                        } else {
-                                            parts.push(Part::Text {
-                                                content: text.clone(),
-                                                finished: false,
-                                            });
+                let Some(candidate) = chunk_result.candidates.and_then(|c| c.into_iter().next()) else {
+                    continue;
+                };
+
+                if let Some(content) = candidate.content {
+                    for part in content.parts {
+                        match part {
+                            GeminiPart::Text { text, thought } => {
+                                let is_thought = thought.unwrap_or(false);
+                                let current_type = if is_thought { PartType::Reasoning } else { PartType::Text };
+
+                                let index = match &last_part {
+                                    Some((last_type, index)) if *last_type == current_type => *index,
+                                    _ => {
+                                        if let Some((_, index)) = last_part {
+                                            yield StreamDelta::PartFinished { index };
                                         }
+                                        part_count += 1;
+                                        part_count - 1
+                                    }
+                                };
+                                last_part = Some((current_type, index));
+                                if !is_thought {
+                                    match &mut answer {
+                                        Some((answer_index, answer)) if *answer_index == index => answer.push_str(&text),
+                                        _ => answer = Some((index, text.clone())),
+                                    }
+                                }
+
+                                yield if is_thought {
+                                    StreamDelta::ReasoningDelta { index, text, signature: None }
+                                } else {
+                                    StreamDelta::TextDelta { index, text }
+                                };
+                            },
+                            GeminiPart::FunctionCall { function_call, thought_signature } => {
+                                if let Some((last_type, index)) = &last_part {
+                                    if *last_type != PartType::FunctionCall {
+                                        yield StreamDelta::PartFinished { index: *index };
+                                    }
+                                }
+                                let index = part_count;
+                                part_count += 1;
+                                last_part = Some((PartType::FunctionCall, index));
+
+                                yield StreamDelta::ToolCallDelta {
+                                    index,
+                                    id: None,
+                                    name: Some(function_call.name),
+                                    arguments: function_call.args.to_string(),
+                                    signature: thought_signature,
+                                };
+                            },
+                            GeminiPart::ExecutableCode { executable_code } => {
+                                if let Some((_, index)) = last_part {
+                                    yield StreamDelta::PartFinished { index };
+                                }
+                                let index = part_count;
+                                part_count += 1;
+                                last_part = Some((PartType::HostedToolCall, index));
+                                code_call = Some((index, Some(executable_code.code.clone())));
+
+                                yield StreamDelta::HostedToolCall {
+                                    index,
+                                    id: None,
+                                    call: HostedToolCall::CodeInterpreter {
+                                        code: Some(executable_code.code),
+                                        container_id: None,
+                                        outputs: Vec::new(),
                                     },
-                                    GeminiPart::FunctionCall { function_call, thought_signature } => {
-                                        if last_part_type
-                                            .as_ref()
-                                            .is_some_and(|last_type| *last_type != PartType::FunctionCall)
-                                        {
-                                            if let Some(last_part) = parts.last_mut() {
-                                                match last_part {
-                                                    Part::Text { finished, .. } => *finished = true,
-                                                    Part::Reasoning { finished, .. } => *finished = true,
-                                                    _ => {}
-                                                }
-                                            }
+                                };
+                            },
+                            GeminiPart::CodeExecutionResult { code_execution_result } => {
+                                // The result replaces the call it completes.
+                                let (index, code) = match code_call.take() {
+                                    Some(call) => call,
+                                    None => {
+                                        if let Some((_, index)) = last_part {
+                                            yield StreamDelta::PartFinished { index };
                                         }
-                                        last_part_type = Some(PartType::FunctionCall);
-
-                                        parts.push(Part::FunctionCall {
-                                            id: None,
-                                            name: function_call.name.clone(),
-                                            arguments: function_call.args.clone(),
-                                            signature: thought_signature.clone(),
-                                            finished: false,
-                                        });
+                                        part_count += 1;
+                                        (part_count - 1, None)
+                                    }
+                                };
+                                last_part = Some((PartType::HostedToolCall, index));
+
+                                yield StreamDelta::HostedToolCall {
+                                    index,
+                                    id: None,
+                                    call: HostedToolCall::CodeInterpreter {
+                                        code,
+                                        container_id: None,
+                                        outputs: code_execution_result.outputs(),
                                     },
-                                    _ => {}
+                                };
+                            },
+                            media => {
+                                let Some(Part::Media { media_type, data, mime_type, uri, .. }) = media.media() else {
+                                    continue;
+                                };
+                                if let Some((_, index)) = last_part {
+                                    yield StreamDelta::PartFinished { index };
                                 }
-                            }
-                        }
+                                let index = part_count;
+                                part_count += 1;
+                                last_part = Some((PartType::Media, index));
 
-                        if let Some(finish_reason) = &candidate.finish_reason {
-                            for part in current_response.data[0].parts_mut() {
-                                match part {
-                                    Part::Text { finished, .. } => *finished = true,
-                                    Part::Reasoning { finished, .. } => *finished = true,
-                                    Part::FunctionCall { finished, .. } => *finished = true,
-                                    Part::FunctionResponse { finished, .. } => *finished = true,
-                                    Part::Media { finished, .. } => *finished = true,
-                                }
+                                yield StreamDelta::Media { index, media_type, data, mime_type, uri };
                             }
-
-                            current_response.finish = match finish_reason.as_str() {
-                                "STOP" => FinishReason::Stop,
-                                "MAX_TOKENS" => FinishReason::OutputTokens,
-                                "SAFETY" => FinishReason::ContentFilter,
-                                "RECITATION" => FinishReason::ContentFilter,
-                                _ => FinishReason::Stop,
-                            };
                         }
                     }
                 }
 
-                yield current_response.clone();
+                if candidate.grounding_metadata.is_some() {
+                    grounding = candidate.grounding_metadata;
+                }
+
+                if let Some(finish_reason) = candidate.finish_reason {
+                    finish = Some(match finish_reason.as_str() {
+                        "STOP" => FinishReason::Stop,
+                        "MAX_TOKENS" => FinishReason::OutputTokens,
+                        "SAFETY" => FinishReason::ContentFilter,
+                        "RECITATION" => FinishReason::ContentFilter,
+                        _ => FinishReason::Stop,
+                    });
+                }
+            }
+
+            let answer = answer.map(|(_, answer)| answer).unwrap_or_default();
+            for part in grounding.map(|g| g.parts(&answer)).unwrap_or_default() {
+                if let Some((_, index)) = last_part.take() {
+                    yield StreamDelta::PartFinished { index };
+                }
+                let index = part_count;
+                part_count += 1;
+                match part {
+                    Part::HostedToolCall { id, call, .. } => yield StreamDelta::HostedToolCall { index, id, call },
+                    Part::Citation { citation, .. } => yield StreamDelta::Citation { index, citation },
+                    _ => {}
+                }
+            }
+            if let Some(finish) = finish {
+                yield StreamDelta::Finish(finish);
             }
         })
     }
@@ -319,10 +948,24 @@ struct GeminiRequest {
     tools: Vec<GeminiTool>,
     #[serde(skip_serializing_if = "Option::is_none")]
     system_instruction: Option<GeminiContent>,
+    tool_config: Option<GeminiToolConfig>,
     generation_config: GeminiGenerationConfig,
     safety_settings: Option<Vec<GeminiSafetySetting>>,
 }
 
+#[skip_serializing_none]
+#[derive(Debug, Serialize)]
+struct GeminiToolConfig {
+    function_calling_config: GeminiFunctionCallingConfig,
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Serialize)]
+struct GeminiFunctionCallingConfig {
+    mode: String,
+    allowed_function_names: Option<Vec<String>>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 struct GeminiContent {
     role: String,
@@ -349,6 +992,39 @@ enum GeminiPart {
     InlineData {
         inline_data: GeminiInlineData,
     },
+    FileData {
+        file_data: GeminiFileData,
+    },
+    ExecutableCode {
+        executable_code: GeminiExecutableCode,
+    },
+    CodeExecutionResult {
+        code_execution_result: GeminiCodeExecutionResult,
+    },
+}
+
+/// Code written by the model for the code execution tool.
+#[derive(Debug, Serialize, Deserialize)]
+struct GeminiExecutableCode {
+    language: Option<String>,
+    code: String,
+}
+
+/// Result of running the preceding [`GeminiExecutableCode`].
+#[derive(Debug, Serialize, Deserialize)]
+struct GeminiCodeExecutionResult {
+    outcome: Option<String>,
+    output: Option<String>,
+}
+
+impl GeminiCodeExecutionResult {
+    fn outputs(self) -> Vec<CodeOutput> {
+        self.output
+            .filter(|output| !output.is_empty())
+            .map(|logs| CodeOutput::Logs { logs })
+            .into_iter()
+            .collect()
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -380,13 +1056,70 @@ struct GeminiFunctionResponseBlob {
 
 #[derive(Debug, Serialize, Deserialize)]
 struct GeminiInlineData {
+    #[serde(alias = "mimeType")]
     mime_type: String,
     data: String,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
+struct GeminiFileData {
+    #[serde(alias = "mimeType")]
+    mime_type: String,
+    #[serde(alias = "fileUri")]
+    file_uri: String,
+}
+
+impl GeminiPart {
+    /// Media part of inline data or a file reference returned by the model.
+    fn media(self) -> Option<Part> {
+        let (data, mime_type, uri) = match self {
+            GeminiPart::InlineData { inline_data } => {
+                (inline_data.data, inline_data.mime_type, None)
+            }
+            GeminiPart::FileData { file_data } => {
+                (String::new(), file_data.mime_type, Some(file_data.file_uri))
+            }
+            _ => return None,
+        };
+        Some(Part::Media {
+            media_type: MediaType::from_mime_type(&mime_type),
+            data,
+            mime_type,
+            uri,
+            finished: true,
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GeminiModelList {
+    #[serde(default)]
+    models: Vec<GeminiModelEntry>,
+    next_page_token: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GeminiModelEntry {
+    name: String,
+    display_name: Option<String>,
+    input_token_limit: Option<u32>,
+    output_token_limit: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GeminiFileResponse {
+    file: GeminiFile,
+}
+
+/// A tool; each sets exactly one of the fields.
+#[skip_serializing_none]
+#[derive(Debug, Default, Serialize)]
 struct GeminiTool {
-    function_declarations: Vec<GeminiFunctionDeclaration>,
+    function_declarations: Option<Vec<GeminiFunctionDeclaration>>,
+    google_search: Option<Value>,
+    code_execution: Option<Value>,
 }
 
 #[derive(Debug, Serialize)]
@@ -405,8 +1138,18 @@ struct GeminiGenerationConfig {
     top_k: Option<u32>,
     max_output_tokens: Option<u32>,
     stop_sequences: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    frequency_penalty: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    presence_penalty: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    candidate_count: Option<u32>,
     response_mime_type: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    response_modalities: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     thinking_config: Option<GeminiThinkingConfig>,
 }
 
@@ -428,7 +1171,7 @@ impl GeminiRequest {
     ) -> Result<Self, ClientError> {
         let mut contents = Vec::new();
 
-        for msg in messages_in {
+        for msg in correct_media_types(messages_in) {
             let role = match msg {
                 Message::User(_) => "user",
                 Message::Assistant(_) => "model",
@@ -446,7 +1189,10 @@ impl GeminiRequest {
                         thought: Some(true),
                     }),
                     Part::Media {
-                        data, mime_type, ..
+                        data,
+                        mime_type,
+                        uri,
+                        ..
                     } => {
                         let anchor_text = part.anchor_media();
                         parts.push(GeminiPart::Text {
@@ -454,12 +1200,21 @@ impl GeminiRequest {
                             thought: None,
                         });
 
-                        parts.push(GeminiPart::InlineData {
-                            inline_data: GeminiInlineData {
-                                mime_type: mime_type.clone(),
-                                data: data.clone(),
-                            },
-                        });
+                        match uri {
+                            // Uploaded files are referenced instead of inlined.
+                            Some(file_uri) if data.is_empty() => parts.push(GeminiPart::FileData {
+                                file_data: GeminiFileData {
+                                    mime_type: mime_type.clone(),
+                                    file_uri: file_uri.clone(),
+                                },
+                            }),
+                            _ => parts.push(GeminiPart::InlineData {
+                                inline_data: GeminiInlineData {
+                                    mime_type: mime_type.clone(),
+                                    data: data.clone(),
+                                },
+                            }),
+                        }
                     }
                     Part::FunctionCall {
                         name,
@@ -475,6 +1230,12 @@ impl GeminiRequest {
                             thought_signature: signature.clone(),
                         });
                     }
+                    // Computer-use actions and hosted tool results from other providers
+                    // have no Gemini equivalent.
+                    Part::ComputerCall { .. }
+                    | Part::ComputerResult { .. }
+                    | Part::HostedToolCall { .. }
+                    | Part::Citation { .. } => {}
                     Part::FunctionResponse {
                         name,
                         response,
@@ -522,25 +1283,63 @@ impl GeminiRequest {
             }
         }
 
-        let tools = if !tool_defs.is_empty() {
+        let tool_config = model_options
+            .tool_choice
+            .as_ref()
+            .filter(|_| !tool_defs.is_empty())
+            .map(|choice| {
+                let (mode, allowed) = match choice {
+                    ToolChoice::Auto => ("AUTO", None),
+                    ToolChoice::None => ("NONE", None),
+                    ToolChoice::Required => ("ANY", None),
+                    ToolChoice::Specific(name) => ("ANY", Some(vec![name.clone()])),
+                };
+                GeminiToolConfig {
+                    function_calling_config: GeminiFunctionCallingConfig {
+                        mode: mode.to_string(),
+                        allowed_function_names: allowed,
+                    },
+                }
+            });
+
+        let mut tools = if !tool_defs.is_empty() {
             vec![GeminiTool {
-                function_declarations: tool_defs
-                    .into_iter()
-                    .map(|t| GeminiFunctionDeclaration {
-                        name: t.name.into_owned(),
-                        description: t.description.map(|d| d.into_owned()).unwrap_or_default(),
-                        parameters_json_schema: Some(Value::Object((*t.input_schema).clone())),
-                    })
-                    .collect(),
+                function_declarations: Some(
+                    canonical_tools(tool_defs)
+                        .into_iter()
+                        .map(|t| GeminiFunctionDeclaration {
+                            name: t.name.into_owned(),
+                            description: t.description.map(|d| d.into_owned()).unwrap_or_default(),
+                            parameters_json_schema: Some(Value::Object((*t.input_schema).clone())),
+                        })
+                        .collect(),
+                ),
+                ..Default::default()
             }]
         } else {
             Vec::new()
         };
+        tools.extend(
+            model_options
+                .built_in_tools
+                .iter()
+                .flatten()
+                .map(|tool| match tool {
+                    BuiltInTool::WebSearch => GeminiTool {
+                        google_search: Some(json!({})),
+                        ..Default::default()
+                    },
+                    BuiltInTool::CodeExecution => GeminiTool {
+                        code_execution: Some(json!({})),
+                        ..Default::default()
+                    },
+                }),
+        );
 
-        let system_instruction = model_options.system.as_ref().map(|s| GeminiContent {
+        let system_instruction = model_options.system_prompt().map(|s| GeminiContent {
             role: "user".to_string(),
             parts: vec![GeminiPart::Text {
-                text: s.clone(),
+                text: s,
                 thought: None,
             }],
         });
@@ -549,13 +1348,23 @@ impl GeminiRequest {
             contents,
             tools,
             system_instruction,
+            tool_config,
             generation_config: GeminiGenerationConfig {
                 temperature: model_options.temperature,
                 top_p: model_options.top_p,
                 top_k: model_options.provider.top_k,
                 max_output_tokens: model_options.max_tokens,
-                stop_sequences: model_options.provider.stop_sequences.clone(),
+                stop_sequences: model_options
+                    .provider
+                    .stop_sequences
+                    .clone()
+                    .or_else(|| model_options.stop.clone()),
+                frequency_penalty: model_options.frequency_penalty,
+                presence_penalty: model_options.presence_penalty,
+                seed: model_options.seed,
+                candidate_count: model_options.n,
                 response_mime_type: model_options.provider.response_mime_type.clone(),
+                response_modalities: model_options.provider.response_modalities.clone(),
                 thinking_config: if model_options.reasoning.unwrap_or(false)
                     || model_options.provider.include_thoughts.unwrap_or(false)
                 {
@@ -573,6 +1382,50 @@ impl GeminiRequest {
     }
 }
 
+// --- Token Counting Types ---
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GeminiCountTokensRequest {
+    generate_content_request: GeminiGenerateContentRequest,
+}
+
+#[derive(Debug, Serialize)]
+struct GeminiGenerateContentRequest {
+    model: String,
+    #[serde(flatten)]
+    request: GeminiRequest,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GeminiCountTokensResponse {
+    total_tokens: u32,
+}
+
+// --- Embedding Types ---
+
+#[derive(Debug, Serialize)]
+struct GeminiBatchEmbedRequest {
+    requests: Vec<GeminiEmbedContentRequest>,
+}
+
+#[derive(Debug, Serialize)]
+struct GeminiEmbedContentRequest {
+    model: String,
+    content: GeminiContent,
+}
+
+#[derive(Debug, Deserialize)]
+struct GeminiBatchEmbedResponse {
+    embeddings: Vec<GeminiEmbedding>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GeminiEmbedding {
+    values: Vec<f32>,
+}
+
 // --- Response Types ---
 
 #[derive(Debug, Deserialize)]
@@ -580,6 +1433,7 @@ impl GeminiRequest {
 struct GeminiResponse {
     candidates: Option<Vec<GeminiCandidate>>,
     usage_metadata: Option<GeminiUsageMetadata>,
+    model_version: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -589,6 +1443,97 @@ struct GeminiCandidate {
     content: Option<GeminiContent>,
     finish_reason: Option<String>,
     index: Option<u32>,
+    grounding_metadata: Option<GeminiGroundingMetadata>,
+}
+
+/// Searches made by the Google Search tool and the pages they found.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GeminiGroundingMetadata {
+    #[serde(default)]
+    web_search_queries: Vec<String>,
+    #[serde(default)]
+    grounding_chunks: Vec<GeminiGroundingChunk>,
+    #[serde(default)]
+    grounding_supports: Vec<GeminiGroundingSupport>,
+}
+
+/// A segment of the answer and the chunks backing it.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GeminiGroundingSupport {
+    segment: GeminiSegment,
+    #[serde(default)]
+    grounding_chunk_indices: Vec<usize>,
+}
+
+/// Byte offsets into the text of the answer.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GeminiSegment {
+    #[serde(default)]
+    start_index: usize,
+    #[serde(default)]
+    end_index: usize,
+}
+
+#[derive(Debug, Deserialize)]
+struct GeminiGroundingChunk {
+    web: Option<GeminiWebChunk>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GeminiWebChunk {
+    uri: String,
+    title: Option<String>,
+}
+
+impl GeminiGroundingMetadata {
+    /// The search as a hosted tool call, with the first query if there were
+    /// several, followed by a citation for each source of each segment of the
+    /// answer. Nothing is returned if no search was made.
+    fn parts(self, answer: &str) -> Vec<Part> {
+        if self.web_search_queries.is_empty() && self.grounding_chunks.is_empty() {
+            return Vec::new();
+        }
+        // Gemini counts bytes, citations count characters.
+        let char_index = |byte: usize| answer.get(..byte).map(|text| text.chars().count());
+        let citations = self.grounding_supports.iter().flat_map(|support| {
+            support
+                .grounding_chunk_indices
+                .iter()
+                .filter_map(|&chunk| self.grounding_chunks.get(chunk)?.web.as_ref())
+                .map(|web| Part::Citation {
+                    citation: Citation {
+                        url: Some(web.uri.clone()),
+                        title: web.title.clone(),
+                        start_index: char_index(support.segment.start_index),
+                        end_index: char_index(support.segment.end_index),
+                        ..Default::default()
+                    },
+                    finished: true,
+                })
+        });
+        let citations: Vec<Part> = citations.collect();
+
+        let search = Part::HostedToolCall {
+            id: None,
+            call: HostedToolCall::WebSearch {
+                query: self.web_search_queries.into_iter().next(),
+                sources: self
+                    .grounding_chunks
+                    .into_iter()
+                    .filter_map(|chunk| chunk.web)
+                    .map(|web| SearchSource {
+                        url: web.uri,
+                        title: web.title,
+                    })
+                    .collect(),
+            },
+            finished: true,
+        };
+        std::iter::once(search).chain(citations).collect()
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -599,6 +1544,23 @@ struct GeminiUsageMetadata {
     candidates_token_count: Option<u32>,
     total_token_count: u32,
     thoughts_token_count: Option<u32>,
+    /// Prompt tokens served from an implicit or explicit context cache,
+    /// included in `prompt_token_count`.
+    cached_content_token_count: Option<u32>,
+}
+
+impl From<GeminiUsageMetadata> for Usage {
+    fn from(usage: GeminiUsageMetadata) -> Self {
+        Usage {
+            prompt_tokens: Some(usage.prompt_token_count),
+            completion_tokens: Some(
+                usage.candidates_token_count.unwrap_or(0) + usage.thoughts_token_count.unwrap_or(0),
+            ),
+            cached_tokens: usage.cached_content_token_count,
+            cache_creation_tokens: None,
+            reasoning_tokens: usage.thoughts_token_count,
+        }
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -616,95 +1578,566 @@ struct GeminiError {
 
 impl From<GeminiResponse> for Response {
     fn from(resp: GeminiResponse) -> Self {
-        let mut parts = Vec::new();
-        let mut finish_reason = FinishReason::Unfinished;
+        let usage = resp.usage_metadata.map(Usage::from).unwrap_or_default();
+        let mut candidates = resp
+            .candidates
+            .unwrap_or_default()
+            .into_iter()
+            .map(convert_candidate);
+        let (message, finish) = candidates
+            .next()
+            .unwrap_or((Message::Assistant(Vec::new()), FinishReason::Unfinished));
 
-        if let Some(mut candidates) = resp.candidates {
-            if !candidates.is_empty() {
-                let candidate = candidates.remove(0);
-                if let Some(content) = candidate.content {
-                    for part in content.parts {
-                        match part {
-                            GeminiPart::Text { text, thought } => {
-                                if thought.unwrap_or(false) {
-                                    parts.push(Part::Reasoning {
-                                        content: text,
-                                        summary: None,
-                                        signature: None,
-                                        finished: true,
-                                    });
-                                } else {
-                                    parts.push(Part::Text {
-                                        content: text,
-                                        finished: true,
-                                    });
-                                }
-                            }
-                            GeminiPart::FunctionCall {
-                                function_call,
-                                thought_signature,
-                            } => {
-                                parts.push(Part::FunctionCall {
-                                    id: None,
-                                    name: function_call.name,
-                                    arguments: function_call.args,
-                                    signature: thought_signature,
-                                    finished: true,
-                                });
-                            }
-                            GeminiPart::FunctionResponse { function_response } => {
-                                let mut inner_parts = Vec::new();
-                                if let Some(gemini_parts) = function_response.parts {
-                                    for p in gemini_parts {
-                                        inner_parts.push(Part::Media {
-                                            media_type: MediaType::Binary,
-                                            data: p.inline_data.data,
-                                            mime_type: p.inline_data.mime_type,
-                                            uri: None,
-                                            finished: true,
-                                        });
-                                    }
-                                }
+        Response {
+            data: vec![message],
+            usage,
+            finish,
+            alternatives: candidates.map(|(message, _)| vec![message]).collect(),
+            model: resp.model_version,
+            iterations: Vec::new(),
+        }
+    }
+}
 
-                                parts.push(Part::FunctionResponse {
-                                    id: None,
-                                    name: function_response.name,
-                                    response: function_response.response,
-                                    parts: inner_parts,
-                                    finished: true,
-                                });
-                            }
-                            _ => {}
-                        }
+fn convert_candidate(candidate: GeminiCandidate) -> (Message, FinishReason) {
+    let mut parts = Vec::new();
+    let mut finish = FinishReason::Unfinished;
+
+    if let Some(content) = candidate.content {
+        for part in content.parts {
+            match part {
+                GeminiPart::Text { text, thought } => {
+                    if thought.unwrap_or(false) {
+                        parts.push(Part::Reasoning {
+                            content: text,
+                            summary: None,
+                            signature: None,
+                            finished: true,
+                        });
+                    } else {
+                        parts.push(Part::Text {
+                            content: text,
+                            finished: true,
+                        });
                     }
                 }
+                GeminiPart::FunctionCall {
+                    function_call,
+                    thought_signature,
+                } => {
+                    parts.push(Part::FunctionCall {
+                        id: None,
+                        name: function_call.name,
+                        arguments: function_call.args,
+                        signature: thought_signature,
+                        raw_arguments: None,
+                        finished: true,
+                    });
+                }
+                GeminiPart::FunctionResponse { function_response } => {
+                    let mut inner_parts = Vec::new();
+                    if let Some(gemini_parts) = function_response.parts {
+                        for p in gemini_parts {
+                            inner_parts.push(Part::Media {
+                                media_type: MediaType::Binary,
+                                data: p.inline_data.data,
+                                mime_type: p.inline_data.mime_type,
+                                uri: None,
+                                finished: true,
+                            });
+                        }
+                    }
 
-                if let Some(reason) = candidate.finish_reason {
-                    finish_reason = match reason.as_str() {
-                        "STOP" => FinishReason::Stop,
-                        "MAX_TOKENS" => FinishReason::OutputTokens,
-                        "SAFETY" => FinishReason::ContentFilter,
-                        "RECITATION" => FinishReason::ContentFilter,
-                        _ => FinishReason::Stop,
-                    };
+                    parts.push(Part::FunctionResponse {
+                        id: None,
+                        name: function_response.name,
+                        response: function_response.response,
+                        parts: inner_parts,
+                        finished: true,
+                    });
+                }
+                GeminiPart::ExecutableCode { executable_code } => {
+                    parts.push(Part::HostedToolCall {
+                        id: None,
+                        call: HostedToolCall::CodeInterpreter {
+                            code: Some(executable_code.code),
+                            container_id: None,
+                            outputs: Vec::new(),
+                        },
+                        finished: true,
+                    });
+                }
+                GeminiPart::CodeExecutionResult {
+                    code_execution_result,
+                } => {
+                    let outputs = code_execution_result.outputs();
+                    match parts.last_mut() {
+                        Some(Part::HostedToolCall {
+                            call:
+                                HostedToolCall::CodeInterpreter {
+                                    outputs: code_outputs,
+                                    ..
+                                },
+                            ..
+                        }) => code_outputs.extend(outputs),
+                        _ => parts.push(Part::HostedToolCall {
+                            id: None,
+                            call: HostedToolCall::CodeInterpreter {
+                                code: None,
+                                container_id: None,
+                                outputs,
+                            },
+                            finished: true,
+                        }),
+                    }
                 }
+                media => parts.extend(media.media()),
             }
         }
-
-        let usage = resp
-            .usage_metadata
-            .map(|u| Usage {
-                prompt_tokens: Some(u.prompt_token_count),
-                completion_tokens: Some(
-                    u.candidates_token_count.unwrap_or(0) + u.thoughts_token_count.unwrap_or(0),
-                ),
+    }
+    if let Some(grounding) = candidate.grounding_metadata {
+        let answer = parts
+            .iter()
+            .rev()
+            .find_map(|part| match part {
+                Part::Text { content, .. } => Some(content.as_str()),
+                _ => None,
             })
             .unwrap_or_default();
+        let grounding = grounding.parts(answer);
+        parts.extend(grounding);
+    }
 
-        Response {
-            data: vec![Message::Assistant(parts)],
-            usage,
-            finish: finish_reason,
-        }
+    if let Some(reason) = candidate.finish_reason {
+        finish = match reason.as_str() {
+            "STOP" => FinishReason::Stop,
+            "MAX_TOKENS" => FinishReason::OutputTokens,
+            "SAFETY" => FinishReason::ContentFilter,
+            "RECITATION" => FinishReason::ContentFilter,
+            _ => FinishReason::Stop,
+        };
+    }
+    (Message::Assistant(parts), finish)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::options::ReasoningVisibility;
+    use futures::TryStreamExt;
+
+    #[test]
+    fn test_upload_base_url() {
+        assert_eq!(
+            upload_base_url("https://generativelanguage.googleapis.com/v1beta"),
+            "https://generativelanguage.googleapis.com/upload/v1beta"
+        );
+        assert_eq!(
+            upload_base_url("http://localhost:8080"),
+            "http://localhost:8080/upload"
+        );
+    }
+
+    #[test]
+    fn test_expired_uploads_are_not_reused() {
+        let client = GeminiClient::new(
+            "key".to_string(),
+            "http://localhost".to_string(),
+            ModelOptions::new("gemini-2.5-flash"),
+            TransportOptions::default(),
+        );
+        let upload = |expires: Option<DateTime<Utc>>| Upload {
+            uri: "https://generativelanguage.googleapis.com/v1beta/files/abc".to_string(),
+            expires,
+        };
+        let now = Utc::now();
+        let mut uploads = client.uploads.lock().unwrap();
+        uploads.insert(1, upload(None));
+        uploads.insert(2, upload(Some(now + chrono::Duration::hours(47))));
+        uploads.insert(3, upload(Some(now + chrono::Duration::minutes(5))));
+        uploads.insert(4, upload(Some(now - chrono::Duration::hours(1))));
+        drop(uploads);
+
+        assert!(client.cached_upload(1).is_some());
+        assert!(client.cached_upload(2).is_some());
+        assert!(client.cached_upload(3).is_none());
+        assert!(client.cached_upload(4).is_none());
+        // Expired entries are dropped, so the payload is uploaded again.
+        assert_eq!(client.uploads.lock().unwrap().len(), 2);
+
+        let file: GeminiFile = serde_json::from_value(json!({
+            "name": "files/abc",
+            "mimeType": "video/mp4",
+            "uri": "https://generativelanguage.googleapis.com/v1beta/files/abc",
+            "expirationTime": "2025-01-03T10:00:00.123456Z"
+        }))
+        .unwrap();
+        assert_eq!(
+            Upload::from(&file).expires.unwrap().to_rfc3339(),
+            "2025-01-03T10:00:00.123456+00:00"
+        );
+    }
+
+    #[test]
+    fn test_uploaded_media_is_referenced_by_uri() {
+        let options = ModelOptions::<GeminiModel>::new("gemini-2.5-flash");
+        let messages = vec![Message::User(vec![
+            Part::Media {
+                media_type: MediaType::Binary,
+                data: String::new(),
+                mime_type: "video/mp4".to_string(),
+                uri: Some("https://generativelanguage.googleapis.com/v1beta/files/abc".to_string()),
+                finished: true,
+            },
+            Part::Media {
+                media_type: MediaType::Image,
+                data: "aGVsbG8=".to_string(),
+                mime_type: "image/png".to_string(),
+                uri: None,
+                finished: true,
+            },
+        ])];
+
+        let request = GeminiRequest::new(messages, &options, vec![]).unwrap();
+        let parts = &serde_json::to_value(request).unwrap()["contents"][0]["parts"];
+
+        assert_eq!(
+            parts[1]["fileData"],
+            json!({
+                "mime_type": "video/mp4",
+                "file_uri": "https://generativelanguage.googleapis.com/v1beta/files/abc"
+            })
+        );
+        assert_eq!(parts[3]["inlineData"]["data"], "aGVsbG8=");
+    }
+
+    #[test]
+    fn test_sampling_options() {
+        let mut options = ModelOptions::<GeminiModel>::new("gemini-2.5-flash");
+        options.stop = Some(vec!["END".to_string()]);
+        options.presence_penalty = Some(0.5);
+        options.seed = Some(7);
+
+        let request = GeminiRequest::new(vec![], &options, vec![]).unwrap();
+        let config = &serde_json::to_value(request).unwrap()["generation_config"];
+        assert_eq!(config["stopSequences"], json!(["END"]));
+        assert_eq!(config["presencePenalty"], 0.5);
+        assert_eq!(config["seed"], 7);
+
+        // Provider-specific stop sequences take precedence.
+        options.provider.stop_sequences = Some(vec!["STOP".to_string()]);
+        let request = GeminiRequest::new(vec![], &options, vec![]).unwrap();
+        assert_eq!(
+            serde_json::to_value(request).unwrap()["generation_config"]["stopSequences"],
+            json!(["STOP"])
+        );
+    }
+
+    #[test]
+    fn test_built_in_tools() {
+        let mut options = ModelOptions::<GeminiModel>::new("gemini-2.5-flash");
+        options.built_in_tools = Some(vec![BuiltInTool::WebSearch, BuiltInTool::CodeExecution]);
+        let request = GeminiRequest::new(vec![], &options, vec![]).unwrap();
+        assert_eq!(
+            serde_json::to_value(request).unwrap()["tools"],
+            json!([{ "google_search": {} }, { "code_execution": {} }])
+        );
+
+        let body = json!({
+            "candidates": [{
+                "content": {
+                    "role": "model",
+                    "parts": [
+                        { "executableCode": { "language": "PYTHON", "code": "print(6 * 7)" } },
+                        { "codeExecutionResult": { "outcome": "OUTCOME_OK", "output": "42\n" } },
+                        { "text": "6 × 7 = 42." }
+                    ]
+                },
+                "groundingMetadata": {
+                    "webSearchQueries": ["six times seven"],
+                    "groundingChunks": [{ "web": { "uri": "https://example.com", "title": "example.com" } }],
+                    "groundingSupports": [{
+                        "segment": { "startIndex": 5, "endIndex": 12, "text": "7 = 42." },
+                        "groundingChunkIndices": [0]
+                    }]
+                },
+                "finishReason": "STOP"
+            }]
+        });
+        let response: Response = serde_json::from_value::<GeminiResponse>(body)
+            .unwrap()
+            .into();
+        let parts = response.data[0].parts();
+        assert_eq!(parts.len(), 4);
+        assert_eq!(
+            parts[0],
+            Part::HostedToolCall {
+                id: None,
+                call: HostedToolCall::CodeInterpreter {
+                    code: Some("print(6 * 7)".to_string()),
+                    container_id: None,
+                    outputs: vec![CodeOutput::Logs {
+                        logs: "42\n".to_string()
+                    }],
+                },
+                finished: true,
+            }
+        );
+        assert_eq!(
+            parts[2],
+            Part::HostedToolCall {
+                id: None,
+                call: HostedToolCall::WebSearch {
+                    query: Some("six times seven".to_string()),
+                    sources: vec![SearchSource {
+                        url: "https://example.com".to_string(),
+                        title: Some("example.com".to_string()),
+                    }],
+                },
+                finished: true,
+            }
+        );
+        assert_eq!(
+            parts[3],
+            Part::Citation {
+                citation: Citation {
+                    url: Some("https://example.com".to_string()),
+                    title: Some("example.com".to_string()),
+                    start_index: Some(4),
+                    end_index: Some(11),
+                    ..Default::default()
+                },
+                finished: true,
+            }
+        );
+    }
+
+    #[test]
+    fn test_tool_choice() {
+        let schema = json!({ "type": "object" }).as_object().unwrap().clone();
+        let tools = vec![rmcp::model::Tool::new("extract", "Extract fields", schema)];
+        let mut options = ModelOptions::<GeminiModel>::new("gemini-2.5-flash");
+        options.tool_choice = Some(ToolChoice::Specific("extract".to_string()));
+
+        let request = GeminiRequest::new(vec![], &options, tools).unwrap();
+        assert_eq!(
+            serde_json::to_value(request).unwrap()["tool_config"],
+            json!({
+                "function_calling_config": {
+                    "mode": "ANY",
+                    "allowed_function_names": ["extract"]
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn test_generated_image_is_media() {
+        let body = json!({
+            "candidates": [{
+                "content": {
+                    "role": "model",
+                    "parts": [
+                        { "text": "Here is your lighthouse." },
+                        { "inlineData": { "mimeType": "image/png", "data": "iVBORw0KGgo=" } },
+                        { "fileData": { "mimeType": "video/mp4", "fileUri": "gs://bucket/clip.mp4" } }
+                    ]
+                },
+                "finishReason": "STOP"
+            }]
+        });
+        let response: Response = serde_json::from_value::<GeminiResponse>(body)
+            .unwrap()
+            .into();
+
+        assert_eq!(
+            response.data[0].parts()[1],
+            Part::Media {
+                media_type: MediaType::Image,
+                data: "iVBORw0KGgo=".to_string(),
+                mime_type: "image/png".to_string(),
+                uri: None,
+                finished: true,
+            }
+        );
+        assert_eq!(
+            response.data[0].parts()[2],
+            Part::Media {
+                media_type: MediaType::Binary,
+                data: String::new(),
+                mime_type: "video/mp4".to_string(),
+                uri: Some("gs://bucket/clip.mp4".to_string()),
+                finished: true,
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_streamed_inline_data_is_media() {
+        let chunks = [
+            json!({
+                "candidates": [{
+                    "content": { "role": "model", "parts": [{ "text": "Here is your lighthouse." }] }
+                }]
+            }),
+            json!({
+                "candidates": [{
+                    "content": {
+                        "role": "model",
+                        "parts": [{ "inlineData": { "mimeType": "image/png", "data": "iVBORw0KGgo=" } }]
+                    },
+                    "finishReason": "STOP"
+                }]
+            }),
+        ];
+        let body: String = chunks
+            .iter()
+            .map(|chunk| format!("data: {}\n\n", chunk))
+            .collect();
+        let response = http::Response::builder()
+            .header("content-type", "text/event-stream")
+            .body(body)
+            .unwrap();
+
+        let deltas: Vec<StreamDelta> = GeminiStream::deltas(response.into())
+            .try_collect()
+            .await
+            .unwrap();
+
+        assert_eq!(
+            deltas,
+            vec![
+                StreamDelta::TextDelta {
+                    index: 0,
+                    text: "Here is your lighthouse.".to_string(),
+                },
+                StreamDelta::PartFinished { index: 0 },
+                StreamDelta::Media {
+                    index: 1,
+                    media_type: MediaType::Image,
+                    data: "iVBORw0KGgo=".to_string(),
+                    mime_type: "image/png".to_string(),
+                    uri: None,
+                },
+                StreamDelta::Finish(FinishReason::Stop),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_multiple_candidates() {
+        let candidate = |text: &str| {
+            json!({
+                "content": { "role": "model", "parts": [{ "text": text }] },
+                "finishReason": "STOP"
+            })
+        };
+        let body = json!({ "candidates": [candidate("A"), candidate("B")] });
+        let response: Response = serde_json::from_value::<GeminiResponse>(body)
+            .unwrap()
+            .into();
+        assert_eq!(response.finish, FinishReason::Stop);
+        assert_eq!(
+            response.alternatives,
+            vec![vec![Message::Assistant(vec![Part::Text {
+                content: "B".to_string(),
+                finished: true,
+            }])]]
+        );
+
+        let mut options = ModelOptions::<GeminiModel>::new("gemini-2.5-flash");
+        options.n = Some(2);
+        let request = GeminiRequest::new(vec![], &options, vec![]).unwrap();
+        assert_eq!(
+            serde_json::to_value(request).unwrap()["generation_config"]["candidateCount"],
+            2
+        );
+    }
+
+    #[test]
+    fn test_cached_tokens_are_priced() {
+        let body = json!({
+            "candidates": [{
+                "content": { "role": "model", "parts": [{ "text": "Hi" }] },
+                "finishReason": "STOP"
+            }],
+            "usageMetadata": {
+                "promptTokenCount": 1000000,
+                "candidatesTokenCount": 0,
+                "totalTokenCount": 1000000,
+                "cachedContentTokenCount": 750000,
+                "cacheTokensDetails": [{ "modality": "TEXT", "tokenCount": 750000 }]
+            }
+        });
+        let response: Response = serde_json::from_value::<GeminiResponse>(body)
+            .unwrap()
+            .into();
+        assert_eq!(response.usage.cached_tokens, Some(750000));
+
+        // 250k uncached at $0.30 and 750k cached at $0.075 per million.
+        let cost = crate::cost::PriceTable::default()
+            .cost("gemini-2.5-flash", &response.usage)
+            .unwrap();
+        assert!((cost - 0.13125).abs() < 1e-9, "{}", cost);
+    }
+
+    #[tokio::test]
+    async fn test_streamed_deltas_hide_reasoning() {
+        use crate::testing::server::{TestResponse, TestServer};
+
+        let chunks = [
+            json!({
+                "candidates": [{
+                    "content": { "role": "model", "parts": [{ "text": "Compare the decimals.", "thought": true }] }
+                }]
+            }),
+            json!({
+                "candidates": [{
+                    "content": { "role": "model", "parts": [{ "text": "9.9" }] },
+                    "finishReason": "STOP"
+                }]
+            }),
+        ];
+        let body: String = chunks
+            .iter()
+            .map(|chunk| format!("data: {}\n\n", chunk))
+            .collect();
+        let server = TestServer::start(move |_, _| {
+            TestResponse::new(200, body.clone()).with_header("content-type", "text/event-stream")
+        })
+        .await;
+        let mut options = ModelOptions::new("gemini-2.5-flash");
+        // Gemini does not summarize, so the summary visibility shows nothing either.
+        options.reasoning_visibility = Some(ReasoningVisibility::Summary);
+        let client = GeminiClient::new(
+            "key".to_string(),
+            server.url().to_string(),
+            options,
+            TransportOptions::default(),
+        );
+
+        let deltas: Vec<StreamDelta> = client
+            .request_stream_deltas(vec![], vec![])
+            .await
+            .unwrap()
+            .try_collect()
+            .await
+            .unwrap();
+
+        assert_eq!(
+            deltas[..3],
+            [
+                StreamDelta::ReasoningDelta {
+                    index: 0,
+                    text: String::new(),
+                    signature: None,
+                },
+                StreamDelta::PartFinished { index: 0 },
+                StreamDelta::TextDelta {
+                    index: 1,
+                    text: "9.9".to_string(),
+                },
+            ]
+        );
     }
 }
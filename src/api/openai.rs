@@ -9,18 +9,170 @@ use serde_with::skip_serializing_none;
 use std::collections::HashMap;
 use std::pin::Pin;
 
-use crate::client::{Client, ClientError, StreamingClient};
-use crate::http::{add_extra_headers, build_http_client, RequestBuilderExt, ResponseExt};
-use crate::model::{FinishReason, MediaType, Message, Part, Response, Usage};
-use crate::options::{ModelOptions, TransportOptions};
-use crate::sse::SSEResponseExt;
+use crate::client::{Capabilities, Client, ClientError, Modality, ModelInfo, StreamingClient};
+use crate::embeddings::EmbeddingsClient;
+use crate::files::{file_reference, FileRef, FileUpload};
+use crate::finetune::{FineTuningClient, FineTuningJob, FineTuningJobRequest, TrainingFile};
+use crate::hosted::Citation;
+use crate::http::{
+    add_extra_headers, build_http_client, send_with_retry, MultipartForm, RequestBuilderExt,
+    ResponseExt,
+};
+use crate::images::{ImageGenerationClient, ImageOptions};
+use crate::markdown::{strip_delta_markdown, strip_response_markdown, strip_stream_markdown};
+use crate::model::{correct_media_types, FinishReason, MediaType, Message, Part, Response, Usage};
+use crate::moderation::{ModerationClient, ModerationResult};
+pub use crate::options::SystemRole;
+use crate::options::{ModelOptions, ToolChoice, TransportOptions};
+use crate::repair::finalize_arguments;
+use crate::sse::{error_event, SSEResponseExt};
+use crate::stream::{
+    accumulate, with_delta_reasoning_visibility, with_reasoning_visibility, ChoiceDelta,
+    StreamDelta,
+};
+use crate::strict::strict_tool_parameters;
+use crate::tokenize::TokenCounter;
+use crate::tools::canonical_tools;
+use crate::transcription::{
+    audio_file_name, Transcription, TranscriptionClient, TranscriptionOptions,
+};
 
 /// Trait for models compatible with OpenAI's Chat Completions API.
 pub trait OpenAICompatibleModel:
     Send + Sync + Default + Serialize + for<'de> Deserialize<'de> + Clone
 {
+    /// Identifier of the provider serving this model family.
+    const PROVIDER: &'static str = "openai";
+
+    /// Role used to send the system prompt to the given model.
+    fn system_role(_model: &str) -> SystemRole {
+        SystemRole::System
+    }
+
+    /// Features supported by the given model.
+    ///
+    /// Defaults to what OpenAI-compatible APIs generally offer: tools, streaming,
+    /// system prompts and JSON mode, but no vision and an unknown context window.
+    fn capabilities(_model: &str) -> Capabilities {
+        Capabilities {
+            supports_tools: true,
+            supports_vision: false,
+            supports_streaming: true,
+            supports_system_prompt: true,
+            max_context_tokens: None,
+            supports_json_mode: true,
+        }
+    }
+
+    /// URL of the model catalog.
+    fn models_url(base_url: &str) -> String {
+        format!("{}/models", base_url)
+    }
+
+    /// Provider-specific headers sent with every request, such as app attribution.
+    fn headers(&self) -> Vec<(&'static str, String)> {
+        Vec::new()
+    }
+}
+
+/// Check whether a model is an OpenAI reasoning model: the o-series and
+/// gpt-5 family.
+pub(crate) fn is_reasoning_model(model: &str) -> bool {
+    ["o1", "o3", "o4", "gpt-5"]
+        .iter()
+        .any(|prefix| model.starts_with(prefix))
+}
+
+/// Parse the model catalog of an OpenAI-compatible API.
+///
+/// Providers extend the OpenAI schema in different ways, so the details are read
+/// from the fields they commonly use: `context_window` (Groq), `context_length`
+/// (OpenRouter, Together), `max_context_length` and `deprecation` (Mistral), and
+/// `architecture.input_modalities` (OpenRouter). Ollama's `{"models": [...]}`
+/// listing is accepted as well.
+pub(crate) fn parse_model_list(body: &Value) -> Vec<ModelInfo> {
+    let entries = body
+        .get("data")
+        .or_else(|| body.get("models"))
+        .and_then(Value::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+
+    entries
+        .iter()
+        .filter_map(|entry| {
+            let field = |name: &str| entry.get(name).and_then(Value::as_str);
+            let number = |names: &[&str]| {
+                names
+                    .iter()
+                    .find_map(|name| entry.get(*name).and_then(Value::as_u64))
+                    .and_then(|n| u32::try_from(n).ok())
+            };
+
+            let id = field("id")
+                .or_else(|| field("model"))
+                .or_else(|| field("name"))?;
+            let display_name = field("name").filter(|name| *name != id);
+
+            let mut input_modalities: Vec<Modality> = entry
+                .pointer("/architecture/input_modalities")
+                .and_then(Value::as_array)
+                .map(|names| {
+                    names
+                        .iter()
+                        .filter_map(Value::as_str)
+                        .filter_map(Modality::parse)
+                        .collect()
+                })
+                .unwrap_or_default();
+            if let Some(vision) = entry
+                .pointer("/capabilities/vision")
+                .and_then(Value::as_bool)
+            {
+                input_modalities.push(Modality::Text);
+                if vision {
+                    input_modalities.push(Modality::Image);
+                }
+            }
+
+            Some(ModelInfo {
+                id: id.to_string(),
+                display_name: display_name.map(str::to_string),
+                context_window: number(&["context_window", "context_length", "max_context_length"]),
+                max_output_tokens: entry
+                    .pointer("/top_provider/max_completion_tokens")
+                    .and_then(Value::as_u64)
+                    .and_then(|n| u32::try_from(n).ok()),
+                input_modalities,
+                deprecation_date: field("deprecation").map(str::to_string),
+            })
+        })
+        .collect()
 }
 
+/// Marker trait for OpenAI-compatible providers that expose the `/embeddings` endpoint.
+pub trait OpenAICompatibleEmbeddings: OpenAICompatibleModel {}
+
+/// Marker trait for OpenAI-compatible providers that expose the `/files` and
+/// `/fine_tuning/jobs` endpoints.
+pub trait OpenAICompatibleFineTuning: OpenAICompatibleModel {}
+
+/// Marker trait for OpenAI-compatible providers that accept `/files` uploads
+/// as chat inputs.
+pub trait OpenAICompatibleFiles: OpenAICompatibleModel {}
+
+/// Marker trait for OpenAI-compatible providers that expose the
+/// `/images/generations` endpoint.
+pub trait OpenAICompatibleImages: OpenAICompatibleModel {}
+
+/// Marker trait for OpenAI-compatible providers that expose the
+/// `/audio/transcriptions` endpoint.
+pub trait OpenAICompatibleTranscription: OpenAICompatibleModel {}
+
+/// Marker trait for OpenAI-compatible providers that expose the `/moderations`
+/// endpoint.
+pub trait OpenAICompatibleModeration: OpenAICompatibleModel {}
+
 /// Generic client for OpenAI-compatible Chat Completions APIs.
 #[derive(Debug, Clone)]
 pub struct OpenAIClient<M> {
@@ -41,18 +193,21 @@ impl<M: OpenAICompatibleModel> OpenAIClient<M> {
             api_key,
             base_url,
             model_options,
-            transport_options,
+            transport_options: transport_options.for_provider(M::PROVIDER),
         }
     }
 
-    fn handle_error_response(status: reqwest::StatusCode, body: &str) -> ClientError {
-        if let Ok(error_resp) = serde_json::from_str::<OpenAIErrorResponse>(body) {
-            ClientError::ProviderError(format!(
+    pub(crate) fn handle_error_response(status: reqwest::StatusCode, body: &str) -> ClientError {
+        let message = match serde_json::from_str::<OpenAIErrorResponse>(body) {
+            Ok(error_resp) => format!(
                 "OpenAI error ({}): {}",
                 error_resp.error.error_type, error_resp.error.message
-            ))
-        } else {
-            ClientError::ProviderError(format!("HTTP {}: {}", status, body))
+            ),
+            Err(_) => body.to_string(),
+        };
+        ClientError::Status {
+            status: status.as_u16(),
+            message,
         }
     }
 
@@ -68,20 +223,96 @@ impl<M: OpenAICompatibleModel> OpenAIClient<M> {
 
         let request_body = OpenAIRequest::new(messages, &self.model_options, model, tools, stream);
 
+        Ok(self
+            .post(&url)?
+            .json_logged(&request_body, &self.transport_options))
+    }
+
+    async fn send_stream(
+        &self,
+        messages: Vec<Message>,
+        tools: Vec<rmcp::model::Tool>,
+    ) -> Result<reqwest::Response, ClientError> {
+        let req = self.build_request(messages, tools, true)?;
+        let response = send_with_retry(req, &self.transport_options).await?;
+        let status = response.status();
+
+        if !status.is_success() {
+            let body = response
+                .text_logged(&self.transport_options)
+                .await
+                .unwrap_or_default();
+            return Err(Self::handle_error_response(status, &body));
+        }
+
+        Ok(response)
+    }
+
+    /// Send a streaming request and return deltas tagged with their choice index.
+    ///
+    /// Use this instead of [`request_stream_deltas`](StreamingClient::request_stream_deltas)
+    /// when the request asks for several choices; the other streaming methods only
+    /// report the first one. Combine with
+    /// [`accumulate_choices`](crate::stream::accumulate_choices) to get one response
+    /// per choice.
+    pub async fn request_stream_choices(
+        &self,
+        messages: Vec<Message>,
+        tools: Vec<rmcp::model::Tool>,
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<ChoiceDelta, ClientError>> + Send>>, ClientError>
+    {
+        let response = self.send_stream(messages, tools).await?;
+        Ok(Box::pin(OpenAIStream::choice_deltas(response)))
+    }
+
+    fn post(&self, url: &str) -> Result<reqwest::RequestBuilder, ClientError> {
+        Ok(self
+            .authorized(reqwest::Method::POST, url)?
+            .header(CONTENT_TYPE, HeaderValue::from_static("application/json")))
+    }
+
+    fn authorized(
+        &self,
+        method: reqwest::Method,
+        url: &str,
+    ) -> Result<reqwest::RequestBuilder, ClientError> {
         let http_client = build_http_client(&self.transport_options)?;
 
         let mut headers = HeaderMap::new();
-        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
         headers.insert(
             AUTHORIZATION,
             HeaderValue::from_str(&format!("Bearer {}", self.api_key))
                 .map_err(|_| ClientError::Config("Invalid API key".to_string()))?,
         );
 
-        let mut req = http_client.post(&url).headers(headers);
-        req = add_extra_headers(req, &self.transport_options);
+        for (name, value) in self.model_options.provider.headers() {
+            headers.insert(
+                name,
+                HeaderValue::from_str(&value)
+                    .map_err(|_| ClientError::Config(format!("Invalid {} header", name)))?,
+            );
+        }
+
+        let req = http_client.request(method, url).headers(headers);
+        Ok(add_extra_headers(req, &self.transport_options))
+    }
+
+    async fn send_json<T: serde::de::DeserializeOwned>(
+        &self,
+        req: reqwest::RequestBuilder,
+    ) -> Result<T, ClientError> {
+        let response = send_with_retry(req, &self.transport_options).await?;
+        let status = response.status();
+
+        if !status.is_success() {
+            let body = response
+                .text_logged(&self.transport_options)
+                .await
+                .unwrap_or_default();
+            return Err(Self::handle_error_response(status, &body));
+        }
 
-        Ok(req.json_logged(&request_body))
+        response.json_logged(&self.transport_options).await
     }
 }
 
@@ -96,16 +327,26 @@ impl<M: OpenAICompatibleModel> Client for OpenAIClient<M> {
     ) -> Result<Response, ClientError> {
         let req = self.build_request(messages, tools, false)?;
 
-        let response = req.send().await?;
+        let response = send_with_retry(req, &self.transport_options).await?;
         let status = response.status();
 
         if !status.is_success() {
-            let body = response.text_logged().await.unwrap_or_default();
+            let body = response
+                .text_logged(&self.transport_options)
+                .await
+                .unwrap_or_default();
             return Err(Self::handle_error_response(status, &body));
         }
 
-        let openai_response: OpenAIResponse = response.json_logged().await?;
-        Ok(openai_response.into())
+        let openai_response: OpenAIResponse = response.json_logged(&self.transport_options).await?;
+        let mut response: Response = openai_response.into();
+        if let Some(visibility) = self.model_options.reasoning_visibility {
+            response.set_reasoning_visibility(visibility);
+        }
+        if self.model_options.plain_text.unwrap_or(false) {
+            strip_response_markdown(&mut response);
+        }
+        Ok(response)
     }
 
     fn model_options(&self) -> &ModelOptions<Self::ModelProvider> {
@@ -115,6 +356,21 @@ impl<M: OpenAICompatibleModel> Client for OpenAIClient<M> {
     fn transport_options(&self) -> &TransportOptions {
         &self.transport_options
     }
+
+    fn provider(&self) -> &'static str {
+        M::PROVIDER
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        M::capabilities(&self.model_options.model)
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>, ClientError> {
+        let url = M::models_url(&self.base_url);
+        let req = self.authorized(reqwest::Method::GET, &url)?;
+        let body: Value = self.send_json(req).await?;
+        Ok(parse_model_list(&body))
+    }
 }
 
 #[async_trait]
@@ -125,19 +381,279 @@ impl<M: OpenAICompatibleModel> StreamingClient for OpenAIClient<M> {
         tools: Vec<rmcp::model::Tool>,
     ) -> Result<Pin<Box<dyn Stream<Item = Result<Response, ClientError>> + Send>>, ClientError>
     {
-        let req = self.build_request(messages, tools, true)?;
-        let response = req.send().await?;
+        let response = self.send_stream(messages, tools).await?;
+        let stream = OpenAIStream::create(response);
+        let stream = with_reasoning_visibility(stream, self.model_options.reasoning_visibility);
+        if self.model_options.plain_text.unwrap_or(false) {
+            return Ok(Box::pin(strip_stream_markdown(stream)));
+        }
+        Ok(Box::pin(stream))
+    }
+
+    async fn request_stream_deltas(
+        &self,
+        messages: Vec<Message>,
+        tools: Vec<rmcp::model::Tool>,
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<StreamDelta, ClientError>> + Send>>, ClientError>
+    {
+        let response = self.send_stream(messages, tools).await?;
+        let deltas = with_delta_reasoning_visibility(
+            OpenAIStream::deltas(response),
+            self.model_options.reasoning_visibility,
+            |_, signature| signature,
+        );
+        if self.model_options.plain_text.unwrap_or(false) {
+            return Ok(Box::pin(strip_delta_markdown(deltas)));
+        }
+        Ok(Box::pin(deltas))
+    }
+}
+
+#[async_trait]
+impl<M: OpenAICompatibleEmbeddings> EmbeddingsClient for OpenAIClient<M> {
+    async fn embed(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>, ClientError> {
+        let url = format!("{}/embeddings", self.base_url);
+        let request_body = OpenAIEmbeddingRequest {
+            model: self.model_options.model.clone(),
+            input: texts,
+        };
+
+        let req = self
+            .post(&url)?
+            .json_logged(&request_body, &self.transport_options);
+        let response = send_with_retry(req, &self.transport_options).await?;
         let status = response.status();
 
         if !status.is_success() {
-            let body = response.text_logged().await.unwrap_or_default();
+            let body = response
+                .text_logged(&self.transport_options)
+                .await
+                .unwrap_or_default();
             return Err(Self::handle_error_response(status, &body));
         }
 
-        Ok(Box::pin(OpenAIStream::create(response)))
+        let mut embedding_response: OpenAIEmbeddingResponse =
+            response.json_logged(&self.transport_options).await?;
+        embedding_response.data.sort_by_key(|d| d.index);
+        Ok(embedding_response
+            .data
+            .into_iter()
+            .map(|d| d.embedding)
+            .collect())
+    }
+
+    fn max_batch_size(&self) -> usize {
+        2048
+    }
+}
+
+#[async_trait]
+impl<M: OpenAICompatibleFineTuning> FineTuningClient for OpenAIClient<M> {
+    async fn upload_training_file(
+        &self,
+        filename: &str,
+        jsonl: String,
+    ) -> Result<TrainingFile, ClientError> {
+        let url = format!("{}/files", self.base_url);
+        let form = MultipartForm::new().text("purpose", "fine-tune").file(
+            "file",
+            jsonl.into_bytes(),
+            "application/jsonl",
+            filename,
+        );
+
+        let req = self
+            .authorized(reqwest::Method::POST, &url)?
+            .multipart_logged(&form, &self.transport_options);
+        self.send_json(req).await
+    }
+
+    async fn create_fine_tuning_job(
+        &self,
+        request: FineTuningJobRequest,
+    ) -> Result<FineTuningJob, ClientError> {
+        let url = format!("{}/fine_tuning/jobs", self.base_url);
+        let req = self
+            .post(&url)?
+            .json_logged(&request, &self.transport_options);
+        self.send_json(req).await
+    }
+
+    async fn get_fine_tuning_job(&self, id: &str) -> Result<FineTuningJob, ClientError> {
+        let url = format!("{}/fine_tuning/jobs/{}", self.base_url, id);
+        let req = self.authorized(reqwest::Method::GET, &url)?;
+        self.send_json(req).await
+    }
+
+    async fn cancel_fine_tuning_job(&self, id: &str) -> Result<FineTuningJob, ClientError> {
+        let url = format!("{}/fine_tuning/jobs/{}/cancel", self.base_url, id);
+        let req = self.post(&url)?;
+        self.send_json(req).await
+    }
+}
+
+#[async_trait]
+impl<M: OpenAICompatibleFiles> FileUpload for OpenAIClient<M> {
+    async fn upload_file(
+        &self,
+        data: Vec<u8>,
+        mime_type: &str,
+        name: Option<&str>,
+    ) -> Result<FileRef, ClientError> {
+        let url = format!("{}/files", self.base_url);
+        let form = MultipartForm::new().text("purpose", "user_data").file(
+            "file",
+            data,
+            mime_type,
+            name.unwrap_or("file"),
+        );
+
+        let req = self
+            .authorized(reqwest::Method::POST, &url)?
+            .multipart_logged(&form, &self.transport_options);
+        let file: OpenAIFile = self.send_json(req).await?;
+        Ok(FileRef {
+            uri: file.id.clone(),
+            id: file.id,
+            mime_type: mime_type.to_string(),
+            name: Some(file.filename),
+            size_bytes: Some(file.bytes),
+        })
+    }
+
+    async fn delete_file(&self, file: &FileRef) -> Result<(), ClientError> {
+        let url = format!("{}/files/{}", self.base_url, file.id);
+        let req = self.authorized(reqwest::Method::DELETE, &url)?;
+        let _: Value = self.send_json(req).await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<M: OpenAICompatibleTranscription> TranscriptionClient for OpenAIClient<M> {
+    async fn transcribe(
+        &self,
+        audio: Vec<u8>,
+        mime_type: &str,
+        options: TranscriptionOptions,
+    ) -> Result<Transcription, ClientError> {
+        let url = format!("{}/audio/transcriptions", self.base_url);
+        let model = options
+            .model
+            .unwrap_or_else(|| self.model_options.model.clone());
+        // Segments are only returned in the verbose format, which the
+        // gpt-4o transcription models do not support.
+        let response_format = if options.timestamps.unwrap_or(false) {
+            "verbose_json"
+        } else {
+            "json"
+        };
+
+        let mut form = MultipartForm::new()
+            .text("model", model)
+            .text("response_format", response_format);
+        if let Some(language) = options.language {
+            form = form.text("language", language);
+        }
+        if let Some(prompt) = options.prompt {
+            form = form.text("prompt", prompt);
+        }
+        if let Some(temperature) = options.temperature {
+            form = form.text("temperature", temperature.to_string());
+        }
+        let form = form.file("file", audio, mime_type, audio_file_name(mime_type));
+
+        let req = self
+            .authorized(reqwest::Method::POST, &url)?
+            .multipart_logged(&form, &self.transport_options);
+        self.send_json(req).await
+    }
+}
+
+#[async_trait]
+impl<M: OpenAICompatibleImages> ImageGenerationClient for OpenAIClient<M> {
+    async fn generate_images(
+        &self,
+        prompt: &str,
+        options: ImageOptions,
+    ) -> Result<Vec<Part>, ClientError> {
+        let url = format!("{}/images/generations", self.base_url);
+        let model = options
+            .model
+            .unwrap_or_else(|| self.model_options.model.clone());
+        // DALL-E returns URLs by default, while GPT image models only return
+        // base64 data and reject the parameter.
+        let response_format = model.starts_with("dall-e").then(|| "b64_json".to_string());
+        let mime_type = match options.output_format.as_deref() {
+            Some("jpeg") => "image/jpeg",
+            Some("webp") => "image/webp",
+            _ => "image/png",
+        };
+        let request_body = OpenAIImageRequest {
+            model,
+            prompt: prompt.to_string(),
+            n: options.count,
+            size: options.size,
+            quality: options.quality,
+            output_format: options.output_format,
+            response_format,
+        };
+
+        let req = self
+            .post(&url)?
+            .json_logged(&request_body, &self.transport_options);
+        let response: OpenAIImageResponse = self.send_json(req).await?;
+        Ok(response
+            .data
+            .into_iter()
+            .map(|image| Part::Media {
+                media_type: MediaType::Image,
+                data: image.b64_json.unwrap_or_default(),
+                mime_type: mime_type.to_string(),
+                uri: image.url,
+                finished: true,
+            })
+            .collect())
+    }
+}
+
+#[async_trait]
+impl<M: OpenAICompatibleModeration> ModerationClient for OpenAIClient<M> {
+    async fn moderate(&self, texts: Vec<String>) -> Result<Vec<ModerationResult>, ClientError> {
+        let url = format!("{}/moderations", self.base_url);
+        let request_body = OpenAIModerationRequest {
+            model: self.model_options.model.clone(),
+            input: texts,
+        };
+
+        let req = self
+            .post(&url)?
+            .json_logged(&request_body, &self.transport_options);
+        let response: OpenAIModerationResponse = self.send_json(req).await?;
+        Ok(response
+            .results
+            .into_iter()
+            .map(|result| {
+                let mut categories: Vec<String> = result
+                    .categories
+                    .into_iter()
+                    .filter(|(_, flagged)| *flagged == Some(true))
+                    .map(|(category, _)| category)
+                    .collect();
+                categories.sort();
+                ModerationResult {
+                    flagged: result.flagged,
+                    categories,
+                    scores: result.category_scores,
+                }
+            })
+            .collect())
     }
 }
 
+#[async_trait]
+impl<M: OpenAICompatibleModel> TokenCounter for OpenAIClient<M> {}
+
 // --- Streaming Implementation ---
 
 struct OpenAIStream;
@@ -146,113 +662,242 @@ impl OpenAIStream {
     fn create(
         response: reqwest::Response,
     ) -> impl Stream<Item = Result<Response, ClientError>> + Send {
-        let sse_stream = response.sse();
+        accumulate(Self::deltas(response))
+    }
+
+    fn deltas(
+        response: reqwest::Response,
+    ) -> impl Stream<Item = Result<StreamDelta, ClientError>> + Send {
+        Self::choice_deltas(response).filter_map(|delta| async move {
+            match delta {
+                Ok(ChoiceDelta { choice: 0, delta }) => Some(Ok(delta)),
+                Ok(_) => None,
+                Err(e) => Some(Err(e)),
+            }
+        })
+    }
+
+    fn choice_deltas(
+        response: reqwest::Response,
+    ) -> impl Stream<Item = Result<ChoiceDelta, ClientError>> + Send {
+        Self::event_deltas(response.sse())
+    }
 
+    /// Parse the SSE data payloads of a chat completion stream.
+    fn event_deltas<S>(events: S) -> impl Stream<Item = Result<ChoiceDelta, ClientError>> + Send
+    where
+        S: Stream<Item = Result<String, ClientError>> + Send + 'static,
+    {
         Box::pin(async_stream::try_stream! {
-            let mut stream = Box::pin(sse_stream);
-            let mut current_response = Response {
-                data: vec![Message::Assistant(vec![])],
-                usage: Usage::default(),
-                finish: FinishReason::Unfinished,
-            };
+            let mut stream = Box::pin(events);
 
-            let mut tool_index_map: HashMap<u32, usize> = HashMap::new();
-            let mut current_text_part_index: Option<usize> = None;
+            let mut choices: HashMap<usize, ChoiceState> = HashMap::new();
+            // Citations of the first choice, sent before it finishes.
+            let mut citations = Vec::new();
 
             while let Some(event_result) = stream.next().await {
                 let event_str = event_result?;
+                // OpenRouter sends errors as chunks that are otherwise valid.
+                if let Some(error) = error_event(&event_str) {
+                    Err(error)?;
+                }
 
                 let chunk_result: OpenAIStreamChunk = serde_json::from_str(&event_str)
                     .map_err(|e| ClientError::ProviderError(format!("JSON parse error: {} | Input: {}", e, event_str)))?;
 
                 if let Some(usage) = chunk_result.usage {
-                    current_response.usage.prompt_tokens = Some(usage.prompt_tokens);
-                    current_response.usage.completion_tokens = Some(usage.completion_tokens);
+                    yield ChoiceDelta {
+                        choice: 0,
+                        delta: StreamDelta::Usage(usage.into()),
+                    };
+                }
+                let chunk_citations = chunk_result.sources.citations();
+                if !chunk_citations.is_empty() {
+                    citations = chunk_citations;
                 }
 
                 for choice in chunk_result.choices {
-                    let parts = current_response.data[0].parts_mut();
+                    let choice_index = choice.index as usize;
+                    if !choices.contains_key(&choice_index) {
+                        if let Some(model) = &chunk_result.model {
+                            yield ChoiceDelta {
+                                choice: choice_index,
+                                delta: StreamDelta::Model { model: model.clone() },
+                            };
+                        }
+                    }
+                    let state = choices.entry(choice_index).or_default();
 
                     if let Some(delta) = choice.delta {
-                        if let Some(delta_content) = delta.content {
-                            if let Some(idx) = current_text_part_index {
-                                if let Some(Part::Text { content, .. }) = parts.get_mut(idx) {
-                                    content.push_str(&delta_content);
+                        if let Some(text) = delta.reasoning_content.or(delta.reasoning).filter(|text| !text.is_empty()) {
+                            let index = match state.current_reasoning_part_index {
+                                Some(index) => index,
+                                None => {
+                                    let index = state.next_part();
+                                    state.current_reasoning_part_index = Some(index);
+                                    index
                                 }
-                            } else {
-                                parts.push(Part::Text { content: delta_content, finished: false });
-                                current_text_part_index = Some(parts.len() - 1);
+                            };
+                            yield ChoiceDelta {
+                                choice: choice_index,
+                                delta: StreamDelta::ReasoningDelta { index, text, signature: None },
+                            };
+                        }
+
+                        // Reasoning precedes the answer, so it is complete once the answer starts.
+                        let answer_started = delta.content.as_deref().is_some_and(|text| !text.is_empty())
+                            || delta.tool_calls.as_ref().is_some_and(|calls| !calls.is_empty());
+                        if answer_started {
+                            if let Some(index) = state.current_reasoning_part_index.take() {
+                                yield ChoiceDelta {
+                                    choice: choice_index,
+                                    delta: StreamDelta::PartFinished { index },
+                                };
                             }
                         }
 
+                        if let Some(delta_content) = delta.content {
+                            let index = match state.current_text_part_index {
+                                Some(index) => index,
+                                None => {
+                                    let index = state.next_part();
+                                    state.current_text_part_index = Some(index);
+                                    index
+                                }
+                            };
+                            yield ChoiceDelta {
+                                choice: choice_index,
+                                delta: StreamDelta::TextDelta { index, text: delta_content },
+                            };
+                        }
+
                         if let Some(tool_calls) = delta.tool_calls {
-                            for tool_call in tool_calls {
-                                let idx = *tool_index_map.entry(tool_call.index).or_insert_with(|| {
-                                    parts.push(Part::FunctionCall {
-                                        id: None,
-                                        name: String::new(),
-                                        arguments: Value::String(String::new()),
+                            for (position, tool_call) in tool_calls.into_iter().enumerate() {
+                                let (name, arguments) = match tool_call.function {
+                                    Some(function) => (function.name, function.arguments.unwrap_or_default()),
+                                    None => (None, String::new()),
+                                };
+                                let (index, id) = state.tool_call_part(
+                                    tool_call.index.unwrap_or(position as u32),
+                                    tool_call.id,
+                                    name.is_some(),
+                                    !arguments.is_empty(),
+                                );
+
+                                yield ChoiceDelta {
+                                    choice: choice_index,
+                                    delta: StreamDelta::ToolCallDelta {
+                                        index,
+                                        id,
+                                        name,
+                                        arguments,
                                         signature: None,
-                                        finished: false,
-                                    });
-                                    parts.len() - 1
-                                });
-
-                                if let Some(Part::FunctionCall { id: p_id, name: p_name, arguments: p_args, .. }) = parts.get_mut(idx) {
-                                    if let Some(id) = tool_call.id {
-                                        *p_id = Some(id);
-                                    }
-                                    if let Some(function) = tool_call.function {
-                                        if let Some(name) = function.name {
-                                            p_name.push_str(&name);
-                                        }
-                                        if let Some(args) = function.arguments {
-                                            if let Value::String(arg_str) = p_args {
-                                                arg_str.push_str(&args);
-                                            }
-                                        }
-                                    }
-                                }
+                                    },
+                                };
                             }
                         }
                     }
 
                     if let Some(finish_reason) = choice.finish_reason {
-                        for part in parts.iter_mut() {
-                            match part {
-                                Part::Text { finished, .. } => *finished = true,
-                                Part::Reasoning { finished, .. } => *finished = true,
-                                Part::FunctionCall { finished, arguments, .. } => {
-                                    *finished = true;
-                                    if let Value::String(json_str) = arguments {
-                                        if let Ok(json_val) = serde_json::from_str(json_str) {
-                                            *arguments = json_val;
-                                        } else {
-                                            *arguments = json!({});
-                                        }
-                                    }
-                                },
-                                Part::FunctionResponse { finished, .. } => *finished = true,
-                                Part::Media { finished, .. } => *finished = true,
+                        if choice_index == 0 {
+                            for citation in std::mem::take(&mut citations) {
+                                yield ChoiceDelta {
+                                    choice: 0,
+                                    delta: StreamDelta::Citation { index: state.next_part(), citation },
+                                };
                             }
                         }
-
-                        current_response.finish = match finish_reason.as_str() {
-                            "stop" => FinishReason::Stop,
-                            "length" => FinishReason::OutputTokens,
-                            "tool_calls" => FinishReason::ToolCalls,
-                            "content_filter" => FinishReason::ContentFilter,
-                            _ => FinishReason::Stop,
+                        yield ChoiceDelta {
+                            choice: choice_index,
+                            delta: StreamDelta::Finish(match finish_reason.as_str() {
+                                "stop" => FinishReason::Stop,
+                                "length" => FinishReason::OutputTokens,
+                                "tool_calls" => FinishReason::ToolCalls,
+                                "content_filter" => FinishReason::ContentFilter,
+                                _ => FinishReason::Stop,
+                            }),
                         };
                     }
                 }
-
-                yield current_response.clone();
             }
         })
     }
 }
 
+/// Part bookkeeping for a single choice of a streamed response.
+#[derive(Default)]
+struct ChoiceState {
+    part_count: usize,
+    /// The call last streamed at each tool call index.
+    tool_calls: HashMap<u32, StreamedToolCall>,
+    current_text_part_index: Option<usize>,
+    current_reasoning_part_index: Option<usize>,
+}
+
+struct StreamedToolCall {
+    part: usize,
+    id: String,
+    /// Whether the id was sent by the server rather than synthesized.
+    id_received: bool,
+    has_arguments: bool,
+}
+
+impl ChoiceState {
+    fn next_part(&mut self) -> usize {
+        self.part_count += 1;
+        self.part_count - 1
+    }
+
+    /// Find the part of a tool call delta and the id to report with it.
+    ///
+    /// Some OpenAI-compatible servers reuse one index for every call or omit
+    /// ids. A delta at a known index starts a new call when it carries a
+    /// different id, or a name once arguments have started. Calls without an id
+    /// get a synthesized one, replaced if the server sends one later.
+    fn tool_call_part(
+        &mut self,
+        index: u32,
+        id: Option<String>,
+        has_name: bool,
+        has_arguments: bool,
+    ) -> (usize, Option<String>) {
+        let id = id.filter(|id| !id.is_empty());
+        if let Some(call) = self.tool_calls.get_mut(&index) {
+            let new_call = match &id {
+                Some(id) => call.id_received && *id != call.id,
+                None => has_name && call.has_arguments,
+            };
+            if !new_call {
+                if let Some(id) = &id {
+                    call.id = id.clone();
+                    call.id_received = true;
+                }
+                call.has_arguments |= has_arguments;
+                return (call.part, id);
+            }
+        }
+
+        let part = self.next_part();
+        let id_received = id.is_some();
+        let id = id.unwrap_or_else(synthesize_call_id);
+        self.tool_calls.insert(
+            index,
+            StreamedToolCall {
+                part,
+                id: id.clone(),
+                id_received,
+                has_arguments,
+            },
+        );
+        (part, Some(id))
+    }
+}
+
+/// Id for a tool call sent without one, so its result can be matched to it.
+fn synthesize_call_id() -> String {
+    format!("call_{}", uuid::Uuid::new_v4().simple())
+}
+
 // --- Request Types ---
 
 #[skip_serializing_none]
@@ -265,13 +910,27 @@ struct OpenAIRequest<M> {
     max_completion_tokens: Option<u32>,
     temperature: Option<f32>,
     top_p: Option<f32>,
+    stop: Option<Vec<String>>,
+    frequency_penalty: Option<f32>,
+    presence_penalty: Option<f32>,
+    seed: Option<u64>,
+    n: Option<u32>,
     stream: Option<bool>,
+    stream_options: Option<OpenAIStreamOptions>,
     #[serde(skip_serializing_if = "Vec::is_empty")]
     tools: Vec<OpenAITool>,
+    tool_choice: Option<Value>,
     #[serde(flatten)]
     provider_options: M,
 }
 
+/// Options of streamed requests. Usage is only reported in a final chunk
+/// when requested.
+#[derive(Debug, Serialize)]
+struct OpenAIStreamOptions {
+    include_usage: bool,
+}
+
 #[derive(Debug, Serialize)]
 struct OpenAIMessage {
     role: String,
@@ -296,9 +955,31 @@ enum OpenAIContent {
 enum OpenAIContentPart {
     Text { text: String },
     ImageUrl { image_url: OpenAIImageUrl },
+    InputAudio { input_audio: OpenAIInputAudio },
     File { file: OpenAIFileContent },
 }
 
+#[derive(Debug, Serialize)]
+struct OpenAIInputAudio {
+    data: String,
+    format: String,
+}
+
+/// `input_audio` format of an audio mime type: `wav` and `mp3` as OpenAI
+/// expects, other formats by their subtype, e.g. `flac`, for compatible
+/// servers that accept them.
+fn input_audio_format(mime_type: &str) -> String {
+    let essence = mime_type.split(';').next().unwrap_or_default().trim();
+    let subtype = essence
+        .split_once('/')
+        .map_or(essence, |(_, subtype)| subtype);
+    match subtype.trim_start_matches("x-") {
+        "wav" | "wave" | "vnd.wave" => "wav".to_string(),
+        "mpeg" | "mp3" => "mp3".to_string(),
+        other => other.to_string(),
+    }
+}
+
 #[derive(Debug, Serialize)]
 struct OpenAIFileContent {
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -326,10 +1007,13 @@ struct OpenAIFunction {
     name: String,
     description: Option<String>,
     parameters: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    strict: Option<bool>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 struct OpenAIToolCall {
+    #[serde(default)]
     id: String,
     #[serde(rename = "type")]
     call_type: String,
@@ -350,148 +1034,38 @@ impl<M: OpenAICompatibleModel> OpenAIRequest<M> {
         tool_defs: Vec<rmcp::model::Tool>,
         stream: bool,
     ) -> Self {
-        let mut messages = Vec::new();
-
-        if let Some(system) = &model_options.system {
-            messages.push(OpenAIMessage {
-                role: "system".to_string(),
-                content: OpenAIContent::Text(system.clone()),
-                name: None,
-                tool_call_id: None,
-                tool_calls: Vec::new(),
-            });
+        let messages = convert_messages(
+            model_options.system_prompt().as_deref(),
+            model_options
+                .system_role
+                .unwrap_or_else(|| M::system_role(&model)),
+            correct_media_types(messages_in),
+        );
+        let tools = convert_tools(tool_defs, model_options.strict_tools.unwrap_or(false));
+        if let Some(built_in) = model_options
+            .built_in_tools
+            .as_ref()
+            .filter(|t| !t.is_empty())
+        {
+            tracing::warn!(
+                "Chat completions do not host tools, leaving out {:?}; use the Responses API",
+                built_in
+            );
         }
-
-        for msg in messages_in {
-            let role = match msg {
-                Message::User(_) => "user",
-                Message::Assistant(_) => "assistant",
-            };
-
-            let mut content_parts = Vec::new();
-            let mut tool_calls = Vec::new();
-            let mut tool_call_id = None;
-            let name = None;
-
-            for part in msg.parts() {
-                match part {
-                    Part::Text { content: t, .. } => {
-                        content_parts.push(OpenAIContentPart::Text { text: t.clone() })
-                    }
-                    Part::Media {
-                        media_type: MediaType::Image,
-                        data,
-                        mime_type,
-                        ..
-                    } => {
-                        let anchor_text = part.anchor_media();
-                        content_parts.push(OpenAIContentPart::Text { text: anchor_text });
-                        content_parts.push(OpenAIContentPart::ImageUrl {
-                            image_url: OpenAIImageUrl {
-                                url: format!("data:{};base64,{}", mime_type, data),
-                            },
-                        });
-                    }
-                    Part::Media { data, uri, .. } => {
-                        let anchor_text = part.anchor_media();
-                        content_parts.push(OpenAIContentPart::Text { text: anchor_text });
-                        content_parts.push(OpenAIContentPart::File {
-                            file: OpenAIFileContent {
-                                file_data: Some(data.clone()),
-                                file_id: None,
-                                filename: uri.clone(),
-                            },
-                        });
-                    }
-                    Part::FunctionCall {
-                        id: Some(call_id),
-                        name: fn_name,
-                        arguments,
-                        ..
-                    } => {
-                        tool_calls.push(OpenAIToolCall {
-                            id: call_id.clone(),
-                            call_type: "function".to_string(),
-                            function: OpenAIFunctionCall {
-                                name: fn_name.clone(),
-                                arguments: arguments.to_string(),
-                            },
-                        });
-                    }
-                    Part::FunctionResponse {
-                        id: Some(call_id),
-                        response,
-                        parts,
-                        ..
-                    } => {
-                        tool_call_id = Some(call_id.clone());
-
-                        let mut content_str = String::new();
-
-                        if response != &serde_json::json!({}) {
-                            content_str.push_str(&response.to_string());
-                        }
-
-                        for part in parts {
-                            if let Part::Media {
-                                media_type,
-                                mime_type,
-                                ..
-                            } = part
-                            {
-                                let anchor_text = part.anchor_media();
-                                content_str.push_str(&format!("\n{}", anchor_text));
-
-                                match media_type {
-                                    MediaType::Image => content_str.push_str("\n[Image Content]"),
-                                    _ => content_str.push_str(&format!("\n[File: {}]", mime_type)),
-                                }
-                            }
-                        }
-
-                        content_parts.push(OpenAIContentPart::Text { text: content_str });
-                    }
-                    _ => {}
-                }
-            }
-
-            let final_role = if tool_call_id.is_some() { "tool" } else { role };
-
-            let content = if content_parts.len() == 1 {
-                if let OpenAIContentPart::Text { text } = &content_parts[0] {
-                    OpenAIContent::Text(text.clone())
-                } else {
-                    OpenAIContent::Parts(content_parts)
+        let tool_choice = model_options
+            .tool_choice
+            .as_ref()
+            .filter(|_| !tools.is_empty())
+            .map(|choice| match choice {
+                ToolChoice::Auto => json!("auto"),
+                ToolChoice::None => json!("none"),
+                ToolChoice::Required => json!("required"),
+                ToolChoice::Specific(name) => {
+                    json!({ "type": "function", "function": { "name": name } })
                 }
-            } else if !content_parts.is_empty() {
-                OpenAIContent::Parts(content_parts)
-            } else {
-                OpenAIContent::Text(String::new())
-            };
-
-            messages.push(OpenAIMessage {
-                role: final_role.to_string(),
-                content,
-                name,
-                tool_call_id,
-                tool_calls,
             });
-        }
-
-        let tools = tool_defs
-            .into_iter()
-            .map(|t| OpenAITool {
-                tool_type: "function".to_string(),
-                function: OpenAIFunction {
-                    name: t.name.into_owned(),
-                    description: t.description.map(|d| d.into_owned()),
-                    parameters: Value::Object((*t.input_schema).clone()),
-                },
-            })
-            .collect();
 
-        let is_reasoning_model = model.starts_with("o1") || model.starts_with("o3");
-        let (max_tokens, max_completion_tokens) = if is_reasoning_model {
+        let (max_tokens, max_completion_tokens) = if is_reasoning_model(&model) {
             (None, model_options.max_tokens)
         } else {
             (model_options.max_tokens, None)
@@ -504,21 +1078,286 @@ impl<M: OpenAICompatibleModel> OpenAIRequest<M> {
             max_completion_tokens,
             temperature: model_options.temperature,
             top_p: model_options.top_p,
+            stop: model_options.stop.clone(),
+            frequency_penalty: model_options.frequency_penalty,
+            presence_penalty: model_options.presence_penalty,
+            seed: model_options.seed,
+            n: model_options.n,
             stream: if stream { Some(true) } else { None },
+            stream_options: stream.then_some(OpenAIStreamOptions {
+                include_usage: true,
+            }),
             tools,
+            tool_choice,
             provider_options: model_options.provider.clone(),
         }
     }
 }
 
+/// Render a conversation as an example in the OpenAI chat fine-tuning format.
+pub(crate) fn chat_training_example(
+    system: Option<&str>,
+    messages: Vec<Message>,
+    tools: Vec<rmcp::model::Tool>,
+) -> Value {
+    let mut example = serde_json::Map::new();
+    example.insert(
+        "messages".to_string(),
+        serde_json::to_value(convert_messages(system, SystemRole::System, messages))
+            .unwrap_or_default(),
+    );
+    if !tools.is_empty() {
+        example.insert(
+            "tools".to_string(),
+            serde_json::to_value(convert_tools(tools, false)).unwrap_or_default(),
+        );
+    }
+    Value::Object(example)
+}
+
+fn convert_messages(
+    system: Option<&str>,
+    system_role: SystemRole,
+    messages_in: Vec<Message>,
+) -> Vec<OpenAIMessage> {
+    let mut messages = Vec::new();
+
+    if let Some(system) = system {
+        messages.push(OpenAIMessage {
+            role: system_role.as_str().to_string(),
+            content: OpenAIContent::Text(system.to_string()),
+            name: None,
+            tool_call_id: None,
+            tool_calls: Vec::new(),
+        });
+    }
+
+    for msg in messages_in {
+        let role = match msg {
+            Message::User(_) => "user",
+            Message::Assistant(_) => "assistant",
+        };
+
+        let mut content_parts = Vec::new();
+        let mut tool_calls = Vec::new();
+        let mut tool_call_id = None;
+        let name = None;
+
+        for part in msg.parts() {
+            match part {
+                Part::Text { content: t, .. } => {
+                    content_parts.push(OpenAIContentPart::Text { text: t.clone() })
+                }
+                // Chat Completions only takes images inline or by URL; images
+                // uploaded as files are sent as file parts below.
+                Part::Media {
+                    media_type: MediaType::Image,
+                    data,
+                    mime_type,
+                    uri,
+                    ..
+                } if file_reference(data, uri).is_none_or(|uri| uri.starts_with("http")) => {
+                    let anchor_text = part.anchor_media();
+                    content_parts.push(OpenAIContentPart::Text { text: anchor_text });
+                    let url = match file_reference(data, uri) {
+                        Some(url) => url.to_string(),
+                        None => format!("data:{};base64,{}", mime_type, data),
+                    };
+                    content_parts.push(OpenAIContentPart::ImageUrl {
+                        image_url: OpenAIImageUrl { url },
+                    });
+                }
+                // Audio replies are represented by their transcripts.
+                Part::Media {
+                    media_type: MediaType::Audio,
+                    ..
+                } if role == "assistant" => {}
+                Part::Media {
+                    media_type: MediaType::Audio,
+                    data,
+                    mime_type,
+                    uri,
+                    ..
+                } if file_reference(data, uri).is_none() => {
+                    let anchor_text = part.anchor_media();
+                    content_parts.push(OpenAIContentPart::Text { text: anchor_text });
+                    content_parts.push(OpenAIContentPart::InputAudio {
+                        input_audio: OpenAIInputAudio {
+                            data: data.clone(),
+                            format: input_audio_format(mime_type),
+                        },
+                    });
+                }
+                Part::Media { data, uri, .. } => {
+                    let anchor_text = part.anchor_media();
+                    content_parts.push(OpenAIContentPart::Text { text: anchor_text });
+                    let file = match file_reference(data, uri) {
+                        Some(file_id) => OpenAIFileContent {
+                            file_data: None,
+                            file_id: Some(file_id.to_string()),
+                            filename: None,
+                        },
+                        None => OpenAIFileContent {
+                            file_data: Some(data.clone()),
+                            file_id: None,
+                            filename: uri.clone(),
+                        },
+                    };
+                    content_parts.push(OpenAIContentPart::File { file });
+                }
+                Part::FunctionCall {
+                    id: Some(call_id),
+                    name: fn_name,
+                    arguments,
+                    ..
+                } => {
+                    tool_calls.push(OpenAIToolCall {
+                        id: call_id.clone(),
+                        call_type: "function".to_string(),
+                        function: OpenAIFunctionCall {
+                            name: fn_name.clone(),
+                            arguments: arguments.to_string(),
+                        },
+                    });
+                }
+                Part::FunctionResponse {
+                    id: Some(call_id),
+                    response,
+                    parts,
+                    ..
+                } => {
+                    tool_call_id = Some(call_id.clone());
+                    content_parts.push(OpenAIContentPart::Text {
+                        text: function_output_text(response, parts),
+                    });
+                }
+                _ => {}
+            }
+        }
+
+        let final_role = if tool_call_id.is_some() { "tool" } else { role };
+
+        let content = if content_parts.len() == 1 {
+            if let OpenAIContentPart::Text { text } = &content_parts[0] {
+                OpenAIContent::Text(text.clone())
+            } else {
+                OpenAIContent::Parts(content_parts)
+            }
+        } else if !content_parts.is_empty() {
+            OpenAIContent::Parts(content_parts)
+        } else {
+            OpenAIContent::Text(String::new())
+        };
+
+        messages.push(OpenAIMessage {
+            role: final_role.to_string(),
+            content,
+            name,
+            tool_call_id,
+            tool_calls,
+        });
+    }
+
+    messages
+}
+
+/// Render a tool result as text. Media parts are referenced by their anchors only.
+pub(crate) fn function_output_text(response: &Value, parts: &[Part]) -> String {
+    let mut content_str = String::new();
+
+    if response != &serde_json::json!({}) {
+        content_str.push_str(&response.to_string());
+    }
+
+    for part in parts {
+        if let Part::Media {
+            media_type,
+            mime_type,
+            ..
+        } = part
+        {
+            let anchor_text = part.anchor_media();
+            content_str.push_str(&format!("\n{}", anchor_text));
+
+            match media_type {
+                MediaType::Image => content_str.push_str("\n[Image Content]"),
+                _ => content_str.push_str(&format!("\n[File: {}]", mime_type)),
+            }
+        }
+    }
+
+    content_str
+}
+
+fn convert_tools(tool_defs: Vec<rmcp::model::Tool>, strict: bool) -> Vec<OpenAITool> {
+    canonical_tools(tool_defs)
+        .into_iter()
+        .map(|t| {
+            let strict_parameters = strict.then(|| strict_tool_parameters(&t)).flatten();
+            OpenAITool {
+                tool_type: "function".to_string(),
+                function: OpenAIFunction {
+                    strict: strict_parameters.is_some().then_some(true),
+                    parameters: strict_parameters
+                        .unwrap_or_else(|| Value::Object((*t.input_schema).clone())),
+                    name: t.name.into_owned(),
+                    description: t.description.map(|d| d.into_owned()),
+                },
+            }
+        })
+        .collect()
+}
+
 // --- Response Types ---
 
 #[derive(Debug, Deserialize)]
 #[allow(dead_code)]
 struct OpenAIResponse {
     id: String,
+    model: Option<String>,
     choices: Vec<OpenAIChoice>,
     usage: Option<OpenAIUsage>,
+    #[serde(flatten)]
+    sources: OpenAISources,
+}
+
+/// Pages searched for a response, sent by Perplexity with the response and with
+/// every chunk of a stream.
+#[derive(Debug, Default, Deserialize)]
+struct OpenAISources {
+    citations: Option<Vec<String>>,
+    search_results: Option<Vec<OpenAISearchResult>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenAISearchResult {
+    url: String,
+    title: Option<String>,
+}
+
+impl OpenAISources {
+    /// Citations of the pages, without offsets since the text refers to them by
+    /// number, e.g. `[1]`.
+    fn citations(self) -> Vec<Citation> {
+        match (self.search_results, self.citations) {
+            (Some(results), _) if !results.is_empty() => results
+                .into_iter()
+                .map(|result| Citation {
+                    url: Some(result.url),
+                    title: result.title,
+                    ..Default::default()
+                })
+                .collect(),
+            (_, Some(urls)) => urls
+                .into_iter()
+                .map(|url| Citation {
+                    url: Some(url),
+                    ..Default::default()
+                })
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -527,11 +1366,25 @@ struct OpenAIChoice {
     finish_reason: Option<String>,
 }
 
+#[derive(Debug, Deserialize)]
+struct OpenAIResponseAudio {
+    /// Base64-encoded audio.
+    data: String,
+    #[serde(default)]
+    transcript: String,
+}
+
 #[derive(Debug, Deserialize)]
 #[allow(dead_code)]
 struct OpenAIResponseMessage {
     role: String,
     content: Option<String>,
+    /// Reasoning text, as sent by DeepSeek and vLLM.
+    reasoning_content: Option<String>,
+    /// Reasoning text, as sent by Groq and OpenRouter.
+    reasoning: Option<String>,
+    /// Spoken reply of audio models.
+    audio: Option<OpenAIResponseAudio>,
     tool_calls: Option<Vec<OpenAIToolCall>>,
 }
 
@@ -539,6 +1392,32 @@ struct OpenAIResponseMessage {
 struct OpenAIUsage {
     prompt_tokens: u32,
     completion_tokens: u32,
+    prompt_tokens_details: Option<OpenAIPromptTokensDetails>,
+    completion_tokens_details: Option<OpenAICompletionTokensDetails>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenAIPromptTokensDetails {
+    cached_tokens: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenAICompletionTokensDetails {
+    reasoning_tokens: Option<u32>,
+}
+
+impl From<OpenAIUsage> for Usage {
+    fn from(usage: OpenAIUsage) -> Self {
+        Usage {
+            prompt_tokens: Some(usage.prompt_tokens),
+            completion_tokens: Some(usage.completion_tokens),
+            cached_tokens: usage.prompt_tokens_details.and_then(|d| d.cached_tokens),
+            cache_creation_tokens: None,
+            reasoning_tokens: usage
+                .completion_tokens_details
+                .and_then(|d| d.reasoning_tokens),
+        }
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -555,54 +1434,170 @@ struct OpenAIError {
 
 impl From<OpenAIResponse> for Response {
     fn from(resp: OpenAIResponse) -> Self {
-        let mut parts = Vec::new();
-        let mut finish_reason = FinishReason::Stop;
-
-        if let Some(choice) = resp.choices.first() {
-            if let Some(content) = &choice.message.content {
-                parts.push(Part::Text {
-                    content: content.clone(),
-                    finished: true,
-                });
-            }
-            if let Some(tool_calls) = &choice.message.tool_calls {
-                for tool_call in tool_calls {
-                    parts.push(Part::FunctionCall {
-                        id: Some(tool_call.id.clone()),
-                        name: tool_call.function.name.clone(),
-                        arguments: serde_json::from_str(&tool_call.function.arguments)
-                            .unwrap_or(Value::Null),
-                        signature: None,
+        let usage = resp.usage.map(Usage::from).unwrap_or_default();
+        let mut choices = resp.choices.iter().map(convert_choice);
+        let (mut message, finish) = choices
+            .next()
+            .unwrap_or((Message::Assistant(Vec::new()), FinishReason::Stop));
+        message
+            .parts_mut()
+            .extend(
+                resp.sources
+                    .citations()
+                    .into_iter()
+                    .map(|citation| Part::Citation {
+                        citation,
                         finished: true,
-                    });
-                }
-            }
-
-            if let Some(reason) = &choice.finish_reason {
-                finish_reason = match reason.as_str() {
-                    "stop" => FinishReason::Stop,
-                    "length" => FinishReason::OutputTokens,
-                    "tool_calls" => FinishReason::ToolCalls,
-                    "content_filter" => FinishReason::ContentFilter,
-                    _ => FinishReason::Stop,
-                };
-            }
-        }
-
-        let usage = resp
-            .usage
-            .map(|u| Usage {
-                prompt_tokens: Some(u.prompt_tokens),
-                completion_tokens: Some(u.completion_tokens),
-            })
-            .unwrap_or_default();
+                    }),
+            );
 
         Response {
-            data: vec![Message::Assistant(parts)],
+            data: vec![message],
             usage,
-            finish: finish_reason,
+            finish,
+            alternatives: choices.map(|(message, _)| vec![message]).collect(),
+            model: resp.model,
+            iterations: Vec::new(),
+        }
+    }
+}
+
+fn convert_choice(choice: &OpenAIChoice) -> (Message, FinishReason) {
+    let mut parts = Vec::new();
+    let reasoning = choice
+        .message
+        .reasoning_content
+        .as_ref()
+        .or(choice.message.reasoning.as_ref())
+        .filter(|text| !text.is_empty());
+    if let Some(reasoning) = reasoning {
+        parts.push(Part::Reasoning {
+            content: reasoning.clone(),
+            summary: None,
+            signature: None,
+            finished: true,
+        });
+    }
+    if let Some(content) = &choice.message.content {
+        parts.push(Part::Text {
+            content: content.clone(),
+            finished: true,
+        });
+    }
+    if let Some(audio) = &choice.message.audio {
+        if choice.message.content.is_none() {
+            parts.push(Part::Text {
+                content: audio.transcript.clone(),
+                finished: true,
+            });
+        }
+        // The reply does not state its format, so it is read from the data.
+        let mut media = Part::Media {
+            media_type: MediaType::Audio,
+            data: audio.data.clone(),
+            mime_type: "audio/wav".to_string(),
+            uri: None,
+            finished: true,
+        };
+        media.correct_media_type();
+        parts.push(media);
+    }
+    if let Some(tool_calls) = &choice.message.tool_calls {
+        for tool_call in tool_calls {
+            let (arguments, raw_arguments) = finalize_arguments(&tool_call.function.arguments);
+            parts.push(Part::FunctionCall {
+                id: Some(match tool_call.id.as_str() {
+                    "" => synthesize_call_id(),
+                    id => id.to_string(),
+                }),
+                name: tool_call.function.name.clone(),
+                arguments,
+                signature: None,
+                raw_arguments,
+                finished: true,
+            });
         }
     }
+
+    let finish = match choice.finish_reason.as_deref() {
+        Some("length") => FinishReason::OutputTokens,
+        Some("tool_calls") => FinishReason::ToolCalls,
+        Some("content_filter") => FinishReason::ContentFilter,
+        _ => FinishReason::Stop,
+    };
+    (Message::Assistant(parts), finish)
+}
+
+// --- Embedding Types ---
+
+#[derive(Debug, Serialize)]
+struct OpenAIEmbeddingRequest {
+    model: String,
+    input: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenAIEmbeddingResponse {
+    data: Vec<OpenAIEmbeddingData>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenAIEmbeddingData {
+    index: usize,
+    embedding: Vec<f32>,
+}
+
+/// A file uploaded to `/files`.
+#[derive(Debug, Deserialize)]
+struct OpenAIFile {
+    id: String,
+    filename: String,
+    bytes: u64,
+}
+
+// --- Image Types ---
+
+#[skip_serializing_none]
+#[derive(Debug, Serialize)]
+struct OpenAIImageRequest {
+    model: String,
+    prompt: String,
+    n: Option<u32>,
+    size: Option<String>,
+    quality: Option<String>,
+    output_format: Option<String>,
+    response_format: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenAIImageResponse {
+    data: Vec<OpenAIImage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenAIImage {
+    b64_json: Option<String>,
+    url: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct OpenAIModerationRequest {
+    model: String,
+    input: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenAIModerationResponse {
+    results: Vec<OpenAIModerationResult>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenAIModerationResult {
+    flagged: bool,
+    #[serde(default)]
+    categories: HashMap<String, Option<bool>>,
+    #[serde(default)]
+    category_scores: HashMap<String, f64>,
 }
 
 // --- Stream Types ---
@@ -611,12 +1606,17 @@ impl From<OpenAIResponse> for Response {
 #[allow(dead_code)]
 struct OpenAIStreamChunk {
     id: String,
+    model: Option<String>,
     choices: Vec<OpenAIStreamChoice>,
     usage: Option<OpenAIUsage>,
+    #[serde(flatten)]
+    sources: OpenAISources,
 }
 
 #[derive(Debug, Deserialize)]
 struct OpenAIStreamChoice {
+    #[serde(default)]
+    index: u32,
     delta: Option<OpenAIDelta>,
     finish_reason: Option<String>,
 }
@@ -624,12 +1624,14 @@ struct OpenAIStreamChoice {
 #[derive(Debug, Deserialize)]
 struct OpenAIDelta {
     content: Option<String>,
+    reasoning_content: Option<String>,
+    reasoning: Option<String>,
     tool_calls: Option<Vec<OpenAIStreamToolCall>>,
 }
 
 #[derive(Debug, Deserialize)]
 struct OpenAIStreamToolCall {
-    index: u32,
+    index: Option<u32>,
     id: Option<String>,
     function: Option<OpenAIStreamFunction>,
 }
@@ -639,3 +1641,700 @@ struct OpenAIStreamFunction {
     name: Option<String>,
     arguments: Option<String>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::options::ReasoningVisibility;
+    use crate::providers::deepseek::DeepSeekModel;
+    use crate::providers::ollama::OllamaModel;
+    use crate::providers::openai::OpenAIModel;
+    use serde_json::json;
+
+    fn request_json<M: OpenAICompatibleModel>(model: &str) -> Value {
+        let mut options = ModelOptions::<M>::new(model);
+        options.system = Some("Be brief.".to_string());
+        options.max_tokens = Some(100);
+        let messages = vec![Message::User(vec![Part::Text {
+            content: "Hi".to_string(),
+            finished: true,
+        }])];
+        let request = OpenAIRequest::new(messages, &options, model.to_string(), vec![], false);
+        serde_json::to_value(request).unwrap()
+    }
+
+    #[test]
+    fn test_system_role_per_model_family() {
+        let cases = [
+            (request_json::<OpenAIModel>("gpt-4o"), "system"),
+            (request_json::<OpenAIModel>("o1"), "developer"),
+            (request_json::<OpenAIModel>("o3-mini"), "developer"),
+            (request_json::<OpenAIModel>("o4-mini"), "developer"),
+            (request_json::<OpenAIModel>("gpt-5"), "developer"),
+            (request_json::<OpenAIModel>("gpt-5-mini"), "developer"),
+            (request_json::<DeepSeekModel>("o1-compatible"), "system"),
+        ];
+
+        for (json, role) in cases {
+            assert_eq!(json["messages"][0]["role"], role, "request: {}", json);
+            assert_eq!(json["messages"][0]["content"], "Be brief.");
+            assert_eq!(json["messages"][1]["role"], "user");
+        }
+    }
+
+    #[test]
+    fn test_reasoning_models_use_max_completion_tokens() {
+        let chat = request_json::<OpenAIModel>("gpt-4o");
+        assert_eq!(chat["max_tokens"], 100);
+        assert!(chat.get("max_completion_tokens").is_none());
+
+        for model in ["o3-mini", "gpt-5", "gpt-5-nano"] {
+            let reasoning = request_json::<OpenAIModel>(model);
+            assert_eq!(reasoning["max_completion_tokens"], 100);
+            assert!(reasoning.get("max_tokens").is_none());
+        }
+    }
+
+    #[test]
+    fn test_system_role_override() {
+        let messages = || {
+            vec![Message::User(vec![Part::Text {
+                content: "Hi".to_string(),
+                finished: true,
+            }])]
+        };
+        let role = |model: &str, system_role| {
+            let mut options = ModelOptions::<OpenAIModel>::new(model);
+            options.system = Some("Be brief.".to_string());
+            options.system_role = Some(system_role);
+            let request =
+                OpenAIRequest::new(messages(), &options, model.to_string(), vec![], false);
+            serde_json::to_value(request).unwrap()["messages"][0]["role"].clone()
+        };
+        assert_eq!(role("gpt-4o", SystemRole::Developer), "developer");
+        assert_eq!(role("o3-mini", SystemRole::System), "system");
+    }
+
+    #[test]
+    fn test_parse_model_list() {
+        let body = json!({
+            "object": "list",
+            "data": [
+                { "id": "gpt-4o", "object": "model", "owned_by": "openai" },
+                {
+                    "id": "llama-3.3-70b-versatile",
+                    "context_window": 131072
+                },
+                {
+                    "id": "openai/gpt-4o",
+                    "name": "OpenAI: GPT-4o",
+                    "context_length": 128000,
+                    "architecture": { "input_modalities": ["text", "image", "file"] },
+                    "top_provider": { "max_completion_tokens": 16384 }
+                },
+                {
+                    "id": "mistral-small-2402",
+                    "max_context_length": 32768,
+                    "capabilities": { "vision": false },
+                    "deprecation": "2025-06-01T00:00:00Z"
+                }
+            ]
+        });
+
+        let models = parse_model_list(&body);
+        assert_eq!(models.len(), 4);
+        assert_eq!(models[0].id, "gpt-4o");
+        assert_eq!(models[0].context_window, None);
+        assert_eq!(models[1].context_window, Some(131072));
+        assert_eq!(models[2].display_name.as_deref(), Some("OpenAI: GPT-4o"));
+        assert_eq!(models[2].max_output_tokens, Some(16384));
+        assert_eq!(
+            models[2].input_modalities,
+            vec![Modality::Text, Modality::Image, Modality::Document]
+        );
+        assert_eq!(models[3].input_modalities, vec![Modality::Text]);
+        assert_eq!(
+            models[3].deprecation_date.as_deref(),
+            Some("2025-06-01T00:00:00Z")
+        );
+
+        let ollama = parse_model_list(&json!({
+            "models": [{ "name": "llama3:latest", "model": "llama3:latest", "size": 1 }]
+        }));
+        assert_eq!(ollama[0].id, "llama3:latest");
+        assert_eq!(ollama[0].display_name, None);
+        assert_eq!(
+            OllamaModel::models_url("http://localhost:11434/v1/"),
+            "http://localhost:11434/api/tags"
+        );
+        assert_eq!(
+            OpenAIModel::models_url("https://api.openai.com/v1"),
+            "https://api.openai.com/v1/models"
+        );
+    }
+
+    /// Accumulate the first choice of a captured SSE body.
+    fn replay(body: &str) -> Response {
+        let events = crate::testing::strategies::decode_sse(&[body.as_bytes().to_vec()]);
+        let deltas = OpenAIStream::event_deltas(futures::stream::iter(events.into_iter().map(Ok)));
+        let deltas: Vec<_> = futures::executor::block_on(deltas.collect());
+        let mut acc = crate::stream::ChoiceAccumulator::new();
+        deltas
+            .into_iter()
+            .for_each(|delta| acc.apply(delta.unwrap()));
+        acc.into_responses().remove(0)
+    }
+
+    fn calls(response: &Response) -> Vec<(String, String, Value)> {
+        response.data[0]
+            .parts()
+            .iter()
+            .filter_map(|part| match part {
+                Part::FunctionCall {
+                    id: Some(id),
+                    name,
+                    arguments,
+                    ..
+                } => Some((id.clone(), name.clone(), arguments.clone())),
+                _ => None,
+            })
+            .collect()
+    }
+
+    const GROQ_STREAM: &str = r#"data: {"id":"chatcmpl-1","object":"chat.completion.chunk","model":"llama-3.3-70b-versatile","choices":[{"index":0,"delta":{"role":"assistant","content":null},"finish_reason":null}]}
+
+data: {"id":"chatcmpl-1","object":"chat.completion.chunk","model":"llama-3.3-70b-versatile","choices":[{"index":0,"delta":{"tool_calls":[{"id":"call_a1","type":"function","function":{"name":"get_weather","arguments":"{\"city\":\"Paris\"}"},"index":0}]},"finish_reason":null}]}
+
+data: {"id":"chatcmpl-1","object":"chat.completion.chunk","model":"llama-3.3-70b-versatile","choices":[{"index":0,"delta":{"tool_calls":[{"id":"call_b2","type":"function","function":{"name":"get_weather","arguments":"{\"city\":\"Tokyo\"}"},"index":1}]},"finish_reason":null}]}
+
+data: {"id":"chatcmpl-1","object":"chat.completion.chunk","model":"llama-3.3-70b-versatile","choices":[{"index":0,"delta":{},"finish_reason":"tool_calls"}],"x_groq":{"usage":{"prompt_tokens":12,"completion_tokens":30}}}
+
+data: [DONE]
+"#;
+
+    const OLLAMA_STREAM: &str = r#"data: {"id":"chatcmpl-7","object":"chat.completion.chunk","model":"qwen2.5:7b","choices":[{"index":0,"delta":{"role":"assistant","content":"","tool_calls":[{"id":"call_x9","index":0,"type":"function","function":{"name":"get_weather","arguments":"{\"city\":\"Paris\"}"}}]},"finish_reason":null}]}
+
+data: {"id":"chatcmpl-7","object":"chat.completion.chunk","model":"qwen2.5:7b","choices":[{"index":0,"delta":{"role":"assistant","content":"","tool_calls":[{"id":"call_y3","index":0,"type":"function","function":{"name":"get_weather","arguments":"{\"city\":\"Tokyo\"}"}}]},"finish_reason":null}]}
+
+data: {"id":"chatcmpl-7","object":"chat.completion.chunk","model":"qwen2.5:7b","choices":[{"index":0,"delta":{"role":"assistant","content":""},"finish_reason":"tool_calls"}]}
+
+data: [DONE]
+"#;
+
+    const VLLM_STREAM: &str = r#"data: {"id":"chatcmpl-3","object":"chat.completion.chunk","model":"meta-llama/Llama-3.1-8B-Instruct","choices":[{"index":0,"delta":{"role":"assistant","content":null},"finish_reason":null}]}
+
+data: {"id":"chatcmpl-3","object":"chat.completion.chunk","model":"meta-llama/Llama-3.1-8B-Instruct","choices":[{"index":0,"delta":{"tool_calls":[{"index":0,"type":"function","function":{"name":"get_weather","arguments":""}}]},"finish_reason":null}]}
+
+data: {"id":"chatcmpl-3","object":"chat.completion.chunk","model":"meta-llama/Llama-3.1-8B-Instruct","choices":[{"index":0,"delta":{"tool_calls":[{"index":0,"function":{"arguments":"{\"city\": "}}]},"finish_reason":null}]}
+
+data: {"id":"chatcmpl-3","object":"chat.completion.chunk","model":"meta-llama/Llama-3.1-8B-Instruct","choices":[{"index":0,"delta":{"tool_calls":[{"index":0,"function":{"arguments":"\"Paris\"}"}}]},"finish_reason":null}]}
+
+data: {"id":"chatcmpl-3","object":"chat.completion.chunk","model":"meta-llama/Llama-3.1-8B-Instruct","choices":[{"index":0,"delta":{"tool_calls":[{"index":0,"type":"function","function":{"name":"get_weather","arguments":"{\"city\": \"Tokyo\"}"}}]},"finish_reason":null}]}
+
+data: {"id":"chatcmpl-3","object":"chat.completion.chunk","model":"meta-llama/Llama-3.1-8B-Instruct","choices":[{"index":0,"delta":{"tool_calls":[{"function":{"name":"get_time","arguments":"{}"}}]},"finish_reason":"tool_calls"}]}
+
+data: [DONE]
+"#;
+
+    #[test]
+    fn test_stream_tool_calls_from_compatible_servers() {
+        let weather = |city: &str| json!({ "city": city });
+
+        assert_eq!(
+            replay(GROQ_STREAM).model.as_deref(),
+            Some("llama-3.3-70b-versatile")
+        );
+        let groq = calls(&replay(GROQ_STREAM));
+        assert_eq!(
+            groq,
+            vec![
+                (
+                    "call_a1".to_string(),
+                    "get_weather".to_string(),
+                    weather("Paris")
+                ),
+                (
+                    "call_b2".to_string(),
+                    "get_weather".to_string(),
+                    weather("Tokyo")
+                ),
+            ]
+        );
+
+        // Ollama sends every call at index 0, told apart by their ids.
+        let ollama = calls(&replay(OLLAMA_STREAM));
+        assert_eq!(
+            ollama,
+            vec![
+                (
+                    "call_x9".to_string(),
+                    "get_weather".to_string(),
+                    weather("Paris")
+                ),
+                (
+                    "call_y3".to_string(),
+                    "get_weather".to_string(),
+                    weather("Tokyo")
+                ),
+            ]
+        );
+
+        // vLLM omits ids, reuses index 0 and may drop the index entirely.
+        let vllm = calls(&replay(VLLM_STREAM));
+        let names: Vec<_> = vllm.iter().map(|(_, name, _)| name.as_str()).collect();
+        assert_eq!(names, vec!["get_weather", "get_weather", "get_time"]);
+        assert_eq!(vllm[0].2, weather("Paris"));
+        assert_eq!(vllm[1].2, weather("Tokyo"));
+        assert_eq!(vllm[2].2, json!({}));
+        assert!(vllm.iter().all(|(id, _, _)| id.starts_with("call_")));
+        assert_ne!(vllm[0].0, vllm[1].0);
+    }
+
+    const PERPLEXITY_STREAM: &str = r#"data: {"id":"1","model":"sonar","citations":["https://a.example"],"choices":[{"index":0,"delta":{"role":"assistant","content":"Rust 1.90"},"finish_reason":null}]}
+
+data: {"id":"1","model":"sonar","citations":["https://a.example","https://b.example"],"search_results":[{"title":"A","url":"https://a.example","date":null},{"title":"B","url":"https://b.example"}],"choices":[{"index":0,"delta":{"content":" is out [1][2]."},"finish_reason":"stop"}]}
+
+"#;
+
+    #[test]
+    fn test_perplexity_citations() {
+        let citations = |response: &Response| -> Vec<Citation> {
+            response.data[0]
+                .parts()
+                .iter()
+                .filter_map(|part| match part {
+                    Part::Citation { citation, .. } => Some(citation.clone()),
+                    _ => None,
+                })
+                .collect()
+        };
+        let streamed = replay(PERPLEXITY_STREAM);
+        assert_eq!(
+            streamed.data[0].content().as_deref(),
+            Some("Rust 1.90 is out [1][2].")
+        );
+        assert_eq!(
+            citations(&streamed),
+            vec![
+                Citation {
+                    url: Some("https://a.example".to_string()),
+                    title: Some("A".to_string()),
+                    ..Default::default()
+                },
+                Citation {
+                    url: Some("https://b.example".to_string()),
+                    title: Some("B".to_string()),
+                    ..Default::default()
+                },
+            ]
+        );
+
+        let response: OpenAIResponse = serde_json::from_value(json!({
+            "id": "1",
+            "model": "sonar",
+            "citations": ["https://a.example"],
+            "choices": [{
+                "index": 0,
+                "message": { "role": "assistant", "content": "Rust 1.90 is out [1]." },
+                "finish_reason": "stop"
+            }]
+        }))
+        .unwrap();
+        let response = Response::from(response);
+        assert_eq!(response.data[0].parts().len(), 2);
+        assert_eq!(
+            citations(&response)[0].url.as_deref(),
+            Some("https://a.example")
+        );
+    }
+
+    const DEEPSEEK_STREAM: &str = r#"data: {"id":"1","object":"chat.completion.chunk","model":"deepseek-reasoner","choices":[{"index":0,"delta":{"role":"assistant","content":null,"reasoning_content":""},"finish_reason":null}]}
+
+data: {"id":"1","object":"chat.completion.chunk","model":"deepseek-reasoner","choices":[{"index":0,"delta":{"content":null,"reasoning_content":"9.11 has more"},"finish_reason":null}]}
+
+data: {"id":"1","object":"chat.completion.chunk","model":"deepseek-reasoner","choices":[{"index":0,"delta":{"content":null,"reasoning_content":" digits, but 9.9 is larger."},"finish_reason":null}]}
+
+data: {"id":"1","object":"chat.completion.chunk","model":"deepseek-reasoner","choices":[{"index":0,"delta":{"content":"9.9","reasoning_content":null},"finish_reason":null}]}
+
+data: {"id":"1","object":"chat.completion.chunk","model":"deepseek-reasoner","choices":[{"index":0,"delta":{"content":"","reasoning_content":null},"finish_reason":"stop"}]}
+
+data: [DONE]
+"#;
+
+    #[test]
+    fn test_error_chunk() {
+        let events = vec![
+            r#"{"id":"1","choices":[{"index":0,"delta":{"content":"Hel"}}]}"#.to_string(),
+            r#"{"id":"1","error":{"code":502,"message":"Provider returned error"},"choices":[{"index":0,"delta":{"content":""},"finish_reason":"error"}]}"#.to_string(),
+        ];
+        let deltas = OpenAIStream::event_deltas(futures::stream::iter(events.into_iter().map(Ok)));
+        let deltas: Vec<_> = futures::executor::block_on(deltas.collect());
+        assert!(deltas[0].is_ok());
+        assert!(matches!(
+            deltas.last(),
+            Some(Err(ClientError::ProviderError(m))) if m == "Stream error (502): Provider returned error"
+        ));
+    }
+
+    #[test]
+    fn test_reasoning_content() {
+        let response = replay(DEEPSEEK_STREAM);
+        assert_eq!(
+            response.data[0].parts(),
+            &vec![
+                Part::Reasoning {
+                    content: "9.11 has more digits, but 9.9 is larger.".to_string(),
+                    summary: None,
+                    signature: None,
+                    finished: true,
+                },
+                Part::Text {
+                    content: "9.9".to_string(),
+                    finished: true,
+                },
+            ]
+        );
+
+        // Groq and OpenRouter name the field `reasoning`.
+        let response: OpenAIResponse = serde_json::from_value(json!({
+            "id": "chatcmpl-4",
+            "choices": [{
+                "message": {
+                    "role": "assistant",
+                    "content": "9.9",
+                    "reasoning": "Compare the decimals."
+                },
+                "finish_reason": "stop"
+            }]
+        }))
+        .unwrap();
+        let response = Response::from(response);
+        assert!(matches!(
+            response.data[0].parts()[..],
+            [Part::Reasoning { ref content, finished: true, .. }, Part::Text { .. }]
+                if content == "Compare the decimals."
+        ));
+    }
+
+    #[test]
+    fn test_missing_tool_call_id_is_synthesized() {
+        let response: OpenAIResponse = serde_json::from_value(json!({
+            "id": "chatcmpl-9",
+            "choices": [{
+                "message": {
+                    "role": "assistant",
+                    "tool_calls": [{
+                        "type": "function",
+                        "function": { "name": "get_time", "arguments": "{}" }
+                    }]
+                },
+                "finish_reason": "tool_calls"
+            }]
+        }))
+        .unwrap();
+        let response = Response::from(response);
+        let [(id, name, _)] = <[_; 1]>::try_from(calls(&response)).unwrap();
+        assert!(id.starts_with("call_") && id.len() > 5);
+        assert_eq!(name, "get_time");
+    }
+
+    #[test]
+    fn test_multiple_choices() {
+        let choice = |text: &str| {
+            json!({
+                "message": { "role": "assistant", "content": text },
+                "finish_reason": "stop"
+            })
+        };
+        let response: OpenAIResponse = serde_json::from_value(json!({
+            "id": "chatcmpl-2",
+            "model": "gpt-4o-2024-11-20",
+            "choices": [choice("A"), choice("B"), choice("C")]
+        }))
+        .unwrap();
+        let response = Response::from(response);
+
+        let texts: Vec<&str> = response
+            .candidates()
+            .map(|messages| match &messages[0].parts()[0] {
+                Part::Text { content, .. } => content.as_str(),
+                part => panic!("unexpected part {:?}", part),
+            })
+            .collect();
+        assert_eq!(texts, vec!["A", "B", "C"]);
+        assert_eq!(response.alternatives.len(), 2);
+        assert_eq!(response.model.as_deref(), Some("gpt-4o-2024-11-20"));
+
+        let mut options = ModelOptions::<OpenAIModel>::new("gpt-4o");
+        options.n = Some(3);
+        let request = OpenAIRequest::new(vec![], &options, "gpt-4o".to_string(), vec![], false);
+        assert_eq!(serde_json::to_value(request).unwrap()["n"], 3);
+    }
+
+    #[test]
+    fn test_file_references() {
+        let file = FileRef {
+            id: "file-abc".to_string(),
+            uri: "file-abc".to_string(),
+            mime_type: "application/pdf".to_string(),
+            name: Some("report.pdf".to_string()),
+            size_bytes: None,
+        };
+        let options = ModelOptions::<OpenAIModel>::new("gpt-4o");
+        let messages = vec![Message::User(vec![file.to_part()])];
+        let request = OpenAIRequest::new(messages, &options, "gpt-4o".to_string(), vec![], false);
+        let json = serde_json::to_value(request).unwrap();
+
+        assert_eq!(
+            json["messages"][0]["content"][1],
+            json!({ "type": "file", "file": { "file_id": "file-abc" } })
+        );
+    }
+
+    #[test]
+    fn test_audio() {
+        const WAV: &str = "UklGRiQAAABXQVZFZm10IBAAAAABAAEA";
+        let audio = |data: &str, mime_type: &str| Part::Media {
+            media_type: MediaType::Audio,
+            data: data.to_string(),
+            mime_type: mime_type.to_string(),
+            uri: None,
+            finished: true,
+        };
+        let options = ModelOptions::<OpenAIModel>::new("gpt-4o-audio-preview");
+        let messages = vec![Message::User(vec![
+            audio(WAV, "audio/x-wav"),
+            audio("T2dnUwACAAAAAAAAAAA=", "audio/ogg"),
+        ])];
+        let request = OpenAIRequest::new(messages, &options, options.model.clone(), vec![], false);
+        let content = &serde_json::to_value(request).unwrap()["messages"][0]["content"];
+        assert_eq!(
+            content[1],
+            json!({ "type": "input_audio", "input_audio": { "data": WAV, "format": "wav" } })
+        );
+        assert_eq!(content[3]["input_audio"]["format"], "ogg");
+
+        let mut options = options;
+        options.provider = OpenAIModel::default().with_audio_output("alloy", "wav");
+        let request = OpenAIRequest::new(vec![], &options, options.model.clone(), vec![], false);
+        let json = serde_json::to_value(request).unwrap();
+        assert_eq!(json["modalities"], json!(["text", "audio"]));
+        assert_eq!(json["audio"], json!({ "voice": "alloy", "format": "wav" }));
+        assert!(request_json::<OpenAIModel>("gpt-4o").get("audio").is_none());
+
+        let response: OpenAIResponse = serde_json::from_value(json!({
+            "id": "chatcmpl-5",
+            "choices": [{
+                "message": {
+                    "role": "assistant",
+                    "content": null,
+                    "audio": { "id": "audio_1", "data": WAV, "transcript": "Hello!", "expires_at": 0 }
+                },
+                "finish_reason": "stop"
+            }]
+        }))
+        .unwrap();
+        let response = Response::from(response);
+        assert_eq!(
+            response.data[0].parts(),
+            &vec![
+                Part::Text {
+                    content: "Hello!".to_string(),
+                    finished: true,
+                },
+                audio(WAV, "audio/wav"),
+            ]
+        );
+
+        // Spoken replies are sent back as their transcripts.
+        let messages = convert_messages(None, SystemRole::System, response.data);
+        assert_eq!(
+            serde_json::to_value(&messages[0].content).unwrap(),
+            "Hello!"
+        );
+    }
+
+    #[test]
+    fn test_sampling_options() {
+        let mut options = ModelOptions::<OpenAIModel>::new("gpt-4o");
+        options.stop = Some(vec!["END".to_string()]);
+        options.frequency_penalty = Some(0.5);
+        options.seed = Some(7);
+
+        let request = OpenAIRequest::new(vec![], &options, "gpt-4o".to_string(), vec![], false);
+        let body = serde_json::to_value(request).unwrap();
+        assert_eq!(body["stop"], json!(["END"]));
+        assert_eq!(body["frequency_penalty"], 0.5);
+        assert_eq!(body["seed"], 7);
+        assert!(body.get("presence_penalty").is_none());
+    }
+
+    #[test]
+    fn test_strict_tools() {
+        let schema = |value: Value| value.as_object().unwrap().clone();
+        let tools = vec![
+            rmcp::model::Tool::new(
+                "search",
+                "Search",
+                schema(json!({
+                    "type": "object",
+                    "properties": { "query": { "type": "string" }, "limit": { "type": "integer" } },
+                    "required": ["query"]
+                })),
+            ),
+            rmcp::model::Tool::new(
+                "tag",
+                "Tag",
+                schema(json!({ "type": "object", "additionalProperties": { "type": "string" } })),
+            ),
+        ];
+        let mut options = ModelOptions::<OpenAIModel>::new("gpt-4o");
+        options.strict_tools = Some(true);
+
+        let request = OpenAIRequest::new(vec![], &options, "gpt-4o".to_string(), tools, false);
+        let body = serde_json::to_value(request).unwrap();
+        let search = &body["tools"][0]["function"];
+        assert_eq!(search["strict"], true);
+        assert_eq!(search["parameters"]["required"], json!(["limit", "query"]));
+        assert_eq!(
+            search["parameters"]["properties"]["limit"]["type"],
+            json!(["integer", "null"])
+        );
+        // Maps have no strict equivalent, so the tool is sent as is.
+        assert!(body["tools"][1]["function"].get("strict").is_none());
+    }
+
+    #[test]
+    fn test_tool_choice() {
+        let tool = || {
+            let schema = json!({ "type": "object" }).as_object().unwrap().clone();
+            vec![rmcp::model::Tool::new("extract", "Extract fields", schema)]
+        };
+        let mut options = ModelOptions::<OpenAIModel>::new("gpt-4o");
+        options.tool_choice = Some(ToolChoice::Specific("extract".to_string()));
+
+        let request = OpenAIRequest::new(vec![], &options, "gpt-4o".to_string(), tool(), false);
+        assert_eq!(
+            serde_json::to_value(request).unwrap()["tool_choice"],
+            json!({ "type": "function", "function": { "name": "extract" } })
+        );
+
+        options.tool_choice = Some(ToolChoice::Required);
+        let request = OpenAIRequest::new(vec![], &options, "gpt-4o".to_string(), tool(), false);
+        assert_eq!(
+            serde_json::to_value(request).unwrap()["tool_choice"],
+            "required"
+        );
+
+        // Without tools, OpenAI rejects a tool choice.
+        let request = OpenAIRequest::new(vec![], &options, "gpt-4o".to_string(), vec![], false);
+        assert!(serde_json::to_value(request)
+            .unwrap()
+            .get("tool_choice")
+            .is_none());
+    }
+
+    #[test]
+    fn test_transcription_response() {
+        let body = json!({
+            "task": "transcribe",
+            "language": "english",
+            "duration": 2.5,
+            "text": "Hello there.",
+            "segments": [
+                { "id": 0, "seek": 0, "start": 0.0, "end": 2.5, "text": " Hello there.", "tokens": [1, 2] }
+            ]
+        });
+        let transcription: Transcription = serde_json::from_value(body).unwrap();
+        assert_eq!(transcription.duration, Some(2.5));
+        assert_eq!(transcription.segments[0].end, 2.5);
+
+        let plain: Transcription =
+            serde_json::from_value(json!({ "text": "Hello there." })).unwrap();
+        assert_eq!(plain.text, "Hello there.");
+        assert!(plain.segments.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_streamed_deltas_hide_reasoning() {
+        use crate::testing::server::{TestResponse, TestServer};
+        use futures::TryStreamExt;
+
+        let server = TestServer::start(|_, _| {
+            TestResponse::new(200, DEEPSEEK_STREAM).with_header("content-type", "text/event-stream")
+        })
+        .await;
+        let mut options = ModelOptions::<DeepSeekModel>::new("deepseek-reasoner");
+        options.reasoning_visibility = Some(ReasoningVisibility::Hidden);
+        let client = OpenAIClient::new(
+            "key".to_string(),
+            server.url().to_string(),
+            options,
+            TransportOptions::default(),
+        );
+
+        let deltas: Vec<StreamDelta> = client
+            .request_stream_deltas(vec![], vec![])
+            .await
+            .unwrap()
+            .try_collect()
+            .await
+            .unwrap();
+
+        assert!(deltas.iter().all(|delta| !matches!(
+            delta,
+            StreamDelta::ReasoningDelta { text, .. } if !text.is_empty()
+        )));
+        assert!(deltas.contains(&StreamDelta::TextDelta {
+            index: 1,
+            text: "9.9".to_string()
+        }));
+    }
+
+    #[tokio::test]
+    async fn test_streamed_run_reports_usage() {
+        use crate::agent::Agent;
+        use crate::cost::CostTracker;
+        use crate::testing::server::{TestResponse, TestServer};
+        use std::sync::Arc;
+
+        // Like OpenAI, the server only reports usage of streams when asked to.
+        let server = TestServer::start(|request, _| {
+            let body: Value = serde_json::from_slice(&request.body).unwrap();
+            let mut stream = r#"data: {"id":"1","choices":[{"index":0,"delta":{"content":"Hi"},"finish_reason":"stop"}]}
+
+"#
+            .to_string();
+            if body["stream_options"]["include_usage"] == json!(true) {
+                stream.push_str(
+                    r#"data: {"id":"1","choices":[],"usage":{"prompt_tokens":9,"completion_tokens":3,"total_tokens":12}}
+
+"#,
+                );
+            }
+            stream.push_str("data: [DONE]\n\n");
+            TestResponse::new(200, stream).with_header("content-type", "text/event-stream")
+        })
+        .await;
+        let client = OpenAIClient::new(
+            "key".to_string(),
+            server.url().to_string(),
+            ModelOptions::<OpenAIModel>::new("gpt-4o"),
+            TransportOptions::default(),
+        );
+        let tracker = Arc::new(CostTracker::new());
+        let agent = Agent::new(client).with_cost_tracker(tracker.clone());
+
+        let snapshots: Vec<Response> = agent
+            .chat_stream(vec![])
+            .map(Result::unwrap)
+            .collect()
+            .await;
+
+        let usage = &snapshots.last().unwrap().usage;
+        assert_eq!(usage.prompt_tokens, Some(9));
+        assert_eq!(usage.completion_tokens, Some(3));
+        assert_eq!(tracker.total().usage.completion_tokens, Some(3));
+        assert_eq!(tracker.total().requests, 1);
+    }
+}
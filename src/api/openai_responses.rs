@@ -0,0 +1,1768 @@
+//! OpenAI Responses API client implementation.
+//!
+//! The Responses API is OpenAI's successor to Chat Completions. It returns
+//! reasoning items for reasoning models, hosts built-in tools and can continue a
+//! conversation stored on the server via `previous_response_id`.
+//!
+//! Reasoning items are returned as [`Part::Reasoning`] with an opaque signature
+//! holding the item id and its encrypted content, so they can be sent back in
+//! later turns without storing the conversation on the server.
+
+use async_trait::async_trait;
+use futures::{Stream, StreamExt};
+use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION, CONTENT_TYPE};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use serde_with::skip_serializing_none;
+use std::collections::{HashMap, HashSet};
+use std::pin::Pin;
+
+use crate::api::openai::{
+    function_output_text, parse_model_list, OpenAIClient, OpenAICompatibleModel,
+};
+use crate::client::{Capabilities, Client, ClientError, ModelInfo, StreamingClient};
+use crate::computer::{ComputerAction, ComputerDisplay, ComputerSafetyCheck};
+use crate::files::file_reference;
+use crate::hosted::{
+    BuiltInTool, Citation, CodeOutput, FileSearchResult, HostedToolCall, SearchSource,
+};
+use crate::http::{
+    add_extra_headers, build_http_client, send_with_retry, RequestBuilderExt, ResponseExt,
+};
+use crate::markdown::{strip_delta_markdown, strip_response_markdown, strip_stream_markdown};
+use crate::model::{correct_media_types, FinishReason, MediaType, Message, Part, Response, Usage};
+use crate::options::{ModelOptions, ToolChoice, TransportOptions};
+use crate::repair::finalize_arguments;
+use crate::sse::{error_event, SSEResponseExt};
+use crate::stream::{
+    accumulate, with_delta_reasoning_visibility, with_reasoning_visibility, StreamDelta,
+};
+use crate::strict::strict_tool_parameters;
+use crate::tokenize::TokenCounter;
+use crate::tools::canonical_tools;
+
+/// A response stored by the Responses API, with the id to continue from.
+#[derive(Debug, Clone)]
+pub struct StoredResponse {
+    /// Response id, usable as `previous_response_id` for the next turn.
+    pub id: String,
+    /// The response itself.
+    pub response: Response,
+}
+
+/// A tool hosted by OpenAI, enabled with [`OpenAIResponsesClient::with_hosted_tool`].
+///
+/// Calls of hosted tools are reported as [`Part::HostedToolCall`], and text
+/// backed by their results is followed by [`Part::Citation`] parts.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ResponsesHostedTool {
+    /// Search the web.
+    WebSearch,
+    /// Search files uploaded to vector stores.
+    FileSearch {
+        vector_store_ids: Vec<String>,
+        max_num_results: Option<u32>,
+    },
+    /// Run Python code in a sandbox.
+    CodeInterpreter,
+}
+
+impl ResponsesHostedTool {
+    /// Search the given vector stores.
+    pub fn file_search(vector_store_ids: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        ResponsesHostedTool::FileSearch {
+            vector_store_ids: vector_store_ids.into_iter().map(Into::into).collect(),
+            max_num_results: None,
+        }
+    }
+
+    fn definition(&self) -> Value {
+        match self {
+            ResponsesHostedTool::WebSearch => json!({ "type": "web_search" }),
+            ResponsesHostedTool::FileSearch {
+                vector_store_ids,
+                max_num_results,
+            } => {
+                let mut tool = json!({
+                    "type": "file_search",
+                    "vector_store_ids": vector_store_ids,
+                });
+                if let Some(max) = max_num_results {
+                    tool["max_num_results"] = json!(max);
+                }
+                tool
+            }
+            ResponsesHostedTool::CodeInterpreter => {
+                json!({ "type": "code_interpreter", "container": { "type": "auto" } })
+            }
+        }
+    }
+
+    fn built_in(tool: BuiltInTool) -> Self {
+        match tool {
+            BuiltInTool::WebSearch => ResponsesHostedTool::WebSearch,
+            BuiltInTool::CodeExecution => ResponsesHostedTool::CodeInterpreter,
+        }
+    }
+
+    /// Output field to include so the results are returned.
+    fn include(&self) -> &'static str {
+        match self {
+            ResponsesHostedTool::WebSearch => "web_search_call.action.sources",
+            ResponsesHostedTool::FileSearch { .. } => "file_search_call.results",
+            ResponsesHostedTool::CodeInterpreter => "code_interpreter_call.outputs",
+        }
+    }
+}
+
+/// Add the hosted tools enabled on a client to the tools of a request.
+///
+/// Each hosted tool type is sent once, as the API rejects duplicates. Tools of
+/// the client come first and replace tools of the same type in the request,
+/// which come from [`ModelOptions::built_in_tools`].
+fn merge_hosted_tools(tools: &mut Vec<Value>, hosted: &[Value]) {
+    let tool_type = |tool: &Value| tool["type"].as_str().map(str::to_string);
+    let mut types = HashSet::new();
+    let hosted: Vec<Value> = hosted
+        .iter()
+        .filter(|tool| tool_type(tool).is_none_or(|t| types.insert(t)))
+        .cloned()
+        .collect();
+    tools.retain(|tool| tool_type(tool).is_none_or(|t| !types.contains(&t)));
+    tools.extend(hosted);
+}
+
+/// Generic client for OpenAI-compatible Responses APIs.
+#[derive(Debug, Clone)]
+pub struct OpenAIResponsesClient<M> {
+    api_key: String,
+    base_url: String,
+    model_options: ModelOptions<M>,
+    transport_options: TransportOptions,
+    builtin_tools: Vec<Value>,
+    include: Vec<&'static str>,
+    computer_use: Option<ComputerDisplay>,
+}
+
+impl<M: OpenAICompatibleModel> OpenAIResponsesClient<M> {
+    pub fn new(
+        api_key: String,
+        base_url: String,
+        model_options: ModelOptions<M>,
+        transport_options: TransportOptions,
+    ) -> Self {
+        Self {
+            api_key,
+            base_url,
+            model_options,
+            transport_options: transport_options.for_provider(M::PROVIDER),
+            builtin_tools: Vec::new(),
+            include: Vec::new(),
+            computer_use: None,
+        }
+    }
+
+    /// Enable a tool hosted by the provider, such as `{"type": "web_search"}`.
+    ///
+    /// The definition is sent as is alongside the MCP tools of every request.
+    /// Tools are sent once per type: the first tool enabled on the client wins,
+    /// over later ones and over [`ModelOptions::built_in_tools`] of that type.
+    pub fn with_builtin_tool(mut self, tool: Value) -> Self {
+        self.builtin_tools.push(tool);
+        self
+    }
+
+    /// Enable a hosted tool and request its results in responses.
+    ///
+    /// Like [`with_builtin_tool`](Self::with_builtin_tool), it replaces a tool
+    /// of the same type from the model options.
+    pub fn with_hosted_tool(mut self, tool: ResponsesHostedTool) -> Self {
+        self.builtin_tools.push(tool.definition());
+        self.include.push(tool.include());
+        self
+    }
+
+    /// Offer the computer-use tool for the given display.
+    ///
+    /// Actions arrive as [`Part::ComputerCall`]; answer them with
+    /// [`Part::ComputerResult`]. Requires a computer-use model such as
+    /// `computer-use-preview`.
+    pub fn with_computer_use(mut self, display: ComputerDisplay) -> Self {
+        self.computer_use = Some(display);
+        self
+    }
+
+    /// Send a request continuing the stored response `previous_response_id`.
+    ///
+    /// When chaining, the server already knows the earlier turns, so `messages`
+    /// only needs to hold the new ones. The returned id continues the chain.
+    pub async fn request_chained(
+        &self,
+        previous_response_id: Option<&str>,
+        messages: Vec<Message>,
+        tools: Vec<rmcp::model::Tool>,
+    ) -> Result<StoredResponse, ClientError> {
+        let req = self.build_request(previous_response_id, messages, tools, false)?;
+
+        let response = send_with_retry(req, &self.transport_options).await?;
+        let status = response.status();
+
+        if !status.is_success() {
+            let body = response
+                .text_logged(&self.transport_options)
+                .await
+                .unwrap_or_default();
+            return Err(OpenAIClient::<M>::handle_error_response(status, &body));
+        }
+
+        let mut responses_response: ResponsesResponse =
+            response.json_logged(&self.transport_options).await?;
+        if let Some(error) = responses_response.error.take() {
+            return Err(error.into());
+        }
+        let id = responses_response.id.clone();
+        let mut response: Response = responses_response.into();
+        if let Some(visibility) = self.model_options.reasoning_visibility {
+            response.set_reasoning_visibility(visibility);
+        }
+        if self.model_options.plain_text.unwrap_or(false) {
+            strip_response_markdown(&mut response);
+        }
+        Ok(StoredResponse { id, response })
+    }
+
+    fn build_request(
+        &self,
+        previous_response_id: Option<&str>,
+        messages: Vec<Message>,
+        tools: Vec<rmcp::model::Tool>,
+        stream: bool,
+    ) -> Result<reqwest::RequestBuilder, ClientError> {
+        let url = format!("{}/responses", self.base_url);
+
+        let mut request_body = ResponsesRequest::new(messages, &self.model_options, tools, stream);
+        request_body.previous_response_id = previous_response_id.map(str::to_string);
+        merge_hosted_tools(&mut request_body.tools, &self.builtin_tools);
+        for field in &self.include {
+            if !request_body.include.iter().any(|f| f == field) {
+                request_body.include.push(field.to_string());
+            }
+        }
+        if let Some(display) = &self.computer_use {
+            request_body.tools.push(json!({
+                "type": "computer_use_preview",
+                "display_width": display.width,
+                "display_height": display.height,
+                "environment": display.environment,
+            }));
+            // Computer use requires automatic truncation of long histories.
+            request_body.truncation = Some("auto".to_string());
+        }
+
+        Ok(self
+            .authorized(reqwest::Method::POST, &url)?
+            .header(CONTENT_TYPE, HeaderValue::from_static("application/json"))
+            .json_logged(&request_body, &self.transport_options))
+    }
+
+    fn authorized(
+        &self,
+        method: reqwest::Method,
+        url: &str,
+    ) -> Result<reqwest::RequestBuilder, ClientError> {
+        let http_client = build_http_client(&self.transport_options)?;
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", self.api_key))
+                .map_err(|_| ClientError::Config("Invalid API key".to_string()))?,
+        );
+        for (name, value) in self.model_options.provider.headers() {
+            headers.insert(
+                name,
+                HeaderValue::from_str(&value)
+                    .map_err(|_| ClientError::Config(format!("Invalid {} header", name)))?,
+            );
+        }
+
+        let req = http_client.request(method, url).headers(headers);
+        Ok(add_extra_headers(req, &self.transport_options))
+    }
+
+    async fn send_stream(
+        &self,
+        messages: Vec<Message>,
+        tools: Vec<rmcp::model::Tool>,
+    ) -> Result<reqwest::Response, ClientError> {
+        let req = self.build_request(None, messages, tools, true)?;
+        let response = send_with_retry(req, &self.transport_options).await?;
+        let status = response.status();
+
+        if !status.is_success() {
+            let body = response
+                .text_logged(&self.transport_options)
+                .await
+                .unwrap_or_default();
+            return Err(OpenAIClient::<M>::handle_error_response(status, &body));
+        }
+
+        Ok(response)
+    }
+}
+
+#[async_trait]
+impl<M: OpenAICompatibleModel> Client for OpenAIResponsesClient<M> {
+    type ModelProvider = M;
+
+    async fn request(
+        &self,
+        messages: Vec<Message>,
+        tools: Vec<rmcp::model::Tool>,
+    ) -> Result<Response, ClientError> {
+        Ok(self.request_chained(None, messages, tools).await?.response)
+    }
+
+    fn model_options(&self) -> &ModelOptions<Self::ModelProvider> {
+        &self.model_options
+    }
+
+    fn transport_options(&self) -> &TransportOptions {
+        &self.transport_options
+    }
+
+    fn provider(&self) -> &'static str {
+        M::PROVIDER
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        M::capabilities(&self.model_options.model)
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>, ClientError> {
+        let url = M::models_url(&self.base_url);
+        let response = send_with_retry(
+            self.authorized(reqwest::Method::GET, &url)?,
+            &self.transport_options,
+        )
+        .await?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response
+                .text_logged(&self.transport_options)
+                .await
+                .unwrap_or_default();
+            return Err(OpenAIClient::<M>::handle_error_response(status, &body));
+        }
+        Ok(parse_model_list(
+            &response.json_logged(&self.transport_options).await?,
+        ))
+    }
+}
+
+#[async_trait]
+impl<M: OpenAICompatibleModel> StreamingClient for OpenAIResponsesClient<M> {
+    async fn request_stream(
+        &self,
+        messages: Vec<Message>,
+        tools: Vec<rmcp::model::Tool>,
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<Response, ClientError>> + Send>>, ClientError>
+    {
+        let response = self.send_stream(messages, tools).await?;
+        let stream = accumulate(ResponsesStream::deltas(response.sse()));
+        let stream = with_reasoning_visibility(stream, self.model_options.reasoning_visibility);
+        if self.model_options.plain_text.unwrap_or(false) {
+            return Ok(Box::pin(strip_stream_markdown(stream)));
+        }
+        Ok(Box::pin(stream))
+    }
+
+    async fn request_stream_deltas(
+        &self,
+        messages: Vec<Message>,
+        tools: Vec<rmcp::model::Tool>,
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<StreamDelta, ClientError>> + Send>>, ClientError>
+    {
+        let response = self.send_stream(messages, tools).await?;
+        let deltas = with_delta_reasoning_visibility(
+            ResponsesStream::deltas(response.sse()),
+            self.model_options.reasoning_visibility,
+            |_, signature| signature,
+        );
+        if self.model_options.plain_text.unwrap_or(false) {
+            return Ok(Box::pin(strip_delta_markdown(deltas)));
+        }
+        Ok(Box::pin(deltas))
+    }
+}
+
+#[async_trait]
+impl<M: OpenAICompatibleModel> TokenCounter for OpenAIResponsesClient<M> {}
+
+/// Encode a reasoning item into the opaque signature of a [`Part::Reasoning`].
+fn reasoning_signature(id: &str, encrypted_content: Option<&str>) -> String {
+    match encrypted_content {
+        Some(encrypted) => format!("{}:{}", id, encrypted),
+        None => id.to_string(),
+    }
+}
+
+/// Split a reasoning signature into the item id and its encrypted content.
+fn parse_reasoning_signature(signature: &str) -> (String, Option<String>) {
+    match signature.split_once(':') {
+        Some((id, encrypted)) => (id.to_string(), Some(encrypted.to_string())),
+        None => (signature.to_string(), None),
+    }
+}
+
+// --- Streaming Implementation ---
+
+struct ResponsesStream;
+
+impl ResponsesStream {
+    fn deltas<S>(events: S) -> impl Stream<Item = Result<StreamDelta, ClientError>> + Send
+    where
+        S: Stream<Item = Result<String, ClientError>> + Send + 'static,
+    {
+        Box::pin(async_stream::try_stream! {
+            let mut events = Box::pin(events);
+
+            // Part index per (output item, content or summary index).
+            let mut part_indices: HashMap<(u32, u32), usize> = HashMap::new();
+
+            while let Some(event_result) = events.next().await {
+                let event_str = event_result?;
+
+                // Error events without a type are not part of the enum.
+                let event: ResponsesStreamEvent = serde_json::from_str(&event_str)
+                    .map_err(|e| error_event(&event_str).unwrap_or_else(|| ClientError::ProviderError(format!("JSON parse error: {} | Input: {}", e, event_str))))?;
+
+                let part_count = part_indices.len();
+                match event {
+                    ResponsesStreamEvent::OutputItemAdded { output_index, item } => match item {
+                        ResponsesOutputItem::FunctionCall { call_id, name, .. } => {
+                            let index = *part_indices.entry((output_index, 0)).or_insert(part_count);
+                            yield StreamDelta::ToolCallDelta {
+                                index,
+                                id: Some(call_id),
+                                name: Some(name),
+                                arguments: String::new(),
+                                signature: None,
+                            };
+                        }
+                        ResponsesOutputItem::Reasoning { .. } => {
+                            let index = *part_indices.entry((output_index, 0)).or_insert(part_count);
+                            yield StreamDelta::ReasoningDelta { index, text: String::new(), signature: None };
+                        }
+                        _ => {}
+                    },
+                    ResponsesStreamEvent::OutputTextDelta { output_index, content_index, delta } => {
+                        let index = *part_indices.entry((output_index, content_index)).or_insert(part_count);
+                        yield StreamDelta::TextDelta { index, text: delta };
+                    }
+                    ResponsesStreamEvent::ReasoningSummaryPartAdded { output_index, summary_index } => {
+                        if summary_index > 0 {
+                            let index = *part_indices.entry((output_index, 0)).or_insert(part_count);
+                            yield StreamDelta::ReasoningDelta { index, text: "\n\n".to_string(), signature: None };
+                            yield StreamDelta::ReasoningSummaryDelta { index, text: "\n\n".to_string() };
+                        }
+                    }
+                    ResponsesStreamEvent::ReasoningSummaryTextDelta { output_index, delta } => {
+                        let index = *part_indices.entry((output_index, 0)).or_insert(part_count);
+                        yield StreamDelta::ReasoningDelta { index, text: delta.clone(), signature: None };
+                        yield StreamDelta::ReasoningSummaryDelta { index, text: delta };
+                    }
+                    ResponsesStreamEvent::FunctionCallArgumentsDelta { output_index, delta } => {
+                        let index = *part_indices.entry((output_index, 0)).or_insert(part_count);
+                        yield StreamDelta::ToolCallDelta {
+                            index,
+                            id: None,
+                            name: None,
+                            arguments: delta,
+                            signature: None,
+                        };
+                    }
+                    ResponsesStreamEvent::OutputItemDone { output_index, item } => {
+                        match item {
+                            ResponsesOutputItem::Reasoning { id, encrypted_content, .. } => {
+                                let index = *part_indices.entry((output_index, 0)).or_insert(part_count);
+                                yield StreamDelta::ReasoningDelta {
+                                    index,
+                                    text: String::new(),
+                                    signature: Some(reasoning_signature(&id, encrypted_content.as_deref())),
+                                };
+                            }
+                            ResponsesOutputItem::ComputerCall { id, call_id, action, pending_safety_checks } => {
+                                let index = *part_indices.entry((output_index, 0)).or_insert(part_count);
+                                yield StreamDelta::ComputerCall {
+                                    index,
+                                    id: Some(call_id),
+                                    action,
+                                    safety_checks: pending_safety_checks,
+                                    signature: Some(id),
+                                };
+                            }
+                            ResponsesOutputItem::Message { content } => {
+                                // Citations follow the text parts of the message.
+                                let citations = content.into_iter().flat_map(|content| match content {
+                                    ResponsesOutputContent::OutputText { annotations, .. } => annotations,
+                                    _ => Vec::new(),
+                                });
+                                // Text parts use the content indices as keys; citations take the ones after.
+                                let first_key = part_indices.keys().filter(|(item, _)| *item == output_index).count() as u32;
+                                for (key, citation) in (first_key..).zip(citations.filter_map(ResponsesAnnotation::citation)) {
+                                    let index = part_indices.len();
+                                    part_indices.insert((output_index, key), index);
+                                    yield StreamDelta::Citation { index, citation };
+                                }
+                            }
+                            item => {
+                                if let Some((id, call)) = item.hosted_tool_call() {
+                                    let index = *part_indices.entry((output_index, 0)).or_insert(part_count);
+                                    yield StreamDelta::HostedToolCall { index, id: Some(id), call };
+                                }
+                            }
+                        }
+                        let mut finished: Vec<usize> = part_indices
+                            .iter()
+                            .filter(|((item_index, _), _)| *item_index == output_index)
+                            .map(|(_, index)| *index)
+                            .collect();
+                        finished.sort_unstable();
+                        for index in finished {
+                            yield StreamDelta::PartFinished { index };
+                        }
+                    }
+                    ResponsesStreamEvent::Completed { response } | ResponsesStreamEvent::Incomplete { response } => {
+                        let finish = response.finish_reason();
+                        if let Some(model) = response.model {
+                            yield StreamDelta::Model { model };
+                        }
+                        if let Some(usage) = response.usage {
+                            yield StreamDelta::Usage(usage.into());
+                        }
+                        yield StreamDelta::Finish(finish);
+                    }
+                    ResponsesStreamEvent::Failed { response } => {
+                        let error = response.error.unwrap_or_else(|| ResponsesError {
+                            code: None,
+                            message: "Response failed".to_string(),
+                        });
+                        Err(ClientError::from(error))?;
+                    }
+                    ResponsesStreamEvent::Error { code, message } => {
+                        Err(ClientError::from(ResponsesError { code, message }))?;
+                    }
+                    ResponsesStreamEvent::Other => {}
+                }
+            }
+        })
+    }
+}
+
+// --- Request Types ---
+
+#[skip_serializing_none]
+#[derive(Debug, Serialize)]
+struct ResponsesRequest<M> {
+    model: String,
+    input: Vec<ResponsesInputItem>,
+    instructions: Option<String>,
+    previous_response_id: Option<String>,
+    max_output_tokens: Option<u32>,
+    temperature: Option<f32>,
+    top_p: Option<f32>,
+    reasoning: Option<ResponsesReasoning>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    include: Vec<String>,
+    stream: Option<bool>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tools: Vec<Value>,
+    tool_choice: Option<Value>,
+    truncation: Option<String>,
+    #[serde(flatten)]
+    provider_options: M,
+}
+
+#[derive(Debug, Serialize)]
+struct ResponsesReasoning {
+    summary: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ResponsesInputItem {
+    Message {
+        role: String,
+        content: ResponsesInputContent,
+    },
+    Reasoning {
+        id: String,
+        summary: Vec<ResponsesSummary>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        encrypted_content: Option<String>,
+    },
+    FunctionCall {
+        call_id: String,
+        name: String,
+        arguments: String,
+    },
+    FunctionCallOutput {
+        call_id: String,
+        output: String,
+    },
+    ComputerCall {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        id: Option<String>,
+        call_id: String,
+        action: ComputerAction,
+        pending_safety_checks: Vec<ComputerSafetyCheck>,
+        status: String,
+    },
+    ComputerCallOutput {
+        call_id: String,
+        output: ResponsesComputerScreenshot,
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        acknowledged_safety_checks: Vec<ComputerSafetyCheck>,
+    },
+}
+
+#[derive(Debug, Serialize)]
+struct ResponsesComputerScreenshot {
+    #[serde(rename = "type")]
+    output_type: &'static str,
+    image_url: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+enum ResponsesInputContent {
+    Text(String),
+    Parts(Vec<ResponsesInputPart>),
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type")]
+enum ResponsesInputPart {
+    #[serde(rename = "input_text")]
+    Text { text: String },
+    #[serde(rename = "input_image")]
+    Image {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        image_url: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        file_id: Option<String>,
+    },
+    #[serde(rename = "input_file")]
+    File {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        file_data: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        file_id: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        filename: Option<String>,
+    },
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ResponsesSummary {
+    #[serde(rename = "type", default = "summary_text")]
+    summary_type: String,
+    text: String,
+}
+
+fn summary_text() -> String {
+    "summary_text".to_string()
+}
+
+impl<M: OpenAICompatibleModel> ResponsesRequest<M> {
+    fn new(
+        messages: Vec<Message>,
+        model_options: &ModelOptions<M>,
+        tool_defs: Vec<rmcp::model::Tool>,
+        stream: bool,
+    ) -> Self {
+        let reasoning = model_options.reasoning.unwrap_or(false);
+        let hosted_tools: Vec<ResponsesHostedTool> = model_options
+            .built_in_tools
+            .iter()
+            .flatten()
+            .map(|tool| ResponsesHostedTool::built_in(*tool))
+            .collect();
+        let tool_choice = model_options
+            .tool_choice
+            .as_ref()
+            .filter(|_| !tool_defs.is_empty())
+            .map(|choice| match choice {
+                ToolChoice::Auto => json!("auto"),
+                ToolChoice::None => json!("none"),
+                ToolChoice::Required => json!("required"),
+                ToolChoice::Specific(name) => json!({ "type": "function", "name": name }),
+            });
+
+        ResponsesRequest {
+            model: model_options.model.clone(),
+            input: convert_messages(correct_media_types(messages)),
+            instructions: model_options.system_prompt(),
+            previous_response_id: None,
+            max_output_tokens: model_options.max_tokens,
+            temperature: model_options.temperature,
+            top_p: model_options.top_p,
+            reasoning: reasoning.then(|| ResponsesReasoning {
+                summary: "auto".to_string(),
+            }),
+            include: reasoning
+                .then_some("reasoning.encrypted_content")
+                .into_iter()
+                .chain(hosted_tools.iter().map(ResponsesHostedTool::include))
+                .map(str::to_string)
+                .collect(),
+            stream: if stream { Some(true) } else { None },
+            tools: convert_tools(tool_defs, model_options.strict_tools.unwrap_or(false))
+                .into_iter()
+                .chain(hosted_tools.iter().map(ResponsesHostedTool::definition))
+                .collect(),
+            tool_choice,
+            truncation: None,
+            provider_options: model_options.provider.clone(),
+        }
+    }
+}
+
+fn convert_messages(messages: Vec<Message>) -> Vec<ResponsesInputItem> {
+    let mut items = Vec::new();
+    // Safety checks of earlier computer calls, acknowledged by their results.
+    let mut safety_checks: HashMap<String, Vec<ComputerSafetyCheck>> = HashMap::new();
+
+    for msg in messages {
+        match msg {
+            Message::User(parts) => {
+                let mut content = Vec::new();
+                for part in &parts {
+                    match part {
+                        Part::Text { content: text, .. } => {
+                            content.push(ResponsesInputPart::Text { text: text.clone() })
+                        }
+                        Part::Media {
+                            media_type: MediaType::Image,
+                            data,
+                            mime_type,
+                            uri,
+                            ..
+                        } => {
+                            content.push(ResponsesInputPart::Text {
+                                text: part.anchor_media(),
+                            });
+                            content.push(match file_reference(data, uri) {
+                                Some(uri) if uri.starts_with("http") => ResponsesInputPart::Image {
+                                    image_url: Some(uri.to_string()),
+                                    file_id: None,
+                                },
+                                Some(file_id) => ResponsesInputPart::Image {
+                                    image_url: None,
+                                    file_id: Some(file_id.to_string()),
+                                },
+                                None => ResponsesInputPart::Image {
+                                    image_url: Some(format!("data:{};base64,{}", mime_type, data)),
+                                    file_id: None,
+                                },
+                            });
+                        }
+                        Part::Media {
+                            data,
+                            mime_type,
+                            uri,
+                            ..
+                        } => {
+                            content.push(ResponsesInputPart::Text {
+                                text: part.anchor_media(),
+                            });
+                            content.push(match file_reference(data, uri) {
+                                Some(file_id) => ResponsesInputPart::File {
+                                    file_data: None,
+                                    file_id: Some(file_id.to_string()),
+                                    filename: None,
+                                },
+                                None => ResponsesInputPart::File {
+                                    file_data: Some(format!("data:{};base64,{}", mime_type, data)),
+                                    file_id: None,
+                                    filename: uri.clone(),
+                                },
+                            });
+                        }
+                        Part::FunctionResponse {
+                            id: Some(call_id),
+                            response,
+                            parts,
+                            ..
+                        } => items.push(ResponsesInputItem::FunctionCallOutput {
+                            call_id: call_id.clone(),
+                            output: function_output_text(response, parts),
+                        }),
+                        Part::ComputerResult {
+                            id: Some(call_id),
+                            screenshot,
+                            mime_type,
+                            ..
+                        } => items.push(ResponsesInputItem::ComputerCallOutput {
+                            call_id: call_id.clone(),
+                            output: ResponsesComputerScreenshot {
+                                output_type: "computer_screenshot",
+                                image_url: format!("data:{};base64,{}", mime_type, screenshot),
+                            },
+                            acknowledged_safety_checks: safety_checks
+                                .remove(call_id)
+                                .unwrap_or_default(),
+                        }),
+                        _ => {}
+                    }
+                }
+                if !content.is_empty() {
+                    items.push(ResponsesInputItem::Message {
+                        role: "user".to_string(),
+                        content: ResponsesInputContent::Parts(content),
+                    });
+                }
+            }
+            Message::Assistant(parts) => {
+                for part in parts {
+                    match part {
+                        Part::Text { content, .. } => items.push(ResponsesInputItem::Message {
+                            role: "assistant".to_string(),
+                            content: ResponsesInputContent::Text(content),
+                        }),
+                        Part::Reasoning {
+                            content,
+                            summary,
+                            signature: Some(signature),
+                            ..
+                        } => {
+                            let (id, encrypted_content) = parse_reasoning_signature(&signature);
+                            let text = if content.is_empty() {
+                                summary.unwrap_or_default()
+                            } else {
+                                content
+                            };
+                            let summary = if text.is_empty() {
+                                Vec::new()
+                            } else {
+                                vec![ResponsesSummary {
+                                    summary_type: summary_text(),
+                                    text,
+                                }]
+                            };
+                            items.push(ResponsesInputItem::Reasoning {
+                                id,
+                                summary,
+                                encrypted_content,
+                            });
+                        }
+                        Part::FunctionCall {
+                            id: Some(call_id),
+                            name,
+                            arguments,
+                            ..
+                        } => items.push(ResponsesInputItem::FunctionCall {
+                            call_id,
+                            name,
+                            arguments: arguments.to_string(),
+                        }),
+                        Part::ComputerCall {
+                            id: Some(call_id),
+                            action,
+                            safety_checks: checks,
+                            signature,
+                            ..
+                        } => {
+                            safety_checks.insert(call_id.clone(), checks.clone());
+                            items.push(ResponsesInputItem::ComputerCall {
+                                id: signature,
+                                call_id,
+                                action,
+                                pending_safety_checks: checks,
+                                status: "completed".to_string(),
+                            });
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+    }
+
+    items
+}
+
+fn convert_tools(tool_defs: Vec<rmcp::model::Tool>, strict: bool) -> Vec<Value> {
+    canonical_tools(tool_defs)
+        .into_iter()
+        .map(|t| {
+            let strict_parameters = strict.then(|| strict_tool_parameters(&t)).flatten();
+            json!({
+                "type": "function",
+                "name": t.name,
+                "description": t.description,
+                "strict": strict_parameters.is_some(),
+                "parameters": strict_parameters
+                    .unwrap_or_else(|| Value::Object((*t.input_schema).clone())),
+            })
+        })
+        .collect()
+}
+
+// --- Response Types ---
+
+#[derive(Debug, Deserialize)]
+struct ResponsesResponse {
+    id: String,
+    model: Option<String>,
+    status: Option<String>,
+    incomplete_details: Option<ResponsesIncompleteDetails>,
+    #[serde(default)]
+    output: Vec<ResponsesOutputItem>,
+    usage: Option<ResponsesUsage>,
+    error: Option<ResponsesError>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ResponsesIncompleteDetails {
+    reason: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ResponsesOutputItem {
+    Message {
+        #[serde(default)]
+        content: Vec<ResponsesOutputContent>,
+    },
+    Reasoning {
+        id: String,
+        #[serde(default)]
+        summary: Vec<ResponsesSummary>,
+        encrypted_content: Option<String>,
+    },
+    FunctionCall {
+        call_id: String,
+        name: String,
+        #[serde(default)]
+        arguments: String,
+    },
+    ComputerCall {
+        id: String,
+        call_id: String,
+        action: ComputerAction,
+        #[serde(default)]
+        pending_safety_checks: Vec<ComputerSafetyCheck>,
+    },
+    WebSearchCall {
+        id: String,
+        action: Option<ResponsesWebSearchAction>,
+    },
+    FileSearchCall {
+        id: String,
+        #[serde(default)]
+        queries: Vec<String>,
+        #[serde(default, deserialize_with = "null_as_default")]
+        results: Vec<FileSearchResult>,
+    },
+    CodeInterpreterCall {
+        id: String,
+        code: Option<String>,
+        container_id: Option<String>,
+        #[serde(default, deserialize_with = "null_as_default")]
+        outputs: Vec<CodeOutput>,
+    },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Deserialize)]
+struct ResponsesWebSearchAction {
+    query: Option<String>,
+    #[serde(default)]
+    sources: Vec<SearchSource>,
+}
+
+/// Deserialize `null` as the default value, for lists the API reports as `null`
+/// until they are populated.
+fn null_as_default<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Default + Deserialize<'de>,
+{
+    Ok(Option::<T>::deserialize(deserializer)?.unwrap_or_default())
+}
+
+impl ResponsesOutputItem {
+    /// The hosted tool call described by this item, if it is one.
+    fn hosted_tool_call(&self) -> Option<(String, HostedToolCall)> {
+        let (id, call) = match self {
+            ResponsesOutputItem::WebSearchCall { id, action } => (
+                id,
+                HostedToolCall::WebSearch {
+                    query: action.as_ref().and_then(|a| a.query.clone()),
+                    sources: action
+                        .as_ref()
+                        .map(|a| a.sources.clone())
+                        .unwrap_or_default(),
+                },
+            ),
+            ResponsesOutputItem::FileSearchCall {
+                id,
+                queries,
+                results,
+            } => (
+                id,
+                HostedToolCall::FileSearch {
+                    queries: queries.clone(),
+                    results: results.clone(),
+                },
+            ),
+            ResponsesOutputItem::CodeInterpreterCall {
+                id,
+                code,
+                container_id,
+                outputs,
+            } => (
+                id,
+                HostedToolCall::CodeInterpreter {
+                    code: code.clone(),
+                    container_id: container_id.clone(),
+                    outputs: outputs.clone(),
+                },
+            ),
+            _ => return None,
+        };
+        Some((id.clone(), call))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ResponsesOutputContent {
+    OutputText {
+        text: String,
+        #[serde(default)]
+        annotations: Vec<ResponsesAnnotation>,
+    },
+    Refusal {
+        refusal: String,
+    },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ResponsesAnnotation {
+    UrlCitation {
+        url: String,
+        title: Option<String>,
+        start_index: Option<usize>,
+        end_index: Option<usize>,
+    },
+    FileCitation {
+        file_id: String,
+        filename: Option<String>,
+        index: Option<usize>,
+    },
+    ContainerFileCitation {
+        file_id: String,
+        filename: Option<String>,
+        start_index: Option<usize>,
+        end_index: Option<usize>,
+    },
+    #[serde(other)]
+    Other,
+}
+
+impl ResponsesAnnotation {
+    fn citation(self) -> Option<Citation> {
+        match self {
+            ResponsesAnnotation::UrlCitation {
+                url,
+                title,
+                start_index,
+                end_index,
+            } => Some(Citation {
+                url: Some(url),
+                title,
+                start_index,
+                end_index,
+                ..Default::default()
+            }),
+            ResponsesAnnotation::FileCitation {
+                file_id,
+                filename,
+                index,
+            } => Some(Citation {
+                file_id: Some(file_id),
+                title: filename,
+                start_index: index,
+                end_index: index,
+                ..Default::default()
+            }),
+            ResponsesAnnotation::ContainerFileCitation {
+                file_id,
+                filename,
+                start_index,
+                end_index,
+            } => Some(Citation {
+                file_id: Some(file_id),
+                title: filename,
+                start_index,
+                end_index,
+                ..Default::default()
+            }),
+            ResponsesAnnotation::Other => None,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ResponsesUsage {
+    input_tokens: u32,
+    output_tokens: u32,
+    input_tokens_details: Option<ResponsesInputTokensDetails>,
+    output_tokens_details: Option<ResponsesOutputTokensDetails>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ResponsesInputTokensDetails {
+    cached_tokens: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ResponsesOutputTokensDetails {
+    reasoning_tokens: Option<u32>,
+}
+
+impl From<ResponsesUsage> for Usage {
+    fn from(usage: ResponsesUsage) -> Self {
+        Usage {
+            prompt_tokens: Some(usage.input_tokens),
+            completion_tokens: Some(usage.output_tokens),
+            cached_tokens: usage.input_tokens_details.and_then(|d| d.cached_tokens),
+            cache_creation_tokens: None,
+            reasoning_tokens: usage.output_tokens_details.and_then(|d| d.reasoning_tokens),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ResponsesError {
+    code: Option<String>,
+    message: String,
+}
+
+impl From<ResponsesError> for ClientError {
+    fn from(error: ResponsesError) -> Self {
+        ClientError::ProviderError(format!(
+            "OpenAI error ({}): {}",
+            error.code.as_deref().unwrap_or("unknown"),
+            error.message
+        ))
+    }
+}
+
+impl ResponsesResponse {
+    fn finish_reason(&self) -> FinishReason {
+        let has_refusal = self.output.iter().any(|item| {
+            matches!(item, ResponsesOutputItem::Message { content }
+                if content.iter().any(|c| matches!(c, ResponsesOutputContent::Refusal { .. })))
+        });
+        let reason = self
+            .incomplete_details
+            .as_ref()
+            .and_then(|details| details.reason.as_deref());
+
+        match (self.status.as_deref(), reason) {
+            (Some("incomplete"), Some("max_output_tokens")) => FinishReason::OutputTokens,
+            (Some("incomplete"), Some("content_filter")) => FinishReason::ContentFilter,
+            (Some("failed"), _) => FinishReason::Error,
+            _ if has_refusal => FinishReason::ContentFilter,
+            _ if self.output.iter().any(|item| {
+                matches!(
+                    item,
+                    ResponsesOutputItem::FunctionCall { .. }
+                        | ResponsesOutputItem::ComputerCall { .. }
+                )
+            }) =>
+            {
+                FinishReason::ToolCalls
+            }
+            _ => FinishReason::Stop,
+        }
+    }
+}
+
+impl From<ResponsesResponse> for Response {
+    fn from(resp: ResponsesResponse) -> Self {
+        let finish = resp.finish_reason();
+        let mut parts = Vec::new();
+
+        for item in resp.output {
+            match item {
+                ResponsesOutputItem::Message { content } => {
+                    for content in content {
+                        match content {
+                            ResponsesOutputContent::OutputText { text, annotations } => {
+                                parts.push(Part::Text {
+                                    content: text,
+                                    finished: true,
+                                });
+                                parts.extend(annotations.into_iter().filter_map(|a| {
+                                    Some(Part::Citation {
+                                        citation: a.citation()?,
+                                        finished: true,
+                                    })
+                                }));
+                            }
+                            ResponsesOutputContent::Refusal { refusal } => parts.push(Part::Text {
+                                content: refusal,
+                                finished: true,
+                            }),
+                            ResponsesOutputContent::Other => {}
+                        }
+                    }
+                }
+                ResponsesOutputItem::Reasoning {
+                    id,
+                    summary,
+                    encrypted_content,
+                } => {
+                    // The summary is all of the reasoning the API returns.
+                    let summary = summary
+                        .into_iter()
+                        .map(|s| s.text)
+                        .collect::<Vec<_>>()
+                        .join("\n\n");
+                    parts.push(Part::Reasoning {
+                        content: summary.clone(),
+                        summary: (!summary.is_empty()).then_some(summary),
+                        signature: Some(reasoning_signature(&id, encrypted_content.as_deref())),
+                        finished: true,
+                    })
+                }
+                ResponsesOutputItem::FunctionCall {
+                    call_id,
+                    name,
+                    arguments,
+                } => {
+                    let (arguments, raw_arguments) = finalize_arguments(&arguments);
+                    parts.push(Part::FunctionCall {
+                        id: Some(call_id),
+                        name,
+                        arguments,
+                        signature: None,
+                        raw_arguments,
+                        finished: true,
+                    });
+                }
+                ResponsesOutputItem::ComputerCall {
+                    id,
+                    call_id,
+                    action,
+                    pending_safety_checks,
+                } => parts.push(Part::ComputerCall {
+                    id: Some(call_id),
+                    action,
+                    safety_checks: pending_safety_checks,
+                    signature: Some(id),
+                    finished: true,
+                }),
+                item => {
+                    if let Some((id, call)) = item.hosted_tool_call() {
+                        parts.push(Part::HostedToolCall {
+                            id: Some(id),
+                            call,
+                            finished: true,
+                        });
+                    }
+                }
+            }
+        }
+
+        Response {
+            data: vec![Message::Assistant(parts)],
+            usage: resp.usage.map(Usage::from).unwrap_or_default(),
+            finish,
+            alternatives: Vec::new(),
+            model: resp.model,
+            iterations: Vec::new(),
+        }
+    }
+}
+
+// --- Stream Types ---
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type")]
+enum ResponsesStreamEvent {
+    #[serde(rename = "response.output_item.added")]
+    OutputItemAdded {
+        output_index: u32,
+        item: ResponsesOutputItem,
+    },
+    #[serde(rename = "response.output_item.done")]
+    OutputItemDone {
+        output_index: u32,
+        item: ResponsesOutputItem,
+    },
+    #[serde(rename = "response.output_text.delta")]
+    OutputTextDelta {
+        output_index: u32,
+        content_index: u32,
+        delta: String,
+    },
+    #[serde(rename = "response.reasoning_summary_part.added")]
+    ReasoningSummaryPartAdded {
+        output_index: u32,
+        summary_index: u32,
+    },
+    #[serde(rename = "response.reasoning_summary_text.delta")]
+    ReasoningSummaryTextDelta { output_index: u32, delta: String },
+    #[serde(rename = "response.function_call_arguments.delta")]
+    FunctionCallArgumentsDelta { output_index: u32, delta: String },
+    #[serde(rename = "response.completed")]
+    Completed { response: ResponsesResponse },
+    #[serde(rename = "response.incomplete")]
+    Incomplete { response: ResponsesResponse },
+    #[serde(rename = "response.failed")]
+    Failed { response: ResponsesResponse },
+    #[serde(rename = "error")]
+    Error {
+        code: Option<String>,
+        message: String,
+    },
+    #[serde(other)]
+    Other,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::computer::MouseButton;
+    use crate::options::ReasoningVisibility;
+    use crate::providers::openai::OpenAIModel;
+    use crate::stream::ResponseAccumulator;
+
+    #[test]
+    fn test_reasoning_round_trip() {
+        let body = json!({
+            "id": "resp_1",
+            "status": "completed",
+            "output": [
+                {
+                    "type": "reasoning",
+                    "id": "rs_1",
+                    "summary": [{ "type": "summary_text", "text": "Need the weather." }],
+                    "encrypted_content": "c2VjcmV0"
+                },
+                {
+                    "type": "function_call",
+                    "id": "fc_1",
+                    "call_id": "call_1",
+                    "name": "weather",
+                    "arguments": "{\"city\":\"Paris\"}"
+                },
+                { "type": "web_search_call", "id": "ws_1", "status": "completed" }
+            ],
+            "usage": {
+                "input_tokens": 10,
+                "output_tokens": 20,
+                "output_tokens_details": { "reasoning_tokens": 12 }
+            }
+        });
+        let response: Response = serde_json::from_value::<ResponsesResponse>(body)
+            .unwrap()
+            .into();
+
+        assert_eq!(response.finish, FinishReason::ToolCalls);
+        assert_eq!(response.usage.reasoning_tokens, Some(12));
+
+        let mut options = ModelOptions::<OpenAIModel>::new("o4-mini");
+        options.system = Some("Be brief.".to_string());
+        options.reasoning = Some(true);
+        let mut messages = response.data;
+        messages.push(Message::User(vec![Part::FunctionResponse {
+            id: Some("call_1".to_string()),
+            name: "weather".to_string(),
+            response: json!({ "temperature": 18 }),
+            parts: vec![],
+            finished: true,
+        }]));
+        let request = ResponsesRequest::new(messages, &options, vec![], false);
+
+        assert_eq!(
+            serde_json::to_value(request).unwrap(),
+            json!({
+                "model": "o4-mini",
+                "instructions": "Be brief.",
+                "reasoning": { "summary": "auto" },
+                "include": ["reasoning.encrypted_content"],
+                "input": [
+                    {
+                        "type": "reasoning",
+                        "id": "rs_1",
+                        "summary": [{ "type": "summary_text", "text": "Need the weather." }],
+                        "encrypted_content": "c2VjcmV0"
+                    },
+                    {
+                        "type": "function_call",
+                        "call_id": "call_1",
+                        "name": "weather",
+                        "arguments": "{\"city\":\"Paris\"}"
+                    },
+                    {
+                        "type": "function_call_output",
+                        "call_id": "call_1",
+                        "output": "{\"temperature\":18}"
+                    }
+                ]
+            })
+        );
+    }
+
+    #[test]
+    fn test_computer_call_round_trip() {
+        let body = json!({
+            "id": "resp_1",
+            "status": "completed",
+            "output": [{
+                "type": "computer_call",
+                "id": "cu_1",
+                "call_id": "call_1",
+                "action": { "type": "click", "x": 10, "y": 20, "button": "left" },
+                "pending_safety_checks": [
+                    { "id": "sc_1", "code": "malicious_instructions", "message": "Check the page." }
+                ],
+                "status": "completed"
+            }]
+        });
+        let response: Response = serde_json::from_value::<ResponsesResponse>(body)
+            .unwrap()
+            .into();
+
+        assert_eq!(response.finish, FinishReason::ToolCalls);
+        match &response.data[0].parts()[..] {
+            [Part::ComputerCall {
+                id,
+                action,
+                safety_checks,
+                signature,
+                ..
+            }] => {
+                assert_eq!(id.as_deref(), Some("call_1"));
+                assert_eq!(signature.as_deref(), Some("cu_1"));
+                assert_eq!(
+                    action,
+                    &ComputerAction::Click {
+                        x: 10,
+                        y: 20,
+                        button: MouseButton::Left
+                    }
+                );
+                assert_eq!(safety_checks.len(), 1);
+            }
+            other => panic!("Unexpected parts: {:?}", other),
+        }
+
+        let mut messages = response.data;
+        messages.push(Message::User(vec![Part::ComputerResult {
+            id: Some("call_1".to_string()),
+            screenshot: "aW1n".to_string(),
+            mime_type: "image/png".to_string(),
+            finished: true,
+        }]));
+        let input = serde_json::to_value(convert_messages(messages)).unwrap();
+
+        assert_eq!(input[0]["type"], "computer_call");
+        assert_eq!(input[0]["id"], "cu_1");
+        assert_eq!(
+            input[1],
+            json!({
+                "type": "computer_call_output",
+                "call_id": "call_1",
+                "output": { "type": "computer_screenshot", "image_url": "data:image/png;base64,aW1n" },
+                "acknowledged_safety_checks": [
+                    { "id": "sc_1", "code": "malicious_instructions", "message": "Check the page." }
+                ]
+            })
+        );
+    }
+
+    #[test]
+    fn test_hosted_tools() {
+        let client = OpenAIResponsesClient::new(
+            "key".to_string(),
+            "https://api.openai.com/v1".to_string(),
+            ModelOptions::<OpenAIModel>::new("gpt-5"),
+            TransportOptions::default(),
+        )
+        .with_hosted_tool(ResponsesHostedTool::WebSearch)
+        .with_hosted_tool(ResponsesHostedTool::file_search(["vs_1"]));
+        let request = client
+            .build_request(None, vec![], vec![], false)
+            .unwrap()
+            .build()
+            .unwrap();
+        let body: Value =
+            serde_json::from_slice(request.body().unwrap().as_bytes().unwrap()).unwrap();
+        assert_eq!(
+            body["tools"],
+            json!([
+                { "type": "web_search" },
+                { "type": "file_search", "vector_store_ids": ["vs_1"] }
+            ])
+        );
+        assert_eq!(
+            body["include"],
+            json!(["web_search_call.action.sources", "file_search_call.results"])
+        );
+
+        let mut options = ModelOptions::<OpenAIModel>::new("gpt-5");
+        options.built_in_tools = Some(vec![BuiltInTool::CodeExecution]);
+        let request = serde_json::to_value(ResponsesRequest::new(vec![], &options, vec![], false));
+        let request = request.unwrap();
+        assert_eq!(
+            request["tools"],
+            json!([{ "type": "code_interpreter", "container": { "type": "auto" } }])
+        );
+        assert_eq!(request["include"], json!(["code_interpreter_call.outputs"]));
+
+        // A hosted tool enabled on both the client and the options is sent once,
+        // as configured on the client.
+        let mut options = ModelOptions::<OpenAIModel>::new("gpt-5");
+        options.built_in_tools = Some(vec![BuiltInTool::WebSearch, BuiltInTool::CodeExecution]);
+        let client = OpenAIResponsesClient::new(
+            "key".to_string(),
+            "https://api.openai.com/v1".to_string(),
+            options,
+            TransportOptions::default(),
+        )
+        .with_builtin_tool(json!({ "type": "web_search", "search_context_size": "high" }))
+        .with_hosted_tool(ResponsesHostedTool::WebSearch)
+        .with_hosted_tool(ResponsesHostedTool::CodeInterpreter);
+        let request = client
+            .build_request(None, vec![], vec![], false)
+            .unwrap()
+            .build()
+            .unwrap();
+        let body: Value =
+            serde_json::from_slice(request.body().unwrap().as_bytes().unwrap()).unwrap();
+        assert_eq!(
+            body["tools"],
+            json!([
+                { "type": "web_search", "search_context_size": "high" },
+                { "type": "code_interpreter", "container": { "type": "auto" } }
+            ])
+        );
+        assert_eq!(
+            body["include"],
+            json!([
+                "web_search_call.action.sources",
+                "code_interpreter_call.outputs"
+            ])
+        );
+
+        let body = json!({
+            "id": "resp_1",
+            "status": "completed",
+            "output": [
+                {
+                    "type": "web_search_call",
+                    "id": "ws_1",
+                    "status": "completed",
+                    "action": {
+                        "type": "search",
+                        "query": "rust release",
+                        "sources": [{ "type": "url", "url": "https://blog.rust-lang.org" }]
+                    }
+                },
+                {
+                    "type": "code_interpreter_call",
+                    "id": "ci_1",
+                    "status": "completed",
+                    "code": "print(1 + 1)",
+                    "container_id": "cntr_1",
+                    "outputs": [{ "type": "logs", "logs": "2\n" }]
+                },
+                { "type": "file_search_call", "id": "fs_1", "queries": ["refunds"], "results": null },
+                {
+                    "type": "message",
+                    "id": "msg_1",
+                    "content": [{
+                        "type": "output_text",
+                        "text": "Rust 1.90 is out.",
+                        "annotations": [{
+                            "type": "url_citation",
+                            "url": "https://blog.rust-lang.org",
+                            "title": "Rust Blog",
+                            "start_index": 0,
+                            "end_index": 17
+                        }]
+                    }]
+                }
+            ]
+        });
+        let response: Response = serde_json::from_value::<ResponsesResponse>(body)
+            .unwrap()
+            .into();
+
+        assert_eq!(response.finish, FinishReason::Stop);
+        match &response.data[0].parts()[..] {
+            [Part::HostedToolCall {
+                id,
+                call: HostedToolCall::WebSearch { query, sources },
+                ..
+            }, Part::HostedToolCall {
+                call: HostedToolCall::CodeInterpreter { code, outputs, .. },
+                ..
+            }, Part::HostedToolCall {
+                call: HostedToolCall::FileSearch { queries, results },
+                ..
+            }, Part::Text { .. }, Part::Citation { citation, .. }] => {
+                assert_eq!(id.as_deref(), Some("ws_1"));
+                assert_eq!(query.as_deref(), Some("rust release"));
+                assert_eq!(sources[0].url, "https://blog.rust-lang.org");
+                assert_eq!(code.as_deref(), Some("print(1 + 1)"));
+                assert_eq!(
+                    outputs,
+                    &vec![CodeOutput::Logs {
+                        logs: "2\n".to_string()
+                    }]
+                );
+                assert_eq!(queries, &vec!["refunds".to_string()]);
+                assert!(results.is_empty());
+                assert_eq!(citation.title.as_deref(), Some("Rust Blog"));
+                assert_eq!(citation.end_index, Some(17));
+            }
+            other => panic!("Unexpected parts: {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stream_events() {
+        let events = [
+            json!({ "type": "response.created", "response": { "id": "resp_1", "output": [] } }),
+            json!({ "type": "response.output_item.added", "output_index": 0,
+                "item": { "type": "reasoning", "id": "rs_1", "summary": [] } }),
+            json!({ "type": "response.reasoning_summary_text.delta", "output_index": 0,
+                "summary_index": 0, "delta": "Thinking" }),
+            json!({ "type": "response.output_item.done", "output_index": 0,
+                "item": { "type": "reasoning", "id": "rs_1", "summary": [], "encrypted_content": "abc" } }),
+            json!({ "type": "response.output_item.added", "output_index": 1,
+                "item": { "type": "message", "id": "msg_1", "role": "assistant", "content": [] } }),
+            json!({ "type": "response.output_text.delta", "output_index": 1,
+                "content_index": 0, "delta": "Hello" }),
+            json!({ "type": "response.output_text.delta", "output_index": 1,
+                "content_index": 0, "delta": " there" }),
+            json!({ "type": "response.output_item.done", "output_index": 1,
+                "item": { "type": "message", "id": "msg_1", "role": "assistant", "content": [] } }),
+            json!({ "type": "response.completed", "response": {
+                "id": "resp_1", "status": "completed", "output": [],
+                "usage": { "input_tokens": 3, "output_tokens": 5 } } }),
+        ];
+        let deltas = ResponsesStream::deltas(futures::stream::iter(
+            events.into_iter().map(|event| Ok(event.to_string())),
+        ));
+
+        let mut accumulator = ResponseAccumulator::new();
+        let deltas: Vec<StreamDelta> = deltas.map(|d| d.unwrap()).collect().await;
+        for delta in deltas {
+            accumulator.apply(delta);
+        }
+        let response = accumulator.into_response();
+
+        assert_eq!(response.finish, FinishReason::Stop);
+        assert_eq!(response.usage.completion_tokens, Some(5));
+        match &response.data[0].parts()[..] {
+            [Part::Reasoning {
+                content,
+                signature,
+                finished: true,
+                ..
+            }, Part::Text {
+                content: text,
+                finished: true,
+            }] => {
+                assert_eq!(content, "Thinking");
+                assert_eq!(signature.as_deref(), Some("rs_1:abc"));
+                assert_eq!(text, "Hello there");
+            }
+            other => panic!("Unexpected parts: {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_streamed_deltas_reasoning_visibility() {
+        use crate::testing::server::{TestResponse, TestServer};
+        use futures::TryStreamExt;
+
+        let events = [
+            json!({ "type": "response.output_item.added", "output_index": 0,
+                "item": { "type": "reasoning", "id": "rs_1", "summary": [] } }),
+            json!({ "type": "response.reasoning_summary_text.delta", "output_index": 0,
+                "summary_index": 0, "delta": "Thinking" }),
+            json!({ "type": "response.output_item.done", "output_index": 0,
+                "item": { "type": "reasoning", "id": "rs_1", "summary": [], "encrypted_content": "abc" } }),
+            json!({ "type": "response.output_text.delta", "output_index": 1,
+                "content_index": 0, "delta": "Hello" }),
+            json!({ "type": "response.completed", "response": {
+                "id": "resp_1", "status": "completed", "output": [] } }),
+        ];
+        let body: String = events
+            .iter()
+            .map(|event| format!("data: {}\n\n", event))
+            .collect();
+        let server = TestServer::start(move |_, _| {
+            TestResponse::new(200, body.clone()).with_header("content-type", "text/event-stream")
+        })
+        .await;
+        let reasoning = |visibility| {
+            let mut options = ModelOptions::<OpenAIModel>::new("gpt-5");
+            options.reasoning_visibility = Some(visibility);
+            let client = OpenAIResponsesClient::new(
+                "key".to_string(),
+                server.url().to_string(),
+                options,
+                TransportOptions::default(),
+            );
+            async move {
+                let deltas: Vec<StreamDelta> = client
+                    .request_stream_deltas(vec![], vec![])
+                    .await
+                    .unwrap()
+                    .try_collect()
+                    .await
+                    .unwrap();
+                let mut accumulator = ResponseAccumulator::new();
+                deltas
+                    .into_iter()
+                    .for_each(|delta| accumulator.apply(delta));
+                accumulator.into_response().data[0].parts()[0].clone()
+            }
+        };
+
+        let summary = |content: &str, summary: Option<&str>| Part::Reasoning {
+            content: content.to_string(),
+            summary: summary.map(str::to_string),
+            signature: Some("rs_1:abc".to_string()),
+            finished: true,
+        };
+        assert_eq!(
+            reasoning(ReasoningVisibility::Full).await,
+            summary("Thinking", Some("Thinking"))
+        );
+        assert_eq!(
+            reasoning(ReasoningVisibility::Summary).await,
+            summary("", Some("Thinking"))
+        );
+        assert_eq!(
+            reasoning(ReasoningVisibility::Hidden).await,
+            summary("", None)
+        );
+    }
+}
@@ -0,0 +1,119 @@
+//! Concurrent batch requests.
+//!
+//! [`BatchExt`] is implemented for every [`Client`] and sends many independent
+//! conversations with bounded concurrency, returning the results in input order.
+
+use async_trait::async_trait;
+use futures::StreamExt;
+use rmcp::model::Tool;
+
+use crate::client::{Client, ClientError};
+use crate::model::{Message, Response};
+
+/// Default number of requests in flight at once.
+pub const DEFAULT_CONCURRENCY: usize = 4;
+
+/// Extension trait for sending batches of requests.
+#[async_trait]
+pub trait BatchExt: Client {
+    /// Send one request per conversation, running at most `concurrency` at once.
+    ///
+    /// Results are returned in the same order as the conversations. A failed
+    /// request does not abort the rest of the batch.
+    async fn request_batch(
+        &self,
+        conversations: Vec<Vec<Message>>,
+        tools: Vec<Tool>,
+        concurrency: usize,
+    ) -> Vec<Result<Response, ClientError>> {
+        futures::stream::iter(conversations)
+            .map(|messages| self.request(messages, tools.clone()))
+            .buffered(concurrency.max(1))
+            .collect()
+            .await
+    }
+}
+
+impl<C: Client + ?Sized> BatchExt for C {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{FinishReason, Part, Usage};
+    use crate::options::{ModelOptions, TransportOptions};
+    use std::time::Duration;
+
+    struct DelayClient {
+        options: ModelOptions<()>,
+        transport: TransportOptions,
+    }
+
+    #[async_trait]
+    impl Client for DelayClient {
+        type ModelProvider = ();
+
+        async fn request(
+            &self,
+            messages: Vec<Message>,
+            _tools: Vec<Tool>,
+        ) -> Result<Response, ClientError> {
+            let text = messages[0].content().unwrap_or_default();
+            let delay: u64 = text
+                .parse()
+                .map_err(|_| ClientError::ProviderError(format!("Not a number: {}", text)))?;
+            tokio::time::sleep(Duration::from_millis(delay)).await;
+            Ok(Response {
+                data: vec![Message::Assistant(vec![Part::Text {
+                    content: text,
+                    finished: true,
+                }])],
+                usage: Usage::default(),
+                finish: FinishReason::Stop,
+                alternatives: Vec::new(),
+                model: None,
+                iterations: Vec::new(),
+            })
+        }
+
+        fn model_options(&self) -> &ModelOptions<()> {
+            &self.options
+        }
+
+        fn transport_options(&self) -> &TransportOptions {
+            &self.transport
+        }
+    }
+
+    #[tokio::test]
+    async fn test_request_batch_preserves_order() {
+        let client = DelayClient {
+            options: ModelOptions::new("test-model"),
+            transport: TransportOptions::default(),
+        };
+        let conversations = ["30", "x", "10", "20"]
+            .iter()
+            .map(|t| {
+                vec![Message::User(vec![Part::Text {
+                    content: t.to_string(),
+                    finished: true,
+                }])]
+            })
+            .collect();
+
+        let results = client.request_batch(conversations, vec![], 2).await;
+
+        let contents: Vec<Option<String>> = results
+            .iter()
+            .map(|r| r.as_ref().ok().and_then(|r| r.data[0].content()))
+            .collect();
+        assert_eq!(
+            contents,
+            vec![
+                Some("30".into()),
+                None,
+                Some("10".into()),
+                Some("20".into())
+            ]
+        );
+    }
+}
@@ -0,0 +1,465 @@
+//! Response caching.
+//!
+//! A [`CachedClient`] answers repeated requests from a [`CacheStore`] instead of
+//! sending them again. Requests are keyed on the provider, model options,
+//! messages and tools, so any change to the prompt or settings is a cache miss.
+//! This makes deterministic evaluation runs and test suites free after the
+//! first run:
+//!
+//! ```ignore
+//! use unia::cache::{CachedClient, DiskCache};
+//!
+//! let client = CachedClient::new(client, DiskCache::new(".cache/llm"));
+//! let response = client.request(messages, vec![]).await?;
+//! ```
+//!
+//! [`InMemoryCache`] and [`DiskCache`] are provided. Stores map string keys to
+//! JSON-serializable responses, so a key-value service such as Redis only needs
+//! `GET` and `SET` to implement the trait.
+
+use async_trait::async_trait;
+use futures::{Stream, StreamExt};
+use rmcp::model::Tool;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use tracing::{debug, warn};
+
+use crate::client::{Capabilities, Client, ClientError, ModelInfo, StreamingClient};
+use crate::layer::ClientLayer;
+use crate::model::{FinishReason, Message, Response};
+use crate::options::{ModelOptions, TransportOptions};
+
+/// Storage backend for cached responses.
+#[async_trait]
+pub trait CacheStore: Send + Sync {
+    /// Look up a response, returning `None` on a miss.
+    async fn get(&self, key: &str) -> Result<Option<Response>, ClientError>;
+
+    /// Store a response, replacing any previous one under the key.
+    async fn put(&self, key: &str, response: &Response) -> Result<(), ClientError>;
+}
+
+/// Cache kept in process memory, evicting the least recently used response
+/// once it is full.
+#[derive(Debug)]
+pub struct InMemoryCache {
+    capacity: usize,
+    entries: Mutex<LruEntries>,
+}
+
+#[derive(Debug, Default)]
+struct LruEntries {
+    /// Responses with the tick of their last use.
+    responses: HashMap<String, (Response, u64)>,
+    tick: u64,
+}
+
+impl LruEntries {
+    fn next_tick(&mut self) -> u64 {
+        self.tick += 1;
+        self.tick
+    }
+}
+
+impl InMemoryCache {
+    /// Create a cache holding at most `capacity` responses.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            entries: Mutex::new(LruEntries::default()),
+        }
+    }
+
+    /// Number of cached responses.
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().responses.len()
+    }
+
+    /// Check whether the cache is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[async_trait]
+impl CacheStore for InMemoryCache {
+    async fn get(&self, key: &str) -> Result<Option<Response>, ClientError> {
+        let mut entries = self.entries.lock().unwrap();
+        let tick = entries.next_tick();
+        Ok(entries.responses.get_mut(key).map(|(response, used)| {
+            *used = tick;
+            response.clone()
+        }))
+    }
+
+    async fn put(&self, key: &str, response: &Response) -> Result<(), ClientError> {
+        let mut entries = self.entries.lock().unwrap();
+        if !entries.responses.contains_key(key) && entries.responses.len() >= self.capacity {
+            let oldest = entries
+                .responses
+                .iter()
+                .min_by_key(|(_, (_, used))| *used)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                entries.responses.remove(&oldest);
+            }
+        }
+        let tick = entries.next_tick();
+        entries
+            .responses
+            .insert(key.to_string(), (response.clone(), tick));
+        Ok(())
+    }
+}
+
+/// Cache writing one JSON file per response into a directory.
+///
+/// Entries never expire; delete the directory to clear the cache.
+#[derive(Debug, Clone)]
+pub struct DiskCache {
+    dir: PathBuf,
+}
+
+impl DiskCache {
+    /// Create a cache in the given directory. The directory is created on first write.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", key))
+    }
+}
+
+fn io_error(action: &str, path: &std::path::Path, e: std::io::Error) -> ClientError {
+    ClientError::Config(format!("Failed to {} {}: {}", action, path.display(), e))
+}
+
+#[async_trait]
+impl CacheStore for DiskCache {
+    async fn get(&self, key: &str) -> Result<Option<Response>, ClientError> {
+        let path = self.path(key);
+        match tokio::fs::read(&path).await {
+            Ok(bytes) => Ok(Some(serde_json::from_slice(&bytes)?)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(io_error("read", &path, e)),
+        }
+    }
+
+    async fn put(&self, key: &str, response: &Response) -> Result<(), ClientError> {
+        let path = self.path(key);
+        tokio::fs::create_dir_all(&self.dir)
+            .await
+            .map_err(|e| io_error("create", &self.dir, e))?;
+        let json = serde_json::to_vec(response)?;
+        tokio::fs::write(&path, json)
+            .await
+            .map_err(|e| io_error("write", &path, e))
+    }
+}
+
+/// Compute the cache key of a request.
+///
+/// The key is a 128-bit FNV-1a hash of the serialized request. Unlike
+/// [`std::hash::DefaultHasher`], it is the same in every process, so it can be
+/// used with persistent stores.
+pub fn cache_key<T: Serialize>(
+    provider: &str,
+    options: &ModelOptions<T>,
+    messages: &[Message],
+    tools: &[Tool],
+) -> Result<String, ClientError> {
+    const OFFSET: u128 = 0x6c62272e07bb014262b821756295c58d;
+    const PRIME: u128 = 0x0000000001000000000000000000013b;
+
+    let request = serde_json::to_vec(&(provider, options, messages, tools))?;
+    let hash = request.iter().fold(OFFSET, |hash, byte| {
+        (hash ^ *byte as u128).wrapping_mul(PRIME)
+    });
+    Ok(format!("{:032x}", hash))
+}
+
+/// Only complete responses are worth replaying.
+fn is_cacheable(response: &Response) -> bool {
+    !matches!(
+        response.finish,
+        FinishReason::Error | FinishReason::Unfinished
+    )
+}
+
+/// Layer answering repeated requests from a [`CacheStore`].
+#[derive(Clone)]
+pub struct CacheLayer {
+    store: Arc<dyn CacheStore>,
+}
+
+impl CacheLayer {
+    /// Create a layer backed by the given store.
+    pub fn new<S: CacheStore + 'static>(store: S) -> Self {
+        Self::from_arc(Arc::new(store))
+    }
+
+    /// Create a layer backed by a shared store.
+    pub fn from_arc(store: Arc<dyn CacheStore>) -> Self {
+        Self { store }
+    }
+}
+
+impl<C: Client> ClientLayer<C> for CacheLayer {
+    type Client = CachedClient<C>;
+
+    fn layer(&self, inner: C) -> CachedClient<C> {
+        CachedClient {
+            inner,
+            store: self.store.clone(),
+        }
+    }
+}
+
+/// Client wrapper returning cached responses for repeated requests.
+///
+/// Failures of the store are logged and the request is sent as if it missed,
+/// so a broken cache never breaks the client. Streamed requests are cached
+/// once the stream completes, and a hit is replayed as a single snapshot.
+#[derive(Clone)]
+pub struct CachedClient<C> {
+    inner: C,
+    store: Arc<dyn CacheStore>,
+}
+
+impl<C: Client> CachedClient<C>
+where
+    C::ModelProvider: Serialize,
+{
+    /// Wrap a client with the given store.
+    pub fn new<S: CacheStore + 'static>(inner: C, store: S) -> Self {
+        CacheLayer::new(store).layer(inner)
+    }
+
+    /// Consume the wrapper and return the inner client.
+    pub fn into_inner(self) -> C {
+        self.inner
+    }
+
+    /// Look up a request, returning its key for storing the response on a miss.
+    async fn lookup(
+        &self,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> (Option<String>, Option<Response>) {
+        let key = match cache_key(
+            self.inner.provider(),
+            self.inner.model_options(),
+            messages,
+            tools,
+        ) {
+            Ok(key) => key,
+            Err(e) => {
+                warn!("Failed to compute cache key: {}", e);
+                return (None, None);
+            }
+        };
+        match self.store.get(&key).await {
+            Ok(Some(response)) => {
+                debug!("Response cache hit for {}", key);
+                (Some(key), Some(response))
+            }
+            Ok(None) => (Some(key), None),
+            Err(e) => {
+                warn!("Failed to read response cache: {}", e);
+                (Some(key), None)
+            }
+        }
+    }
+}
+
+async fn store(store: &dyn CacheStore, key: &str, response: &Response) {
+    if !is_cacheable(response) {
+        return;
+    }
+    if let Err(e) = store.put(key, response).await {
+        warn!("Failed to write response cache: {}", e);
+    }
+}
+
+#[async_trait]
+impl<C: Client> Client for CachedClient<C>
+where
+    C::ModelProvider: Serialize,
+{
+    type ModelProvider = C::ModelProvider;
+
+    async fn request(
+        &self,
+        messages: Vec<Message>,
+        tools: Vec<Tool>,
+    ) -> Result<Response, ClientError> {
+        let (key, cached) = self.lookup(&messages, &tools).await;
+        if let Some(response) = cached {
+            return Ok(response);
+        }
+
+        let response = self.inner.request(messages, tools).await?;
+        if let Some(key) = key {
+            store(self.store.as_ref(), &key, &response).await;
+        }
+        Ok(response)
+    }
+
+    fn model_options(&self) -> &ModelOptions<Self::ModelProvider> {
+        self.inner.model_options()
+    }
+
+    fn transport_options(&self) -> &TransportOptions {
+        self.inner.transport_options()
+    }
+
+    fn provider(&self) -> &'static str {
+        self.inner.provider()
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        self.inner.capabilities()
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>, ClientError> {
+        self.inner.list_models().await
+    }
+}
+
+#[async_trait]
+impl<C: StreamingClient> StreamingClient for CachedClient<C>
+where
+    C::ModelProvider: Serialize,
+{
+    async fn request_stream(
+        &self,
+        messages: Vec<Message>,
+        tools: Vec<Tool>,
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<Response, ClientError>> + Send>>, ClientError>
+    {
+        let (key, cached) = self.lookup(&messages, &tools).await;
+        if let Some(response) = cached {
+            return Ok(Box::pin(futures::stream::once(async { Ok(response) })));
+        }
+
+        let mut inner = self.inner.request_stream(messages, tools).await?;
+        let Some(key) = key else {
+            return Ok(inner);
+        };
+        let cache = self.store.clone();
+        Ok(Box::pin(async_stream::stream! {
+            let mut last = None;
+            let mut failed = false;
+            while let Some(item) = inner.next().await {
+                match &item {
+                    Ok(response) => last = Some(response.clone()),
+                    Err(_) => failed = true,
+                }
+                yield item;
+            }
+            if let (Some(response), false) = (last, failed) {
+                store(cache.as_ref(), &key, &response).await;
+            }
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{Part, Usage};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    struct CountingClient {
+        options: ModelOptions<()>,
+        transport: TransportOptions,
+        calls: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl Client for CountingClient {
+        type ModelProvider = ();
+
+        async fn request(
+            &self,
+            messages: Vec<Message>,
+            _tools: Vec<Tool>,
+        ) -> Result<Response, ClientError> {
+            let calls = self.calls.fetch_add(1, Ordering::SeqCst) + 1;
+            Ok(Response {
+                data: vec![Message::Assistant(vec![Part::Text {
+                    content: format!("{} #{}", messages[0].content().unwrap(), calls),
+                    finished: true,
+                }])],
+                usage: Usage::default(),
+                finish: FinishReason::Stop,
+                alternatives: Vec::new(),
+                model: None,
+                iterations: Vec::new(),
+            })
+        }
+
+        fn model_options(&self) -> &ModelOptions<()> {
+            &self.options
+        }
+
+        fn transport_options(&self) -> &TransportOptions {
+            &self.transport
+        }
+    }
+
+    fn user(text: &str) -> Vec<Message> {
+        vec![Message::User(vec![Part::Text {
+            content: text.to_string(),
+            finished: true,
+        }])]
+    }
+
+    #[tokio::test]
+    async fn test_cached_client() {
+        let client = CachedClient::new(
+            CountingClient {
+                options: ModelOptions::new("test-model"),
+                transport: TransportOptions::default(),
+                calls: AtomicUsize::new(0),
+            },
+            InMemoryCache::new(1),
+        );
+
+        let answer = |response: Response| response.data[0].content().unwrap();
+        assert_eq!(
+            answer(client.request(user("a"), vec![]).await.unwrap()),
+            "a #1"
+        );
+        assert_eq!(
+            answer(client.request(user("a"), vec![]).await.unwrap()),
+            "a #1"
+        );
+        assert_eq!(
+            answer(client.request(user("b"), vec![]).await.unwrap()),
+            "b #2"
+        );
+        // "a" was evicted to make room for "b".
+        assert_eq!(
+            answer(client.request(user("a"), vec![]).await.unwrap()),
+            "a #3"
+        );
+    }
+
+    #[test]
+    fn test_cache_key() {
+        let options = ModelOptions::<()>::new("test-model");
+        let key = cache_key("openai", &options, &user("a"), &[]).unwrap();
+        assert_eq!(key.len(), 32);
+        assert_eq!(key, cache_key("openai", &options, &user("a"), &[]).unwrap());
+        assert_ne!(
+            key,
+            cache_key("anthropic", &options, &user("a"), &[]).unwrap()
+        );
+        assert_ne!(key, cache_key("openai", &options, &user("b"), &[]).unwrap());
+    }
+}
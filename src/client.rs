@@ -2,11 +2,16 @@
 
 use async_trait::async_trait;
 use futures::Stream;
+use serde::{Deserialize, Serialize};
+use std::pin::Pin;
+use std::sync::Arc;
 use thiserror::Error;
 
 use crate::model::{Message, Response};
 use crate::options::{ModelOptions, TransportOptions};
+use crate::stream::{cancellable, deltas_from_snapshots, StreamDelta};
 use rmcp::model::Tool;
+use tokio_util::sync::CancellationToken;
 
 /// Errors that can occur during client operations.
 #[derive(Error, Debug)]
@@ -20,11 +25,108 @@ pub enum ClientError {
     #[error("Provider error: {0}")]
     ProviderError(String),
 
+    #[error("HTTP {status}: {message}")]
+    Status { status: u16, message: String },
+
     #[error("Stream cancelled")]
     StreamCancelled,
 
+    #[error("Deadline exceeded")]
+    DeadlineExceeded,
+
     #[error("Configuration error: {0}")]
     Config(String),
+
+    #[error("Policy violation: {0}")]
+    PolicyViolation(String),
+}
+
+impl ClientError {
+    /// HTTP status of the failed response, if the error came from one.
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            ClientError::Status { status, .. } => Some(*status),
+            ClientError::Http(e) => e.status().map(|s| s.as_u16()),
+            _ => None,
+        }
+    }
+
+    /// Whether the error is likely to go away on its own, such as a rate limit,
+    /// a server error or a connection failure.
+    pub fn is_transient(&self) -> bool {
+        if let ClientError::Http(e) = self {
+            if e.is_connect() || e.is_timeout() {
+                return true;
+            }
+        }
+        matches!(self.status(), Some(429 | 500..=599))
+    }
+}
+
+/// Features supported by a client's provider and model.
+///
+/// Higher-level code can check these to degrade gracefully, e.g. by not sending
+/// tools or images to a model that cannot handle them. The default describes a
+/// client with no known capabilities.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Capabilities {
+    /// Function calling.
+    pub supports_tools: bool,
+    /// Image input.
+    pub supports_vision: bool,
+    /// Streaming responses.
+    pub supports_streaming: bool,
+    /// A dedicated system prompt.
+    pub supports_system_prompt: bool,
+    /// Size of the context window in tokens, if known.
+    pub max_context_tokens: Option<u32>,
+    /// Native JSON output mode.
+    pub supports_json_mode: bool,
+}
+
+/// Kind of content a model accepts or produces.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Modality {
+    Text,
+    Image,
+    Audio,
+    Video,
+    Document,
+}
+
+impl Modality {
+    /// Parse the modality names used by provider model catalogs.
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "text" => Some(Modality::Text),
+            "image" | "images" => Some(Modality::Image),
+            "audio" => Some(Modality::Audio),
+            "video" => Some(Modality::Video),
+            "file" | "document" | "pdf" => Some(Modality::Document),
+            _ => None,
+        }
+    }
+}
+
+/// A model offered by a provider, as reported by [`Client::list_models`].
+///
+/// Providers report different details; fields a provider does not report are
+/// `None` or empty.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ModelInfo {
+    /// Identifier to use as the model name in requests.
+    pub id: String,
+    /// Human-readable name.
+    pub display_name: Option<String>,
+    /// Size of the context window in tokens.
+    pub context_window: Option<u32>,
+    /// Maximum number of output tokens.
+    pub max_output_tokens: Option<u32>,
+    /// Accepted input modalities.
+    pub input_modalities: Vec<Modality>,
+    /// Date after which the model is no longer served.
+    pub deprecation_date: Option<String>,
 }
 
 /// Main client trait for LLM providers.
@@ -40,11 +142,48 @@ pub trait Client: Send + Sync {
         tools: Vec<Tool>,
     ) -> Result<Response, ClientError>;
 
+    /// Send a request that is aborted when `cancel` is triggered.
+    ///
+    /// The in-flight HTTP request is dropped and [`ClientError::StreamCancelled`]
+    /// is returned.
+    async fn request_with_cancellation(
+        &self,
+        messages: Vec<Message>,
+        tools: Vec<Tool>,
+        cancel: CancellationToken,
+    ) -> Result<Response, ClientError> {
+        tokio::select! {
+            biased;
+            _ = cancel.cancelled() => Err(ClientError::StreamCancelled),
+            response = self.request(messages, tools) => response,
+        }
+    }
+
     /// Get reference to the model options.
     fn model_options(&self) -> &ModelOptions<Self::ModelProvider>;
 
     /// Get reference to the transport options.
     fn transport_options(&self) -> &TransportOptions;
+
+    /// Identifier of the provider backing this client (e.g. `openai`, `anthropic`).
+    fn provider(&self) -> &'static str {
+        "unknown"
+    }
+
+    /// Features supported by the configured provider and model.
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::default()
+    }
+
+    /// List the models offered by the provider.
+    ///
+    /// Returns [`ClientError::Config`] for clients without a model catalog.
+    async fn list_models(&self) -> Result<Vec<ModelInfo>, ClientError> {
+        Err(ClientError::Config(format!(
+            "Provider {} does not support listing models",
+            self.provider()
+        )))
+    }
 }
 
 /// Extension trait for streaming support.
@@ -59,4 +198,272 @@ pub trait StreamingClient: Client {
         std::pin::Pin<Box<dyn Stream<Item = Result<Response, ClientError>> + Send>>,
         ClientError,
     >;
+
+    /// Send a streaming request and yield incremental [`StreamDelta`] events.
+    ///
+    /// Unlike [`request_stream`](Self::request_stream), which yields the full response
+    /// accumulated so far on every event, this only yields what changed. The default
+    /// implementation derives the deltas from `request_stream`; built-in providers
+    /// produce them natively.
+    async fn request_stream_deltas(
+        &self,
+        messages: Vec<Message>,
+        tools: Vec<Tool>,
+    ) -> Result<
+        std::pin::Pin<Box<dyn Stream<Item = Result<StreamDelta, ClientError>> + Send>>,
+        ClientError,
+    > {
+        let stream = self.request_stream(messages, tools).await?;
+        Ok(Box::pin(deltas_from_snapshots(stream)))
+    }
+
+    /// Send a streaming request that is aborted when `cancel` is triggered.
+    ///
+    /// After cancellation the stream yields [`ClientError::StreamCancelled`] once
+    /// and ends, closing the underlying connection.
+    async fn request_stream_with_cancellation(
+        &self,
+        messages: Vec<Message>,
+        tools: Vec<Tool>,
+        cancel: CancellationToken,
+    ) -> Result<
+        std::pin::Pin<Box<dyn Stream<Item = Result<Response, ClientError>> + Send>>,
+        ClientError,
+    > {
+        let stream = tokio::select! {
+            biased;
+            _ = cancel.cancelled() => return Err(ClientError::StreamCancelled),
+            stream = self.request_stream(messages, tools) => stream?,
+        };
+        Ok(Box::pin(cancellable(stream, cancel)))
+    }
+}
+
+type ResponseStream = Pin<Box<dyn Stream<Item = Result<Response, ClientError>> + Send>>;
+type DeltaStream = Pin<Box<dyn Stream<Item = Result<StreamDelta, ClientError>> + Send>>;
+
+/// Object-safe counterpart of [`StreamingClient`].
+///
+/// [`Client`] has an associated model options type, so clients of different
+/// providers cannot share a trait object. Every streaming client implements
+/// `DynClient`, which hides that type; use it through [`BoxedClient`], which
+/// implements [`Client`] and [`StreamingClient`] again.
+///
+/// The methods carry a `dyn_` prefix so they do not shadow the [`Client`]
+/// methods when both traits are in scope.
+#[async_trait]
+pub trait DynClient: Send + Sync {
+    /// See [`Client::request`].
+    async fn dyn_request(
+        &self,
+        messages: Vec<Message>,
+        tools: Vec<Tool>,
+    ) -> Result<Response, ClientError>;
+
+    /// See [`Client::request_with_cancellation`].
+    async fn dyn_request_with_cancellation(
+        &self,
+        messages: Vec<Message>,
+        tools: Vec<Tool>,
+        cancel: CancellationToken,
+    ) -> Result<Response, ClientError>;
+
+    /// See [`StreamingClient::request_stream`].
+    async fn dyn_request_stream(
+        &self,
+        messages: Vec<Message>,
+        tools: Vec<Tool>,
+    ) -> Result<ResponseStream, ClientError>;
+
+    /// See [`StreamingClient::request_stream_deltas`].
+    async fn dyn_request_stream_deltas(
+        &self,
+        messages: Vec<Message>,
+        tools: Vec<Tool>,
+    ) -> Result<DeltaStream, ClientError>;
+
+    /// Model options without the provider-specific part.
+    fn dyn_model_options(&self) -> ModelOptions<()>;
+
+    /// See [`Client::transport_options`].
+    fn dyn_transport_options(&self) -> &TransportOptions;
+
+    /// See [`Client::provider`].
+    fn dyn_provider(&self) -> &'static str;
+
+    /// See [`Client::capabilities`].
+    fn dyn_capabilities(&self) -> Capabilities;
+
+    /// See [`Client::list_models`].
+    async fn dyn_list_models(&self) -> Result<Vec<ModelInfo>, ClientError>;
+}
+
+#[async_trait]
+impl<C: StreamingClient> DynClient for C {
+    async fn dyn_request(
+        &self,
+        messages: Vec<Message>,
+        tools: Vec<Tool>,
+    ) -> Result<Response, ClientError> {
+        self.request(messages, tools).await
+    }
+
+    async fn dyn_request_with_cancellation(
+        &self,
+        messages: Vec<Message>,
+        tools: Vec<Tool>,
+        cancel: CancellationToken,
+    ) -> Result<Response, ClientError> {
+        self.request_with_cancellation(messages, tools, cancel)
+            .await
+    }
+
+    async fn dyn_request_stream(
+        &self,
+        messages: Vec<Message>,
+        tools: Vec<Tool>,
+    ) -> Result<ResponseStream, ClientError> {
+        self.request_stream(messages, tools).await
+    }
+
+    async fn dyn_request_stream_deltas(
+        &self,
+        messages: Vec<Message>,
+        tools: Vec<Tool>,
+    ) -> Result<DeltaStream, ClientError> {
+        self.request_stream_deltas(messages, tools).await
+    }
+
+    fn dyn_model_options(&self) -> ModelOptions<()> {
+        self.model_options().erased()
+    }
+
+    fn dyn_transport_options(&self) -> &TransportOptions {
+        self.transport_options()
+    }
+
+    fn dyn_provider(&self) -> &'static str {
+        self.provider()
+    }
+
+    fn dyn_capabilities(&self) -> Capabilities {
+        self.capabilities()
+    }
+
+    async fn dyn_list_models(&self) -> Result<Vec<ModelInfo>, ClientError> {
+        self.list_models().await
+    }
+}
+
+/// A client of any provider behind a shared trait object.
+///
+/// Boxed clients of different providers have the same type, so they can be
+/// kept in a `Vec` or a registry built from configuration. Cloning is cheap and
+/// shares the underlying client.
+///
+/// ```ignore
+/// use unia::client::BoxedClient;
+///
+/// let clients: Vec<BoxedClient> = vec![
+///     BoxedClient::new(OpenAI::create(openai_key, "gpt-5".into())),
+///     BoxedClient::new(Anthropic::create(anthropic_key, "claude-sonnet-4-5".into())),
+/// ];
+/// let agent = Agent::new(clients[1].clone());
+/// ```
+#[derive(Clone)]
+pub struct BoxedClient {
+    client: Arc<dyn DynClient>,
+    model_options: ModelOptions<()>,
+}
+
+impl BoxedClient {
+    /// Box a streaming client.
+    pub fn new<C: StreamingClient + 'static>(client: C) -> Self {
+        Self::from_arc(Arc::new(client))
+    }
+
+    /// Wrap a shared client.
+    pub fn from_arc(client: Arc<dyn DynClient>) -> Self {
+        Self {
+            model_options: client.dyn_model_options(),
+            client,
+        }
+    }
+
+    /// The underlying client.
+    pub fn inner(&self) -> &Arc<dyn DynClient> {
+        &self.client
+    }
+}
+
+impl std::fmt::Debug for BoxedClient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BoxedClient")
+            .field("provider", &self.client.dyn_provider())
+            .field("model", &self.model_options.model)
+            .finish()
+    }
+}
+
+#[async_trait]
+impl Client for BoxedClient {
+    type ModelProvider = ();
+
+    async fn request(
+        &self,
+        messages: Vec<Message>,
+        tools: Vec<Tool>,
+    ) -> Result<Response, ClientError> {
+        self.client.dyn_request(messages, tools).await
+    }
+
+    async fn request_with_cancellation(
+        &self,
+        messages: Vec<Message>,
+        tools: Vec<Tool>,
+        cancel: CancellationToken,
+    ) -> Result<Response, ClientError> {
+        self.client
+            .dyn_request_with_cancellation(messages, tools, cancel)
+            .await
+    }
+
+    fn model_options(&self) -> &ModelOptions<()> {
+        &self.model_options
+    }
+
+    fn transport_options(&self) -> &TransportOptions {
+        self.client.dyn_transport_options()
+    }
+
+    fn provider(&self) -> &'static str {
+        self.client.dyn_provider()
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        self.client.dyn_capabilities()
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>, ClientError> {
+        self.client.dyn_list_models().await
+    }
+}
+
+#[async_trait]
+impl StreamingClient for BoxedClient {
+    async fn request_stream(
+        &self,
+        messages: Vec<Message>,
+        tools: Vec<Tool>,
+    ) -> Result<ResponseStream, ClientError> {
+        self.client.dyn_request_stream(messages, tools).await
+    }
+
+    async fn request_stream_deltas(
+        &self,
+        messages: Vec<Message>,
+        tools: Vec<Tool>,
+    ) -> Result<DeltaStream, ClientError> {
+        self.client.dyn_request_stream_deltas(messages, tools).await
+    }
 }
@@ -0,0 +1,134 @@
+//! Computer-use tool types.
+//!
+//! Computer-use models operate a graphical interface: they ask for UI actions
+//! such as clicks and key presses, and expect a screenshot of the screen after
+//! each one. Enable the provider-native tool on the client
+//! ([`AnthropicModel::computer_use`](crate::api::anthropic::AnthropicModel::computer_use)
+//! or [`OpenAIResponsesClient::with_computer_use`](crate::api::openai_responses::OpenAIResponsesClient::with_computer_use)),
+//! then answer each [`Part::ComputerCall`](crate::model::Part::ComputerCall) with a
+//! [`Part::ComputerResult`](crate::model::Part::ComputerResult) carrying the id of
+//! the call:
+//!
+//! ```ignore
+//! let response = client.request(messages.clone(), vec![]).await?;
+//! for part in response.data[0].parts() {
+//!     if let Part::ComputerCall { id, action, .. } = part {
+//!         let screenshot = desktop.perform(action).await?;
+//!         results.push(Part::ComputerResult {
+//!             id: id.clone(),
+//!             screenshot,
+//!             mime_type: "image/png".to_string(),
+//!             finished: true,
+//!         });
+//!     }
+//! }
+//! ```
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// The screen a computer-use model operates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ComputerDisplay {
+    /// Width of the screen in pixels.
+    pub width: u32,
+    /// Height of the screen in pixels.
+    pub height: u32,
+    /// Kind of environment the screen shows.
+    #[serde(default)]
+    pub environment: ComputerEnvironment,
+}
+
+impl ComputerDisplay {
+    /// Describe a browser display of the given size.
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            environment: ComputerEnvironment::default(),
+        }
+    }
+
+    /// Set the environment.
+    pub fn with_environment(mut self, environment: ComputerEnvironment) -> Self {
+        self.environment = environment;
+        self
+    }
+}
+
+/// Kind of environment a computer-use model operates.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ComputerEnvironment {
+    #[default]
+    Browser,
+    Mac,
+    Windows,
+    Ubuntu,
+}
+
+/// A point on the screen, in pixels from the top left corner.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct Coordinate {
+    pub x: i32,
+    pub y: i32,
+}
+
+/// A mouse button.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MouseButton {
+    #[default]
+    Left,
+    Right,
+    Wheel,
+    Back,
+    Forward,
+}
+
+/// A UI action requested by a computer-use model.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ComputerAction {
+    /// Take a screenshot without acting.
+    Screenshot,
+    /// Click a button at a position.
+    Click {
+        x: i32,
+        y: i32,
+        #[serde(default)]
+        button: MouseButton,
+    },
+    /// Double-click the left button at a position.
+    DoubleClick { x: i32, y: i32 },
+    /// Move the pointer to a position.
+    Move { x: i32, y: i32 },
+    /// Drag the pointer along a path with the left button pressed.
+    Drag { path: Vec<Coordinate> },
+    /// Scroll at a position. Positive amounts scroll right and down.
+    Scroll {
+        x: i32,
+        y: i32,
+        scroll_x: i32,
+        scroll_y: i32,
+    },
+    /// Type text.
+    Type { text: String },
+    /// Press a key combination, e.g. `["ctrl", "s"]`.
+    Keypress { keys: Vec<String> },
+    /// Wait for the screen to settle.
+    Wait,
+}
+
+/// A safety check raised by the provider for a computer action.
+///
+/// Returning a result for the action acknowledges its checks, so applications
+/// should confirm them with the user before performing the action.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct ComputerSafetyCheck {
+    pub id: String,
+    #[serde(default)]
+    pub code: Option<String>,
+    #[serde(default)]
+    pub message: Option<String>,
+}
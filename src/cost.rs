@@ -0,0 +1,376 @@
+//! Usage and cost accounting.
+//!
+//! A [`PriceTable`] maps model identifiers to per-token prices and turns a
+//! [`Usage`] into a cost in USD. The default table covers common models with
+//! list prices at the time of writing; prices change, so override or extend it
+//! with [`PriceTable::with_price`] where accuracy matters.
+//!
+//! A [`CostTracker`] accumulates usage and cost across requests, overall and per
+//! conversation. It can be shared between agents:
+//!
+//! ```ignore
+//! use std::sync::Arc;
+//! use unia::cost::CostTracker;
+//!
+//! let tracker = Arc::new(CostTracker::new());
+//! let agent = Agent::new(client).with_cost_tracker(tracker.clone());
+//! agent.chat_in(&mut conversation, message).await?;
+//!
+//! let summary = tracker.conversation(conversation.id()).unwrap();
+//! println!("{} requests, ${:.4}", summary.requests, summary.cost);
+//! ```
+//!
+//! Usage of agents tagged with an [experiment](crate::experiment) variant is
+//! also accumulated per variant.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+
+use crate::model::{Response, Usage};
+
+/// Prices of a model in USD per million tokens.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ModelPricing {
+    /// Price of uncached prompt tokens.
+    pub input_per_million: f64,
+    /// Price of completion tokens, including reasoning tokens.
+    pub output_per_million: f64,
+    /// Price of prompt tokens read from the prompt cache. Defaults to the input price.
+    pub cached_input_per_million: Option<f64>,
+    /// Price of prompt tokens written to the prompt cache. Defaults to the input price.
+    pub cache_write_per_million: Option<f64>,
+}
+
+impl ModelPricing {
+    /// Create pricing without a cache discount.
+    pub fn new(input_per_million: f64, output_per_million: f64) -> Self {
+        Self {
+            input_per_million,
+            output_per_million,
+            cached_input_per_million: None,
+            cache_write_per_million: None,
+        }
+    }
+
+    /// Set the price of cached prompt tokens.
+    pub fn with_cached_input(mut self, cached_input_per_million: f64) -> Self {
+        self.cached_input_per_million = Some(cached_input_per_million);
+        self
+    }
+
+    /// Set the price of prompt tokens written to the cache.
+    pub fn with_cache_write(mut self, cache_write_per_million: f64) -> Self {
+        self.cache_write_per_million = Some(cache_write_per_million);
+        self
+    }
+
+    /// Cost of the given usage in USD.
+    pub fn cost(&self, usage: &Usage) -> f64 {
+        let prompt = usage.prompt_tokens.unwrap_or(0);
+        let cached = usage.cached_tokens.unwrap_or(0).min(prompt);
+        let written = usage
+            .cache_creation_tokens
+            .unwrap_or(0)
+            .min(prompt - cached);
+        let completion = usage.completion_tokens.unwrap_or(0);
+        let cached_price = self
+            .cached_input_per_million
+            .unwrap_or(self.input_per_million);
+        let write_price = self
+            .cache_write_per_million
+            .unwrap_or(self.input_per_million);
+
+        ((prompt - cached - written) as f64 * self.input_per_million
+            + cached as f64 * cached_price
+            + written as f64 * write_price
+            + completion as f64 * self.output_per_million)
+            / 1_000_000.0
+    }
+}
+
+/// Per-model prices used to compute costs.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PriceTable {
+    prices: HashMap<String, ModelPricing>,
+}
+
+static DEFAULT_PRICES: LazyLock<PriceTable> = LazyLock::new(PriceTable::default);
+
+impl Default for PriceTable {
+    /// Indicative list prices for common models.
+    ///
+    /// Anthropic models are billed 1.25 times the input price for cache writes.
+    fn default() -> Self {
+        let prices = [
+            ("gpt-4o", 2.5, 10.0, 1.25),
+            ("gpt-4o-mini", 0.15, 0.6, 0.075),
+            ("gpt-4.1", 2.0, 8.0, 0.5),
+            ("gpt-4.1-mini", 0.4, 1.6, 0.1),
+            ("gpt-4.1-nano", 0.1, 0.4, 0.025),
+            ("gpt-5", 1.25, 10.0, 0.125),
+            ("gpt-5-mini", 0.25, 2.0, 0.025),
+            ("o3", 2.0, 8.0, 0.5),
+            ("o4-mini", 1.1, 4.4, 0.275),
+            ("claude-opus-4", 15.0, 75.0, 1.5),
+            ("claude-opus-4-5", 5.0, 25.0, 0.5),
+            ("claude-sonnet-4", 3.0, 15.0, 0.3),
+            ("claude-haiku-4-5", 1.0, 5.0, 0.1),
+            ("claude-3-5-haiku", 0.8, 4.0, 0.08),
+            ("gemini-2.5-pro", 1.25, 10.0, 0.31),
+            ("gemini-2.5-flash", 0.3, 2.5, 0.075),
+        ];
+
+        Self {
+            prices: prices
+                .into_iter()
+                .map(|(model, input, output, cached)| {
+                    let mut pricing = ModelPricing::new(input, output).with_cached_input(cached);
+                    if model.starts_with("claude") {
+                        pricing = pricing.with_cache_write(input * 1.25);
+                    }
+                    (model.to_string(), pricing)
+                })
+                .collect(),
+        }
+    }
+}
+
+impl PriceTable {
+    /// Create a table without any prices.
+    pub fn empty() -> Self {
+        Self {
+            prices: HashMap::new(),
+        }
+    }
+
+    /// Set the price of a model, replacing any existing entry.
+    pub fn with_price(mut self, model: impl Into<String>, pricing: ModelPricing) -> Self {
+        self.prices.insert(model.into(), pricing);
+        self
+    }
+
+    /// Find the price of a model.
+    ///
+    /// A `provider/` prefix is ignored. Models without an exact entry use the
+    /// longest entry they start with, so dated snapshots such as
+    /// `gpt-4o-2024-08-06` are priced like `gpt-4o`.
+    pub fn lookup(&self, model: &str) -> Option<&ModelPricing> {
+        let model = model.rsplit('/').next().unwrap_or(model);
+        self.prices.get(model).or_else(|| {
+            self.prices
+                .iter()
+                .filter(|(name, _)| model.starts_with(name.as_str()))
+                .max_by_key(|(name, _)| name.len())
+                .map(|(_, pricing)| pricing)
+        })
+    }
+
+    /// Cost of the given usage in USD, if the model is known.
+    pub fn cost(&self, model: &str, usage: &Usage) -> Option<f64> {
+        self.lookup(model).map(|pricing| pricing.cost(usage))
+    }
+}
+
+impl Response {
+    /// Cost of this response in USD according to the default [`PriceTable`].
+    ///
+    /// Returns `None` if the model is not in the table.
+    pub fn cost(&self, model: &str) -> Option<f64> {
+        DEFAULT_PRICES.cost(model, &self.usage)
+    }
+}
+
+/// Accumulated usage and cost.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CostSummary {
+    /// Total token usage.
+    pub usage: Usage,
+    /// Total cost in USD of the requests whose model has a known price.
+    pub cost: f64,
+    /// Number of recorded requests.
+    pub requests: usize,
+}
+
+impl CostSummary {
+    fn add(&mut self, usage: &Usage, cost: Option<f64>) {
+        self.usage += usage.clone();
+        self.cost += cost.unwrap_or(0.0);
+        self.requests += 1;
+    }
+}
+
+#[derive(Debug, Default)]
+struct Totals {
+    total: CostSummary,
+    conversations: HashMap<String, CostSummary>,
+    variants: HashMap<String, CostSummary>,
+}
+
+/// Accumulates usage and cost across requests and conversations.
+#[derive(Debug)]
+pub struct CostTracker {
+    prices: PriceTable,
+    totals: Mutex<Totals>,
+}
+
+impl Default for CostTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CostTracker {
+    /// Create a tracker using the default price table.
+    pub fn new() -> Self {
+        Self::with_prices(PriceTable::default())
+    }
+
+    /// Create a tracker using the given price table.
+    pub fn with_prices(prices: PriceTable) -> Self {
+        Self {
+            prices,
+            totals: Mutex::new(Totals::default()),
+        }
+    }
+
+    /// Get the price table.
+    pub fn prices(&self) -> &PriceTable {
+        &self.prices
+    }
+
+    /// Record the usage of a request, optionally attributing it to a conversation.
+    ///
+    /// Returns the cost of the request, or `None` if the model has no known price.
+    /// Usage is recorded either way.
+    pub fn record(&self, conversation: Option<&str>, model: &str, usage: &Usage) -> Option<f64> {
+        self.record_variant(conversation, None, model, usage)
+    }
+
+    /// Like [`record`](Self::record), also attributing the usage to an
+    /// experiment variant.
+    pub fn record_variant(
+        &self,
+        conversation: Option<&str>,
+        variant: Option<&str>,
+        model: &str,
+        usage: &Usage,
+    ) -> Option<f64> {
+        let cost = self.prices.cost(model, usage);
+        let mut totals = self.totals.lock().unwrap();
+        totals.total.add(usage, cost);
+        if let Some(id) = conversation {
+            totals
+                .conversations
+                .entry(id.to_string())
+                .or_default()
+                .add(usage, cost);
+        }
+        if let Some(id) = variant {
+            totals
+                .variants
+                .entry(id.to_string())
+                .or_default()
+                .add(usage, cost);
+        }
+        cost
+    }
+
+    /// Totals of a single conversation.
+    pub fn conversation(&self, id: &str) -> Option<CostSummary> {
+        self.totals.lock().unwrap().conversations.get(id).cloned()
+    }
+
+    /// Totals of a single experiment variant.
+    pub fn variant(&self, id: &str) -> Option<CostSummary> {
+        self.totals.lock().unwrap().variants.get(id).cloned()
+    }
+
+    /// Totals across all recorded requests.
+    pub fn total(&self) -> CostSummary {
+        self.totals.lock().unwrap().total.clone()
+    }
+
+    /// Forget all recorded usage.
+    pub fn reset(&self) {
+        *self.totals.lock().unwrap() = Totals::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn usage(prompt: u32, completion: u32, cached: Option<u32>) -> Usage {
+        Usage {
+            prompt_tokens: Some(prompt),
+            completion_tokens: Some(completion),
+            cached_tokens: cached,
+            cache_creation_tokens: None,
+            reasoning_tokens: None,
+        }
+    }
+
+    #[test]
+    fn test_pricing_lookup_and_cost() {
+        let table = PriceTable::empty()
+            .with_price("gpt-4o", ModelPricing::new(2.0, 8.0).with_cached_input(1.0))
+            .with_price("gpt-4o-mini", ModelPricing::new(0.5, 1.0));
+
+        let pricing = table.lookup("openai/gpt-4o-2024-08-06").unwrap();
+        assert_eq!(pricing.input_per_million, 2.0);
+        assert_eq!(
+            table
+                .lookup("gpt-4o-mini-2024-07-18")
+                .unwrap()
+                .input_per_million,
+            0.5
+        );
+        assert!(table.lookup("unknown-model").is_none());
+
+        // 600k uncached at $2, 400k cached at $1, 500k completion at $8.
+        let cost = table
+            .cost("gpt-4o", &usage(1_000_000, 500_000, Some(400_000)))
+            .unwrap();
+        assert!((cost - 5.6).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_cost_tracker() {
+        let tracker = CostTracker::with_prices(
+            PriceTable::empty().with_price("model", ModelPricing::new(1.0, 2.0)),
+        );
+
+        assert_eq!(
+            tracker.record(Some("a"), "model", &usage(1_000_000, 0, None)),
+            Some(1.0)
+        );
+        tracker.record(Some("b"), "model", &usage(0, 1_000_000, None));
+        assert_eq!(
+            tracker.record(Some("a"), "other", &usage(10, 10, None)),
+            None
+        );
+
+        let a = tracker.conversation("a").unwrap();
+        assert_eq!(a.requests, 2);
+        assert_eq!(a.usage.prompt_tokens, Some(1_000_010));
+        assert!((a.cost - 1.0).abs() < 1e-9);
+
+        tracker.record_variant(
+            Some("a"),
+            Some("treatment"),
+            "model",
+            &usage(0, 500_000, None),
+        );
+        assert_eq!(tracker.conversation("a").unwrap().requests, 3);
+        let treatment = tracker.variant("treatment").unwrap();
+        assert_eq!(treatment.requests, 1);
+        assert!((treatment.cost - 1.0).abs() < 1e-9);
+
+        let total = tracker.total();
+        assert_eq!(total.requests, 4);
+        assert!((total.cost - 4.0).abs() < 1e-9);
+        assert!(tracker.conversation("c").is_none());
+        assert!(tracker.variant("control").is_none());
+    }
+}
@@ -0,0 +1,307 @@
+//! Structured diffs between conversations.
+//!
+//! [`diff_conversations`] compares two transcripts, such as the output of an
+//! agent before and after a prompt change, and reports which messages were
+//! added, removed or edited, down to the changed parts:
+//!
+//! ```ignore
+//! use unia::diff::diff_conversations;
+//!
+//! let diff = diff_conversations(&baseline.messages(), &candidate.messages());
+//! assert!(diff.is_empty(), "agent behavior changed:\n{}", diff);
+//! ```
+//!
+//! Messages and parts are aligned on their longest common subsequence. Messages
+//! left unmatched on both sides at the same position are reported as edits when
+//! they have the same role.
+
+use serde::Serialize;
+use std::fmt;
+
+use crate::model::{Message, Part, Role};
+
+/// A change between two conversations.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MessageDiff {
+    /// A message only in the second conversation, at `index` in it.
+    Added { index: usize, message: Message },
+    /// A message only in the first conversation, at `index` in it.
+    Removed { index: usize, message: Message },
+    /// A message at `old_index` in the first conversation changed into the one
+    /// at `new_index` in the second.
+    Edited {
+        old_index: usize,
+        new_index: usize,
+        role: Role,
+        parts: Vec<PartDiff>,
+    },
+}
+
+/// A change between the parts of an edited message.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PartDiff {
+    /// A part only in the new message, at `index` in it.
+    Added { index: usize, part: Part },
+    /// A part only in the old message, at `index` in it.
+    Removed { index: usize, part: Part },
+    /// A part at `index` in the new message replacing a different one.
+    Changed { index: usize, old: Part, new: Part },
+}
+
+/// Differences between two conversations, in conversation order.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ConversationDiff {
+    pub changes: Vec<MessageDiff>,
+}
+
+impl ConversationDiff {
+    /// Check whether the conversations are equal.
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Number of changed messages.
+    pub fn len(&self) -> usize {
+        self.changes.len()
+    }
+}
+
+/// Compare two conversations.
+pub fn diff_conversations(a: &[Message], b: &[Message]) -> ConversationDiff {
+    let mut changes = Vec::new();
+    for (removed, added) in unmatched_runs(a, b) {
+        let paired = removed.len().min(added.len());
+        for (&old_index, &new_index) in removed.iter().zip(&added) {
+            let (old, new) = (&a[old_index], &b[new_index]);
+            if old.role() == new.role() {
+                changes.push(MessageDiff::Edited {
+                    old_index,
+                    new_index,
+                    role: new.role(),
+                    parts: diff_parts(old.parts(), new.parts()),
+                });
+            } else {
+                changes.push(MessageDiff::Removed {
+                    index: old_index,
+                    message: old.clone(),
+                });
+                changes.push(MessageDiff::Added {
+                    index: new_index,
+                    message: new.clone(),
+                });
+            }
+        }
+        changes.extend(removed[paired..].iter().map(|&index| MessageDiff::Removed {
+            index,
+            message: a[index].clone(),
+        }));
+        changes.extend(added[paired..].iter().map(|&index| MessageDiff::Added {
+            index,
+            message: b[index].clone(),
+        }));
+    }
+    ConversationDiff { changes }
+}
+
+fn diff_parts(a: &[Part], b: &[Part]) -> Vec<PartDiff> {
+    let mut changes = Vec::new();
+    for (removed, added) in unmatched_runs(a, b) {
+        let paired = removed.len().min(added.len());
+        changes.extend(removed.iter().zip(&added).map(|(&old_index, &new_index)| {
+            PartDiff::Changed {
+                index: new_index,
+                old: a[old_index].clone(),
+                new: b[new_index].clone(),
+            }
+        }));
+        changes.extend(removed[paired..].iter().map(|&index| PartDiff::Removed {
+            index,
+            part: a[index].clone(),
+        }));
+        changes.extend(added[paired..].iter().map(|&index| PartDiff::Added {
+            index,
+            part: b[index].clone(),
+        }));
+    }
+    changes
+}
+
+/// Indices of the items between consecutive matches of the longest common
+/// subsequence, as (only in `a`, only in `b`) pairs.
+fn unmatched_runs<T: PartialEq>(a: &[T], b: &[T]) -> Vec<(Vec<usize>, Vec<usize>)> {
+    // lengths[i][j] is the length of the LCS of a[i..] and b[j..].
+    let mut lengths = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lengths[i][j] = if a[i] == b[j] {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+
+    let mut runs = Vec::new();
+    let (mut removed, mut added) = (Vec::new(), Vec::new());
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a[i] == b[j] {
+            if !removed.is_empty() || !added.is_empty() {
+                runs.push((std::mem::take(&mut removed), std::mem::take(&mut added)));
+            }
+            i += 1;
+            j += 1;
+        } else if j == b.len() || (i < a.len() && lengths[i + 1][j] >= lengths[i][j + 1]) {
+            removed.push(i);
+            i += 1;
+        } else {
+            added.push(j);
+            j += 1;
+        }
+    }
+    if !removed.is_empty() || !added.is_empty() {
+        runs.push((removed, added));
+    }
+    runs
+}
+
+impl fmt::Display for ConversationDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for change in &self.changes {
+            match change {
+                MessageDiff::Added { index, message } => {
+                    writeln!(f, "+ [{}] {}", index, summary(message))?
+                }
+                MessageDiff::Removed { index, message } => {
+                    writeln!(f, "- [{}] {}", index, summary(message))?
+                }
+                MessageDiff::Edited {
+                    old_index,
+                    new_index,
+                    role,
+                    parts,
+                } => {
+                    writeln!(f, "~ [{} -> {}] {:?}", old_index, new_index, role)?;
+                    for part in parts {
+                        match part {
+                            PartDiff::Added { part, .. } => {
+                                writeln!(f, "    + {}", part_summary(part))?
+                            }
+                            PartDiff::Removed { part, .. } => {
+                                writeln!(f, "    - {}", part_summary(part))?
+                            }
+                            PartDiff::Changed { old, new, .. } => {
+                                writeln!(f, "    - {}", part_summary(old))?;
+                                writeln!(f, "    + {}", part_summary(new))?;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn summary(message: &Message) -> String {
+    let parts: Vec<String> = message.parts().iter().map(part_summary).collect();
+    format!("{:?}: {}", message.role(), parts.join(" | "))
+}
+
+fn part_summary(part: &Part) -> String {
+    match part {
+        Part::Text { content, .. } => format!("{:?}", content),
+        Part::Reasoning { content, .. } => format!("reasoning {:?}", content),
+        Part::FunctionCall {
+            name, arguments, ..
+        } => format!("call {}({})", name, arguments),
+        Part::FunctionResponse { name, response, .. } => {
+            format!("result {} {}", name, response)
+        }
+        Part::Media { mime_type, .. } => format!("media {}", mime_type),
+        Part::ComputerCall { action, .. } => format!("computer {:?}", action),
+        Part::ComputerResult { mime_type, .. } => format!("screenshot {}", mime_type),
+        Part::HostedToolCall { call, .. } => format!("hosted {:?}", call),
+        Part::Citation { citation, .. } => format!("citation {:?}", citation),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn text(content: &str) -> Part {
+        Part::Text {
+            content: content.to_string(),
+            finished: true,
+        }
+    }
+
+    #[test]
+    fn test_diff_conversations() {
+        let call = |city: &str| Part::FunctionCall {
+            id: None,
+            name: "weather".to_string(),
+            arguments: json!({ "city": city }),
+            signature: None,
+            raw_arguments: None,
+            finished: true,
+        };
+        let a = vec![
+            Message::User(vec![text("Weather in Paris?")]),
+            Message::Assistant(vec![text("Let me check."), call("Paris")]),
+            Message::Assistant(vec![text("Sunny.")]),
+        ];
+        let b = vec![
+            Message::User(vec![text("Weather in Paris?")]),
+            Message::Assistant(vec![text("Let me check."), call("paris")]),
+            Message::Assistant(vec![text("Sunny.")]),
+            Message::User(vec![text("Thanks!")]),
+        ];
+
+        assert!(diff_conversations(&a, &a).is_empty());
+
+        let diff = diff_conversations(&a, &b);
+        assert_eq!(
+            diff.changes,
+            vec![
+                MessageDiff::Edited {
+                    old_index: 1,
+                    new_index: 1,
+                    role: Role::Assistant,
+                    parts: vec![PartDiff::Changed {
+                        index: 1,
+                        old: call("Paris"),
+                        new: call("paris"),
+                    }],
+                },
+                MessageDiff::Added {
+                    index: 3,
+                    message: b[3].clone(),
+                },
+            ]
+        );
+        assert_eq!(
+            diff.to_string(),
+            "~ [1 -> 1] Assistant\n    - call weather({\"city\":\"Paris\"})\n    + call weather({\"city\":\"paris\"})\n+ [3] User: \"Thanks!\"\n"
+        );
+    }
+
+    #[test]
+    fn test_role_change_is_not_an_edit() {
+        let a = vec![Message::User(vec![text("Hi")])];
+        let b = vec![Message::Assistant(vec![text("Hi")])];
+
+        let diff = diff_conversations(&a, &b);
+        assert!(matches!(
+            diff.changes.as_slice(),
+            [
+                MessageDiff::Removed { index: 0, .. },
+                MessageDiff::Added { index: 0, .. }
+            ]
+        ));
+    }
+}
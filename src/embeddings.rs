@@ -0,0 +1,125 @@
+//! Embeddings API support.
+//!
+//! Embedding clients reuse the regular provider clients: create a client with an
+//! embedding model (e.g. `OpenAI::create(key, "text-embedding-3-small".into())`) and
+//! call [`EmbeddingsClient::embed`] on it.
+
+use async_trait::async_trait;
+
+use crate::client::ClientError;
+
+/// Trait for providers that can turn text into embedding vectors.
+#[async_trait]
+pub trait EmbeddingsClient: Send + Sync {
+    /// Embed a batch of texts in a single request.
+    ///
+    /// The returned vectors are in the same order as the input texts.
+    async fn embed(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>, ClientError>;
+
+    /// Maximum number of inputs the provider accepts per request.
+    fn max_batch_size(&self) -> usize {
+        96
+    }
+
+    /// Embed any number of texts, splitting them into provider-sized batches.
+    async fn embed_batched(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>, ClientError> {
+        let batch_size = self.max_batch_size().max(1);
+        let mut embeddings = Vec::with_capacity(texts.len());
+
+        let mut texts = texts.into_iter().peekable();
+        while texts.peek().is_some() {
+            let batch: Vec<String> = texts.by_ref().take(batch_size).collect();
+            let expected = batch.len();
+            let result = self.embed(batch).await?;
+            if result.len() != expected {
+                return Err(ClientError::ProviderError(format!(
+                    "Expected {} embeddings, got {}",
+                    expected,
+                    result.len()
+                )));
+            }
+            embeddings.extend(result);
+        }
+
+        Ok(embeddings)
+    }
+
+    /// Embed a single text.
+    async fn embed_one(&self, text: String) -> Result<Vec<f32>, ClientError> {
+        self.embed(vec![text])
+            .await?
+            .pop()
+            .ok_or_else(|| ClientError::ProviderError("No embedding returned".to_string()))
+    }
+}
+
+/// Compute the cosine similarity between two vectors.
+///
+/// Returns `0.0` if either vector has zero magnitude or the lengths differ.
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() {
+        return 0.0;
+    }
+
+    let (dot, norm_a, norm_b) = a
+        .iter()
+        .zip(b)
+        .fold((0.0f32, 0.0f32, 0.0f32), |(dot, na, nb), (x, y)| {
+            (dot + x * y, na + x * x, nb + y * y)
+        });
+
+    if norm_a == 0.0 || norm_b == 0.0 {
+        0.0
+    } else {
+        dot / (norm_a.sqrt() * norm_b.sqrt())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    struct CountingEmbedder {
+        calls: Mutex<Vec<usize>>,
+    }
+
+    #[async_trait]
+    impl EmbeddingsClient for CountingEmbedder {
+        async fn embed(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>, ClientError> {
+            self.calls.lock().unwrap().push(texts.len());
+            Ok(texts.iter().map(|t| vec![t.len() as f32]).collect())
+        }
+
+        fn max_batch_size(&self) -> usize {
+            2
+        }
+    }
+
+    #[tokio::test]
+    async fn test_embed_batched_preserves_order() {
+        let embedder = CountingEmbedder {
+            calls: Mutex::new(Vec::new()),
+        };
+        let texts = vec!["a", "bb", "ccc", "dddd", "eeeee"]
+            .into_iter()
+            .map(String::from)
+            .collect();
+
+        let result = embedder.embed_batched(texts).await.unwrap();
+
+        assert_eq!(
+            result,
+            vec![vec![1.0], vec![2.0], vec![3.0], vec![4.0], vec![5.0]]
+        );
+        assert_eq!(*embedder.calls.lock().unwrap(), vec![2, 2, 1]);
+    }
+
+    #[test]
+    fn test_cosine_similarity() {
+        assert!((cosine_similarity(&[1.0, 0.0], &[1.0, 0.0]) - 1.0).abs() < 1e-6);
+        assert!(cosine_similarity(&[1.0, 0.0], &[0.0, 1.0]).abs() < 1e-6);
+        assert_eq!(cosine_similarity(&[0.0, 0.0], &[1.0, 1.0]), 0.0);
+        assert_eq!(cosine_similarity(&[1.0], &[1.0, 1.0]), 0.0);
+    }
+}
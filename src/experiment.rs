@@ -0,0 +1,273 @@
+//! Prompt versioning and A/B experiments.
+//!
+//! A [`PromptRegistry`] keeps the versions of named prompts, so a run can be
+//! traced back to the exact prompt it used. An [`Experiment`] splits sessions
+//! between [`Variant`]s, each combining a prompt version and a model. The
+//! assignment is a stable hash of the session key, so a session keeps its
+//! variant across requests and processes:
+//!
+//! ```ignore
+//! use unia::experiment::{Experiment, PromptRegistry, PromptVersion, Variant};
+//!
+//! let prompts = PromptRegistry::new()
+//!     .with_version(PromptVersion::new("support", "v1", "You are a support agent."))
+//!     .with_version(PromptVersion::new("support", "v2", "You are a concise support agent."));
+//! let experiment = Experiment::new("support-prompt")
+//!     .with_variant(Variant::new("control").with_prompt(prompts.get("support", "v1").unwrap().clone()))
+//!     .with_variant(Variant::new("concise").with_prompt(prompts.latest("support").unwrap().clone()));
+//!
+//! let variant = experiment.assign(user_id).unwrap();
+//! let mut options = ModelOptions::new("gpt-5");
+//! variant.apply(&mut options);
+//! let agent = Agent::new(OpenAI::create_with_options(key, options, TransportOptions::default()))
+//!     .with_cost_tracker(tracker.clone())
+//!     .with_variant(variant.id.clone());
+//!
+//! // Later: compare the variants.
+//! let control = tracker.variant("control");
+//! ```
+//!
+//! An agent tagged with [`Agent::with_variant`](crate::agent::Agent::with_variant)
+//! records the variant in its cost tracker, in [`AgentRun`](crate::agent::AgentRun)
+//! records and, with the `otel` feature, on its `invoke_agent` span.
+
+use serde::{Deserialize, Serialize};
+use serde_with::skip_serializing_none;
+use std::collections::HashMap;
+
+use crate::options::ModelOptions;
+
+/// A version of a named prompt.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PromptVersion {
+    pub name: String,
+    pub version: String,
+    pub text: String,
+}
+
+impl PromptVersion {
+    pub fn new(
+        name: impl Into<String>,
+        version: impl Into<String>,
+        text: impl Into<String>,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            version: version.into(),
+            text: text.into(),
+        }
+    }
+
+    /// Identifier of the version, `{name}@{version}`.
+    pub fn id(&self) -> String {
+        format!("{}@{}", self.name, self.version)
+    }
+}
+
+/// Versions of named prompts, in the order they were registered.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PromptRegistry {
+    prompts: HashMap<String, Vec<PromptVersion>>,
+}
+
+impl PromptRegistry {
+    /// Create an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a prompt version.
+    pub fn with_version(mut self, prompt: PromptVersion) -> Self {
+        self.register(prompt);
+        self
+    }
+
+    /// Add a prompt version, replacing a registered one with the same name and
+    /// version.
+    pub fn register(&mut self, prompt: PromptVersion) {
+        let versions = self.prompts.entry(prompt.name.clone()).or_default();
+        versions.retain(|p| p.version != prompt.version);
+        versions.push(prompt);
+    }
+
+    /// Get a version of a prompt.
+    pub fn get(&self, name: &str, version: &str) -> Option<&PromptVersion> {
+        self.versions(name).iter().find(|p| p.version == version)
+    }
+
+    /// Get the most recently registered version of a prompt.
+    pub fn latest(&self, name: &str) -> Option<&PromptVersion> {
+        self.versions(name).last()
+    }
+
+    /// All versions of a prompt, oldest first.
+    pub fn versions(&self, name: &str) -> &[PromptVersion] {
+        self.prompts.get(name).map_or(&[], Vec::as_slice)
+    }
+}
+
+/// One arm of an [`Experiment`].
+#[skip_serializing_none]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Variant {
+    /// Identifier recorded with the usage and runs of the variant.
+    pub id: String,
+    /// System prompt of the variant.
+    pub prompt: Option<PromptVersion>,
+    /// Model of the variant.
+    pub model: Option<String>,
+    /// Relative share of sessions assigned to the variant.
+    pub weight: u32,
+}
+
+impl Variant {
+    /// Create a variant with weight 1 that changes nothing.
+    pub fn new(id: impl Into<String>) -> Self {
+        Self {
+            id: id.into(),
+            prompt: None,
+            model: None,
+            weight: 1,
+        }
+    }
+
+    /// Set the system prompt.
+    pub fn with_prompt(mut self, prompt: PromptVersion) -> Self {
+        self.prompt = Some(prompt);
+        self
+    }
+
+    /// Set the model.
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.model = Some(model.into());
+        self
+    }
+
+    /// Set the relative share of sessions.
+    pub fn with_weight(mut self, weight: u32) -> Self {
+        self.weight = weight;
+        self
+    }
+
+    /// Set the system prompt and model of the variant in model options.
+    pub fn apply<T>(&self, options: &mut ModelOptions<T>) {
+        if let Some(prompt) = &self.prompt {
+            options.system = Some(prompt.text.clone());
+        }
+        if let Some(model) = &self.model {
+            options.model = model.clone();
+        }
+    }
+}
+
+/// Deterministic split of sessions between variants.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Experiment {
+    pub name: String,
+    pub variants: Vec<Variant>,
+}
+
+impl Experiment {
+    /// Create an experiment without variants.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            variants: Vec::new(),
+        }
+    }
+
+    /// Add a variant.
+    pub fn with_variant(mut self, variant: Variant) -> Self {
+        self.variants.push(variant);
+        self
+    }
+
+    /// Variant of a session, e.g. a user or conversation id.
+    ///
+    /// Sessions are spread over the variants in proportion to their weights.
+    /// The same key always gets the same variant as long as the variants are
+    /// unchanged, and keys are split independently in different experiments.
+    /// Returns `None` if no variant has a weight.
+    pub fn assign(&self, key: &str) -> Option<&Variant> {
+        let total: u64 = self.variants.iter().map(|v| u64::from(v.weight)).sum();
+        if total == 0 {
+            return None;
+        }
+        let mut pick = stable_hash(&[self.name.as_bytes(), b"\0", key.as_bytes()]) % total;
+        self.variants.iter().find(|variant| {
+            let weight = u64::from(variant.weight);
+            if pick < weight {
+                return true;
+            }
+            pick -= weight;
+            false
+        })
+    }
+}
+
+/// 64-bit FNV-1a hash, which unlike [`std::hash::DefaultHasher`] is the same in
+/// every process.
+pub(crate) fn stable_hash(chunks: &[&[u8]]) -> u64 {
+    const OFFSET: u64 = 0xcbf29ce484222325;
+    const PRIME: u64 = 0x100000001b3;
+
+    chunks
+        .iter()
+        .flat_map(|chunk| chunk.iter())
+        .fold(OFFSET, |hash, byte| {
+            (hash ^ u64::from(*byte)).wrapping_mul(PRIME)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prompt_registry() {
+        let mut registry = PromptRegistry::new()
+            .with_version(PromptVersion::new("support", "v1", "Be helpful."))
+            .with_version(PromptVersion::new("support", "v2", "Be concise."));
+        assert_eq!(registry.latest("support").unwrap().id(), "support@v2");
+        assert_eq!(registry.get("support", "v1").unwrap().text, "Be helpful.");
+
+        registry.register(PromptVersion::new("support", "v1", "Be kind."));
+        assert_eq!(registry.versions("support").len(), 2);
+        assert_eq!(registry.latest("support").unwrap().text, "Be kind.");
+        assert!(registry.latest("sales").is_none());
+    }
+
+    #[test]
+    fn test_experiment_assignment() {
+        let experiment = Experiment::new("prompt")
+            .with_variant(Variant::new("control").with_weight(3))
+            .with_variant(
+                Variant::new("treatment")
+                    .with_model("gpt-5-mini")
+                    .with_prompt(PromptVersion::new("support", "v2", "Be concise.")),
+            );
+
+        let assigned: Vec<&str> = (0..1000)
+            .map(|i| {
+                experiment
+                    .assign(&format!("user-{}", i))
+                    .unwrap()
+                    .id
+                    .as_str()
+            })
+            .collect();
+        let treatment = assigned.iter().filter(|id| **id == "treatment").count();
+        assert!((200..300).contains(&treatment), "{}", treatment);
+        assert_eq!(
+            experiment.assign("user-7").unwrap().id,
+            experiment.clone().assign("user-7").unwrap().id
+        );
+
+        let mut options = ModelOptions::<()>::new("gpt-5");
+        experiment.variants[1].apply(&mut options);
+        assert_eq!(options.model, "gpt-5-mini");
+        assert_eq!(options.system.as_deref(), Some("Be concise."));
+
+        assert!(Experiment::new("empty").assign("user").is_none());
+    }
+}
@@ -0,0 +1,222 @@
+//! Export of conversations and agent runs as fine-tuning datasets.
+//!
+//! [`DatasetExporter`] turns recorded [`AgentRun`]s or plain conversations into
+//! JSONL in one of the supported [`ExportFormat`]s, optionally dropping failed runs
+//! and redacting sensitive content first:
+//!
+//! ```ignore
+//! use unia::export::{DatasetExporter, ExportFormat};
+//! use unia::redact::PatternRedactor;
+//!
+//! let jsonl = DatasetExporter::new(ExportFormat::OpenAIChat)
+//!     .successful_only()
+//!     .with_redactor(PatternRedactor::pii())
+//!     .export_runs(&runs)?;
+//! ```
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::agent::AgentRun;
+use crate::api::openai::chat_training_example;
+use crate::client::ClientError;
+use crate::model::{Message, Part};
+use crate::redact::Redactor;
+
+/// Output format of an exported dataset.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    /// OpenAI chat fine-tuning format (`{"messages": [...]}`).
+    OpenAIChat,
+    /// ShareGPT format (`{"conversations": [{"from": ..., "value": ...}]}`).
+    ShareGPT,
+}
+
+/// Converts conversations into fine-tuning JSONL.
+pub struct DatasetExporter {
+    format: ExportFormat,
+    successful_only: bool,
+    system: Option<String>,
+    redactor: Option<Box<dyn Redactor>>,
+}
+
+impl DatasetExporter {
+    /// Create an exporter for the given format.
+    pub fn new(format: ExportFormat) -> Self {
+        Self {
+            format,
+            successful_only: false,
+            system: None,
+            redactor: None,
+        }
+    }
+
+    /// Skip runs that failed or did not finish normally.
+    pub fn successful_only(mut self) -> Self {
+        self.successful_only = true;
+        self
+    }
+
+    /// Set the system prompt used for conversations that have none.
+    pub fn with_system(mut self, system: impl Into<String>) -> Self {
+        self.system = Some(system.into());
+        self
+    }
+
+    /// Redact every message before export.
+    pub fn with_redactor<R: Redactor + 'static>(mut self, redactor: R) -> Self {
+        self.redactor = Some(Box::new(redactor));
+        self
+    }
+
+    /// Export agent runs as JSONL, one run per line.
+    pub fn export_runs(&self, runs: &[AgentRun]) -> Result<String, ClientError> {
+        let mut jsonl = String::new();
+        for run in runs {
+            if self.successful_only && !run.is_success() {
+                continue;
+            }
+            let system = run.system.as_deref().or(self.system.as_deref());
+            self.push_line(&mut jsonl, system, run.messages())?;
+        }
+        Ok(jsonl)
+    }
+
+    /// Export plain conversations as JSONL, one conversation per line.
+    pub fn export_conversations(
+        &self,
+        conversations: &[Vec<Message>],
+    ) -> Result<String, ClientError> {
+        let mut jsonl = String::new();
+        for conversation in conversations {
+            self.push_line(&mut jsonl, self.system.as_deref(), conversation.clone())?;
+        }
+        Ok(jsonl)
+    }
+
+    fn push_line(
+        &self,
+        jsonl: &mut String,
+        system: Option<&str>,
+        messages: Vec<Message>,
+    ) -> Result<(), ClientError> {
+        let (system, messages) = match &self.redactor {
+            Some(redactor) => (
+                system.map(|s| redactor.redact(s)),
+                messages
+                    .iter()
+                    .map(|m| redactor.redact_message(m))
+                    .collect(),
+            ),
+            None => (system.map(str::to_string), messages),
+        };
+
+        let example = match self.format {
+            ExportFormat::OpenAIChat => chat_training_example(system.as_deref(), messages, vec![]),
+            ExportFormat::ShareGPT => sharegpt_example(system.as_deref(), &messages),
+        };
+
+        jsonl.push_str(&serde_json::to_string(&example)?);
+        jsonl.push('\n');
+        Ok(())
+    }
+}
+
+fn sharegpt_example(system: Option<&str>, messages: &[Message]) -> Value {
+    let mut turns = Vec::new();
+    if let Some(system) = system {
+        turns.push(json!({ "from": "system", "value": system }));
+    }
+
+    for message in messages {
+        let speaker = match message {
+            Message::User(_) => "human",
+            Message::Assistant(_) => "gpt",
+        };
+
+        let mut text = Vec::new();
+        for part in message.parts() {
+            match part {
+                Part::Text { content, .. } => text.push(content.as_str()),
+                Part::FunctionCall {
+                    name, arguments, ..
+                } => turns.push(json!({
+                    "from": "function_call",
+                    "value": json!({ "name": name, "arguments": arguments }).to_string(),
+                })),
+                Part::FunctionResponse { name, response, .. } => turns.push(json!({
+                    "from": "observation",
+                    "value": json!({ "name": name, "content": response }).to_string(),
+                })),
+                _ => {}
+            }
+        }
+
+        if !text.is_empty() {
+            turns.push(json!({ "from": speaker, "value": text.join("\n") }));
+        }
+    }
+
+    json!({ "conversations": turns })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{FinishReason, Usage};
+
+    fn run(answer: &str, error: Option<&str>) -> AgentRun {
+        AgentRun {
+            version: crate::schema::RUN_FORMAT_VERSION,
+            id: "run".to_string(),
+            provider: "openai".to_string(),
+            model: "gpt-5".to_string(),
+            served_model: None,
+            system: None,
+            prompt_version: None,
+            variant: None,
+            input: vec![Message::User(vec![Part::Text {
+                content: "Contact me at jane@example.com".to_string(),
+                finished: true,
+            }])],
+            output: vec![Message::Assistant(vec![Part::Text {
+                content: answer.to_string(),
+                finished: true,
+            }])],
+            usage: Usage::default(),
+            iterations: Vec::new(),
+            finish: if error.is_some() {
+                FinishReason::Error
+            } else {
+                FinishReason::Stop
+            },
+            error: error.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_export_sharegpt() {
+        let runs = vec![run("Sure.", None), run("", Some("timeout"))];
+        let jsonl = DatasetExporter::new(ExportFormat::ShareGPT)
+            .successful_only()
+            .with_redactor(|text: &str| text.replace("jane@example.com", "[EMAIL]"))
+            .export_runs(&runs)
+            .unwrap();
+
+        let lines: Vec<Value> = jsonl
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 1);
+        assert_eq!(
+            lines[0],
+            json!({
+                "conversations": [
+                    { "from": "human", "value": "Contact me at [EMAIL]" },
+                    { "from": "gpt", "value": "Sure." }
+                ]
+            })
+        );
+    }
+}
@@ -0,0 +1,162 @@
+//! Provider file storage.
+//!
+//! Providers with a Files API store uploads and let requests reference them
+//! instead of inlining the data, which keeps large documents out of every
+//! request body. [`FileUpload::upload_file`] returns a [`FileRef`] whose
+//! [`to_part`](FileRef::to_part) can be sent like inline media:
+//!
+//! ```ignore
+//! use unia::files::FileUpload;
+//!
+//! let file = client.upload_file(pdf_bytes, "application/pdf", Some("report.pdf")).await?;
+//! let message = Message::User(vec![
+//!     Part::Text {
+//!         content: "Summarize this.".to_string(),
+//!         finished: true,
+//!     },
+//!     file.to_part(),
+//! ]);
+//! let response = client.request(vec![message], vec![]).await?;
+//! client.delete_file(&file).await?;
+//! ```
+//!
+//! A reference is a `Part::Media` with empty `data` and the file's `uri`. Files
+//! belong to the provider account they were uploaded with, so a reference only
+//! works with clients of the same provider.
+//!
+//! [`FileUpload::attach_path`] reads a file from disk and uploads it only when
+//! it is too large to send inline:
+//!
+//! ```ignore
+//! let report = client.attach_path(Path::new("report.pdf"), 4 * 1024 * 1024).await?;
+//! ```
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_with::skip_serializing_none;
+
+use std::path::Path;
+
+use crate::client::ClientError;
+use crate::model::{inline_media, read_attachment, MediaType, Part};
+
+/// Trait for providers that can store files for later requests.
+#[async_trait]
+pub trait FileUpload: Send + Sync {
+    /// Upload a file. It is ready to be referenced once this returns.
+    async fn upload_file(
+        &self,
+        data: Vec<u8>,
+        mime_type: &str,
+        name: Option<&str>,
+    ) -> Result<FileRef, ClientError>;
+
+    /// Delete an uploaded file.
+    async fn delete_file(&self, file: &FileRef) -> Result<(), ClientError>;
+
+    /// Attach a file to a message, uploading it if it is larger than
+    /// `inline_limit` bytes and inlining it as [`Part::from_path`] does
+    /// otherwise.
+    async fn attach_path(&self, path: &Path, inline_limit: usize) -> Result<Part, ClientError> {
+        let (data, mime_type, name) = read_attachment(path)?;
+        if data.len() <= inline_limit {
+            return Ok(inline_media(&data, mime_type, name));
+        }
+        let file = self.upload_file(data, &mime_type, Some(&name)).await?;
+        Ok(file.to_part())
+    }
+}
+
+/// A file stored by a provider.
+#[skip_serializing_none]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileRef {
+    /// Provider id of the file, used to manage it.
+    pub id: String,
+    /// Reference to the file in `Part::Media { uri }`. This is the file id for
+    /// OpenAI and Anthropic and the file URI for Gemini.
+    pub uri: String,
+    pub mime_type: String,
+    /// Name the file was uploaded with.
+    pub name: Option<String>,
+    /// File size in bytes, if reported.
+    pub size_bytes: Option<u64>,
+}
+
+impl FileRef {
+    /// A media part referencing the file. Images become image parts and
+    /// anything else a document.
+    pub fn to_part(&self) -> Part {
+        let media_type = if self.mime_type.starts_with("image/") {
+            MediaType::Image
+        } else {
+            MediaType::Document
+        };
+        Part::Media {
+            media_type,
+            data: String::new(),
+            mime_type: self.mime_type.clone(),
+            uri: Some(self.uri.clone()),
+            finished: true,
+        }
+    }
+}
+
+/// The uploaded file a media part refers to, if it has no inline data.
+pub(crate) fn file_reference<'a>(data: &str, uri: &'a Option<String>) -> Option<&'a str> {
+    uri.as_deref().filter(|_| data.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Uploads;
+
+    #[async_trait]
+    impl FileUpload for Uploads {
+        async fn upload_file(
+            &self,
+            data: Vec<u8>,
+            mime_type: &str,
+            name: Option<&str>,
+        ) -> Result<FileRef, ClientError> {
+            Ok(FileRef {
+                id: "file-1".to_string(),
+                uri: "file-1".to_string(),
+                mime_type: mime_type.to_string(),
+                name: name.map(str::to_string),
+                size_bytes: Some(data.len() as u64),
+            })
+        }
+
+        async fn delete_file(&self, _file: &FileRef) -> Result<(), ClientError> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_attach_path() {
+        let path = std::env::temp_dir().join(format!("unia-attach-{}.png", uuid::Uuid::new_v4()));
+        std::fs::write(&path, [0u8; 64]).unwrap();
+
+        let inline = Uploads.attach_path(&path, 64).await.unwrap();
+        assert!(matches!(
+            inline,
+            Part::Media { media_type: MediaType::Image, ref data, .. } if !data.is_empty()
+        ));
+
+        let uploaded = Uploads.attach_path(&path, 63).await.unwrap();
+        assert_eq!(
+            uploaded,
+            Part::Media {
+                media_type: MediaType::Image,
+                data: String::new(),
+                mime_type: "image/png".to_string(),
+                uri: Some("file-1".to_string()),
+                finished: true,
+            }
+        );
+        std::fs::remove_file(path).unwrap();
+    }
+}
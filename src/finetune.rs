@@ -0,0 +1,302 @@
+//! Fine-tuning job management.
+//!
+//! A fine-tune goes through three steps: upload a JSONL training file, create a
+//! job referencing it, and poll the job until it finishes. The resulting model id
+//! can then be used like any other model:
+//!
+//! ```ignore
+//! use unia::finetune::{FineTuningClient, FineTuningJobRequest, TrainingDataset};
+//!
+//! let mut dataset = TrainingDataset::new().with_system("You are a support agent.");
+//! dataset.add_conversation(conversation, vec![]);
+//!
+//! let file = client.upload_training_file("support.jsonl", dataset.to_jsonl()?).await?;
+//! let job = client
+//!     .create_fine_tuning_job(FineTuningJobRequest::new("gpt-4.1-mini", file.id))
+//!     .await?;
+//! let job = client
+//!     .wait_for_fine_tuning_job(&job.id, Duration::from_secs(30))
+//!     .await?;
+//!
+//! let tuned = OpenAI::create_with_options(key, job.model_options().unwrap(), Default::default());
+//! ```
+
+use async_trait::async_trait;
+use rmcp::model::Tool;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use serde_with::skip_serializing_none;
+use std::time::Duration;
+use tracing::debug;
+
+use crate::api::openai::chat_training_example;
+use crate::client::ClientError;
+use crate::model::Message;
+use crate::options::ModelOptions;
+
+/// Trait for providers that can run fine-tuning jobs.
+#[async_trait]
+pub trait FineTuningClient: Send + Sync {
+    /// Upload a JSONL training file.
+    async fn upload_training_file(
+        &self,
+        filename: &str,
+        jsonl: String,
+    ) -> Result<TrainingFile, ClientError>;
+
+    /// Start a fine-tuning job.
+    async fn create_fine_tuning_job(
+        &self,
+        request: FineTuningJobRequest,
+    ) -> Result<FineTuningJob, ClientError>;
+
+    /// Get the current state of a fine-tuning job.
+    async fn get_fine_tuning_job(&self, id: &str) -> Result<FineTuningJob, ClientError>;
+
+    /// Cancel a running fine-tuning job.
+    async fn cancel_fine_tuning_job(&self, id: &str) -> Result<FineTuningJob, ClientError>;
+
+    /// Poll a fine-tuning job until it reaches a terminal state.
+    ///
+    /// Failed and cancelled jobs are returned as-is; check [`FineTuningJob::status`].
+    async fn wait_for_fine_tuning_job(
+        &self,
+        id: &str,
+        poll_interval: Duration,
+    ) -> Result<FineTuningJob, ClientError> {
+        loop {
+            let job = self.get_fine_tuning_job(id).await?;
+            if job.status.is_terminal() {
+                return Ok(job);
+            }
+            debug!("Fine-tuning job {} is {:?}", id, job.status);
+            tokio::time::sleep(poll_interval).await;
+        }
+    }
+}
+
+/// An uploaded training file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrainingFile {
+    /// Provider file id, referenced when creating a job.
+    pub id: String,
+    /// Name the file was uploaded with.
+    pub filename: String,
+    /// File size in bytes.
+    pub bytes: u64,
+}
+
+/// Hyperparameters for a fine-tuning job. Unset values use the provider defaults.
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Hyperparameters {
+    pub n_epochs: Option<u32>,
+    pub batch_size: Option<u32>,
+    pub learning_rate_multiplier: Option<f64>,
+}
+
+/// Parameters for creating a fine-tuning job.
+#[skip_serializing_none]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FineTuningJobRequest {
+    /// Base model to fine-tune.
+    pub model: String,
+    /// Id of the uploaded training file.
+    pub training_file: String,
+    /// Id of an uploaded validation file.
+    pub validation_file: Option<String>,
+    /// Suffix appended to the fine-tuned model name.
+    pub suffix: Option<String>,
+    pub hyperparameters: Option<Hyperparameters>,
+    pub seed: Option<u64>,
+}
+
+impl FineTuningJobRequest {
+    /// Create a job request for a base model and training file.
+    pub fn new(model: impl Into<String>, training_file: impl Into<String>) -> Self {
+        Self {
+            model: model.into(),
+            training_file: training_file.into(),
+            validation_file: None,
+            suffix: None,
+            hyperparameters: None,
+            seed: None,
+        }
+    }
+
+    /// Set the validation file.
+    pub fn with_validation_file(mut self, file_id: impl Into<String>) -> Self {
+        self.validation_file = Some(file_id.into());
+        self
+    }
+
+    /// Set the model name suffix.
+    pub fn with_suffix(mut self, suffix: impl Into<String>) -> Self {
+        self.suffix = Some(suffix.into());
+        self
+    }
+
+    /// Set the hyperparameters.
+    pub fn with_hyperparameters(mut self, hyperparameters: Hyperparameters) -> Self {
+        self.hyperparameters = Some(hyperparameters);
+        self
+    }
+
+    /// Set the seed.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+}
+
+/// Status of a fine-tuning job.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FineTuningStatus {
+    ValidatingFiles,
+    Queued,
+    Running,
+    Succeeded,
+    Failed,
+    Cancelled,
+}
+
+impl FineTuningStatus {
+    /// Check whether the job has stopped, successfully or not.
+    pub fn is_terminal(&self) -> bool {
+        matches!(self, Self::Succeeded | Self::Failed | Self::Cancelled)
+    }
+}
+
+/// Error reported for a failed fine-tuning job.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FineTuningError {
+    pub code: Option<String>,
+    pub message: Option<String>,
+}
+
+/// A fine-tuning job.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FineTuningJob {
+    pub id: String,
+    /// Base model being fine-tuned.
+    pub model: String,
+    pub status: FineTuningStatus,
+    /// Id of the resulting model, available once the job succeeded.
+    pub fine_tuned_model: Option<String>,
+    pub training_file: String,
+    pub error: Option<FineTuningError>,
+    pub created_at: Option<u64>,
+    pub finished_at: Option<u64>,
+}
+
+impl FineTuningJob {
+    /// Model options targeting the fine-tuned model, if the job succeeded.
+    pub fn model_options<M: Default>(&self) -> Option<ModelOptions<M>> {
+        self.fine_tuned_model.clone().map(ModelOptions::new)
+    }
+}
+
+/// Builder for fine-tuning datasets in the OpenAI chat JSONL format.
+#[derive(Debug, Clone, Default)]
+pub struct TrainingDataset {
+    system: Option<String>,
+    examples: Vec<Value>,
+}
+
+impl TrainingDataset {
+    /// Create an empty dataset.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Prepend a system prompt to every conversation added afterwards.
+    pub fn with_system(mut self, system: impl Into<String>) -> Self {
+        self.system = Some(system.into());
+        self
+    }
+
+    /// Add a conversation and the tools that were available in it.
+    pub fn add_conversation(&mut self, messages: Vec<Message>, tools: Vec<Tool>) {
+        self.examples.push(chat_training_example(
+            self.system.as_deref(),
+            messages,
+            tools,
+        ));
+    }
+
+    /// Number of examples in the dataset.
+    pub fn len(&self) -> usize {
+        self.examples.len()
+    }
+
+    /// Check whether the dataset is empty.
+    pub fn is_empty(&self) -> bool {
+        self.examples.is_empty()
+    }
+
+    /// Serialize the dataset as JSONL, one example per line.
+    pub fn to_jsonl(&self) -> Result<String, ClientError> {
+        let mut jsonl = String::new();
+        for example in &self.examples {
+            jsonl.push_str(&serde_json::to_string(example)?);
+            jsonl.push('\n');
+        }
+        Ok(jsonl)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::Part;
+    use serde_json::json;
+
+    #[test]
+    fn test_dataset_jsonl() {
+        let mut dataset = TrainingDataset::new().with_system("Be brief.");
+        dataset.add_conversation(
+            vec![
+                Message::User(vec![Part::Text {
+                    content: "Hi".to_string(),
+                    finished: true,
+                }]),
+                Message::Assistant(vec![Part::Text {
+                    content: "Hello!".to_string(),
+                    finished: true,
+                }]),
+            ],
+            vec![],
+        );
+
+        let jsonl = dataset.to_jsonl().unwrap();
+        let line: Value = serde_json::from_str(jsonl.lines().next().unwrap()).unwrap();
+        assert_eq!(
+            line,
+            json!({
+                "messages": [
+                    { "role": "system", "content": "Be brief." },
+                    { "role": "user", "content": "Hi" },
+                    { "role": "assistant", "content": "Hello!" }
+                ]
+            })
+        );
+    }
+
+    #[test]
+    fn test_job_status() {
+        let job: FineTuningJob = serde_json::from_value(json!({
+            "id": "ftjob-1",
+            "model": "gpt-4.1-mini",
+            "status": "succeeded",
+            "fine_tuned_model": "ft:gpt-4.1-mini:org::abc",
+            "training_file": "file-1",
+            "created_at": 1
+        }))
+        .unwrap();
+
+        assert!(job.status.is_terminal());
+        let options = job.model_options::<()>().unwrap();
+        assert_eq!(options.model, "ft:gpt-4.1-mini:org::abc");
+    }
+}
@@ -0,0 +1,350 @@
+//! Editing of conversation history.
+//!
+//! Applications that curate context between turns can rewrite tool results,
+//! redact messages or fold finished tool calls into a short summary. Providers
+//! reject histories where a tool call is not answered right after it, or a
+//! result answers no call, so every edit keeps calls and results paired and
+//! [`validate_history`] checks the result:
+//!
+//! ```ignore
+//! use unia::history::{collapse_tool_call, validate_history};
+//!
+//! collapse_tool_call(&mut messages, "call_1", "Looked up the weather: sunny in Paris.")?;
+//! validate_history(&messages)?;
+//! ```
+//!
+//! [`Conversation::edit`](crate::memory::Conversation::edit) applies edits to a
+//! conversation and rolls them back if the history is no longer valid.
+
+use serde_json::Value;
+use thiserror::Error;
+
+use crate::model::{Message, Part};
+
+/// Errors raised when editing or validating a history.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum HistoryError {
+    #[error("No message at index {0}")]
+    NoMessage(usize),
+
+    #[error("No tool call with id {0}")]
+    CallNotFound(String),
+
+    #[error("No tool result for call {0}")]
+    ResultNotFound(String),
+
+    #[error("Message {0} has no parts")]
+    EmptyMessage(usize),
+
+    #[error("Tool call {id} in message {index} is not answered by the next message")]
+    UnansweredCall { index: usize, id: String },
+
+    #[error("Tool result {id} in message {index} answers no call in the previous message")]
+    OrphanResult { index: usize, id: String },
+}
+
+/// Id of a tool or computer call.
+fn call_id(part: &Part) -> Option<&str> {
+    match part {
+        Part::FunctionCall { id, .. } | Part::ComputerCall { id, .. } => id.as_deref(),
+        _ => None,
+    }
+}
+
+/// Id of the call a tool or computer result answers.
+fn result_id(part: &Part) -> Option<&str> {
+    match part {
+        Part::FunctionResponse { id, .. } | Part::ComputerResult { id, .. } => id.as_deref(),
+        _ => None,
+    }
+}
+
+/// Check that a history satisfies the constraints shared by providers.
+///
+/// Messages must not be empty, every tool call must be answered in the next
+/// message, and every tool result must answer a call of the previous message.
+/// Calls without an id are matched by providers on their name and are not
+/// checked.
+pub fn validate_history(messages: &[Message]) -> Result<(), HistoryError> {
+    for (index, message) in messages.iter().enumerate() {
+        if message.parts().is_empty() {
+            return Err(HistoryError::EmptyMessage(index));
+        }
+
+        let previous = index.checked_sub(1).map(|i| &messages[i]);
+        let next = messages.get(index + 1);
+        for part in message.parts() {
+            if let Some(id) = call_id(part) {
+                let answered = next.is_some_and(|next| {
+                    matches!(next, Message::User(_))
+                        && next.parts().iter().any(|p| result_id(p) == Some(id))
+                });
+                if !answered {
+                    return Err(HistoryError::UnansweredCall {
+                        index,
+                        id: id.to_string(),
+                    });
+                }
+            }
+            if let Some(id) = result_id(part) {
+                let called = previous.is_some_and(|previous| {
+                    matches!(previous, Message::Assistant(_))
+                        && previous.parts().iter().any(|p| call_id(p) == Some(id))
+                });
+                if !called {
+                    return Err(HistoryError::OrphanResult {
+                        index,
+                        id: id.to_string(),
+                    });
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Replace the result of a tool call, e.g. with a shorter version of it.
+///
+/// Media attached to the previous result is dropped.
+pub fn replace_tool_result(
+    messages: &mut [Message],
+    call_id: &str,
+    result: Value,
+) -> Result<(), HistoryError> {
+    let part = messages
+        .iter_mut()
+        .flat_map(Message::parts_mut)
+        .find(|part| {
+            matches!(part, Part::FunctionResponse { .. }) && result_id(part) == Some(call_id)
+        })
+        .ok_or_else(|| HistoryError::ResultNotFound(call_id.to_string()))?;
+    if let Part::FunctionResponse {
+        response, parts, ..
+    } = part
+    {
+        *response = result;
+        parts.clear();
+    }
+    Ok(())
+}
+
+/// Replace the content of a message with a placeholder.
+///
+/// Text and media become the placeholder and reasoning and citations are
+/// dropped. Tool calls and results keep their ids and names, with empty
+/// arguments and the placeholder as result, so they stay paired. Computer use
+/// and hosted tool parts are kept as is.
+pub fn redact_message(
+    messages: &mut [Message],
+    index: usize,
+    placeholder: &str,
+) -> Result<(), HistoryError> {
+    let message = messages
+        .get_mut(index)
+        .ok_or(HistoryError::NoMessage(index))?;
+    let redacted = |part: Part| match part {
+        Part::Text { .. } | Part::Media { .. } => Some(Part::Text {
+            content: placeholder.to_string(),
+            finished: true,
+        }),
+        Part::Reasoning { .. } | Part::Citation { .. } => None,
+        Part::FunctionCall { id, name, .. } => Some(Part::FunctionCall {
+            id,
+            name,
+            arguments: serde_json::json!({}),
+            signature: None,
+            raw_arguments: None,
+            finished: true,
+        }),
+        Part::FunctionResponse { id, name, .. } => Some(Part::FunctionResponse {
+            id,
+            name,
+            response: Value::String(placeholder.to_string()),
+            parts: vec![],
+            finished: true,
+        }),
+        // Screenshots cannot be replaced by text, and computer calls must stay
+        // answered by one.
+        part @ (Part::ComputerCall { .. }
+        | Part::ComputerResult { .. }
+        | Part::HostedToolCall { .. }) => Some(part),
+    };
+
+    let parts = std::mem::take(message.parts_mut());
+    let mut parts: Vec<Part> = parts.into_iter().filter_map(redacted).collect();
+    // Merge the runs of placeholders left by consecutive text and media parts.
+    parts.dedup_by(|a, b| {
+        matches!((a, b), (Part::Text { content: a, .. }, Part::Text { content: b, .. }) if a == b)
+    });
+    *message.parts_mut() = parts;
+    Ok(())
+}
+
+/// Replace a tool call and its result with a text summary.
+///
+/// The summary takes the place of the call in the assistant message. Messages
+/// left empty are removed, and the assistant messages around a removed result
+/// message are merged.
+pub fn collapse_tool_call(
+    messages: &mut Vec<Message>,
+    id: &str,
+    summary: &str,
+) -> Result<(), HistoryError> {
+    let (call_index, part_index) = messages
+        .iter()
+        .enumerate()
+        .find_map(|(i, message)| {
+            let part = message
+                .parts()
+                .iter()
+                .position(|p| call_id(p) == Some(id))?;
+            Some((i, part))
+        })
+        .ok_or_else(|| HistoryError::CallNotFound(id.to_string()))?;
+
+    messages[call_index].parts_mut()[part_index] = Part::Text {
+        content: summary.to_string(),
+        finished: true,
+    };
+
+    for index in (0..messages.len()).rev() {
+        messages[index]
+            .parts_mut()
+            .retain(|part| result_id(part) != Some(id));
+        if messages[index].parts().is_empty() {
+            messages.remove(index);
+            merge_at(messages, index);
+        }
+    }
+    Ok(())
+}
+
+/// Merge the message at `index` into the previous one if they have the same
+/// role.
+fn merge_at(messages: &mut Vec<Message>, index: usize) {
+    if index == 0 || index >= messages.len() {
+        return;
+    }
+    if messages[index - 1].role() == messages[index].role() {
+        let message = messages.remove(index);
+        messages[index - 1]
+            .parts_mut()
+            .extend(message.parts().iter().cloned());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn text(content: &str) -> Part {
+        Part::Text {
+            content: content.to_string(),
+            finished: true,
+        }
+    }
+
+    fn call(id: &str) -> Part {
+        Part::FunctionCall {
+            id: Some(id.to_string()),
+            name: "weather".to_string(),
+            arguments: json!({ "city": "Paris" }),
+            signature: None,
+            raw_arguments: None,
+            finished: true,
+        }
+    }
+
+    fn result(id: &str, response: Value) -> Part {
+        Part::FunctionResponse {
+            id: Some(id.to_string()),
+            name: "weather".to_string(),
+            response,
+            parts: vec![],
+            finished: true,
+        }
+    }
+
+    fn history() -> Vec<Message> {
+        vec![
+            Message::User(vec![text("Weather in Paris?")]),
+            Message::Assistant(vec![text("Let me check."), call("1")]),
+            Message::User(vec![result("1", json!({ "forecast": "sunny" }))]),
+            Message::Assistant(vec![text("It is sunny.")]),
+        ]
+    }
+
+    #[test]
+    fn test_validate_history() {
+        let messages = history();
+        assert_eq!(validate_history(&messages), Ok(()));
+
+        assert_eq!(
+            validate_history(&messages[..2]),
+            Err(HistoryError::UnansweredCall {
+                index: 1,
+                id: "1".to_string()
+            })
+        );
+        assert_eq!(
+            validate_history(&[messages[0].clone(), messages[2].clone()]),
+            Err(HistoryError::OrphanResult {
+                index: 1,
+                id: "1".to_string()
+            })
+        );
+        assert_eq!(
+            validate_history(&[Message::User(vec![])]),
+            Err(HistoryError::EmptyMessage(0))
+        );
+    }
+
+    #[test]
+    fn test_replace_and_redact() {
+        let mut messages = history();
+        replace_tool_result(&mut messages, "1", json!("sunny")).unwrap();
+        assert_eq!(messages[2].parts()[0], result("1", json!("sunny")));
+        assert_eq!(
+            replace_tool_result(&mut messages, "2", json!(null)),
+            Err(HistoryError::ResultNotFound("2".to_string()))
+        );
+
+        redact_message(&mut messages, 1, "[redacted]").unwrap();
+        redact_message(&mut messages, 2, "[redacted]").unwrap();
+        assert_eq!(messages[1].content().as_deref(), Some("[redacted]"));
+        assert!(matches!(
+            &messages[1].parts()[1],
+            Part::FunctionCall { id: Some(id), arguments, .. } if id == "1" && *arguments == json!({})
+        ));
+        assert_eq!(messages[2].parts()[0], result("1", json!("[redacted]")));
+        assert_eq!(validate_history(&messages), Ok(()));
+        assert_eq!(
+            redact_message(&mut messages, 4, ""),
+            Err(HistoryError::NoMessage(4))
+        );
+    }
+
+    #[test]
+    fn test_collapse_tool_call() {
+        let mut messages = history();
+        collapse_tool_call(&mut messages, "1", "Checked the weather: sunny.").unwrap();
+
+        assert_eq!(
+            messages,
+            vec![
+                Message::User(vec![text("Weather in Paris?")]),
+                Message::Assistant(vec![
+                    text("Let me check."),
+                    text("Checked the weather: sunny."),
+                    text("It is sunny."),
+                ]),
+            ]
+        );
+        assert_eq!(validate_history(&messages), Ok(()));
+        assert_eq!(
+            collapse_tool_call(&mut messages, "1", ""),
+            Err(HistoryError::CallNotFound("1".to_string()))
+        );
+    }
+}
@@ -0,0 +1,127 @@
+//! Results of tools hosted by the provider.
+//!
+//! Some providers run tools such as web search or a code sandbox on their own
+//! servers. The model calls them without a round trip through the application,
+//! and the response reports what was done as a [`Part::HostedToolCall`]. Text
+//! backed by search or file results is followed by [`Part::Citation`] parts.
+//!
+//! Hosted tools are enabled for any provider with
+//! [`ModelOptions::built_in_tools`], next to the MCP tools of a request:
+//!
+//! ```ignore
+//! use unia::hosted::BuiltInTool;
+//!
+//! let mut options = ModelOptions::new("gemini-2.5-flash");
+//! options.built_in_tools = Some(vec![BuiltInTool::WebSearch]);
+//! ```
+//!
+//! The OpenAI Responses client can also enable tools with provider-specific
+//! settings through `with_hosted_tool`; those replace the option of the same
+//! type, so each tool is sent once.
+//!
+//! Agents do not run hosted tools themselves; their calls and results are kept
+//! in the response like any other part.
+//!
+//! [`Part::HostedToolCall`]: crate::model::Part::HostedToolCall
+//! [`Part::Citation`]: crate::model::Part::Citation
+//! [`ModelOptions::built_in_tools`]: crate::options::ModelOptions::built_in_tools
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// A tool hosted by the provider, enabled with
+/// [`ModelOptions::built_in_tools`](crate::options::ModelOptions::built_in_tools).
+///
+/// | Tool | OpenAI Responses | Anthropic | Gemini |
+/// |------|------------------|-----------|--------|
+/// | `WebSearch` | `web_search` | `web_search` | `google_search` |
+/// | `CodeExecution` | `code_interpreter` | `code_execution` | `code_execution` |
+///
+/// Tools a provider does not host are left out of the request with a warning.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BuiltInTool {
+    /// Search the web, reported as [`HostedToolCall::WebSearch`].
+    WebSearch,
+    /// Run code in a sandbox, reported as [`HostedToolCall::CodeInterpreter`].
+    CodeExecution,
+}
+
+/// A call of a hosted tool and its results.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum HostedToolCall {
+    /// A web search.
+    WebSearch {
+        query: Option<String>,
+        /// Pages consulted by the search, if the provider reports them.
+        #[serde(default)]
+        sources: Vec<SearchSource>,
+    },
+    /// A search over uploaded files.
+    FileSearch {
+        #[serde(default)]
+        queries: Vec<String>,
+        /// Matching file chunks, if the provider reports them.
+        #[serde(default)]
+        results: Vec<FileSearchResult>,
+    },
+    /// Code run in a sandbox.
+    CodeInterpreter {
+        code: Option<String>,
+        container_id: Option<String>,
+        #[serde(default)]
+        outputs: Vec<CodeOutput>,
+    },
+}
+
+/// A page consulted by a web search.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct SearchSource {
+    pub url: String,
+    #[serde(default)]
+    pub title: Option<String>,
+}
+
+/// A file chunk matching a file search.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct FileSearchResult {
+    pub file_id: String,
+    #[serde(default)]
+    pub filename: Option<String>,
+    /// Relevance score between 0 and 1.
+    #[serde(default)]
+    pub score: Option<f64>,
+    /// Text of the matching chunk.
+    #[serde(default)]
+    pub text: Option<String>,
+}
+
+/// Output of sandboxed code.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum CodeOutput {
+    /// Standard output and error.
+    Logs { logs: String },
+    /// A generated image.
+    Image { url: String },
+}
+
+/// A source backing part of a response text.
+///
+/// Offsets are character offsets into the closest preceding text part.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct Citation {
+    /// Web page or document URI.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// Id of a file uploaded to the provider.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start_index: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub end_index: Option<usize>,
+}
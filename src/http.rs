@@ -1,13 +1,22 @@
 //! HTTP client utilities for making requests to LLM APIs.
 
-use reqwest::{Client, RequestBuilder};
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use rand::Rng;
+use regex::Regex;
+use reqwest::header::RETRY_AFTER;
+use reqwest::{Client, RequestBuilder, StatusCode};
+use serde_json::{json, Value};
+use std::sync::LazyLock;
+use std::time::Duration;
 
 use crate::client::ClientError;
-use crate::options::TransportOptions;
+use crate::options::{LogFormat, LogPolicy, RetryPolicy, TransportOptions};
+use crate::ratelimit::RateLimiter;
 
 /// Build a configured HTTP client from transport options.
 pub fn build_http_client(transport_options: &TransportOptions) -> Result<Client, reqwest::Error> {
-    let mut builder = Client::builder();
+    let mut builder = Client::builder().user_agent(transport_options.user_agent());
 
     match transport_options {
         TransportOptions::Http { timeout, proxy, .. } => {
@@ -25,7 +34,8 @@ pub fn build_http_client(transport_options: &TransportOptions) -> Result<Client,
     builder.build()
 }
 
-/// Add extra headers to a request if specified in transport options.
+/// Add extra headers to a request if specified in transport options, along with
+/// the headers of the current [`TraceContext`](crate::trace::TraceContext).
 pub fn add_extra_headers(
     mut request: RequestBuilder,
     transport_options: &TransportOptions,
@@ -39,50 +49,689 @@ pub fn add_extra_headers(
             }
         }
     }
+    crate::trace::inject(request)
+}
+
+/// Sends requests in place of the network, for example to record and replay
+/// provider traffic in tests. Set with
+/// [`TransportOptions::with_http_transport`].
+///
+/// Every request of a client passes through it, including each retry, after
+/// the rate limiter and before the response is parsed.
+#[async_trait]
+pub trait HttpTransport: Send + Sync + std::fmt::Debug {
+    /// Send a request built by `client`.
+    async fn send(
+        &self,
+        client: Client,
+        request: reqwest::Request,
+    ) -> Result<reqwest::Response, ClientError>;
+}
+
+/// Send a request, retrying transient failures according to the transport's retry policy.
+///
+/// If the transport has a rate limiter, every attempt waits for its quota first
+/// and the limiter is updated from the rate limit headers of the response.
+///
+/// Non-success responses that are not retryable (or that exhaust the retry budget)
+/// are returned as-is so callers can map them to provider-specific errors.
+pub async fn send_with_retry(
+    request: RequestBuilder,
+    transport_options: &TransportOptions,
+) -> Result<reqwest::Response, ClientError> {
+    let limiter = transport_options.rate_limiter().map(|limiter| {
+        let tokens = estimate_body_tokens(&request);
+        (limiter.as_ref(), tokens)
+    });
+
+    let transport = transport_options.http_transport().map(|t| t.as_ref());
+
+    let Some(policy) = transport_options.retry_policy() else {
+        return send_limited(request, limiter, transport).await;
+    };
+
+    let mut attempt = 1;
+    loop {
+        let Some(current) = request.try_clone() else {
+            // Streaming bodies cannot be replayed.
+            return send_limited(request, limiter, transport).await;
+        };
+
+        let last_attempt = attempt >= policy.max_attempts;
+        let delay = match send_limited(current, limiter, transport).await {
+            Ok(response) => {
+                let status = response.status();
+                if last_attempt || !policy.should_retry_status(status.as_u16()) {
+                    return Ok(response);
+                }
+                retry_after(&response, policy).unwrap_or_else(|| jittered_backoff(policy, attempt))
+            }
+            Err(e) => {
+                if last_attempt || !e.is_transient() {
+                    return Err(e);
+                }
+                jittered_backoff(policy, attempt)
+            }
+        };
+
+        tracing::warn!(
+            "Request attempt {}/{} failed, retrying in {:?}",
+            attempt,
+            policy.max_attempts,
+            delay
+        );
+        tokio::time::sleep(delay).await;
+        attempt += 1;
+    }
+}
+
+/// Time a request was sent, kept in the extensions of its response to log the
+/// request duration.
+#[derive(Clone, Copy)]
+struct SentAt(std::time::Instant);
+
+/// Send a request once, waiting for rate limiter quota first.
+async fn send_limited(
+    request: RequestBuilder,
+    limiter: Option<(&RateLimiter, usize)>,
+    transport: Option<&dyn HttpTransport>,
+) -> Result<reqwest::Response, ClientError> {
+    if let Some((limiter, tokens)) = limiter {
+        limiter.acquire(tokens).await;
+    }
+    let sent_at = SentAt(std::time::Instant::now());
+    let mut response = match transport {
+        Some(transport) => {
+            let (client, request) = request.build_split();
+            transport.send(client, request?).await?
+        }
+        None => request.send().await?,
+    };
+    response.extensions_mut().insert(sent_at);
+    if let Some((limiter, _)) = limiter {
+        limiter.observe_headers(response.headers());
+    }
+    Ok(response)
+}
+
+/// Estimate the prompt tokens of a request from the size of its body, at about
+/// four bytes per token.
+fn estimate_body_tokens(request: &RequestBuilder) -> usize {
     request
+        .try_clone()
+        .and_then(|request| request.build().ok())
+        .and_then(|request| Some(request.body()?.as_bytes()?.len()))
+        .map_or(0, |bytes| bytes.div_ceil(4))
+}
+
+/// Parse the `Retry-After` header of 429/503 responses, capped at the policy's maximum delay.
+fn retry_after(response: &reqwest::Response, policy: &RetryPolicy) -> Option<Duration> {
+    if !matches!(
+        response.status(),
+        StatusCode::TOO_MANY_REQUESTS | StatusCode::SERVICE_UNAVAILABLE
+    ) {
+        return None;
+    }
+
+    let value = response.headers().get(RETRY_AFTER)?.to_str().ok()?;
+    Some(parse_retry_after(value, Utc::now())?.min(policy.max_delay))
+}
+
+/// Parse a `Retry-After` value, either a number of seconds or an HTTP date.
+/// Dates in the past mean no delay.
+fn parse_retry_after(value: &str, now: DateTime<Utc>) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<f64>() {
+        if !seconds.is_finite() || seconds < 0.0 {
+            return None;
+        }
+        return Some(Duration::from_secs_f64(seconds));
+    }
+
+    // The preferred IMF-fixdate format is also valid RFC 2822. The obsolete
+    // RFC 850 and asctime formats are accepted as well.
+    let date = DateTime::parse_from_rfc2822(value)
+        .map(|date| date.with_timezone(&Utc))
+        .or_else(|_| {
+            NaiveDateTime::parse_from_str(value, "%A, %d-%b-%y %H:%M:%S GMT").map(|d| d.and_utc())
+        })
+        .or_else(|_| {
+            NaiveDateTime::parse_from_str(value, "%a %b %e %H:%M:%S %Y").map(|d| d.and_utc())
+        })
+        .ok()?;
+    Some((date - now).to_std().unwrap_or_default())
+}
+
+pub(crate) fn jittered_backoff(policy: &RetryPolicy, attempt: u32) -> Duration {
+    let delay = policy.backoff(attempt);
+    if policy.jitter {
+        delay.mul_f64(rand::rng().random_range(0.5..=1.0))
+    } else {
+        delay
+    }
+}
+
+/// A `multipart/form-data` request body, used to upload files.
+///
+/// Unlike [`reqwest::multipart::Form`], the body is encoded to bytes, so
+/// requests sending it can be retried by [`send_with_retry`].
+#[derive(Debug, Clone)]
+pub struct MultipartForm {
+    boundary: String,
+    fields: Vec<MultipartField>,
+}
+
+#[derive(Debug, Clone)]
+struct MultipartField {
+    name: String,
+    file: Option<(String, String)>,
+    data: Vec<u8>,
+}
+
+impl Default for MultipartForm {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MultipartForm {
+    /// Create an empty form.
+    pub fn new() -> Self {
+        Self {
+            boundary: format!("unia-{}", uuid::Uuid::new_v4().simple()),
+            fields: Vec::new(),
+        }
+    }
+
+    /// Add a text field.
+    pub fn text(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.fields.push(MultipartField {
+            name: name.into(),
+            file: None,
+            data: value.into().into_bytes(),
+        });
+        self
+    }
+
+    /// Add a file field.
+    pub fn file(
+        mut self,
+        name: impl Into<String>,
+        data: Vec<u8>,
+        mime_type: impl Into<String>,
+        file_name: impl Into<String>,
+    ) -> Self {
+        self.fields.push(MultipartField {
+            name: name.into(),
+            file: Some((file_name.into(), mime_type.into())),
+            data,
+        });
+        self
+    }
+
+    /// Value of the `Content-Type` header for the form.
+    pub fn content_type(&self) -> String {
+        format!("multipart/form-data; boundary={}", self.boundary)
+    }
+
+    /// Encode the form.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut body = Vec::new();
+        for field in &self.fields {
+            body.extend_from_slice(format!("--{}\r\n", self.boundary).as_bytes());
+            let mut disposition = format!(
+                "Content-Disposition: form-data; name=\"{}\"",
+                quote_form_value(&field.name)
+            );
+            if let Some((file_name, mime_type)) = &field.file {
+                disposition.push_str(&format!(
+                    "; filename=\"{}\"\r\nContent-Type: {}",
+                    quote_form_value(file_name),
+                    mime_type
+                ));
+            }
+            body.extend_from_slice(format!("{}\r\n\r\n", disposition).as_bytes());
+            body.extend_from_slice(&field.data);
+            body.extend_from_slice(b"\r\n");
+        }
+        body.extend_from_slice(format!("--{}--\r\n", self.boundary).as_bytes());
+        body
+    }
+
+    /// Text fields with their values and files with their sizes, for logging.
+    fn summary(&self) -> String {
+        self.fields
+            .iter()
+            .map(|field| match &field.file {
+                Some((file_name, mime_type)) => format!(
+                    "{}: file {} ({}, {} bytes)",
+                    field.name,
+                    file_name,
+                    mime_type,
+                    field.data.len()
+                ),
+                None => format!("{}: {}", field.name, String::from_utf8_lossy(&field.data)),
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// Escape a name for a quoted `Content-Disposition` parameter.
+fn quote_form_value(value: &str) -> String {
+    value
+        .replace('"', "%22")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
 }
 
 /// Extension trait for RequestBuilder that logs request body.
 pub trait RequestBuilderExt {
-    /// Set JSON request body and log it. Returns the RequestBuilder for chaining.
-    fn json_logged<T: serde::Serialize + ?Sized>(self, json: &T) -> Self;
+    /// Set JSON request body and log it according to the transport's
+    /// [`LogPolicy`]. Returns the RequestBuilder for chaining.
+    fn json_logged<T: serde::Serialize + ?Sized>(
+        self,
+        json: &T,
+        transport_options: &TransportOptions,
+    ) -> Self;
+
+    /// Set a multipart request body and log its fields according to the
+    /// transport's [`LogPolicy`]. File contents are never logged.
+    fn multipart_logged(self, form: &MultipartForm, transport_options: &TransportOptions) -> Self;
 }
 
 impl RequestBuilderExt for RequestBuilder {
-    fn json_logged<T: serde::Serialize + ?Sized>(self, json: &T) -> Self {
-        if let Ok(req_body) = serde_json::to_string_pretty(json) {
-            tracing::debug!("API request body ({} bytes):\n{}", req_body.len(), req_body);
+    fn json_logged<T: serde::Serialize + ?Sized>(
+        self,
+        json: &T,
+        transport_options: &TransportOptions,
+    ) -> Self {
+        let policy = transport_options.log_policy();
+        if policy != LogPolicy::Off && tracing::enabled!(tracing::Level::DEBUG) {
+            if let Ok(mut value) = serde_json::to_value(json) {
+                if policy == LogPolicy::Redacted {
+                    redact_log_value(&mut value);
+                }
+                let req_body = match transport_options.log_format() {
+                    LogFormat::Text => serde_json::to_string_pretty(&value),
+                    LogFormat::Json => serde_json::to_string(&value),
+                };
+                if let Ok(req_body) = req_body {
+                    let event = BodyLog::request("API request body", &self);
+                    log_body(event, &req_body, transport_options);
+                }
+            }
         }
 
         self.json(json)
     }
+
+    fn multipart_logged(self, form: &MultipartForm, transport_options: &TransportOptions) -> Self {
+        if tracing::enabled!(tracing::Level::DEBUG) {
+            let event = BodyLog::request("API request form", &self);
+            log_body(event, &form.summary(), transport_options);
+        }
+
+        self.header(reqwest::header::CONTENT_TYPE, form.content_type())
+            .body(form.to_bytes())
+    }
 }
 
 /// Extension trait for Response that logs response body.
 #[async_trait::async_trait]
 pub trait ResponseExt {
-    /// Get response text and log it. Consumes the response.
-    async fn text_logged(self) -> Result<String, reqwest::Error>;
+    /// Get response text and log it according to the transport's [`LogPolicy`].
+    /// Consumes the response.
+    async fn text_logged(
+        self,
+        transport_options: &TransportOptions,
+    ) -> Result<String, reqwest::Error>;
 
-    /// Parse response as JSON and log it. Consumes the response.
-    async fn json_logged<T: serde::de::DeserializeOwned>(self) -> Result<T, ClientError>;
+    /// Parse response as JSON and log it according to the transport's
+    /// [`LogPolicy`]. Consumes the response.
+    async fn json_logged<T: serde::de::DeserializeOwned>(
+        self,
+        transport_options: &TransportOptions,
+    ) -> Result<T, ClientError>;
 }
 
 #[async_trait::async_trait]
 impl ResponseExt for reqwest::Response {
-    async fn text_logged(self) -> Result<String, reqwest::Error> {
+    async fn text_logged(
+        self,
+        transport_options: &TransportOptions,
+    ) -> Result<String, reqwest::Error> {
+        let event = BodyLog::response(&self);
         let text = self.text().await?;
-        tracing::debug!("API response ({} bytes):\n{}", text.len(), text);
+        log_response(event, &text, transport_options);
         Ok(text)
     }
 
-    async fn json_logged<T: serde::de::DeserializeOwned>(self) -> Result<T, ClientError> {
+    async fn json_logged<T: serde::de::DeserializeOwned>(
+        self,
+        transport_options: &TransportOptions,
+    ) -> Result<T, ClientError> {
+        let event = BodyLog::response(&self);
         let bytes = self.bytes().await?;
 
         if let Ok(text) = std::str::from_utf8(&bytes) {
-            tracing::debug!("API response ({} bytes):\n{}", text.len(), text);
+            log_response(event, text, transport_options);
         }
 
         serde_json::from_slice(&bytes).map_err(ClientError::from)
     }
 }
+
+/// Metadata of a logged request or response body.
+struct BodyLog {
+    /// `request` or `response`.
+    kind: &'static str,
+    label: &'static str,
+    url: Option<String>,
+    status: Option<u16>,
+    duration: Option<Duration>,
+}
+
+impl BodyLog {
+    fn request(label: &'static str, request: &RequestBuilder) -> Self {
+        let url = request
+            .try_clone()
+            .and_then(|request| request.build().ok())
+            .map(|request| request.url().to_string());
+        Self {
+            kind: "request",
+            label,
+            url,
+            status: None,
+            duration: None,
+        }
+    }
+
+    /// Metadata of a response whose body is about to be read. The duration
+    /// includes reading the body.
+    fn response(response: &reqwest::Response) -> Self {
+        Self {
+            kind: "response",
+            label: "API response",
+            url: Some(response.url().to_string()),
+            status: Some(response.status().as_u16()),
+            duration: (response.extensions().get::<SentAt>()).map(|SentAt(start)| start.elapsed()),
+        }
+    }
+
+    /// Single-line JSON event for [`LogFormat::Json`]. The body is omitted with
+    /// [`LogPolicy::MetadataOnly`].
+    fn to_json(&self, body: &str, transport_options: &TransportOptions) -> Value {
+        let mut event = json!({
+            "event": self.kind,
+            "provider": transport_options.provider(),
+            "url": self.url,
+            "status": self.status,
+            "duration_ms": self.duration.map(|d| d.as_millis() as u64),
+            "bytes": body.len(),
+        });
+        if transport_options.log_policy() != LogPolicy::MetadataOnly {
+            let (logged, truncated) = truncate_body(body, transport_options.max_log_body_bytes());
+            event["body"] = Value::String(logged.to_string());
+            event["truncated"] = Value::Bool(truncated);
+        }
+        event
+    }
+}
+
+fn log_response(event: BodyLog, text: &str, transport_options: &TransportOptions) {
+    let policy = transport_options.log_policy();
+    if policy == LogPolicy::Redacted && tracing::enabled!(tracing::Level::DEBUG) {
+        let redacted = match serde_json::from_str::<Value>(text) {
+            Ok(mut value) => {
+                redact_log_value(&mut value);
+                value.to_string()
+            }
+            Err(_) => redact_log_text(text),
+        };
+        log_body(event, &redacted, transport_options);
+    } else {
+        log_body(event, text, transport_options);
+    }
+}
+
+fn log_body(event: BodyLog, body: &str, transport_options: &TransportOptions) {
+    let policy = transport_options.log_policy();
+    if policy == LogPolicy::Off {
+        return;
+    }
+    match transport_options.log_format() {
+        LogFormat::Json => {
+            tracing::debug!("{}", event.to_json(body, transport_options))
+        }
+        LogFormat::Text if policy == LogPolicy::MetadataOnly => {
+            tracing::debug!("{} ({} bytes)", event.label, body.len())
+        }
+        LogFormat::Text => {
+            let (logged, truncated) = truncate_body(body, transport_options.max_log_body_bytes());
+            let ellipsis = if truncated { "\n[truncated]" } else { "" };
+            tracing::debug!(
+                "{} ({} bytes):\n{}{}",
+                event.label,
+                body.len(),
+                logged,
+                ellipsis
+            )
+        }
+    }
+}
+
+/// Cut a body to at most `max_bytes` bytes on a character boundary. Returns
+/// whether it was cut.
+pub(crate) fn truncate_body(body: &str, max_bytes: Option<usize>) -> (&str, bool) {
+    match max_bytes {
+        Some(max_bytes) if body.len() > max_bytes => {
+            let mut end = max_bytes;
+            while !body.is_char_boundary(end) {
+                end -= 1;
+            }
+            (&body[..end], true)
+        }
+        _ => (body, false),
+    }
+}
+
+/// Strings at least this long that only use base64 characters are treated as
+/// media blobs.
+const MIN_BLOB_LEN: usize = 256;
+
+/// Object keys whose values are credentials.
+const SECRET_KEYS: &[&str] = &[
+    "api_key",
+    "apikey",
+    "api-key",
+    "x-api-key",
+    "x-goog-api-key",
+    "authorization",
+    "access_token",
+    "secret",
+];
+
+static API_KEY: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\b(?:sk-[A-Za-z0-9_-]{16,}|AIza[A-Za-z0-9_-]{30,})")
+        .expect("built-in pattern is valid")
+});
+
+static BLOB: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(&format!("[A-Za-z0-9+/]{{{},}}={{0,2}}", MIN_BLOB_LEN))
+        .expect("built-in pattern is valid")
+});
+
+/// Replace media blobs and credentials in a logged JSON body.
+fn redact_log_value(value: &mut Value) {
+    match value {
+        Value::String(s) => *s = redact_log_text(s),
+        Value::Array(items) => items.iter_mut().for_each(redact_log_value),
+        Value::Object(map) => {
+            for (key, value) in map.iter_mut() {
+                if SECRET_KEYS.contains(&key.to_ascii_lowercase().as_str()) {
+                    *value = Value::String("[REDACTED]".to_string());
+                } else {
+                    redact_log_value(value);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Replace media blobs and API keys in logged text.
+fn redact_log_text(text: &str) -> String {
+    let text = BLOB.replace_all(text, |caps: &regex::Captures| {
+        format!("[base64: {} chars]", caps[0].len())
+    });
+    API_KEY.replace_all(&text, "[API_KEY]").into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::server::{TestResponse, TestServer};
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_retry_after() {
+        let now = DateTime::parse_from_rfc3339("2015-10-21T07:28:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let seconds = |value| parse_retry_after(value, now).map(|d: Duration| d.as_secs_f64());
+        assert_eq!(seconds("120"), Some(120.0));
+        assert_eq!(seconds(" 1.5 "), Some(1.5));
+        assert_eq!(seconds("Wed, 21 Oct 2015 07:30:00 GMT"), Some(120.0));
+        assert_eq!(seconds("Wednesday, 21-Oct-15 07:29:00 GMT"), Some(60.0));
+        assert_eq!(seconds("Wed Oct 21 07:28:30 2015"), Some(30.0));
+        assert_eq!(seconds("Tue, 20 Oct 2015 07:28:00 GMT"), Some(0.0));
+        assert_eq!(seconds("-1"), None);
+        assert_eq!(seconds("soon"), None);
+    }
+
+    #[tokio::test]
+    async fn test_send_with_retry() {
+        // Without honoring Retry-After, the base delay would exceed the timeout.
+        let policy = RetryPolicy::new()
+            .with_max_attempts(3)
+            .with_base_delay(Duration::from_secs(60))
+            .with_jitter(false);
+        let transport = TransportOptions::new().with_retry(policy);
+        let send = |server: &TestServer| {
+            let client = build_http_client(&transport).unwrap();
+            let request = client.get(server.url());
+            let transport = transport.clone();
+            async move {
+                tokio::time::timeout(Duration::from_secs(5), send_with_retry(request, &transport))
+                    .await
+                    .expect("Retry-After was not honored")
+                    .unwrap()
+            }
+        };
+
+        let server = TestServer::start(|_, attempt| match attempt {
+            0 => TestResponse::new(429, "").with_header("Retry-After", "0"),
+            1 => TestResponse::new(503, "")
+                .with_header("Retry-After", "Wed, 21 Oct 2015 07:28:00 GMT"),
+            _ => TestResponse::new(200, "ok"),
+        })
+        .await;
+        assert_eq!(send(&server).await.status(), 200);
+        assert_eq!(server.requests().len(), 3);
+
+        let server = TestServer::start(|_, _| {
+            TestResponse::new(503, "busy").with_header("Retry-After", "0")
+        })
+        .await;
+        assert_eq!(send(&server).await.status(), 503);
+        assert_eq!(server.requests().len(), 3);
+
+        let server = TestServer::start(|_, _| TestResponse::new(400, "bad")).await;
+        assert_eq!(send(&server).await.status(), 400);
+        assert_eq!(server.requests().len(), 1);
+    }
+
+    #[test]
+    fn test_multipart_form() {
+        let form = MultipartForm::new().text("model", "whisper-1").file(
+            "file",
+            b"RIFF".to_vec(),
+            "audio/wav",
+            "say \"hi\".wav",
+        );
+        let request = Client::new()
+            .post("http://localhost")
+            .multipart_logged(&form, &TransportOptions::default());
+
+        // Byte bodies can be cloned for retries.
+        let request = request.try_clone().unwrap().build().unwrap();
+        let boundary = &form.boundary;
+        assert_eq!(
+            request.headers()[reqwest::header::CONTENT_TYPE],
+            format!("multipart/form-data; boundary={}", boundary)
+        );
+        assert_eq!(
+            String::from_utf8(request.body().unwrap().as_bytes().unwrap().to_vec()).unwrap(),
+            format!(
+                "--{b}\r\nContent-Disposition: form-data; name=\"model\"\r\n\r\nwhisper-1\r\n\
+                 --{b}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"say %22hi%22.wav\"\r\n\
+                 Content-Type: audio/wav\r\n\r\nRIFF\r\n--{b}--\r\n",
+                b = boundary
+            )
+        );
+    }
+
+    #[test]
+    fn test_json_log_event() {
+        let request = Client::new().post("https://api.openai.com/v1/chat/completions");
+        let event = BodyLog::request("API request body", &request);
+        let options = TransportOptions::new()
+            .with_log_format(LogFormat::Json)
+            .with_max_log_body_bytes(3)
+            .for_provider("openai");
+
+        assert_eq!(
+            event.to_json("{\"é\":1}", &options),
+            json!({
+                "event": "request",
+                "provider": "openai",
+                "url": "https://api.openai.com/v1/chat/completions",
+                "status": null,
+                "duration_ms": null,
+                "bytes": 8,
+                "body": "{\"",
+                "truncated": true,
+            })
+        );
+
+        let options = options.with_log_policy(LogPolicy::MetadataOnly);
+        assert!(event.to_json("{}", &options).get("body").is_none());
+        assert_eq!(truncate_body("short", Some(10)), ("short", false));
+    }
+
+    #[test]
+    fn test_redact_log_value() {
+        let blob = "iVBORw0KGgo".repeat(40);
+        let mut value = json!({
+            "api_key": "secret-value",
+            "messages": [{
+                "content": [
+                    { "type": "text", "text": "Key is sk-abcdefghijklmnop1234, see image" },
+                    { "type": "image_url", "image_url": { "url": format!("data:image/png;base64,{}", blob) } },
+                ]
+            }]
+        });
+        redact_log_value(&mut value);
+
+        assert_eq!(value["api_key"], "[REDACTED]");
+        assert_eq!(
+            value["messages"][0]["content"][0]["text"],
+            "Key is [API_KEY], see image"
+        );
+        assert_eq!(
+            value["messages"][0]["content"][1]["image_url"]["url"],
+            "data:image/png;base64,[base64: 440 chars]"
+        );
+    }
+}
@@ -0,0 +1,94 @@
+//! Image generation API support.
+//!
+//! Image generation reuses the regular provider clients: create a client with an
+//! image model (e.g. `OpenAI::create(key, "gpt-image-1".into())` or
+//! `Gemini::create(key, "gemini-2.5-flash-image".into())`) and call
+//! [`ImageGenerationClient::generate_images`] on it. Images are returned as
+//! `Part::Media` values, so they can be sent back in a conversation as is:
+//!
+//! ```ignore
+//! use unia::images::{ImageGenerationClient, ImageOptions};
+//!
+//! let images = client
+//!     .generate_images("A watercolor lighthouse at dusk", ImageOptions::new())
+//!     .await?;
+//! let message = Message::User(
+//!     std::iter::once(Part::Text {
+//!         content: "Describe this image.".to_string(),
+//!         finished: true,
+//!     })
+//!     .chain(images)
+//!     .collect(),
+//! );
+//! ```
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_with::skip_serializing_none;
+
+use crate::client::ClientError;
+use crate::model::Part;
+
+/// Trait for providers that can generate images from a prompt.
+#[async_trait]
+pub trait ImageGenerationClient: Send + Sync {
+    /// Generate images, returned as image `Part::Media` values with base64 data.
+    async fn generate_images(
+        &self,
+        prompt: &str,
+        options: ImageOptions,
+    ) -> Result<Vec<Part>, ClientError>;
+}
+
+/// Options for image generation. Unset values use the provider defaults.
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImageOptions {
+    /// Model to use instead of the client's model.
+    pub model: Option<String>,
+    /// Number of images to generate.
+    pub count: Option<u32>,
+    /// Image size such as `1024x1024`. OpenAI only.
+    pub size: Option<String>,
+    /// Quality such as `high` or `hd`, depending on the model. OpenAI only.
+    pub quality: Option<String>,
+    /// Image format: `png`, `jpeg` or `webp`. OpenAI GPT image models only.
+    pub output_format: Option<String>,
+}
+
+impl ImageOptions {
+    /// Create options with the provider defaults.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the model to use instead of the client's model.
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.model = Some(model.into());
+        self
+    }
+
+    /// Set the number of images to generate.
+    pub fn with_count(mut self, count: u32) -> Self {
+        self.count = Some(count);
+        self
+    }
+
+    /// Set the image size.
+    pub fn with_size(mut self, size: impl Into<String>) -> Self {
+        self.size = Some(size.into());
+        self
+    }
+
+    /// Set the image quality.
+    pub fn with_quality(mut self, quality: impl Into<String>) -> Self {
+        self.quality = Some(quality.into());
+        self
+    }
+
+    /// Set the image format.
+    pub fn with_output_format(mut self, output_format: impl Into<String>) -> Self {
+        self.output_format = Some(output_format.into());
+        self
+    }
+}
@@ -0,0 +1,491 @@
+//! Middleware for clients.
+//!
+//! A [`ClientLayer`] wraps a client in another client that adds a cross-cutting
+//! concern such as logging, redaction or rate limiting, without the provider
+//! implementations knowing about it. Layers are applied with
+//! [`ClientLayerExt::layer`]; the last layer applied is the outermost one and
+//! sees each request first:
+//!
+//! ```ignore
+//! use std::sync::Arc;
+//! use unia::layer::{ClientLayerExt, LoggingLayer, MetricsLayer};
+//! use unia::ratelimit::{RateLimitLayer, RateLimiter};
+//! use unia::redact::{PatternRedactor, RedactLayer};
+//!
+//! let metrics = MetricsLayer::new();
+//! let client = OpenAI::create(key, "gpt-5".into())
+//!     .layer(RateLimitLayer::new(Arc::new(RateLimiter::new().with_requests_per_minute(50))))
+//!     .layer(RedactLayer::new(PatternRedactor::pii()))
+//!     .layer(metrics.clone())
+//!     .layer(LoggingLayer);
+//!
+//! let response = client.request(messages, vec![]).await?;
+//! println!("{} requests so far", metrics.metrics().requests());
+//! ```
+//!
+//! Besides the layers in this module, [`RateLimitLayer`](crate::ratelimit::RateLimitLayer),
+//! [`RedactLayer`](crate::redact::RedactLayer), [`CacheLayer`](crate::cache::CacheLayer) and
+//! [`ModelPolicy`](crate::policy::ModelPolicy) can be applied as layers. Any
+//! `Fn(C) -> W` closure is a layer as well.
+
+use async_stream::stream;
+use async_trait::async_trait;
+use futures::{Stream, StreamExt};
+use rmcp::model::Tool;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::{debug, warn};
+
+use crate::client::{Capabilities, Client, ClientError, ModelInfo, StreamingClient};
+use crate::model::{Message, Response, Usage};
+use crate::options::{ModelOptions, TransportOptions};
+use crate::stream::{merge_usage, StreamDelta};
+
+/// Wraps a client in a client adding some behavior.
+pub trait ClientLayer<C> {
+    /// The wrapping client.
+    type Client;
+
+    /// Wrap a client.
+    fn layer(&self, inner: C) -> Self::Client;
+}
+
+impl<C, W, F> ClientLayer<C> for F
+where
+    F: Fn(C) -> W,
+{
+    type Client = W;
+
+    fn layer(&self, inner: C) -> W {
+        self(inner)
+    }
+}
+
+/// Extension trait for applying [`ClientLayer`]s.
+pub trait ClientLayerExt: Client + Sized {
+    /// Wrap the client with a layer.
+    fn layer<L: ClientLayer<Self>>(self, layer: L) -> L::Client {
+        layer.layer(self)
+    }
+}
+
+impl<C: Client> ClientLayerExt for C {}
+
+/// Layer logging every request at debug level, and failures at warn level.
+///
+/// Events are emitted with target `unia::client` and carry the provider, model,
+/// duration and token usage, but no message content.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LoggingLayer;
+
+impl<C: Client> ClientLayer<C> for LoggingLayer {
+    type Client = LoggingClient<C>;
+
+    fn layer(&self, inner: C) -> LoggingClient<C> {
+        LoggingClient { inner }
+    }
+}
+
+/// Client wrapper added by [`LoggingLayer`].
+#[derive(Debug, Clone)]
+pub struct LoggingClient<C> {
+    inner: C,
+}
+
+impl<C: Client> LoggingClient<C> {
+    /// Consume the wrapper and return the inner client.
+    pub fn into_inner(self) -> C {
+        self.inner
+    }
+
+    fn log<T>(&self, kind: &str, started: Instant, result: &Result<T, ClientError>) {
+        let provider = self.inner.provider();
+        let model = &self.inner.model_options().model;
+        match result {
+            Ok(_) => debug!(
+                target: "unia::client",
+                provider,
+                model,
+                elapsed = ?started.elapsed(),
+                "{} succeeded",
+                kind
+            ),
+            Err(e) => warn!(
+                target: "unia::client",
+                provider,
+                model,
+                elapsed = ?started.elapsed(),
+                status = e.status(),
+                "{} failed: {}",
+                kind,
+                e
+            ),
+        }
+    }
+}
+
+#[async_trait]
+impl<C: Client> Client for LoggingClient<C> {
+    type ModelProvider = C::ModelProvider;
+
+    async fn request(
+        &self,
+        messages: Vec<Message>,
+        tools: Vec<Tool>,
+    ) -> Result<Response, ClientError> {
+        let started = Instant::now();
+        let result = self.inner.request(messages, tools).await;
+        self.log("Request", started, &result);
+        if let Ok(response) = &result {
+            debug!(
+                target: "unia::client",
+                prompt_tokens = response.usage.prompt_tokens,
+                completion_tokens = response.usage.completion_tokens,
+                finish = ?response.finish,
+                "Request usage"
+            );
+        }
+        result
+    }
+
+    fn model_options(&self) -> &ModelOptions<Self::ModelProvider> {
+        self.inner.model_options()
+    }
+
+    fn transport_options(&self) -> &TransportOptions {
+        self.inner.transport_options()
+    }
+
+    fn provider(&self) -> &'static str {
+        self.inner.provider()
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        self.inner.capabilities()
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>, ClientError> {
+        self.inner.list_models().await
+    }
+}
+
+#[async_trait]
+impl<C: StreamingClient> StreamingClient for LoggingClient<C> {
+    async fn request_stream(
+        &self,
+        messages: Vec<Message>,
+        tools: Vec<Tool>,
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<Response, ClientError>> + Send>>, ClientError>
+    {
+        let started = Instant::now();
+        let result = self.inner.request_stream(messages, tools).await;
+        self.log("Stream request", started, &result);
+        result
+    }
+
+    async fn request_stream_deltas(
+        &self,
+        messages: Vec<Message>,
+        tools: Vec<Tool>,
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<StreamDelta, ClientError>> + Send>>, ClientError>
+    {
+        let started = Instant::now();
+        let result = self.inner.request_stream_deltas(messages, tools).await;
+        self.log("Stream request", started, &result);
+        result
+    }
+}
+
+/// Request counters collected by a [`MetricsLayer`].
+#[derive(Debug, Default)]
+pub struct ClientMetrics {
+    requests: AtomicU64,
+    errors: AtomicU64,
+    prompt_tokens: AtomicU64,
+    completion_tokens: AtomicU64,
+    latency_micros: AtomicU64,
+}
+
+impl ClientMetrics {
+    /// Number of requests sent, including streamed ones.
+    pub fn requests(&self) -> u64 {
+        self.requests.load(Ordering::Relaxed)
+    }
+
+    /// Number of failed requests.
+    pub fn errors(&self) -> u64 {
+        self.errors.load(Ordering::Relaxed)
+    }
+
+    /// Prompt tokens reported by the provider.
+    pub fn prompt_tokens(&self) -> u64 {
+        self.prompt_tokens.load(Ordering::Relaxed)
+    }
+
+    /// Completion tokens reported by the provider.
+    pub fn completion_tokens(&self) -> u64 {
+        self.completion_tokens.load(Ordering::Relaxed)
+    }
+
+    /// Total time spent waiting for responses. For streams, this is the time
+    /// until the stream was opened.
+    pub fn total_latency(&self) -> Duration {
+        Duration::from_micros(self.latency_micros.load(Ordering::Relaxed))
+    }
+
+    fn record<T>(&self, started: Instant, result: &Result<T, ClientError>) {
+        self.requests.fetch_add(1, Ordering::Relaxed);
+        self.latency_micros
+            .fetch_add(started.elapsed().as_micros() as u64, Ordering::Relaxed);
+        if result.is_err() {
+            self.errors.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    fn record_usage(&self, usage: &Usage) {
+        self.prompt_tokens
+            .fetch_add(usage.prompt_tokens.unwrap_or(0) as u64, Ordering::Relaxed);
+        self.completion_tokens.fetch_add(
+            usage.completion_tokens.unwrap_or(0) as u64,
+            Ordering::Relaxed,
+        );
+    }
+}
+
+/// Layer counting requests, errors, tokens and latency.
+///
+/// Clones share the same [`ClientMetrics`], so one layer can be applied to
+/// several clients to aggregate their metrics.
+#[derive(Debug, Clone, Default)]
+pub struct MetricsLayer {
+    metrics: Arc<ClientMetrics>,
+}
+
+impl MetricsLayer {
+    /// Create a layer with fresh counters.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The counters updated by clients wrapped with this layer.
+    pub fn metrics(&self) -> &Arc<ClientMetrics> {
+        &self.metrics
+    }
+}
+
+impl<C: Client> ClientLayer<C> for MetricsLayer {
+    type Client = MetricsClient<C>;
+
+    fn layer(&self, inner: C) -> MetricsClient<C> {
+        MetricsClient {
+            inner,
+            metrics: self.metrics.clone(),
+        }
+    }
+}
+
+/// Client wrapper added by [`MetricsLayer`].
+#[derive(Debug, Clone)]
+pub struct MetricsClient<C> {
+    inner: C,
+    metrics: Arc<ClientMetrics>,
+}
+
+impl<C: Client> MetricsClient<C> {
+    /// The counters updated by this client.
+    pub fn metrics(&self) -> &Arc<ClientMetrics> {
+        &self.metrics
+    }
+
+    /// Consume the wrapper and return the inner client.
+    pub fn into_inner(self) -> C {
+        self.inner
+    }
+}
+
+#[async_trait]
+impl<C: Client> Client for MetricsClient<C> {
+    type ModelProvider = C::ModelProvider;
+
+    async fn request(
+        &self,
+        messages: Vec<Message>,
+        tools: Vec<Tool>,
+    ) -> Result<Response, ClientError> {
+        let started = Instant::now();
+        let result = self.inner.request(messages, tools).await;
+        self.metrics.record(started, &result);
+        if let Ok(response) = &result {
+            self.metrics.record_usage(&response.usage);
+        }
+        result
+    }
+
+    fn model_options(&self) -> &ModelOptions<Self::ModelProvider> {
+        self.inner.model_options()
+    }
+
+    fn transport_options(&self) -> &TransportOptions {
+        self.inner.transport_options()
+    }
+
+    fn provider(&self) -> &'static str {
+        self.inner.provider()
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        self.inner.capabilities()
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>, ClientError> {
+        self.inner.list_models().await
+    }
+}
+
+#[async_trait]
+impl<C: StreamingClient> StreamingClient for MetricsClient<C> {
+    async fn request_stream(
+        &self,
+        messages: Vec<Message>,
+        tools: Vec<Tool>,
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<Response, ClientError>> + Send>>, ClientError>
+    {
+        let started = Instant::now();
+        let result = self.inner.request_stream(messages, tools).await;
+        self.metrics.record(started, &result);
+        let mut inner = result?;
+
+        // Snapshots carry the usage accumulated so far; count the last one.
+        let metrics = self.metrics.clone();
+        Ok(Box::pin(stream! {
+            let mut usage = None;
+            while let Some(item) = inner.next().await {
+                match &item {
+                    Ok(response) => usage = Some(response.usage.clone()),
+                    Err(_) => {
+                        metrics.errors.fetch_add(1, Ordering::Relaxed);
+                    }
+                }
+                yield item;
+            }
+            if let Some(usage) = usage {
+                metrics.record_usage(&usage);
+            }
+        }))
+    }
+
+    async fn request_stream_deltas(
+        &self,
+        messages: Vec<Message>,
+        tools: Vec<Tool>,
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<StreamDelta, ClientError>> + Send>>, ClientError>
+    {
+        let started = Instant::now();
+        let result = self.inner.request_stream_deltas(messages, tools).await;
+        self.metrics.record(started, &result);
+
+        let mut inner = result?;
+
+        // Usage deltas replace the values reported before; count the merged total.
+        let metrics = self.metrics.clone();
+        Ok(Box::pin(stream! {
+            let mut usage = Usage::default();
+            while let Some(item) = inner.next().await {
+                match &item {
+                    Ok(StreamDelta::Usage(update)) => merge_usage(&mut usage, update),
+                    Ok(_) => {}
+                    Err(_) => {
+                        metrics.errors.fetch_add(1, Ordering::Relaxed);
+                    }
+                }
+                yield item;
+            }
+            metrics.record_usage(&usage);
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{FinishReason, Part};
+    use crate::redact::RedactLayer;
+
+    /// Echoes the text of the last message, failing on empty input.
+    struct EchoClient {
+        options: ModelOptions<()>,
+        transport: TransportOptions,
+    }
+
+    #[async_trait]
+    impl Client for EchoClient {
+        type ModelProvider = ();
+
+        async fn request(
+            &self,
+            messages: Vec<Message>,
+            _tools: Vec<Tool>,
+        ) -> Result<Response, ClientError> {
+            let text = messages
+                .last()
+                .and_then(Message::content)
+                .ok_or_else(|| ClientError::ProviderError("Empty input".to_string()))?;
+            Ok(Response {
+                data: vec![Message::Assistant(vec![Part::Text {
+                    content: text,
+                    finished: true,
+                }])],
+                usage: Usage {
+                    prompt_tokens: Some(10),
+                    completion_tokens: Some(5),
+                    ..Default::default()
+                },
+                finish: FinishReason::Stop,
+                alternatives: Vec::new(),
+                model: None,
+                iterations: Vec::new(),
+            })
+        }
+
+        fn model_options(&self) -> &ModelOptions<()> {
+            &self.options
+        }
+
+        fn transport_options(&self) -> &TransportOptions {
+            &self.transport
+        }
+    }
+
+    #[tokio::test]
+    async fn test_layers() {
+        let metrics = MetricsLayer::new();
+        let client = EchoClient {
+            options: ModelOptions::new("test-model"),
+            transport: TransportOptions::default(),
+        }
+        .layer(RedactLayer::new(|text: &str| {
+            text.replace("secret", "[REDACTED]")
+        }))
+        .layer(metrics.clone())
+        .layer(LoggingLayer);
+
+        let message = |text: &str| {
+            vec![Message::User(vec![Part::Text {
+                content: text.to_string(),
+                finished: true,
+            }])]
+        };
+        let response = client.request(message("my secret"), vec![]).await.unwrap();
+        assert_eq!(response.data[0].content().as_deref(), Some("my [REDACTED]"));
+        assert!(client.request(vec![], vec![]).await.is_err());
+
+        let metrics = metrics.metrics();
+        assert_eq!(metrics.requests(), 2);
+        assert_eq!(metrics.errors(), 1);
+        assert_eq!(metrics.prompt_tokens(), 10);
+        assert_eq!(metrics.completion_tokens(), 5);
+        assert_eq!(client.model_options().model, "test-model");
+    }
+}
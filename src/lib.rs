@@ -57,18 +57,57 @@
 
 pub mod agent;
 pub mod api;
+pub mod batch;
+pub mod cache;
 pub mod client;
+pub mod computer;
+pub mod cost;
+pub mod diff;
+pub mod embeddings;
+pub mod experiment;
+pub mod export;
+pub mod files;
+pub mod finetune;
+pub mod history;
+pub mod hosted;
 pub mod http;
+pub mod images;
+pub mod layer;
+pub mod markdown;
 pub mod mcp;
+pub mod memory;
 pub mod model;
+pub mod moderation;
 pub mod options;
+pub mod orchestration;
+#[cfg(feature = "otel")]
+pub mod otel;
+pub mod policy;
+pub mod prompt;
 pub mod providers;
+pub mod ratelimit;
+#[cfg(feature = "realtime")]
+pub mod realtime;
+pub mod redact;
+pub mod repair;
+pub mod residency;
+pub mod routing;
+pub mod schema;
+pub mod serve;
 pub mod sse;
 pub mod stream;
+pub mod strict;
+pub mod synth;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+pub mod tokenize;
 pub mod tools;
+pub mod trace;
+pub mod transcription;
 
-pub use agent::Agent;
-pub use client::{Client, ClientError, StreamingClient};
+pub use agent::{Agent, AgentError};
+pub use client::{BoxedClient, Client, ClientError, DynClient, StreamingClient};
+pub use embeddings::EmbeddingsClient;
 pub use mcp::{AttachResources, MCPServer};
 pub use model::{GeneralRequest, Message, Response};
 pub use tools::{Tool, ToolError, ToolService};
@@ -0,0 +1,307 @@
+//! Plain-text output support.
+//!
+//! When [`ModelOptions::plain_text`](crate::options::ModelOptions::plain_text) is
+//! set, providers ask the model for unformatted text and pass the text parts of
+//! every response through [`strip_markdown`], so leftover formatting does not end
+//! up in speech synthesis or SMS messages. Streamed text deltas are stripped line
+//! by line.
+
+use futures::{Stream, StreamExt};
+use regex::Regex;
+use std::collections::btree_map::Entry;
+use std::collections::BTreeMap;
+use std::sync::LazyLock;
+
+use crate::client::ClientError;
+use crate::model::{Message, Part, Response};
+use crate::stream::StreamDelta;
+
+/// Instruction appended to the system prompt in plain-text mode.
+pub const PLAIN_TEXT_INSTRUCTION: &str = "Respond in plain text only. Do not use markdown or \
+any other formatting: no headings, bold or italic text, bullet points, tables, code blocks or links.";
+
+static FENCE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^\s*(```|~~~)").unwrap());
+static RULE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^\s{0,3}(?:(?:-\s*){3,}|(?:\*\s*){3,}|(?:_\s*){3,})$").unwrap());
+static TABLE_SEPARATOR: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^\s*\|?(?:\s*:?-{3,}:?\s*\|)+\s*(?::?-{3,}:?\s*)?$").unwrap());
+static LINE_PREFIX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^(\s*)(?:#{1,6}\s+|>\s?|[-*+]\s+)").unwrap());
+static INLINE: LazyLock<Vec<(Regex, &'static str)>> = LazyLock::new(|| {
+    [
+        (r"!\[([^\]]*)\]\([^)]*\)", "$1"),
+        (r"\[([^\]]+)\]\([^)]*\)", "$1"),
+        (r"`([^`]+)`", "$1"),
+        (r"\*\*(.+?)\*\*", "$1"),
+        (r"__(.+?)__", "$1"),
+        (r"~~(.+?)~~", "$1"),
+        (r"\*([^*\s][^*]*?)\*", "$1"),
+        (r"\b_([^_]+)_\b", "$1"),
+    ]
+    .into_iter()
+    .map(|(pattern, replacement)| (Regex::new(pattern).unwrap(), replacement))
+    .collect()
+});
+
+/// Remove markdown formatting from text, keeping its content.
+///
+/// Headings, emphasis, inline code, links, list markers, block quotes, rules and
+/// code fences are removed. Table rows are flattened into comma-separated text.
+/// The contents of code blocks are kept verbatim.
+pub fn strip_markdown(text: &str) -> String {
+    let mut stripper = LineStripper::default();
+    let lines: Vec<String> = text
+        .lines()
+        .filter_map(|line| stripper.strip(line))
+        .collect();
+
+    let mut stripped = lines.join("\n");
+    if text.ends_with('\n') {
+        stripped.push('\n');
+    }
+    stripped
+}
+
+/// Strips markdown one line at a time, tracking whether lines are in a code block.
+#[derive(Debug, Default)]
+struct LineStripper {
+    in_fence: bool,
+}
+
+impl LineStripper {
+    /// Strip a line, or drop it if it only carries formatting.
+    fn strip(&mut self, line: &str) -> Option<String> {
+        if FENCE.is_match(line) {
+            self.in_fence = !self.in_fence;
+            return None;
+        }
+        if self.in_fence {
+            return Some(line.to_string());
+        }
+        if RULE.is_match(line) || TABLE_SEPARATOR.is_match(line) {
+            return None;
+        }
+
+        let mut line = LINE_PREFIX.replace(line, "$1").into_owned();
+        if line.trim_start().starts_with('|') {
+            line = line
+                .trim()
+                .trim_matches('|')
+                .split('|')
+                .map(str::trim)
+                .collect::<Vec<_>>()
+                .join(", ");
+        }
+        for (regex, replacement) in INLINE.iter() {
+            line = regex.replace_all(&line, *replacement).into_owned();
+        }
+        Some(line)
+    }
+}
+
+/// Strip markdown from the text parts of a response.
+pub fn strip_response_markdown(response: &mut Response) {
+    for message in &mut response.data {
+        if let Message::Assistant(parts) = message {
+            for part in parts {
+                if let Part::Text { content, .. } = part {
+                    *content = strip_markdown(content);
+                }
+            }
+        }
+    }
+}
+
+/// Strip markdown from every snapshot of a response stream.
+pub(crate) fn strip_stream_markdown<S>(
+    stream: S,
+) -> impl Stream<Item = Result<Response, ClientError>> + Send
+where
+    S: Stream<Item = Result<Response, ClientError>> + Send,
+{
+    stream.map(|response| {
+        response.map(|mut response| {
+            strip_response_markdown(&mut response);
+            response
+        })
+    })
+}
+
+/// Strip markdown from the text deltas of a stream.
+///
+/// Formatting can span deltas, so text is held back until its line is complete.
+/// The last line of a part is released when the part or the response finishes.
+pub(crate) fn strip_delta_markdown<S>(
+    stream: S,
+) -> impl Stream<Item = Result<StreamDelta, ClientError>> + Send
+where
+    S: Stream<Item = Result<StreamDelta, ClientError>> + Send + 'static,
+{
+    async_stream::try_stream! {
+        let mut stream = Box::pin(stream);
+        // Incomplete line and stripper of each open text part.
+        let mut parts: BTreeMap<usize, (String, LineStripper)> = BTreeMap::new();
+
+        while let Some(delta) = stream.next().await {
+            match delta? {
+                StreamDelta::TextDelta { index, text } => {
+                    if let Entry::Vacant(part) = parts.entry(index) {
+                        // Open the part before its first line is complete, keeping the part order.
+                        part.insert(Default::default());
+                        yield StreamDelta::TextDelta { index, text: String::new() };
+                    }
+                    let (pending, stripper) = parts.get_mut(&index).unwrap();
+                    pending.push_str(&text);
+                    let mut released = String::new();
+                    while let Some(end) = pending.find('\n') {
+                        let line: String = pending.drain(..=end).collect();
+                        let line = line[..end].strip_suffix('\r').unwrap_or(&line[..end]);
+                        if let Some(line) = stripper.strip(line) {
+                            released.push_str(&line);
+                            released.push('\n');
+                        }
+                    }
+                    if !released.is_empty() {
+                        yield StreamDelta::TextDelta { index, text: released };
+                    }
+                }
+                StreamDelta::PartFinished { index } => {
+                    if let Some(text) = parts.remove(&index).and_then(release) {
+                        yield StreamDelta::TextDelta { index, text };
+                    }
+                    yield StreamDelta::PartFinished { index };
+                }
+                StreamDelta::Finish(reason) => {
+                    for (index, part) in std::mem::take(&mut parts) {
+                        if let Some(text) = release(part) {
+                            yield StreamDelta::TextDelta { index, text };
+                        }
+                    }
+                    yield StreamDelta::Finish(reason);
+                }
+                delta => yield delta,
+            }
+        }
+        for (index, part) in parts {
+            if let Some(text) = release(part) {
+                yield StreamDelta::TextDelta { index, text };
+            }
+        }
+    }
+}
+
+/// Strip the incomplete last line of a text part.
+fn release((pending, mut stripper): (String, LineStripper)) -> Option<String> {
+    if pending.is_empty() {
+        return None;
+    }
+    stripper.strip(&pending).filter(|line| !line.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::TryStreamExt;
+
+    #[test]
+    fn test_strip_markdown() {
+        let markdown = "\
+# Weather for **Paris**
+
+> Updated hourly
+
+- Temperature: *18°C*
+- Wind: `12 km/h`, see [the forecast](https://example.com)
+
+| Day | High |
+|-----|------|
+| Mon | 20 |
+
+---
+```
+let snake_case_name = 1;
+```
+Keep snake_case_name intact.";
+
+        assert_eq!(
+            strip_markdown(markdown),
+            "\
+Weather for Paris
+
+Updated hourly
+
+Temperature: 18°C
+Wind: 12 km/h, see the forecast
+
+Day, High
+Mon, 20
+
+let snake_case_name = 1;
+Keep snake_case_name intact."
+        );
+    }
+
+    #[tokio::test]
+    async fn test_strip_delta_markdown() {
+        let markdown = "# Weather\n\n- Temperature: **18".to_string()
+            + "°C**\r\n```\nlet x = 1;\n```\nSee [the forecast](https://example.com)";
+        let chunks = [6, 14, 30, 3, 9, 1];
+        let mut deltas = vec![StreamDelta::ReasoningDelta {
+            index: 0,
+            text: "Thinking".to_string(),
+            signature: None,
+        }];
+        let mut rest = markdown.as_str();
+        for len in chunks {
+            let (chunk, tail) = rest.split_at(len);
+            deltas.push(StreamDelta::TextDelta {
+                index: 1,
+                text: chunk.to_string(),
+            });
+            rest = tail;
+        }
+        deltas.push(StreamDelta::TextDelta {
+            index: 1,
+            text: rest.to_string(),
+        });
+        deltas.push(StreamDelta::PartFinished { index: 1 });
+
+        let stripped: Vec<StreamDelta> =
+            strip_delta_markdown(futures::stream::iter(deltas.into_iter().map(Ok)))
+                .try_collect()
+                .await
+                .unwrap();
+
+        // The part is opened by the first delta, and lines are released once complete.
+        assert_eq!(
+            stripped[..3],
+            [
+                StreamDelta::ReasoningDelta {
+                    index: 0,
+                    text: "Thinking".to_string(),
+                    signature: None,
+                },
+                StreamDelta::TextDelta {
+                    index: 1,
+                    text: String::new(),
+                },
+                StreamDelta::TextDelta {
+                    index: 1,
+                    text: "Weather\n\n".to_string(),
+                },
+            ]
+        );
+        assert_eq!(
+            stripped.last(),
+            Some(&StreamDelta::PartFinished { index: 1 })
+        );
+        let text: String = stripped
+            .iter()
+            .filter_map(|delta| match delta {
+                StreamDelta::TextDelta { text, .. } => Some(text.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(text, strip_markdown(&markdown));
+    }
+}
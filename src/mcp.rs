@@ -1,16 +1,35 @@
-use crate::model::{MediaType, Message, Part};
+use crate::client::{Client, ClientError};
+use crate::model::{FinishReason, MediaType, Message, Part, Response};
+use crate::options::RetryPolicy;
 use async_trait::async_trait;
+use futures::future::BoxFuture;
 use rmcp::model::{
-    AnnotateAble, Annotated, CallToolRequestParam, GetPromptRequestParam, GetPromptResult, Prompt,
-    PromptMessage, PromptMessageContent, PromptMessageRole, RawContent, ReadResourceRequestParam,
-    ReadResourceResult, Resource, ResourceContents, Tool,
+    AnnotateAble, Annotated, CallToolRequest, CallToolRequestParam, CallToolResult,
+    CancelledNotification, CancelledNotificationMethod, CancelledNotificationParam, ClientInfo,
+    ClientRequest, Content, CreateElicitationRequestParam, CreateElicitationResult,
+    CreateMessageRequestMethod, CreateMessageRequestParam, CreateMessageResult, ElicitationAction,
+    ElicitationSchema, GetPromptRequestParam, GetPromptResult, Implementation, ListRootsResult,
+    Meta, Prompt, PromptMessage, PromptMessageContent, PromptMessageRole, RawAudioContent,
+    RawContent, ReadResourceRequestParam, ReadResourceResult, Resource, ResourceContents, Role,
+    Root, SamplingMessage, ServerResult, Tool,
 };
-use rmcp::service::{RoleClient, RunningService};
-use rmcp::ClientHandler;
+use rmcp::service::{
+    NotificationContext, PeerRequestOptions, RequestContext, RequestHandle, RoleClient,
+    RunningService, ServiceError,
+};
+use rmcp::{ClientHandler, ErrorData, ServiceExt};
+use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
-use std::collections::HashMap;
+use std::any::Any;
+use std::collections::{BTreeMap, HashSet};
+use std::future::Future;
 use std::ops::Deref;
+use std::sync::Arc;
+use std::time::Duration;
 use thiserror::Error;
+use tokio::sync::{broadcast, mpsc, oneshot};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, warn};
 use uuid::Uuid;
 
 #[derive(Debug, Error)]
@@ -27,6 +46,64 @@ pub enum MCPError {
     ServerNotFound(String),
     #[error("Server ID mismatch")]
     ServerIdMismatch,
+    #[error("Tool call cancelled")]
+    Cancelled,
+    /// The connection to the server failed or was closed.
+    #[error("MCP transport error: {0}")]
+    Transport(String),
+    #[error("Invalid MCP configuration: {0}")]
+    Config(String),
+    #[error("MCP {operation} timed out after {timeout:?}")]
+    Timeout {
+        operation: &'static str,
+        timeout: Duration,
+    },
+}
+
+/// Timeouts for MCP server calls. `None` disables the timeout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MCPTimeouts {
+    /// Timeout for listing tools, prompts and resources.
+    pub list: Option<Duration>,
+    /// Timeout for a single tool call.
+    pub call_tool: Option<Duration>,
+    /// Timeout for getting a prompt or reading a resource.
+    pub read: Option<Duration>,
+}
+
+impl Default for MCPTimeouts {
+    fn default() -> Self {
+        Self {
+            list: Some(Duration::from_secs(30)),
+            call_tool: Some(Duration::from_secs(120)),
+            read: Some(Duration::from_secs(30)),
+        }
+    }
+}
+
+impl MCPTimeouts {
+    /// Timeouts that never expire.
+    pub fn none() -> Self {
+        Self {
+            list: None,
+            call_tool: None,
+            read: None,
+        }
+    }
+}
+
+/// Await an MCP call, failing with [`MCPError::Timeout`] if it takes too long.
+pub async fn with_timeout<T>(
+    operation: &'static str,
+    timeout: Option<Duration>,
+    future: impl Future<Output = Result<T, MCPError>>,
+) -> Result<T, MCPError> {
+    match timeout {
+        Some(timeout) => tokio::time::timeout(timeout, future)
+            .await
+            .unwrap_or(Err(MCPError::Timeout { operation, timeout })),
+        None => future.await,
+    }
 }
 
 /// A wrapper type that associates a value with an optional server ID.
@@ -62,6 +139,68 @@ impl Servable for Prompt {}
 impl Servable for GetPromptResult {}
 impl Servable for ReadResourceResult {}
 
+/// A list of an MCP server that changed, announced by its `list_changed`
+/// notification.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ListChanged {
+    Tools,
+    Prompts,
+    Resources,
+}
+
+/// Number of unread change notifications kept per subscriber.
+const LIST_CHANGED_CAPACITY: usize = 16;
+
+/// Subscription to the list changes of one or more MCP servers, from
+/// [`MCPServer::subscribe`].
+///
+/// A subscription that fell behind reports every kind of list as changed.
+#[derive(Debug, Default)]
+pub struct ListChanges {
+    receivers: Vec<broadcast::Receiver<ListChanged>>,
+}
+
+impl ListChanges {
+    pub fn new(receiver: broadcast::Receiver<ListChanged>) -> Self {
+        Self {
+            receivers: vec![receiver],
+        }
+    }
+
+    /// Combine the subscriptions of several servers.
+    pub fn merge(subscriptions: impl IntoIterator<Item = ListChanges>) -> Self {
+        Self {
+            receivers: subscriptions
+                .into_iter()
+                .flat_map(|s| s.receivers)
+                .collect(),
+        }
+    }
+
+    /// Lists reported as changed since the last call, without waiting.
+    pub fn pending(&mut self) -> HashSet<ListChanged> {
+        let mut changed = HashSet::new();
+        for receiver in &mut self.receivers {
+            loop {
+                match receiver.try_recv() {
+                    Ok(list) => {
+                        changed.insert(list);
+                    }
+                    Err(broadcast::error::TryRecvError::Lagged(_)) => {
+                        changed.extend([
+                            ListChanged::Tools,
+                            ListChanged::Prompts,
+                            ListChanged::Resources,
+                        ]);
+                    }
+                    Err(_) => break,
+                }
+            }
+        }
+        changed
+    }
+}
+
 /// Trait for MCP servers that can be used by the Agent.
 #[async_trait]
 pub trait MCPServer: Send + Sync {
@@ -76,6 +215,24 @@ pub trait MCPServer: Send + Sync {
         server_id: Option<String>,
     ) -> Result<Part, MCPError>;
 
+    /// Execute a tool, aborting the call when `cancel` is triggered.
+    ///
+    /// The default implementation stops awaiting the call. Servers that support
+    /// request cancellation should override it to notify the remote side as well.
+    async fn call_tool_cancellable(
+        &self,
+        name: String,
+        args: Value,
+        server_id: Option<String>,
+        cancel: CancellationToken,
+    ) -> Result<Part, MCPError> {
+        tokio::select! {
+            biased;
+            _ = cancel.cancelled() => Err(MCPError::Cancelled),
+            result = self.call_tool(name, args, server_id) => result,
+        }
+    }
+
     /// List available prompts.
     async fn list_prompts(&self) -> Result<Vec<Served<Prompt>>, MCPError>;
 
@@ -94,16 +251,18 @@ pub trait MCPServer: Send + Sync {
         &self,
         resource: &Served<Resource>,
     ) -> Result<Served<ReadResourceResult>, MCPError>;
+
+    /// Subscribe to the list changes the server announces. The default
+    /// subscription never reports a change.
+    fn subscribe(&self) -> ListChanges {
+        ListChanges::default()
+    }
 }
 
 #[async_trait]
 impl<S: ClientHandler + Send + Sync> MCPServer for RunningService<RoleClient, S> {
     async fn list_tools(&self) -> Result<Vec<Served<Tool>>, MCPError> {
-        let result = self
-            .deref()
-            .list_tools(None)
-            .await
-            .map_err(|e| MCPError::Mcp(e.to_string()))?;
+        let result = self.deref().list_tools(None).await.map_err(service_error)?;
         Ok(result.tools.into_iter().map(|t| t.served(None)).collect())
     }
 
@@ -122,53 +281,59 @@ impl<S: ClientHandler + Send + Sync> MCPServer for RunningService<RoleClient, S>
             .deref()
             .call_tool(params)
             .await
-            .map_err(|e| MCPError::Mcp(e.to_string()))?;
-
-        let mut structured = json!({});
-        let mut parts = Vec::new();
-        let mut parsed_text_content: Option<Value> = None;
-        let mut raw_text_content: Vec<String> = Vec::new();
-
-        for content in result.content {
-            match content.raw {
-                RawContent::Text(text_content) => {
-                    if let Ok(parsed) = serde_json::from_str::<Value>(&text_content.text) {
-                        parsed_text_content = Some(parsed);
-                    } else {
-                        raw_text_content.push(text_content.text);
+            .map_err(service_error)?;
+
+        Ok(tool_result_to_part(name, result))
+    }
+
+    async fn call_tool_cancellable(
+        &self,
+        name: String,
+        args: Value,
+        _server_id: Option<String>,
+        cancel: CancellationToken,
+    ) -> Result<Part, MCPError> {
+        let request = ClientRequest::CallToolRequest(CallToolRequest {
+            method: Default::default(),
+            params: CallToolRequestParam {
+                name: name.clone().into(),
+                arguments: args.as_object().cloned(),
+            },
+            extensions: Default::default(),
+        });
+
+        let handle = self
+            .deref()
+            .send_cancellable_request(
+                request,
+                PeerRequestOptions {
+                    timeout: None,
+                    meta: crate::trace::current_meta().map(Meta),
+                },
+            )
+            .await
+            .map_err(service_error)?;
+        let mut pending = PendingToolCall(Some(handle));
+        let rx = &mut pending.0.as_mut().expect("handle is set").rx;
+
+        let response = tokio::select! {
+            response = rx => response,
+            _ = cancel.cancelled() => {
+                if let Some(handle) = pending.0.take() {
+                    if let Err(e) = handle.cancel(Some("Cancelled by client".to_string())).await {
+                        debug!("Failed to send cancellation for tool {}: {}", name, e);
                     }
                 }
-                RawContent::Image(image_content) => {
-                    parts.push(Part::Media {
-                        media_type: MediaType::Image,
-                        data: image_content.data,
-                        mime_type: image_content.mime_type,
-                        uri: None,
-                        finished: true,
-                    });
-                }
-                RawContent::Resource(resource) => {
-                    parts.push(Part::from(resource.resource));
-                }
-                _ => {}
+                return Err(MCPError::Cancelled);
             }
-        }
+        };
+        pending.0 = None;
 
-        if let Some(s) = result.structured_content {
-            structured = s;
-        } else if let Some(parsed) = parsed_text_content {
-            structured = parsed;
-        } else if !raw_text_content.is_empty() {
-            structured = json!({ "response": raw_text_content });
+        match response.map_err(|_| MCPError::Transport("Transport closed".to_string()))? {
+            Ok(ServerResult::CallToolResult(result)) => Ok(tool_result_to_part(name, result)),
+            Ok(_) => Err(MCPError::Mcp("Unexpected response".to_string())),
+            Err(e) => Err(service_error(e)),
         }
-
-        Ok(Part::FunctionResponse {
-            id: None,
-            name,
-            response: structured,
-            parts,
-            finished: true,
-        })
     }
 
     async fn list_prompts(&self) -> Result<Vec<Served<Prompt>>, MCPError> {
@@ -176,7 +341,7 @@ impl<S: ClientHandler + Send + Sync> MCPServer for RunningService<RoleClient, S>
             .deref()
             .list_prompts(None)
             .await
-            .map_err(|e| MCPError::Mcp(e.to_string()))?;
+            .map_err(service_error)?;
         Ok(result.prompts.into_iter().map(|p| p.served(None)).collect())
     }
 
@@ -193,7 +358,7 @@ impl<S: ClientHandler + Send + Sync> MCPServer for RunningService<RoleClient, S>
             .get_prompt(params)
             .await
             .map(|r| r.served(None))
-            .map_err(|e| MCPError::Mcp(e.to_string()))
+            .map_err(service_error)
     }
 
     async fn list_resources(&self) -> Result<Vec<Served<Resource>>, MCPError> {
@@ -201,7 +366,7 @@ impl<S: ClientHandler + Send + Sync> MCPServer for RunningService<RoleClient, S>
             .deref()
             .list_resources(None)
             .await
-            .map_err(|e| MCPError::Mcp(e.to_string()))?;
+            .map_err(service_error)?;
         Ok(result
             .resources
             .into_iter()
@@ -220,13 +385,936 @@ impl<S: ClientHandler + Send + Sync> MCPServer for RunningService<RoleClient, S>
             .read_resource(params)
             .await
             .map(|r| r.served(None))
-            .map_err(|e| MCPError::Mcp(e.to_string()))
+            .map_err(service_error)
+    }
+
+    /// Only connections made with an [`MCPClientHandler`] receive the
+    /// notifications of the server.
+    fn subscribe(&self) -> ListChanges {
+        match (self.service() as &dyn Any).downcast_ref::<MCPClientHandler>() {
+            Some(handler) => handler.subscribe(),
+            None => ListChanges::default(),
+        }
+    }
+}
+
+/// Convert an error of an MCP request, telling transport failures apart.
+fn service_error(error: ServiceError) -> MCPError {
+    match error {
+        ServiceError::TransportSend(_) | ServiceError::TransportClosed => {
+            MCPError::Transport(error.to_string())
+        }
+        _ => MCPError::Mcp(error.to_string()),
+    }
+}
+
+/// A tool call request that has been sent but not answered yet.
+///
+/// Dropping it, for example because the agent run was aborted, tells the server to
+/// cancel the request instead of leaving it running.
+struct PendingToolCall(Option<RequestHandle<RoleClient>>);
+
+impl Drop for PendingToolCall {
+    fn drop(&mut self) {
+        if let Some(handle) = self.0.take() {
+            let notification = CancelledNotification {
+                params: CancelledNotificationParam {
+                    request_id: handle.id,
+                    reason: Some("Cancelled by client".to_string()),
+                },
+                method: CancelledNotificationMethod,
+                extensions: Default::default(),
+            };
+            let peer = handle.peer;
+            if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+                runtime.spawn(async move {
+                    let _ = peer.send_notification(notification.into()).await;
+                });
+            }
+        }
+    }
+}
+
+/// Convert the result of an MCP tool call into a function response part.
+fn tool_result_to_part(name: String, result: CallToolResult) -> Part {
+    let mut structured = json!({});
+    let mut parts = Vec::new();
+    let mut parsed_text_content: Option<Value> = None;
+    let mut raw_text_content: Vec<String> = Vec::new();
+
+    for content in result.content {
+        match content.raw {
+            RawContent::Text(text_content) => {
+                if let Ok(parsed) = serde_json::from_str::<Value>(&text_content.text) {
+                    parsed_text_content = Some(parsed);
+                } else {
+                    raw_text_content.push(text_content.text);
+                }
+            }
+            RawContent::Image(image_content) => {
+                parts.push(Part::Media {
+                    media_type: MediaType::Image,
+                    data: image_content.data,
+                    mime_type: image_content.mime_type,
+                    uri: None,
+                    finished: true,
+                });
+            }
+            RawContent::Resource(resource) => {
+                parts.push(Part::from(resource.resource));
+            }
+            _ => {}
+        }
+    }
+
+    if let Some(s) = result.structured_content {
+        structured = s;
+    } else if let Some(parsed) = parsed_text_content {
+        structured = parsed;
+    } else if !raw_text_content.is_empty() {
+        structured = json!({ "response": raw_text_content });
+    }
+
+    Part::FunctionResponse {
+        id: None,
+        name,
+        response: structured,
+        parts,
+        finished: true,
+    }
+}
+
+/// Completion function of a [`MCPClientHandler`].
+type Sampler =
+    Arc<dyn Fn(Vec<Message>) -> BoxFuture<'static, Result<Response, ClientError>> + Send + Sync>;
+
+/// MCP client handler answering the requests servers send to the client.
+///
+/// Connecting with an `MCPClientHandler` instead of `()` lets servers use the
+/// client capabilities it is configured with:
+///
+/// - sampling: `sampling/createMessage` requests, which servers use to ask for
+///   completions, for example to summarize a document inside a tool, are
+///   answered with a unia client, such as the one the agent already holds
+/// - roots: the filesystem roots the server may operate on
+/// - elicitation: `elicitation/create` requests, which ask the user for input
+///   during a tool call, are answered by an [`ElicitationHandler`]
+///
+/// ```ignore
+/// use rmcp::ServiceExt;
+/// use unia::mcp::MCPClientHandler;
+///
+/// let server = MCPClientHandler::new()
+///     .with_sampling(client.clone())
+///     .with_root("file:///home/user/project", Some("project"))
+///     .with_elicitation(|request: CreateElicitationRequestParam| async move {
+///         ask_user(&request.message).await
+///     })
+///     .serve(transport)
+///     .await?;
+/// let agent = Agent::new(client).with_server(server);
+/// ```
+///
+/// Sampling completions are made with the model options of the client. Model
+/// preferences, temperature, token limit and stop sequences of a request are
+/// advisory in MCP and are not forwarded. Messages have no system role, so the
+/// system prompt of a request is sent as text before the first message.
+///
+/// Elicitation requests arrive while the agent waits for the tool call, so a
+/// streaming application can answer them next to the agent stream with
+/// [`elicitation_channel`].
+#[derive(Clone)]
+pub struct MCPClientHandler {
+    info: ClientInfo,
+    sampler: Option<(Sampler, String)>,
+    roots: Vec<Root>,
+    elicitation: Option<Arc<dyn ElicitationHandler>>,
+    changes: broadcast::Sender<ListChanged>,
+}
+
+impl Default for MCPClientHandler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MCPClientHandler {
+    /// Create a handler without capabilities.
+    pub fn new() -> Self {
+        let info = ClientInfo {
+            client_info: Implementation {
+                name: "unia".to_string(),
+                version: env!("CARGO_PKG_VERSION").to_string(),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        Self {
+            info,
+            sampler: None,
+            roots: Vec::new(),
+            elicitation: None,
+            changes: broadcast::channel(LIST_CHANGED_CAPACITY).0,
+        }
+    }
+
+    /// Subscribe to the list changes announced by the server.
+    pub fn subscribe(&self) -> ListChanges {
+        ListChanges::new(self.changes.subscribe())
+    }
+
+    fn list_changed(&self, list: ListChanged) {
+        debug!("MCP server announced a change of its {:?} list", list);
+        // Fails only without subscribers.
+        let _ = self.changes.send(list);
+    }
+
+    /// Answer sampling requests with a client.
+    pub fn with_sampling<C: Client + 'static>(mut self, client: C) -> Self {
+        let model = client.model_options().model.clone();
+        let client = Arc::new(client);
+        let sampler: Sampler = Arc::new(move |messages| {
+            let client = client.clone();
+            Box::pin(async move { client.request(messages, vec![]).await })
+        });
+        self.sampler = Some((sampler, model));
+        self.info.capabilities.sampling = Some(Default::default());
+        self
+    }
+
+    /// Advertise a filesystem root, given as a `file://` URI.
+    pub fn with_root(mut self, uri: impl Into<String>, name: Option<&str>) -> Self {
+        self.roots.push(Root {
+            uri: uri.into(),
+            name: name.map(str::to_string),
+        });
+        self.info.capabilities.roots = Some(Default::default());
+        self
+    }
+
+    /// Answer elicitation requests with a handler.
+    pub fn with_elicitation<H: ElicitationHandler + 'static>(mut self, handler: H) -> Self {
+        self.elicitation = Some(Arc::new(handler));
+        self.info.capabilities.elicitation = Some(Default::default());
+        self
+    }
+
+    /// Set the name and version the client reports to servers.
+    pub fn with_client_info(mut self, implementation: Implementation) -> Self {
+        self.info.client_info = implementation;
+        self
+    }
+}
+
+impl ClientHandler for MCPClientHandler {
+    async fn create_message(
+        &self,
+        params: CreateMessageRequestParam,
+        _context: RequestContext<RoleClient>,
+    ) -> Result<CreateMessageResult, ErrorData> {
+        let Some((sampler, model)) = &self.sampler else {
+            return Err(ErrorData::method_not_found::<CreateMessageRequestMethod>());
+        };
+        let response = sampler(sampling_messages(params))
+            .await
+            .map_err(|e| ErrorData::internal_error(e.to_string(), None))?;
+        sampling_result(response, model)
+    }
+
+    async fn list_roots(
+        &self,
+        _context: RequestContext<RoleClient>,
+    ) -> Result<ListRootsResult, ErrorData> {
+        Ok(ListRootsResult {
+            roots: self.roots.clone(),
+        })
+    }
+
+    async fn create_elicitation(
+        &self,
+        request: CreateElicitationRequestParam,
+        _context: RequestContext<RoleClient>,
+    ) -> Result<CreateElicitationResult, ErrorData> {
+        Ok(match &self.elicitation {
+            Some(handler) => handler.elicit(request).await,
+            None => CreateElicitationResult {
+                action: ElicitationAction::Decline,
+                content: None,
+            },
+        })
+    }
+
+    async fn on_tool_list_changed(&self, _context: NotificationContext<RoleClient>) {
+        self.list_changed(ListChanged::Tools);
+    }
+
+    async fn on_prompt_list_changed(&self, _context: NotificationContext<RoleClient>) {
+        self.list_changed(ListChanged::Prompts);
+    }
+
+    async fn on_resource_list_changed(&self, _context: NotificationContext<RoleClient>) {
+        self.list_changed(ListChanged::Resources);
+    }
+
+    fn get_info(&self) -> ClientInfo {
+        self.info.clone()
+    }
+}
+
+/// Answers the `elicitation/create` requests of MCP servers, which ask the user
+/// for input during a tool call.
+///
+/// Implemented for closures taking the request and returning a future of the
+/// result.
+#[async_trait]
+pub trait ElicitationHandler: Send + Sync {
+    /// Ask the user for the requested input. Accepted content must match the
+    /// requested schema.
+    async fn elicit(&self, request: CreateElicitationRequestParam) -> CreateElicitationResult;
+}
+
+#[async_trait]
+impl<F, Fut> ElicitationHandler for F
+where
+    F: Fn(CreateElicitationRequestParam) -> Fut + Send + Sync,
+    Fut: Future<Output = CreateElicitationResult> + Send,
+{
+    async fn elicit(&self, request: CreateElicitationRequestParam) -> CreateElicitationResult {
+        self(request).await
+    }
+}
+
+/// An elicitation request waiting for the user, received from an
+/// [`elicitation_channel`]. Dropping it without an answer cancels the request.
+#[derive(Debug)]
+pub struct ElicitationRequest {
+    pub message: String,
+    pub requested_schema: ElicitationSchema,
+    reply: oneshot::Sender<CreateElicitationResult>,
+}
+
+impl ElicitationRequest {
+    /// Answer the request.
+    pub fn respond(self, result: CreateElicitationResult) {
+        let _ = self.reply.send(result);
+    }
+
+    /// Accept the request with the input of the user.
+    pub fn accept(self, content: Value) {
+        self.respond(CreateElicitationResult {
+            action: ElicitationAction::Accept,
+            content: Some(content),
+        });
+    }
+
+    /// Decline to provide the input, letting the tool call continue without it.
+    pub fn decline(self) {
+        self.respond(CreateElicitationResult {
+            action: ElicitationAction::Decline,
+            content: None,
+        });
+    }
+}
+
+/// [`ElicitationHandler`] forwarding requests to the receiver of an
+/// [`elicitation_channel`].
+#[derive(Debug, Clone)]
+pub struct ElicitationSender {
+    sender: mpsc::Sender<ElicitationRequest>,
+}
+
+#[async_trait]
+impl ElicitationHandler for ElicitationSender {
+    async fn elicit(&self, request: CreateElicitationRequestParam) -> CreateElicitationResult {
+        let (reply, answer) = oneshot::channel();
+        let request = ElicitationRequest {
+            message: request.message,
+            requested_schema: request.requested_schema,
+            reply,
+        };
+        if self.sender.send(request).await.is_err() {
+            debug!("Elicitation receiver dropped, cancelling request");
+        }
+        answer.await.unwrap_or(CreateElicitationResult {
+            action: ElicitationAction::Cancel,
+            content: None,
+        })
+    }
+}
+
+/// Channel of elicitation requests, for answering them where the user
+/// interface lives, for example next to the stream of an agent:
+///
+/// ```ignore
+/// let (sender, mut requests) = elicitation_channel(8);
+/// let server = MCPClientHandler::new().with_elicitation(sender).serve(transport).await?;
+/// let agent = Agent::new(client).with_server(server);
+///
+/// let mut stream = agent.chat_stream(messages);
+/// loop {
+///     tokio::select! {
+///         Some(request) = requests.recv() => request.accept(ask_user(&request.message).await),
+///         response = stream.next() => match response {
+///             Some(response) => render(response?),
+///             None => break,
+///         },
+///     }
+/// }
+/// ```
+///
+/// Requests are cancelled once the receiver is dropped.
+pub fn elicitation_channel(
+    buffer: usize,
+) -> (ElicitationSender, mpsc::Receiver<ElicitationRequest>) {
+    let (sender, receiver) = mpsc::channel(buffer);
+    (ElicitationSender { sender }, receiver)
+}
+
+/// Convert the messages of a sampling request, merging consecutive messages of
+/// the same role.
+fn sampling_messages(params: CreateMessageRequestParam) -> Vec<Message> {
+    let mut messages: Vec<Message> = Vec::new();
+    if let Some(system) = params.system_prompt.filter(|s| !s.is_empty()) {
+        messages.push(Message::User(vec![Part::Text {
+            content: system,
+            finished: true,
+        }]));
+    }
+    for message in params.messages {
+        let Some(part) = content_to_part(message.content.raw) else {
+            continue;
+        };
+        match (messages.last_mut(), message.role) {
+            (Some(Message::User(parts)), Role::User)
+            | (Some(Message::Assistant(parts)), Role::Assistant) => parts.push(part),
+            (_, Role::User) => messages.push(Message::User(vec![part])),
+            (_, Role::Assistant) => messages.push(Message::Assistant(vec![part])),
+        }
+    }
+    messages
+}
+
+/// Convert content sent by a server. Resource links have no equivalent and are
+/// dropped.
+fn content_to_part(content: RawContent) -> Option<Part> {
+    match content {
+        RawContent::Text(text) => Some(Part::Text {
+            content: text.text,
+            finished: true,
+        }),
+        RawContent::Image(image) => Some(Part::Media {
+            media_type: MediaType::Image,
+            data: image.data,
+            mime_type: image.mime_type,
+            uri: None,
+            finished: true,
+        }),
+        RawContent::Audio(audio) => Some(Part::Media {
+            media_type: MediaType::Audio,
+            data: audio.data,
+            mime_type: audio.mime_type,
+            uri: None,
+            finished: true,
+        }),
+        RawContent::Resource(resource) => Some(Part::from(resource.resource)),
+        RawContent::ResourceLink(_) => None,
+    }
+}
+
+/// Convert the response to a sampling request. A sampling result holds a single
+/// content block: the text of the reply, or its first image or audio part.
+fn sampling_result(response: Response, model: &str) -> Result<CreateMessageResult, ErrorData> {
+    let parts: Vec<Part> = response
+        .data
+        .into_iter()
+        .flat_map(|m| match m {
+            Message::Assistant(parts) => parts,
+            Message::User(_) => Vec::new(),
+        })
+        .collect();
+    let text: Vec<&str> = parts
+        .iter()
+        .filter_map(|part| match part {
+            Part::Text { content, .. } => Some(content.as_str()),
+            _ => None,
+        })
+        .collect();
+    let content = if !text.is_empty() {
+        Content::text(text.join("\n"))
+    } else {
+        parts
+            .into_iter()
+            .find_map(|part| match part {
+                Part::Media {
+                    media_type: MediaType::Image,
+                    data,
+                    mime_type,
+                    ..
+                } => Some(Content::image(data, mime_type)),
+                Part::Media {
+                    media_type: MediaType::Audio,
+                    data,
+                    mime_type,
+                    ..
+                } => Some(RawContent::Audio(RawAudioContent { data, mime_type }).no_annotation()),
+                _ => None,
+            })
+            .ok_or_else(|| ErrorData::internal_error("The model returned no content", None))?
+    };
+
+    let stop_reason = match response.finish {
+        FinishReason::Stop => Some(CreateMessageResult::STOP_REASON_END_TURN),
+        FinishReason::OutputTokens => Some(CreateMessageResult::STOP_REASON_END_MAX_TOKEN),
+        _ => None,
+    };
+    Ok(CreateMessageResult {
+        model: response.model.unwrap_or_else(|| model.to_string()),
+        stop_reason: stop_reason.map(str::to_string),
+        message: SamplingMessage {
+            role: Role::Assistant,
+            content,
+        },
+    })
+}
+
+/// MCP server wrapper that bounds every call with a timeout.
+///
+/// A hung server otherwise stalls the caller forever. Calls that time out are
+/// dropped, which for tool calls also tells the server to cancel the request.
+pub struct TimeoutMCPServer<S> {
+    inner: S,
+    timeouts: MCPTimeouts,
+}
+
+impl<S: MCPServer> TimeoutMCPServer<S> {
+    /// Wrap a server with the default timeouts.
+    pub fn new(inner: S) -> Self {
+        Self::with_timeouts(inner, MCPTimeouts::default())
+    }
+
+    /// Wrap a server with the given timeouts.
+    pub fn with_timeouts(inner: S, timeouts: MCPTimeouts) -> Self {
+        Self { inner, timeouts }
+    }
+}
+
+#[async_trait]
+impl<S: MCPServer> MCPServer for TimeoutMCPServer<S> {
+    async fn list_tools(&self) -> Result<Vec<Served<Tool>>, MCPError> {
+        with_timeout("list_tools", self.timeouts.list, self.inner.list_tools()).await
+    }
+
+    async fn call_tool(
+        &self,
+        name: String,
+        args: Value,
+        server_id: Option<String>,
+    ) -> Result<Part, MCPError> {
+        with_timeout(
+            "call_tool",
+            self.timeouts.call_tool,
+            self.inner.call_tool(name, args, server_id),
+        )
+        .await
+    }
+
+    async fn call_tool_cancellable(
+        &self,
+        name: String,
+        args: Value,
+        server_id: Option<String>,
+        cancel: CancellationToken,
+    ) -> Result<Part, MCPError> {
+        with_timeout(
+            "call_tool",
+            self.timeouts.call_tool,
+            self.inner
+                .call_tool_cancellable(name, args, server_id, cancel),
+        )
+        .await
+    }
+
+    async fn list_prompts(&self) -> Result<Vec<Served<Prompt>>, MCPError> {
+        with_timeout(
+            "list_prompts",
+            self.timeouts.list,
+            self.inner.list_prompts(),
+        )
+        .await
+    }
+
+    async fn get_prompt(
+        &self,
+        prompt: &Served<Prompt>,
+        args: Option<serde_json::Map<String, Value>>,
+    ) -> Result<Served<GetPromptResult>, MCPError> {
+        with_timeout(
+            "get_prompt",
+            self.timeouts.read,
+            self.inner.get_prompt(prompt, args),
+        )
+        .await
+    }
+
+    async fn list_resources(&self) -> Result<Vec<Served<Resource>>, MCPError> {
+        with_timeout(
+            "list_resources",
+            self.timeouts.list,
+            self.inner.list_resources(),
+        )
+        .await
+    }
+
+    async fn read_resource(
+        &self,
+        resource: &Served<Resource>,
+    ) -> Result<Served<ReadResourceResult>, MCPError> {
+        with_timeout(
+            "read_resource",
+            self.timeouts.read,
+            self.inner.read_resource(resource),
+        )
+        .await
+    }
+
+    fn subscribe(&self) -> ListChanges {
+        self.inner.subscribe()
+    }
+}
+
+/// A connection to an MCP server.
+type Connection = Arc<RunningService<RoleClient, MCPClientHandler>>;
+
+type Connect =
+    Box<dyn Fn(MCPClientHandler) -> BoxFuture<'static, Result<Connection, MCPError>> + Send + Sync>;
+
+/// MCP server wrapper that connects on first use and reconnects after the
+/// transport fails.
+///
+/// The server is connected with `connect`, which serves the given handler on a
+/// new stdio or HTTP transport. Connection attempts are retried with the
+/// backoff of a [`RetryPolicy`]. Every connection runs the initialization
+/// handshake again, and subscribers of [`subscribe`](MCPServer::subscribe) are
+/// told that all lists changed, as the server may have changed meanwhile.
+///
+/// Listing and reading are retried on a new connection if the transport fails
+/// during the request. Tool calls are not, as the server may already have run
+/// them; the next call reconnects.
+///
+/// ```ignore
+/// let server = ReconnectingMCPServer::new(MCPClientHandler::new(), |handler| async move {
+///     let transport = TokioChildProcess::new(Command::new("uvx").arg("mcp-server-git"))?;
+///     Ok::<_, Box<dyn std::error::Error + Send + Sync>>(handler.serve(transport).await?)
+/// });
+/// ```
+pub struct ReconnectingMCPServer {
+    handler: MCPClientHandler,
+    connect: Connect,
+    retry: RetryPolicy,
+    connection: tokio::sync::Mutex<Option<Connection>>,
+}
+
+impl ReconnectingMCPServer {
+    /// Wrap a connection function. No connection is made until the server is
+    /// first used.
+    pub fn new<F, Fut, E>(handler: MCPClientHandler, connect: F) -> Self
+    where
+        F: Fn(MCPClientHandler) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<RunningService<RoleClient, MCPClientHandler>, E>>
+            + Send
+            + 'static,
+        E: std::fmt::Display,
+    {
+        Self {
+            handler,
+            connect: Box::new(move |handler| {
+                let connection = connect(handler);
+                Box::pin(async move {
+                    connection
+                        .await
+                        .map(Arc::new)
+                        .map_err(|e| MCPError::Transport(e.to_string()))
+                })
+            }),
+            retry: RetryPolicy::default(),
+            connection: tokio::sync::Mutex::new(None),
+        }
+    }
+
+    /// Set the retry policy of connection attempts.
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// The open connection, connecting if there is none or it was closed.
+    async fn connection(&self) -> Result<Connection, MCPError> {
+        let mut current = self.connection.lock().await;
+        if let Some(connection) = current.as_ref() {
+            if !connection.is_transport_closed() {
+                return Ok(connection.clone());
+            }
+            warn!("MCP server connection closed, reconnecting");
+        }
+        let reconnect = current.take().is_some();
+
+        let mut attempt = 1;
+        let connection = loop {
+            match (self.connect)(self.handler.clone()).await {
+                Ok(connection) => break connection,
+                Err(e) if attempt < self.retry.max_attempts => {
+                    warn!(
+                        "Failed to connect to MCP server (attempt {}): {}",
+                        attempt, e
+                    );
+                    tokio::time::sleep(crate::http::jittered_backoff(&self.retry, attempt)).await;
+                    attempt += 1;
+                }
+                Err(e) => return Err(e),
+            }
+        };
+        if reconnect {
+            for list in [
+                ListChanged::Tools,
+                ListChanged::Prompts,
+                ListChanged::Resources,
+            ] {
+                self.handler.list_changed(list);
+            }
+        }
+        *current = Some(connection.clone());
+        Ok(connection)
+    }
+
+    /// Run a request, on a new connection if the transport failed and `retry`
+    /// is set.
+    async fn request<T, F, Fut>(&self, retry: bool, request: F) -> Result<T, MCPError>
+    where
+        F: Fn(Connection) -> Fut,
+        Fut: Future<Output = Result<T, MCPError>>,
+    {
+        let connection = self.connection().await?;
+        match request(connection.clone()).await {
+            Err(MCPError::Transport(e)) => {
+                warn!("MCP server transport failed: {}", e);
+                self.disconnect(&connection).await;
+                if !retry {
+                    return Err(MCPError::Transport(e));
+                }
+                request(self.connection().await?).await
+            }
+            result => result,
+        }
+    }
+
+    /// Drop a failed connection, unless another request already replaced it.
+    async fn disconnect(&self, failed: &Connection) {
+        let mut current = self.connection.lock().await;
+        if current
+            .as_ref()
+            .is_some_and(|connection| Arc::ptr_eq(connection, failed))
+        {
+            *current = None;
+        }
+    }
+}
+
+#[async_trait]
+impl MCPServer for ReconnectingMCPServer {
+    async fn list_tools(&self) -> Result<Vec<Served<Tool>>, MCPError> {
+        self.request(
+            true,
+            |connection| async move { connection.list_tools().await },
+        )
+        .await
+    }
+
+    async fn call_tool(
+        &self,
+        name: String,
+        args: Value,
+        _server_id: Option<String>,
+    ) -> Result<Part, MCPError> {
+        self.request(false, |connection| {
+            let (name, args) = (name.clone(), args.clone());
+            async move { MCPServer::call_tool(&*connection, name, args, None).await }
+        })
+        .await
+    }
+
+    async fn call_tool_cancellable(
+        &self,
+        name: String,
+        args: Value,
+        _server_id: Option<String>,
+        cancel: CancellationToken,
+    ) -> Result<Part, MCPError> {
+        self.request(false, |connection| {
+            let (name, args, cancel) = (name.clone(), args.clone(), cancel.clone());
+            async move {
+                connection
+                    .call_tool_cancellable(name, args, None, cancel)
+                    .await
+            }
+        })
+        .await
+    }
+
+    async fn list_prompts(&self) -> Result<Vec<Served<Prompt>>, MCPError> {
+        self.request(
+            true,
+            |connection| async move { connection.list_prompts().await },
+        )
+        .await
+    }
+
+    async fn get_prompt(
+        &self,
+        prompt: &Served<Prompt>,
+        args: Option<serde_json::Map<String, Value>>,
+    ) -> Result<Served<GetPromptResult>, MCPError> {
+        self.request(true, |connection| {
+            let args = args.clone();
+            async move { MCPServer::get_prompt(&*connection, prompt, args).await }
+        })
+        .await
+    }
+
+    async fn list_resources(&self) -> Result<Vec<Served<Resource>>, MCPError> {
+        self.request(true, |connection| async move {
+            connection.list_resources().await
+        })
+        .await
+    }
+
+    async fn read_resource(
+        &self,
+        resource: &Served<Resource>,
+    ) -> Result<Served<ReadResourceResult>, MCPError> {
+        self.request(true, |connection| async move {
+            MCPServer::read_resource(&*connection, resource).await
+        })
+        .await
+    }
+
+    fn subscribe(&self) -> ListChanges {
+        self.handler.subscribe()
+    }
+}
+
+/// Separator between the prefix and the name of a tool in a [`MultiMCPServer`],
+/// e.g. `github__create_issue`.
+pub const PREFIX_SEPARATOR: &str = "__";
+
+/// Which tools of a server a [`MultiMCPServer`] exposes, and under which names.
+///
+/// Patterns match the names the server itself uses, and may contain `*`
+/// wildcards, e.g. `create_*`.
+#[derive(Debug, Clone, Default)]
+pub struct ToolFilter {
+    /// Only expose tools matching one of these patterns. All tools if `None`.
+    pub allow: Option<Vec<String>>,
+    /// Hide tools matching one of these patterns, even if allowed.
+    pub deny: Vec<String>,
+    /// Prefix of the exposed tool names, separated by [`PREFIX_SEPARATOR`].
+    pub prefix: Option<String>,
+}
+
+impl ToolFilter {
+    /// Create a filter exposing all tools under their own names.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add an allowed pattern.
+    pub fn with_allow(mut self, pattern: impl Into<String>) -> Self {
+        self.allow.get_or_insert_with(Vec::new).push(pattern.into());
+        self
+    }
+
+    /// Add a denied pattern.
+    pub fn with_deny(mut self, pattern: impl Into<String>) -> Self {
+        self.deny.push(pattern.into());
+        self
+    }
+
+    /// Set the prefix of the exposed tool names.
+    pub fn with_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.prefix = Some(prefix.into());
+        self
+    }
+
+    /// Whether a tool, by the name its server uses, is exposed.
+    pub fn allows(&self, name: &str) -> bool {
+        let matches = |pattern: &String| wildcard_match(pattern, name);
+        self.allow
+            .as_ref()
+            .is_none_or(|allow| allow.iter().any(matches))
+            && !self.deny.iter().any(matches)
+    }
+
+    /// Exposed name of a tool.
+    fn expose(&self, name: &str) -> String {
+        match &self.prefix {
+            Some(prefix) => format!("{}{}{}", prefix, PREFIX_SEPARATOR, name),
+            None => name.to_string(),
+        }
+    }
+
+    /// Name the server uses for an exposed tool name, if the filter exposes it.
+    fn resolve<'a>(&self, name: &'a str) -> Option<&'a str> {
+        let name = match &self.prefix {
+            Some(prefix) => name
+                .strip_prefix(prefix.as_str())?
+                .strip_prefix(PREFIX_SEPARATOR)?,
+            None => name,
+        };
+        self.allows(name).then_some(name)
+    }
+}
+
+/// Whether a name matches a pattern in which `*` matches any text.
+fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = name.strip_prefix(first) else {
+        return false;
+    };
+    let mut parts: Vec<&str> = parts.collect();
+    let Some(last) = parts.pop() else {
+        return rest.is_empty();
+    };
+    for part in parts {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
     }
+    rest.len() >= last.len() && rest.ends_with(last)
+}
+
+/// A server of a [`MultiMCPServer`].
+struct Member {
+    id: String,
+    server: Box<dyn MCPServer>,
+    filter: ToolFilter,
 }
 
 /// A helper to combine multiple MCP servers into one.
+///
+/// Tools of all servers are listed in the order the servers were added. With
+/// many servers, [`ToolFilter`]s keep name collisions and irrelevant tools out
+/// of the prompt:
+///
+/// ```ignore
+/// let server = MultiMCPServer::new()
+///     .add_filtered_server(github, ToolFilter::new().with_prefix("github").with_deny("delete_*"))
+///     .add_filtered_server(jira, ToolFilter::new().with_prefix("jira"))
+///     .with_max_tools(64);
+/// ```
 pub struct MultiMCPServer {
-    servers: HashMap<String, Box<dyn MCPServer>>,
+    servers: Vec<Member>,
+    max_tools: Option<usize>,
 }
 
 impl Default for MultiMCPServer {
@@ -238,42 +1326,135 @@ impl Default for MultiMCPServer {
 impl MultiMCPServer {
     pub fn new() -> Self {
         Self {
-            servers: HashMap::new(),
+            servers: Vec::new(),
+            max_tools: None,
         }
     }
 
     pub fn from_servers(servers: Vec<Box<dyn MCPServer>>) -> Self {
-        let mut map = HashMap::new();
-        for server in servers {
-            let id = Uuid::new_v4().to_string();
-            map.insert(id, server);
-        }
-        Self { servers: map }
+        servers
+            .into_iter()
+            .fold(Self::new(), |multi, server| multi.add_boxed_server(server))
+    }
+
+    pub fn add_server<S: MCPServer + 'static>(self, server: S) -> Self {
+        self.add_boxed_server(Box::new(server))
+    }
+
+    pub fn add_boxed_server(self, server: Box<dyn MCPServer>) -> Self {
+        self.add_boxed_filtered_server(server, ToolFilter::new())
+    }
+
+    /// Add a server, exposing the tools its filter allows.
+    pub fn add_filtered_server<S: MCPServer + 'static>(
+        self,
+        server: S,
+        filter: ToolFilter,
+    ) -> Self {
+        self.add_boxed_filtered_server(Box::new(server), filter)
     }
 
-    pub fn add_server<S: MCPServer + 'static>(mut self, server: S) -> Self {
-        let id = Uuid::new_v4().to_string();
-        self.servers.insert(id, Box::new(server));
+    pub fn add_boxed_filtered_server(
+        mut self,
+        server: Box<dyn MCPServer>,
+        filter: ToolFilter,
+    ) -> Self {
+        self.servers.push(Member {
+            id: Uuid::new_v4().to_string(),
+            server,
+            filter,
+        });
         self
     }
 
-    pub fn add_boxed_server(mut self, server: Box<dyn MCPServer>) -> Self {
-        let id = Uuid::new_v4().to_string();
-        self.servers.insert(id, server);
+    /// List at most `max` tools. Tools of servers added later are dropped first,
+    /// and calls to dropped tools are rejected with [`MCPError::ToolNotFound`].
+    pub fn with_max_tools(mut self, max: usize) -> Self {
+        self.max_tools = Some(max);
         self
     }
+
+    /// Connect all servers of an `mcpServers` configuration file, see
+    /// [`MCPConfig`].
+    pub async fn from_config(path: impl AsRef<std::path::Path>) -> Result<Self, MCPError> {
+        MCPConfig::load(path)?
+            .connect(MCPClientHandler::new())
+            .await
+    }
+
+    fn server(&self, id: &str) -> Result<&Member, MCPError> {
+        self.servers
+            .iter()
+            .find(|member| member.id == id)
+            .ok_or_else(|| MCPError::ServerNotFound(id.to_string()))
+    }
+
+    /// Find the server providing a tool, by server ID if known, and the name
+    /// the server uses for it.
+    async fn server_for_tool(
+        &self,
+        name: &str,
+        server_id: Option<String>,
+    ) -> Result<(&dyn MCPServer, String), MCPError> {
+        if self.max_tools.is_some() {
+            // Tools over the limit are not offered, so they cannot be called either.
+            let listed = self.list_tools().await?;
+            if !listed
+                .iter()
+                .any(|t| t.value.name == name && (server_id.is_none() || t.server_id == server_id))
+            {
+                return Err(MCPError::ToolNotFound(name.to_string()));
+            }
+        }
+
+        if let Some(id) = server_id {
+            let member = self.server(&id)?;
+            let name = member
+                .filter
+                .resolve(name)
+                .ok_or_else(|| MCPError::ToolNotFound(name.to_string()))?;
+            return Ok((member.server.as_ref(), name.to_string()));
+        }
+
+        for member in &self.servers {
+            let Some(name) = member.filter.resolve(name) else {
+                continue;
+            };
+            let tools: Vec<Served<Tool>> = member.server.list_tools().await?;
+            if tools.iter().any(|t| t.value.name == name) {
+                return Ok((member.server.as_ref(), name.to_string()));
+            }
+        }
+        Err(MCPError::ToolNotFound(name.to_string()))
+    }
 }
 
 #[async_trait]
 impl MCPServer for MultiMCPServer {
     async fn list_tools(&self) -> Result<Vec<Served<Tool>>, MCPError> {
         let mut all_tools = Vec::new();
-        for (id, server) in &self.servers {
-            let tools: Vec<Served<Tool>> = server.list_tools().await?;
-            all_tools.extend(tools.into_iter().map(|mut t| {
-                t.server_id = Some(id.clone());
-                t
-            }));
+        for member in &self.servers {
+            let tools: Vec<Served<Tool>> = member.server.list_tools().await?;
+            all_tools.extend(
+                tools
+                    .into_iter()
+                    .filter(|t| member.filter.allows(&t.value.name))
+                    .map(|mut t| {
+                        t.value.name = member.filter.expose(&t.value.name).into();
+                        t.server_id = Some(member.id.clone());
+                        t
+                    }),
+            );
+        }
+        if let Some(max) = self.max_tools {
+            if all_tools.len() > max {
+                warn!(
+                    "Listing {} of {} MCP tools, the rest exceed the tool limit",
+                    max,
+                    all_tools.len()
+                );
+                all_tools.truncate(max);
+            }
         }
         Ok(all_tools)
     }
@@ -284,28 +1465,33 @@ impl MCPServer for MultiMCPServer {
         args: Value,
         server_id: Option<String>,
     ) -> Result<Part, MCPError> {
-        if let Some(id) = server_id {
-            if let Some(server) = self.servers.get(&id) {
-                return server.call_tool(name, args, None).await;
-            }
-            return Err(MCPError::ServerNotFound(id));
-        }
+        let (server, tool) = self.server_for_tool(&name, server_id).await?;
+        let mut part = server.call_tool(tool, args, None).await?;
+        expose_response_name(&mut part, name);
+        Ok(part)
+    }
 
-        for server in self.servers.values() {
-            let tools: Vec<Served<Tool>> = server.list_tools().await?;
-            if tools.iter().any(|t| t.value.name == name) {
-                return server.call_tool(name, args, None).await;
-            }
-        }
-        Err(MCPError::ToolNotFound(name))
+    async fn call_tool_cancellable(
+        &self,
+        name: String,
+        args: Value,
+        server_id: Option<String>,
+        cancel: CancellationToken,
+    ) -> Result<Part, MCPError> {
+        let (server, tool) = self.server_for_tool(&name, server_id).await?;
+        let mut part = server
+            .call_tool_cancellable(tool, args, None, cancel)
+            .await?;
+        expose_response_name(&mut part, name);
+        Ok(part)
     }
 
     async fn list_prompts(&self) -> Result<Vec<Served<Prompt>>, MCPError> {
         let mut all_prompts = Vec::new();
-        for (id, server) in &self.servers {
-            let prompts: Vec<Served<Prompt>> = server.list_prompts().await?;
+        for member in &self.servers {
+            let prompts: Vec<Served<Prompt>> = member.server.list_prompts().await?;
             all_prompts.extend(prompts.into_iter().map(|mut p| {
-                p.server_id = Some(id.clone());
+                p.server_id = Some(member.id.clone());
                 p
             }));
         }
@@ -317,21 +1503,18 @@ impl MCPServer for MultiMCPServer {
         prompt: &Served<Prompt>,
         args: Option<serde_json::Map<String, Value>>,
     ) -> Result<Served<GetPromptResult>, MCPError> {
-        if let Some(id) = &prompt.server_id {
-            if let Some(server) = self.servers.get(id) {
-                return server.get_prompt(prompt, args).await;
-            }
-            return Err(MCPError::ServerNotFound(id.clone()));
+        match &prompt.server_id {
+            Some(id) => self.server(id)?.server.get_prompt(prompt, args).await,
+            None => Err(MCPError::ServerIdMismatch),
         }
-        Err(MCPError::ServerIdMismatch)
     }
 
     async fn list_resources(&self) -> Result<Vec<Served<Resource>>, MCPError> {
         let mut all_resources = Vec::new();
-        for (id, server) in &self.servers {
-            let resources: Vec<Served<Resource>> = server.list_resources().await?;
+        for member in &self.servers {
+            let resources: Vec<Served<Resource>> = member.server.list_resources().await?;
             all_resources.extend(resources.into_iter().map(|mut r| {
-                r.server_id = Some(id.clone());
+                r.server_id = Some(member.id.clone());
                 r
             }));
         }
@@ -342,13 +1525,177 @@ impl MCPServer for MultiMCPServer {
         &self,
         resource: &Served<Resource>,
     ) -> Result<Served<ReadResourceResult>, MCPError> {
-        if let Some(id) = &resource.server_id {
-            if let Some(server) = self.servers.get(id) {
-                return server.read_resource(resource).await;
+        match &resource.server_id {
+            Some(id) => self.server(id)?.server.read_resource(resource).await,
+            None => Err(MCPError::ServerIdMismatch),
+        }
+    }
+
+    fn subscribe(&self) -> ListChanges {
+        ListChanges::merge(self.servers.iter().map(|member| member.server.subscribe()))
+    }
+}
+
+/// MCP servers in the `mcpServers` configuration format shared by Claude
+/// Desktop, Cursor and other MCP clients:
+///
+/// ```json
+/// {
+///   "mcpServers": {
+///     "git": { "command": "uvx", "args": ["mcp-server-git"], "env": { "GIT_DIR": "." } },
+///     "github": { "url": "https://api.githubcopilot.com/mcp/", "headers": { "Authorization": "Bearer ..." } },
+///     "legacy": { "url": "http://localhost:8000/sse", "type": "sse" }
+///   }
+/// }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MCPConfig {
+    /// Servers by name.
+    #[serde(rename = "mcpServers", default)]
+    pub servers: BTreeMap<String, MCPServerConfig>,
+}
+
+/// How to connect to an MCP server.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum MCPServerConfig {
+    /// A local server started as a child process and spoken to over stdio.
+    Stdio {
+        command: String,
+        #[serde(default)]
+        args: Vec<String>,
+        #[serde(default)]
+        env: BTreeMap<String, String>,
+    },
+    /// A remote server, over streamable HTTP unless `type` is `sse`.
+    Http {
+        url: String,
+        #[serde(default)]
+        headers: BTreeMap<String, String>,
+        #[serde(rename = "type", alias = "transport", default)]
+        transport: Option<String>,
+    },
+}
+
+impl MCPConfig {
+    /// Read a configuration file.
+    pub fn load(path: impl AsRef<std::path::Path>) -> Result<Self, MCPError> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| MCPError::Config(format!("{}: {}", path.display(), e)))?;
+        Self::parse(&text)
+    }
+
+    /// Parse a configuration.
+    pub fn parse(text: &str) -> Result<Self, MCPError> {
+        serde_json::from_str(text).map_err(|e| MCPError::Config(e.to_string()))
+    }
+
+    /// Connect all servers, serving a clone of `handler` on each connection.
+    /// Fails if any server cannot be connected.
+    pub async fn connect(&self, handler: MCPClientHandler) -> Result<MultiMCPServer, MCPError> {
+        let connections = self.servers.iter().map(|(name, server)| {
+            let handler = handler.clone();
+            async move {
+                server
+                    .connect(handler)
+                    .await
+                    .map_err(|e| MCPError::Transport(format!("{}: {}", name, e)))
+            }
+        });
+        let connections = futures::future::try_join_all(connections).await?;
+        Ok(connections
+            .into_iter()
+            .fold(MultiMCPServer::new(), MultiMCPServer::add_server))
+    }
+}
+
+impl MCPServerConfig {
+    /// Connect to the server.
+    ///
+    /// Pass it to a [`ReconnectingMCPServer`] to connect lazily and reconnect:
+    ///
+    /// ```ignore
+    /// let server = ReconnectingMCPServer::new(MCPClientHandler::new(), move |handler| {
+    ///     let config = config.clone();
+    ///     async move { config.connect(handler).await }
+    /// });
+    /// ```
+    pub async fn connect(
+        &self,
+        handler: MCPClientHandler,
+    ) -> Result<RunningService<RoleClient, MCPClientHandler>, MCPError> {
+        use rmcp::transport::sse_client::SseClientConfig;
+        use rmcp::transport::streamable_http_client::StreamableHttpClientTransportConfig;
+        use rmcp::transport::{SseClientTransport, StreamableHttpClientTransport};
+
+        let transport_error = |e: &dyn std::fmt::Display| MCPError::Transport(e.to_string());
+        match self {
+            MCPServerConfig::Stdio { command, args, env } => {
+                let mut child = tokio::process::Command::new(command)
+                    .args(args)
+                    .envs(env)
+                    .stdin(std::process::Stdio::piped())
+                    .stdout(std::process::Stdio::piped())
+                    .spawn()
+                    .map_err(|e| MCPError::Transport(format!("{}: {}", command, e)))?;
+                let (Some(stdout), Some(stdin)) = (child.stdout.take(), child.stdin.take()) else {
+                    return Err(MCPError::Transport("stdio not captured".to_string()));
+                };
+                // The server exits when the connection closes its stdin.
+                handler
+                    .serve((stdout, stdin))
+                    .await
+                    .map_err(|e| transport_error(&e))
+            }
+            MCPServerConfig::Http {
+                url,
+                headers,
+                transport,
+            } => {
+                let mut header_map = reqwest::header::HeaderMap::new();
+                for (name, value) in headers {
+                    let name = reqwest::header::HeaderName::from_bytes(name.as_bytes())
+                        .map_err(|e| MCPError::Config(format!("header {}: {}", name, e)))?;
+                    let value = reqwest::header::HeaderValue::from_str(value)
+                        .map_err(|e| MCPError::Config(format!("header {}: {}", name, e)))?;
+                    header_map.insert(name, value);
+                }
+                let client = reqwest::Client::builder()
+                    .default_headers(header_map)
+                    .build()
+                    .map_err(|e| transport_error(&e))?;
+
+                if transport.as_deref() == Some("sse") {
+                    let config = SseClientConfig {
+                        sse_endpoint: url.as_str().into(),
+                        ..Default::default()
+                    };
+                    let transport = SseClientTransport::start_with_client(client, config)
+                        .await
+                        .map_err(|e| transport_error(&e))?;
+                    handler
+                        .serve(transport)
+                        .await
+                        .map_err(|e| transport_error(&e))
+                } else {
+                    let config = StreamableHttpClientTransportConfig::with_uri(url.as_str());
+                    let transport = StreamableHttpClientTransport::with_client(client, config);
+                    handler
+                        .serve(transport)
+                        .await
+                        .map_err(|e| transport_error(&e))
+                }
             }
-            return Err(MCPError::ServerNotFound(id.clone()));
         }
-        Err(MCPError::ServerIdMismatch)
+    }
+}
+
+/// Name a tool response after the exposed name of the tool, which the model
+/// called it by.
+fn expose_response_name(part: &mut Part, exposed: String) {
+    if let Part::FunctionResponse { name, .. } = part {
+        *name = exposed;
     }
 }
 
@@ -480,3 +1827,261 @@ impl From<Served<ReadResourceResult>> for Vec<Part> {
         served.value.contents.into_iter().map(Part::from).collect()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockClient;
+    use rmcp::model::{ClientResult, CreateElicitationRequest, ServerRequest};
+    use rmcp::{ServerHandler, ServiceExt};
+
+    struct Server;
+
+    impl ServerHandler for Server {}
+
+    async fn connect(
+        handler: MCPClientHandler,
+    ) -> (
+        RunningService<RoleClient, MCPClientHandler>,
+        RunningService<rmcp::RoleServer, Server>,
+    ) {
+        let (client_transport, server_transport) = tokio::io::duplex(4096);
+        let server = tokio::spawn(Server.serve(server_transport));
+        let client = handler.serve(client_transport).await.unwrap();
+        (client, server.await.unwrap().unwrap())
+    }
+
+    #[test]
+    fn test_tool_filter() {
+        assert!(wildcard_match("create_*", "create_issue"));
+        assert!(wildcard_match("*_issue*", "create_issue_comment"));
+        assert!(wildcard_match("a*a", "aa"));
+        assert!(!wildcard_match("a*a", "a"));
+        assert!(!wildcard_match("create", "create_issue"));
+
+        let filter = ToolFilter::new()
+            .with_allow("create_*")
+            .with_allow("search")
+            .with_deny("create_repo")
+            .with_prefix("github");
+        assert!(filter.allows("search") && filter.allows("create_issue"));
+        assert!(!filter.allows("create_repo") && !filter.allows("delete_repo"));
+        assert_eq!(filter.expose("search"), "github__search");
+        assert_eq!(filter.resolve("github__search"), Some("search"));
+        assert_eq!(filter.resolve("github__create_repo"), None);
+        assert_eq!(filter.resolve("search"), None);
+        assert_eq!(ToolFilter::new().resolve("search"), Some("search"));
+    }
+
+    #[tokio::test]
+    async fn test_sampling() {
+        let client = MockClient::new().with_text("Paris.");
+        let (_client, server) =
+            connect(MCPClientHandler::new().with_sampling(client.clone())).await;
+        let info = server.peer().peer_info().unwrap();
+        assert!(info.capabilities.sampling.is_some());
+        assert!(info.capabilities.roots.is_none());
+        assert_eq!(info.client_info.name, "unia");
+
+        let message = |role, text: &str| SamplingMessage {
+            role,
+            content: Content::text(text),
+        };
+        let result = server
+            .peer()
+            .create_message(CreateMessageRequestParam {
+                messages: vec![
+                    message(Role::User, "What is the capital of France?"),
+                    message(Role::User, "Answer in one word."),
+                ],
+                model_preferences: None,
+                system_prompt: Some("You are a geography teacher.".to_string()),
+                include_context: None,
+                temperature: None,
+                max_tokens: 100,
+                stop_sequences: None,
+                metadata: None,
+            })
+            .await
+            .unwrap();
+        assert_eq!(result.message.role, Role::Assistant);
+        assert_eq!(result.message.content.as_text().unwrap().text, "Paris.");
+        assert_eq!(result.model, "mock");
+        assert_eq!(
+            result.stop_reason.as_deref(),
+            Some(CreateMessageResult::STOP_REASON_END_TURN)
+        );
+
+        let requests = client.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].messages.len(), 1);
+        assert_eq!(
+            requests[0].messages[0].content().as_deref(),
+            Some(
+                "You are a geography teacher.\nWhat is the capital of France?\nAnswer in one word."
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn test_roots_and_elicitation() {
+        let (sender, mut requests) = elicitation_channel(1);
+        let handler = MCPClientHandler::new()
+            .with_root("file:///work", Some("work"))
+            .with_elicitation(sender);
+        let (_client, server) = connect(handler).await;
+        let info = server.peer().peer_info().unwrap();
+        assert!(info.capabilities.elicitation.is_some());
+
+        let roots = server.peer().list_roots().await.unwrap().roots;
+        assert_eq!(roots.len(), 1);
+        assert_eq!(roots[0].uri, "file:///work");
+
+        let elicit = |message: &str| {
+            let request = ServerRequest::CreateElicitationRequest(CreateElicitationRequest {
+                method: Default::default(),
+                params: CreateElicitationRequestParam {
+                    message: message.to_string(),
+                    requested_schema: ElicitationSchema::builder()
+                        .required_string("name")
+                        .build()
+                        .unwrap(),
+                },
+                extensions: Default::default(),
+            });
+            let peer = server.peer().clone();
+            async move {
+                match peer.send_request(request).await.unwrap() {
+                    ClientResult::CreateElicitationResult(result) => result,
+                    result => panic!("unexpected result {:?}", result),
+                }
+            }
+        };
+        let answer = tokio::spawn(elicit("What is your name?"));
+        let request = requests.recv().await.unwrap();
+        assert_eq!(request.message, "What is your name?");
+        request.accept(json!({ "name": "Ada" }));
+        let result = answer.await.unwrap();
+        assert_eq!(result.action, ElicitationAction::Accept);
+        assert_eq!(result.content, Some(json!({ "name": "Ada" })));
+
+        drop(requests);
+        assert_eq!(
+            elicit("Still there?").await.action,
+            ElicitationAction::Cancel
+        );
+    }
+
+    #[tokio::test]
+    async fn test_reconnecting_server() {
+        let servers = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let connected = servers.clone();
+        let server = ReconnectingMCPServer::new(MCPClientHandler::new(), move |handler| {
+            let servers = connected.clone();
+            async move {
+                let (client, server) = tokio::io::duplex(4096);
+                let server = tokio::spawn(Server.serve(server));
+                let client = handler.serve(client).await?;
+                let server = server.await.unwrap().unwrap();
+                servers.lock().unwrap().push(server);
+                Ok::<_, rmcp::service::ClientInitializeError>(client)
+            }
+        });
+        let mut changes = server.subscribe();
+        assert!(servers.lock().unwrap().is_empty());
+
+        server.list_tools().await.unwrap();
+        server.list_prompts().await.unwrap();
+        assert_eq!(servers.lock().unwrap().len(), 1);
+        assert!(changes.pending().is_empty());
+
+        let dropped = servers.lock().unwrap().pop().unwrap();
+        dropped.cancel().await.unwrap();
+        server.list_tools().await.unwrap();
+        assert_eq!(servers.lock().unwrap().len(), 1);
+        assert_eq!(changes.pending().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_mcp_config() {
+        let config = MCPConfig::parse(
+            r#"{
+                "mcpServers": {
+                    "git": { "command": "uvx", "args": ["mcp-server-git"], "env": { "A": "1" } },
+                    "github": { "url": "https://example.com/mcp", "headers": { "X-Key": "k" } },
+                    "legacy": { "url": "http://localhost:8000/sse", "type": "sse" }
+                }
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.servers["git"],
+            MCPServerConfig::Stdio {
+                command: "uvx".to_string(),
+                args: vec!["mcp-server-git".to_string()],
+                env: BTreeMap::from([("A".to_string(), "1".to_string())]),
+            }
+        );
+        assert!(matches!(
+            &config.servers["github"],
+            MCPServerConfig::Http { headers, transport: None, .. } if headers["X-Key"] == "k"
+        ));
+        assert!(matches!(
+            &config.servers["legacy"],
+            MCPServerConfig::Http { transport: Some(t), .. } if t == "sse"
+        ));
+
+        assert!(matches!(
+            MCPConfig::parse(r#"{ "mcpServers": { "bad": { "args": [] } } }"#),
+            Err(MCPError::Config(_))
+        ));
+        let missing = MCPConfig::parse(
+            r#"{ "mcpServers": { "missing": { "command": "/nonexistent/mcp-server" } } }"#,
+        )
+        .unwrap();
+        let error = missing
+            .connect(MCPClientHandler::new())
+            .await
+            .err()
+            .unwrap();
+        assert!(
+            matches!(&error, MCPError::Transport(e) if e.starts_with("missing: ")),
+            "{}",
+            error
+        );
+    }
+
+    #[tokio::test]
+    async fn test_list_changed() {
+        let (client, server) = connect(MCPClientHandler::new()).await;
+        let mut changes = MCPServer::subscribe(&client);
+        assert!(changes.pending().is_empty());
+
+        server.peer().notify_tool_list_changed().await.unwrap();
+        server.peer().notify_prompt_list_changed().await.unwrap();
+        let mut changed = HashSet::new();
+        for _ in 0..100 {
+            changed.extend(changes.pending());
+            if changed.len() == 2 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(
+            changed,
+            HashSet::from([ListChanged::Tools, ListChanged::Prompts])
+        );
+        assert!(changes.pending().is_empty());
+
+        let multi = MultiMCPServer::new().add_server(client);
+        let mut changes = multi.subscribe();
+        server.peer().notify_resource_list_changed().await.unwrap();
+        for _ in 0..100 {
+            if changes.pending().contains(&ListChanged::Resources) {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("resource list change was not reported");
+    }
+}
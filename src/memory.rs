@@ -0,0 +1,349 @@
+//! Conversation history and persistence.
+//!
+//! A [`Conversation`] keeps the message history of a session so it does not have
+//! to be rebuilt for every [`Agent::chat_in`](crate::agent::Agent::chat_in) call.
+//! Conversations can be persisted through a [`MemoryStore`]; [`InMemoryStore`] and
+//! [`FileStore`] are provided, and custom backends only need to implement the trait.
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use crate::client::ClientError;
+use crate::history::{validate_history, HistoryError};
+use crate::model::{Message, Part};
+use crate::tokenize::estimate_tokens;
+
+/// Message history of a single session.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Conversation {
+    id: String,
+    messages: Vec<Message>,
+}
+
+impl Default for Conversation {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Conversation {
+    /// Create an empty conversation with a random id.
+    pub fn new() -> Self {
+        Self::with_id(uuid::Uuid::new_v4().to_string())
+    }
+
+    /// Create an empty conversation with the given id.
+    pub fn with_id(id: impl Into<String>) -> Self {
+        Self {
+            id: id.into(),
+            messages: Vec::new(),
+        }
+    }
+
+    /// Get the conversation id.
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Get the message history.
+    pub fn messages(&self) -> &[Message] {
+        &self.messages
+    }
+
+    /// Append a message.
+    pub fn push(&mut self, message: Message) {
+        self.messages.push(message);
+    }
+
+    /// Append several messages.
+    pub fn extend(&mut self, messages: impl IntoIterator<Item = Message>) {
+        self.messages.extend(messages);
+    }
+
+    /// Remove the last message.
+    pub fn pop(&mut self) -> Option<Message> {
+        self.messages.pop()
+    }
+
+    /// Remove all messages.
+    pub fn clear(&mut self) {
+        self.messages.clear();
+    }
+
+    /// Number of messages.
+    pub fn len(&self) -> usize {
+        self.messages.len()
+    }
+
+    /// Check whether the conversation has no messages.
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+    }
+
+    /// Edit the message history with the [`history`](crate::history) helpers.
+    ///
+    /// The edited history is checked with
+    /// [`validate_history`](crate::history::validate_history). If the edit or
+    /// the check fails, the history is left unchanged.
+    pub fn edit<T>(
+        &mut self,
+        edit: impl FnOnce(&mut Vec<Message>) -> Result<T, HistoryError>,
+    ) -> Result<T, HistoryError> {
+        let mut messages = self.messages.clone();
+        let value = edit(&mut messages)?;
+        validate_history(&messages)?;
+        self.messages = messages;
+        Ok(value)
+    }
+
+    /// Estimated token count of the history.
+    pub fn estimated_tokens(&self) -> usize {
+        estimate_tokens(&self.messages, &[])
+    }
+
+    /// Drop the oldest messages until the estimated token count fits the budget.
+    ///
+    /// The history is cut at a user turn so that tool results are never separated
+    /// from their calls. The latest turn is always kept, even if it exceeds the
+    /// budget on its own. Returns the number of removed messages.
+    pub fn trim_to_token_budget(&mut self, budget: usize) -> usize {
+        let turn_starts: Vec<usize> = self
+            .messages
+            .iter()
+            .enumerate()
+            .filter(|(_, m)| is_turn_start(m))
+            .map(|(i, _)| i)
+            .collect();
+
+        let cut = turn_starts
+            .iter()
+            .copied()
+            .find(|&start| estimate_tokens(&self.messages[start..], &[]) <= budget)
+            .or(turn_starts.last().copied())
+            .unwrap_or(0);
+
+        self.messages.drain(..cut);
+        cut
+    }
+}
+
+/// A user message that is not a tool or computer result starts a new turn.
+fn is_turn_start(message: &Message) -> bool {
+    matches!(message, Message::User(parts)
+    if !parts.iter().any(|p| {
+        matches!(p, Part::FunctionResponse { .. } | Part::ComputerResult { .. })
+    }))
+}
+
+/// Storage backend for conversations.
+#[async_trait]
+pub trait MemoryStore: Send + Sync {
+    /// Load a conversation by id, returning `None` if it does not exist.
+    async fn load(&self, id: &str) -> Result<Option<Conversation>, ClientError>;
+
+    /// Save a conversation, replacing any previous version.
+    async fn save(&self, conversation: &Conversation) -> Result<(), ClientError>;
+
+    /// Delete a conversation. Deleting a missing conversation is not an error.
+    async fn delete(&self, id: &str) -> Result<(), ClientError>;
+
+    /// Load a conversation, or create an empty one with the given id.
+    async fn load_or_create(&self, id: &str) -> Result<Conversation, ClientError> {
+        Ok(self
+            .load(id)
+            .await?
+            .unwrap_or_else(|| Conversation::with_id(id)))
+    }
+}
+
+/// Conversation store kept in process memory.
+#[derive(Debug, Default)]
+pub struct InMemoryStore {
+    conversations: Mutex<HashMap<String, Conversation>>,
+}
+
+impl InMemoryStore {
+    /// Create an empty store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl MemoryStore for InMemoryStore {
+    async fn load(&self, id: &str) -> Result<Option<Conversation>, ClientError> {
+        Ok(self.conversations.lock().unwrap().get(id).cloned())
+    }
+
+    async fn save(&self, conversation: &Conversation) -> Result<(), ClientError> {
+        self.conversations
+            .lock()
+            .unwrap()
+            .insert(conversation.id.clone(), conversation.clone());
+        Ok(())
+    }
+
+    async fn delete(&self, id: &str) -> Result<(), ClientError> {
+        self.conversations.lock().unwrap().remove(id);
+        Ok(())
+    }
+}
+
+/// Conversation store writing one JSON file per conversation into a directory.
+#[derive(Debug, Clone)]
+pub struct FileStore {
+    dir: PathBuf,
+}
+
+impl FileStore {
+    /// Create a store in the given directory. The directory is created on first save.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    fn path(&self, id: &str) -> Result<PathBuf, ClientError> {
+        if id.is_empty() || id.contains(['/', '\\']) || id.starts_with('.') {
+            return Err(ClientError::Config(format!(
+                "Invalid conversation id: {}",
+                id
+            )));
+        }
+        Ok(self.dir.join(format!("{}.json", id)))
+    }
+}
+
+fn io_error(action: &str, path: &std::path::Path, e: std::io::Error) -> ClientError {
+    ClientError::Config(format!("Failed to {} {}: {}", action, path.display(), e))
+}
+
+#[async_trait]
+impl MemoryStore for FileStore {
+    async fn load(&self, id: &str) -> Result<Option<Conversation>, ClientError> {
+        let path = self.path(id)?;
+        match tokio::fs::read(&path).await {
+            Ok(bytes) => Ok(Some(serde_json::from_slice(&bytes)?)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(io_error("read", &path, e)),
+        }
+    }
+
+    async fn save(&self, conversation: &Conversation) -> Result<(), ClientError> {
+        let path = self.path(&conversation.id)?;
+        tokio::fs::create_dir_all(&self.dir)
+            .await
+            .map_err(|e| io_error("create", &self.dir, e))?;
+        let json = serde_json::to_vec_pretty(conversation)?;
+        tokio::fs::write(&path, json)
+            .await
+            .map_err(|e| io_error("write", &path, e))
+    }
+
+    async fn delete(&self, id: &str) -> Result<(), ClientError> {
+        let path = self.path(id)?;
+        match tokio::fs::remove_file(&path).await {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(io_error("delete", &path, e)),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn text(content: &str) -> Vec<Part> {
+        vec![Part::Text {
+            content: content.to_string(),
+            finished: true,
+        }]
+    }
+
+    #[test]
+    fn test_trim_keeps_tool_pairs() {
+        let mut conversation = Conversation::new();
+        conversation.push(Message::User(text(&"a".repeat(400))));
+        conversation.push(Message::Assistant(vec![Part::FunctionCall {
+            id: Some("1".to_string()),
+            name: "lookup".to_string(),
+            arguments: json!({}),
+            signature: None,
+            raw_arguments: None,
+            finished: true,
+        }]));
+        conversation.push(Message::User(vec![Part::FunctionResponse {
+            id: Some("1".to_string()),
+            name: "lookup".to_string(),
+            response: json!({ "result": "x".repeat(400) }),
+            parts: vec![],
+            finished: true,
+        }]));
+        conversation.push(Message::Assistant(text("done")));
+        conversation.push(Message::User(text("next question")));
+
+        let removed = conversation.trim_to_token_budget(50);
+
+        assert_eq!(removed, 4);
+        assert_eq!(conversation.len(), 1);
+        assert!(is_turn_start(&conversation.messages()[0]));
+    }
+
+    #[test]
+    fn test_edit_rolls_back_invalid_history() {
+        let mut conversation = Conversation::new();
+        conversation.push(Message::User(text("Look it up")));
+        conversation.push(Message::Assistant(vec![Part::FunctionCall {
+            id: Some("1".to_string()),
+            name: "lookup".to_string(),
+            arguments: json!({}),
+            signature: None,
+            raw_arguments: None,
+            finished: true,
+        }]));
+        conversation.push(Message::User(vec![Part::FunctionResponse {
+            id: Some("1".to_string()),
+            name: "lookup".to_string(),
+            response: json!({ "result": "x".repeat(400) }),
+            parts: vec![],
+            finished: true,
+        }]));
+
+        let result = conversation.edit(|messages| {
+            messages.remove(1);
+            Ok(())
+        });
+        assert!(matches!(result, Err(HistoryError::OrphanResult { .. })));
+        assert_eq!(conversation.len(), 3);
+
+        conversation
+            .edit(|messages| crate::history::replace_tool_result(messages, "1", json!("x")))
+            .unwrap();
+        assert!(matches!(
+            &conversation.messages()[2].parts()[0],
+            Part::FunctionResponse { response, .. } if *response == json!("x")
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_file_store_roundtrip() {
+        let dir = std::env::temp_dir().join(format!("unia-memory-{}", uuid::Uuid::new_v4()));
+        let store = FileStore::new(&dir);
+
+        let mut conversation = store.load_or_create("session").await.unwrap();
+        assert!(conversation.is_empty());
+        conversation.push(Message::User(text("hello")));
+        store.save(&conversation).await.unwrap();
+
+        let loaded = store.load("session").await.unwrap().unwrap();
+        assert_eq!(loaded.messages()[0].content().as_deref(), Some("hello"));
+
+        store.delete("session").await.unwrap();
+        assert!(store.load("session").await.unwrap().is_none());
+        assert!(store.load("../escape").await.is_err());
+
+        std::fs::remove_dir_all(dir).ok();
+    }
+}
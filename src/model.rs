@@ -1,9 +1,18 @@
 //! Common data models for provider-agnostic LLM requests and responses.
 
+use base64::prelude::*;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use serde_with::skip_serializing_none;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use tracing::debug;
+
+use crate::client::ClientError;
+use crate::computer::{ComputerAction, ComputerSafetyCheck};
+use crate::hosted::{Citation, HostedToolCall};
+use crate::options::ReasoningVisibility;
 
 /// Role of the message sender.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
@@ -12,7 +21,7 @@ pub enum Role {
     Assistant,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum MediaType {
     /// Image content (e.g., PNG, JPEG)
@@ -21,12 +30,145 @@ pub enum MediaType {
     Document,
     /// Plain text content
     Text,
+    /// Audio content (e.g., WAV, MP3)
+    Audio,
     /// Binary or other content
     Binary,
 }
 
+impl MediaType {
+    /// Media type of content with a mime type.
+    pub fn from_mime_type(mime_type: &str) -> Self {
+        let essence = mime_type.split(';').next().unwrap_or_default().trim();
+        match essence {
+            "application/pdf" => MediaType::Document,
+            "application/json" | "application/xml" | "application/x-yaml" => MediaType::Text,
+            _ if essence.starts_with("image/") => MediaType::Image,
+            _ if essence.starts_with("text/") => MediaType::Text,
+            _ if essence.starts_with("audio/") => MediaType::Audio,
+            _ => MediaType::Binary,
+        }
+    }
+}
+
+/// Mime type of a file from its extension, `application/octet-stream` if it
+/// is unknown.
+pub(crate) fn mime_type_for_path(path: &Path) -> &'static str {
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    match extension.as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "heic" => "image/heic",
+        "pdf" => "application/pdf",
+        "txt" | "log" => "text/plain",
+        "md" | "markdown" => "text/markdown",
+        "csv" => "text/csv",
+        "html" | "htm" => "text/html",
+        "xml" => "application/xml",
+        "json" => "application/json",
+        "yaml" | "yml" => "application/x-yaml",
+        "mp3" => "audio/mpeg",
+        "wav" => "audio/wav",
+        "ogg" => "audio/ogg",
+        "flac" => "audio/flac",
+        "m4a" => "audio/mp4",
+        "mp4" => "video/mp4",
+        "webm" => "video/webm",
+        "mov" => "video/quicktime",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Mime type of content from its leading magic bytes.
+///
+/// Recognizes common image, document, audio and video formats. Text and
+/// unknown formats return `None`.
+///
+/// # Example
+/// ```
+/// use unia::model::sniff_mime_type;
+///
+/// assert_eq!(sniff_mime_type(b"\x89PNG\r\n\x1a\n..."), Some("image/png"));
+/// assert_eq!(sniff_mime_type(b"%PDF-1.7"), Some("application/pdf"));
+/// assert_eq!(sniff_mime_type(b"plain text"), None);
+/// ```
+pub fn sniff_mime_type(data: &[u8]) -> Option<&'static str> {
+    let at = |offset: usize, magic: &[u8]| data.get(offset..offset + magic.len()) == Some(magic);
+    Some(match data {
+        _ if at(0, b"\x89PNG\r\n\x1a\n") => "image/png",
+        _ if at(0, b"\xff\xd8\xff") => "image/jpeg",
+        _ if at(0, b"GIF87a") || at(0, b"GIF89a") => "image/gif",
+        _ if at(0, b"RIFF") && at(8, b"WEBP") => "image/webp",
+        _ if at(0, b"BM") && data.len() > 14 => "image/bmp",
+        _ if at(0, b"%PDF-") => "application/pdf",
+        _ if at(0, b"RIFF") && at(8, b"WAVE") => "audio/wav",
+        _ if at(0, b"ID3") || at(0, b"\xff\xfb") || at(0, b"\xff\xf3") || at(0, b"\xff\xf2") => {
+            "audio/mpeg"
+        }
+        _ if at(0, b"OggS") => "audio/ogg",
+        _ if at(0, b"fLaC") => "audio/flac",
+        _ if at(0, b"\x1a\x45\xdf\xa3") => "video/webm",
+        _ if at(4, b"ftyp") => match data.get(8..12) {
+            Some(b"heic" | b"heix" | b"mif1") => "image/heic",
+            Some(b"M4A ") => "audio/mp4",
+            Some(b"qt  ") => "video/quicktime",
+            _ => "video/mp4",
+        },
+        _ => return None,
+    })
+}
+
+/// Read a file to attach, returning its content, mime type and file name.
+///
+/// The mime type is sniffed from the content, or guessed from the extension
+/// for text and unknown formats.
+pub(crate) fn read_attachment(path: &Path) -> Result<(Vec<u8>, String, String), ClientError> {
+    let data = std::fs::read(path)
+        .map_err(|e| ClientError::Config(format!("Failed to read {}: {}", path.display(), e)))?;
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let mime_type = sniff_mime_type(&data).unwrap_or_else(|| mime_type_for_path(path));
+    Ok((data, mime_type.to_string(), name))
+}
+
+/// Correct the mime and media types of inline media parts from their content,
+/// including those nested in tool results.
+pub(crate) fn correct_media_types(mut messages: Vec<Message>) -> Vec<Message> {
+    fn correct(parts: &mut [Part]) {
+        for part in parts {
+            match part {
+                Part::FunctionResponse { parts, .. } => correct(parts),
+                part => part.correct_media_type(),
+            }
+        }
+    }
+    for message in &mut messages {
+        correct(message.parts_mut());
+    }
+    messages
+}
+
+/// An inline media part with base64 data, named by its `uri`.
+pub(crate) fn inline_media(data: &[u8], mime_type: String, name: String) -> Part {
+    Part::Media {
+        media_type: MediaType::from_mime_type(&mime_type),
+        data: BASE64_STANDARD.encode(data),
+        mime_type,
+        uri: Some(name),
+        finished: true,
+    }
+}
+
 /// A part of a message content.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
 #[serde(tag = "type", content = "data")]
 pub enum Part {
     /// Text content
@@ -49,6 +191,10 @@ pub enum Part {
         name: String,
         arguments: Value,
         signature: Option<String>,
+        /// Original argument text, kept when the model emitted malformed JSON.
+        /// `arguments` then holds the repaired value, or `null` if repair failed.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        raw_arguments: Option<String>,
         #[serde(default)]
         finished: bool,
     },
@@ -70,9 +216,111 @@ pub enum Part {
         #[serde(default)]
         finished: bool,
     },
+    /// UI action requested through the provider's computer-use tool
+    ComputerCall {
+        id: Option<String>,
+        action: ComputerAction,
+        /// Checks to confirm before performing the action.
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        safety_checks: Vec<ComputerSafetyCheck>,
+        signature: Option<String>,
+        #[serde(default)]
+        finished: bool,
+    },
+    /// Screenshot taken after performing a computer action
+    ComputerResult {
+        /// Id of the answered [`Part::ComputerCall`].
+        id: Option<String>,
+        /// Base64-encoded image.
+        screenshot: String,
+        mime_type: String,
+        #[serde(default)]
+        finished: bool,
+    },
+    /// Call of a tool run by the provider, with its results
+    HostedToolCall {
+        id: Option<String>,
+        call: HostedToolCall,
+        #[serde(default)]
+        finished: bool,
+    },
+    /// Source backing the preceding text
+    Citation {
+        citation: Citation,
+        #[serde(default)]
+        finished: bool,
+    },
 }
 
 impl Part {
+    /// Inline media part with the content of a file.
+    ///
+    /// The mime type is guessed from the file extension and the file name is
+    /// kept as the part's `uri`. Large files are better uploaded with
+    /// [`FileUpload::attach_path`](crate::files::FileUpload::attach_path).
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Part, ClientError> {
+        let (data, mime_type, name) = read_attachment(path.as_ref())?;
+        Ok(inline_media(&data, mime_type, name))
+    }
+
+    /// Inline media part with a mime type sniffed from the content, or
+    /// `application/octet-stream` for unknown formats.
+    pub fn from_bytes(data: impl AsRef<[u8]>) -> Part {
+        let data = data.as_ref();
+        let mime_type = sniff_mime_type(data).unwrap_or("application/octet-stream");
+        Part::Media {
+            media_type: MediaType::from_mime_type(mime_type),
+            data: BASE64_STANDARD.encode(data),
+            mime_type: mime_type.to_string(),
+            uri: None,
+            finished: true,
+        }
+    }
+
+    /// Set the mime and media types of an inline media part from its content
+    /// if they do not match it, e.g. a PNG image labeled `image/jpeg`.
+    ///
+    /// Declared types of text, unknown formats and uploaded files are kept, as
+    /// is the declared top-level type of containers such as `audio/mp4` that
+    /// the content does not tell apart from `video/mp4`. Providers correct the
+    /// types of every request this way.
+    pub fn correct_media_type(&mut self) {
+        let Part::Media {
+            media_type,
+            data,
+            mime_type,
+            ..
+        } = self
+        else {
+            return;
+        };
+        if *media_type == MediaType::Text || data.is_empty() {
+            return;
+        }
+        // 64 base64 characters decode to the first 48 bytes.
+        let head = &data.as_bytes()[..data.len().min(64)];
+        let Some(sniffed) = BASE64_STANDARD
+            .decode(&head[..head.len() / 4 * 4])
+            .ok()
+            .and_then(|bytes| sniff_mime_type(&bytes))
+        else {
+            return;
+        };
+
+        let subtype = |mime: &str| {
+            let essence = mime.split(';').next().unwrap_or_default().trim();
+            essence
+                .split_once('/')
+                .map(|(_, subtype)| subtype.to_ascii_lowercase())
+        };
+        if subtype(mime_type) == subtype(sniffed) {
+            return;
+        }
+        debug!("Correcting media labeled {} to {}", mime_type, sniffed);
+        *mime_type = sniffed.to_string();
+        *media_type = MediaType::from_mime_type(sniffed);
+    }
+
     pub fn anchor_media(&self) -> String {
         match self {
             Part::Media { mime_type, uri, .. } => {
@@ -85,7 +333,7 @@ impl Part {
 }
 
 /// A single message in a conversation.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
 #[serde(tag = "role", content = "content")]
 pub enum Message {
     #[serde(rename = "user")]
@@ -95,6 +343,22 @@ pub enum Message {
 }
 
 impl Message {
+    /// User message with text followed by files attached with
+    /// [`Part::from_path`].
+    pub fn user_with_files<P: AsRef<Path>>(
+        text: impl Into<String>,
+        paths: impl IntoIterator<Item = P>,
+    ) -> Result<Message, ClientError> {
+        let mut parts = vec![Part::Text {
+            content: text.into(),
+            finished: true,
+        }];
+        for path in paths {
+            parts.push(Part::from_path(path)?);
+        }
+        Ok(Message::User(parts))
+    }
+
     /// Get the role of the message.
     pub fn role(&self) -> Role {
         match self {
@@ -126,7 +390,7 @@ impl Message {
             .iter()
             .filter_map(|p| match p {
                 Part::Text { content: text, .. } => Some(text.as_str()),
-                Part::Reasoning { content, .. } => Some(content.as_str()),
+                Part::Reasoning { content, .. } if !content.is_empty() => Some(content.as_str()),
                 _ => None,
             })
             .collect();
@@ -167,7 +431,7 @@ pub struct GeneralRequest {
 }
 
 /// Reason for finishing the response generation.
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
 pub enum FinishReason {
     Stop,
     PromptTokens,
@@ -175,6 +439,8 @@ pub enum FinishReason {
     ToolCalls,
     ContentFilter,
     Error,
+    /// An agent stopped at its iteration limit with tool calls still pending.
+    MaxIterations,
     /// Default state when response is incomplete or streaming.
     /// If this is returned to the user, something went wrong.
     Unfinished,
@@ -182,41 +448,117 @@ pub enum FinishReason {
 
 /// Token usage information.
 #[skip_serializing_none]
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq, JsonSchema)]
 pub struct Usage {
     /// Total prompt tokens used
     pub prompt_tokens: Option<u32>,
 
     /// Total completion tokens used
     pub completion_tokens: Option<u32>,
+
+    /// Prompt tokens read from the provider's prompt cache (included in `prompt_tokens`)
+    pub cached_tokens: Option<u32>,
+
+    /// Prompt tokens written to the provider's prompt cache (included in `prompt_tokens`)
+    pub cache_creation_tokens: Option<u32>,
+
+    /// Completion tokens spent on reasoning (included in `completion_tokens`)
+    pub reasoning_tokens: Option<u32>,
+}
+
+/// Sum of token counts, saturating at `u32::MAX`. Missing on both sides stays missing.
+fn add_tokens(a: Option<u32>, b: Option<u32>) -> Option<u32> {
+    a.map(|v| v.saturating_add(b.unwrap_or(0))).or(b)
+}
+
+impl Usage {
+    /// Prompt plus completion tokens, if either is known.
+    pub fn total_tokens(&self) -> Option<u32> {
+        add_tokens(self.prompt_tokens, self.completion_tokens)
+    }
 }
 
 impl std::ops::Add for Usage {
     type Output = Self;
 
     fn add(self, other: Self) -> Self {
+        self + &other
+    }
+}
+
+impl std::ops::Add<&Usage> for Usage {
+    type Output = Self;
+
+    fn add(self, other: &Usage) -> Self {
         Self {
-            prompt_tokens: self
-                .prompt_tokens
-                .map(|v| v + other.prompt_tokens.unwrap_or(0))
-                .or(other.prompt_tokens),
-            completion_tokens: self
-                .completion_tokens
-                .map(|v| v + other.completion_tokens.unwrap_or(0))
-                .or(other.completion_tokens),
+            prompt_tokens: add_tokens(self.prompt_tokens, other.prompt_tokens),
+            completion_tokens: add_tokens(self.completion_tokens, other.completion_tokens),
+            cached_tokens: add_tokens(self.cached_tokens, other.cached_tokens),
+            cache_creation_tokens: add_tokens(
+                self.cache_creation_tokens,
+                other.cache_creation_tokens,
+            ),
+            reasoning_tokens: add_tokens(self.reasoning_tokens, other.reasoning_tokens),
         }
     }
 }
 
 impl std::ops::AddAssign for Usage {
     fn add_assign(&mut self, other: Self) {
-        *self = self.clone() + other;
+        *self += &other;
+    }
+}
+
+impl std::ops::AddAssign<&Usage> for Usage {
+    fn add_assign(&mut self, other: &Usage) {
+        *self = std::mem::take(self) + other;
+    }
+}
+
+impl std::iter::Sum for Usage {
+    fn sum<I: Iterator<Item = Usage>>(iter: I) -> Self {
+        iter.fold(Usage::default(), |total, usage| total + usage)
+    }
+}
+
+impl<'a> std::iter::Sum<&'a Usage> for Usage {
+    fn sum<I: Iterator<Item = &'a Usage>>(iter: I) -> Self {
+        iter.fold(Usage::default(), |total, usage| total + usage)
+    }
+}
+
+/// Total usage per model.
+pub fn usage_by_model<M: Into<String>>(
+    usages: impl IntoIterator<Item = (M, Usage)>,
+) -> BTreeMap<String, Usage> {
+    let mut totals: BTreeMap<String, Usage> = BTreeMap::new();
+    for (model, usage) in usages {
+        *totals.entry(model.into()).or_default() += usage;
+    }
+    totals
+}
+
+/// Usage of one model request of an agent run.
+#[skip_serializing_none]
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq, JsonSchema)]
+pub struct IterationUsage {
+    /// Model that served the request, as reported by the provider.
+    pub model: Option<String>,
+    pub usage: Usage,
+}
+
+impl From<&Response> for IterationUsage {
+    fn from(response: &Response) -> Self {
+        Self {
+            model: response.model.clone(),
+            usage: response.usage.clone(),
+        }
     }
 }
 
 /// Provider-agnostic response structure.
 #[skip_serializing_none]
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Response {
     /// Generated messages (typically one assistant message, but can be multiple)
     pub data: Vec<Message>,
@@ -226,12 +568,117 @@ pub struct Response {
 
     /// Finish reason for the response generation
     pub finish: FinishReason,
+
+    /// Model that generated the response as reported by the provider, such as the
+    /// dated snapshot behind an alias or the upstream model chosen by a router.
+    pub model: Option<String>,
+
+    /// Messages of the other candidates when several were requested with
+    /// [`ModelOptions::n`](crate::options::ModelOptions::n). `data` holds the first.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub alternatives: Vec<Vec<Message>>,
+
+    /// Usage of each model request of an agent run, in order; `usage` is their
+    /// sum. Empty for single requests.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub iterations: Vec<IterationUsage>,
+}
+
+impl Response {
+    /// Messages of every candidate, starting with `data`.
+    pub fn candidates(&self) -> impl Iterator<Item = &[Message]> {
+        std::iter::once(self.data.as_slice()).chain(self.alternatives.iter().map(Vec::as_slice))
+    }
+
+    /// Remove the reasoning a visibility does not show from every candidate.
+    pub fn set_reasoning_visibility(&mut self, visibility: ReasoningVisibility) {
+        if visibility == ReasoningVisibility::Full {
+            return;
+        }
+        let messages = self
+            .data
+            .iter_mut()
+            .chain(self.alternatives.iter_mut().flatten());
+        for part in messages.flat_map(Message::parts_mut) {
+            if let Part::Reasoning {
+                content, summary, ..
+            } = part
+            {
+                content.clear();
+                if visibility == ReasoningVisibility::Hidden {
+                    *summary = None;
+                }
+            }
+        }
+    }
+
+    /// Usage per model that served the response. Requests whose model was not
+    /// reported are attributed to `default_model`.
+    pub fn usage_by_model(&self, default_model: &str) -> BTreeMap<String, Usage> {
+        breakdown(
+            &self.iterations,
+            self.model.as_deref(),
+            &self.usage,
+            default_model,
+        )
+    }
+}
+
+/// Usage per model of a response or agent run, which is a single request if
+/// there are no iterations.
+pub(crate) fn breakdown(
+    iterations: &[IterationUsage],
+    model: Option<&str>,
+    usage: &Usage,
+    default_model: &str,
+) -> BTreeMap<String, Usage> {
+    if iterations.is_empty() {
+        return usage_by_model([(model.unwrap_or(default_model), usage.clone())]);
+    }
+    usage_by_model(iterations.iter().map(|iteration| {
+        (
+            iteration.model.as_deref().unwrap_or(default_model),
+            iteration.usage.clone(),
+        )
+    }))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_usage_arithmetic() {
+        let usage = |prompt, cached| Usage {
+            prompt_tokens: Some(prompt),
+            cached_tokens: cached,
+            ..Default::default()
+        };
+        let total = usage(10, None) + &usage(u32::MAX, Some(5));
+        assert_eq!(total.prompt_tokens, Some(u32::MAX));
+        assert_eq!(total.cached_tokens, Some(5));
+        assert_eq!(total.completion_tokens, None);
+        assert_eq!(total.total_tokens(), Some(u32::MAX));
+
+        let usages = [usage(1, None), usage(2, Some(1)), usage(3, None)];
+        assert_eq!(usages.iter().sum::<Usage>(), usage(6, Some(1)));
+        assert_eq!(usages.into_iter().sum::<Usage>(), usage(6, Some(1)));
+        assert_eq!(std::iter::empty::<Usage>().sum::<Usage>(), Usage::default());
+
+        let by_model = usage_by_model([
+            ("a", usage(1, None)),
+            ("b", usage(2, None)),
+            ("a", usage(3, None)),
+        ]);
+        assert_eq!(
+            by_model,
+            BTreeMap::from([
+                ("a".to_string(), usage(4, None)),
+                ("b".to_string(), usage(2, None))
+            ])
+        );
+    }
+
     #[test]
     fn test_anchor_media() {
         let part = Part::Media {
@@ -260,4 +707,121 @@ mod tests {
 
         assert_eq!(part.anchor_media(), "File (image/png) at unknown:");
     }
+
+    #[test]
+    fn test_user_with_files() {
+        let dir = std::env::temp_dir().join(format!("unia-files-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("notes.MD"), "# Notes").unwrap();
+        std::fs::write(dir.join("report.pdf"), b"%PDF-1.7").unwrap();
+
+        let message = Message::user_with_files(
+            "Compare these.",
+            [dir.join("notes.MD"), dir.join("report.pdf")],
+        )
+        .unwrap();
+        assert_eq!(
+            message.parts()[1],
+            Part::Media {
+                media_type: MediaType::Text,
+                data: BASE64_STANDARD.encode("# Notes"),
+                mime_type: "text/markdown".to_string(),
+                uri: Some("notes.MD".to_string()),
+                finished: true,
+            }
+        );
+        assert!(matches!(
+            &message.parts()[2],
+            Part::Media { media_type: MediaType::Document, mime_type, .. } if mime_type == "application/pdf"
+        ));
+
+        assert!(matches!(
+            Part::from_path(dir.join("missing.png")),
+            Err(ClientError::Config(_))
+        ));
+
+        // The content wins over the extension.
+        std::fs::write(dir.join("photo.jpg"), b"\x89PNG\r\n\x1a\n").unwrap();
+        assert!(matches!(
+            Part::from_path(dir.join("photo.jpg")).unwrap(),
+            Part::Media { media_type: MediaType::Image, mime_type, .. } if mime_type == "image/png"
+        ));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_correct_media_type() {
+        let media = |data: &[u8], mime_type: &str, media_type: MediaType| Part::Media {
+            media_type,
+            data: BASE64_STANDARD.encode(data),
+            mime_type: mime_type.to_string(),
+            uri: None,
+            finished: true,
+        };
+        let png = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+
+        assert_eq!(
+            Part::from_bytes(png),
+            media(png, "image/png", MediaType::Image)
+        );
+        assert_eq!(
+            Part::from_bytes(b"\0\x01"),
+            media(b"\0\x01", "application/octet-stream", MediaType::Binary)
+        );
+
+        let messages = correct_media_types(vec![Message::User(vec![
+            media(png, "image/jpeg", MediaType::Image),
+            media(b"%PDF-1.4", "application/octet-stream", MediaType::Binary),
+            media(b"\0\0\0\x20ftypisom", "audio/mp4", MediaType::Binary),
+            media(b"# Title", "text/markdown", MediaType::Document),
+        ])]);
+        assert_eq!(
+            messages[0].parts(),
+            &vec![
+                media(png, "image/png", MediaType::Image),
+                media(b"%PDF-1.4", "application/pdf", MediaType::Document),
+                media(b"\0\0\0\x20ftypisom", "audio/mp4", MediaType::Binary),
+                media(b"# Title", "text/markdown", MediaType::Document),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_reasoning_visibility() {
+        let reasoning = |content: &str, summary: Option<&str>| Part::Reasoning {
+            content: content.to_string(),
+            summary: summary.map(str::to_string),
+            signature: Some("sig".to_string()),
+            finished: true,
+        };
+        let mut response = Response {
+            data: vec![Message::Assistant(vec![
+                reasoning("Long chain of thought.", Some("Thought briefly.")),
+                Part::Text {
+                    content: "Answer.".to_string(),
+                    finished: true,
+                },
+            ])],
+            usage: Usage::default(),
+            finish: FinishReason::Stop,
+            alternatives: vec![vec![Message::Assistant(vec![reasoning("Other.", None)])]],
+            model: None,
+            iterations: Vec::new(),
+        };
+
+        let mut full = response.clone();
+        full.set_reasoning_visibility(ReasoningVisibility::Full);
+        assert_eq!(full.data, response.data);
+
+        response.set_reasoning_visibility(ReasoningVisibility::Summary);
+        assert_eq!(
+            response.data[0].parts()[0],
+            reasoning("", Some("Thought briefly."))
+        );
+        assert_eq!(response.alternatives[0][0].parts()[0], reasoning("", None));
+        assert_eq!(response.data[0].content().as_deref(), Some("Answer."));
+
+        response.set_reasoning_visibility(ReasoningVisibility::Hidden);
+        assert_eq!(response.data[0].parts()[0], reasoning("", None));
+    }
 }
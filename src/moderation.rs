@@ -0,0 +1,254 @@
+//! Content moderation and safety screening.
+//!
+//! [`ModerationClient`] classifies text with a provider moderation endpoint.
+//! Like embeddings, it reuses the regular provider clients: create a client with
+//! a moderation model (e.g. `OpenAI::create(key, "omni-moderation-latest".into())`)
+//! and call [`ModerationClient::moderate`] on it.
+//!
+//! A [`SafetyPolicy`] screens the messages of an agent run. The agent passes it
+//! the user input before the first request, every tool result before it is sent
+//! back to the model, and every model message before it is returned or its tool
+//! calls are executed. [`ModerationPolicy`] screens them with a moderation model:
+//!
+//! ```ignore
+//! use unia::moderation::ModerationPolicy;
+//!
+//! let moderation = OpenAI::create(key.clone(), "omni-moderation-latest".into());
+//! let agent = Agent::new(OpenAI::create(key, "gpt-5".into()))
+//!     .with_safety_policy(ModerationPolicy::new(moderation).with_redaction("[removed]"));
+//! ```
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tracing::warn;
+
+use crate::client::ClientError;
+use crate::history::redact_message;
+use crate::model::{Message, Part};
+
+/// Trait for providers that can classify content as harmful.
+#[async_trait]
+pub trait ModerationClient: Send + Sync {
+    /// Classify a batch of texts in a single request.
+    ///
+    /// The returned results are in the same order as the input texts.
+    async fn moderate(&self, texts: Vec<String>) -> Result<Vec<ModerationResult>, ClientError>;
+}
+
+/// Classification of one text.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ModerationResult {
+    /// Whether the text violates any category.
+    pub flagged: bool,
+    /// Names of the violated categories, such as `harassment` or `violence`.
+    pub categories: Vec<String>,
+    /// Score between 0 and 1 of every category.
+    pub scores: HashMap<String, f64>,
+}
+
+/// Where a message screened by a [`SafetyPolicy`] comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SafetyStage {
+    /// User input or tool results, before they are sent to the model.
+    Input,
+    /// A model message, before it is returned or acted upon.
+    Output,
+}
+
+/// Decision of a [`SafetyPolicy`] about a message.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SafetyVerdict {
+    /// Keep the message as is.
+    Allow,
+    /// Use this message in place of the screened one.
+    Redact(Message),
+    /// Stop the run with [`ClientError::PolicyViolation`] and this reason.
+    Reject(String),
+}
+
+/// Screening of the messages of an agent run.
+///
+/// Installed with [`Agent::with_safety_policy`](crate::agent::Agent::with_safety_policy).
+#[async_trait]
+pub trait SafetyPolicy: Send + Sync {
+    /// Decide whether a message may be used.
+    async fn screen(
+        &self,
+        message: &Message,
+        stage: SafetyStage,
+    ) -> Result<SafetyVerdict, ClientError>;
+}
+
+/// Safety policy rejecting or redacting messages flagged by a moderation model.
+///
+/// The text, reasoning and tool results of a message are classified. Flagged
+/// messages are rejected unless a redaction placeholder is set, in which case
+/// their content is replaced as by [`redact_message`].
+pub struct ModerationPolicy<M> {
+    client: M,
+    placeholder: Option<String>,
+    input: bool,
+    output: bool,
+}
+
+impl<M: ModerationClient> ModerationPolicy<M> {
+    /// Create a policy rejecting flagged input and output.
+    pub fn new(client: M) -> Self {
+        Self {
+            client,
+            placeholder: None,
+            input: true,
+            output: true,
+        }
+    }
+
+    /// Replace the content of flagged messages with a placeholder instead of
+    /// rejecting them.
+    pub fn with_redaction(mut self, placeholder: impl Into<String>) -> Self {
+        self.placeholder = Some(placeholder.into());
+        self
+    }
+
+    /// Set which stages are screened. Both are by default.
+    pub fn with_stages(mut self, input: bool, output: bool) -> Self {
+        self.input = input;
+        self.output = output;
+        self
+    }
+}
+
+/// Text of a message to classify.
+fn moderated_text(message: &Message) -> String {
+    let mut texts = Vec::new();
+    for part in message.parts() {
+        match part {
+            Part::Text { content, .. } | Part::Reasoning { content, .. } => {
+                texts.push(content.clone())
+            }
+            Part::FunctionCall { arguments, .. } => texts.push(arguments.to_string()),
+            Part::FunctionResponse {
+                response, parts, ..
+            } => {
+                texts.push(response.to_string());
+                texts.extend(parts.iter().filter_map(|part| match part {
+                    Part::Text { content, .. } => Some(content.clone()),
+                    _ => None,
+                }));
+            }
+            _ => {}
+        }
+    }
+    texts.join("\n")
+}
+
+#[async_trait]
+impl<M: ModerationClient> SafetyPolicy for ModerationPolicy<M> {
+    async fn screen(
+        &self,
+        message: &Message,
+        stage: SafetyStage,
+    ) -> Result<SafetyVerdict, ClientError> {
+        let enabled = match stage {
+            SafetyStage::Input => self.input,
+            SafetyStage::Output => self.output,
+        };
+        let text = moderated_text(message);
+        if !enabled || text.trim().is_empty() {
+            return Ok(SafetyVerdict::Allow);
+        }
+
+        let result = self
+            .client
+            .moderate(vec![text])
+            .await?
+            .pop()
+            .ok_or_else(|| ClientError::ProviderError("No moderation result returned".into()))?;
+        if !result.flagged {
+            return Ok(SafetyVerdict::Allow);
+        }
+
+        let categories = result.categories.join(", ");
+        warn!(
+            target: "unia::policy",
+            ?stage,
+            categories = categories.as_str(),
+            "Content flagged by moderation"
+        );
+        Ok(match &self.placeholder {
+            Some(placeholder) => {
+                let mut messages = [message.clone()];
+                // The index is in bounds, so redaction cannot fail.
+                let _ = redact_message(&mut messages, 0, placeholder);
+                let [redacted] = messages;
+                SafetyVerdict::Redact(redacted)
+            }
+            None => SafetyVerdict::Reject(format!("content flagged as {}", categories)),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct KeywordModeration;
+
+    #[async_trait]
+    impl ModerationClient for KeywordModeration {
+        async fn moderate(&self, texts: Vec<String>) -> Result<Vec<ModerationResult>, ClientError> {
+            Ok(texts
+                .iter()
+                .map(|text| ModerationResult {
+                    flagged: text.contains("attack"),
+                    categories: vec!["violence".to_string()],
+                    scores: HashMap::new(),
+                })
+                .collect())
+        }
+    }
+
+    fn text(content: &str) -> Message {
+        Message::User(vec![Part::Text {
+            content: content.to_string(),
+            finished: true,
+        }])
+    }
+
+    #[tokio::test]
+    async fn test_moderation_policy() {
+        let policy = ModerationPolicy::new(KeywordModeration);
+        assert_eq!(
+            policy
+                .screen(&text("Hello"), SafetyStage::Input)
+                .await
+                .unwrap(),
+            SafetyVerdict::Allow
+        );
+        assert_eq!(
+            policy
+                .screen(&text("Plan an attack"), SafetyStage::Input)
+                .await
+                .unwrap(),
+            SafetyVerdict::Reject("content flagged as violence".to_string())
+        );
+
+        let policy = ModerationPolicy::new(KeywordModeration)
+            .with_redaction("[removed]")
+            .with_stages(false, true);
+        assert_eq!(
+            policy
+                .screen(&text("Plan an attack"), SafetyStage::Input)
+                .await
+                .unwrap(),
+            SafetyVerdict::Allow
+        );
+        assert_eq!(
+            policy
+                .screen(&text("Plan an attack"), SafetyStage::Output)
+                .await
+                .unwrap(),
+            SafetyVerdict::Redact(text("[removed]"))
+        );
+    }
+}
@@ -3,8 +3,15 @@
 use serde::{Deserialize, Serialize};
 use serde_with::skip_serializing_none;
 use std::collections::HashMap;
+use std::sync::Arc;
 use std::time::Duration;
 
+use crate::hosted::BuiltInTool;
+use crate::http::HttpTransport;
+use crate::markdown::PLAIN_TEXT_INSTRUCTION;
+use crate::prompt::{context_variables, substitute};
+use crate::ratelimit::RateLimiter;
+
 /// Generic model options containing common model behavior parameters
 /// and provider-specific model configuration.
 ///
@@ -18,6 +25,8 @@ pub struct ModelOptions<T> {
 
     /// System instructions passed to the model.
     /// These are typically prepended to the conversation or sent as a specific system message.
+    /// Context placeholders such as `{date}` are resolved when a request is made;
+    /// see [`context_variables`](crate::prompt::context_variables).
     pub system: Option<String>,
 
     /// Enable reasoning/thinking mode (for models that support it, e.g., o1, Claude 4.5).
@@ -35,6 +44,59 @@ pub struct ModelOptions<T> {
     /// Limits the length of the response.
     pub max_tokens: Option<u32>,
 
+    /// Sequences at which generation stops. Not supported by the OpenAI Responses API.
+    pub stop: Option<Vec<String>>,
+
+    /// Penalty for tokens by how often they already appear (-2.0 - 2.0).
+    /// Supported by OpenAI chat completions and Gemini.
+    pub frequency_penalty: Option<f32>,
+
+    /// Penalty for tokens that already appear at all (-2.0 - 2.0).
+    /// Supported by OpenAI chat completions and Gemini.
+    pub presence_penalty: Option<f32>,
+
+    /// Seed for best-effort deterministic sampling.
+    /// Supported by OpenAI chat completions and Gemini.
+    pub seed: Option<u64>,
+
+    /// Number of candidates to generate, returned in
+    /// [`Response::alternatives`](crate::model::Response::alternatives) after the first.
+    /// Supported by OpenAI chat completions and Gemini. Streams only report the first; see
+    /// [`OpenAIClient::request_stream_choices`](crate::api::openai::OpenAIClient::request_stream_choices).
+    pub n: Option<u32>,
+
+    /// Locale of the user, e.g. `de-DE`, available to the system prompt as `{locale}`.
+    pub locale: Option<String>,
+
+    /// Return plain text without markdown.
+    /// The model is instructed accordingly and remaining formatting is stripped from text output.
+    /// Streamed text deltas are released a line at a time, once the line can be stripped.
+    pub plain_text: Option<bool>,
+
+    /// How much of the model's reasoning returned responses show, e.g. to back a
+    /// "show thinking" toggle. Reasoning is still generated and counted in usage.
+    pub reasoning_visibility: Option<ReasoningVisibility>,
+
+    /// Whether and which tool the model must call.
+    /// Only sent with requests that offer tools.
+    pub tool_choice: Option<ToolChoice>,
+
+    /// Send tool schemas in strict mode, so tool arguments always match them.
+    /// Supported by OpenAI. Schemas are converted with
+    /// [`strict_schema`](crate::strict::strict_schema); tools whose schema cannot be
+    /// converted are sent as is.
+    pub strict_tools: Option<bool>,
+
+    /// Role of the message carrying the system prompt, overriding the default of
+    /// the model family, e.g. for a new OpenAI reasoning model or a compatible
+    /// server expecting `developer` messages. Supported by OpenAI-compatible
+    /// Chat Completions APIs.
+    pub system_role: Option<SystemRole>,
+
+    /// Tools hosted by the provider, offered alongside the tools of each request.
+    /// See [`BuiltInTool`] for the providers supporting each.
+    pub built_in_tools: Option<Vec<BuiltInTool>>,
+
     /// Provider-specific model options.
     /// Contains fields unique to the specific provider (e.g., `top_k` for Anthropic/Gemini).
     pub provider: T,
@@ -49,11 +111,123 @@ impl<T: Default> ModelOptions<T> {
             temperature: None,
             top_p: None,
             max_tokens: None,
+            stop: None,
+            frequency_penalty: None,
+            presence_penalty: None,
+            seed: None,
+            n: None,
+            locale: None,
+            plain_text: None,
+            reasoning_visibility: None,
+            tool_choice: None,
+            strict_tools: None,
+            system_role: None,
+            built_in_tools: None,
             provider: T::default(),
         }
     }
 }
 
+impl<T> ModelOptions<T> {
+    /// Copy of the options without the provider-specific part.
+    pub fn erased(&self) -> ModelOptions<()> {
+        ModelOptions {
+            model: self.model.clone(),
+            system: self.system.clone(),
+            reasoning: self.reasoning,
+            temperature: self.temperature,
+            top_p: self.top_p,
+            max_tokens: self.max_tokens,
+            stop: self.stop.clone(),
+            frequency_penalty: self.frequency_penalty,
+            presence_penalty: self.presence_penalty,
+            seed: self.seed,
+            n: self.n,
+            locale: self.locale.clone(),
+            plain_text: self.plain_text,
+            reasoning_visibility: self.reasoning_visibility,
+            tool_choice: self.tool_choice.clone(),
+            strict_tools: self.strict_tools,
+            system_role: self.system_role,
+            built_in_tools: self.built_in_tools.clone(),
+            provider: (),
+        }
+    }
+
+    /// System prompt to send, with context placeholders resolved and instructions
+    /// derived from the output options.
+    pub fn system_prompt(&self) -> Option<String> {
+        let system = self.system.as_ref().map(|system| {
+            substitute(
+                system,
+                &context_variables(&self.model, self.locale.as_deref()),
+            )
+        });
+        if !self.plain_text.unwrap_or(false) {
+            return system;
+        }
+        Some(match system {
+            Some(system) => format!("{}\n\n{}", system, PLAIN_TEXT_INSTRUCTION),
+            None => PLAIN_TEXT_INSTRUCTION.to_string(),
+        })
+    }
+}
+
+/// Tool calling mode of a request.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ToolChoice {
+    /// The model decides whether to call tools. The provider default.
+    Auto,
+    /// The model must not call tools.
+    None,
+    /// The model must call at least one tool.
+    Required,
+    /// The model must call the named tool, e.g. to extract structured data.
+    Specific(String),
+}
+
+/// Role of the message carrying the system prompt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SystemRole {
+    /// Classic `system` message.
+    #[default]
+    System,
+    /// `developer` message, expected by OpenAI reasoning models.
+    Developer,
+}
+
+impl SystemRole {
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            SystemRole::System => "system",
+            SystemRole::Developer => "developer",
+        }
+    }
+}
+
+/// What reasoning parts of a response contain.
+///
+/// Hidden reasoning leaves an empty [`Part::Reasoning`](crate::model::Part::Reasoning)
+/// with its signature, so conversations can still be continued with providers
+/// that require the reasoning to be sent back.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReasoningVisibility {
+    /// The reasoning as returned by the provider.
+    #[default]
+    Full,
+    /// Only the summary of the reasoning, for providers that summarize it.
+    Summary,
+    /// Neither the reasoning nor its summary.
+    Hidden,
+}
+
+/// `User-Agent` sent when none is configured.
+pub const DEFAULT_USER_AGENT: &str =
+    concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
+
 /// Transport configuration options.
 ///
 /// Controls how requests are sent over the network.
@@ -67,6 +241,25 @@ pub enum TransportOptions {
         proxy: Option<String>,
         /// Additional HTTP headers to send with every request.
         headers: Option<HashMap<String, String>>,
+        /// Retry policy for transient failures. If None, requests are sent once.
+        retry: Option<RetryPolicy>,
+        /// `User-Agent` header. If None, [`DEFAULT_USER_AGENT`] is used.
+        user_agent: Option<String>,
+        /// Client-side rate limiter every request waits on before it is sent.
+        rate_limiter: Option<Arc<RateLimiter>>,
+        /// What request and response bodies are logged at debug level.
+        log_policy: LogPolicy,
+        /// How request and response bodies are logged.
+        log_format: LogFormat,
+        /// Logged bodies longer than this many bytes are truncated. If None, bodies
+        /// are logged whole.
+        max_log_body_bytes: Option<usize>,
+        /// Provider named in structured logs. Set by the client the options are
+        /// passed to.
+        provider: Option<&'static str>,
+        /// Sends requests instead of the HTTP client, e.g. to replay recorded
+        /// traffic. If None, requests go to the network.
+        http_transport: Option<Arc<dyn HttpTransport>>,
     },
 }
 
@@ -76,6 +269,14 @@ impl Default for TransportOptions {
             timeout: None,
             proxy: None,
             headers: None,
+            retry: None,
+            user_agent: None,
+            rate_limiter: None,
+            log_policy: LogPolicy::default(),
+            log_format: LogFormat::default(),
+            max_log_body_bytes: None,
+            provider: None,
+            http_transport: None,
         }
     }
 }
@@ -111,4 +312,243 @@ impl TransportOptions {
         }
         self
     }
+
+    /// Set the retry policy.
+    pub fn with_retry(mut self, policy: RetryPolicy) -> Self {
+        match &mut self {
+            TransportOptions::Http { retry, .. } => *retry = Some(policy),
+        }
+        self
+    }
+
+    /// Set the `User-Agent` header.
+    pub fn with_user_agent(mut self, value: impl Into<String>) -> Self {
+        match &mut self {
+            TransportOptions::Http { user_agent, .. } => *user_agent = Some(value.into()),
+        }
+        self
+    }
+
+    /// Wait for quota of a rate limiter before sending every request.
+    ///
+    /// The limiter adapts to the rate limit headers returned by the provider, and
+    /// can be shared by several clients drawing from the same API key.
+    pub fn with_rate_limiter(mut self, limiter: Arc<RateLimiter>) -> Self {
+        match &mut self {
+            TransportOptions::Http { rate_limiter, .. } => *rate_limiter = Some(limiter),
+        }
+        self
+    }
+
+    /// Set what request and response bodies are logged.
+    pub fn with_log_policy(mut self, policy: LogPolicy) -> Self {
+        match &mut self {
+            TransportOptions::Http { log_policy, .. } => *log_policy = policy,
+        }
+        self
+    }
+
+    /// Set how request and response bodies are logged.
+    pub fn with_log_format(mut self, format: LogFormat) -> Self {
+        match &mut self {
+            TransportOptions::Http { log_format, .. } => *log_format = format,
+        }
+        self
+    }
+
+    /// Truncate logged bodies to at most `bytes` bytes.
+    pub fn with_max_log_body_bytes(mut self, bytes: usize) -> Self {
+        match &mut self {
+            TransportOptions::Http {
+                max_log_body_bytes, ..
+            } => *max_log_body_bytes = Some(bytes),
+        }
+        self
+    }
+
+    /// Send requests through a transport instead of the network, such as the
+    /// `HttpReplay` of the `testing` feature, which replays recorded traffic.
+    pub fn with_http_transport<H: HttpTransport + 'static>(mut self, transport: H) -> Self {
+        match &mut self {
+            TransportOptions::Http { http_transport, .. } => {
+                *http_transport = Some(Arc::new(transport))
+            }
+        }
+        self
+    }
+
+    /// Name the provider in structured logs.
+    pub(crate) fn for_provider(mut self, name: &'static str) -> Self {
+        match &mut self {
+            TransportOptions::Http { provider, .. } => *provider = Some(name),
+        }
+        self
+    }
+
+    /// Get the `User-Agent` header sent with requests.
+    pub fn user_agent(&self) -> &str {
+        match self {
+            TransportOptions::Http { user_agent, .. } => {
+                user_agent.as_deref().unwrap_or(DEFAULT_USER_AGENT)
+            }
+        }
+    }
+
+    /// Get the retry policy, if any.
+    pub fn retry_policy(&self) -> Option<&RetryPolicy> {
+        match self {
+            TransportOptions::Http { retry, .. } => retry.as_ref(),
+        }
+    }
+
+    /// Get the body logging policy.
+    pub fn log_policy(&self) -> LogPolicy {
+        match self {
+            TransportOptions::Http { log_policy, .. } => *log_policy,
+        }
+    }
+
+    /// Get the body logging format.
+    pub fn log_format(&self) -> LogFormat {
+        match self {
+            TransportOptions::Http { log_format, .. } => *log_format,
+        }
+    }
+
+    /// Get the size logged bodies are truncated to, if any.
+    pub fn max_log_body_bytes(&self) -> Option<usize> {
+        match self {
+            TransportOptions::Http {
+                max_log_body_bytes, ..
+            } => *max_log_body_bytes,
+        }
+    }
+
+    /// Get the provider named in structured logs, if set.
+    pub fn provider(&self) -> Option<&'static str> {
+        match self {
+            TransportOptions::Http { provider, .. } => *provider,
+        }
+    }
+
+    /// Get the rate limiter, if any.
+    pub fn rate_limiter(&self) -> Option<&Arc<RateLimiter>> {
+        match self {
+            TransportOptions::Http { rate_limiter, .. } => rate_limiter.as_ref(),
+        }
+    }
+
+    /// Get the transport requests are sent through, if any.
+    pub fn http_transport(&self) -> Option<&Arc<dyn HttpTransport>> {
+        match self {
+            TransportOptions::Http { http_transport, .. } => http_transport.as_ref(),
+        }
+    }
+}
+
+/// What request and response bodies are logged at debug level.
+///
+/// Bodies contain the full conversation, so they can carry sensitive user
+/// content even when redacted.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LogPolicy {
+    /// Log nothing.
+    Off,
+    /// Log body sizes only.
+    MetadataOnly,
+    /// Log bodies with base64 media blobs and API keys replaced by placeholders.
+    #[default]
+    Redacted,
+    /// Log bodies verbatim.
+    Full,
+}
+
+/// How request and response bodies are logged.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LogFormat {
+    /// A readable message per body, with JSON bodies pretty-printed.
+    #[default]
+    Text,
+    /// A single-line JSON object per body, with the provider, URL, status and
+    /// duration of the request, for log pipelines to parse.
+    Json,
+}
+
+/// Retry policy with exponential backoff for transient request failures.
+///
+/// Requests are retried on connection errors, timeouts, and any status listed in
+/// `retry_on_status`. A `Retry-After` header on 429/503 responses takes precedence
+/// over the computed backoff.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    /// Maximum number of attempts, including the first one.
+    pub max_attempts: u32,
+    /// Delay before the first retry. Doubled on every subsequent retry.
+    pub base_delay: Duration,
+    /// Upper bound for a single delay, including `Retry-After` values.
+    pub max_delay: Duration,
+    /// Randomize delays to avoid synchronized retries across clients.
+    pub jitter: bool,
+    /// HTTP status codes that trigger a retry.
+    pub retry_on_status: Vec<u16>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+            jitter: true,
+            retry_on_status: vec![408, 429, 500, 502, 503, 504],
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Create the default retry policy.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the maximum number of attempts.
+    pub fn with_max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts;
+        self
+    }
+
+    /// Set the base delay.
+    pub fn with_base_delay(mut self, delay: Duration) -> Self {
+        self.base_delay = delay;
+        self
+    }
+
+    /// Set the maximum delay.
+    pub fn with_max_delay(mut self, delay: Duration) -> Self {
+        self.max_delay = delay;
+        self
+    }
+
+    /// Enable or disable jitter.
+    pub fn with_jitter(mut self, jitter: bool) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Set the status codes that trigger a retry.
+    pub fn with_retry_on_status(mut self, statuses: Vec<u16>) -> Self {
+        self.retry_on_status = statuses;
+        self
+    }
+
+    /// Check whether a response status should be retried.
+    pub fn should_retry_status(&self, status: u16) -> bool {
+        self.retry_on_status.contains(&status)
+    }
+
+    /// Backoff delay (without jitter) before the given retry, starting at 1.
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let factor = 2u32.saturating_pow(retry.saturating_sub(1));
+        self.base_delay.saturating_mul(factor).min(self.max_delay)
+    }
 }
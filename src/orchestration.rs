@@ -0,0 +1,489 @@
+//! Multi-agent orchestration.
+//!
+//! A [`Step`] takes messages and produces a [`Response`]. Every [`Agent`] is a
+//! step, and the patterns of this module are steps made of other steps, so they
+//! nest freely:
+//!
+//! - [`Sequential`] runs steps as a pipeline, each working on the answer of the
+//!   one before
+//! - [`Parallel`] sends the same messages to all its steps at once and reduces
+//!   their answers to one
+//! - [`Handoff`] lets a router model pick the specialist that answers
+//!
+//! ```ignore
+//! use unia::orchestration::{Handoff, Parallel, Sequential};
+//!
+//! let research = Parallel::new()
+//!     .with_step(web_researcher)
+//!     .with_step(paper_researcher);
+//! let pipeline = Sequential::new().with_step(research).with_step(writer);
+//! let support = Handoff::new(router_client)
+//!     .with_specialist("billing", "Questions about invoices and payments", billing_agent)
+//!     .with_specialist("technical", "Bugs, errors and how-to questions", tech_agent);
+//!
+//! let response = pipeline.run(messages).await?;
+//! ```
+//!
+//! The usage of all model requests is summed in the returned response, and
+//! [`Response::iterations`] lists each of them in order.
+
+use async_trait::async_trait;
+use rmcp::model::Tool;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tracing::{debug, warn};
+
+use crate::agent::{Agent, AgentError};
+use crate::client::{BoxedClient, Client, ClientError};
+use crate::model::{FinishReason, IterationUsage, Message, Part, Response, Usage};
+
+/// A unit of work of an orchestration.
+#[async_trait]
+pub trait Step: Send + Sync {
+    /// Run the step on a conversation. On failure, the error carries the
+    /// messages and usage of the work done before it.
+    async fn run(&self, messages: Vec<Message>) -> Result<Response, AgentError>;
+}
+
+#[async_trait]
+impl<C: Client> Step for Agent<C> {
+    async fn run(&self, messages: Vec<Message>) -> Result<Response, AgentError> {
+        self.chat(messages).await
+    }
+}
+
+#[async_trait]
+impl<S: Step + ?Sized> Step for Arc<S> {
+    async fn run(&self, messages: Vec<Message>) -> Result<Response, AgentError> {
+        (**self).run(messages).await
+    }
+}
+
+#[async_trait]
+impl<S: Step + ?Sized> Step for Box<S> {
+    async fn run(&self, messages: Vec<Message>) -> Result<Response, AgentError> {
+        (**self).run(messages).await
+    }
+}
+
+/// Steps run one after another.
+///
+/// The first step gets the input messages. Every later step gets the final
+/// answer of the step before as a user message, so the steps do not need to
+/// share tools or understand each other's tool calls. The response is the one
+/// of the last step.
+#[derive(Default)]
+pub struct Sequential {
+    steps: Vec<Box<dyn Step>>,
+}
+
+impl Sequential {
+    /// Create an empty pipeline, which answers with no messages.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append a step.
+    pub fn with_step(mut self, step: impl Step + 'static) -> Self {
+        self.steps.push(Box::new(step));
+        self
+    }
+}
+
+#[async_trait]
+impl Step for Sequential {
+    async fn run(&self, mut messages: Vec<Message>) -> Result<Response, AgentError> {
+        let mut done = Vec::new();
+        for (i, step) in self.steps.iter().enumerate() {
+            if i > 0 {
+                messages = vec![user_text(final_text(&done[i - 1]))];
+            }
+            debug!("Running pipeline step {}", i + 1);
+            match step.run(messages.clone()).await {
+                Ok(response) => done.push(response),
+                Err(mut e) => {
+                    e.partial = combine(&done, e.partial);
+                    return Err(e);
+                }
+            }
+        }
+        let last = done.pop().unwrap_or_else(|| empty_response(Vec::new()));
+        Ok(combine(&done, last))
+    }
+}
+
+/// Combines the answers of the steps of a [`Parallel`] into the final messages.
+pub type Reducer = Arc<dyn Fn(&[Response]) -> Vec<Message> + Send + Sync>;
+
+/// Steps run concurrently on the same messages.
+///
+/// By default the answers are joined into one assistant message, in the order
+/// the steps were added. Use [`with_reducer`](Self::with_reducer) to combine
+/// them differently, or follow the step with a summarizing agent in a
+/// [`Sequential`].
+pub struct Parallel {
+    steps: Vec<Box<dyn Step>>,
+    reducer: Reducer,
+}
+
+impl Default for Parallel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Parallel {
+    /// Create an empty fan-out.
+    pub fn new() -> Self {
+        Self {
+            steps: Vec::new(),
+            reducer: Arc::new(join_answers),
+        }
+    }
+
+    /// Add a step.
+    pub fn with_step(mut self, step: impl Step + 'static) -> Self {
+        self.steps.push(Box::new(step));
+        self
+    }
+
+    /// Set how the answers of the steps are combined.
+    pub fn with_reducer(
+        mut self,
+        reducer: impl Fn(&[Response]) -> Vec<Message> + Send + Sync + 'static,
+    ) -> Self {
+        self.reducer = Arc::new(reducer);
+        self
+    }
+}
+
+#[async_trait]
+impl Step for Parallel {
+    async fn run(&self, messages: Vec<Message>) -> Result<Response, AgentError> {
+        let results =
+            futures::future::join_all(self.steps.iter().map(|step| step.run(messages.clone())))
+                .await;
+
+        let mut done = Vec::new();
+        let mut error = None;
+        for result in results {
+            match result {
+                Ok(response) => done.push(response),
+                Err(e) if error.is_none() => error = Some(e),
+                Err(e) => {
+                    warn!("Parallel step failed: {}", e.source);
+                    done.push(e.partial);
+                }
+            }
+        }
+        if let Some(mut e) = error {
+            e.partial = combine(&done, e.partial);
+            return Err(e);
+        }
+        let reduced = empty_response((self.reducer)(&done));
+        Ok(combine(&done, reduced))
+    }
+}
+
+/// Name of the tool the router of a [`Handoff`] calls to pick a specialist.
+pub const HANDOFF_TOOL: &str = "handoff";
+
+struct Specialist {
+    name: String,
+    description: String,
+    step: Box<dyn Step>,
+}
+
+/// A router model hands the conversation to one of several specialists.
+///
+/// The router is asked once, with a [`HANDOFF_TOOL`] tool listing the
+/// specialists and their descriptions, and the chosen specialist answers the
+/// input messages. A router that answers in text instead is understood if the
+/// text names a specialist. If it names none, the default specialist answers,
+/// or the run fails without one.
+pub struct Handoff<R: Client = BoxedClient> {
+    router: R,
+    specialists: Vec<Specialist>,
+    default: Option<String>,
+}
+
+impl<R: Client> Handoff<R> {
+    /// Create a handoff routed by a model.
+    pub fn new(router: R) -> Self {
+        Self {
+            router,
+            specialists: Vec::new(),
+            default: None,
+        }
+    }
+
+    /// Add a specialist. The description tells the router when to pick it.
+    pub fn with_specialist(
+        mut self,
+        name: impl Into<String>,
+        description: impl Into<String>,
+        step: impl Step + 'static,
+    ) -> Self {
+        self.specialists.push(Specialist {
+            name: name.into(),
+            description: description.into(),
+            step: Box::new(step),
+        });
+        self
+    }
+
+    /// Set the specialist answering when the router picks none.
+    pub fn with_default(mut self, name: impl Into<String>) -> Self {
+        self.default = Some(name.into());
+        self
+    }
+
+    fn handoff_tool(&self) -> Tool {
+        let specialists: Vec<String> = self
+            .specialists
+            .iter()
+            .map(|s| format!("- {}: {}", s.name, s.description))
+            .collect();
+        let names: Vec<&str> = self.specialists.iter().map(|s| s.name.as_str()).collect();
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "specialist": {
+                    "type": "string",
+                    "enum": names,
+                    "description": "Specialist to hand the conversation to."
+                }
+            },
+            "required": ["specialist"]
+        });
+        Tool::new(
+            HANDOFF_TOOL,
+            format!(
+                "Hand the conversation to the specialist best suited to answer it:\n{}",
+                specialists.join("\n")
+            ),
+            schema.as_object().cloned().unwrap_or_default(),
+        )
+    }
+
+    /// Specialist chosen by the router's response.
+    fn chosen(&self, response: &Response) -> Option<&Specialist> {
+        let find = |name: &str| self.specialists.iter().find(|s| s.name == name.trim());
+        let parts = response.data.iter().flat_map(|m| m.parts());
+        for part in parts {
+            match part {
+                Part::FunctionCall {
+                    name, arguments, ..
+                } if name == HANDOFF_TOOL => {
+                    if let Some(specialist) = arguments
+                        .get("specialist")
+                        .and_then(Value::as_str)
+                        .and_then(find)
+                    {
+                        return Some(specialist);
+                    }
+                }
+                Part::Text { content, .. } => {
+                    if let Some(specialist) = find(content).or_else(|| {
+                        self.specialists
+                            .iter()
+                            .find(|s| content.contains(s.name.as_str()))
+                    }) {
+                        return Some(specialist);
+                    }
+                }
+                _ => {}
+            }
+        }
+        self.default.as_deref().and_then(find)
+    }
+}
+
+#[async_trait]
+impl<R: Client> Step for Handoff<R> {
+    async fn run(&self, messages: Vec<Message>) -> Result<Response, AgentError> {
+        let routed = self
+            .router
+            .request(messages.clone(), vec![self.handoff_tool()])
+            .await
+            .map_err(|source| AgentError {
+                partial: empty_response(Vec::new()),
+                source,
+            })?;
+        let specialist = self.chosen(&routed);
+        // Only the usage of the router is kept.
+        let routed = Response {
+            data: Vec::new(),
+            finish: FinishReason::Stop,
+            ..routed
+        };
+
+        let Some(specialist) = specialist else {
+            return Err(AgentError {
+                partial: combine(&[], routed),
+                source: ClientError::ProviderError("The router chose no specialist".to_string()),
+            });
+        };
+        debug!("Handing off to {}", specialist.name);
+        match specialist.step.run(messages).await {
+            Ok(response) => Ok(combine(std::slice::from_ref(&routed), response)),
+            Err(mut e) => {
+                e.partial = combine(std::slice::from_ref(&routed), e.partial);
+                Err(e)
+            }
+        }
+    }
+}
+
+/// Add the usage of earlier responses to a response.
+fn combine(earlier: &[Response], mut last: Response) -> Response {
+    let mut usage = Usage::default();
+    let mut iterations = Vec::new();
+    for response in earlier.iter().chain(std::iter::once(&last)) {
+        usage += &response.usage;
+        if response.iterations.is_empty() && response.usage != Usage::default() {
+            iterations.push(IterationUsage::from(response));
+        } else {
+            iterations.extend(response.iterations.iter().cloned());
+        }
+    }
+    last.usage = usage;
+    last.iterations = iterations;
+    last
+}
+
+fn empty_response(data: Vec<Message>) -> Response {
+    Response {
+        data,
+        usage: Usage::default(),
+        finish: FinishReason::Stop,
+        model: None,
+        alternatives: Vec::new(),
+        iterations: Vec::new(),
+    }
+}
+
+/// Text of the last assistant message of a response.
+fn final_text(response: &Response) -> String {
+    response
+        .data
+        .iter()
+        .rev()
+        .find(|m| matches!(m, Message::Assistant(_)))
+        .and_then(Message::content)
+        .unwrap_or_default()
+}
+
+fn user_text(text: String) -> Message {
+    Message::User(vec![Part::Text {
+        content: text,
+        finished: true,
+    }])
+}
+
+/// Default reducer of [`Parallel`].
+fn join_answers(responses: &[Response]) -> Vec<Message> {
+    let answers: Vec<String> = responses.iter().map(final_text).collect();
+    vec![Message::Assistant(vec![Part::Text {
+        content: answers.join("\n\n"),
+        finished: true,
+    }])]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockClient;
+
+    fn reply(text: &str, tokens: u32) -> Response {
+        Response {
+            usage: Usage {
+                prompt_tokens: Some(tokens),
+                ..Default::default()
+            },
+            ..empty_response(vec![Message::Assistant(vec![Part::Text {
+                content: text.to_string(),
+                finished: true,
+            }])])
+        }
+    }
+
+    fn agent(responses: Vec<Response>) -> (Agent<MockClient>, MockClient) {
+        let client = MockClient::with_responses(responses);
+        (Agent::new(client.clone()), client)
+    }
+
+    #[tokio::test]
+    async fn test_sequential_and_parallel() {
+        let (researcher, _) = agent(vec![reply("Facts.", 10)]);
+        let (critic, _) = agent(vec![reply("Doubts.", 20)]);
+        let (writer, writer_client) = agent(vec![reply("Essay.", 5)]);
+        let pipeline = Sequential::new()
+            .with_step(Parallel::new().with_step(researcher).with_step(critic))
+            .with_step(writer);
+
+        let response = pipeline.run(vec![user_text("Topic".into())]).await.unwrap();
+        assert_eq!(final_text(&response), "Essay.");
+        assert_eq!(response.usage.prompt_tokens, Some(35));
+        assert_eq!(response.iterations.len(), 3);
+        assert_eq!(
+            writer_client.requests()[0].messages[0].content().as_deref(),
+            Some("Facts.\n\nDoubts.")
+        );
+
+        let (first, _) = agent(vec![reply("Draft.", 10)]);
+        let (failing, _) = agent(vec![]);
+        let error = Sequential::new()
+            .with_step(first)
+            .with_step(failing)
+            .run(vec![])
+            .await
+            .unwrap_err();
+        assert_eq!(error.partial.usage.prompt_tokens, Some(10));
+
+        let (a, _) = agent(vec![reply("A", 1)]);
+        let (b, _) = agent(vec![reply("B", 1)]);
+        let response = Parallel::new()
+            .with_step(a)
+            .with_step(b)
+            .with_reducer(|responses| {
+                let answers: Vec<String> = responses.iter().map(final_text).collect();
+                vec![user_text(answers.join("+"))]
+            })
+            .run(vec![])
+            .await
+            .unwrap();
+        assert_eq!(response.data[0].content().as_deref(), Some("A+B"));
+    }
+
+    #[tokio::test]
+    async fn test_handoff() {
+        let router = MockClient::new()
+            .with_tool_call(HANDOFF_TOOL, json!({ "specialist": "billing" }))
+            .with_response(reply("It is a technical question.", 3))
+            .with_text("No idea.");
+        let (billing, _) = agent(vec![reply("Invoice sent.", 10)]);
+        let (technical, _) = agent(vec![reply("Restart it.", 10)]);
+        let handoff = Handoff::new(router.clone())
+            .with_specialist("billing", "Invoices and payments", billing)
+            .with_specialist("technical", "Bugs and errors", technical);
+
+        let response = handoff.run(vec![user_text("Bill?".into())]).await.unwrap();
+        assert_eq!(final_text(&response), "Invoice sent.");
+        let tool = &router.requests()[0].tools[0];
+        assert_eq!(tool.name, HANDOFF_TOOL);
+        assert!(tool
+            .description
+            .as_deref()
+            .unwrap()
+            .contains("- billing: Invoices"));
+
+        let response = handoff.run(vec![user_text("Bug?".into())]).await.unwrap();
+        assert_eq!(final_text(&response), "Restart it.");
+        assert_eq!(response.usage.prompt_tokens, Some(13));
+        assert_eq!(response.data.len(), 1);
+
+        let error = handoff.run(vec![user_text("?".into())]).await.unwrap_err();
+        assert!(matches!(error.source, ClientError::ProviderError(_)));
+    }
+}
@@ -0,0 +1,409 @@
+//! `tracing` spans following the OpenTelemetry GenAI semantic conventions.
+//!
+//! Enabled with the `otel` feature. Wrapping a client with [`OtelLayer`] emits a
+//! `chat {model}` span per request, and the [`Agent`](crate::agent::Agent) emits
+//! an `invoke_agent` span per run with an `execute_tool {name}` span per tool
+//! call. Spans carry the `gen_ai.*` attributes of the conventions, such as
+//! `gen_ai.provider.name`, `gen_ai.request.model`, `gen_ai.usage.input_tokens`
+//! and `gen_ai.response.finish_reasons`, plus `otel.name`, `otel.kind` and
+//! `error.type`, which `tracing-opentelemetry` maps onto the exported span.
+//! Message content is never recorded.
+//!
+//! ```ignore
+//! use unia::layer::ClientLayerExt;
+//! use unia::otel::OtelLayer;
+//!
+//! let client = OpenAI::create(key, "gpt-5".into()).layer(OtelLayer);
+//! let agent = Agent::new(client).with_server(server);
+//! ```
+
+use async_trait::async_trait;
+use futures::{Stream, StreamExt};
+use rmcp::model::Tool;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tracing::field::Empty;
+use tracing::Span;
+
+use crate::client::{Capabilities, Client, ClientError, ModelInfo, StreamingClient};
+use crate::layer::ClientLayer;
+use crate::model::{FinishReason, Message, Response, Usage};
+use crate::options::{ModelOptions, TransportOptions};
+use crate::stream::{merge_usage, StreamDelta};
+
+/// Span of a model request.
+pub(crate) fn chat_span<T>(provider: &str, options: &ModelOptions<T>) -> Span {
+    tracing::info_span!(
+        target: "unia::otel",
+        "chat",
+        otel.name = format!("chat {}", options.model),
+        otel.kind = "client",
+        otel.status_code = Empty,
+        gen_ai.operation.name = "chat",
+        gen_ai.provider.name = provider,
+        gen_ai.request.model = %options.model,
+        gen_ai.request.temperature = options.temperature,
+        gen_ai.request.top_p = options.top_p,
+        gen_ai.request.max_tokens = options.max_tokens,
+        gen_ai.response.finish_reasons = Empty,
+        gen_ai.usage.input_tokens = Empty,
+        gen_ai.usage.output_tokens = Empty,
+        error.type = Empty,
+    )
+}
+
+/// Span of an agent run.
+pub(crate) fn agent_span(
+    provider: &str,
+    model: &str,
+    variant: Option<&str>,
+    prompt_version: Option<&str>,
+) -> Span {
+    tracing::info_span!(
+        target: "unia::otel",
+        "invoke_agent",
+        otel.name = "invoke_agent",
+        otel.kind = "internal",
+        otel.status_code = Empty,
+        gen_ai.operation.name = "invoke_agent",
+        gen_ai.provider.name = provider,
+        gen_ai.request.model = model,
+        gen_ai.response.finish_reasons = Empty,
+        gen_ai.usage.input_tokens = Empty,
+        gen_ai.usage.output_tokens = Empty,
+        unia.experiment.variant = variant,
+        unia.prompt.version = prompt_version,
+        error.type = Empty,
+    )
+}
+
+/// Span of a tool call made by an agent.
+pub(crate) fn tool_span(name: &str, id: Option<&str>) -> Span {
+    tracing::info_span!(
+        target: "unia::otel",
+        "execute_tool",
+        otel.name = format!("execute_tool {}", name),
+        otel.kind = "internal",
+        otel.status_code = Empty,
+        gen_ai.operation.name = "execute_tool",
+        gen_ai.tool.name = name,
+        gen_ai.tool.call.id = id,
+        error.type = Empty,
+    )
+}
+
+/// Record the finish reason and usage of a response.
+pub(crate) fn record_response(span: &Span, usage: &Usage, finish: &FinishReason) {
+    span.record(
+        "gen_ai.response.finish_reasons",
+        format!("[\"{}\"]", finish_reason(finish)),
+    );
+    if let Some(tokens) = usage.prompt_tokens {
+        span.record("gen_ai.usage.input_tokens", tokens);
+    }
+    if let Some(tokens) = usage.completion_tokens {
+        span.record("gen_ai.usage.output_tokens", tokens);
+    }
+}
+
+/// Record a failure.
+pub(crate) fn record_error(span: &Span, error: &ClientError) {
+    span.record("otel.status_code", "ERROR");
+    span.record("error.type", error_type(error));
+}
+
+fn finish_reason(finish: &FinishReason) -> &'static str {
+    match finish {
+        FinishReason::Stop => "stop",
+        FinishReason::PromptTokens | FinishReason::OutputTokens => "length",
+        FinishReason::ToolCalls => "tool_calls",
+        FinishReason::ContentFilter => "content_filter",
+        FinishReason::Error => "error",
+        FinishReason::MaxIterations => "max_iterations",
+        FinishReason::Unfinished => "unfinished",
+    }
+}
+
+fn error_type(error: &ClientError) -> String {
+    match error {
+        ClientError::Http(e) if e.is_timeout() => "timeout".to_string(),
+        ClientError::Http(_) => "http".to_string(),
+        ClientError::Parse(_) => "parse".to_string(),
+        ClientError::ProviderError(_) => "provider".to_string(),
+        ClientError::Status { status, .. } => status.to_string(),
+        ClientError::StreamCancelled => "cancelled".to_string(),
+        ClientError::DeadlineExceeded => "deadline_exceeded".to_string(),
+        ClientError::Config(_) => "config".to_string(),
+        ClientError::PolicyViolation(_) => "policy_violation".to_string(),
+    }
+}
+
+/// Run a stream of response snapshots in a span, recording the usage and finish
+/// reason of the last one when it ends. The span stays open until then.
+pub(crate) fn instrument_responses<'a>(
+    mut inner: Pin<Box<dyn Stream<Item = Result<Response, ClientError>> + Send + 'a>>,
+    span: Span,
+) -> Pin<Box<dyn Stream<Item = Result<Response, ClientError>> + Send + 'a>> {
+    let stream = async_stream::stream! {
+        let mut last = None;
+        while let Some(item) = inner.next().await {
+            match &item {
+                Ok(response) => last = Some((response.usage.clone(), response.finish.clone())),
+                Err(e) => record_error(&Span::current(), e),
+            }
+            yield item;
+        }
+        if let Some((usage, finish)) = last {
+            record_response(&Span::current(), &usage, &finish);
+        }
+    };
+    Box::pin(InSpan::new(Box::pin(stream), span))
+}
+
+/// Stream that enters a span whenever it is polled, so work done while
+/// producing items is attributed to the span.
+pub(crate) struct InSpan<S> {
+    inner: S,
+    span: Span,
+}
+
+impl<S> InSpan<S> {
+    pub(crate) fn new(inner: S, span: Span) -> Self {
+        Self { inner, span }
+    }
+}
+
+impl<S: Stream + Unpin> Stream for InSpan<S> {
+    type Item = S::Item;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<S::Item>> {
+        let this = &mut *self;
+        let _entered = this.span.enter();
+        this.inner.poll_next_unpin(cx)
+    }
+}
+
+/// Layer emitting a GenAI `chat` span for every request.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OtelLayer;
+
+impl<C: Client> ClientLayer<C> for OtelLayer {
+    type Client = OtelClient<C>;
+
+    fn layer(&self, inner: C) -> OtelClient<C> {
+        OtelClient { inner }
+    }
+}
+
+/// Client wrapper added by [`OtelLayer`].
+#[derive(Debug, Clone)]
+pub struct OtelClient<C> {
+    inner: C,
+}
+
+impl<C: Client> OtelClient<C> {
+    /// Consume the wrapper and return the inner client.
+    pub fn into_inner(self) -> C {
+        self.inner
+    }
+
+    fn span(&self) -> Span {
+        chat_span(self.inner.provider(), self.inner.model_options())
+    }
+}
+
+#[async_trait]
+impl<C: Client> Client for OtelClient<C> {
+    type ModelProvider = C::ModelProvider;
+
+    async fn request(
+        &self,
+        messages: Vec<Message>,
+        tools: Vec<Tool>,
+    ) -> Result<Response, ClientError> {
+        let span = self.span();
+        let result =
+            tracing::Instrument::instrument(self.inner.request(messages, tools), span.clone())
+                .await;
+        match &result {
+            Ok(response) => record_response(&span, &response.usage, &response.finish),
+            Err(e) => record_error(&span, e),
+        }
+        result
+    }
+
+    fn model_options(&self) -> &ModelOptions<Self::ModelProvider> {
+        self.inner.model_options()
+    }
+
+    fn transport_options(&self) -> &TransportOptions {
+        self.inner.transport_options()
+    }
+
+    fn provider(&self) -> &'static str {
+        self.inner.provider()
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        self.inner.capabilities()
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>, ClientError> {
+        self.inner.list_models().await
+    }
+}
+
+#[async_trait]
+impl<C: StreamingClient> StreamingClient for OtelClient<C> {
+    async fn request_stream(
+        &self,
+        messages: Vec<Message>,
+        tools: Vec<Tool>,
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<Response, ClientError>> + Send>>, ClientError>
+    {
+        let span = self.span();
+        let inner = tracing::Instrument::instrument(
+            self.inner.request_stream(messages, tools),
+            span.clone(),
+        )
+        .await
+        .inspect_err(|e| record_error(&span, e))?;
+
+        Ok(instrument_responses(inner, span))
+    }
+
+    async fn request_stream_deltas(
+        &self,
+        messages: Vec<Message>,
+        tools: Vec<Tool>,
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<StreamDelta, ClientError>> + Send>>, ClientError>
+    {
+        let span = self.span();
+        let mut inner = tracing::Instrument::instrument(
+            self.inner.request_stream_deltas(messages, tools),
+            span.clone(),
+        )
+        .await
+        .inspect_err(|e| record_error(&span, e))?;
+
+        let stream = async_stream::stream! {
+            let mut usage = Usage::default();
+            let mut finish = FinishReason::Unfinished;
+            while let Some(item) = inner.next().await {
+                match &item {
+                    Ok(StreamDelta::Usage(update)) => merge_usage(&mut usage, update),
+                    Ok(StreamDelta::Finish(reason)) => finish = reason.clone(),
+                    Ok(_) => {}
+                    Err(e) => record_error(&Span::current(), e),
+                }
+                yield item;
+            }
+            record_response(&Span::current(), &usage, &finish);
+        };
+        Ok(Box::pin(InSpan::new(Box::pin(stream), span)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layer::ClientLayerExt;
+    use crate::model::Part;
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id, Record};
+    use tracing_subscriber::layer::{Context as LayerContext, SubscriberExt};
+
+    type Fields = Arc<Mutex<HashMap<String, String>>>;
+
+    /// Collects the fields of every span into one map.
+    struct Collect(Fields);
+
+    impl Visit for Collect {
+        fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+            self.0
+                .lock()
+                .unwrap()
+                .insert(field.name().to_string(), format!("{:?}", value));
+        }
+
+        fn record_str(&mut self, field: &Field, value: &str) {
+            self.0
+                .lock()
+                .unwrap()
+                .insert(field.name().to_string(), value.to_string());
+        }
+    }
+
+    impl<S: tracing::Subscriber> tracing_subscriber::Layer<S> for Collect {
+        fn on_new_span(&self, attrs: &Attributes<'_>, _id: &Id, _ctx: LayerContext<'_, S>) {
+            attrs.record(&mut Collect(self.0.clone()));
+        }
+
+        fn on_record(&self, _id: &Id, values: &Record<'_>, _ctx: LayerContext<'_, S>) {
+            values.record(&mut Collect(self.0.clone()));
+        }
+    }
+
+    struct StubClient {
+        options: ModelOptions<()>,
+        transport: TransportOptions,
+    }
+
+    #[async_trait]
+    impl Client for StubClient {
+        type ModelProvider = ();
+
+        async fn request(
+            &self,
+            _messages: Vec<Message>,
+            _tools: Vec<Tool>,
+        ) -> Result<Response, ClientError> {
+            Ok(Response {
+                data: vec![Message::Assistant(vec![Part::Text {
+                    content: "Hi".to_string(),
+                    finished: true,
+                }])],
+                usage: Usage {
+                    prompt_tokens: Some(12),
+                    completion_tokens: Some(3),
+                    ..Default::default()
+                },
+                finish: FinishReason::Stop,
+                alternatives: Vec::new(),
+                model: None,
+                iterations: Vec::new(),
+            })
+        }
+
+        fn model_options(&self) -> &ModelOptions<()> {
+            &self.options
+        }
+
+        fn transport_options(&self) -> &TransportOptions {
+            &self.transport
+        }
+    }
+
+    #[tokio::test]
+    async fn test_chat_span_attributes() {
+        let fields = Fields::default();
+        let subscriber = tracing_subscriber::registry().with(Collect(fields.clone()));
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let client = StubClient {
+            options: ModelOptions::new("test-model"),
+            transport: TransportOptions::default(),
+        }
+        .layer(OtelLayer);
+        client.request(vec![], vec![]).await.unwrap();
+
+        let fields = fields.lock().unwrap();
+        assert_eq!(fields["otel.name"], "chat test-model");
+        assert_eq!(fields["gen_ai.request.model"], "test-model");
+        assert_eq!(fields["gen_ai.usage.input_tokens"], "12");
+        assert_eq!(fields["gen_ai.usage.output_tokens"], "3");
+        assert_eq!(fields["gen_ai.response.finish_reasons"], "[\"stop\"]");
+    }
+}
@@ -0,0 +1,308 @@
+//! Model usage policies for restricting which models and providers may be used.
+//!
+//! A [`ModelPolicy`] holds allow and deny rules matched against the provider
+//! identifier and model name of a client. Wrapping a client in a [`PolicyClient`]
+//! enforces the policy before every request and emits an audit log event
+//! (target `unia::policy`) whenever a request is rejected. A policy is also a
+//! [`ClientLayer`], so `client.layer(policy)` does the same.
+//!
+//! Policies are plain serde types, so they can be loaded from JSON configuration:
+//!
+//! ```json
+//! {
+//!   "allow": [{ "provider": "openai" }, { "provider": "anthropic", "model": "claude-*" }],
+//!   "deny": [{ "model": "*-preview" }]
+//! }
+//! ```
+
+use async_trait::async_trait;
+use futures::Stream;
+use rmcp::model::Tool;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::pin::Pin;
+use tracing::warn;
+
+use crate::client::{Capabilities, Client, ClientError, ModelInfo, StreamingClient};
+use crate::layer::ClientLayer;
+use crate::model::{Message, Response};
+use crate::options::{ModelOptions, TransportOptions};
+use crate::stream::StreamDelta;
+
+/// A single policy rule.
+///
+/// Both fields accept `*` wildcards. A missing field matches anything.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PolicyRule {
+    /// Provider identifier pattern (e.g. `openai`, `anthropic`).
+    #[serde(default)]
+    pub provider: Option<String>,
+    /// Model name pattern (e.g. `gpt-5*`).
+    #[serde(default)]
+    pub model: Option<String>,
+}
+
+impl PolicyRule {
+    /// Create a rule matching every model of a provider.
+    pub fn provider(provider: impl Into<String>) -> Self {
+        Self {
+            provider: Some(provider.into()),
+            model: None,
+        }
+    }
+
+    /// Create a rule matching a model pattern on any provider.
+    pub fn model(model: impl Into<String>) -> Self {
+        Self {
+            provider: None,
+            model: Some(model.into()),
+        }
+    }
+
+    /// Restrict the rule to a model pattern.
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.model = Some(model.into());
+        self
+    }
+
+    /// Check whether the rule matches the given provider and model.
+    pub fn matches(&self, provider: &str, model: &str) -> bool {
+        self.provider
+            .as_deref()
+            .is_none_or(|pattern| wildcard_match(pattern, provider))
+            && self
+                .model
+                .as_deref()
+                .is_none_or(|pattern| wildcard_match(pattern, model))
+    }
+}
+
+/// Allow/deny policy for model usage.
+///
+/// Deny rules take precedence over allow rules. An empty allow list permits
+/// every model that is not explicitly denied.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ModelPolicy {
+    /// Rules describing the models that may be used.
+    #[serde(default)]
+    pub allow: Vec<PolicyRule>,
+    /// Rules describing the models that must never be used.
+    #[serde(default)]
+    pub deny: Vec<PolicyRule>,
+}
+
+impl ModelPolicy {
+    /// Create an empty (permit-all) policy.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load a policy from a JSON file.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, ClientError> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path).map_err(|e| {
+            ClientError::Config(format!(
+                "Failed to read policy file {}: {}",
+                path.display(),
+                e
+            ))
+        })?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    /// Add an allow rule.
+    pub fn allow(mut self, rule: PolicyRule) -> Self {
+        self.allow.push(rule);
+        self
+    }
+
+    /// Add a deny rule.
+    pub fn deny(mut self, rule: PolicyRule) -> Self {
+        self.deny.push(rule);
+        self
+    }
+
+    /// Check whether the provider/model combination is permitted.
+    pub fn check(&self, provider: &str, model: &str) -> Result<(), ClientError> {
+        let allowed =
+            self.allow.is_empty() || self.allow.iter().any(|r| r.matches(provider, model));
+        let reason = if self.deny.iter().any(|r| r.matches(provider, model)) {
+            "matched a deny rule"
+        } else if !allowed {
+            "not in the allow list"
+        } else {
+            return Ok(());
+        };
+
+        warn!(
+            target: "unia::policy",
+            provider,
+            model,
+            reason,
+            "Model policy violation"
+        );
+        Err(ClientError::PolicyViolation(format!(
+            "{}/{} {}",
+            provider, model, reason
+        )))
+    }
+}
+
+impl<C: Client> ClientLayer<C> for ModelPolicy {
+    type Client = PolicyClient<C>;
+
+    fn layer(&self, inner: C) -> PolicyClient<C> {
+        PolicyClient::new(inner, self.clone())
+    }
+}
+
+/// Client wrapper that enforces a [`ModelPolicy`] before every request.
+#[derive(Debug, Clone)]
+pub struct PolicyClient<C> {
+    inner: C,
+    policy: ModelPolicy,
+}
+
+impl<C: Client> PolicyClient<C> {
+    /// Wrap a client with the given policy.
+    pub fn new(inner: C, policy: ModelPolicy) -> Self {
+        Self { inner, policy }
+    }
+
+    /// Get a reference to the enforced policy.
+    pub fn policy(&self) -> &ModelPolicy {
+        &self.policy
+    }
+
+    /// Consume the wrapper and return the inner client.
+    pub fn into_inner(self) -> C {
+        self.inner
+    }
+
+    fn enforce(&self) -> Result<(), ClientError> {
+        self.policy
+            .check(self.inner.provider(), &self.inner.model_options().model)
+    }
+}
+
+#[async_trait]
+impl<C: Client> Client for PolicyClient<C> {
+    type ModelProvider = C::ModelProvider;
+
+    async fn request(
+        &self,
+        messages: Vec<Message>,
+        tools: Vec<Tool>,
+    ) -> Result<Response, ClientError> {
+        self.enforce()?;
+        self.inner.request(messages, tools).await
+    }
+
+    fn model_options(&self) -> &ModelOptions<Self::ModelProvider> {
+        self.inner.model_options()
+    }
+
+    fn transport_options(&self) -> &TransportOptions {
+        self.inner.transport_options()
+    }
+
+    fn provider(&self) -> &'static str {
+        self.inner.provider()
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        self.inner.capabilities()
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>, ClientError> {
+        self.inner.list_models().await
+    }
+}
+
+#[async_trait]
+impl<C: StreamingClient> StreamingClient for PolicyClient<C> {
+    async fn request_stream(
+        &self,
+        messages: Vec<Message>,
+        tools: Vec<Tool>,
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<Response, ClientError>> + Send>>, ClientError>
+    {
+        self.enforce()?;
+        self.inner.request_stream(messages, tools).await
+    }
+    async fn request_stream_deltas(
+        &self,
+        messages: Vec<Message>,
+        tools: Vec<Tool>,
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<StreamDelta, ClientError>> + Send>>, ClientError>
+    {
+        self.enforce()?;
+        self.inner.request_stream_deltas(messages, tools).await
+    }
+}
+
+/// Match `text` against a pattern where `*` matches any sequence of characters.
+pub(crate) fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let mut segments = pattern.split('*');
+    let first = segments.next().unwrap_or_default();
+    let Some(mut rest) = text.strip_prefix(first) else {
+        return false;
+    };
+
+    let segments: Vec<&str> = segments.collect();
+    let Some((last, middle)) = segments.split_last() else {
+        return rest.is_empty();
+    };
+
+    for segment in middle {
+        match rest.find(segment) {
+            Some(pos) => rest = &rest[pos + segment.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wildcard_match() {
+        assert!(wildcard_match("gpt-5", "gpt-5"));
+        assert!(!wildcard_match("gpt-5", "gpt-5-mini"));
+        assert!(wildcard_match("gpt-5*", "gpt-5-mini"));
+        assert!(wildcard_match("*", "anything"));
+        assert!(wildcard_match(
+            "claude-*-sonnet*",
+            "claude-4.5-sonnet-latest"
+        ));
+        assert!(!wildcard_match("claude-*-opus", "claude-4.5-sonnet"));
+        assert!(wildcard_match("*-preview", "gemini-3-pro-preview"));
+    }
+
+    #[test]
+    fn test_policy_check() {
+        let policy = ModelPolicy::new()
+            .allow(PolicyRule::provider("openai"))
+            .allow(PolicyRule::provider("anthropic").with_model("claude-*"))
+            .deny(PolicyRule::model("*-preview"));
+
+        assert!(policy.check("openai", "gpt-5").is_ok());
+        assert!(policy.check("anthropic", "claude-opus-4").is_ok());
+        assert!(policy.check("openai", "gpt-5-preview").is_err());
+        assert!(policy.check("gemini", "gemini-3-pro").is_err());
+    }
+
+    #[test]
+    fn test_policy_deserialize() {
+        let policy: ModelPolicy =
+            serde_json::from_str(r#"{"deny": [{"provider": "deepseek"}]}"#).unwrap();
+
+        assert!(policy.check("openai", "gpt-5").is_ok());
+        assert!(matches!(
+            policy.check("deepseek", "deepseek-chat"),
+            Err(ClientError::PolicyViolation(_))
+        ));
+    }
+}
@@ -0,0 +1,250 @@
+//! Composable system prompts.
+//!
+//! A [`SystemPrompt`] assembles a system prompt from a persona, constraints,
+//! tool usage guidance and context sections, instead of one large string
+//! literal. `{name}` placeholders in any section are replaced with the values of
+//! [`with_variable`](SystemPrompt::with_variable):
+//!
+//! ```ignore
+//! use unia::prompt::SystemPrompt;
+//!
+//! let prompt = SystemPrompt::new()
+//!     .with_persona("You are a support agent for {product}.")
+//!     .with_constraint("Answer in at most three sentences.")
+//!     .with_constraint("Never promise refunds.")
+//!     .with_tool_guidance("Look up the order before answering questions about it.")
+//!     .with_context("Customer", customer_summary)
+//!     .with_variable("product", "Acme Cloud");
+//!
+//! let mut options = ModelOptions::new("gpt-5");
+//! prompt.apply(&mut options);
+//! ```
+//!
+//! Placeholders without a value are kept, so the context placeholders of
+//! [`context_variables`], such as `{date}`, are resolved when each request is
+//! made. The rendered prompt is sent as the system or developer message,
+//! depending on the provider and model. Agent runs record a [`prompt_version`] of the system
+//! prompt they were made with, so runs can be grouped by prompt.
+
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::experiment::stable_hash;
+use crate::options::ModelOptions;
+
+/// Builder of a system prompt from sections.
+///
+/// Sections are rendered in a fixed order, separated by blank lines: persona,
+/// constraints, tool usage guidance, then context in the order it was added.
+/// Empty sections are omitted.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SystemPrompt {
+    /// Who the model is and what it does.
+    pub persona: Option<String>,
+    /// Rules the model must follow, rendered as a list.
+    pub constraints: Vec<String>,
+    /// Instructions on when and how to use tools, rendered as a list.
+    pub tool_guidance: Vec<String>,
+    /// Labeled context sections, such as facts about the user.
+    pub context: Vec<(String, String)>,
+    /// Values of `{name}` placeholders.
+    pub variables: BTreeMap<String, String>,
+}
+
+impl SystemPrompt {
+    /// Create an empty prompt.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the persona.
+    pub fn with_persona(mut self, persona: impl Into<String>) -> Self {
+        self.persona = Some(persona.into());
+        self
+    }
+
+    /// Add a constraint.
+    pub fn with_constraint(mut self, constraint: impl Into<String>) -> Self {
+        self.constraints.push(constraint.into());
+        self
+    }
+
+    /// Add tool usage guidance.
+    pub fn with_tool_guidance(mut self, guidance: impl Into<String>) -> Self {
+        self.tool_guidance.push(guidance.into());
+        self
+    }
+
+    /// Add a labeled context section.
+    pub fn with_context(mut self, label: impl Into<String>, text: impl Into<String>) -> Self {
+        self.context.push((label.into(), text.into()));
+        self
+    }
+
+    /// Set the value of the `{name}` placeholder.
+    pub fn with_variable(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.variables.insert(name.into(), value.into());
+        self
+    }
+
+    /// Render the prompt text.
+    ///
+    /// Placeholders without a value are kept as is.
+    pub fn render(&self) -> String {
+        let mut sections = Vec::new();
+        if let Some(persona) = &self.persona {
+            sections.push(persona.clone());
+        }
+        if !self.constraints.is_empty() {
+            sections.push(format!("Constraints:\n{}", bullets(&self.constraints)));
+        }
+        if !self.tool_guidance.is_empty() {
+            sections.push(format!("Tool usage:\n{}", bullets(&self.tool_guidance)));
+        }
+        for (label, text) in &self.context {
+            sections.push(format!("{}:\n{}", label, text));
+        }
+        substitute(&sections.join("\n\n"), &self.variables)
+    }
+
+    /// Version of the rendered prompt; see [`prompt_version`].
+    pub fn version(&self) -> String {
+        prompt_version(&self.render())
+    }
+
+    /// Use the rendered prompt as the system prompt of model options.
+    pub fn apply<T>(&self, options: &mut ModelOptions<T>) {
+        options.system = Some(self.render());
+    }
+}
+
+/// Short identifier of a prompt text, which changes whenever the text does.
+///
+/// The identifier is the same in every process, so it can be compared across
+/// stored [`AgentRun`](crate::agent::AgentRun) records.
+pub fn prompt_version(text: &str) -> String {
+    format!("{:016x}", stable_hash(&[text.as_bytes()]))
+}
+
+/// Values of the context placeholders of system prompts at the current time.
+///
+/// - `{date}`: local date, e.g. `2025-03-14`
+/// - `{weekday}`: local day of the week, e.g. `Friday`
+/// - `{time}`: local time, e.g. `09:30`
+/// - `{datetime}`: local date and time in RFC 3339 format
+/// - `{timezone}`: IANA name of the local time zone, or its UTC offset
+/// - `{model}`: model identifier
+/// - `{locale}`: locale of the user, if known
+pub fn context_variables(model: &str, locale: Option<&str>) -> BTreeMap<String, String> {
+    let now = Local::now();
+    let timezone =
+        iana_time_zone::get_timezone().unwrap_or_else(|_| now.format("UTC%:z").to_string());
+    let mut variables = BTreeMap::from([
+        ("date".to_string(), now.format("%Y-%m-%d").to_string()),
+        ("weekday".to_string(), now.format("%A").to_string()),
+        ("time".to_string(), now.format("%H:%M").to_string()),
+        (
+            "datetime".to_string(),
+            now.to_rfc3339_opts(chrono::SecondsFormat::Secs, false),
+        ),
+        ("timezone".to_string(), timezone),
+        ("model".to_string(), model.to_string()),
+    ]);
+    if let Some(locale) = locale {
+        variables.insert("locale".to_string(), locale.to_string());
+    }
+    variables
+}
+
+fn bullets(items: &[String]) -> String {
+    items
+        .iter()
+        .map(|item| format!("- {}", item))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Replace `{name}` placeholders with their values.
+pub(crate) fn substitute(text: &str, variables: &BTreeMap<String, String>) -> String {
+    let mut output = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('{') {
+        output.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let value = after
+            .find('}')
+            .and_then(|end| Some((end, variables.get(&after[..end])?)));
+        match value {
+            Some((end, value)) => {
+                output.push_str(value);
+                rest = &after[end + 1..];
+            }
+            None => {
+                output.push('{');
+                rest = after;
+            }
+        }
+    }
+    output.push_str(rest);
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_system_prompt() {
+        let prompt = SystemPrompt::new()
+            .with_persona("You are a support agent for {product}.")
+            .with_constraint("Be brief.")
+            .with_constraint("Never promise refunds.")
+            .with_tool_guidance("Look up orders before answering.")
+            .with_context("Customer", "Plan: {plan}, JSON: {\"a\": 1}")
+            .with_variable("product", "Acme Cloud");
+
+        assert_eq!(
+            prompt.render(),
+            "You are a support agent for Acme Cloud.\n\n\
+             Constraints:\n- Be brief.\n- Never promise refunds.\n\n\
+             Tool usage:\n- Look up orders before answering.\n\n\
+             Customer:\nPlan: {plan}, JSON: {\"a\": 1}"
+        );
+        assert_eq!(prompt.version(), prompt.clone().version());
+        assert_ne!(
+            prompt.version(),
+            prompt.with_variable("plan", "pro").version()
+        );
+
+        let mut options = ModelOptions::<()>::new("gpt-5");
+        SystemPrompt::new().with_persona("Hi").apply(&mut options);
+        assert_eq!(options.system.as_deref(), Some("Hi"));
+        assert_eq!(SystemPrompt::new().render(), "");
+    }
+
+    #[test]
+    fn test_context_variables() {
+        let mut options = ModelOptions::<()>::new("gpt-5");
+        options.system = Some("Today is {date}. You are {model} ({locale}, {unknown}).".into());
+        options.locale = Some("de-DE".to_string());
+
+        let today = Local::now().format("%Y-%m-%d").to_string();
+        let system = options.system_prompt().unwrap();
+        // Allow for the date changing during the test.
+        assert!(
+            system.starts_with("Today is ")
+                && system.ends_with(". You are gpt-5 (de-DE, {unknown})."),
+            "{}",
+            system
+        );
+        assert!(
+            system.contains(&today)
+                || system.contains(&Local::now().format("%Y-%m-%d").to_string())
+        );
+
+        let variables = context_variables("gpt-5", None);
+        assert!(!variables.contains_key("locale"));
+        assert!(!variables["timezone"].is_empty());
+    }
+}
@@ -57,7 +57,7 @@ pub use hyperbolic::{Hyperbolic, HyperbolicClient, HyperbolicModel};
 pub use mistral::{Mistral, MistralClient, MistralModel};
 pub use moonshot::{Moonshot, MoonshotClient, MoonshotModel};
 pub use ollama::{Ollama, OllamaClient, OllamaModel};
-pub use openai::{OpenAI, OpenAIClient, OpenAIModel};
+pub use openai::{OpenAI, OpenAIClient, OpenAIModel, OpenAIResponsesClient};
 pub use openrouter::{OpenRouter, OpenRouterClient, OpenRouterModel};
 pub use perplexity::{Perplexity, PerplexityClient, PerplexityModel};
 pub use together::{Together, TogetherClient, TogetherModel};
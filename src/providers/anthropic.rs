@@ -1,8 +1,12 @@
 //! Anthropic API client implementation.
 
-pub use crate::api::anthropic::{AnthropicClient, AnthropicModel};
+pub use crate::api::anthropic::{
+    AnthropicBeta, AnthropicCacheTtl, AnthropicClient, AnthropicModel,
+};
+use crate::client::ClientError;
 use crate::options::{ModelOptions, TransportOptions};
 use crate::providers::Provider;
+use crate::residency::{Region, RegionalProvider};
 
 pub struct Anthropic;
 
@@ -30,3 +34,22 @@ impl Provider for Anthropic {
         )
     }
 }
+
+/// Region-pinned clients are rejected, see [`residency`](crate::residency).
+impl RegionalProvider for Anthropic {
+    fn regional_base_url(_region: Region) -> Option<&'static str> {
+        None
+    }
+
+    fn create_in_region_with_options(
+        _api_key: String,
+        _model_options: ModelOptions<AnthropicModel>,
+        _transport_options: TransportOptions,
+        region: Region,
+    ) -> Result<Self::Client, ClientError> {
+        Err(ClientError::Config(format!(
+            "Anthropic has no endpoint in region {:?}",
+            region
+        )))
+    }
+}
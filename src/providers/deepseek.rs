@@ -8,7 +8,9 @@ use serde::{Deserialize, Serialize};
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct DeepSeekModel;
 
-impl OpenAICompatibleModel for DeepSeekModel {}
+impl OpenAICompatibleModel for DeepSeekModel {
+    const PROVIDER: &'static str = "deepseek";
+}
 
 pub type DeepSeekClient = OpenAIClient<DeepSeekModel>;
 
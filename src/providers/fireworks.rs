@@ -8,7 +8,9 @@ use serde::{Deserialize, Serialize};
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct FireworksModel;
 
-impl OpenAICompatibleModel for FireworksModel {}
+impl OpenAICompatibleModel for FireworksModel {
+    const PROVIDER: &'static str = "fireworks";
+}
 
 pub type FireworksClient = OpenAIClient<FireworksModel>;
 
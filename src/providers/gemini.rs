@@ -1,7 +1,9 @@
 //! Gemini provider implementation.
 
+use crate::client::ClientError;
 use crate::options::{ModelOptions, TransportOptions};
 use crate::providers::Provider;
+use crate::residency::{Region, RegionalProvider};
 
 pub use crate::api::gemini::{GeminiClient, GeminiModel};
 
@@ -31,3 +33,22 @@ impl Provider for Gemini {
         )
     }
 }
+
+/// Region-pinned clients are rejected, see [`residency`](crate::residency).
+impl RegionalProvider for Gemini {
+    fn regional_base_url(_region: Region) -> Option<&'static str> {
+        None
+    }
+
+    fn create_in_region_with_options(
+        _api_key: String,
+        _model_options: ModelOptions<GeminiModel>,
+        _transport_options: TransportOptions,
+        region: Region,
+    ) -> Result<Self::Client, ClientError> {
+        Err(ClientError::Config(format!(
+            "Gemini has no endpoint in region {:?}",
+            region
+        )))
+    }
+}
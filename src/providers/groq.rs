@@ -1,6 +1,6 @@
 //! Groq API client implementation.
 
-use crate::api::openai::{OpenAIClient, OpenAICompatibleModel};
+use crate::api::openai::{OpenAIClient, OpenAICompatibleModel, OpenAICompatibleTranscription};
 use crate::options::{ModelOptions, TransportOptions};
 use crate::providers::Provider;
 use serde::{Deserialize, Serialize};
@@ -8,7 +8,11 @@ use serde::{Deserialize, Serialize};
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct GroqModel;
 
-impl OpenAICompatibleModel for GroqModel {}
+impl OpenAICompatibleModel for GroqModel {
+    const PROVIDER: &'static str = "groq";
+}
+
+impl OpenAICompatibleTranscription for GroqModel {}
 
 pub type GroqClient = OpenAIClient<GroqModel>;
 
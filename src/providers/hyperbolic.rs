@@ -8,7 +8,9 @@ use serde::{Deserialize, Serialize};
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct HyperbolicModel;
 
-impl OpenAICompatibleModel for HyperbolicModel {}
+impl OpenAICompatibleModel for HyperbolicModel {
+    const PROVIDER: &'static str = "hyperbolic";
+}
 
 pub type HyperbolicClient = OpenAIClient<HyperbolicModel>;
 
@@ -1,6 +1,6 @@
 //! Mistral API client implementation.
 
-use crate::api::openai::{OpenAIClient, OpenAICompatibleModel};
+use crate::api::openai::{OpenAIClient, OpenAICompatibleEmbeddings, OpenAICompatibleModel};
 use crate::options::{ModelOptions, TransportOptions};
 use crate::providers::Provider;
 use serde::{Deserialize, Serialize};
@@ -8,7 +8,11 @@ use serde::{Deserialize, Serialize};
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct MistralModel;
 
-impl OpenAICompatibleModel for MistralModel {}
+impl OpenAICompatibleModel for MistralModel {
+    const PROVIDER: &'static str = "mistral";
+}
+
+impl OpenAICompatibleEmbeddings for MistralModel {}
 
 pub type MistralClient = OpenAIClient<MistralModel>;
 
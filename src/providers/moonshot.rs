@@ -8,7 +8,9 @@ use serde::{Deserialize, Serialize};
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct MoonshotModel;
 
-impl OpenAICompatibleModel for MoonshotModel {}
+impl OpenAICompatibleModel for MoonshotModel {
+    const PROVIDER: &'static str = "moonshot";
+}
 
 pub type MoonshotClient = OpenAIClient<MoonshotModel>;
 
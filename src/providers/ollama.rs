@@ -1,6 +1,6 @@
 //! Ollama API client implementation.
 
-use crate::api::openai::{OpenAIClient, OpenAICompatibleModel};
+use crate::api::openai::{OpenAIClient, OpenAICompatibleEmbeddings, OpenAICompatibleModel};
 use crate::options::{ModelOptions, TransportOptions};
 use crate::providers::Provider;
 use serde::{Deserialize, Serialize};
@@ -8,7 +8,17 @@ use serde::{Deserialize, Serialize};
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct OllamaModel;
 
-impl OpenAICompatibleModel for OllamaModel {}
+impl OpenAICompatibleModel for OllamaModel {
+    const PROVIDER: &'static str = "ollama";
+
+    /// Ollama lists local models on its native `/api/tags` endpoint.
+    fn models_url(base_url: &str) -> String {
+        let root = base_url.trim_end_matches('/');
+        format!("{}/api/tags", root.strip_suffix("/v1").unwrap_or(root))
+    }
+}
+
+impl OpenAICompatibleEmbeddings for OllamaModel {}
 
 pub type OllamaClient = OpenAIClient<OllamaModel>;
 
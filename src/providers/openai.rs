@@ -1,19 +1,177 @@
 //! OpenAI API client implementation.
 
-use crate::api::openai::{OpenAIClient as GenericOpenAIClient, OpenAICompatibleModel};
+use crate::api::openai::{
+    is_reasoning_model, OpenAIClient as GenericOpenAIClient, OpenAICompatibleEmbeddings,
+    OpenAICompatibleFiles, OpenAICompatibleFineTuning, OpenAICompatibleImages,
+    OpenAICompatibleModel, OpenAICompatibleModeration, OpenAICompatibleTranscription, SystemRole,
+};
+use crate::api::openai_responses::OpenAIResponsesClient as GenericOpenAIResponsesClient;
+pub use crate::api::openai_responses::ResponsesHostedTool;
+use crate::client::{Capabilities, ClientError};
 use crate::options::{ModelOptions, TransportOptions};
 use crate::providers::Provider;
+use crate::residency::{Region, RegionalProvider};
 use serde::{Deserialize, Serialize};
+use serde_with::skip_serializing_none;
 
+/// OpenAI model options.
+#[skip_serializing_none]
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
-pub struct OpenAIModel;
+pub struct OpenAIModel {
+    /// Output modalities, e.g. `["text", "audio"]` for spoken replies from
+    /// audio models such as `gpt-4o-audio-preview`. Chat Completions only.
+    pub modalities: Option<Vec<String>>,
+    /// Voice and format of spoken replies, required with the `audio` modality.
+    pub audio: Option<OpenAIAudioOutput>,
+}
+
+impl OpenAIModel {
+    /// Reply with audio as well as text.
+    pub fn with_audio_output(
+        mut self,
+        voice: impl Into<String>,
+        format: impl Into<String>,
+    ) -> Self {
+        self.modalities = Some(vec!["text".to_string(), "audio".to_string()]);
+        self.audio = Some(OpenAIAudioOutput {
+            voice: voice.into(),
+            format: format.into(),
+        });
+        self
+    }
+}
+
+/// Voice and encoding of spoken replies.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OpenAIAudioOutput {
+    /// Voice, e.g. `alloy`.
+    pub voice: String,
+    /// Encoding: `wav`, `mp3`, `flac`, `opus` or `pcm16`.
+    pub format: String,
+}
+
+impl OpenAICompatibleModel for OpenAIModel {
+    const PROVIDER: &'static str = "openai";
+
+    fn system_role(model: &str) -> SystemRole {
+        if is_reasoning_model(model) {
+            SystemRole::Developer
+        } else {
+            SystemRole::System
+        }
+    }
+
+    fn capabilities(model: &str) -> Capabilities {
+        // The first o1 previews accept neither images nor system messages.
+        let early_o1 = model.starts_with("o1-mini") || model.starts_with("o1-preview");
+        let max_context_tokens = if model.starts_with("gpt-4.1") {
+            Some(1_047_576)
+        } else if model.starts_with("gpt-5") {
+            Some(400_000)
+        } else if is_reasoning_model(model) {
+            Some(200_000)
+        } else if model.starts_with("gpt-4o") || model.starts_with("gpt-4-turbo") {
+            Some(128_000)
+        } else {
+            None
+        };
+
+        Capabilities {
+            supports_tools: true,
+            supports_vision: max_context_tokens.is_some() && !early_o1,
+            supports_streaming: true,
+            supports_system_prompt: !early_o1,
+            max_context_tokens,
+            supports_json_mode: true,
+        }
+    }
+}
+
+impl OpenAICompatibleEmbeddings for OpenAIModel {}
+
+impl OpenAICompatibleFineTuning for OpenAIModel {}
+
+impl OpenAICompatibleFiles for OpenAIModel {}
 
-impl OpenAICompatibleModel for OpenAIModel {}
+impl OpenAICompatibleImages for OpenAIModel {}
+
+impl OpenAICompatibleModeration for OpenAIModel {}
+
+impl OpenAICompatibleTranscription for OpenAIModel {}
 
 pub type OpenAIClient = GenericOpenAIClient<OpenAIModel>;
 
+pub type OpenAIResponsesClient = GenericOpenAIResponsesClient<OpenAIModel>;
+
 pub struct OpenAI;
 
+impl OpenAI {
+    /// Create a client for the Responses API instead of Chat Completions.
+    ///
+    /// Reasoning models return reasoning summaries there, and conversations can
+    /// be continued on the server with
+    /// [`request_chained`](GenericOpenAIResponsesClient::request_chained).
+    pub fn responses(api_key: String, model: String) -> OpenAIResponsesClient {
+        Self::responses_with_options(
+            api_key,
+            ModelOptions::new(model),
+            TransportOptions::default(),
+        )
+    }
+
+    /// Create a Responses API client with custom model and transport options.
+    pub fn responses_with_options(
+        api_key: String,
+        model_options: ModelOptions<OpenAIModel>,
+        transport_options: TransportOptions,
+    ) -> OpenAIResponsesClient {
+        OpenAIResponsesClient::new(
+            api_key,
+            "https://api.openai.com".to_string(),
+            model_options,
+            transport_options,
+        )
+    }
+
+    /// Create a Responses API client bound to the endpoint of the given region,
+    /// like [`create_in_region`](RegionalProvider::create_in_region).
+    pub fn responses_in_region(
+        api_key: String,
+        model: String,
+        region: Region,
+    ) -> Result<OpenAIResponsesClient, ClientError> {
+        Self::responses_in_region_with_options(
+            api_key,
+            ModelOptions::new(model),
+            TransportOptions::default(),
+            region,
+        )
+    }
+
+    /// Create a regional Responses API client with custom model and transport options.
+    pub fn responses_in_region_with_options(
+        api_key: String,
+        model_options: ModelOptions<OpenAIModel>,
+        transport_options: TransportOptions,
+        region: Region,
+    ) -> Result<OpenAIResponsesClient, ClientError> {
+        Ok(OpenAIResponsesClient::new(
+            api_key,
+            Self::regional_endpoint(region)?,
+            model_options,
+            transport_options,
+        ))
+    }
+
+    fn regional_endpoint(region: Region) -> Result<String, ClientError> {
+        Self::regional_base_url(region)
+            .map(str::to_string)
+            .ok_or_else(|| {
+                ClientError::Config(format!("OpenAI has no endpoint in region {:?}", region))
+            })
+    }
+}
+
 impl Provider for OpenAI {
     type Client = OpenAIClient;
 
@@ -38,3 +196,26 @@ impl Provider for OpenAI {
         )
     }
 }
+
+impl RegionalProvider for OpenAI {
+    fn regional_base_url(region: Region) -> Option<&'static str> {
+        match region {
+            Region::Us => Some("https://us.api.openai.com"),
+            Region::Eu => Some("https://eu.api.openai.com"),
+        }
+    }
+
+    fn create_in_region_with_options(
+        api_key: String,
+        model_options: ModelOptions<OpenAIModel>,
+        transport_options: TransportOptions,
+        region: Region,
+    ) -> Result<Self::Client, ClientError> {
+        Ok(OpenAIClient::new(
+            api_key,
+            Self::regional_endpoint(region)?,
+            model_options,
+            transport_options,
+        ))
+    }
+}
@@ -5,10 +5,48 @@ use crate::options::{ModelOptions, TransportOptions};
 use crate::providers::Provider;
 use serde::{Deserialize, Serialize};
 
+/// OpenRouter model options.
+///
+/// The attribution fields identify your app on the OpenRouter rankings. They
+/// are sent as headers, not in the request body.
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
-pub struct OpenRouterModel;
+pub struct OpenRouterModel {
+    /// URL of your app, sent as the `HTTP-Referer` header.
+    #[serde(default, skip_serializing)]
+    pub http_referer: Option<String>,
+    /// Name of your app, sent as the `X-Title` header.
+    #[serde(default, skip_serializing)]
+    pub app_title: Option<String>,
+}
+
+impl OpenRouterModel {
+    /// Set the app URL used for attribution.
+    pub fn with_http_referer(mut self, url: impl Into<String>) -> Self {
+        self.http_referer = Some(url.into());
+        self
+    }
+
+    /// Set the app name used for attribution.
+    pub fn with_app_title(mut self, title: impl Into<String>) -> Self {
+        self.app_title = Some(title.into());
+        self
+    }
+}
+
+impl OpenAICompatibleModel for OpenRouterModel {
+    const PROVIDER: &'static str = "openrouter";
 
-impl OpenAICompatibleModel for OpenRouterModel {}
+    fn headers(&self) -> Vec<(&'static str, String)> {
+        let mut headers = Vec::new();
+        if let Some(referer) = &self.http_referer {
+            headers.push(("HTTP-Referer", referer.clone()));
+        }
+        if let Some(title) = &self.app_title {
+            headers.push(("X-Title", title.clone()));
+        }
+        headers
+    }
+}
 
 pub type OpenRouterClient = OpenAIClient<OpenRouterModel>;
 
@@ -38,3 +76,25 @@ impl Provider for OpenRouter {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_attribution_headers() {
+        let model = OpenRouterModel::default()
+            .with_http_referer("https://example.com")
+            .with_app_title("Example");
+
+        assert_eq!(
+            model.headers(),
+            vec![
+                ("HTTP-Referer", "https://example.com".to_string()),
+                ("X-Title", "Example".to_string()),
+            ]
+        );
+        // Attribution is sent as headers only.
+        assert_eq!(serde_json::to_value(&model).unwrap(), serde_json::json!({}));
+    }
+}
@@ -8,7 +8,9 @@ use serde::{Deserialize, Serialize};
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct PerplexityModel;
 
-impl OpenAICompatibleModel for PerplexityModel {}
+impl OpenAICompatibleModel for PerplexityModel {
+    const PROVIDER: &'static str = "perplexity";
+}
 
 pub type PerplexityClient = OpenAIClient<PerplexityModel>;
 
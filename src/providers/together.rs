@@ -8,7 +8,9 @@ use serde::{Deserialize, Serialize};
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct TogetherModel;
 
-impl OpenAICompatibleModel for TogetherModel {}
+impl OpenAICompatibleModel for TogetherModel {
+    const PROVIDER: &'static str = "together";
+}
 
 pub type TogetherClient = OpenAIClient<TogetherModel>;
 
@@ -8,7 +8,9 @@ use serde::{Deserialize, Serialize};
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct XAIModel;
 
-impl OpenAICompatibleModel for XAIModel {}
+impl OpenAICompatibleModel for XAIModel {
+    const PROVIDER: &'static str = "xai";
+}
 
 pub type XAIClient = OpenAIClient<XAIModel>;
 
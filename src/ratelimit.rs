@@ -0,0 +1,422 @@
+//! Client-side rate limiting.
+//!
+//! A [`RateLimiter`] tracks request and token quotas with token buckets that
+//! refill continuously over a minute. Callers ask how long they would have to wait
+//! with [`RateLimiter::delay_for`] and reserve capacity with
+//! [`RateLimiter::acquire`], which sleeps until enough quota is available.
+//!
+//! ```ignore
+//! use std::sync::Arc;
+//! use unia::ratelimit::RateLimiter;
+//!
+//! let limiter = Arc::new(
+//!     RateLimiter::new()
+//!         .with_requests_per_minute(50)
+//!         .with_tokens_per_minute(40_000),
+//! );
+//! let agent = Agent::new(client).with_rate_limiter(limiter);
+//! ```
+//!
+//! Outside of an agent, [`RateLimitLayer`] makes a client wait for quota before
+//! every request. A limiter set with
+//! [`TransportOptions::with_rate_limiter`] is applied to every HTTP request of
+//! the client instead, and learns the actual quota from the `x-ratelimit-*`
+//! (OpenAI) and `anthropic-ratelimit-*` headers of the responses through
+//! [`RateLimiter::observe_headers`]. Limits announced by the provider are
+//! adopted when none are configured.
+
+use async_trait::async_trait;
+use futures::Stream;
+use reqwest::header::HeaderMap;
+use rmcp::model::Tool;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::client::{Capabilities, Client, ClientError, ModelInfo, StreamingClient};
+use crate::layer::ClientLayer;
+use crate::model::{Message, Response};
+use crate::options::{ModelOptions, TransportOptions};
+use crate::stream::StreamDelta;
+use crate::tokenize::{count_tokens, estimate_tokens};
+
+/// A continuously refilling token bucket.
+#[derive(Debug, Clone)]
+struct Bucket {
+    capacity: f64,
+    available: f64,
+    refill_per_sec: f64,
+    updated: Instant,
+}
+
+impl Bucket {
+    fn per_minute(limit: u32, now: Instant) -> Self {
+        let capacity = limit as f64;
+        Self {
+            capacity,
+            available: capacity,
+            refill_per_sec: capacity / 60.0,
+            updated: now,
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.available = (self.available + elapsed * self.refill_per_sec).min(self.capacity);
+        self.updated = now;
+    }
+
+    /// Amounts above the capacity could never be satisfied and are clamped to it.
+    fn clamp(&self, amount: f64) -> f64 {
+        amount.min(self.capacity)
+    }
+
+    fn delay(&mut self, amount: f64, now: Instant) -> Duration {
+        self.refill(now);
+        let missing = self.clamp(amount) - self.available;
+        if missing <= 0.0 || self.refill_per_sec <= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(missing / self.refill_per_sec)
+        }
+    }
+
+    fn take(&mut self, amount: f64, now: Instant) {
+        self.refill(now);
+        self.available -= self.clamp(amount);
+    }
+
+    /// Lower the available amount to what the provider reports as remaining.
+    fn observe(&mut self, remaining: f64, now: Instant) {
+        self.refill(now);
+        self.available = self.available.min(remaining);
+    }
+}
+
+#[derive(Debug)]
+struct Buckets {
+    requests: Option<Bucket>,
+    tokens: Option<Bucket>,
+}
+
+impl Buckets {
+    fn delay(&mut self, tokens: usize, now: Instant) -> Duration {
+        let requests = self
+            .requests
+            .as_mut()
+            .map_or(Duration::ZERO, |b| b.delay(1.0, now));
+        let tokens = self
+            .tokens
+            .as_mut()
+            .map_or(Duration::ZERO, |b| b.delay(tokens as f64, now));
+        requests.max(tokens)
+    }
+
+    fn take(&mut self, tokens: usize, now: Instant) {
+        if let Some(bucket) = &mut self.requests {
+            bucket.take(1.0, now);
+        }
+        if let Some(bucket) = &mut self.tokens {
+            bucket.take(tokens as f64, now);
+        }
+    }
+}
+
+/// Adapt a bucket to the reported per-minute limit and remaining quota.
+fn observe(bucket: &mut Option<Bucket>, limit: Option<u32>, remaining: Option<u32>, now: Instant) {
+    if bucket.is_none() {
+        *bucket = limit.map(|limit| Bucket::per_minute(limit, now));
+    }
+    if let (Some(bucket), Some(remaining)) = (bucket, remaining) {
+        bucket.observe(remaining as f64, now);
+    }
+}
+
+/// Read a numeric header, trying the names used by each provider in turn.
+fn header_value(headers: &HeaderMap, names: &[&str]) -> Option<u32> {
+    names
+        .iter()
+        .find_map(|name| headers.get(*name)?.to_str().ok()?.trim().parse().ok())
+}
+
+/// Request and token quota shared by everything sending through it.
+///
+/// Without any limits configured, all requests pass immediately.
+#[derive(Debug)]
+pub struct RateLimiter {
+    buckets: Mutex<Buckets>,
+}
+
+impl Default for RateLimiter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RateLimiter {
+    /// Create a limiter without limits.
+    pub fn new() -> Self {
+        Self {
+            buckets: Mutex::new(Buckets {
+                requests: None,
+                tokens: None,
+            }),
+        }
+    }
+
+    /// Limit the number of requests per minute.
+    pub fn with_requests_per_minute(self, limit: u32) -> Self {
+        self.buckets.lock().unwrap().requests = Some(Bucket::per_minute(limit, Instant::now()));
+        self
+    }
+
+    /// Limit the number of tokens per minute.
+    pub fn with_tokens_per_minute(self, limit: u32) -> Self {
+        self.buckets.lock().unwrap().tokens = Some(Bucket::per_minute(limit, Instant::now()));
+        self
+    }
+
+    /// Time to wait before a request using `tokens` tokens fits the quota.
+    pub fn delay_for(&self, tokens: usize) -> Duration {
+        self.buckets.lock().unwrap().delay(tokens, Instant::now())
+    }
+
+    /// Reserve quota for a request if it is available right away.
+    ///
+    /// Returns the time to wait otherwise, without reserving anything.
+    pub fn try_acquire(&self, tokens: usize) -> Result<(), Duration> {
+        let mut buckets = self.buckets.lock().unwrap();
+        let now = Instant::now();
+        let delay = buckets.delay(tokens, now);
+        if delay.is_zero() {
+            buckets.take(tokens, now);
+            Ok(())
+        } else {
+            Err(delay)
+        }
+    }
+
+    /// Wait until a request using `tokens` tokens fits the quota and reserve it.
+    pub async fn acquire(&self, tokens: usize) {
+        while let Err(delay) = self.try_acquire(tokens) {
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    /// Adapt to the rate limit headers of a provider response.
+    ///
+    /// Remaining quota lowers what is available right away. Limits are only
+    /// adopted if none were configured, and are assumed to be per minute.
+    pub fn observe_headers(&self, headers: &HeaderMap) {
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().unwrap();
+        observe(
+            &mut buckets.requests,
+            header_value(
+                headers,
+                &[
+                    "x-ratelimit-limit-requests",
+                    "anthropic-ratelimit-requests-limit",
+                ],
+            ),
+            header_value(
+                headers,
+                &[
+                    "x-ratelimit-remaining-requests",
+                    "anthropic-ratelimit-requests-remaining",
+                ],
+            ),
+            now,
+        );
+        observe(
+            &mut buckets.tokens,
+            header_value(
+                headers,
+                &[
+                    "x-ratelimit-limit-tokens",
+                    "anthropic-ratelimit-tokens-limit",
+                ],
+            ),
+            header_value(
+                headers,
+                &[
+                    "x-ratelimit-remaining-tokens",
+                    "anthropic-ratelimit-tokens-remaining",
+                ],
+            ),
+            now,
+        );
+    }
+}
+
+/// Layer waiting for quota of a shared [`RateLimiter`] before every request.
+///
+/// Requests reserve the estimated prompt tokens of their messages and tools.
+#[derive(Debug, Clone)]
+pub struct RateLimitLayer {
+    limiter: Arc<RateLimiter>,
+}
+
+impl RateLimitLayer {
+    /// Create a layer drawing from the given limiter.
+    pub fn new(limiter: Arc<RateLimiter>) -> Self {
+        Self { limiter }
+    }
+}
+
+impl<C: Client> ClientLayer<C> for RateLimitLayer {
+    type Client = RateLimitedClient<C>;
+
+    fn layer(&self, inner: C) -> RateLimitedClient<C> {
+        RateLimitedClient {
+            inner,
+            limiter: self.limiter.clone(),
+        }
+    }
+}
+
+/// Client wrapper added by [`RateLimitLayer`].
+#[derive(Debug, Clone)]
+pub struct RateLimitedClient<C> {
+    inner: C,
+    limiter: Arc<RateLimiter>,
+}
+
+impl<C: Client> RateLimitedClient<C> {
+    /// Consume the wrapper and return the inner client.
+    pub fn into_inner(self) -> C {
+        self.inner
+    }
+
+    async fn acquire(&self, messages: &[Message], tools: &[Tool]) {
+        let model = &self.inner.model_options().model;
+        let tokens = count_tokens(messages, model) + estimate_tokens(&[], tools);
+        self.limiter.acquire(tokens).await;
+    }
+}
+
+#[async_trait]
+impl<C: Client> Client for RateLimitedClient<C> {
+    type ModelProvider = C::ModelProvider;
+
+    async fn request(
+        &self,
+        messages: Vec<Message>,
+        tools: Vec<Tool>,
+    ) -> Result<Response, ClientError> {
+        self.acquire(&messages, &tools).await;
+        self.inner.request(messages, tools).await
+    }
+
+    fn model_options(&self) -> &ModelOptions<Self::ModelProvider> {
+        self.inner.model_options()
+    }
+
+    fn transport_options(&self) -> &TransportOptions {
+        self.inner.transport_options()
+    }
+
+    fn provider(&self) -> &'static str {
+        self.inner.provider()
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        self.inner.capabilities()
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>, ClientError> {
+        self.inner.list_models().await
+    }
+}
+
+#[async_trait]
+impl<C: StreamingClient> StreamingClient for RateLimitedClient<C> {
+    async fn request_stream(
+        &self,
+        messages: Vec<Message>,
+        tools: Vec<Tool>,
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<Response, ClientError>> + Send>>, ClientError>
+    {
+        self.acquire(&messages, &tools).await;
+        self.inner.request_stream(messages, tools).await
+    }
+
+    async fn request_stream_deltas(
+        &self,
+        messages: Vec<Message>,
+        tools: Vec<Tool>,
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<StreamDelta, ClientError>> + Send>>, ClientError>
+    {
+        self.acquire(&messages, &tools).await;
+        self.inner.request_stream_deltas(messages, tools).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bucket_refill() {
+        let start = Instant::now();
+        let mut bucket = Bucket::per_minute(60, start);
+
+        bucket.take(60.0, start);
+        assert_eq!(bucket.delay(1.0, start), Duration::from_secs(1));
+        assert_eq!(
+            bucket.delay(1.0, start + Duration::from_secs(1)),
+            Duration::ZERO
+        );
+
+        // Requests larger than the capacity wait for a full bucket instead of forever.
+        assert_eq!(
+            bucket.delay(1000.0, start + Duration::from_secs(1)),
+            Duration::from_secs(59)
+        );
+    }
+
+    #[test]
+    fn test_try_acquire() {
+        let limiter = RateLimiter::new()
+            .with_requests_per_minute(2)
+            .with_tokens_per_minute(1000);
+
+        assert!(limiter.try_acquire(600).is_ok());
+        let delay = limiter.try_acquire(600).unwrap_err();
+        assert!(delay > Duration::from_secs(11) && delay <= Duration::from_secs(12));
+        assert!(limiter.try_acquire(300).is_ok());
+        assert!(limiter.try_acquire(0).is_err());
+
+        assert!(RateLimiter::new().try_acquire(usize::MAX).is_ok());
+    }
+
+    #[test]
+    fn test_observe_headers() {
+        let headers = |pairs: &[(&'static str, &str)]| {
+            let mut map = HeaderMap::new();
+            for (name, value) in pairs {
+                map.insert(*name, value.parse().unwrap());
+            }
+            map
+        };
+
+        // Limits are adopted from the provider when none are configured.
+        let limiter = RateLimiter::new();
+        limiter.observe_headers(&headers(&[
+            ("x-ratelimit-limit-requests", "60"),
+            ("x-ratelimit-remaining-requests", "0"),
+        ]));
+        let delay = limiter.delay_for(0);
+        assert!(delay > Duration::from_millis(900) && delay <= Duration::from_secs(1));
+
+        // Configured limits are kept, but remaining quota is respected.
+        let limiter = RateLimiter::new().with_tokens_per_minute(6000);
+        limiter.observe_headers(&headers(&[
+            ("anthropic-ratelimit-tokens-limit", "100000"),
+            ("anthropic-ratelimit-tokens-remaining", "1000"),
+        ]));
+        assert!(limiter.try_acquire(1000).is_ok());
+        let delay = limiter.try_acquire(1000).unwrap_err();
+        assert!(delay > Duration::from_secs(9) && delay <= Duration::from_secs(10));
+    }
+}
@@ -0,0 +1,695 @@
+//! OpenAI Realtime API over WebSockets.
+//!
+//! Enabled with the `realtime` feature. Unlike the HTTP clients, a realtime
+//! session is a long-lived connection: audio is streamed in as it is captured,
+//! and the model answers with audio, transcripts and tool calls as events.
+//! Audio is 24 kHz mono PCM16 in both directions.
+//!
+//! ```ignore
+//! use unia::realtime::{RealtimeClient, RealtimeEvent, RealtimeSession};
+//!
+//! let client = RealtimeClient::new(key, "gpt-realtime");
+//! let session = RealtimeSession::new()
+//!     .with_instructions("You are a friendly voice assistant.")
+//!     .with_tools(tools);
+//! let mut connection = client.connect(session).await?;
+//!
+//! connection.send_audio(&microphone_chunk).await?;
+//! while let Some(event) = connection.next_event().await {
+//!     match event? {
+//!         RealtimeEvent::AudioDelta(pcm) => speaker.play(&pcm),
+//!         RealtimeEvent::FunctionCall(call) => {
+//!             let result = server.call_tool(/* ... */).await?;
+//!             connection.send_message(Message::User(vec![result])).await?;
+//!             connection.create_response().await?;
+//!         }
+//!         _ => {}
+//!     }
+//! }
+//! ```
+//!
+//! Messages are bridged to the regular model: [`send_message`](RealtimeConnection::send_message)
+//! adds text, audio, images and tool results to the conversation, and every
+//! finished response is reported as a [`Response`] with its text or transcript
+//! and tool calls.
+
+use base64::prelude::*;
+use futures::{SinkExt, StreamExt};
+use rmcp::model::Tool;
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+use tokio::net::TcpStream;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::http::HeaderValue;
+use tokio_tungstenite::tungstenite::{self, Message as Frame};
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+use tracing::{debug, warn};
+
+use crate::client::ClientError;
+use crate::model::{FinishReason, MediaType, Message, Part, Response, Usage};
+use crate::repair::finalize_arguments;
+use crate::tools::canonical_tools;
+
+const DEFAULT_BASE_URL: &str = "wss://api.openai.com/v1/realtime";
+
+/// Audio format of realtime sessions.
+const PCM_FORMAT: &str = "audio/pcm";
+const PCM_RATE: u32 = 24_000;
+
+/// Client for the OpenAI Realtime API.
+#[derive(Debug, Clone)]
+pub struct RealtimeClient {
+    api_key: String,
+    base_url: String,
+    model: String,
+}
+
+impl RealtimeClient {
+    /// Create a client for a realtime model, e.g. `gpt-realtime`.
+    pub fn new(api_key: impl Into<String>, model: impl Into<String>) -> Self {
+        Self {
+            api_key: api_key.into(),
+            base_url: DEFAULT_BASE_URL.to_string(),
+            model: model.into(),
+        }
+    }
+
+    /// Set the WebSocket endpoint, e.g. for Azure or a proxy.
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into();
+        self
+    }
+
+    /// Open a session with the given configuration.
+    pub async fn connect(
+        &self,
+        session: RealtimeSession,
+    ) -> Result<RealtimeConnection, ClientError> {
+        let url = format!("{}?model={}", self.base_url, self.model);
+        let mut request = url.into_client_request().map_err(websocket_error)?;
+        let authorization = HeaderValue::from_str(&format!("Bearer {}", self.api_key))
+            .map_err(|e| ClientError::Config(format!("Invalid API key: {}", e)))?;
+        request.headers_mut().insert("Authorization", authorization);
+
+        debug!("Connecting to realtime endpoint {}", self.base_url);
+        let (socket, _) = tokio_tungstenite::connect_async(request)
+            .await
+            .map_err(websocket_error)?;
+        let mut connection = RealtimeConnection { socket };
+        connection.update_session(&session).await?;
+        Ok(connection)
+    }
+}
+
+fn websocket_error(error: tungstenite::Error) -> ClientError {
+    match error {
+        tungstenite::Error::Http(response) => ClientError::Status {
+            status: response.status().as_u16(),
+            message: response
+                .body()
+                .as_deref()
+                .map(|body| String::from_utf8_lossy(body).into_owned())
+                .unwrap_or_default(),
+        },
+        other => ClientError::ProviderError(format!("WebSocket error: {}", other)),
+    }
+}
+
+/// Configuration of a realtime session.
+#[derive(Debug, Clone, Default)]
+pub struct RealtimeSession {
+    /// System instructions.
+    pub instructions: Option<String>,
+    /// Voice of audio responses, e.g. `marin`.
+    pub voice: Option<String>,
+    /// Respond with text instead of audio.
+    pub text_only: bool,
+    /// Tools the model may call.
+    pub tools: Vec<Tool>,
+    /// Disable voice activity detection. Audio must then be committed and
+    /// responses created explicitly.
+    pub manual_turns: bool,
+    /// Transcription model for input audio, e.g. `gpt-4o-transcribe`. Input is
+    /// not transcribed unless set.
+    pub input_transcription: Option<String>,
+}
+
+impl RealtimeSession {
+    /// Create a session with the provider defaults.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the system instructions.
+    pub fn with_instructions(mut self, instructions: impl Into<String>) -> Self {
+        self.instructions = Some(instructions.into());
+        self
+    }
+
+    /// Set the voice of audio responses.
+    pub fn with_voice(mut self, voice: impl Into<String>) -> Self {
+        self.voice = Some(voice.into());
+        self
+    }
+
+    /// Respond with text instead of audio.
+    pub fn with_text_only(mut self, text_only: bool) -> Self {
+        self.text_only = text_only;
+        self
+    }
+
+    /// Set the tools the model may call.
+    pub fn with_tools(mut self, tools: Vec<Tool>) -> Self {
+        self.tools = tools;
+        self
+    }
+
+    /// Disable voice activity detection.
+    pub fn with_manual_turns(mut self, manual_turns: bool) -> Self {
+        self.manual_turns = manual_turns;
+        self
+    }
+
+    /// Transcribe input audio with a model.
+    pub fn with_input_transcription(mut self, model: impl Into<String>) -> Self {
+        self.input_transcription = Some(model.into());
+        self
+    }
+
+    /// The `session` object of a `session.update` event.
+    fn to_json(&self) -> Value {
+        let format = json!({ "type": PCM_FORMAT, "rate": PCM_RATE });
+        let mut input = Map::new();
+        input.insert("format".into(), format.clone());
+        if self.manual_turns {
+            input.insert("turn_detection".into(), Value::Null);
+        }
+        if let Some(model) = &self.input_transcription {
+            input.insert("transcription".into(), json!({ "model": model }));
+        }
+        let mut output = Map::new();
+        output.insert("format".into(), format);
+        if let Some(voice) = &self.voice {
+            output.insert("voice".into(), json!(voice));
+        }
+
+        let mut session = Map::new();
+        session.insert("type".into(), json!("realtime"));
+        if let Some(instructions) = &self.instructions {
+            session.insert("instructions".into(), json!(instructions));
+        }
+        let modality = if self.text_only { "text" } else { "audio" };
+        session.insert("output_modalities".into(), json!([modality]));
+        session.insert("audio".into(), json!({ "input": input, "output": output }));
+        if !self.tools.is_empty() {
+            let tools: Vec<Value> = canonical_tools(self.tools.clone())
+                .into_iter()
+                .map(|tool| {
+                    json!({
+                        "type": "function",
+                        "name": tool.name,
+                        "description": tool.description,
+                        "parameters": Value::Object((*tool.input_schema).clone()),
+                    })
+                })
+                .collect();
+            session.insert("tools".into(), json!(tools));
+        }
+        Value::Object(session)
+    }
+}
+
+/// Event received from a realtime session.
+#[derive(Debug, Clone)]
+pub enum RealtimeEvent {
+    /// The session was created or its configuration updated.
+    SessionUpdated,
+    /// Voice activity detection heard the user start speaking.
+    SpeechStarted,
+    /// Voice activity detection heard the user stop speaking.
+    SpeechStopped,
+    /// Transcript of a user audio turn, if input transcription is enabled.
+    InputTranscript(String),
+    /// Text of a text response.
+    TextDelta(String),
+    /// Transcript of an audio response.
+    TranscriptDelta(String),
+    /// PCM16 audio of an audio response.
+    AudioDelta(Vec<u8>),
+    /// A complete `Part::FunctionCall`. Answer it with a
+    /// `Part::FunctionResponse` and create a new response.
+    FunctionCall(Part),
+    /// A response finished. It holds the text or transcript and the tool calls
+    /// of the response.
+    ResponseDone(Response),
+    /// Any other server event, as received.
+    Other(Value),
+}
+
+/// An open realtime session.
+pub struct RealtimeConnection {
+    socket: WebSocketStream<MaybeTlsStream<TcpStream>>,
+}
+
+impl RealtimeConnection {
+    async fn send(&mut self, event: Value) -> Result<(), ClientError> {
+        self.socket
+            .send(Frame::text(event.to_string()))
+            .await
+            .map_err(websocket_error)
+    }
+
+    /// Change the configuration of the session.
+    pub async fn update_session(&mut self, session: &RealtimeSession) -> Result<(), ClientError> {
+        self.send(json!({ "type": "session.update", "session": session.to_json() }))
+            .await
+    }
+
+    /// Append PCM16 audio to the input buffer.
+    pub async fn send_audio(&mut self, pcm: &[u8]) -> Result<(), ClientError> {
+        self.send(json!({
+            "type": "input_audio_buffer.append",
+            "audio": BASE64_STANDARD.encode(pcm),
+        }))
+        .await
+    }
+
+    /// Commit the input buffer as a user message, ending the turn when voice
+    /// activity detection is disabled.
+    pub async fn commit_audio(&mut self) -> Result<(), ClientError> {
+        self.send(json!({ "type": "input_audio_buffer.commit" }))
+            .await
+    }
+
+    /// Discard the audio in the input buffer.
+    pub async fn clear_audio(&mut self) -> Result<(), ClientError> {
+        self.send(json!({ "type": "input_audio_buffer.clear" }))
+            .await
+    }
+
+    /// Add a message to the conversation without requesting a response.
+    ///
+    /// Text, PCM16 audio and images are supported, along with tool calls and
+    /// results. Each tool call and result becomes its own conversation item.
+    pub async fn send_message(&mut self, message: Message) -> Result<(), ClientError> {
+        for item in conversation_items(&message) {
+            self.send(json!({ "type": "conversation.item.create", "item": item }))
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Ask the model to respond to the conversation so far.
+    pub async fn create_response(&mut self) -> Result<(), ClientError> {
+        self.send(json!({ "type": "response.create" })).await
+    }
+
+    /// Stop the response in progress, e.g. when the user interrupts it.
+    pub async fn cancel_response(&mut self) -> Result<(), ClientError> {
+        self.send(json!({ "type": "response.cancel" })).await
+    }
+
+    /// Wait for the next event. Returns `None` once the session is closed.
+    ///
+    /// Error events of the server are returned as errors; the session stays
+    /// open after them.
+    pub async fn next_event(&mut self) -> Option<Result<RealtimeEvent, ClientError>> {
+        loop {
+            let frame = match self.socket.next().await? {
+                Ok(frame) => frame,
+                Err(e) => return Some(Err(websocket_error(e))),
+            };
+            let text = match frame {
+                Frame::Text(text) => text,
+                Frame::Close(_) => return None,
+                _ => continue,
+            };
+            let event = match serde_json::from_str::<Value>(&text) {
+                Ok(event) => event,
+                Err(e) => return Some(Err(e.into())),
+            };
+            return Some(parse_event(event));
+        }
+    }
+
+    /// Close the session.
+    pub async fn close(mut self) -> Result<(), ClientError> {
+        self.socket.close(None).await.map_err(websocket_error)
+    }
+}
+
+/// Conversation items for a message.
+fn conversation_items(message: &Message) -> Vec<Value> {
+    let (role, text_type) = match message {
+        Message::User(_) => ("user", "input_text"),
+        Message::Assistant(_) => ("assistant", "output_text"),
+    };
+    let mut items = Vec::new();
+    let mut content = Vec::new();
+    for part in message.parts() {
+        match part {
+            Part::Text { content: text, .. } => {
+                content.push(json!({ "type": text_type, "text": text }))
+            }
+            Part::Media {
+                mime_type, data, ..
+            } if mime_type.starts_with("audio/") => {
+                content.push(json!({ "type": "input_audio", "audio": data }))
+            }
+            Part::Media {
+                media_type: MediaType::Image,
+                mime_type,
+                data,
+                ..
+            } => content.push(json!({
+                "type": "input_image",
+                "image_url": format!("data:{};base64,{}", mime_type, data),
+            })),
+            Part::FunctionCall {
+                id,
+                name,
+                arguments,
+                ..
+            } => items.push(json!({
+                "type": "function_call",
+                "call_id": id,
+                "name": name,
+                "arguments": arguments.to_string(),
+            })),
+            Part::FunctionResponse { id, response, .. } => {
+                let output = match response {
+                    Value::String(text) => text.clone(),
+                    other => other.to_string(),
+                };
+                items.push(json!({
+                    "type": "function_call_output",
+                    "call_id": id,
+                    "output": output,
+                }))
+            }
+            other => warn!("Realtime sessions do not accept {:?} parts", other),
+        }
+    }
+    if !content.is_empty() {
+        items.insert(
+            0,
+            json!({ "type": "message", "role": role, "content": content }),
+        );
+    }
+    items
+}
+
+#[derive(Debug, Deserialize)]
+struct RealtimeResponse {
+    status: String,
+    #[serde(default)]
+    status_details: Option<Value>,
+    #[serde(default)]
+    output: Vec<RealtimeItem>,
+    #[serde(default)]
+    usage: Option<RealtimeUsage>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum RealtimeItem {
+    Message {
+        #[serde(default)]
+        content: Vec<RealtimeContent>,
+    },
+    FunctionCall {
+        call_id: String,
+        name: String,
+        arguments: String,
+    },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Deserialize)]
+struct RealtimeContent {
+    text: Option<String>,
+    transcript: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RealtimeUsage {
+    input_tokens: Option<u32>,
+    output_tokens: Option<u32>,
+    input_token_details: Option<RealtimeInputDetails>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RealtimeInputDetails {
+    cached_tokens: Option<u32>,
+}
+
+fn function_call(call_id: String, name: String, arguments: &str) -> Part {
+    let (arguments, raw_arguments) = finalize_arguments(arguments);
+    Part::FunctionCall {
+        id: Some(call_id),
+        name,
+        arguments,
+        signature: None,
+        raw_arguments,
+        finished: true,
+    }
+}
+
+impl From<RealtimeResponse> for Response {
+    fn from(response: RealtimeResponse) -> Self {
+        let mut parts = Vec::new();
+        for item in response.output {
+            match item {
+                RealtimeItem::Message { content } => {
+                    parts.extend(content.into_iter().filter_map(|content| {
+                        Some(Part::Text {
+                            content: content.text.or(content.transcript)?,
+                            finished: true,
+                        })
+                    }))
+                }
+                RealtimeItem::FunctionCall {
+                    call_id,
+                    name,
+                    arguments,
+                } => parts.push(function_call(call_id, name, &arguments)),
+                RealtimeItem::Other => {}
+            }
+        }
+
+        let reason = response
+            .status_details
+            .as_ref()
+            .and_then(|details| details.get("reason"))
+            .and_then(Value::as_str);
+        let finish = match (response.status.as_str(), reason) {
+            ("completed", _) if parts.iter().any(|p| matches!(p, Part::FunctionCall { .. })) => {
+                FinishReason::ToolCalls
+            }
+            ("completed" | "cancelled", _) => FinishReason::Stop,
+            ("incomplete", Some("max_output_tokens")) => FinishReason::OutputTokens,
+            ("incomplete", Some("content_filter")) => FinishReason::ContentFilter,
+            _ => FinishReason::Error,
+        };
+        let usage = response.usage.map_or_else(Usage::default, |usage| Usage {
+            prompt_tokens: usage.input_tokens,
+            completion_tokens: usage.output_tokens,
+            cached_tokens: usage.input_token_details.and_then(|d| d.cached_tokens),
+            ..Default::default()
+        });
+
+        Response {
+            data: vec![Message::Assistant(parts)],
+            usage,
+            finish,
+            alternatives: Vec::new(),
+            model: None,
+            iterations: Vec::new(),
+        }
+    }
+}
+
+/// Convert a server event.
+fn parse_event(mut event: Value) -> Result<RealtimeEvent, ClientError> {
+    let kind = event
+        .get("type")
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+        .to_string();
+    let text = |event: &Value, key: &str| {
+        event
+            .get(key)
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string()
+    };
+
+    Ok(match kind.as_str() {
+        "session.created" | "session.updated" => RealtimeEvent::SessionUpdated,
+        "input_audio_buffer.speech_started" => RealtimeEvent::SpeechStarted,
+        "input_audio_buffer.speech_stopped" => RealtimeEvent::SpeechStopped,
+        "conversation.item.input_audio_transcription.completed" => {
+            RealtimeEvent::InputTranscript(text(&event, "transcript"))
+        }
+        "response.output_text.delta" | "response.text.delta" => {
+            RealtimeEvent::TextDelta(text(&event, "delta"))
+        }
+        "response.output_audio_transcript.delta" | "response.audio_transcript.delta" => {
+            RealtimeEvent::TranscriptDelta(text(&event, "delta"))
+        }
+        "response.output_audio.delta" | "response.audio.delta" => {
+            let audio = BASE64_STANDARD
+                .decode(text(&event, "delta"))
+                .map_err(|e| ClientError::ProviderError(format!("Invalid audio delta: {}", e)))?;
+            RealtimeEvent::AudioDelta(audio)
+        }
+        "response.function_call_arguments.done" => RealtimeEvent::FunctionCall(function_call(
+            text(&event, "call_id"),
+            text(&event, "name"),
+            &text(&event, "arguments"),
+        )),
+        "response.done" => {
+            let response: RealtimeResponse = serde_json::from_value(event["response"].take())?;
+            RealtimeEvent::ResponseDone(response.into())
+        }
+        "error" => {
+            let message = event
+                .pointer("/error/message")
+                .and_then(Value::as_str)
+                .unwrap_or("Unknown realtime error");
+            return Err(ClientError::ProviderError(message.to_string()));
+        }
+        _ => RealtimeEvent::Other(event),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use tokio::net::TcpListener;
+
+    #[test]
+    fn test_session_config() {
+        let tool = Tool::new("lookup", "Look up an order", Arc::new(Map::new()));
+        let session = RealtimeSession::new()
+            .with_instructions("Be brief.")
+            .with_voice("marin")
+            .with_tools(vec![tool])
+            .with_manual_turns(true)
+            .to_json();
+        assert_eq!(session["output_modalities"], json!(["audio"]));
+        assert_eq!(session["audio"]["output"]["voice"], "marin");
+        assert_eq!(session["audio"]["input"]["turn_detection"], Value::Null);
+        assert!(session["audio"]["input"].get("transcription").is_none());
+        assert_eq!(session["tools"][0]["name"], "lookup");
+    }
+
+    #[test]
+    fn test_conversation_items() {
+        let message = Message::User(vec![
+            Part::Text {
+                content: "Hi".to_string(),
+                finished: true,
+            },
+            Part::FunctionResponse {
+                id: Some("call_1".to_string()),
+                name: "lookup".to_string(),
+                response: json!({ "status": "shipped" }),
+                parts: vec![],
+                finished: true,
+            },
+        ]);
+        assert_eq!(
+            conversation_items(&message),
+            vec![
+                json!({
+                    "type": "message",
+                    "role": "user",
+                    "content": [{ "type": "input_text", "text": "Hi" }],
+                }),
+                json!({
+                    "type": "function_call_output",
+                    "call_id": "call_1",
+                    "output": r#"{"status":"shipped"}"#,
+                }),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_realtime_session() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut socket = tokio_tungstenite::accept_async(stream).await.unwrap();
+            let received = |frame: Option<Result<Frame, _>>| -> Value {
+                serde_json::from_str(frame.unwrap().unwrap().to_text().unwrap()).unwrap()
+            };
+            assert_eq!(received(socket.next().await)["type"], "session.update");
+            assert_eq!(
+                received(socket.next().await)["type"],
+                "input_audio_buffer.append"
+            );
+
+            let events = [
+                json!({ "type": "session.updated", "session": {} }),
+                json!({ "type": "response.output_audio.delta", "delta": BASE64_STANDARD.encode([1u8, 2]) }),
+                json!({
+                    "type": "response.function_call_arguments.done",
+                    "call_id": "call_1",
+                    "name": "lookup",
+                    "arguments": "{\"order\":7}",
+                }),
+                json!({ "type": "response.done", "response": {
+                    "status": "completed",
+                    "output": [
+                        { "type": "message", "content": [{ "type": "output_audio", "transcript": "Checking." }] },
+                        { "type": "function_call", "call_id": "call_1", "name": "lookup", "arguments": "{\"order\":7}" },
+                    ],
+                    "usage": { "input_tokens": 20, "output_tokens": 5 },
+                }}),
+                json!({ "type": "error", "error": { "message": "Unknown event" } }),
+            ];
+            for event in events {
+                socket.send(Frame::text(event.to_string())).await.unwrap();
+            }
+            socket.close(None).await.unwrap();
+        });
+
+        let client = RealtimeClient::new("key", "gpt-realtime")
+            .with_base_url(format!("ws://{}/v1/realtime", address));
+        let mut connection = client.connect(RealtimeSession::new()).await.unwrap();
+        connection.send_audio(&[0, 0]).await.unwrap();
+
+        let mut events = Vec::new();
+        while let Some(event) = connection.next_event().await {
+            events.push(event);
+        }
+        server.await.unwrap();
+
+        assert_eq!(events.len(), 5);
+        assert!(matches!(events[0], Ok(RealtimeEvent::SessionUpdated)));
+        assert!(matches!(&events[1], Ok(RealtimeEvent::AudioDelta(pcm)) if *pcm == [1, 2]));
+        let call = function_call("call_1".to_string(), "lookup".to_string(), "{\"order\":7}");
+        assert!(matches!(&events[2], Ok(RealtimeEvent::FunctionCall(part)) if *part == call));
+        let Ok(RealtimeEvent::ResponseDone(response)) = &events[3] else {
+            panic!("expected a response, got {:?}", events[3]);
+        };
+        assert_eq!(response.finish, FinishReason::ToolCalls);
+        assert_eq!(response.usage.prompt_tokens, Some(20));
+        assert_eq!(
+            response.data[0].parts(),
+            &[
+                Part::Text {
+                    content: "Checking.".to_string(),
+                    finished: true,
+                },
+                call,
+            ]
+        );
+        assert!(matches!(&events[4], Err(ClientError::ProviderError(m)) if m == "Unknown event"));
+    }
+}
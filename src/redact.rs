@@ -0,0 +1,345 @@
+//! Redaction of sensitive content in conversations.
+//!
+//! A [`Redactor`] rewrites text to remove sensitive data. [`PatternRedactor`]
+//! replaces regex matches and ships with a set of common PII patterns; any
+//! `Fn(&str) -> String` closure can be used as a redactor as well.
+//!
+//! [`RedactLayer`] applies a redactor to every request of a client, so sensitive
+//! content never reaches the provider.
+
+use async_trait::async_trait;
+use futures::Stream;
+use regex::Regex;
+use rmcp::model::Tool;
+use serde_json::Value;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use crate::client::{Capabilities, Client, ClientError, ModelInfo, StreamingClient};
+use crate::computer::ComputerAction;
+use crate::layer::ClientLayer;
+use crate::model::{Message, Part, Response};
+use crate::options::{ModelOptions, TransportOptions};
+use crate::stream::StreamDelta;
+
+/// Trait for rewriting text to strip sensitive content.
+pub trait Redactor: Send + Sync {
+    /// Return a redacted copy of the text.
+    fn redact(&self, text: &str) -> String;
+
+    /// Return a redacted copy of a JSON value, redacting every string in it.
+    fn redact_value(&self, value: &Value) -> Value {
+        match value {
+            Value::String(s) => Value::String(self.redact(s)),
+            Value::Array(items) => {
+                Value::Array(items.iter().map(|v| self.redact_value(v)).collect())
+            }
+            Value::Object(map) => Value::Object(
+                map.iter()
+                    .map(|(k, v)| (k.clone(), self.redact_value(v)))
+                    .collect(),
+            ),
+            other => other.clone(),
+        }
+    }
+
+    /// Return a redacted copy of a part.
+    ///
+    /// Media payloads and screenshots are left untouched.
+    fn redact_part(&self, part: &Part) -> Part {
+        match part {
+            Part::Text { content, finished } => Part::Text {
+                content: self.redact(content),
+                finished: *finished,
+            },
+            Part::Reasoning {
+                content,
+                summary,
+                signature,
+                finished,
+            } => Part::Reasoning {
+                content: self.redact(content),
+                summary: summary.as_deref().map(|s| self.redact(s)),
+                signature: signature.clone(),
+                finished: *finished,
+            },
+            Part::FunctionCall {
+                id,
+                name,
+                arguments,
+                signature,
+                raw_arguments,
+                finished,
+            } => Part::FunctionCall {
+                id: id.clone(),
+                name: name.clone(),
+                arguments: self.redact_value(arguments),
+                signature: signature.clone(),
+                raw_arguments: raw_arguments.as_deref().map(|raw| self.redact(raw)),
+                finished: *finished,
+            },
+            Part::FunctionResponse {
+                id,
+                name,
+                response,
+                parts,
+                finished,
+            } => Part::FunctionResponse {
+                id: id.clone(),
+                name: name.clone(),
+                response: self.redact_value(response),
+                parts: parts.iter().map(|p| self.redact_part(p)).collect(),
+                finished: *finished,
+            },
+            Part::ComputerCall {
+                id,
+                action: ComputerAction::Type { text },
+                safety_checks,
+                signature,
+                finished,
+            } => Part::ComputerCall {
+                id: id.clone(),
+                action: ComputerAction::Type {
+                    text: self.redact(text),
+                },
+                safety_checks: safety_checks.clone(),
+                signature: signature.clone(),
+                finished: *finished,
+            },
+            Part::Media { .. }
+            | Part::ComputerCall { .. }
+            | Part::ComputerResult { .. }
+            | Part::HostedToolCall { .. }
+            | Part::Citation { .. } => part.clone(),
+        }
+    }
+
+    /// Return a redacted copy of a message.
+    fn redact_message(&self, message: &Message) -> Message {
+        let parts = message
+            .parts()
+            .iter()
+            .map(|p| self.redact_part(p))
+            .collect();
+        match message {
+            Message::User(_) => Message::User(parts),
+            Message::Assistant(_) => Message::Assistant(parts),
+        }
+    }
+}
+
+impl<F> Redactor for F
+where
+    F: Fn(&str) -> String + Send + Sync,
+{
+    fn redact(&self, text: &str) -> String {
+        self(text)
+    }
+}
+
+/// Redactor that replaces regex matches with fixed placeholders.
+#[derive(Debug, Clone, Default)]
+pub struct PatternRedactor {
+    rules: Vec<(Regex, String)>,
+}
+
+impl PatternRedactor {
+    /// Create a redactor without any rules.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a redactor for common PII: email addresses, phone numbers, payment
+    /// card numbers, IPv4 addresses and API keys.
+    pub fn pii() -> Self {
+        let rules = [
+            (r"[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}", "[EMAIL]"),
+            (r"\b(?:sk|pk|rk)-[A-Za-z0-9_-]{16,}\b", "[API_KEY]"),
+            (r"\b(?:\d[ -]?){13,16}\b", "[CARD]"),
+            (
+                r"\+?\b\d{1,3}[ .-]?\(?\d{3}\)?[ .-]?\d{3}[ .-]?\d{4}\b",
+                "[PHONE]",
+            ),
+            (r"\b(?:\d{1,3}\.){3}\d{1,3}\b", "[IP]"),
+        ];
+
+        Self {
+            rules: rules
+                .into_iter()
+                .map(|(pattern, replacement)| {
+                    (
+                        Regex::new(pattern).expect("built-in pattern is valid"),
+                        replacement.to_string(),
+                    )
+                })
+                .collect(),
+        }
+    }
+
+    /// Add a rule replacing every match of `pattern` with `replacement`.
+    pub fn with_pattern(
+        mut self,
+        pattern: &str,
+        replacement: impl Into<String>,
+    ) -> Result<Self, ClientError> {
+        let regex = Regex::new(pattern)
+            .map_err(|e| ClientError::Config(format!("Invalid redaction pattern: {}", e)))?;
+        self.rules.push((regex, replacement.into()));
+        Ok(self)
+    }
+}
+
+impl Redactor for PatternRedactor {
+    fn redact(&self, text: &str) -> String {
+        self.rules
+            .iter()
+            .fold(text.to_string(), |text, (regex, replacement)| {
+                regex.replace_all(&text, replacement.as_str()).into_owned()
+            })
+    }
+}
+
+/// Layer redacting the messages of every request before it is sent.
+#[derive(Clone)]
+pub struct RedactLayer {
+    redactor: Arc<dyn Redactor>,
+}
+
+impl RedactLayer {
+    /// Create a layer applying the given redactor.
+    pub fn new<R: Redactor + 'static>(redactor: R) -> Self {
+        Self {
+            redactor: Arc::new(redactor),
+        }
+    }
+}
+
+impl<C: Client> ClientLayer<C> for RedactLayer {
+    type Client = RedactingClient<C>;
+
+    fn layer(&self, inner: C) -> RedactingClient<C> {
+        RedactingClient {
+            inner,
+            redactor: self.redactor.clone(),
+        }
+    }
+}
+
+/// Client wrapper added by [`RedactLayer`].
+#[derive(Clone)]
+pub struct RedactingClient<C> {
+    inner: C,
+    redactor: Arc<dyn Redactor>,
+}
+
+impl<C: Client> RedactingClient<C> {
+    /// Consume the wrapper and return the inner client.
+    pub fn into_inner(self) -> C {
+        self.inner
+    }
+
+    fn redact(&self, messages: Vec<Message>) -> Vec<Message> {
+        messages
+            .iter()
+            .map(|m| self.redactor.redact_message(m))
+            .collect()
+    }
+}
+
+#[async_trait]
+impl<C: Client> Client for RedactingClient<C> {
+    type ModelProvider = C::ModelProvider;
+
+    async fn request(
+        &self,
+        messages: Vec<Message>,
+        tools: Vec<Tool>,
+    ) -> Result<Response, ClientError> {
+        self.inner.request(self.redact(messages), tools).await
+    }
+
+    fn model_options(&self) -> &ModelOptions<Self::ModelProvider> {
+        self.inner.model_options()
+    }
+
+    fn transport_options(&self) -> &TransportOptions {
+        self.inner.transport_options()
+    }
+
+    fn provider(&self) -> &'static str {
+        self.inner.provider()
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        self.inner.capabilities()
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>, ClientError> {
+        self.inner.list_models().await
+    }
+}
+
+#[async_trait]
+impl<C: StreamingClient> StreamingClient for RedactingClient<C> {
+    async fn request_stream(
+        &self,
+        messages: Vec<Message>,
+        tools: Vec<Tool>,
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<Response, ClientError>> + Send>>, ClientError>
+    {
+        self.inner
+            .request_stream(self.redact(messages), tools)
+            .await
+    }
+
+    async fn request_stream_deltas(
+        &self,
+        messages: Vec<Message>,
+        tools: Vec<Tool>,
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<StreamDelta, ClientError>> + Send>>, ClientError>
+    {
+        self.inner
+            .request_stream_deltas(self.redact(messages), tools)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_pii_redaction() {
+        let redactor = PatternRedactor::pii();
+        assert_eq!(
+            redactor.redact("Mail jane.doe@example.com or call +1 555-123-4567"),
+            "Mail [EMAIL] or call [PHONE]"
+        );
+        assert_eq!(
+            redactor.redact("Card 4111 1111 1111 1111, key sk-abcdefghijklmnop1234"),
+            "Card [CARD], key [API_KEY]"
+        );
+        assert_eq!(redactor.redact("from 192.168.0.1"), "from [IP]");
+    }
+
+    #[test]
+    fn test_redact_message() {
+        let redactor = |text: &str| text.replace("secret", "***");
+        let message = Message::Assistant(vec![Part::FunctionCall {
+            id: None,
+            name: "lookup".to_string(),
+            arguments: json!({ "query": ["a secret", 1] }),
+            signature: None,
+            raw_arguments: None,
+            finished: true,
+        }]);
+
+        match &redactor.redact_message(&message).parts()[0] {
+            Part::FunctionCall { arguments, .. } => {
+                assert_eq!(arguments, &json!({ "query": ["a ***", 1] }))
+            }
+            other => panic!("Expected function call, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,244 @@
+//! Tolerant parsing of model-generated JSON.
+//!
+//! Models occasionally emit tool arguments that are almost, but not quite, valid
+//! JSON: trailing commas, unquoted keys, single-quoted strings, Python literals or
+//! output that was cut off mid-object. [`repair_json`] fixes these common mistakes,
+//! and [`parse_arguments`] applies it to tool call arguments while reporting
+//! whether a repair was needed.
+
+use serde_json::{json, Value};
+use tracing::warn;
+
+/// Result of parsing tool call arguments.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParsedArguments {
+    /// The arguments were valid JSON.
+    Valid(Value),
+    /// The arguments were malformed and have been repaired.
+    Repaired(Value),
+    /// The arguments could not be parsed, even after repair.
+    Invalid,
+}
+
+/// Parse tool call arguments, repairing malformed JSON where possible.
+///
+/// Empty input is treated as an empty object, since providers send it for tools
+/// without parameters.
+pub fn parse_arguments(raw: &str) -> ParsedArguments {
+    if raw.trim().is_empty() {
+        return ParsedArguments::Valid(json!({}));
+    }
+    if let Ok(value) = serde_json::from_str(raw) {
+        return ParsedArguments::Valid(value);
+    }
+    match repair_json(raw) {
+        Some(value) => ParsedArguments::Repaired(value),
+        None => ParsedArguments::Invalid,
+    }
+}
+
+/// Turn raw tool call arguments into the `arguments`/`raw_arguments` pair of
+/// [`Part::FunctionCall`](crate::model::Part::FunctionCall).
+///
+/// The raw text is only kept when it was not valid JSON.
+pub fn finalize_arguments(raw: &str) -> (Value, Option<String>) {
+    match parse_arguments(raw) {
+        ParsedArguments::Valid(value) => (value, None),
+        ParsedArguments::Repaired(value) => {
+            warn!("Repaired malformed tool call arguments: {}", raw);
+            (value, Some(raw.to_string()))
+        }
+        ParsedArguments::Invalid => {
+            warn!("Could not parse tool call arguments: {}", raw);
+            (Value::Null, Some(raw.to_string()))
+        }
+    }
+}
+
+/// Attempt to repair malformed JSON.
+///
+/// Handles trailing commas, comments, unquoted keys and string values, single
+/// quotes, Python/JavaScript literals (`True`, `None`, `undefined`, ...) and
+/// truncated input with unclosed strings, arrays or objects.
+pub fn repair_json(input: &str) -> Option<Value> {
+    let chars: Vec<char> = input.trim().chars().collect();
+    let mut out = String::with_capacity(chars.len() + 8);
+    let mut stack: Vec<char> = Vec::new();
+    let mut expect_key = false;
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            '"' | '\'' => {
+                let (literal, next) = read_string(&chars, i);
+                out.push_str(&literal);
+                i = next;
+                continue;
+            }
+            '/' if chars.get(i + 1) == Some(&'/') => {
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+                continue;
+            }
+            '/' if chars.get(i + 1) == Some(&'*') => {
+                i += 2;
+                while i < chars.len() && !(chars[i] == '*' && chars.get(i + 1) == Some(&'/')) {
+                    i += 1;
+                }
+                i += 2;
+                continue;
+            }
+            '{' | '[' => {
+                stack.push(c);
+                expect_key = c == '{';
+                out.push(c);
+            }
+            '}' | ']' => {
+                trim_trailing_comma(&mut out);
+                stack.pop()?;
+                expect_key = false;
+                out.push(c);
+            }
+            ',' => {
+                expect_key = stack.last() == Some(&'{');
+                out.push(c);
+            }
+            ':' => {
+                expect_key = false;
+                out.push(c);
+            }
+            'e' | 'E' if out.ends_with(|p: char| p.is_ascii_digit()) => out.push(c),
+            c if c.is_alphabetic() || c == '_' || c == '$' => {
+                let start = i;
+                while i < chars.len()
+                    && (chars[i].is_alphanumeric() || matches!(chars[i], '_' | '$' | '-' | '.'))
+                {
+                    i += 1;
+                }
+                let word: String = chars[start..i].iter().collect();
+                let literal = match word.as_str() {
+                    "true" | "True" => Some("true"),
+                    "false" | "False" => Some("false"),
+                    "null" | "None" | "undefined" | "NaN" => Some("null"),
+                    _ => None,
+                };
+                match literal {
+                    Some(literal) if !expect_key => out.push_str(literal),
+                    // Unquoted keys and string values.
+                    _ => out.push_str(&Value::String(word).to_string()),
+                }
+                continue;
+            }
+            _ => out.push(c),
+        }
+        i += 1;
+    }
+
+    // Close whatever the input left open.
+    trim_trailing_comma(&mut out);
+    if out.trim_end().ends_with(':') {
+        out.push_str("null");
+    }
+    while let Some(open) = stack.pop() {
+        out.push(if open == '{' { '}' } else { ']' });
+    }
+
+    serde_json::from_str(&out).ok()
+}
+
+/// Read a single- or double-quoted string starting at `start`, returning it as a
+/// valid JSON string literal and the index after the closing quote. Unterminated
+/// strings are closed.
+fn read_string(chars: &[char], start: usize) -> (String, usize) {
+    let quote = chars[start];
+    let mut content = String::new();
+    let mut i = start + 1;
+
+    while i < chars.len() {
+        match chars[i] {
+            '\\' if i + 1 < chars.len() => {
+                let escaped = chars[i + 1];
+                if escaped == '\'' {
+                    content.push('\'');
+                } else {
+                    content.push('\\');
+                    content.push(escaped);
+                }
+                i += 2;
+                continue;
+            }
+            '\\' => {}
+            c if c == quote => return (format!("\"{}\"", content), i + 1),
+            '"' => content.push_str("\\\""),
+            '\n' => content.push_str("\\n"),
+            '\r' => content.push_str("\\r"),
+            '\t' => content.push_str("\\t"),
+            c => content.push(c),
+        }
+        i += 1;
+    }
+
+    (format!("\"{}\"", content), i)
+}
+
+fn trim_trailing_comma(out: &mut String) {
+    let trimmed_len = out.trim_end().len();
+    if out[..trimmed_len].ends_with(',') {
+        out.truncate(trimmed_len - 1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_repair_json() {
+        let cases = [
+            (r#"{"a": 1, "b": [1, 2,],}"#, json!({ "a": 1, "b": [1, 2] })),
+            (
+                r#"{city: "Paris", unit: 'celsius'}"#,
+                json!({ "city": "Paris", "unit": "celsius" }),
+            ),
+            (
+                r#"{"ok": True, "v": None}"#,
+                json!({ "ok": true, "v": null }),
+            ),
+            (
+                r#"{"q": "it's", "n": [1, {"x": "trunc"#,
+                json!({ "q": "it's", "n": [1, { "x": "trunc" }] }),
+            ),
+            (
+                r#"{"a": 1, // comment
+                "b": 2}"#,
+                json!({ "a": 1, "b": 2 }),
+            ),
+            (r#"{"key":"#, json!({ "key": null })),
+            (
+                r#"{"n": 1e3, "model": gpt-4.1}"#,
+                json!({ "n": 1000.0, "model": "gpt-4.1" }),
+            ),
+        ];
+
+        for (input, expected) in cases {
+            assert_eq!(repair_json(input), Some(expected), "input: {}", input);
+        }
+        assert_eq!(repair_json("}{"), None);
+    }
+
+    #[test]
+    fn test_parse_arguments() {
+        assert_eq!(parse_arguments(""), ParsedArguments::Valid(json!({})));
+        assert_eq!(
+            parse_arguments(r#"{"a": 1}"#),
+            ParsedArguments::Valid(json!({ "a": 1 }))
+        );
+        assert_eq!(
+            parse_arguments(r#"{"a": 1,}"#),
+            ParsedArguments::Repaired(json!({ "a": 1 }))
+        );
+        assert_eq!(parse_arguments("]"), ParsedArguments::Invalid);
+    }
+}
@@ -0,0 +1,283 @@
+//! Data residency aware endpoint selection.
+//!
+//! Some providers expose regional endpoints that keep request data inside a
+//! geographic region. [`RegionalProvider`] creates clients bound to such an
+//! endpoint, and [`ResidencyRouter`] picks the right regional client per request
+//! based on the residency tag of the current request context.
+//!
+//! OpenAI offers US and EU endpoints, which also serve the Responses API, see
+//! [`OpenAI::responses_in_region`](crate::providers::OpenAI::responses_in_region).
+//! Anthropic and Gemini serve requests from a single global endpoint, so creating
+//! a region-pinned client for them fails with [`ClientError::Config`].
+//!
+//! The residency tag is scoped to a future with [`with_residency`]:
+//!
+//! ```ignore
+//! use unia::residency::{with_residency, Region, ResidencyRouter, RegionalProvider};
+//! use unia::providers::OpenAI;
+//!
+//! let router = ResidencyRouter::new(OpenAI::create(key.clone(), "gpt-5".into()))
+//!     .with_region(Region::Eu, OpenAI::create_in_region(key, "gpt-5".into(), Region::Eu)?);
+//!
+//! // Routed to the EU endpoint.
+//! let response = with_residency(Region::Eu, router.request(messages, vec![])).await?;
+//! ```
+
+use async_trait::async_trait;
+use futures::Stream;
+use rmcp::model::Tool;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use tracing::debug;
+
+use crate::client::{Capabilities, Client, ClientError, ModelInfo, StreamingClient};
+use crate::model::{Message, Response};
+use crate::options::{ModelOptions, TransportOptions};
+use crate::providers::Provider;
+use crate::stream::StreamDelta;
+
+/// Geographic region for data residency.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Region {
+    /// United States.
+    Us,
+    /// European Union.
+    Eu,
+}
+
+tokio::task_local! {
+    static RESIDENCY: Region;
+}
+
+/// Run a future with the given residency tag attached to its request context.
+pub async fn with_residency<F: Future>(region: Region, future: F) -> F::Output {
+    RESIDENCY.scope(region, future).await
+}
+
+/// Get the residency tag of the current request context, if any.
+pub fn current_residency() -> Option<Region> {
+    RESIDENCY.try_with(|r| *r).ok()
+}
+
+/// Providers that offer region-pinned endpoints.
+pub trait RegionalProvider: Provider {
+    /// Base URL of the provider endpoint for the given region, if one exists.
+    fn regional_base_url(region: Region) -> Option<&'static str>;
+
+    /// Create a client bound to the endpoint of the given region.
+    fn create_in_region(
+        api_key: String,
+        model: String,
+        region: Region,
+    ) -> Result<Self::Client, ClientError>
+    where
+        <Self::Client as Client>::ModelProvider: Default,
+    {
+        Self::create_in_region_with_options(
+            api_key,
+            ModelOptions::new(model),
+            TransportOptions::default(),
+            region,
+        )
+    }
+
+    /// Create a client bound to the endpoint of the given region with custom options.
+    fn create_in_region_with_options(
+        api_key: String,
+        model_options: ModelOptions<<Self::Client as Client>::ModelProvider>,
+        transport_options: TransportOptions,
+        region: Region,
+    ) -> Result<Self::Client, ClientError>;
+}
+
+/// Client that routes each request to a regional client based on the residency tag.
+///
+/// Requests without a residency tag go to the default client. Requests tagged with
+/// a region that has no configured client are rejected rather than silently sent
+/// elsewhere.
+pub struct ResidencyRouter<C> {
+    default: C,
+    regional: HashMap<Region, C>,
+}
+
+impl<C: Client> ResidencyRouter<C> {
+    /// Create a router with the client used for untagged requests.
+    pub fn new(default: C) -> Self {
+        Self {
+            default,
+            regional: HashMap::new(),
+        }
+    }
+
+    /// Register the client serving a region.
+    pub fn with_region(mut self, region: Region, client: C) -> Self {
+        self.regional.insert(region, client);
+        self
+    }
+
+    /// Select the client for the current request context.
+    pub fn route(&self) -> Result<&C, ClientError> {
+        match current_residency() {
+            None => Ok(&self.default),
+            Some(region) => {
+                debug!("Routing request to {:?} endpoint", region);
+                self.regional.get(&region).ok_or_else(|| {
+                    ClientError::PolicyViolation(format!(
+                        "No {} endpoint configured for region {:?}",
+                        self.default.provider(),
+                        region
+                    ))
+                })
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl<C: Client> Client for ResidencyRouter<C> {
+    type ModelProvider = C::ModelProvider;
+
+    async fn request(
+        &self,
+        messages: Vec<Message>,
+        tools: Vec<Tool>,
+    ) -> Result<Response, ClientError> {
+        self.route()?.request(messages, tools).await
+    }
+
+    fn model_options(&self) -> &ModelOptions<Self::ModelProvider> {
+        self.default.model_options()
+    }
+
+    fn transport_options(&self) -> &TransportOptions {
+        self.default.transport_options()
+    }
+
+    fn provider(&self) -> &'static str {
+        self.default.provider()
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        self.default.capabilities()
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>, ClientError> {
+        self.route()?.list_models().await
+    }
+}
+
+#[async_trait]
+impl<C: StreamingClient> StreamingClient for ResidencyRouter<C> {
+    async fn request_stream(
+        &self,
+        messages: Vec<Message>,
+        tools: Vec<Tool>,
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<Response, ClientError>> + Send>>, ClientError>
+    {
+        self.route()?.request_stream(messages, tools).await
+    }
+    async fn request_stream_deltas(
+        &self,
+        messages: Vec<Message>,
+        tools: Vec<Tool>,
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<StreamDelta, ClientError>> + Send>>, ClientError>
+    {
+        self.route()?.request_stream_deltas(messages, tools).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{FinishReason, Usage};
+
+    struct NamedClient {
+        name: &'static str,
+        options: ModelOptions<()>,
+        transport: TransportOptions,
+    }
+
+    impl NamedClient {
+        fn new(name: &'static str) -> Self {
+            Self {
+                name,
+                options: ModelOptions::new("test-model"),
+                transport: TransportOptions::default(),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl Client for NamedClient {
+        type ModelProvider = ();
+
+        async fn request(
+            &self,
+            _messages: Vec<Message>,
+            _tools: Vec<Tool>,
+        ) -> Result<Response, ClientError> {
+            Ok(Response {
+                data: vec![Message::Assistant(vec![crate::model::Part::Text {
+                    content: self.name.to_string(),
+                    finished: true,
+                }])],
+                usage: Usage::default(),
+                finish: FinishReason::Stop,
+                alternatives: Vec::new(),
+                model: None,
+                iterations: Vec::new(),
+            })
+        }
+
+        fn model_options(&self) -> &ModelOptions<()> {
+            &self.options
+        }
+
+        fn transport_options(&self) -> &TransportOptions {
+            &self.transport
+        }
+    }
+
+    #[tokio::test]
+    async fn test_residency_routing() {
+        let router = ResidencyRouter::new(NamedClient::new("global"))
+            .with_region(Region::Eu, NamedClient::new("eu"));
+
+        let untagged = router.request(vec![], vec![]).await.unwrap();
+        assert_eq!(untagged.data[0].content().as_deref(), Some("global"));
+
+        let eu = with_residency(Region::Eu, router.request(vec![], vec![]))
+            .await
+            .unwrap();
+        assert_eq!(eu.data[0].content().as_deref(), Some("eu"));
+
+        let us = with_residency(Region::Us, router.request(vec![], vec![])).await;
+        assert!(matches!(us, Err(ClientError::PolicyViolation(_))));
+    }
+
+    #[test]
+    fn test_regional_endpoints() {
+        use crate::providers::{Anthropic, Gemini, OpenAI};
+
+        for region in [Region::Us, Region::Eu] {
+            assert!(OpenAI::create_in_region("key".into(), "gpt-5".into(), region).is_ok());
+            // The Responses API is served from the same regional endpoint.
+            let responses = OpenAI::responses_in_region("key".into(), "gpt-5".into(), region);
+            assert!(format!("{:?}", responses.unwrap())
+                .contains(OpenAI::regional_base_url(region).unwrap()));
+            assert!(Anthropic::regional_base_url(region).is_none());
+            assert!(matches!(
+                Anthropic::create_in_region("key".into(), "claude-sonnet-4-5".into(), region),
+                Err(ClientError::Config(_))
+            ));
+            assert!(Gemini::regional_base_url(region).is_none());
+            assert!(matches!(
+                Gemini::create_in_region("key".into(), "gemini-2.5-pro".into(), region),
+                Err(ClientError::Config(_))
+            ));
+        }
+    }
+}
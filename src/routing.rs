@@ -0,0 +1,870 @@
+//! Composition of clients across providers.
+//!
+//! [`FallbackClient`] wraps an ordered list of clients, which may belong to
+//! different providers, and sends each request to the first one that succeeds:
+//!
+//! ```ignore
+//! use unia::providers::{Anthropic, OpenAI, Provider};
+//! use unia::routing::FallbackClient;
+//!
+//! let client = FallbackClient::new(OpenAI::create(openai_key, "gpt-5".into()))
+//!     .with_fallback(Anthropic::create(anthropic_key, "claude-sonnet-4-5".into()));
+//!
+//! // Sent to Anthropic if OpenAI is rate limited or unavailable.
+//! let response = client.request(messages, vec![]).await?;
+//! ```
+//!
+//! [`Router`] spreads requests over a set of clients according to a
+//! [`RoutingPolicy`], failing over in the same way. It tracks the health of each
+//! client and temporarily ejects one that keeps failing, e.g. because its API key
+//! is exhausted:
+//!
+//! ```ignore
+//! use unia::routing::{Router, RoutingPolicy};
+//!
+//! let router = Router::new(OpenAI::create(key_a, "gpt-5".into()))
+//!     .with_backend(OpenAI::create(key_b, "gpt-5".into()))
+//!     .with_policy(RoutingPolicy::RoundRobin);
+//! ```
+//!
+//! [`RoutingPolicy::Auto`] picks a model per request from the estimated prompt
+//! size, the capabilities the request needs and the [`RoutingTargets`]:
+//!
+//! ```ignore
+//! use unia::routing::{Router, RoutingPolicy, RoutingTargets};
+//!
+//! let router = Router::new(OpenAI::create(key, "gpt-5-mini".into()))
+//!     .with_backend(OpenAI::create(key, "gpt-5".into()))
+//!     .with_backend(Gemini::create(gemini_key, "gemini-2.5-flash".into()))
+//!     .with_policy(RoutingPolicy::Auto)
+//!     .with_targets(
+//!         RoutingTargets::new()
+//!             .with_max_cost(0.01)
+//!             .with_max_latency(Duration::from_secs(5)),
+//!     );
+//! ```
+
+use async_trait::async_trait;
+use futures::Stream;
+use rand::Rng;
+use rmcp::model::Tool;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::{debug, warn};
+
+use crate::client::{BoxedClient, Capabilities, Client, ClientError, ModelInfo, StreamingClient};
+use crate::cost::PriceTable;
+use crate::model::{MediaType, Message, Part, Response, Usage};
+use crate::options::{ModelOptions, TransportOptions};
+use crate::stream::StreamDelta;
+use crate::tokenize::estimate_tokens;
+
+type ResponseStream = Pin<Box<dyn Stream<Item = Result<Response, ClientError>> + Send>>;
+type DeltaStream = Pin<Box<dyn Stream<Item = Result<StreamDelta, ClientError>> + Send>>;
+
+/// Client that fails over to the next of an ordered list of clients.
+///
+/// A request goes to the primary client first. When it fails with an error
+/// accepted by the fallback predicate, by default [`ClientError::is_transient`]
+/// (rate limits, server errors and connection failures), the request is sent to
+/// the next client, and so on. Other errors, and the error of the last client,
+/// are returned as-is.
+///
+/// Streaming requests fail over only while the stream is being opened; errors
+/// after the first event are passed to the caller.
+///
+/// The model and transport options reported by the client are those of the
+/// primary client, without its provider-specific options.
+pub struct FallbackClient {
+    clients: Vec<BoxedClient>,
+    model_options: ModelOptions<()>,
+    transport_options: TransportOptions,
+    capabilities: Capabilities,
+    should_fallback: fn(&ClientError) -> bool,
+}
+
+impl FallbackClient {
+    /// Create a fallback chain starting with the primary client.
+    pub fn new<C: StreamingClient + 'static>(primary: C) -> Self {
+        Self {
+            model_options: primary.model_options().erased(),
+            transport_options: primary.transport_options().clone(),
+            capabilities: primary.capabilities(),
+            clients: vec![BoxedClient::new(primary)],
+            should_fallback: ClientError::is_transient,
+        }
+    }
+
+    /// Append a client to try after the ones already in the chain.
+    pub fn with_fallback<C: StreamingClient + 'static>(mut self, client: C) -> Self {
+        self.clients.push(BoxedClient::new(client));
+        self
+    }
+
+    /// Set which errors move the request on to the next client.
+    pub fn with_fallback_on(mut self, predicate: fn(&ClientError) -> bool) -> Self {
+        self.should_fallback = predicate;
+        self
+    }
+
+    /// Call each client in order until one succeeds or fails with a final error.
+    async fn first_success<'a, T, F, Fut>(&'a self, mut call: F) -> Result<T, ClientError>
+    where
+        F: FnMut(&'a BoxedClient) -> Fut,
+        Fut: Future<Output = Result<T, ClientError>>,
+    {
+        let (last, rest) = self
+            .clients
+            .split_last()
+            .expect("fallback chain holds the primary client");
+        for client in rest {
+            match call(client).await {
+                Err(e) if (self.should_fallback)(&e) => warn!(
+                    "{} ({}) failed, falling back: {}",
+                    client.provider(),
+                    &client.model_options().model,
+                    e
+                ),
+                result => return result,
+            }
+        }
+        call(last).await
+    }
+}
+
+#[async_trait]
+impl Client for FallbackClient {
+    type ModelProvider = ();
+
+    async fn request(
+        &self,
+        messages: Vec<Message>,
+        tools: Vec<Tool>,
+    ) -> Result<Response, ClientError> {
+        self.first_success(|client| client.request(messages.clone(), tools.clone()))
+            .await
+    }
+
+    fn model_options(&self) -> &ModelOptions<()> {
+        &self.model_options
+    }
+
+    fn transport_options(&self) -> &TransportOptions {
+        &self.transport_options
+    }
+
+    fn provider(&self) -> &'static str {
+        self.clients[0].provider()
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        self.capabilities
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>, ClientError> {
+        self.first_success(|client| client.list_models()).await
+    }
+}
+
+#[async_trait]
+impl StreamingClient for FallbackClient {
+    async fn request_stream(
+        &self,
+        messages: Vec<Message>,
+        tools: Vec<Tool>,
+    ) -> Result<ResponseStream, ClientError> {
+        self.first_success(|client| client.request_stream(messages.clone(), tools.clone()))
+            .await
+    }
+
+    async fn request_stream_deltas(
+        &self,
+        messages: Vec<Message>,
+        tools: Vec<Tool>,
+    ) -> Result<DeltaStream, ClientError> {
+        self.first_success(|client| client.request_stream_deltas(messages.clone(), tools.clone()))
+            .await
+    }
+}
+
+/// How a [`Router`] picks the client for a request.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RoutingPolicy {
+    /// Take turns between the clients.
+    #[default]
+    RoundRobin,
+    /// Pick clients at random in proportion to their weight.
+    Weighted,
+    /// Pick the cheapest model whose capabilities cover the request, i.e. tools
+    /// if the request has any and vision if it contains images. Models without a
+    /// price come last.
+    Cheapest,
+    /// Pick the client with the lowest recent latency. Clients without a
+    /// measurement are tried first.
+    LowestLatency,
+    /// Pick the cheapest model that covers the request and meets the
+    /// [`RoutingTargets`]. Besides tools and vision, the estimated prompt must
+    /// fit the model's context window when it is known. The request cost is
+    /// estimated from the prompt size and the expected output. Models missing a
+    /// target are tried after the others, so the targets are preferences
+    /// rather than limits.
+    Auto,
+}
+
+/// Cost and latency targets of [`RoutingPolicy::Auto`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RoutingTargets {
+    /// Highest estimated cost of a request in USD. Models without a price miss
+    /// this target.
+    pub max_cost: Option<f64>,
+    /// Highest recent latency. Models without a measurement meet it.
+    pub max_latency: Option<Duration>,
+    /// Completion tokens assumed when estimating the cost of a request.
+    pub expected_output_tokens: u32,
+}
+
+impl Default for RoutingTargets {
+    fn default() -> Self {
+        Self {
+            max_cost: None,
+            max_latency: None,
+            expected_output_tokens: 500,
+        }
+    }
+}
+
+impl RoutingTargets {
+    /// Create targets without cost or latency limits.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the highest estimated cost of a request in USD.
+    pub fn with_max_cost(mut self, max_cost: f64) -> Self {
+        self.max_cost = Some(max_cost);
+        self
+    }
+
+    /// Set the highest recent latency.
+    pub fn with_max_latency(mut self, max_latency: Duration) -> Self {
+        self.max_latency = Some(max_latency);
+        self
+    }
+
+    /// Set the completion tokens assumed when estimating costs.
+    pub fn with_expected_output_tokens(mut self, tokens: u32) -> Self {
+        self.expected_output_tokens = tokens;
+        self
+    }
+
+    fn are_met(&self, cost: Option<f64>, latency: Option<Duration>) -> bool {
+        let cost_met = self
+            .max_cost
+            .is_none_or(|max| cost.is_some_and(|cost| cost <= max));
+        let latency_met = self
+            .max_latency
+            .is_none_or(|max| latency.is_none_or(|latency| latency <= max));
+        cost_met && latency_met
+    }
+}
+
+/// Smoothing factor of the latency moving average.
+const LATENCY_SMOOTHING: f64 = 0.3;
+
+#[derive(Debug, Default)]
+struct Health {
+    consecutive_failures: u32,
+    ejected_until: Option<Instant>,
+    latency: Option<Duration>,
+}
+
+struct Route {
+    client: BoxedClient,
+    weight: u32,
+    capabilities: Capabilities,
+    health: Mutex<Health>,
+}
+
+impl Route {
+    fn new<C: StreamingClient + 'static>(client: C, weight: u32) -> Self {
+        Self {
+            capabilities: client.capabilities(),
+            client: BoxedClient::new(client),
+            weight,
+            health: Mutex::new(Health::default()),
+        }
+    }
+
+    fn is_ejected(&self, now: Instant) -> bool {
+        let health = self.health.lock().unwrap();
+        health.ejected_until.is_some_and(|until| until > now)
+    }
+
+    fn latency(&self) -> Option<Duration> {
+        self.health.lock().unwrap().latency
+    }
+
+    fn price(&self, prices: &PriceTable) -> Option<f64> {
+        prices
+            .lookup(&self.client.model_options().model)
+            .map(|pricing| pricing.input_per_million + pricing.output_per_million)
+    }
+
+    fn estimated_cost(
+        &self,
+        prices: &PriceTable,
+        prompt_tokens: usize,
+        output_tokens: u32,
+    ) -> Option<f64> {
+        let usage = Usage {
+            prompt_tokens: Some(u32::try_from(prompt_tokens).unwrap_or(u32::MAX)),
+            completion_tokens: Some(output_tokens),
+            ..Default::default()
+        };
+        prices.cost(&self.client.model_options().model, &usage)
+    }
+}
+
+/// Client that selects one of several clients per request.
+///
+/// Clients are ordered by the [`RoutingPolicy`] and the request is sent to the
+/// first one, failing over to the next on errors accepted by the fallback
+/// predicate as in [`FallbackClient`]. A client failing with such errors
+/// several times in a row is ejected for a while and only used again once the
+/// ejection expires, or when every client is ejected.
+///
+/// The model and transport options reported by the router are those of the
+/// first client, without its provider-specific options.
+pub struct Router {
+    routes: Vec<Route>,
+    policy: RoutingPolicy,
+    prices: PriceTable,
+    targets: RoutingTargets,
+    next: AtomicUsize,
+    failure_threshold: u32,
+    ejection: Duration,
+    model_options: ModelOptions<()>,
+    transport_options: TransportOptions,
+    should_fallback: fn(&ClientError) -> bool,
+}
+
+impl Router {
+    /// Create a round robin router starting with one client.
+    pub fn new<C: StreamingClient + 'static>(client: C) -> Self {
+        let mut router = Self {
+            routes: Vec::new(),
+            policy: RoutingPolicy::default(),
+            prices: PriceTable::default(),
+            targets: RoutingTargets::default(),
+            next: AtomicUsize::new(0),
+            failure_threshold: 3,
+            ejection: Duration::from_secs(30),
+            model_options: client.model_options().erased(),
+            transport_options: client.transport_options().clone(),
+            should_fallback: ClientError::is_transient,
+        };
+        router.routes.push(Route::new(client, 1));
+        router
+    }
+
+    /// Add a client with weight 1.
+    pub fn with_backend<C: StreamingClient + 'static>(self, client: C) -> Self {
+        self.with_weighted_backend(client, 1)
+    }
+
+    /// Add a client with a weight for [`RoutingPolicy::Weighted`].
+    pub fn with_weighted_backend<C: StreamingClient + 'static>(
+        mut self,
+        client: C,
+        weight: u32,
+    ) -> Self {
+        self.routes.push(Route::new(client, weight));
+        self
+    }
+
+    /// Set the routing policy.
+    pub fn with_policy(mut self, policy: RoutingPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Set the prices used by [`RoutingPolicy::Cheapest`] and
+    /// [`RoutingPolicy::Auto`].
+    pub fn with_prices(mut self, prices: PriceTable) -> Self {
+        self.prices = prices;
+        self
+    }
+
+    /// Set the targets of [`RoutingPolicy::Auto`].
+    pub fn with_targets(mut self, targets: RoutingTargets) -> Self {
+        self.targets = targets;
+        self
+    }
+
+    /// Eject a client for `duration` after `failures` consecutive failures.
+    pub fn with_ejection(mut self, failures: u32, duration: Duration) -> Self {
+        self.failure_threshold = failures.max(1);
+        self.ejection = duration;
+        self
+    }
+
+    /// Set which errors move the request on to the next client and count
+    /// towards ejection.
+    pub fn with_fallback_on(mut self, predicate: fn(&ClientError) -> bool) -> Self {
+        self.should_fallback = predicate;
+        self
+    }
+
+    /// Whether the client at `index`, in the order added, is currently ejected.
+    pub fn is_ejected(&self, index: usize) -> bool {
+        self.routes
+            .get(index)
+            .is_some_and(|route| route.is_ejected(Instant::now()))
+    }
+
+    /// Indices of the clients to try for a request, in order.
+    fn order(&self, messages: &[Message], tools: &[Tool]) -> Result<Vec<usize>, ClientError> {
+        let mut candidates: Vec<usize> = (0..self.routes.len()).collect();
+        let prompt_tokens = match self.policy {
+            RoutingPolicy::Auto => estimate_tokens(messages, tools),
+            _ => 0,
+        };
+
+        if matches!(self.policy, RoutingPolicy::Cheapest | RoutingPolicy::Auto) {
+            let needs_vision = messages.iter().any(|message| {
+                message.parts().iter().any(|part| {
+                    matches!(
+                        part,
+                        Part::Media {
+                            media_type: MediaType::Image,
+                            ..
+                        }
+                    )
+                })
+            });
+            candidates.retain(|&i| {
+                let capabilities = &self.routes[i].capabilities;
+                let fits = capabilities.max_context_tokens.is_none_or(|max| {
+                    prompt_tokens + self.targets.expected_output_tokens as usize <= max as usize
+                });
+                (tools.is_empty() || capabilities.supports_tools)
+                    && (!needs_vision || capabilities.supports_vision)
+                    && (self.policy != RoutingPolicy::Auto || fits)
+            });
+            if candidates.is_empty() {
+                return Err(ClientError::Config(
+                    "No routed model supports the features the request needs".to_string(),
+                ));
+            }
+        }
+
+        let now = Instant::now();
+        let healthy: Vec<usize> = candidates
+            .iter()
+            .copied()
+            .filter(|&i| !self.routes[i].is_ejected(now))
+            .collect();
+        if !healthy.is_empty() {
+            candidates = healthy;
+        }
+
+        match self.policy {
+            RoutingPolicy::RoundRobin => {
+                let start = self.next.fetch_add(1, Ordering::Relaxed) % candidates.len();
+                candidates.rotate_left(start);
+            }
+            RoutingPolicy::Weighted => {
+                let total: u64 = candidates
+                    .iter()
+                    .map(|&i| u64::from(self.routes[i].weight))
+                    .sum();
+                if total > 0 {
+                    let mut pick = rand::rng().random_range(0..total);
+                    let first = candidates
+                        .iter()
+                        .position(|&i| {
+                            let weight = u64::from(self.routes[i].weight);
+                            if pick < weight {
+                                return true;
+                            }
+                            pick -= weight;
+                            false
+                        })
+                        .unwrap_or(0);
+                    let chosen = candidates.remove(first);
+                    candidates.insert(0, chosen);
+                }
+            }
+            RoutingPolicy::Cheapest => candidates.sort_by(|&a, &b| {
+                let price = |i: usize| self.routes[i].price(&self.prices).unwrap_or(f64::MAX);
+                price(a).total_cmp(&price(b))
+            }),
+            RoutingPolicy::LowestLatency => {
+                candidates.sort_by_key(|&i| self.routes[i].latency().unwrap_or(Duration::ZERO))
+            }
+            RoutingPolicy::Auto => {
+                let output_tokens = self.targets.expected_output_tokens;
+                let mut ranked: Vec<(bool, f64, usize)> = candidates
+                    .iter()
+                    .map(|&i| {
+                        let route = &self.routes[i];
+                        let cost = route.estimated_cost(&self.prices, prompt_tokens, output_tokens);
+                        let met = self.targets.are_met(cost, route.latency());
+                        (!met, cost.unwrap_or(f64::MAX), i)
+                    })
+                    .collect();
+                ranked.sort_by(|a, b| a.0.cmp(&b.0).then(a.1.total_cmp(&b.1)));
+                candidates = ranked.into_iter().map(|(_, _, i)| i).collect();
+            }
+        }
+        Ok(candidates)
+    }
+
+    fn record_success(&self, index: usize, latency: Duration) {
+        let mut health = self.routes[index].health.lock().unwrap();
+        health.consecutive_failures = 0;
+        health.ejected_until = None;
+        health.latency = Some(match health.latency {
+            None => latency,
+            Some(average) => {
+                average.mul_f64(1.0 - LATENCY_SMOOTHING) + latency.mul_f64(LATENCY_SMOOTHING)
+            }
+        });
+    }
+
+    fn record_failure(&self, index: usize) {
+        let route = &self.routes[index];
+        let mut health = route.health.lock().unwrap();
+        health.consecutive_failures += 1;
+        if health.consecutive_failures >= self.failure_threshold {
+            warn!(
+                "Ejecting {} ({}) for {:?} after {} failures",
+                route.client.provider(),
+                route.client.model_options().model,
+                self.ejection,
+                health.consecutive_failures
+            );
+            health.consecutive_failures = 0;
+            health.ejected_until = Some(Instant::now() + self.ejection);
+        }
+    }
+
+    /// Call the clients in routing order until one succeeds or fails with a final error.
+    async fn route<'a, T, F, Fut>(
+        &'a self,
+        messages: &[Message],
+        tools: &[Tool],
+        mut call: F,
+    ) -> Result<T, ClientError>
+    where
+        F: FnMut(&'a BoxedClient) -> Fut,
+        Fut: Future<Output = Result<T, ClientError>>,
+    {
+        let order = self.order(messages, tools)?;
+        let last = order.len() - 1;
+        for (attempt, index) in order.into_iter().enumerate() {
+            let client = &self.routes[index].client;
+            debug!(
+                "Routing request to {} ({})",
+                client.provider(),
+                &client.model_options().model
+            );
+            let started = Instant::now();
+            match call(client).await {
+                Ok(result) => {
+                    self.record_success(index, started.elapsed());
+                    return Ok(result);
+                }
+                Err(e) if (self.should_fallback)(&e) => {
+                    self.record_failure(index);
+                    if attempt == last {
+                        return Err(e);
+                    }
+                    warn!(
+                        "{} ({}) failed, trying the next client: {}",
+                        client.provider(),
+                        &client.model_options().model,
+                        e
+                    );
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        unreachable!("router holds at least one client")
+    }
+}
+
+#[async_trait]
+impl Client for Router {
+    type ModelProvider = ();
+
+    async fn request(
+        &self,
+        messages: Vec<Message>,
+        tools: Vec<Tool>,
+    ) -> Result<Response, ClientError> {
+        self.route(&messages, &tools, |client| {
+            client.request(messages.clone(), tools.clone())
+        })
+        .await
+    }
+
+    fn model_options(&self) -> &ModelOptions<()> {
+        &self.model_options
+    }
+
+    fn transport_options(&self) -> &TransportOptions {
+        &self.transport_options
+    }
+
+    fn provider(&self) -> &'static str {
+        self.routes[0].client.provider()
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        self.routes[0].capabilities
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>, ClientError> {
+        self.route(&[], &[], |client| client.list_models()).await
+    }
+}
+
+#[async_trait]
+impl StreamingClient for Router {
+    async fn request_stream(
+        &self,
+        messages: Vec<Message>,
+        tools: Vec<Tool>,
+    ) -> Result<ResponseStream, ClientError> {
+        self.route(&messages, &tools, |client| {
+            client.request_stream(messages.clone(), tools.clone())
+        })
+        .await
+    }
+
+    async fn request_stream_deltas(
+        &self,
+        messages: Vec<Message>,
+        tools: Vec<Tool>,
+    ) -> Result<DeltaStream, ClientError> {
+        self.route(&messages, &tools, |client| {
+            client.request_stream_deltas(messages.clone(), tools.clone())
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cost::ModelPricing;
+    use crate::model::{FinishReason, Usage};
+    use std::sync::Arc;
+
+    struct StubClient {
+        name: &'static str,
+        status: Option<u16>,
+        calls: Arc<AtomicUsize>,
+        options: ModelOptions<()>,
+        transport: TransportOptions,
+        capabilities: Capabilities,
+    }
+
+    impl StubClient {
+        fn new(name: &'static str, status: Option<u16>, calls: &Arc<AtomicUsize>) -> Self {
+            Self {
+                name,
+                status,
+                calls: calls.clone(),
+                options: ModelOptions::new(name),
+                transport: TransportOptions::default(),
+                capabilities: Capabilities::default(),
+            }
+        }
+
+        fn respond(&self) -> Result<Response, ClientError> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            if let Some(status) = self.status {
+                return Err(ClientError::Status {
+                    status,
+                    message: self.name.to_string(),
+                });
+            }
+            Ok(Response {
+                data: vec![Message::Assistant(vec![Part::Text {
+                    content: self.name.to_string(),
+                    finished: true,
+                }])],
+                usage: Usage::default(),
+                finish: FinishReason::Stop,
+                alternatives: Vec::new(),
+                model: None,
+                iterations: Vec::new(),
+            })
+        }
+    }
+
+    #[async_trait]
+    impl Client for StubClient {
+        type ModelProvider = ();
+
+        async fn request(
+            &self,
+            _messages: Vec<Message>,
+            _tools: Vec<Tool>,
+        ) -> Result<Response, ClientError> {
+            self.respond()
+        }
+
+        fn model_options(&self) -> &ModelOptions<()> {
+            &self.options
+        }
+
+        fn transport_options(&self) -> &TransportOptions {
+            &self.transport
+        }
+
+        fn capabilities(&self) -> Capabilities {
+            self.capabilities
+        }
+    }
+
+    #[async_trait]
+    impl StreamingClient for StubClient {
+        async fn request_stream(
+            &self,
+            _messages: Vec<Message>,
+            _tools: Vec<Tool>,
+        ) -> Result<ResponseStream, ClientError> {
+            let response = self.respond()?;
+            Ok(Box::pin(futures::stream::once(async { Ok(response) })))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fallback_on_transient_errors() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let client = FallbackClient::new(StubClient::new("primary", Some(429), &calls))
+            .with_fallback(StubClient::new("secondary", Some(503), &calls))
+            .with_fallback(StubClient::new("tertiary", None, &calls));
+        assert_eq!(client.model_options().model, "primary");
+
+        let response = client.request(vec![], vec![]).await.unwrap();
+        assert_eq!(response.data[0].content().as_deref(), Some("tertiary"));
+        assert_eq!(calls.swap(0, Ordering::SeqCst), 3);
+
+        let stream = client.request_stream(vec![], vec![]).await;
+        assert!(stream.is_ok());
+        assert_eq!(calls.swap(0, Ordering::SeqCst), 3);
+
+        let client = FallbackClient::new(StubClient::new("primary", Some(400), &calls))
+            .with_fallback(StubClient::new("secondary", None, &calls));
+        let error = client.request(vec![], vec![]).await.unwrap_err();
+        assert_eq!(error.status(), Some(400));
+        assert_eq!(calls.swap(0, Ordering::SeqCst), 1);
+
+        let client = FallbackClient::new(StubClient::new("primary", Some(500), &calls))
+            .with_fallback(StubClient::new("secondary", Some(502), &calls));
+        let error = client.request(vec![], vec![]).await.unwrap_err();
+        assert_eq!(error.status(), Some(502));
+    }
+
+    async fn content<C: Client>(client: &C, tools: Vec<Tool>) -> String {
+        let response = client.request(vec![], tools).await.unwrap();
+        response.data[0].content().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_round_robin_with_ejection() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let router = Router::new(StubClient::new("a", None, &calls))
+            .with_backend(StubClient::new("b", None, &calls));
+        assert_eq!(content(&router, vec![]).await, "a");
+        assert_eq!(content(&router, vec![]).await, "b");
+        assert_eq!(content(&router, vec![]).await, "a");
+
+        let failures = Arc::new(AtomicUsize::new(0));
+        let router = Router::new(StubClient::new("bad", Some(503), &failures))
+            .with_backend(StubClient::new("good", None, &calls))
+            .with_ejection(2, Duration::from_secs(60));
+        for _ in 0..3 {
+            assert_eq!(content(&router, vec![]).await, "good");
+        }
+        assert_eq!(failures.load(Ordering::SeqCst), 2);
+        assert!(router.is_ejected(0));
+        for _ in 0..4 {
+            assert_eq!(content(&router, vec![]).await, "good");
+        }
+        assert_eq!(failures.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_cheapest_capable_model() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let mut premium = StubClient::new("premium", None, &calls);
+        premium.capabilities.supports_tools = true;
+        let router = Router::new(premium)
+            .with_backend(StubClient::new("budget", None, &calls))
+            .with_backend(StubClient::new("unpriced", None, &calls))
+            .with_policy(RoutingPolicy::Cheapest)
+            .with_prices(
+                PriceTable::empty()
+                    .with_price("premium", ModelPricing::new(3.0, 15.0))
+                    .with_price("budget", ModelPricing::new(0.1, 0.4)),
+            );
+
+        assert_eq!(content(&router, vec![]).await, "budget");
+        let tool = Tool::new("search", "", Arc::new(serde_json::Map::new()));
+        assert_eq!(content(&router, vec![tool]).await, "premium");
+    }
+
+    #[tokio::test]
+    async fn test_auto_selection() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let mut large = StubClient::new("large", None, &calls);
+        large.capabilities.max_context_tokens = Some(1_000_000);
+        let mut small = StubClient::new("small", None, &calls);
+        small.capabilities.max_context_tokens = Some(1_000);
+        let mut slow = StubClient::new("slow", None, &calls);
+        slow.capabilities.max_context_tokens = Some(1_000);
+        let router = Router::new(large)
+            .with_backend(small)
+            .with_backend(slow)
+            .with_policy(RoutingPolicy::Auto)
+            .with_prices(
+                PriceTable::empty()
+                    .with_price("large", ModelPricing::new(2.0, 8.0))
+                    .with_price("small", ModelPricing::new(0.5, 2.0))
+                    .with_price("slow", ModelPricing::new(0.1, 0.4)),
+            )
+            .with_targets(
+                RoutingTargets::new()
+                    .with_max_cost(0.01)
+                    .with_max_latency(Duration::from_secs(1)),
+            );
+        router.record_success(2, Duration::from_secs(10));
+
+        let request = |text: String| {
+            let messages = vec![Message::User(vec![Part::Text {
+                content: text,
+                finished: true,
+            }])];
+            router.order(&messages, &[]).unwrap()
+        };
+        // The slow model is the cheapest but misses the latency target.
+        assert_eq!(request("Hi".to_string()), vec![1, 0, 2]);
+        // Too long for the small context windows.
+        assert_eq!(request("word ".repeat(2_000)), vec![0]);
+        // Within the context window but over the cost target for both.
+        let router = router.with_targets(RoutingTargets::new().with_max_cost(0.0001));
+        let messages = vec![Message::User(vec![Part::Text {
+            content: "Hi".to_string(),
+            finished: true,
+        }])];
+        assert_eq!(router.order(&messages, &[]).unwrap(), vec![2, 1, 0]);
+    }
+}
@@ -0,0 +1,171 @@
+//! Stable format of persisted agent runs.
+//!
+//! [`AgentRun`] records are meant to be stored and analyzed by external tools,
+//! so their JSON format is versioned. [`run_schema`] describes the current
+//! version as a JSON schema, which is also checked in as
+//! `schemas/agent_run.v1.json`. [`load_run`] reads records of any earlier
+//! version, migrating them first:
+//!
+//! ```ignore
+//! use unia::schema::load_runs;
+//!
+//! let runs = load_runs(&std::fs::read_to_string("runs.jsonl")?)?;
+//! let failures = runs.iter().filter(|run| !run.is_success()).count();
+//! ```
+//!
+//! Version 0 covers records written before the format was versioned.
+
+use schemars::schema::RootSchema;
+use schemars::schema_for;
+use serde_json::Value;
+
+use crate::agent::AgentRun;
+use crate::client::ClientError;
+use crate::model::{Message, Response};
+
+/// Current version of the [`AgentRun`] format.
+pub const RUN_FORMAT_VERSION: u32 = 1;
+
+/// JSON schema of the current [`AgentRun`] format.
+pub fn run_schema() -> RootSchema {
+    let mut schema = schema_for!(AgentRun);
+    schema.schema.metadata().title = Some(format!("AgentRun v{}", RUN_FORMAT_VERSION));
+    schema
+}
+
+/// JSON schema of a [`Response`].
+pub fn response_schema() -> RootSchema {
+    schema_for!(Response)
+}
+
+/// JSON schema of a [`Message`].
+pub fn message_schema() -> RootSchema {
+    schema_for!(Message)
+}
+
+/// Upgrade a serialized run to the current format.
+///
+/// Records from a newer version than this library supports are rejected.
+pub fn migrate_run(mut value: Value) -> Result<Value, ClientError> {
+    let Some(record) = value.as_object_mut() else {
+        // Not a run; deserializing it reports the error.
+        return Ok(value);
+    };
+    let version = record.get("version").and_then(Value::as_u64).unwrap_or(0);
+    if version > u64::from(RUN_FORMAT_VERSION) {
+        return Err(ClientError::Config(format!(
+            "Run format version {} is newer than the supported version {}",
+            version, RUN_FORMAT_VERSION
+        )));
+    }
+
+    if version < 1 {
+        // Unversioned records could omit `finished`, which reads as a part
+        // still being streamed. Every part of a stored run is complete.
+        for key in ["input", "output"] {
+            if let Some(messages) = record.get_mut(key).and_then(Value::as_array_mut) {
+                for message in messages {
+                    if let Some(parts) = message.get_mut("content") {
+                        finish_parts(parts);
+                    }
+                }
+            }
+        }
+    }
+
+    record.insert("version".to_string(), RUN_FORMAT_VERSION.into());
+    Ok(value)
+}
+
+fn finish_parts(parts: &mut Value) {
+    for part in parts.as_array_mut().into_iter().flatten() {
+        if let Some(data) = part.get_mut("data").and_then(Value::as_object_mut) {
+            data.entry("finished").or_insert(Value::Bool(true));
+            if let Some(parts) = data.get_mut("parts") {
+                finish_parts(parts);
+            }
+        }
+    }
+}
+
+/// Read a run record of any supported version.
+pub fn load_run(json: &str) -> Result<AgentRun, ClientError> {
+    let value = migrate_run(serde_json::from_str(json)?)?;
+    Ok(serde_json::from_value(value)?)
+}
+
+/// Read run records from JSONL, one run per line. Blank lines are skipped.
+pub fn load_runs(jsonl: &str) -> Result<Vec<AgentRun>, ClientError> {
+    jsonl
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(load_run)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{FinishReason, Part};
+    use serde_json::json;
+
+    #[test]
+    fn test_checked_in_schema_is_current() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/schemas/agent_run.v1.json");
+        let schema = serde_json::to_value(run_schema()).unwrap();
+        if std::env::var_os("UNIA_UPDATE_SCHEMA").is_some() {
+            let json = serde_json::to_string_pretty(&schema).unwrap();
+            std::fs::write(path, json + "\n").unwrap();
+        }
+
+        let checked_in: Value = serde_json::from_str(&std::fs::read_to_string(path).unwrap())
+            .expect("schema file is valid JSON");
+        assert_eq!(
+            schema, checked_in,
+            "the AgentRun format changed: bump RUN_FORMAT_VERSION if it is not \
+             backward compatible, then run the tests with UNIA_UPDATE_SCHEMA=1"
+        );
+    }
+
+    #[test]
+    fn test_load_unversioned_run() {
+        let record = json!({
+            "id": "run",
+            "provider": "openai",
+            "model": "gpt-5",
+            "input": [{ "role": "user", "content": [{ "type": "Text", "data": { "content": "Hi" } }] }],
+            "output": [{ "role": "assistant", "content": [{
+                "type": "FunctionResponse",
+                "data": {
+                    "id": "1",
+                    "name": "lookup",
+                    "response": {},
+                    "parts": [{ "type": "Text", "data": { "content": "x" } }]
+                }
+            }] }],
+            "usage": {},
+            "finish": "Stop"
+        });
+
+        let runs = load_runs(&format!("{}\n\n{}\n", record, record)).unwrap();
+        assert_eq!(runs.len(), 2);
+        let run = &runs[0];
+        assert_eq!(run.version, RUN_FORMAT_VERSION);
+        assert_eq!(run.finish, FinishReason::Stop);
+        assert!(matches!(
+            &run.input[0].parts()[0],
+            Part::Text { finished: true, .. }
+        ));
+        assert!(matches!(
+            &run.output[0].parts()[0],
+            Part::FunctionResponse { parts, finished: true, .. }
+                if matches!(parts[0], Part::Text { finished: true, .. })
+        ));
+
+        let newer = json!({ "version": RUN_FORMAT_VERSION + 1 });
+        assert!(matches!(
+            load_run(&newer.to_string()),
+            Err(ClientError::Config(_))
+        ));
+    }
+}
@@ -0,0 +1,421 @@
+//! Agents served as MCP servers.
+//!
+//! An [`AgentServer`] exports an [`Agent`], with its client and tools, as an
+//! rmcp [`ServerHandler`]. Another MCP host, including another unia agent, can
+//! then use it as a tool:
+//!
+//! ```ignore
+//! use rmcp::ServiceExt;
+//! use unia::serve::AgentServer;
+//!
+//! let researcher = Agent::new(client).with_server(search_server);
+//! AgentServer::new(researcher)
+//!     .with_name("researcher")
+//!     .with_description("Research a topic on the web and summarize the findings.")
+//!     .serve(rmcp::transport::stdio())
+//!     .await?
+//!     .waiting()
+//!     .await?;
+//! ```
+//!
+//! The server has a single `chat` tool taking a `message` and an optional
+//! `conversation_id`. Calls without an id start a new conversation, and the
+//! result returns its id, so the host can continue it. Conversations are kept
+//! in a [`MemoryStore`] and listed as `conversation://{id}` resources, which
+//! read as the JSON of the conversation.
+//!
+//! Only conversations started through the server can be continued or read,
+//! even if its store is shared with other code. Turns of the same conversation
+//! run one after another.
+
+use rmcp::model::{
+    AnnotateAble, CallToolRequestParam, CallToolResult, Content, Implementation,
+    ListResourcesResult, ListToolsResult, PaginatedRequestParam, RawResource,
+    ReadResourceRequestParam, ReadResourceResult, ResourceContents, ServerCapabilities, ServerInfo,
+    Tool,
+};
+use rmcp::service::{RequestContext, RoleServer};
+use rmcp::{ErrorData, ServerHandler};
+use serde_json::{json, Value};
+use std::sync::{Arc, Mutex};
+
+use crate::agent::Agent;
+use crate::client::{BoxedClient, Client, ClientError};
+use crate::memory::{InMemoryStore, MemoryStore};
+use crate::model::{Message, Part};
+
+/// URI scheme of conversation resources.
+pub const CONVERSATION_SCHEME: &str = "conversation://";
+
+/// Name of the tool chatting with the agent.
+pub const CHAT_TOOL: &str = "chat";
+
+/// MCP server chatting with an agent.
+pub struct AgentServer<C: Client = BoxedClient> {
+    agent: Arc<Agent<C>>,
+    name: String,
+    description: String,
+    store: Arc<dyn MemoryStore>,
+    /// Conversations started through the server, oldest first, each with a
+    /// lock held while a turn runs.
+    conversations: Mutex<Vec<(String, Arc<tokio::sync::Mutex<()>>)>>,
+}
+
+impl<C: Client> AgentServer<C> {
+    /// Serve an agent, keeping conversations in memory.
+    pub fn new(agent: Agent<C>) -> Self {
+        Self::from_arc(Arc::new(agent))
+    }
+
+    /// Serve a shared agent.
+    pub fn from_arc(agent: Arc<Agent<C>>) -> Self {
+        Self {
+            agent,
+            name: "unia-agent".to_string(),
+            description: "Chat with an AI agent.".to_string(),
+            store: Arc::new(InMemoryStore::new()),
+            conversations: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Set the server name announced to clients.
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+
+    /// Set the description of the `chat` tool, which tells the host's model
+    /// what the agent is for.
+    pub fn with_description(mut self, description: impl Into<String>) -> Self {
+        self.description = description.into();
+        self
+    }
+
+    /// Keep conversations in a store.
+    pub fn with_store(mut self, store: impl MemoryStore + 'static) -> Self {
+        self.store = Arc::new(store);
+        self
+    }
+
+    fn chat_tool(&self) -> Tool {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "message": {
+                    "type": "string",
+                    "description": "Message to send to the agent."
+                },
+                "conversation_id": {
+                    "type": "string",
+                    "description": "Conversation to continue, as returned by an earlier call. Omit to start a new conversation."
+                }
+            },
+            "required": ["message"]
+        });
+        Tool::new(
+            CHAT_TOOL,
+            self.description.clone(),
+            schema.as_object().cloned().unwrap_or_default(),
+        )
+    }
+
+    /// Lock of a conversation started through the server.
+    fn conversation_lock(&self, id: &str) -> Option<Arc<tokio::sync::Mutex<()>>> {
+        self.conversations
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|(known, _)| known == id)
+            .map(|(_, lock)| lock.clone())
+    }
+
+    async fn chat(&self, message: &str, id: Option<&str>) -> Result<CallToolResult, ErrorData> {
+        let Some(id) = id else {
+            let id = uuid::Uuid::new_v4().to_string();
+            let result = self.turn(message, &id).await;
+            if result.as_ref().is_ok_and(|r| r.is_error != Some(true)) {
+                let lock = Arc::new(tokio::sync::Mutex::new(()));
+                self.conversations.lock().unwrap().push((id, lock));
+            }
+            return result.map_err(|e| ErrorData::internal_error(e.to_string(), None));
+        };
+        let lock = self.conversation_lock(id).ok_or_else(|| {
+            ErrorData::invalid_params(format!("Unknown conversation: {}", id), None)
+        })?;
+        let _turn = lock.lock().await;
+        self.turn(message, id)
+            .await
+            .map_err(|e| ErrorData::internal_error(e.to_string(), None))
+    }
+
+    /// Run a turn of a conversation and save it.
+    async fn turn(&self, message: &str, id: &str) -> Result<CallToolResult, ClientError> {
+        let mut conversation = self.store.load_or_create(id).await?;
+        let message = Message::User(vec![Part::Text {
+            content: message.to_string(),
+            finished: true,
+        }]);
+        let response = match self.agent.chat_in(&mut conversation, message).await {
+            Ok(response) => response,
+            Err(e) => return Ok(CallToolResult::error(vec![Content::text(e.to_string())])),
+        };
+        self.store.save(&conversation).await?;
+
+        let reply = response
+            .data
+            .iter()
+            .rev()
+            .find(|m| matches!(m, Message::Assistant(_)))
+            .and_then(Message::content)
+            .unwrap_or_default();
+        let mut result = CallToolResult::success(vec![Content::text(reply.clone())]);
+        result.structured_content = Some(json!({ "reply": reply, "conversation_id": id }));
+        Ok(result)
+    }
+}
+
+impl<C: Client + 'static> ServerHandler for AgentServer<C> {
+    fn get_info(&self) -> ServerInfo {
+        ServerInfo {
+            capabilities: ServerCapabilities::builder()
+                .enable_tools()
+                .enable_resources()
+                .build(),
+            server_info: Implementation {
+                name: self.name.clone(),
+                ..Implementation::from_build_env()
+            },
+            instructions: Some(self.description.clone()),
+            ..Default::default()
+        }
+    }
+
+    async fn list_tools(
+        &self,
+        _request: Option<PaginatedRequestParam>,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ListToolsResult, ErrorData> {
+        Ok(ListToolsResult::with_all_items(vec![self.chat_tool()]))
+    }
+
+    async fn call_tool(
+        &self,
+        request: CallToolRequestParam,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<CallToolResult, ErrorData> {
+        if request.name != CHAT_TOOL {
+            return Err(ErrorData::invalid_params(
+                format!("Unknown tool: {}", request.name),
+                None,
+            ));
+        }
+        let arguments = request.arguments.unwrap_or_default();
+        let message = arguments
+            .get("message")
+            .and_then(Value::as_str)
+            .ok_or_else(|| ErrorData::invalid_params("`message` must be a string", None))?;
+        let id = arguments.get("conversation_id").and_then(Value::as_str);
+        self.chat(message, id).await
+    }
+
+    async fn list_resources(
+        &self,
+        _request: Option<PaginatedRequestParam>,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ListResourcesResult, ErrorData> {
+        let resources = self
+            .conversations
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(id, _)| {
+                let mut resource = RawResource::new(
+                    format!("{}{}", CONVERSATION_SCHEME, id),
+                    format!("Conversation {}", id),
+                );
+                resource.mime_type = Some("application/json".to_string());
+                resource.no_annotation()
+            })
+            .collect();
+        Ok(ListResourcesResult::with_all_items(resources))
+    }
+
+    async fn read_resource(
+        &self,
+        request: ReadResourceRequestParam,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ReadResourceResult, ErrorData> {
+        let not_found = || ErrorData::resource_not_found(request.uri.clone(), None);
+        let id = request
+            .uri
+            .strip_prefix(CONVERSATION_SCHEME)
+            .filter(|id| self.conversation_lock(id).is_some())
+            .ok_or_else(not_found)?;
+        let conversation = self
+            .store
+            .load(id)
+            .await
+            .map_err(|e| ErrorData::internal_error(e.to_string(), None))?
+            .ok_or_else(not_found)?;
+        let text = serde_json::to_string(&conversation)
+            .map_err(|e| ErrorData::internal_error(e.to_string(), None))?;
+        Ok(ReadResourceResult {
+            contents: vec![ResourceContents::TextResourceContents {
+                uri: request.uri.clone(),
+                mime_type: Some("application/json".to_string()),
+                text,
+                meta: None,
+            }],
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcp::MCPServer;
+    use crate::memory::Conversation;
+    use crate::testing::MockClient;
+    use async_trait::async_trait;
+    use rmcp::service::{RoleClient, RunningService};
+    use rmcp::ServiceExt;
+
+    async fn connect<C: Client + 'static>(
+        server: AgentServer<C>,
+    ) -> RunningService<RoleClient, ()> {
+        let (client_transport, server_transport) = tokio::io::duplex(4096);
+        tokio::spawn(async move {
+            if let Ok(running) = server.serve(server_transport).await {
+                let _ = running.waiting().await;
+            }
+        });
+        ().serve(client_transport).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_agent_server() {
+        let client = MockClient::new()
+            .with_text("Hello!")
+            .with_text("Still here.");
+        let server = AgentServer::new(Agent::new(client.clone()))
+            .with_name("greeter")
+            .with_description("Greets people.");
+        let host = connect(server).await;
+        assert_eq!(host.peer_info().unwrap().server_info.name, "greeter");
+
+        let tools = MCPServer::list_tools(&host).await.unwrap();
+        assert_eq!(tools.len(), 1);
+        assert_eq!(tools[0].value.name, CHAT_TOOL);
+        assert_eq!(
+            tools[0].value.description.as_deref(),
+            Some("Greets people.")
+        );
+
+        let call = |arguments: Value| {
+            host.peer().call_tool(CallToolRequestParam {
+                name: CHAT_TOOL.into(),
+                arguments: arguments.as_object().cloned(),
+            })
+        };
+        let first = call(json!({ "message": "Hi" })).await.unwrap();
+        assert_eq!(first.content[0].as_text().unwrap().text, "Hello!");
+        let id = first.structured_content.unwrap()["conversation_id"]
+            .as_str()
+            .unwrap()
+            .to_string();
+        let second = call(json!({ "message": "Still there?", "conversation_id": id }))
+            .await
+            .unwrap();
+        assert_eq!(
+            second.structured_content.unwrap()["reply"],
+            json!("Still here.")
+        );
+        assert_eq!(client.requests()[1].messages.len(), 3);
+        assert!(call(json!({})).await.is_err());
+
+        let resources = MCPServer::list_resources(&host).await.unwrap();
+        assert_eq!(resources.len(), 1);
+        let result = MCPServer::read_resource(&host, &resources[0])
+            .await
+            .unwrap();
+        let ResourceContents::TextResourceContents { text, .. } = &result.value.contents[0] else {
+            panic!("expected text contents");
+        };
+        let conversation: Conversation = serde_json::from_str(text).unwrap();
+        assert_eq!(conversation.id(), id);
+        assert_eq!(conversation.len(), 4);
+    }
+
+    /// Store shared with other code, slow to load so that concurrent turns
+    /// overlap.
+    #[derive(Clone, Default)]
+    struct SharedStore(Arc<InMemoryStore>);
+
+    #[async_trait]
+    impl MemoryStore for SharedStore {
+        async fn load(&self, id: &str) -> Result<Option<Conversation>, ClientError> {
+            let conversation = self.0.load(id).await;
+            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+            conversation
+        }
+
+        async fn save(&self, conversation: &Conversation) -> Result<(), ClientError> {
+            self.0.save(conversation).await
+        }
+
+        async fn delete(&self, id: &str) -> Result<(), ClientError> {
+            self.0.delete(id).await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_agent_server_conversations() {
+        let store = SharedStore::default();
+        let mut other = Conversation::with_id("other");
+        other.push(Message::User(vec![Part::Text {
+            content: "Private".to_string(),
+            finished: true,
+        }]));
+        store.save(&other).await.unwrap();
+
+        let client = MockClient::new()
+            .with_text("First")
+            .with_text("Second")
+            .with_text("Third");
+        let server = AgentServer::new(Agent::new(client.clone())).with_store(store.clone());
+        let host = connect(server).await;
+        let call = |arguments: Value| {
+            host.peer().call_tool(CallToolRequestParam {
+                name: CHAT_TOOL.into(),
+                arguments: arguments.as_object().cloned(),
+            })
+        };
+
+        // Conversations the server did not start can be neither continued nor read.
+        assert!(call(json!({ "message": "Hi", "conversation_id": "other" }))
+            .await
+            .is_err());
+        assert!(host
+            .peer()
+            .read_resource(ReadResourceRequestParam {
+                uri: format!("{}other", CONVERSATION_SCHEME),
+            })
+            .await
+            .is_err());
+        assert_eq!(store.load("other").await.unwrap().unwrap().len(), 1);
+
+        let first = call(json!({ "message": "Hi" })).await.unwrap();
+        let id = first.structured_content.unwrap()["conversation_id"]
+            .as_str()
+            .unwrap()
+            .to_string();
+        // Concurrent turns of a conversation both make it into the store.
+        let (second, third) = tokio::join!(
+            call(json!({ "message": "Two", "conversation_id": id })),
+            call(json!({ "message": "Three", "conversation_id": id })),
+        );
+        assert!(second.is_ok() && third.is_ok());
+        assert_eq!(store.load(&id).await.unwrap().unwrap().len(), 6);
+        assert_eq!(client.requests()[2].messages.len(), 5);
+    }
+}
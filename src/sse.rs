@@ -3,18 +3,34 @@
 //! This module provides generic SSE parsing and stream processing
 //! that can be shared across different LLM providers.
 //!
-//! SSE format:
+//! SSE format: an event is a block of `field: value` lines ended by a blank
+//! line. Lines starting with `:` are comments.
 //! ```text
 //! data: {"key": "value"}
 //!
-//! data: {"another": "event"}
+//! event: content_block_delta
+//! id: 42
+//! data: {"first": "line",
+//! data:  "second": "line"}
 //!
 //! data: [DONE]
 //! ```
+//!
+//! Servers sometimes abort a stream by writing a plain error body, such as a
+//! JSON error object or an HTML error page, instead of an event. Such content is
+//! kept, up to [`MAX_UNFRAMED_BYTES`], and returned as the error of the stream.
+//! Others send an event whose data is an error object, which parsers turn into an
+//! error with [`error_event`].
 
-use futures::stream::{self, Stream, StreamExt};
+use futures::stream::{Stream, StreamExt};
+use serde_json::Value;
+use std::time::Duration;
 
 use crate::client::ClientError;
+use crate::http::truncate_body;
+
+/// Maximum size of non-SSE content kept from a stream, see [`SseDecoder::unframed`].
+pub const MAX_UNFRAMED_BYTES: usize = 4 * 1024;
 
 /// Extension trait for `reqwest::Response` to enable SSE streaming.
 ///
@@ -27,92 +43,367 @@ use crate::client::ClientError;
 ///
 /// let response = client.get("https://api.example.com/stream").send().await?;
 ///
-/// // Get raw SSE data lines
+/// // Get the data of every SSE event
 /// let mut stream = response.sse();
 /// while let Some(result) = stream.next().await {
-///     let line = result?;
-///     println!("SSE data: {}", line);
+///     let data = result?;
+///     println!("SSE data: {}", data);
 /// }
 /// ```
 pub trait SSEResponseExt {
-    /// Convert the response into a stream of raw SSE data lines.
+    /// Convert the response into a stream of SSE events.
+    ///
+    /// Stops when the `[DONE]` marker is encountered or the stream ends. If the
+    /// body ends with content that is not SSE, such as an error object, the
+    /// stream fails with that content as a [`ClientError::ProviderError`].
+    fn sse_events(self) -> impl Stream<Item = Result<SseEvent, ClientError>> + Send;
+
+    /// Convert the response into a stream of SSE event data.
     ///
-    /// Returns the content after `data: ` prefix for each SSE event.
-    /// Stops when `[DONE]` marker is encountered or stream ends.
+    /// Returns the `data:` lines of each SSE event, joined with `\n`.
+    /// Stops when the `[DONE]` marker is encountered or the stream ends.
     fn sse(self) -> impl Stream<Item = Result<String, ClientError>> + Send;
 }
 
 impl SSEResponseExt for reqwest::Response {
+    fn sse_events(self) -> impl Stream<Item = Result<SseEvent, ClientError>> + Send {
+        let mut byte_stream = Box::pin(self.bytes_stream());
+
+        async_stream::try_stream! {
+            let mut decoder = SseDecoder::new();
+            while let Some(chunk) = byte_stream.next().await {
+                let chunk = match chunk {
+                    Ok(chunk) => chunk,
+                    // An error body written before the connection failed explains it better.
+                    Err(e) => Err(decoder.unframed_error().unwrap_or(ClientError::from(e)))?,
+                };
+                for event in decoder.push_events(&chunk) {
+                    yield event;
+                }
+                if decoder.is_done() {
+                    return;
+                }
+            }
+            if let Some(event) = decoder.finish_event() {
+                yield event;
+            }
+            if let Some(error) = decoder.unframed_error() {
+                Err(error)?;
+            }
+        }
+    }
+
     fn sse(self) -> impl Stream<Item = Result<String, ClientError>> + Send {
-        let byte_stream = self.bytes_stream();
-
-        stream::unfold(
-            (Box::pin(byte_stream), String::new(), false),
-            |(mut byte_stream, mut buffer, mut stream_ended)| async move {
-                loop {
-                    if !stream_ended {
-                        match byte_stream.next().await {
-                            Some(Ok(chunk)) => {
-                                if let Ok(s) = std::str::from_utf8(&chunk) {
-                                    buffer.push_str(s);
-                                }
-                            }
-                            Some(Err(e)) => {
-                                return Some((
-                                    Err(ClientError::from(e)),
-                                    (byte_stream, buffer, stream_ended),
-                                ));
-                            }
-                            None => {
-                                stream_ended = true;
-                            }
-                        }
-                    }
+        self.sse_events().map(|event| event.map(|event| event.data))
+    }
+}
+
+/// Error reported by the data of an event, such as
+/// `{"error": {"code": 502, "message": "Upstream error"}}`.
+///
+/// Providers report failures after a stream has started as an event with an
+/// `error` field, since the status has already been sent. The error keeps the
+/// message of the provider, prefixed with its type, status or code if any.
+pub fn error_event(data: &str) -> Option<ClientError> {
+    if !data.contains("\"error\"") {
+        return None;
+    }
+    let value: Value = serde_json::from_str(data).ok()?;
+    let error = value.get("error").filter(|error| !error.is_null())?;
+    let message = match error {
+        Value::String(message) => message.clone(),
+        _ => error
+            .get("message")
+            .and_then(Value::as_str)
+            .map_or_else(|| error.to_string(), str::to_string),
+    };
+    let kind = ["type", "status", "code"]
+        .iter()
+        .find_map(|key| match error.get(*key)? {
+            Value::String(kind) => Some(kind.clone()),
+            Value::Number(code) => Some(code.to_string()),
+            _ => None,
+        });
+    Some(ClientError::ProviderError(match kind {
+        Some(kind) => format!("Stream error ({}): {}", kind, message),
+        None => format!("Stream error: {}", message),
+    }))
+}
+
+/// A Server-Sent Event.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SseEvent {
+    /// Event type from the `event:` field, `None` for the default `message` type.
+    pub event: Option<String>,
+    /// Data lines of the event, joined with `\n`.
+    pub data: String,
+    /// Last event id sent by the server, in this or an earlier event.
+    pub id: Option<String>,
+    /// Reconnection time sent with this event.
+    pub retry: Option<Duration>,
+}
+
+/// Incremental decoder turning raw SSE bytes into events.
+///
+/// Network chunks can end anywhere, including inside a line or a multibyte
+/// character, so bytes are buffered until a full line is available. Feeding the
+/// same body in any chunking yields the same events. Lines may end with `\n`,
+/// `\r\n` or `\r`.
+///
+/// # Example
+/// ```
+/// use unia::sse::SseDecoder;
+///
+/// let mut decoder = SseDecoder::new();
+/// assert!(decoder.push(b"data: {\"text\": \"caf\xc3").is_empty());
+/// assert_eq!(decoder.push(b"\xa9\"}\n\n"), vec!["{\"text\": \"caf\u{e9}\"}"]);
+///
+/// let events = decoder.push_events(b"event: ping\ndata: a\ndata: b\n\n");
+/// assert_eq!(events[0].event.as_deref(), Some("ping"));
+/// assert_eq!(events[0].data, "a\nb");
+///
+/// assert!(decoder.push(b"data: [DONE]\n\n").is_empty());
+/// assert!(decoder.is_done());
+/// ```
+#[derive(Debug)]
+pub struct SseDecoder {
+    buffer: Vec<u8>,
+    /// Length of the buffer prefix already searched for a line break.
+    scanned: usize,
+    done: bool,
+    done_marker: Option<String>,
+    /// Fields of the event being read.
+    event: Option<String>,
+    data: Option<String>,
+    id: Option<String>,
+    retry: Option<Duration>,
+    /// Content that is not SSE, from its first line to the end of the body.
+    unframed: Option<String>,
+}
 
-                    while let Some(pos) = buffer.find('\n') {
-                        let line = buffer[..pos].trim().to_string();
-                        buffer.drain(..=pos);
+impl Default for SseDecoder {
+    fn default() -> Self {
+        Self {
+            buffer: Vec::new(),
+            scanned: 0,
+            done: false,
+            done_marker: Some("[DONE]".to_string()),
+            event: None,
+            data: None,
+            id: None,
+            retry: None,
+            unframed: None,
+        }
+    }
+}
 
-                        if line.is_empty() {
-                            continue;
-                        }
+impl SseDecoder {
+    /// Create an empty decoder stopping at the `[DONE]` marker.
+    pub fn new() -> Self {
+        Self::default()
+    }
 
-                        if let Some(data) = parse_sse_line(&line) {
-                            if is_done_marker(data) {
-                                return None;
-                            }
+    /// Stop at events whose data is `marker` instead of `[DONE]`.
+    pub fn with_done_marker(mut self, marker: impl Into<String>) -> Self {
+        self.done_marker = Some(marker.into());
+        self
+    }
 
-                            return Some((
-                                Ok(data.to_string()),
-                                (byte_stream, buffer, stream_ended),
-                            ));
-                        }
-                    }
+    /// Decode events until the body ends, without a done marker.
+    pub fn without_done_marker(mut self) -> Self {
+        self.done_marker = None;
+        self
+    }
 
-                    if stream_ended {
-                        if !buffer.is_empty() {
-                            let line = buffer.trim().to_string();
-                            buffer.clear();
-                            if !line.is_empty() {
-                                if let Some(data) = parse_sse_line(&line) {
-                                    if !is_done_marker(data) {
-                                        return Some((
-                                            Ok(data.to_string()),
-                                            (byte_stream, buffer, stream_ended),
-                                        ));
-                                    }
-                                }
-                            }
-                        }
-
-                        return None;
-                    }
+    /// Feed a chunk of the body, returning every event it completes.
+    ///
+    /// Input after the done marker is ignored.
+    pub fn push_events(&mut self, chunk: &[u8]) -> Vec<SseEvent> {
+        let mut events = Vec::new();
+        if self.done {
+            return events;
+        }
+        self.buffer.extend_from_slice(chunk);
+
+        while let Some((end, next)) = self.next_line() {
+            let line: Vec<u8> = self.buffer.drain(..next).collect();
+            self.scanned = 0;
+            if let Some(event) = self.process_line(&line[..end]) {
+                events.push(event);
+            }
+            if self.done {
+                self.buffer.clear();
+                return events;
+            }
+        }
+        events
+    }
+
+    /// Feed a chunk of the body, returning the data of every event it completes.
+    ///
+    /// Input after the done marker is ignored.
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<String> {
+        self.push_events(chunk)
+            .into_iter()
+            .map(|event| event.data)
+            .collect()
+    }
+
+    /// Flush an event left without a terminating blank line when the body ends.
+    pub fn finish_event(&mut self) -> Option<SseEvent> {
+        let mut line = std::mem::take(&mut self.buffer);
+        self.scanned = 0;
+        if self.done {
+            return None;
+        }
+        if line.last() == Some(&b'\r') {
+            line.pop();
+        }
+        if let Some(event) = self.process_line(&line) {
+            return Some(event);
+        }
+        self.dispatch()
+    }
+
+    /// Flush the data of an event left without a terminating blank line when
+    /// the body ends.
+    pub fn finish(&mut self) -> Option<String> {
+        self.finish_event().map(|event| event.data)
+    }
+
+    /// Check whether the done marker has been seen.
+    pub fn is_done(&self) -> bool {
+        self.done
+    }
+
+    /// Content that is not SSE, up to [`MAX_UNFRAMED_BYTES`].
+    ///
+    /// A line starting with `{`, `[` or `<` where a field is expected is taken
+    /// for a plain body, such as a JSON error object or an HTML error page, and
+    /// it and every line after it are kept here instead of being decoded.
+    pub fn unframed(&self) -> Option<&str> {
+        self.unframed.as_deref()
+    }
+
+    /// Error carrying the content that is not SSE, if any.
+    fn unframed_error(&self) -> Option<ClientError> {
+        let unframed = self.unframed()?.trim();
+        Some(ClientError::ProviderError(format!(
+            "Stream ended with a non-SSE body: {}",
+            unframed
+        )))
+    }
+
+    fn capture_unframed(&mut self, line: &str) {
+        let unframed = self.unframed.get_or_insert_with(String::new);
+        let room = MAX_UNFRAMED_BYTES.saturating_sub(unframed.len() + 1);
+        if room > 0 {
+            if !unframed.is_empty() {
+                unframed.push('\n');
+            }
+            unframed.push_str(truncate_body(line, Some(room)).0);
+        }
+    }
+
+    /// End of the first complete line in the buffer and start of the next one.
+    fn next_line(&mut self) -> Option<(usize, usize)> {
+        let Some(pos) = self.buffer[self.scanned..]
+            .iter()
+            .position(|&b| b == b'\n' || b == b'\r')
+        else {
+            self.scanned = self.buffer.len();
+            return None;
+        };
+        let pos = self.scanned + pos;
+        if self.buffer[pos] == b'\n' {
+            return Some((pos, pos + 1));
+        }
+        match self.buffer.get(pos + 1) {
+            Some(b'\n') => Some((pos, pos + 2)),
+            Some(_) => Some((pos, pos + 1)),
+            None => {
+                // A `\n` may follow in the next chunk.
+                self.scanned = pos;
+                None
+            }
+        }
+    }
+
+    /// Apply a line without its line break, returning the event it completes.
+    fn process_line(&mut self, line: &[u8]) -> Option<SseEvent> {
+        if self.unframed.is_some() || matches!(line.first(), Some(b'{' | b'[' | b'<')) {
+            self.capture_unframed(&String::from_utf8_lossy(line));
+            return None;
+        }
+        if line.is_empty() {
+            return self.dispatch();
+        }
+        let line = String::from_utf8_lossy(line);
+        let (field, value) = parse_sse_field(&line)?;
+        match field {
+            "event" => self.event = Some(value.to_string()),
+            "data" => match &mut self.data {
+                Some(data) => {
+                    data.push('\n');
+                    data.push_str(value);
                 }
+                None => self.data = Some(value.to_string()),
             },
-        )
+            "id" if !value.contains('\0') => {
+                self.id = (!value.is_empty()).then(|| value.to_string());
+            }
+            "retry" => {
+                if let Ok(millis) = value.parse() {
+                    self.retry = Some(Duration::from_millis(millis));
+                }
+            }
+            _ => {}
+        }
+        None
+    }
+
+    /// Complete the current event. Events without data are dropped.
+    fn dispatch(&mut self) -> Option<SseEvent> {
+        let event = self.event.take();
+        let retry = self.retry.take();
+        let data = self.data.take()?;
+        if self.done_marker.as_deref() == Some(data.trim()) {
+            self.done = true;
+            return None;
+        }
+        Some(SseEvent {
+            event,
+            data,
+            id: self.id.clone(),
+            retry,
+        })
     }
 }
 
+/// Split an SSE line into its field name and value.
+///
+/// Returns `None` for comments. A single space after the colon is not part of
+/// the value, and a line without a colon is a field with an empty value.
+///
+/// # Example
+/// ```
+/// use unia::sse::parse_sse_field;
+///
+/// assert_eq!(parse_sse_field("event: ping"), Some(("event", "ping")));
+/// assert_eq!(parse_sse_field("data:x"), Some(("data", "x")));
+/// assert_eq!(parse_sse_field("data"), Some(("data", "")));
+/// assert_eq!(parse_sse_field(": keep-alive"), None);
+/// ```
+pub fn parse_sse_field(line: &str) -> Option<(&str, &str)> {
+    if line.starts_with(':') {
+        return None;
+    }
+    Some(match line.split_once(':') {
+        Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+        None => (line, ""),
+    })
+}
+
 /// Parse an SSE line to extract the data portion.
 ///
 /// SSE lines are in the format: `data: <content>`
@@ -150,6 +441,8 @@ pub fn is_done_marker(data: &str) -> bool {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::testing::strategies::{chunked, decode_sse, sse_body, unicode_text};
+    use proptest::prelude::*;
 
     #[test]
     fn test_parse_sse_line() {
@@ -170,4 +463,135 @@ mod tests {
         assert!(!is_done_marker("data"));
         assert!(!is_done_marker("{\"key\": \"value\"}"));
     }
+
+    proptest! {
+        #[test]
+        fn test_decoder_any_chunking(
+            (events, chunks) in (proptest::collection::vec(unicode_text(12), 0..6), any::<bool>())
+                .prop_flat_map(|(texts, crlf)| {
+                    let events: Vec<String> = texts
+                        .iter()
+                        .map(|text| serde_json::to_string(text).unwrap())
+                        .collect();
+                    let mut body = sse_body(&events);
+                    if crlf {
+                        body = String::from_utf8(body).unwrap().replace('\n', "\r\n").into_bytes();
+                    }
+                    (Just(events), chunked(body))
+                })
+        ) {
+            prop_assert_eq!(decode_sse(&chunks), events);
+        }
+    }
+
+    #[test]
+    fn test_decoder_stops_at_done() {
+        let mut decoder = SseDecoder::new();
+        assert_eq!(
+            decoder.push(b": comment\ndata: a\n\ndata: [DONE]\n\ndata: b\n\n"),
+            vec!["a"]
+        );
+        assert!(decoder.is_done());
+        assert!(decoder.push(b"data: c\n\n").is_empty());
+        assert_eq!(decoder.finish(), None);
+
+        let mut decoder = SseDecoder::new();
+        assert!(decoder.push(b"data: tail").is_empty());
+        assert_eq!(decoder.finish().as_deref(), Some("tail"));
+
+        let mut decoder = SseDecoder::new().without_done_marker();
+        assert_eq!(decoder.push(b"data: [DONE]\n\n"), vec!["[DONE]"]);
+        assert!(!decoder.is_done());
+    }
+
+    #[test]
+    fn test_decoder_event_fields() {
+        let body = "event: content_block_delta\r\nid: 1\r\nretry: 3000\r\ndata: {\"a\":\r\ndata:1}\r\n\r\n\
+                    event: ping\rdata\r\r\
+                    id\n: ignored\nevent: no data\n\n\
+                    data:  indented\n\n";
+        let mut decoder = SseDecoder::new();
+        let mut events = Vec::new();
+        for byte in body.as_bytes() {
+            events.extend(decoder.push_events(std::slice::from_ref(byte)));
+        }
+        events.extend(decoder.finish_event());
+
+        assert_eq!(
+            events,
+            vec![
+                SseEvent {
+                    event: Some("content_block_delta".to_string()),
+                    data: "{\"a\":\n1}".to_string(),
+                    id: Some("1".to_string()),
+                    retry: Some(Duration::from_millis(3000)),
+                },
+                SseEvent {
+                    event: Some("ping".to_string()),
+                    data: String::new(),
+                    id: Some("1".to_string()),
+                    retry: None,
+                },
+                SseEvent {
+                    event: None,
+                    data: " indented".to_string(),
+                    id: None,
+                    retry: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_decoder_keeps_unframed_body() {
+        let mut decoder = SseDecoder::new();
+        let body = "data: {\"a\":1}\n\n{\"error\": {\n  \"message\": \"upstream failed\"\n}}\n\ndata: [DONE]\n";
+        let events = decoder.push_events(body.as_bytes());
+        assert_eq!(events.len(), 1);
+        assert!(!decoder.is_done());
+        assert_eq!(
+            decoder.unframed(),
+            Some("{\"error\": {\n  \"message\": \"upstream failed\"\n}}\n\ndata: [DONE]")
+        );
+        let ClientError::ProviderError(message) = decoder.unframed_error().unwrap() else {
+            panic!("expected a provider error");
+        };
+        assert!(message.contains("upstream failed"), "{}", message);
+
+        let mut decoder = SseDecoder::new();
+        decoder.push_events(b"<html>");
+        decoder.push_events("é".repeat(MAX_UNFRAMED_BYTES).as_bytes());
+        decoder.push_events(b"\n</html>\n");
+        decoder.finish_event();
+        let unframed = decoder.unframed().unwrap();
+        assert!(unframed.len() <= MAX_UNFRAMED_BYTES && unframed.starts_with("<html>é"));
+        assert!(SseDecoder::new().unframed().is_none());
+    }
+
+    #[test]
+    fn test_error_event() {
+        let message = |data: &str| match error_event(data) {
+            Some(ClientError::ProviderError(message)) => Some(message),
+            _ => None,
+        };
+        assert_eq!(
+            message(
+                r#"{"type":"error","error":{"type":"overloaded_error","message":"Overloaded"}}"#
+            )
+            .as_deref(),
+            Some("Stream error (overloaded_error): Overloaded")
+        );
+        assert_eq!(
+            message(r#"{"error":{"code":500,"message":"Internal error","status":"INTERNAL"}}"#)
+                .as_deref(),
+            Some("Stream error (INTERNAL): Internal error")
+        );
+        assert_eq!(
+            message(r#"{"error":"Rate limited"}"#).as_deref(),
+            Some("Stream error: Rate limited")
+        );
+        assert!(error_event(r#"{"error":null,"choices":[]}"#).is_none());
+        assert!(error_event(r#"{"content":"\"error\""}"#).is_none());
+        assert!(error_event("[DONE]").is_none());
+    }
 }
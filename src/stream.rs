@@ -1,3 +1,1616 @@
 //! Streaming support types and utilities.
+//!
+//! Provider stream parsers emit [`StreamDelta`] events describing incremental
+//! changes to the response. [`ResponseAccumulator`] folds those events into a full
+//! [`Response`], which is what [`StreamingClient::request_stream`] yields, while
+//! [`StreamingClient::request_stream_deltas`] exposes the raw events.
+//!
+//! [`DeltaStreamExt`] shapes delta streams declaratively, e.g. to speak an
+//! answer sentence by sentence without its reasoning:
+//!
+//! ```ignore
+//! use unia::stream::{DeltaStreamExt, StreamDelta};
+//!
+//! let mut sentences = client
+//!     .request_stream_deltas(messages, vec![])
+//!     .await?
+//!     .filter_reasoning()
+//!     .buffer_sentences()
+//!     .until_finish();
+//! while let Some(delta) = sentences.next().await {
+//!     if let StreamDelta::TextDelta { text, .. } = delta? {
+//!         tts.speak(&text).await?;
+//!     }
+//! }
+//! ```
+//!
+//! [`StreamingClient::request_stream`]: crate::client::StreamingClient::request_stream
+//! [`StreamingClient::request_stream_deltas`]: crate::client::StreamingClient::request_stream_deltas
+
+use futures::task::{waker_ref, ArcWake};
+use futures::{Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+use tokio_util::sync::CancellationToken;
+
+use crate::client::ClientError;
+use crate::computer::{ComputerAction, ComputerSafetyCheck};
+use crate::hosted::{Citation, HostedToolCall};
+use crate::model::{FinishReason, MediaType, Message, Part, Response, Usage};
+use crate::options::ReasoningVisibility;
+use crate::repair::finalize_arguments;
 
 pub use crate::sse::{is_done_marker, parse_sse_line};
+
+/// An incremental change to a streamed response.
+///
+/// Part-level deltas carry the index of the part they apply to within the
+/// assistant message. A delta for an index equal to the current number of parts
+/// starts a new part.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StreamDelta {
+    /// Text appended to a text part.
+    TextDelta { index: usize, text: String },
+    /// Reasoning text appended to a reasoning part.
+    ReasoningDelta {
+        index: usize,
+        text: String,
+        signature: Option<String>,
+    },
+    /// Summary text appended to a reasoning part.
+    ReasoningSummaryDelta { index: usize, text: String },
+    /// Fragment of a tool call. `arguments` is a raw JSON fragment.
+    ToolCallDelta {
+        index: usize,
+        id: Option<String>,
+        name: Option<String>,
+        arguments: String,
+        signature: Option<String>,
+    },
+    /// A computer-use action. Unlike tool calls, actions are not streamed in
+    /// fragments and arrive whole.
+    ComputerCall {
+        index: usize,
+        id: Option<String>,
+        action: ComputerAction,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        safety_checks: Vec<ComputerSafetyCheck>,
+        signature: Option<String>,
+    },
+    /// A call of a tool run by the provider, with its results. Arrives whole; a
+    /// later delta with the same index replaces it, e.g. once the results are known.
+    HostedToolCall {
+        index: usize,
+        id: Option<String>,
+        call: HostedToolCall,
+    },
+    /// A source backing the preceding text.
+    Citation { index: usize, citation: Citation },
+    /// Media generated by the model, such as an image. Arrives whole.
+    Media {
+        index: usize,
+        media_type: MediaType,
+        data: String,
+        mime_type: String,
+        uri: Option<String>,
+    },
+    /// The part at the given index is complete.
+    PartFinished { index: usize },
+    /// Updated token usage. Only the reported fields are replaced.
+    Usage(Usage),
+    /// The model generating the response, as reported by the provider.
+    Model { model: String },
+    /// The response is complete.
+    Finish(FinishReason),
+}
+
+/// Folds [`StreamDelta`] events into a [`Response`].
+///
+/// Parts are ordered by the first delta of each, so deltas with sparse or
+/// out-of-order indices still land in one part per index.
+#[derive(Debug, Clone)]
+pub struct ResponseAccumulator {
+    response: Response,
+    /// Position in the message of the part for each delta index.
+    positions: HashMap<usize, usize>,
+}
+
+impl Default for ResponseAccumulator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ResponseAccumulator {
+    /// Create an accumulator holding an empty assistant message.
+    pub fn new() -> Self {
+        Self {
+            response: Response {
+                data: vec![Message::Assistant(vec![])],
+                usage: Usage::default(),
+                finish: FinishReason::Unfinished,
+                alternatives: Vec::new(),
+                model: None,
+                iterations: Vec::new(),
+            },
+            positions: HashMap::new(),
+        }
+    }
+
+    /// Get the response accumulated so far.
+    pub fn response(&self) -> &Response {
+        &self.response
+    }
+
+    /// Consume the accumulator and return the response.
+    pub fn into_response(self) -> Response {
+        self.response
+    }
+
+    /// Number of parts in the accumulated assistant message.
+    pub fn len(&self) -> usize {
+        self.response.data[0].parts().len()
+    }
+
+    /// Check whether no parts have been accumulated yet.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Apply a delta to the accumulated response.
+    pub fn apply(&mut self, delta: StreamDelta) {
+        match delta {
+            StreamDelta::TextDelta { index, text } => {
+                let part = self.part_or_insert(index, || Part::Text {
+                    content: String::new(),
+                    finished: false,
+                });
+                if let Part::Text { content, .. } = part {
+                    content.push_str(&text);
+                }
+            }
+            StreamDelta::ReasoningDelta {
+                index,
+                text,
+                signature,
+            } => {
+                let part = self.part_or_insert(index, || Part::Reasoning {
+                    content: String::new(),
+                    summary: None,
+                    signature: None,
+                    finished: false,
+                });
+                if let Part::Reasoning {
+                    content,
+                    signature: sig,
+                    ..
+                } = part
+                {
+                    content.push_str(&text);
+                    if signature.is_some() {
+                        *sig = signature;
+                    }
+                }
+            }
+            StreamDelta::ReasoningSummaryDelta { index, text } => {
+                let part = self.part_or_insert(index, || Part::Reasoning {
+                    content: String::new(),
+                    summary: None,
+                    signature: None,
+                    finished: false,
+                });
+                if let Part::Reasoning { summary, .. } = part {
+                    summary.get_or_insert_with(String::new).push_str(&text);
+                }
+            }
+            StreamDelta::ToolCallDelta {
+                index,
+                id,
+                name,
+                arguments,
+                signature,
+            } => {
+                let part = self.part_or_insert(index, || Part::FunctionCall {
+                    id: None,
+                    name: String::new(),
+                    arguments: Value::String(String::new()),
+                    signature: None,
+                    raw_arguments: None,
+                    finished: false,
+                });
+                if let Part::FunctionCall {
+                    id: p_id,
+                    name: p_name,
+                    arguments: p_args,
+                    signature: p_sig,
+                    ..
+                } = part
+                {
+                    if id.is_some() {
+                        *p_id = id;
+                    }
+                    if let Some(name) = name {
+                        p_name.push_str(&name);
+                    }
+                    if let Value::String(buffer) = p_args {
+                        buffer.push_str(&arguments);
+                    }
+                    if signature.is_some() {
+                        *p_sig = signature;
+                    }
+                }
+            }
+            StreamDelta::ComputerCall {
+                index,
+                id,
+                action,
+                safety_checks,
+                signature,
+            } => {
+                let call = Part::ComputerCall {
+                    id,
+                    action,
+                    safety_checks,
+                    signature,
+                    finished: false,
+                };
+                self.set_part(index, call);
+            }
+            StreamDelta::HostedToolCall { index, id, call } => {
+                self.set_part(
+                    index,
+                    Part::HostedToolCall {
+                        id,
+                        call,
+                        finished: false,
+                    },
+                );
+            }
+            StreamDelta::Citation { index, citation } => {
+                self.set_part(
+                    index,
+                    Part::Citation {
+                        citation,
+                        finished: false,
+                    },
+                );
+            }
+            StreamDelta::Media {
+                index,
+                media_type,
+                data,
+                mime_type,
+                uri,
+            } => {
+                self.set_part(
+                    index,
+                    Part::Media {
+                        media_type,
+                        data,
+                        mime_type,
+                        uri,
+                        finished: false,
+                    },
+                );
+            }
+            StreamDelta::PartFinished { index } => {
+                if let Some(&position) = self.positions.get(&index) {
+                    finish_part(&mut self.response.data[0].parts_mut()[position]);
+                }
+            }
+            StreamDelta::Usage(usage) => merge_usage(&mut self.response.usage, &usage),
+            StreamDelta::Model { model } => self.response.model = Some(model),
+            StreamDelta::Finish(reason) => {
+                for part in self.response.data[0].parts_mut() {
+                    finish_part(part);
+                }
+                self.response.finish = reason;
+            }
+        }
+    }
+
+    /// Replace the part at `index`, or append it if the index is new.
+    fn set_part(&mut self, index: usize, part: Part) {
+        let parts = self.response.data[0].parts_mut();
+        match self.positions.get(&index) {
+            Some(&position) => parts[position] = part,
+            None => {
+                self.positions.insert(index, parts.len());
+                parts.push(part);
+            }
+        }
+    }
+
+    fn part_or_insert(&mut self, index: usize, create: impl FnOnce() -> Part) -> &mut Part {
+        let parts = self.response.data[0].parts_mut();
+        let position = *self.positions.entry(index).or_insert_with(|| {
+            parts.push(create());
+            parts.len() - 1
+        });
+        &mut parts[position]
+    }
+}
+
+/// Replace the fields of `usage` that are reported in `update`.
+pub(crate) fn merge_usage(usage: &mut Usage, update: &Usage) {
+    if update.prompt_tokens.is_some() {
+        usage.prompt_tokens = update.prompt_tokens;
+    }
+    if update.completion_tokens.is_some() {
+        usage.completion_tokens = update.completion_tokens;
+    }
+    if update.cached_tokens.is_some() {
+        usage.cached_tokens = update.cached_tokens;
+    }
+    if update.cache_creation_tokens.is_some() {
+        usage.cache_creation_tokens = update.cache_creation_tokens;
+    }
+    if update.reasoning_tokens.is_some() {
+        usage.reasoning_tokens = update.reasoning_tokens;
+    }
+}
+
+/// Mark a part as finished, parsing buffered tool call arguments.
+fn finish_part(part: &mut Part) {
+    match part {
+        Part::Text { finished, .. } => *finished = true,
+        Part::Reasoning { finished, .. } => *finished = true,
+        Part::FunctionCall {
+            finished,
+            arguments,
+            raw_arguments,
+            ..
+        } => {
+            *finished = true;
+            if let Value::String(json_str) = arguments {
+                (*arguments, *raw_arguments) = finalize_arguments(json_str);
+            }
+        }
+        Part::FunctionResponse { finished, .. } => *finished = true,
+        Part::Media { finished, .. } => *finished = true,
+        Part::ComputerCall { finished, .. } => *finished = true,
+        Part::ComputerResult { finished, .. } => *finished = true,
+        Part::HostedToolCall { finished, .. } => *finished = true,
+        Part::Citation { finished, .. } => *finished = true,
+    }
+}
+
+/// Turn a stream of deltas into a stream of accumulated responses.
+///
+/// A snapshot is yielded after every delta.
+pub fn accumulate<S>(deltas: S) -> impl Stream<Item = Result<Response, ClientError>> + Send
+where
+    S: Stream<Item = Result<StreamDelta, ClientError>> + Send + 'static,
+{
+    async_stream::try_stream! {
+        let mut deltas = Box::pin(deltas);
+        let mut accumulator = ResponseAccumulator::new();
+
+        while let Some(delta) = deltas.next().await {
+            accumulator.apply(delta?);
+            yield accumulator.response().clone();
+        }
+    }
+}
+
+/// A [`StreamDelta`] for one of several choices generated for the same request.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChoiceDelta {
+    /// Index of the choice the delta applies to.
+    pub choice: usize,
+    /// The delta itself.
+    pub delta: StreamDelta,
+}
+
+/// Folds [`ChoiceDelta`] events into one [`Response`] per choice.
+///
+/// Usage deltas describe the request as a whole and are applied to every choice.
+#[derive(Debug, Clone, Default)]
+pub struct ChoiceAccumulator {
+    choices: Vec<ResponseAccumulator>,
+    usage: Usage,
+}
+
+impl ChoiceAccumulator {
+    /// Create an accumulator without any choices.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Apply a delta to its choice, creating the choice if needed.
+    pub fn apply(&mut self, delta: ChoiceDelta) {
+        if let StreamDelta::Usage(usage) = &delta.delta {
+            merge_usage(&mut self.usage, usage);
+            for choice in &mut self.choices {
+                choice.apply(delta.delta.clone());
+            }
+            return;
+        }
+
+        while self.choices.len() <= delta.choice {
+            let mut choice = ResponseAccumulator::new();
+            choice.apply(StreamDelta::Usage(self.usage.clone()));
+            self.choices.push(choice);
+        }
+        self.choices[delta.choice].apply(delta.delta);
+    }
+
+    /// Get the response accumulated so far for a choice.
+    pub fn response(&self, choice: usize) -> Option<&Response> {
+        self.choices.get(choice).map(ResponseAccumulator::response)
+    }
+
+    /// Get the responses accumulated so far, ordered by choice index.
+    pub fn responses(&self) -> Vec<Response> {
+        self.choices.iter().map(|c| c.response().clone()).collect()
+    }
+
+    /// Consume the accumulator and return the responses, ordered by choice index.
+    pub fn into_responses(self) -> Vec<Response> {
+        self.choices
+            .into_iter()
+            .map(ResponseAccumulator::into_response)
+            .collect()
+    }
+
+    /// Number of choices seen so far.
+    pub fn len(&self) -> usize {
+        self.choices.len()
+    }
+
+    /// Check whether no choice has been seen yet.
+    pub fn is_empty(&self) -> bool {
+        self.choices.is_empty()
+    }
+}
+
+/// Turn a stream of choice deltas into a stream of accumulated responses, one per
+/// choice.
+///
+/// A snapshot of all choices is yielded after every delta.
+pub fn accumulate_choices<S>(
+    deltas: S,
+) -> impl Stream<Item = Result<Vec<Response>, ClientError>> + Send
+where
+    S: Stream<Item = Result<ChoiceDelta, ClientError>> + Send + 'static,
+{
+    async_stream::try_stream! {
+        let mut deltas = Box::pin(deltas);
+        let mut accumulator = ChoiceAccumulator::new();
+
+        while let Some(delta) = deltas.next().await {
+            accumulator.apply(delta?);
+            yield accumulator.responses();
+        }
+    }
+}
+
+/// Stop a stream when `cancel` is triggered.
+///
+/// After cancellation, [`ClientError::StreamCancelled`] is yielded once and the
+/// inner stream is dropped.
+pub fn cancellable<S, T>(
+    stream: S,
+    cancel: CancellationToken,
+) -> impl Stream<Item = Result<T, ClientError>> + Send
+where
+    S: Stream<Item = Result<T, ClientError>> + Send + 'static,
+    T: Send + 'static,
+{
+    async_stream::stream! {
+        let mut stream = Box::pin(stream);
+        loop {
+            tokio::select! {
+                biased;
+                _ = cancel.cancelled() => {
+                    yield Err(ClientError::StreamCancelled);
+                    break;
+                }
+                item = stream.next() => match item {
+                    Some(item) => yield item,
+                    None => break,
+                },
+            }
+        }
+    }
+}
+
+/// Apply a reasoning visibility to every snapshot of a response stream.
+pub(crate) fn with_reasoning_visibility<S>(
+    stream: S,
+    visibility: Option<ReasoningVisibility>,
+) -> impl Stream<Item = Result<Response, ClientError>> + Send
+where
+    S: Stream<Item = Result<Response, ClientError>> + Send,
+{
+    stream.map(move |response| {
+        response.map(|mut response| {
+            response.set_reasoning_visibility(visibility.unwrap_or_default());
+            response
+        })
+    })
+}
+
+/// Apply a reasoning visibility to a stream of deltas.
+///
+/// Reasoning text the visibility does not show is emptied rather than dropped,
+/// so the deltas still open their part and carry its signature. `seal` is given
+/// the hidden text of a part along with each of its signatures, for providers
+/// that need it to send the reasoning back.
+pub(crate) fn with_delta_reasoning_visibility<S, F>(
+    stream: S,
+    visibility: Option<ReasoningVisibility>,
+    mut seal: F,
+) -> impl Stream<Item = Result<StreamDelta, ClientError>> + Send
+where
+    S: Stream<Item = Result<StreamDelta, ClientError>> + Send,
+    F: FnMut(&str, String) -> String + Send,
+{
+    let visibility = visibility.unwrap_or_default();
+    let mut hidden: HashMap<usize, String> = HashMap::new();
+    stream.map(move |delta| {
+        delta.map(|delta| match (visibility, delta) {
+            (ReasoningVisibility::Full, delta) => delta,
+            (
+                _,
+                StreamDelta::ReasoningDelta {
+                    index,
+                    text,
+                    signature,
+                },
+            ) => {
+                let text_so_far = hidden.entry(index).or_default();
+                text_so_far.push_str(&text);
+                StreamDelta::ReasoningDelta {
+                    index,
+                    text: String::new(),
+                    signature: signature.map(|signature| seal(text_so_far, signature)),
+                }
+            }
+            (ReasoningVisibility::Hidden, StreamDelta::ReasoningSummaryDelta { index, .. }) => {
+                StreamDelta::ReasoningDelta {
+                    index,
+                    text: String::new(),
+                    signature: None,
+                }
+            }
+            (_, delta) => delta,
+        })
+    })
+}
+
+/// Turn a stream of accumulated responses into a stream of deltas.
+///
+/// This is the fallback used for clients that only produce snapshots. Only the
+/// first message of each snapshot is considered.
+pub fn deltas_from_snapshots<S>(
+    snapshots: S,
+) -> impl Stream<Item = Result<StreamDelta, ClientError>> + Send
+where
+    S: Stream<Item = Result<Response, ClientError>> + Send + 'static,
+{
+    async_stream::try_stream! {
+        let mut snapshots = Box::pin(snapshots);
+        let mut previous = ResponseAccumulator::new().into_response();
+
+        while let Some(snapshot) = snapshots.next().await {
+            let snapshot = snapshot?;
+            for delta in diff_snapshots(&previous, &snapshot) {
+                yield delta;
+            }
+            previous = snapshot;
+        }
+    }
+}
+
+pub(crate) fn diff_snapshots(previous: &Response, current: &Response) -> Vec<StreamDelta> {
+    let mut deltas = Vec::new();
+    let empty = Vec::new();
+    let old_parts = previous.data.first().map(|m| m.parts()).unwrap_or(&empty);
+    let new_parts = current.data.first().map(|m| m.parts()).unwrap_or(&empty);
+
+    for (index, part) in new_parts.iter().enumerate() {
+        let old = old_parts.get(index);
+        match part {
+            Part::Text { content, .. } => {
+                let seen = match old {
+                    Some(Part::Text { content: c, .. }) => c.len(),
+                    _ => 0,
+                };
+                if content.len() > seen || old.is_none() {
+                    deltas.push(StreamDelta::TextDelta {
+                        index,
+                        text: content.get(seen..).unwrap_or_default().to_string(),
+                    });
+                }
+            }
+            Part::Reasoning {
+                content,
+                summary,
+                signature,
+                ..
+            } => {
+                let (seen, seen_summary, old_sig) = match old {
+                    Some(Part::Reasoning {
+                        content: c,
+                        summary: s,
+                        signature: sig,
+                        ..
+                    }) => (c.len(), s.as_ref().map_or(0, String::len), sig.as_ref()),
+                    _ => (0, 0, None),
+                };
+                if content.len() > seen || old.is_none() || signature.as_ref() != old_sig {
+                    deltas.push(StreamDelta::ReasoningDelta {
+                        index,
+                        text: content.get(seen..).unwrap_or_default().to_string(),
+                        signature: signature.clone(),
+                    });
+                }
+                let summary = summary.as_deref().unwrap_or_default();
+                if summary.len() > seen_summary {
+                    deltas.push(StreamDelta::ReasoningSummaryDelta {
+                        index,
+                        text: summary.get(seen_summary..).unwrap_or_default().to_string(),
+                    });
+                }
+            }
+            Part::FunctionCall {
+                id,
+                name,
+                arguments,
+                signature,
+                ..
+            } => match old {
+                Some(Part::FunctionCall {
+                    id: old_id,
+                    name: old_name,
+                    arguments: old_args,
+                    ..
+                }) => {
+                    let new_name = name.get(old_name.len()..).unwrap_or_default();
+                    let new_args = match (old_args, arguments) {
+                        (Value::String(o), Value::String(n)) => {
+                            n.get(o.len()..).unwrap_or_default()
+                        }
+                        _ => "",
+                    };
+                    if !new_name.is_empty() || !new_args.is_empty() || id != old_id {
+                        deltas.push(StreamDelta::ToolCallDelta {
+                            index,
+                            id: id.clone(),
+                            name: (!new_name.is_empty()).then(|| new_name.to_string()),
+                            arguments: new_args.to_string(),
+                            signature: None,
+                        });
+                    }
+                }
+                _ => deltas.push(StreamDelta::ToolCallDelta {
+                    index,
+                    id: id.clone(),
+                    name: Some(name.clone()),
+                    arguments: match arguments {
+                        Value::String(s) => s.clone(),
+                        Value::Null => String::new(),
+                        other => other.to_string(),
+                    },
+                    signature: signature.clone(),
+                }),
+            },
+            Part::ComputerCall {
+                id,
+                action,
+                safety_checks,
+                signature,
+                ..
+            } if !matches!(old, Some(Part::ComputerCall { action: a, .. }) if a == action) => {
+                deltas.push(StreamDelta::ComputerCall {
+                    index,
+                    id: id.clone(),
+                    action: action.clone(),
+                    safety_checks: safety_checks.clone(),
+                    signature: signature.clone(),
+                });
+            }
+            Part::HostedToolCall { id, call, .. } if !matches!(old, Some(Part::HostedToolCall { call: c, .. }) if c == call) =>
+            {
+                deltas.push(StreamDelta::HostedToolCall {
+                    index,
+                    id: id.clone(),
+                    call: call.clone(),
+                });
+            }
+            Part::Citation { citation, .. } if !matches!(old, Some(Part::Citation { citation: c, .. }) if c == citation) =>
+            {
+                deltas.push(StreamDelta::Citation {
+                    index,
+                    citation: citation.clone(),
+                });
+            }
+            Part::Media {
+                media_type,
+                data,
+                mime_type,
+                uri,
+                ..
+            } if !matches!(old, Some(Part::Media { data: d, uri: u, .. }) if d == data && u == uri) =>
+            {
+                deltas.push(StreamDelta::Media {
+                    index,
+                    media_type: media_type.clone(),
+                    data: data.clone(),
+                    mime_type: mime_type.clone(),
+                    uri: uri.clone(),
+                });
+            }
+            _ => {}
+        }
+
+        if is_finished(part) && !old.is_some_and(is_finished) {
+            deltas.push(StreamDelta::PartFinished { index });
+        }
+    }
+
+    if current.usage != previous.usage {
+        deltas.push(StreamDelta::Usage(current.usage.clone()));
+    }
+
+    if current.finish != previous.finish && current.finish != FinishReason::Unfinished {
+        deltas.push(StreamDelta::Finish(current.finish.clone()));
+    }
+
+    deltas
+}
+
+fn is_finished(part: &Part) -> bool {
+    match part {
+        Part::Text { finished, .. }
+        | Part::Reasoning { finished, .. }
+        | Part::FunctionCall { finished, .. }
+        | Part::FunctionResponse { finished, .. }
+        | Part::Media { finished, .. }
+        | Part::ComputerCall { finished, .. }
+        | Part::ComputerResult { finished, .. }
+        | Part::HostedToolCall { finished, .. }
+        | Part::Citation { finished, .. } => *finished,
+    }
+}
+
+/// Combinators on streams of [`StreamDelta`] events.
+///
+/// Errors are passed through unchanged. Implemented for every delta stream,
+/// such as the one returned by
+/// [`request_stream_deltas`](crate::client::StreamingClient::request_stream_deltas).
+pub trait DeltaStreamExt:
+    Stream<Item = Result<StreamDelta, ClientError>> + Send + Sized + 'static
+{
+    /// Transform the text of every text delta.
+    fn map_text<F>(self, mut f: F) -> impl Stream<Item = Result<StreamDelta, ClientError>> + Send
+    where
+        F: FnMut(String) -> String + Send + 'static,
+    {
+        self.map(move |delta| {
+            delta.map(|delta| match delta {
+                StreamDelta::TextDelta { index, text } => StreamDelta::TextDelta {
+                    index,
+                    text: f(text),
+                },
+                other => other,
+            })
+        })
+    }
+
+    /// Drop reasoning parts.
+    fn filter_reasoning(self) -> impl Stream<Item = Result<StreamDelta, ClientError>> + Send {
+        let mut reasoning = HashSet::new();
+        self.filter(move |delta| {
+            let keep = match delta {
+                Ok(StreamDelta::ReasoningDelta { index, .. })
+                | Ok(StreamDelta::ReasoningSummaryDelta { index, .. }) => {
+                    reasoning.insert(*index);
+                    false
+                }
+                Ok(StreamDelta::PartFinished { index }) => !reasoning.contains(index),
+                _ => true,
+            };
+            std::future::ready(keep)
+        })
+    }
+
+    /// Hold back text until it forms complete sentences.
+    ///
+    /// Shorthand for [`chunk_text`](Self::chunk_text) with
+    /// [`Chunking::sentences`].
+    fn buffer_sentences(self) -> impl Stream<Item = Result<StreamDelta, ClientError>> + Send {
+        self.chunk_text(Chunking::sentences())
+    }
+
+    /// Hold back text until it forms complete chunks, e.g. for text-to-speech.
+    ///
+    /// Every text delta holds exactly one chunk with its trailing whitespace,
+    /// so words are never cut. The rest of a part is released when the part or
+    /// the response finishes, or the stream ends.
+    fn chunk_text(
+        self,
+        chunking: Chunking,
+    ) -> impl Stream<Item = Result<StreamDelta, ClientError>> + Send {
+        async_stream::stream! {
+            let mut deltas = Box::pin(self);
+            let mut buffers: BTreeMap<usize, String> = BTreeMap::new();
+            let flush = |buffers: &mut BTreeMap<usize, String>, index: Option<usize>| {
+                let indices: Vec<usize> = match index {
+                    Some(index) => vec![index],
+                    None => buffers.keys().copied().collect(),
+                };
+                indices
+                    .into_iter()
+                    .filter_map(|index| {
+                        let text = buffers.remove(&index)?;
+                        (!text.is_empty()).then_some(StreamDelta::TextDelta { index, text })
+                    })
+                    .collect::<Vec<_>>()
+            };
+
+            while let Some(delta) = deltas.next().await {
+                match delta {
+                    Ok(StreamDelta::TextDelta { index, text }) => {
+                        let buffer = buffers.entry(index).or_default();
+                        buffer.push_str(&text);
+                        while let Some(end) = chunking.chunk_end(buffer) {
+                            let rest = buffer.split_off(end);
+                            let text = std::mem::replace(buffer, rest);
+                            yield Ok(StreamDelta::TextDelta { index, text });
+                        }
+                    }
+                    Ok(StreamDelta::PartFinished { index }) => {
+                        for delta in flush(&mut buffers, Some(index)) {
+                            yield Ok(delta);
+                        }
+                        yield Ok(StreamDelta::PartFinished { index });
+                    }
+                    Ok(StreamDelta::Finish(reason)) => {
+                        for delta in flush(&mut buffers, None) {
+                            yield Ok(delta);
+                        }
+                        yield Ok(StreamDelta::Finish(reason));
+                    }
+                    other => yield other,
+                }
+            }
+            for delta in flush(&mut buffers, None) {
+                yield Ok(delta);
+            }
+        }
+    }
+
+    /// End the stream after the [`StreamDelta::Finish`] event, ignoring
+    /// anything the provider sends after it.
+    fn until_finish(self) -> impl Stream<Item = Result<StreamDelta, ClientError>> + Send {
+        let mut finished = false;
+        self.take_while(move |delta| {
+            let take = !finished;
+            finished |= matches!(delta, Ok(StreamDelta::Finish(_)));
+            std::future::ready(take)
+        })
+    }
+}
+
+impl<S> DeltaStreamExt for S where
+    S: Stream<Item = Result<StreamDelta, ClientError>> + Send + Sized + 'static
+{
+}
+
+/// Where [`DeltaStreamExt::chunk_text`] splits text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChunkBoundary {
+    /// End of a sentence or line.
+    Sentence,
+    /// Blank line between paragraphs.
+    Paragraph,
+}
+
+/// Text chunking for [`DeltaStreamExt::chunk_text`].
+///
+/// Sentences end at `.`, `!`, `?`, `…`, the Arabic `؟` and the Devanagari `।`
+/// followed by whitespace, at the full-width `。`, `！` and `？` used in Chinese
+/// and Japanese, or at a line break. Closing quotes and brackets stay with
+/// their sentence. A period after a known abbreviation such as `Dr.` or `e.g.`
+/// does not end a sentence, nor does one inside a number such as `3.14`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chunking {
+    pub boundary: ChunkBoundary,
+    /// Minimum length of a chunk in characters. Shorter chunks are merged with
+    /// the following ones.
+    pub min_chars: usize,
+    /// Lowercase words, without their final period, that do not end a
+    /// sentence when followed by one.
+    pub abbreviations: Vec<String>,
+}
+
+/// English abbreviations commonly followed by a capitalized word.
+const ABBREVIATIONS: &[&str] = &[
+    "mr", "mrs", "ms", "dr", "prof", "sr", "jr", "st", "vs", "cf", "approx", "e.g", "i.e",
+];
+
+impl Default for Chunking {
+    fn default() -> Self {
+        Self::sentences()
+    }
+}
+
+impl Chunking {
+    /// Split text into sentences.
+    pub fn sentences() -> Self {
+        Self {
+            boundary: ChunkBoundary::Sentence,
+            min_chars: 0,
+            abbreviations: ABBREVIATIONS.iter().map(|a| a.to_string()).collect(),
+        }
+    }
+
+    /// Split text into paragraphs.
+    pub fn paragraphs() -> Self {
+        Self {
+            boundary: ChunkBoundary::Paragraph,
+            ..Self::sentences()
+        }
+    }
+
+    /// Set the minimum length of a chunk in characters.
+    pub fn with_min_chars(mut self, min_chars: usize) -> Self {
+        self.min_chars = min_chars;
+        self
+    }
+
+    /// Add abbreviations, e.g. `["z.b", "bzw"]` for German text.
+    pub fn with_abbreviations<I, A>(mut self, abbreviations: I) -> Self
+    where
+        I: IntoIterator<Item = A>,
+        A: Into<String>,
+    {
+        self.abbreviations
+            .extend(abbreviations.into_iter().map(|a| a.into().to_lowercase()));
+        self
+    }
+
+    /// Byte offset just past the first complete chunk of `text` and the
+    /// whitespace following it.
+    fn chunk_end(&self, text: &str) -> Option<usize> {
+        let mut chars = text.char_indices().peekable();
+        while let Some((i, c)) = chars.next() {
+            // Whether the chunk may grow with the text that follows.
+            let mut open = true;
+            let end = match (self.boundary, c) {
+                (ChunkBoundary::Sentence, '\n') => {
+                    open = false;
+                    i + 1
+                }
+                (ChunkBoundary::Paragraph, '\n') => {
+                    let rest = &text[i + 1..];
+                    let blank = rest.trim_start_matches([' ', '\t', '\r']);
+                    if !blank.starts_with('\n') {
+                        continue;
+                    }
+                    text.len() - blank.len() + 1
+                }
+                (ChunkBoundary::Sentence, c) if is_sentence_end(c) => {
+                    if c == '.' && self.is_abbreviation(&text[..i]) {
+                        continue;
+                    }
+                    // Include closing quotes and brackets, and further punctuation.
+                    let mut end = i + c.len_utf8();
+                    while let Some(&(j, next)) = chars.peek() {
+                        if is_sentence_end(next) || is_closing(next) {
+                            end = j + next.len_utf8();
+                            chars.next();
+                        } else {
+                            break;
+                        }
+                    }
+                    match chars.peek() {
+                        Some(&(_, next)) if next.is_whitespace() => end,
+                        // Full-width punctuation is not followed by a space.
+                        Some(_) if matches!(c, '。' | '！' | '？') => end,
+                        _ => continue,
+                    }
+                }
+                _ => continue,
+            };
+            let rest = &text[end..];
+            let whitespace = rest.len() - rest.trim_start().len();
+            // Wait for the start of the next chunk so all the whitespace is
+            // attached to this one.
+            if whitespace == rest.len() && open {
+                return None;
+            }
+            let end = end + whitespace;
+            if text[..end].chars().count() >= self.min_chars {
+                return Some(end);
+            }
+        }
+        None
+    }
+
+    /// Whether `text` ends with an abbreviation.
+    fn is_abbreviation(&self, text: &str) -> bool {
+        let word = text
+            .rsplit(char::is_whitespace)
+            .next()
+            .unwrap_or_default()
+            .trim_start_matches(|c: char| !c.is_alphanumeric())
+            .to_lowercase();
+        !word.is_empty() && self.abbreviations.contains(&word)
+    }
+}
+
+fn is_sentence_end(c: char) -> bool {
+    matches!(c, '.' | '!' | '?' | '…' | '؟' | '।' | '。' | '！' | '？')
+}
+
+fn is_closing(c: char) -> bool {
+    matches!(
+        c,
+        '"' | '\'' | ')' | ']' | '”' | '’' | '»' | '」' | '』' | '）'
+    )
+}
+
+/// Split a stream into `consumers` streams that each yield every item.
+///
+/// The source is polled lazily, at the pace of the fastest consumer. Items not yet
+/// taken by slower consumers are buffered per consumer, so one slow consumer does
+/// not hold back the others. Dropping a consumer discards its buffer.
+///
+/// Items must be `Clone`; map errors into a shared type first when broadcasting
+/// response streams:
+///
+/// ```ignore
+/// use futures::TryStreamExt;
+/// use std::sync::Arc;
+///
+/// let stream = client.request_stream(messages, vec![]).await?.map_err(Arc::new);
+/// let [ui, transcript] = <[_; 2]>::try_from(broadcast(stream, 2)).unwrap();
+/// ```
+pub fn broadcast<S>(stream: S, consumers: usize) -> Vec<BroadcastStream<S>>
+where
+    S: Stream,
+    S::Item: Clone,
+{
+    let shared = Arc::new(BroadcastShared {
+        state: Mutex::new(BroadcastState {
+            source: Box::pin(stream),
+            queues: (0..consumers).map(|_| Some(VecDeque::new())).collect(),
+            done: false,
+        }),
+        wakers: Arc::new(WakerSet {
+            wakers: Mutex::new(vec![None; consumers]),
+        }),
+    });
+
+    (0..consumers)
+        .map(|index| BroadcastStream {
+            shared: shared.clone(),
+            index,
+        })
+        .collect()
+}
+
+/// One consumer of a stream split with [`broadcast`].
+pub struct BroadcastStream<S: Stream> {
+    shared: Arc<BroadcastShared<S>>,
+    index: usize,
+}
+
+struct BroadcastShared<S: Stream> {
+    state: Mutex<BroadcastState<S>>,
+    wakers: Arc<WakerSet>,
+}
+
+struct BroadcastState<S: Stream> {
+    source: Pin<Box<S>>,
+    /// Pending items per consumer; `None` once the consumer is dropped.
+    queues: Vec<Option<VecDeque<S::Item>>>,
+    done: bool,
+}
+
+/// Wakes every waiting consumer when the source makes progress, so whichever
+/// consumer runs next can pull the item for all of them.
+struct WakerSet {
+    wakers: Mutex<Vec<Option<Waker>>>,
+}
+
+impl ArcWake for WakerSet {
+    fn wake_by_ref(arc_self: &Arc<Self>) {
+        let wakers: Vec<Waker> = arc_self
+            .wakers
+            .lock()
+            .unwrap()
+            .iter_mut()
+            .filter_map(Option::take)
+            .collect();
+        wakers.into_iter().for_each(Waker::wake);
+    }
+}
+
+impl<S> Stream for BroadcastStream<S>
+where
+    S: Stream,
+    S::Item: Clone,
+{
+    type Item = S::Item;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let index = self.index;
+        let shared = &self.shared;
+        let mut state = shared.state.lock().unwrap();
+
+        if let Some(item) = state.queues[index].as_mut().and_then(VecDeque::pop_front) {
+            return Poll::Ready(Some(item));
+        }
+        if state.done {
+            return Poll::Ready(None);
+        }
+
+        shared.wakers.wakers.lock().unwrap()[index] = Some(cx.waker().clone());
+        let waker = waker_ref(&shared.wakers);
+        let polled = state
+            .source
+            .as_mut()
+            .poll_next(&mut Context::from_waker(&waker));
+
+        match polled {
+            Poll::Ready(Some(item)) => {
+                for (i, queue) in state.queues.iter_mut().enumerate() {
+                    if let (true, Some(queue)) = (i != index, queue) {
+                        queue.push_back(item.clone());
+                    }
+                }
+                drop(state);
+                shared.wakers.wakers.lock().unwrap()[index] = None;
+                WakerSet::wake_by_ref(&shared.wakers);
+                Poll::Ready(Some(item))
+            }
+            Poll::Ready(None) => {
+                state.done = true;
+                drop(state);
+                WakerSet::wake_by_ref(&shared.wakers);
+                Poll::Ready(None)
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl<S: Stream> Drop for BroadcastStream<S> {
+    fn drop(&mut self) {
+        if let Ok(mut state) = self.shared.state.lock() {
+            state.queues[self.index] = None;
+        }
+        if let Ok(mut wakers) = self.shared.wakers.wakers.lock() {
+            wakers[self.index] = None;
+        }
+        // The dropped consumer may have been the one registered with the source.
+        WakerSet::wake_by_ref(&self.shared.wakers);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::strategies::{fragments, interleave, unicode_text};
+    use proptest::prelude::*;
+    use serde_json::json;
+
+    /// Kind and full text of a streamed part. Tool calls stream their text as
+    /// the `q` argument.
+    type PartSpec = (u8, String);
+
+    fn part_deltas(index: usize, kind: u8, fragments: Vec<String>) -> Vec<StreamDelta> {
+        fragments
+            .into_iter()
+            .enumerate()
+            .map(|(i, text)| match kind {
+                0 => StreamDelta::TextDelta { index, text },
+                1 => StreamDelta::ReasoningDelta {
+                    index,
+                    text,
+                    signature: None,
+                },
+                _ => StreamDelta::ToolCallDelta {
+                    index,
+                    id: (i == 0).then(|| format!("call_{}", index)),
+                    name: (i == 0).then(|| "search".to_string()),
+                    arguments: text,
+                    signature: None,
+                },
+            })
+            .collect()
+    }
+
+    /// Parts streamed with their deltas interleaved at random.
+    fn interleaved_parts() -> impl Strategy<Value = (Vec<PartSpec>, Vec<StreamDelta>)> {
+        proptest::collection::vec((0u8..3, unicode_text(12)), 1..5)
+            .prop_map(|specs| {
+                specs
+                    .into_iter()
+                    .map(|(kind, text)| match kind {
+                        2 => (kind, json!({ "q": text }).to_string()),
+                        _ => (kind, text),
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .prop_flat_map(|specs| {
+                let split: Vec<_> = specs
+                    .iter()
+                    .map(|(_, text)| fragments(text.clone()))
+                    .collect();
+                (Just(specs), split)
+            })
+            .prop_flat_map(|(specs, split)| {
+                let sequences = specs
+                    .iter()
+                    .zip(split)
+                    .enumerate()
+                    .map(|(index, ((kind, _), fragments))| part_deltas(index, *kind, fragments))
+                    .collect();
+                (Just(specs), interleave(sequences))
+            })
+    }
+
+    fn delta_index(delta: &StreamDelta) -> Option<usize> {
+        match delta {
+            StreamDelta::TextDelta { index, .. }
+            | StreamDelta::ReasoningDelta { index, .. }
+            | StreamDelta::ToolCallDelta { index, .. } => Some(*index),
+            _ => None,
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn test_accumulator_interleaved_parts((specs, deltas) in interleaved_parts()) {
+            // Parts are ordered by the first delta of each.
+            let mut order = Vec::new();
+            for index in deltas.iter().filter_map(delta_index) {
+                if !order.contains(&index) {
+                    order.push(index);
+                }
+            }
+
+            let mut acc = ResponseAccumulator::new();
+            deltas.into_iter().for_each(|delta| acc.apply(delta));
+            acc.apply(StreamDelta::Finish(FinishReason::Stop));
+
+            let parts = acc.response().data[0].parts();
+            prop_assert_eq!(parts.len(), specs.len());
+            for (part, index) in parts.iter().zip(order) {
+                let (kind, text) = &specs[index];
+                match (kind, part) {
+                    (0, Part::Text { content, finished: true }) => prop_assert_eq!(content, text),
+                    (1, Part::Reasoning { content, finished: true, .. }) => {
+                        prop_assert_eq!(content, text)
+                    }
+                    (2, Part::FunctionCall { id, name, arguments, finished: true, .. }) => {
+                        prop_assert_eq!(id.clone(), Some(format!("call_{}", index)));
+                        prop_assert_eq!(name, "search");
+                        prop_assert_eq!(arguments.to_string(), text.clone());
+                    }
+                    _ => prop_assert!(false, "part {} is {:?}", index, part),
+                }
+            }
+        }
+
+        #[test]
+        fn test_snapshot_roundtrip_any_stream(
+            (_, deltas) in interleaved_parts(),
+            cached in proptest::option::of(0u32..100),
+        ) {
+            let mut deltas = deltas;
+            deltas.push(StreamDelta::Usage(Usage {
+                prompt_tokens: Some(7),
+                ..Default::default()
+            }));
+            deltas.push(StreamDelta::Usage(Usage {
+                cached_tokens: cached,
+                ..Default::default()
+            }));
+            deltas.push(StreamDelta::Finish(FinishReason::ToolCalls));
+
+            let mut expected = ResponseAccumulator::new();
+            deltas.iter().cloned().for_each(|d| expected.apply(d));
+
+            let snapshots = accumulate(futures::stream::iter(deltas.into_iter().map(Ok)));
+            let recovered: Vec<StreamDelta> = futures::executor::block_on(
+                deltas_from_snapshots(snapshots).map(|d| d.unwrap()).collect(),
+            );
+            let mut actual = ResponseAccumulator::new();
+            recovered.into_iter().for_each(|d| actual.apply(d));
+
+            prop_assert_eq!(
+                serde_json::to_value(expected.response()).unwrap(),
+                serde_json::to_value(actual.response()).unwrap()
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_delta_combinators() {
+        let text = |index, text: &str| StreamDelta::TextDelta {
+            index,
+            text: text.to_string(),
+        };
+        let deltas = vec![
+            StreamDelta::ReasoningDelta {
+                index: 0,
+                text: "Thinking.".to_string(),
+                signature: None,
+            },
+            StreamDelta::PartFinished { index: 0 },
+            text(1, "Hello there. How"),
+            text(1, " are you? Fine"),
+            text(1, ".\nBye"),
+            StreamDelta::Finish(FinishReason::Stop),
+            text(1, "ignored"),
+        ];
+
+        let stream = futures::stream::iter(deltas.into_iter().map(Ok))
+            .filter_reasoning()
+            .map_text(|text| text.to_uppercase())
+            .buffer_sentences()
+            .until_finish();
+        let output: Vec<StreamDelta> = stream.map(Result::unwrap).collect().await;
+        assert_eq!(
+            output,
+            vec![
+                text(1, "HELLO THERE. "),
+                text(1, "HOW ARE YOU? "),
+                text(1, "FINE.\n"),
+                text(1, "BYE"),
+                StreamDelta::Finish(FinishReason::Stop),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_chunking() {
+        let chunks = |chunking: &Chunking, text: &str| {
+            let mut text = text.to_string();
+            let mut chunks = Vec::new();
+            while let Some(end) = chunking.chunk_end(&text) {
+                let rest = text.split_off(end);
+                chunks.push(std::mem::replace(&mut text, rest));
+            }
+            chunks.push(text);
+            chunks
+        };
+
+        let sentences = Chunking::sentences();
+        assert_eq!(sentences.chunk_end("Pi is 3.14 exactly"), None);
+        assert_eq!(sentences.chunk_end("He said \"stop.\" Then"), Some(16));
+        assert_eq!(sentences.chunk_end("Done. "), None);
+        assert_eq!(
+            chunks(&sentences, "Dr. Smith said, e.g. this... Really? Yes"),
+            vec!["Dr. Smith said, e.g. this... ", "Really? ", "Yes"]
+        );
+        assert_eq!(
+            chunks(&sentences, "你好。今天天气很好！「走吧。」好"),
+            vec!["你好。", "今天天气很好！", "「走吧。」", "好"]
+        );
+        assert_eq!(
+            chunks(&sentences, "नमस्ते। आप कैसे हैं? ठीक"),
+            vec!["नमस्ते। ", "आप कैसे हैं? ", "ठीक"]
+        );
+        assert_eq!(
+            chunks(
+                &sentences.clone().with_min_chars(10),
+                "Hi. Hello there. Bye"
+            ),
+            vec!["Hi. Hello there. ", "Bye"]
+        );
+        assert_eq!(
+            chunks(
+                &Chunking::sentences().with_abbreviations(["z.B"]),
+                "Obst, z.B. Äpfel. Gut"
+            ),
+            vec!["Obst, z.B. Äpfel. ", "Gut"]
+        );
+
+        let paragraphs = Chunking::paragraphs();
+        assert_eq!(
+            chunks(&paragraphs, "One. Two.\nThree.\n \n\nFour"),
+            vec!["One. Two.\nThree.\n \n\n", "Four"]
+        );
+        assert_eq!(paragraphs.chunk_end("One.\n\n"), None);
+    }
+
+    #[tokio::test]
+    async fn test_cancellable() {
+        let cancel = CancellationToken::new();
+        let source = futures::stream::iter([Ok(1), Ok(2)]).chain(futures::stream::pending());
+        let mut stream = Box::pin(cancellable(source, cancel.clone()));
+
+        assert_eq!(stream.next().await.unwrap().unwrap(), 1);
+        assert_eq!(stream.next().await.unwrap().unwrap(), 2);
+        cancel.cancel();
+        assert!(matches!(
+            stream.next().await,
+            Some(Err(ClientError::StreamCancelled))
+        ));
+        assert!(stream.next().await.is_none());
+    }
+
+    #[test]
+    fn test_choice_accumulator() {
+        let text = |choice, text: &str| ChoiceDelta {
+            choice,
+            delta: StreamDelta::TextDelta {
+                index: 0,
+                text: text.to_string(),
+            },
+        };
+
+        let mut acc = ChoiceAccumulator::new();
+        acc.apply(text(0, "Hello"));
+        acc.apply(text(1, "Hi"));
+        acc.apply(text(0, " there"));
+        acc.apply(ChoiceDelta {
+            choice: 1,
+            delta: StreamDelta::Finish(FinishReason::Stop),
+        });
+        acc.apply(ChoiceDelta {
+            choice: 0,
+            delta: StreamDelta::Usage(Usage {
+                prompt_tokens: Some(5),
+                completion_tokens: Some(4),
+                ..Default::default()
+            }),
+        });
+
+        let responses = acc.into_responses();
+        assert_eq!(responses.len(), 2);
+        assert_eq!(
+            responses[0].data[0].content().as_deref(),
+            Some("Hello there")
+        );
+        assert_eq!(responses[0].finish, FinishReason::Unfinished);
+        assert_eq!(responses[1].data[0].content().as_deref(), Some("Hi"));
+        assert_eq!(responses[1].finish, FinishReason::Stop);
+        assert!(responses.iter().all(|r| r.usage.prompt_tokens == Some(5)));
+    }
+
+    #[test]
+    fn test_accumulator() {
+        let mut acc = ResponseAccumulator::new();
+        acc.apply(StreamDelta::TextDelta {
+            index: 0,
+            text: "Hel".to_string(),
+        });
+        acc.apply(StreamDelta::TextDelta {
+            index: 0,
+            text: "lo".to_string(),
+        });
+        acc.apply(StreamDelta::ToolCallDelta {
+            index: 1,
+            id: Some("call_1".to_string()),
+            name: Some("get_weather".to_string()),
+            arguments: "{\"city\":".to_string(),
+            signature: None,
+        });
+        acc.apply(StreamDelta::ToolCallDelta {
+            index: 1,
+            id: None,
+            name: None,
+            arguments: "\"Tokyo\"}".to_string(),
+            signature: None,
+        });
+        acc.apply(StreamDelta::Finish(FinishReason::ToolCalls));
+
+        let response = acc.into_response();
+        assert_eq!(response.finish, FinishReason::ToolCalls);
+        let parts = response.data[0].parts();
+        assert!(matches!(&parts[0], Part::Text { content, finished: true } if content == "Hello"));
+        match &parts[1] {
+            Part::FunctionCall {
+                id,
+                name,
+                arguments,
+                finished,
+                ..
+            } => {
+                assert_eq!(id.as_deref(), Some("call_1"));
+                assert_eq!(name, "get_weather");
+                assert_eq!(arguments, &json!({ "city": "Tokyo" }));
+                assert!(finished);
+            }
+            other => panic!("Expected function call, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_roundtrip() {
+        let deltas = vec![
+            StreamDelta::ReasoningDelta {
+                index: 0,
+                text: "Think".to_string(),
+                signature: None,
+            },
+            StreamDelta::PartFinished { index: 0 },
+            StreamDelta::TextDelta {
+                index: 1,
+                text: "Answer".to_string(),
+            },
+            StreamDelta::TextDelta {
+                index: 1,
+                text: " done".to_string(),
+            },
+            StreamDelta::Media {
+                index: 2,
+                media_type: MediaType::Image,
+                data: "iVBORw0KGgo=".to_string(),
+                mime_type: "image/png".to_string(),
+                uri: None,
+            },
+            StreamDelta::Usage(Usage {
+                prompt_tokens: Some(3),
+                completion_tokens: Some(5),
+                ..Default::default()
+            }),
+            StreamDelta::Finish(FinishReason::Stop),
+        ];
+
+        let snapshots = accumulate(futures::stream::iter(deltas.clone().into_iter().map(Ok)));
+        let recovered: Vec<StreamDelta> = deltas_from_snapshots(snapshots)
+            .map(|d| d.unwrap())
+            .collect()
+            .await;
+
+        let mut expected = ResponseAccumulator::new();
+        deltas.into_iter().for_each(|d| expected.apply(d));
+        let mut actual = ResponseAccumulator::new();
+        recovered.into_iter().for_each(|d| actual.apply(d));
+
+        assert_eq!(
+            serde_json::to_value(expected.response()).unwrap(),
+            serde_json::to_value(actual.response()).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_broadcast() {
+        let mut consumers = broadcast(futures::stream::iter(1..=3), 3);
+        let dropped = consumers.pop().unwrap();
+        drop(dropped);
+        let slow = consumers.pop().unwrap();
+        let fast = consumers.pop().unwrap();
+
+        let fast_items: Vec<i32> = fast.collect().await;
+        let slow_items: Vec<i32> = slow.collect().await;
+
+        assert_eq!(fast_items, vec![1, 2, 3]);
+        assert_eq!(slow_items, vec![1, 2, 3]);
+    }
+
+    #[tokio::test]
+    async fn test_broadcast_concurrent_consumers() {
+        let (tx, rx) = futures::channel::mpsc::unbounded::<u32>();
+        let consumers = broadcast(rx, 2);
+
+        let handles: Vec<_> = consumers
+            .into_iter()
+            .map(|c| tokio::spawn(c.collect::<Vec<u32>>()))
+            .collect();
+
+        for i in 0..100 {
+            tx.unbounded_send(i).unwrap();
+            tokio::task::yield_now().await;
+        }
+        drop(tx);
+
+        for handle in handles {
+            assert_eq!(handle.await.unwrap(), (0..100).collect::<Vec<u32>>());
+        }
+    }
+}
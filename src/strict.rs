@@ -0,0 +1,367 @@
+//! Strict-mode JSON schemas.
+//!
+//! OpenAI only guarantees that generated arguments match a schema in strict mode,
+//! which accepts a subset of JSON schema: every object must list all of its
+//! properties as required and set `additionalProperties: false`, and several
+//! keywords are rejected. Schemas generated by `schemars` do not follow these
+//! rules. [`strict_schema`] rewrites a schema into the strict subset:
+//!
+//! - objects get `additionalProperties: false` and every property is required;
+//!   properties that were optional become nullable instead
+//! - `oneOf` becomes `anyOf`, and single-element `allOf` wrappers are inlined
+//! - annotations and validations strict mode does not accept, such as `default`,
+//!   `uniqueItems` or numeric `format`s, are removed
+//!
+//! Constructs without a strict equivalent, such as maps with arbitrary keys or
+//! properties accepting any value, are reported as a [`StrictSchemaError`] with
+//! the location in the schema.
+//!
+//! With [`ModelOptions::strict_tools`](crate::options::ModelOptions::strict_tools),
+//! tool definitions are converted before they are sent:
+//!
+//! ```ignore
+//! use unia::strict::strict_schema_for;
+//!
+//! let schema = strict_schema_for::<Invoice>()?;
+//! ```
+
+use rmcp::model::Tool;
+use schemars::JsonSchema;
+use serde_json::{json, Map, Value};
+use thiserror::Error;
+use tracing::warn;
+
+use crate::client::ClientError;
+
+/// Keywords strict mode rejects that cannot be dropped without changing which
+/// values the schema accepts.
+const UNSUPPORTED: &[&str] = &[
+    "not",
+    "if",
+    "then",
+    "else",
+    "patternProperties",
+    "unevaluatedProperties",
+    "propertyNames",
+    "dependentRequired",
+    "dependentSchemas",
+    "unevaluatedItems",
+    "prefixItems",
+];
+
+/// Keywords strict mode rejects that only annotate or narrow a schema.
+const DROPPED: &[&str] = &[
+    "$schema",
+    "default",
+    "examples",
+    "uniqueItems",
+    "minProperties",
+    "maxProperties",
+    "contains",
+    "minContains",
+    "maxContains",
+];
+
+/// String formats accepted in strict mode.
+const STRING_FORMATS: &[&str] = &[
+    "date-time",
+    "time",
+    "date",
+    "duration",
+    "email",
+    "hostname",
+    "ipv4",
+    "ipv6",
+    "uuid",
+];
+
+/// A schema construct without a strict-mode equivalent.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("Schema is not supported in strict mode at '{path}': {reason}")]
+pub struct StrictSchemaError {
+    /// JSON pointer to the offending schema, empty for the root.
+    pub path: String,
+    pub reason: String,
+}
+
+impl From<StrictSchemaError> for ClientError {
+    fn from(error: StrictSchemaError) -> Self {
+        ClientError::Config(error.to_string())
+    }
+}
+
+/// Rewrite a JSON schema into the strict-mode subset. The root must be an object.
+pub fn strict_schema(schema: &Value) -> Result<Value, StrictSchemaError> {
+    let mut schema = schema.clone();
+    make_strict(&mut schema, "")?;
+    if !is_object(&schema) {
+        return Err(error("", "the root schema must be an object"));
+    }
+    Ok(schema)
+}
+
+/// Strict-mode schema of a type.
+pub fn strict_schema_for<T: JsonSchema>() -> Result<Value, StrictSchemaError> {
+    let schema =
+        serde_json::to_value(schemars::schema_for!(T)).map_err(|e| error("", &e.to_string()))?;
+    strict_schema(&schema)
+}
+
+/// Strict-mode parameters of a tool, or `None` if its schema cannot be converted.
+pub(crate) fn strict_tool_parameters(tool: &Tool) -> Option<Value> {
+    match strict_schema(&Value::Object((*tool.input_schema).clone())) {
+        Ok(schema) => Some(schema),
+        Err(e) => {
+            warn!("Sending tool {} without strict mode: {}", tool.name, e);
+            None
+        }
+    }
+}
+
+fn error(path: &str, reason: &str) -> StrictSchemaError {
+    StrictSchemaError {
+        path: path.to_string(),
+        reason: reason.to_string(),
+    }
+}
+
+fn is_object(schema: &Value) -> bool {
+    schema.get("type").and_then(Value::as_str) == Some("object")
+        || schema.get("properties").is_some()
+}
+
+fn make_strict(schema: &mut Value, path: &str) -> Result<(), StrictSchemaError> {
+    let Value::Object(map) = schema else {
+        return Err(error(path, "schemas accepting any value are not supported"));
+    };
+
+    for keyword in UNSUPPORTED {
+        if map.contains_key(*keyword) {
+            return Err(error(path, &format!("`{}` is not supported", keyword)));
+        }
+    }
+    for keyword in DROPPED {
+        map.remove(*keyword);
+    }
+    let string_format = map.get("type").and_then(Value::as_str) == Some("string")
+        && map
+            .get("format")
+            .and_then(Value::as_str)
+            .is_some_and(|format| STRING_FORMATS.contains(&format));
+    if !string_format {
+        map.remove("format");
+    }
+
+    if let Some(all_of) = map.remove("allOf") {
+        match all_of {
+            Value::Array(mut schemas) if schemas.len() == 1 => {
+                if let Value::Object(inner) = schemas.remove(0) {
+                    for (key, value) in inner {
+                        map.entry(key).or_insert(value);
+                    }
+                }
+                return make_strict(schema, path);
+            }
+            _ => return Err(error(path, "`allOf` with several schemas is not supported")),
+        }
+    }
+    if let Some(one_of) = map.remove("oneOf") {
+        map.insert("anyOf".to_string(), one_of);
+    }
+
+    for key in ["definitions", "$defs"] {
+        if let Some(Value::Object(definitions)) = map.get_mut(key) {
+            for (name, definition) in definitions.iter_mut() {
+                make_strict(definition, &format!("{}/{}/{}", path, key, name))?;
+            }
+        }
+    }
+    if let Some(Value::Array(schemas)) = map.get_mut("anyOf") {
+        for (i, inner) in schemas.iter_mut().enumerate() {
+            make_strict(inner, &format!("{}/anyOf/{}", path, i))?;
+        }
+    }
+    match map.get_mut("items") {
+        Some(Value::Array(_)) => {
+            return Err(error(path, "tuple `items` are not supported"));
+        }
+        Some(items) => make_strict(items, &format!("{}/items", path))?,
+        None => {}
+    }
+
+    let is_object = is_object(schema);
+    let Value::Object(map) = schema else {
+        unreachable!()
+    };
+    if is_object {
+        make_strict_object(map, path)?;
+    } else if !["type", "$ref", "anyOf", "enum", "const"]
+        .iter()
+        .any(|key| map.contains_key(*key))
+    {
+        return Err(error(path, "schemas accepting any value are not supported"));
+    }
+    Ok(())
+}
+
+fn make_strict_object(map: &mut Map<String, Value>, path: &str) -> Result<(), StrictSchemaError> {
+    match map.get("additionalProperties") {
+        None | Some(Value::Bool(false)) => {}
+        Some(_) => {
+            return Err(error(
+                path,
+                "objects with arbitrary keys (`additionalProperties`) are not supported",
+            ))
+        }
+    }
+    map.insert("additionalProperties".to_string(), Value::Bool(false));
+
+    let required: Vec<String> = map
+        .get("required")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|name| name.as_str().map(str::to_string))
+        .collect();
+    let properties = map
+        .entry("properties")
+        .or_insert_with(|| Value::Object(Map::new()));
+    let Value::Object(properties) = properties else {
+        return Err(error(path, "`properties` must be an object"));
+    };
+    for (name, property) in properties.iter_mut() {
+        make_strict(property, &format!("{}/properties/{}", path, name))?;
+        if !required.contains(name) {
+            make_nullable(property);
+        }
+    }
+    let names = properties.keys().cloned().map(Value::String).collect();
+    map.insert("required".to_string(), Value::Array(names));
+    Ok(())
+}
+
+/// Let a schema also accept `null`, which strict mode uses for optional values.
+fn make_nullable(schema: &mut Value) {
+    let null = Value::String("null".to_string());
+    let simple = schema.get("enum").is_none() && schema.get("const").is_none();
+    match schema.get_mut("type") {
+        Some(Value::Array(types)) if types.contains(&null) => return,
+        Some(Value::Array(types)) if simple => {
+            types.push(null);
+            return;
+        }
+        Some(kind @ Value::String(_)) if simple => {
+            *kind = Value::Array(vec![kind.clone(), null]);
+            return;
+        }
+        _ => {}
+    }
+    if let Some(Value::Array(schemas)) = schema.get("anyOf") {
+        if schemas.iter().any(|s| s.get("type") == Some(&null)) {
+            return;
+        }
+    }
+    *schema = json!({ "anyOf": [schema.clone(), { "type": "null" }] });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[allow(dead_code)]
+    #[derive(JsonSchema)]
+    struct Address {
+        street: String,
+        zip: Option<String>,
+    }
+
+    #[allow(dead_code)]
+    #[derive(JsonSchema)]
+    enum Kind {
+        Personal,
+        Business { vat: String },
+    }
+
+    /// An invoice.
+    #[allow(dead_code)]
+    #[derive(JsonSchema)]
+    struct Invoice {
+        number: u32,
+        #[serde(default)]
+        notes: Vec<String>,
+        /// Billing address.
+        address: Address,
+        shipping: Option<Address>,
+        kind: Kind,
+    }
+
+    #[test]
+    fn test_strict_schema_for() {
+        let schema = strict_schema_for::<Invoice>().unwrap();
+        assert!(schema.get("$schema").is_none());
+        assert_eq!(schema["additionalProperties"], false);
+        assert_eq!(
+            schema["required"],
+            json!(["address", "kind", "notes", "number", "shipping"])
+        );
+        assert!(schema["properties"]["number"].get("format").is_none());
+        assert_eq!(
+            schema["properties"]["notes"]["type"],
+            json!(["array", "null"])
+        );
+        assert_eq!(
+            schema["properties"]["address"],
+            json!({ "description": "Billing address.", "$ref": "#/definitions/Address" })
+        );
+        assert!(schema["properties"]["kind"].get("$ref").is_some());
+
+        let address = &schema["definitions"]["Address"];
+        assert_eq!(address["additionalProperties"], false);
+        assert_eq!(address["required"], json!(["street", "zip"]));
+        assert_eq!(
+            address["properties"]["zip"]["type"],
+            json!(["string", "null"])
+        );
+        assert!(schema["definitions"]["Kind"].get("anyOf").is_some());
+        assert!(schema["definitions"]["Kind"].get("oneOf").is_none());
+    }
+
+    #[test]
+    fn test_unsupported_schemas() {
+        #[allow(dead_code)]
+        #[derive(JsonSchema)]
+        struct Tags {
+            tags: HashMap<String, String>,
+        }
+        assert_eq!(
+            strict_schema_for::<Tags>().unwrap_err().path,
+            "/properties/tags"
+        );
+
+        #[allow(dead_code)]
+        #[derive(JsonSchema)]
+        struct Any {
+            value: serde_json::Value,
+        }
+        let err = strict_schema_for::<Any>().unwrap_err();
+        assert_eq!(err.path, "/properties/value");
+        assert!(err.reason.contains("any value"));
+
+        assert_eq!(
+            strict_schema(&json!({ "type": "string" }))
+                .unwrap_err()
+                .path,
+            ""
+        );
+        assert_eq!(
+            strict_schema(&json!({ "type": "object" })).unwrap(),
+            json!({
+                "type": "object",
+                "properties": {},
+                "required": [],
+                "additionalProperties": false
+            })
+        );
+    }
+}
@@ -0,0 +1,304 @@
+//! Synthetic conversation generation.
+//!
+//! A [`SyntheticGenerator`] renders a [`PromptTemplate`] with values drawn from a
+//! set of variation axes, sends the resulting prompts through
+//! [`BatchExt::request_batch`], and returns one conversation per successful
+//! response. [`dedup_by_similarity`] removes near-duplicates using embeddings.
+//!
+//! ```ignore
+//! use unia::synth::{dedup_by_similarity, PromptTemplate, SyntheticGenerator};
+//!
+//! let conversations = SyntheticGenerator::new(PromptTemplate::new(
+//!     "Write a {tone} customer question about {product}.",
+//! ))
+//! .with_axis("tone", ["polite", "frustrated", "confused"])
+//! .with_axis("product", ["billing", "shipping", "returns"])
+//! .with_count(100)
+//! .generate(&client)
+//! .await?;
+//!
+//! let unique = dedup_by_similarity(&embedder, conversations, 0.95).await?;
+//! ```
+
+use itertools::Itertools;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use std::collections::HashMap;
+use tracing::warn;
+
+use crate::batch::{BatchExt, DEFAULT_CONCURRENCY};
+use crate::client::{Client, ClientError};
+use crate::embeddings::{cosine_similarity, EmbeddingsClient};
+use crate::model::{Message, Part};
+
+/// Prompt template with `{name}` placeholders.
+#[derive(Debug, Clone)]
+pub struct PromptTemplate {
+    template: String,
+}
+
+impl PromptTemplate {
+    /// Create a template.
+    pub fn new(template: impl Into<String>) -> Self {
+        Self {
+            template: template.into(),
+        }
+    }
+
+    /// Substitute every placeholder with its value.
+    ///
+    /// Fails if the template references a variable that is not provided.
+    pub fn render(&self, variables: &HashMap<String, String>) -> Result<String, ClientError> {
+        let mut rendered = String::with_capacity(self.template.len());
+        let mut rest = self.template.as_str();
+
+        while let Some(start) = rest.find('{') {
+            rendered.push_str(&rest[..start]);
+            let Some(len) = rest[start..].find('}') else {
+                break;
+            };
+            let name = &rest[start + 1..start + len];
+            let value = variables.get(name).ok_or_else(|| {
+                ClientError::Config(format!("Missing template variable: {}", name))
+            })?;
+            rendered.push_str(value);
+            rest = &rest[start + len + 1..];
+        }
+        rendered.push_str(rest);
+
+        Ok(rendered)
+    }
+}
+
+/// Generates diverse conversations from a prompt template and variation axes.
+#[derive(Debug, Clone)]
+pub struct SyntheticGenerator {
+    template: PromptTemplate,
+    axes: Vec<(String, Vec<String>)>,
+    count: usize,
+    concurrency: usize,
+    seed: Option<u64>,
+}
+
+impl SyntheticGenerator {
+    /// Create a generator for a template.
+    pub fn new(template: PromptTemplate) -> Self {
+        Self {
+            template,
+            axes: Vec::new(),
+            count: 10,
+            concurrency: DEFAULT_CONCURRENCY,
+            seed: None,
+        }
+    }
+
+    /// Add a variation axis filling the `{name}` placeholder.
+    pub fn with_axis<I, S>(mut self, name: impl Into<String>, values: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.axes
+            .push((name.into(), values.into_iter().map(Into::into).collect()));
+        self
+    }
+
+    /// Set the number of conversations to generate.
+    pub fn with_count(mut self, count: usize) -> Self {
+        self.count = count;
+        self
+    }
+
+    /// Set the number of concurrent requests.
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency;
+        self
+    }
+
+    /// Seed the variation sampling for reproducible prompts.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Render the prompts that will be sent.
+    ///
+    /// Combinations of axis values are shuffled and then cycled through, so every
+    /// combination is used once before any is repeated.
+    pub fn prompts(&self) -> Result<Vec<String>, ClientError> {
+        let mut combinations: Vec<Vec<&String>> = self
+            .axes
+            .iter()
+            .map(|(_, values)| values.iter())
+            .multi_cartesian_product()
+            .collect();
+        if combinations.is_empty() {
+            combinations.push(Vec::new());
+        }
+
+        let mut rng = match self.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_os_rng(),
+        };
+        combinations.shuffle(&mut rng);
+
+        combinations
+            .iter()
+            .cycle()
+            .take(self.count)
+            .map(|combination| {
+                let variables = self
+                    .axes
+                    .iter()
+                    .zip(combination)
+                    .map(|((name, _), value)| (name.clone(), (*value).clone()))
+                    .collect();
+                self.template.render(&variables)
+            })
+            .collect()
+    }
+
+    /// Generate conversations, each consisting of the rendered prompt and the response.
+    ///
+    /// Failed requests are logged and skipped, so fewer than `count` conversations
+    /// may be returned.
+    pub async fn generate<C: Client>(&self, client: &C) -> Result<Vec<Vec<Message>>, ClientError> {
+        let prompts: Vec<Vec<Message>> = self
+            .prompts()?
+            .into_iter()
+            .map(|prompt| {
+                vec![Message::User(vec![Part::Text {
+                    content: prompt,
+                    finished: true,
+                }])]
+            })
+            .collect();
+
+        let results = client
+            .request_batch(prompts.clone(), vec![], self.concurrency)
+            .await;
+
+        Ok(prompts
+            .into_iter()
+            .zip(results)
+            .filter_map(|(mut conversation, result)| match result {
+                Ok(response) => {
+                    conversation.extend(response.data);
+                    Some(conversation)
+                }
+                Err(e) => {
+                    warn!("Synthetic generation request failed: {}", e);
+                    None
+                }
+            })
+            .collect())
+    }
+}
+
+/// Remove conversations whose final message is too similar to an earlier one.
+///
+/// The last message of each conversation is embedded; a conversation is dropped if
+/// its cosine similarity to any kept conversation is at least `threshold`.
+pub async fn dedup_by_similarity<E: EmbeddingsClient>(
+    embedder: &E,
+    conversations: Vec<Vec<Message>>,
+    threshold: f32,
+) -> Result<Vec<Vec<Message>>, ClientError> {
+    let texts = conversations
+        .iter()
+        .map(|c| c.last().and_then(|m| m.content()).unwrap_or_default())
+        .collect();
+    let embeddings = embedder.embed_batched(texts).await?;
+
+    let mut kept: Vec<(Vec<Message>, Vec<f32>)> = Vec::new();
+    for (conversation, embedding) in conversations.into_iter().zip(embeddings) {
+        if kept
+            .iter()
+            .all(|(_, other)| cosine_similarity(&embedding, other) < threshold)
+        {
+            kept.push((conversation, embedding));
+        }
+    }
+
+    Ok(kept
+        .into_iter()
+        .map(|(conversation, _)| conversation)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+
+    #[test]
+    fn test_template_render() {
+        let template = PromptTemplate::new("A {tone} note about {topic}.");
+        let variables = HashMap::from([
+            ("tone".to_string(), "short".to_string()),
+            ("topic".to_string(), "rust".to_string()),
+        ]);
+        assert_eq!(
+            template.render(&variables).unwrap(),
+            "A short note about rust."
+        );
+
+        let missing = HashMap::from([("tone".to_string(), "short".to_string())]);
+        assert!(matches!(
+            template.render(&missing),
+            Err(ClientError::Config(_))
+        ));
+    }
+
+    #[test]
+    fn test_prompts_cover_all_combinations() {
+        let generator = SyntheticGenerator::new(PromptTemplate::new("{a}{b}"))
+            .with_axis("a", ["1", "2"])
+            .with_axis("b", ["x", "y"])
+            .with_count(6)
+            .with_seed(7);
+
+        let prompts = generator.prompts().unwrap();
+        assert_eq!(prompts.len(), 6);
+        let unique: std::collections::HashSet<_> = prompts[..4].iter().collect();
+        assert_eq!(unique.len(), 4);
+        assert_eq!(prompts[4..], prompts[..2]);
+    }
+
+    struct KeywordEmbedder;
+
+    #[async_trait]
+    impl EmbeddingsClient for KeywordEmbedder {
+        async fn embed(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>, ClientError> {
+            Ok(texts
+                .iter()
+                .map(|t| {
+                    vec![
+                        t.contains("cat") as u8 as f32,
+                        t.contains("dog") as u8 as f32,
+                    ]
+                })
+                .collect())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dedup_by_similarity() {
+        let conversations = ["a cat", "another cat", "a dog"]
+            .iter()
+            .map(|t| {
+                vec![Message::Assistant(vec![Part::Text {
+                    content: t.to_string(),
+                    finished: true,
+                }])]
+            })
+            .collect();
+
+        let unique = dedup_by_similarity(&KeywordEmbedder, conversations, 0.9)
+            .await
+            .unwrap();
+        let texts: Vec<String> = unique.iter().map(|c| c[0].content().unwrap()).collect();
+        assert_eq!(texts, vec!["a cat", "a dog"]);
+    }
+}
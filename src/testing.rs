@@ -0,0 +1,17 @@
+//! Helpers for testing code built on this crate.
+//!
+//! - [`mock`]: a scripted [`MockClient`] for agent and application tests.
+//! - [`replay`]: an [`HttpReplay`] transport recording real provider traffic to
+//!   fixtures and replaying it offline.
+//! - [`strategies`]: [`proptest`] strategies for stream parsers.
+//!
+//! Enable the `testing` feature to use it outside this crate.
+
+pub mod mock;
+pub mod replay;
+#[cfg(test)]
+pub(crate) mod server;
+pub mod strategies;
+
+pub use mock::MockClient;
+pub use replay::{HttpReplay, ReplayMode};
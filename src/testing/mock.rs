@@ -0,0 +1,413 @@
+//! A scripted client for tests.
+//!
+//! [`MockClient`] answers requests from a script of responses and records what
+//! it was sent, so agent loops can be tested without a provider:
+//!
+//! ```ignore
+//! use unia::testing::MockClient;
+//!
+//! let client = MockClient::new()
+//!     .with_tool_call("get_weather", json!({ "city": "Paris" }))
+//!     .with_text("It is sunny in Paris.");
+//! let agent = Agent::new(client.clone()).with_server(weather);
+//!
+//! let response = agent.chat(messages).await?;
+//! assert_eq!(client.requests().len(), 2);
+//! ```
+
+use async_trait::async_trait;
+use futures::{Stream, StreamExt};
+use rmcp::model::Tool;
+use serde_json::Value;
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::client::{Capabilities, Client, ClientError, StreamingClient};
+use crate::model::{FinishReason, Message, Part, Response, Usage};
+use crate::options::{ModelOptions, TransportOptions};
+use crate::stream::{accumulate, diff_snapshots, ResponseAccumulator, StreamDelta};
+
+/// A request received by a [`MockClient`].
+#[derive(Debug, Clone)]
+pub struct MockRequest {
+    pub messages: Vec<Message>,
+    pub tools: Vec<Tool>,
+}
+
+/// Client answering requests from a script.
+///
+/// Each request, streamed or not, takes the next scripted outcome. Once the
+/// script is exhausted, requests fail with [`ClientError::ProviderError`].
+/// Clones share the script and the recorded requests.
+#[derive(Clone)]
+pub struct MockClient {
+    script: Arc<Mutex<VecDeque<Result<Response, ClientError>>>>,
+    requests: Arc<Mutex<Vec<MockRequest>>>,
+    options: ModelOptions<()>,
+    transport: TransportOptions,
+    capabilities: Capabilities,
+    stream_delay: Duration,
+}
+
+impl Default for MockClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MockClient {
+    /// Create a client with an empty script.
+    pub fn new() -> Self {
+        Self {
+            script: Arc::new(Mutex::new(VecDeque::new())),
+            requests: Arc::new(Mutex::new(Vec::new())),
+            options: ModelOptions::new("mock"),
+            transport: TransportOptions::default(),
+            capabilities: Capabilities {
+                supports_tools: true,
+                supports_vision: true,
+                supports_streaming: true,
+                supports_system_prompt: true,
+                max_context_tokens: None,
+                supports_json_mode: true,
+            },
+            stream_delay: Duration::ZERO,
+        }
+    }
+
+    /// Create a client answering with the given responses in order.
+    pub fn with_responses(responses: impl IntoIterator<Item = Response>) -> Self {
+        responses.into_iter().fold(Self::new(), |client, response| {
+            client.with_response(response)
+        })
+    }
+
+    /// Append a response to the script.
+    pub fn with_response(self, response: Response) -> Self {
+        self.script.lock().unwrap().push_back(Ok(response));
+        self
+    }
+
+    /// Append a text reply to the script.
+    pub fn with_text(self, text: impl Into<String>) -> Self {
+        self.with_response(Response {
+            data: vec![Message::Assistant(vec![Part::Text {
+                content: text.into(),
+                finished: true,
+            }])],
+            usage: Usage::default(),
+            finish: FinishReason::Stop,
+            alternatives: Vec::new(),
+            model: None,
+            iterations: Vec::new(),
+        })
+    }
+
+    /// Append a turn calling one tool to the script.
+    ///
+    /// Calls are given the ids `call_1`, `call_2`, ... in script order.
+    pub fn with_tool_call(self, name: impl Into<String>, arguments: Value) -> Self {
+        let id = format!("call_{}", self.script.lock().unwrap().len() + 1);
+        self.with_response(Response {
+            data: vec![Message::Assistant(vec![Part::FunctionCall {
+                id: Some(id),
+                name: name.into(),
+                arguments,
+                signature: None,
+                raw_arguments: None,
+                finished: true,
+            }])],
+            usage: Usage::default(),
+            finish: FinishReason::ToolCalls,
+            alternatives: Vec::new(),
+            model: None,
+            iterations: Vec::new(),
+        })
+    }
+
+    /// Append a failed request to the script.
+    pub fn with_error(self, error: ClientError) -> Self {
+        self.script.lock().unwrap().push_back(Err(error));
+        self
+    }
+
+    /// Set the model options reported by the client.
+    pub fn with_model_options(mut self, options: ModelOptions<()>) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Set the capabilities reported by the client. All are enabled by default.
+    pub fn with_capabilities(mut self, capabilities: Capabilities) -> Self {
+        self.capabilities = capabilities;
+        self
+    }
+
+    /// Wait before each delta of a streamed response, to simulate a slow provider.
+    pub fn with_stream_delay(mut self, delay: Duration) -> Self {
+        self.stream_delay = delay;
+        self
+    }
+
+    /// Requests received so far, in order.
+    pub fn requests(&self) -> Vec<MockRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    /// Number of scripted outcomes not yet used.
+    pub fn remaining(&self) -> usize {
+        self.script.lock().unwrap().len()
+    }
+
+    fn next(&self, messages: Vec<Message>, tools: Vec<Tool>) -> Result<Response, ClientError> {
+        self.requests
+            .lock()
+            .unwrap()
+            .push(MockRequest { messages, tools });
+        self.script.lock().unwrap().pop_front().unwrap_or_else(|| {
+            Err(ClientError::ProviderError(
+                "No more mock responses".to_string(),
+            ))
+        })
+    }
+}
+
+/// Split a response into the deltas a provider would stream for it.
+///
+/// Text and reasoning arrive word by word and tool call arguments in a few
+/// fragments, so consumers see partial parts as they would live.
+pub fn stream_deltas(response: &Response) -> Vec<StreamDelta> {
+    let empty = ResponseAccumulator::new().into_response();
+    diff_snapshots(&empty, response)
+        .into_iter()
+        .flat_map(|delta| match delta {
+            StreamDelta::TextDelta { index, text } => split_words(&text)
+                .into_iter()
+                .map(|text| StreamDelta::TextDelta { index, text })
+                .collect(),
+            StreamDelta::ReasoningDelta {
+                index,
+                text,
+                signature,
+            } => {
+                let words = split_words(&text);
+                let last = words.len() - 1;
+                words
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, text)| StreamDelta::ReasoningDelta {
+                        index,
+                        text,
+                        signature: (i == last).then(|| signature.clone()).flatten(),
+                    })
+                    .collect()
+            }
+            StreamDelta::ToolCallDelta {
+                index,
+                id,
+                name,
+                arguments,
+                signature,
+            } => {
+                let mut deltas = vec![StreamDelta::ToolCallDelta {
+                    index,
+                    id,
+                    name,
+                    arguments: String::new(),
+                    signature,
+                }];
+                let chars: Vec<char> = arguments.chars().collect();
+                deltas.extend(chars.chunks(8).map(|chunk| StreamDelta::ToolCallDelta {
+                    index,
+                    id: None,
+                    name: None,
+                    arguments: chunk.iter().collect(),
+                    signature: None,
+                }));
+                deltas
+            }
+            other => vec![other],
+        })
+        .collect()
+}
+
+/// Split text after each space, keeping at least one piece.
+fn split_words(text: &str) -> Vec<String> {
+    let words: Vec<String> = text.split_inclusive(' ').map(str::to_string).collect();
+    if words.is_empty() {
+        vec![String::new()]
+    } else {
+        words
+    }
+}
+
+/// Stream the deltas of a response, waiting `delay` before each one.
+pub(crate) fn simulate_stream(
+    response: &Response,
+    delay: Duration,
+) -> Pin<Box<dyn Stream<Item = Result<StreamDelta, ClientError>> + Send>> {
+    Box::pin(
+        futures::stream::iter(stream_deltas(response)).then(move |delta| async move {
+            if !delay.is_zero() {
+                tokio::time::sleep(delay).await;
+            }
+            Ok(delta)
+        }),
+    )
+}
+
+#[async_trait]
+impl Client for MockClient {
+    type ModelProvider = ();
+
+    async fn request(
+        &self,
+        messages: Vec<Message>,
+        tools: Vec<Tool>,
+    ) -> Result<Response, ClientError> {
+        self.next(messages, tools)
+    }
+
+    fn model_options(&self) -> &ModelOptions<()> {
+        &self.options
+    }
+
+    fn transport_options(&self) -> &TransportOptions {
+        &self.transport
+    }
+
+    fn provider(&self) -> &'static str {
+        "mock"
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        self.capabilities
+    }
+}
+
+#[async_trait]
+impl StreamingClient for MockClient {
+    async fn request_stream(
+        &self,
+        messages: Vec<Message>,
+        tools: Vec<Tool>,
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<Response, ClientError>> + Send>>, ClientError>
+    {
+        let deltas = self.request_stream_deltas(messages, tools).await?;
+        Ok(Box::pin(accumulate(deltas)))
+    }
+
+    async fn request_stream_deltas(
+        &self,
+        messages: Vec<Message>,
+        tools: Vec<Tool>,
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<StreamDelta, ClientError>> + Send>>, ClientError>
+    {
+        let response = self.next(messages, tools)?;
+        Ok(simulate_stream(&response, self.stream_delay))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::TryStreamExt;
+    use serde_json::json;
+
+    fn user(text: &str) -> Vec<Message> {
+        vec![Message::User(vec![Part::Text {
+            content: text.to_string(),
+            finished: true,
+        }])]
+    }
+
+    #[tokio::test]
+    async fn test_mock_client() {
+        let client = MockClient::new()
+            .with_tool_call("get_weather", json!({ "city": "Paris" }))
+            .with_text("It is sunny in Paris.")
+            .with_error(ClientError::StreamCancelled);
+
+        let call = client.request(user("Weather?"), vec![]).await.unwrap();
+        assert_eq!(call.finish, FinishReason::ToolCalls);
+
+        let snapshots: Vec<Response> = client
+            .request_stream(user("Go on"), vec![])
+            .await
+            .unwrap()
+            .try_collect()
+            .await
+            .unwrap();
+        // One snapshot per word, the part being finished, and the finish reason.
+        assert_eq!(snapshots.len(), 7);
+        assert_eq!(snapshots[1].data[0].content().as_deref(), Some("It is "));
+        let last = snapshots.last().unwrap();
+        assert_eq!(
+            last.data[0].parts(),
+            text_parts("It is sunny in Paris.").as_slice()
+        );
+        assert_eq!(last.finish, FinishReason::Stop);
+
+        assert!(matches!(
+            client.request(user("Again"), vec![]).await,
+            Err(ClientError::StreamCancelled)
+        ));
+        assert!(matches!(
+            client.request(user("More"), vec![]).await,
+            Err(ClientError::ProviderError(_))
+        ));
+
+        let requests = client.requests();
+        assert_eq!(requests.len(), 4);
+        assert_eq!(requests[1].messages, user("Go on"));
+        assert_eq!(client.remaining(), 0);
+    }
+
+    fn text_parts(text: &str) -> Vec<Part> {
+        vec![Part::Text {
+            content: text.to_string(),
+            finished: true,
+        }]
+    }
+
+    #[test]
+    fn test_stream_deltas_roundtrip() {
+        let response = Response {
+            data: vec![Message::Assistant(vec![
+                Part::Reasoning {
+                    content: "Look it up".to_string(),
+                    summary: None,
+                    signature: Some("sig".to_string()),
+                    finished: true,
+                },
+                Part::FunctionCall {
+                    id: Some("call_1".to_string()),
+                    name: "search".to_string(),
+                    arguments: json!({ "query": "weather in Paris" }),
+                    signature: None,
+                    raw_arguments: None,
+                    finished: true,
+                },
+            ])],
+            usage: Usage {
+                prompt_tokens: Some(10),
+                ..Default::default()
+            },
+            finish: FinishReason::ToolCalls,
+            alternatives: Vec::new(),
+            model: None,
+            iterations: Vec::new(),
+        };
+
+        let deltas = stream_deltas(&response);
+        assert!(deltas.len() > 6);
+        let mut acc = ResponseAccumulator::new();
+        deltas.into_iter().for_each(|delta| acc.apply(delta));
+        assert_eq!(
+            serde_json::to_value(acc.response()).unwrap(),
+            serde_json::to_value(&response).unwrap()
+        );
+    }
+}
@@ -0,0 +1,380 @@
+//! Record and replay of provider HTTP traffic.
+//!
+//! An [`HttpReplay`] transport saves the raw responses of a provider to fixture
+//! files and answers later runs from them, so tests written against a live
+//! provider run offline and deterministically. Replayed responses go through
+//! the same request building and response and stream parsing as live ones:
+//!
+//! ```ignore
+//! use unia::testing::{HttpReplay, ReplayMode};
+//!
+//! let transport = TransportOptions::new()
+//!     .with_http_transport(HttpReplay::new("tests/fixtures/weather", ReplayMode::from_env()));
+//! let client = OpenAIClient::new(key, base_url, ModelOptions::new("gpt-5-mini"), transport);
+//! let response = client.request(messages, vec![]).await?;
+//! ```
+//!
+//! Run once with `UNIA_REPLAY=record` and a real API key to capture the
+//! fixtures, then commit them. Fixtures are keyed by the method, URL and body
+//! of the request, so changing the prompt, the tools or the model options
+//! requires recording again. Request headers, which carry the API key, are
+//! neither recorded nor part of the key, and neither is a `key` query
+//! parameter. Server-sent event streams are recorded frame by frame and
+//! replayed in the same chunks.
+
+use async_trait::async_trait;
+use reqwest::header::{
+    CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_TYPE, SET_COOKIE, TRANSFER_ENCODING,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use serde_with::skip_serializing_none;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::client::ClientError;
+use crate::http::HttpTransport;
+
+/// How an [`HttpReplay`] uses its fixtures.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ReplayMode {
+    /// Answer from fixtures, failing requests without one.
+    #[default]
+    Replay,
+    /// Send every request and overwrite its fixture.
+    Record,
+    /// Answer from fixtures, recording the requests without one.
+    Auto,
+}
+
+impl ReplayMode {
+    /// Read the mode from the `UNIA_REPLAY` environment variable, which can be
+    /// `replay`, `record` or `auto`. Defaults to [`Replay`](Self::Replay).
+    pub fn from_env() -> Self {
+        match std::env::var("UNIA_REPLAY").as_deref() {
+            Ok("record") => Self::Record,
+            Ok("auto") => Self::Auto,
+            _ => Self::Replay,
+        }
+    }
+}
+
+/// Response headers that are not recorded, because they describe the original
+/// encoding of the body or carry session state.
+const SKIPPED_HEADERS: &[reqwest::header::HeaderName] = &[
+    CONTENT_ENCODING,
+    CONTENT_LENGTH,
+    SET_COOKIE,
+    TRANSFER_ENCODING,
+];
+
+/// A recorded exchange. The request is kept to make fixtures reviewable.
+#[skip_serializing_none]
+#[derive(Debug, Serialize, Deserialize)]
+struct Fixture {
+    method: String,
+    url: String,
+    /// Request body, if it is JSON.
+    request: Option<Value>,
+    status: u16,
+    headers: BTreeMap<String, String>,
+    /// Response body, if it is JSON.
+    body: Option<Value>,
+    /// Response body, if it is neither JSON nor an event stream.
+    text: Option<String>,
+    /// Frames of an event stream response.
+    frames: Option<Vec<String>>,
+}
+
+impl Fixture {
+    fn into_response(self) -> Result<reqwest::Response, ClientError> {
+        let mut builder = http::Response::builder().status(self.status);
+        for (name, value) in &self.headers {
+            builder = builder.header(name, value);
+        }
+        let body = match (self.frames, self.body, self.text) {
+            (Some(frames), _, _) => reqwest::Body::wrap_stream(futures::stream::iter(
+                frames.into_iter().map(Ok::<_, std::io::Error>),
+            )),
+            (None, Some(body), _) => body.to_string().into(),
+            (None, None, text) => text.unwrap_or_default().into(),
+        };
+        let response = builder
+            .body(body)
+            .map_err(|e| ClientError::Config(format!("Invalid fixture: {}", e)))?;
+        Ok(response.into())
+    }
+}
+
+/// Transport recording provider responses to fixtures and replaying them.
+#[derive(Debug, Clone)]
+pub struct HttpReplay {
+    dir: PathBuf,
+    mode: ReplayMode,
+}
+
+fn io_error(action: &str, path: &Path, e: std::io::Error) -> ClientError {
+    ClientError::Config(format!("Failed to {} {}: {}", action, path.display(), e))
+}
+
+impl HttpReplay {
+    /// Keep fixtures in the given directory.
+    pub fn new(dir: impl Into<PathBuf>, mode: ReplayMode) -> Self {
+        Self {
+            dir: dir.into(),
+            mode,
+        }
+    }
+
+    fn path(&self, method: &str, url: &str, body: &[u8]) -> PathBuf {
+        const OFFSET: u128 = 0x6c62272e07bb014262b821756295c58d;
+        const PRIME: u128 = 0x0000000001000000000000000000013b;
+
+        let bytes = [method.as_bytes(), b" ", url.as_bytes(), b"\n", body].concat();
+        let hash = bytes.iter().fold(OFFSET, |hash, byte| {
+            (hash ^ *byte as u128).wrapping_mul(PRIME)
+        });
+        self.dir.join(format!("{:032x}.json", hash))
+    }
+
+    /// Look up the recorded exchange, or fail in replay mode if there is none.
+    async fn replay(&self, path: &Path) -> Result<Option<Fixture>, ClientError> {
+        if self.mode == ReplayMode::Record {
+            return Ok(None);
+        }
+        match tokio::fs::read(path).await {
+            Ok(bytes) => Ok(Some(serde_json::from_slice(&bytes)?)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => match self.mode {
+                ReplayMode::Replay => Err(ClientError::Config(format!(
+                    "No fixture {} for this request; record it with UNIA_REPLAY=record",
+                    path.display()
+                ))),
+                _ => Ok(None),
+            },
+            Err(e) => Err(io_error("read", path, e)),
+        }
+    }
+}
+
+/// URL of a request without the `key` query parameter some providers
+/// authenticate with.
+fn redacted_url(url: &reqwest::Url) -> String {
+    let mut url = url.clone();
+    let pairs: Vec<(String, String)> = url
+        .query_pairs()
+        .filter(|(name, _)| name != "key")
+        .map(|(name, value)| (name.into_owned(), value.into_owned()))
+        .collect();
+    if pairs.is_empty() {
+        url.set_query(None);
+    } else {
+        url.query_pairs_mut().clear().extend_pairs(pairs);
+    }
+    url.to_string()
+}
+
+/// Split an event stream after each blank line, keeping the separators.
+fn split_frames(text: &str) -> Vec<String> {
+    let mut frames = Vec::new();
+    let mut rest = text;
+    while let Some(end) = rest.find("\n\n") {
+        frames.push(rest[..end + 2].to_string());
+        rest = &rest[end + 2..];
+    }
+    if !rest.is_empty() {
+        frames.push(rest.to_string());
+    }
+    frames
+}
+
+async fn record(path: &Path, fixture: &Fixture) -> Result<(), ClientError> {
+    if let Some(dir) = path.parent() {
+        tokio::fs::create_dir_all(dir)
+            .await
+            .map_err(|e| io_error("create", dir, e))?;
+    }
+    let json = serde_json::to_vec_pretty(fixture)?;
+    tokio::fs::write(path, json)
+        .await
+        .map_err(|e| io_error("write", path, e))
+}
+
+#[async_trait]
+impl HttpTransport for HttpReplay {
+    async fn send(
+        &self,
+        client: reqwest::Client,
+        request: reqwest::Request,
+    ) -> Result<reqwest::Response, ClientError> {
+        let method = request.method().to_string();
+        let url = redacted_url(request.url());
+        let body = request
+            .body()
+            .and_then(|body| body.as_bytes())
+            .unwrap_or_default()
+            .to_vec();
+        let path = self.path(&method, &url, &body);
+        if let Some(fixture) = self.replay(&path).await? {
+            return fixture.into_response();
+        }
+
+        let response = client.execute(request).await?;
+        let status = response.status().as_u16();
+        let headers = response
+            .headers()
+            .iter()
+            .filter(|(name, _)| !SKIPPED_HEADERS.contains(name))
+            .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+            .collect::<BTreeMap<_, _>>();
+        let event_stream = headers
+            .get(CONTENT_TYPE.as_str())
+            .is_some_and(|kind| kind.starts_with("text/event-stream"));
+        let text = String::from_utf8_lossy(&response.bytes().await?).into_owned();
+
+        let mut fixture = Fixture {
+            method,
+            url,
+            request: serde_json::from_slice(&body).ok(),
+            status,
+            headers,
+            body: None,
+            text: None,
+            frames: None,
+        };
+        if event_stream {
+            fixture.frames = Some(split_frames(&text));
+        } else {
+            match serde_json::from_str(&text) {
+                Ok(body) => fixture.body = Some(body),
+                Err(_) => fixture.text = Some(text),
+            }
+        }
+        record(&path, &fixture).await?;
+        fixture.into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::openai::OpenAIClient;
+    use crate::client::{Client, StreamingClient};
+    use crate::model::{Message, Part, Response};
+    use crate::options::{ModelOptions, TransportOptions};
+    use crate::providers::OpenAIModel;
+    use crate::testing::server::{TestResponse, TestServer};
+    use futures::TryStreamExt;
+
+    const COMPLETION: &str = r#"{"id":"chatcmpl-1","object":"chat.completion","model":"gpt-5-mini","choices":[{"index":0,"message":{"role":"assistant","content":"Recorded answer"},"finish_reason":"stop"}],"usage":{"prompt_tokens":5,"completion_tokens":2}}"#;
+
+    const STREAM: &str = r#"data: {"id":"chatcmpl-2","object":"chat.completion.chunk","model":"gpt-5-mini","choices":[{"index":0,"delta":{"role":"assistant","content":"Recorded "},"finish_reason":null}]}
+
+data: {"id":"chatcmpl-2","object":"chat.completion.chunk","model":"gpt-5-mini","choices":[{"index":0,"delta":{"content":"stream"},"finish_reason":"stop"}]}
+
+data: [DONE]
+
+"#;
+
+    fn user(text: &str) -> Vec<Message> {
+        vec![Message::User(vec![Part::Text {
+            content: text.to_string(),
+            finished: true,
+        }])]
+    }
+
+    fn client(base_url: &str, dir: &Path, mode: ReplayMode) -> OpenAIClient<OpenAIModel> {
+        OpenAIClient::new(
+            "sk-secret".to_string(),
+            base_url.to_string(),
+            ModelOptions::new("gpt-5-mini"),
+            TransportOptions::new().with_http_transport(HttpReplay::new(dir, mode)),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_record_then_replay() {
+        let dir = std::env::temp_dir().join(format!("unia-replay-{}", uuid::Uuid::new_v4()));
+        let server = TestServer::start(|request, _| {
+            assert_eq!(request.method, "POST");
+            assert_eq!(request.path, "/chat/completions");
+            let body: Value = serde_json::from_slice(&request.body).unwrap();
+            if body["stream"] == true {
+                TestResponse::new(200, STREAM).with_header("Content-Type", "text/event-stream")
+            } else {
+                TestResponse::new(200, COMPLETION).with_header("Content-Type", "application/json")
+            }
+        })
+        .await;
+
+        let recorder = client(server.url(), &dir, ReplayMode::Record);
+        let recorded = recorder.request(user("Hi"), vec![]).await.unwrap();
+        assert_eq!(
+            recorded.data[0].content().as_deref(),
+            Some("Recorded answer")
+        );
+        recorder
+            .request_stream(user("Hi"), vec![])
+            .await
+            .unwrap()
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        assert_eq!(server.requests().len(), 2);
+
+        let fixtures: Vec<String> = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| std::fs::read_to_string(entry.unwrap().path()).unwrap())
+            .collect();
+        assert_eq!(fixtures.len(), 2);
+        assert!(fixtures
+            .iter()
+            .all(|fixture| !fixture.contains("sk-secret")));
+        let stream_fixture = fixtures.iter().find(|f| f.contains("frames")).unwrap();
+        let stream_fixture: Fixture = serde_json::from_str(stream_fixture).unwrap();
+        assert_eq!(stream_fixture.frames.unwrap().len(), 3);
+
+        // Replayed exchanges are parsed by the provider client without
+        // reaching the server.
+        let replayer = client(server.url(), &dir, ReplayMode::Replay);
+        let replayed = replayer.request(user("Hi"), vec![]).await.unwrap();
+        assert_eq!(replayed.data, recorded.data);
+        assert_eq!(replayed.usage, recorded.usage);
+
+        let snapshots: Vec<Response> = replayer
+            .request_stream(user("Hi"), vec![])
+            .await
+            .unwrap()
+            .try_collect()
+            .await
+            .unwrap();
+        assert!(snapshots.len() > 1);
+        assert_eq!(
+            snapshots.last().unwrap().data[0].content().as_deref(),
+            Some("Recorded stream")
+        );
+
+        assert!(matches!(
+            replayer.request(user("Unrecorded"), vec![]).await,
+            Err(ClientError::Config(_))
+        ));
+        assert_eq!(server.requests().len(), 2);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_redacted_url() {
+        let url = reqwest::Url::parse(
+            "https://generativelanguage.googleapis.com/v1beta/models/gemini:streamGenerateContent?alt=sse&key=secret",
+        )
+        .unwrap();
+        assert_eq!(
+            redacted_url(&url),
+            "https://generativelanguage.googleapis.com/v1beta/models/gemini:streamGenerateContent?alt=sse"
+        );
+        assert_eq!(
+            split_frames("data: a\n\ndata: b\n\n"),
+            ["data: a\n\n", "data: b\n\n"]
+        );
+    }
+}
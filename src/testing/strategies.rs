@@ -0,0 +1,144 @@
+//! [`proptest`] strategies for streaming code.
+//!
+//! Streamed responses arrive in network chunks that can end anywhere, including
+//! inside a multibyte character, and providers interleave the deltas of parallel
+//! tool calls. These strategies generate such inputs, so a provider's stream
+//! parser can be checked against the same properties as the built-in ones:
+//!
+//! ```ignore
+//! use proptest::prelude::*;
+//! use unia::testing::strategies::{chunked, decode_sse, sse_body};
+//!
+//! proptest! {
+//!     #[test]
+//!     fn parses_any_chunking(chunks in chunked(sse_body(&events))) {
+//!         let deltas = parse_my_events(decode_sse(&chunks));
+//!         prop_assert_eq!(deltas, expected);
+//!     }
+//! }
+//! ```
+
+use proptest::prelude::*;
+use proptest::sample::subsequence;
+
+use crate::sse::SseDecoder;
+
+/// Text mixing ASCII with multibyte characters of every UTF-8 length.
+pub fn unicode_text(max_chars: usize) -> impl Strategy<Value = String> {
+    let char = prop_oneof![
+        3 => proptest::char::range(' ', '~'),
+        1 => proptest::char::range('\u{a0}', '\u{7ff}'),
+        1 => proptest::char::range('\u{4e00}', '\u{9fff}'),
+        1 => proptest::char::range('\u{1f300}', '\u{1f6ff}'),
+    ];
+    proptest::collection::vec(char, 0..=max_chars).prop_map(|chars| chars.into_iter().collect())
+}
+
+/// Split bytes into chunks at arbitrary offsets, including inside characters.
+///
+/// Concatenating the chunks always gives back the input.
+pub fn chunked(bytes: Vec<u8>) -> impl Strategy<Value = Vec<Vec<u8>>> {
+    let offsets: Vec<usize> = (1..bytes.len()).collect();
+    let max = offsets.len();
+    subsequence(offsets, 0..=max).prop_map(move |cuts| split_at(&bytes, &cuts))
+}
+
+/// Split text into fragments at arbitrary character boundaries.
+///
+/// Concatenating the fragments always gives back the input. Every fragment is
+/// non-empty, except for a single empty fragment when the input is empty.
+pub fn fragments(text: String) -> impl Strategy<Value = Vec<String>> {
+    let offsets: Vec<usize> = text.char_indices().map(|(i, _)| i).skip(1).collect();
+    let max = offsets.len();
+    subsequence(offsets, 0..=max).prop_map(move |cuts| {
+        split_at(text.as_bytes(), &cuts)
+            .into_iter()
+            .map(|bytes| String::from_utf8(bytes).expect("cuts are on char boundaries"))
+            .collect()
+    })
+}
+
+/// Merge sequences in a random order that keeps the order within each one.
+///
+/// Use it to interleave the events of parts streamed in parallel, such as the
+/// argument fragments of several tool calls.
+pub fn interleave<T: Clone + std::fmt::Debug>(
+    sequences: Vec<Vec<T>>,
+) -> impl Strategy<Value = Vec<T>> {
+    let labels: Vec<usize> = sequences
+        .iter()
+        .enumerate()
+        .flat_map(|(label, sequence)| std::iter::repeat_n(label, sequence.len()))
+        .collect();
+    Just(labels).prop_shuffle().prop_map(move |labels| {
+        let mut next = vec![0; sequences.len()];
+        labels
+            .into_iter()
+            .map(|label| {
+                next[label] += 1;
+                sequences[label][next[label] - 1].clone()
+            })
+            .collect()
+    })
+}
+
+/// Encode data payloads as an SSE body terminated by the `[DONE]` marker.
+pub fn sse_body<S: AsRef<str>>(events: &[S]) -> Vec<u8> {
+    let mut body = String::new();
+    for event in events {
+        body.push_str("data: ");
+        body.push_str(event.as_ref());
+        body.push_str("\n\n");
+    }
+    body.push_str("data: [DONE]\n\n");
+    body.into_bytes()
+}
+
+/// Decode the data payloads of a chunked SSE body, as
+/// [`SSEResponseExt::sse`](crate::sse::SSEResponseExt::sse) does for a live
+/// response.
+pub fn decode_sse(chunks: &[Vec<u8>]) -> Vec<String> {
+    let mut decoder = SseDecoder::new();
+    let mut events: Vec<String> = chunks
+        .iter()
+        .flat_map(|chunk| decoder.push(chunk))
+        .collect();
+    events.extend(decoder.finish());
+    events
+}
+
+fn split_at(bytes: &[u8], cuts: &[usize]) -> Vec<Vec<u8>> {
+    let mut chunks = Vec::with_capacity(cuts.len() + 1);
+    let mut start = 0;
+    for &cut in cuts {
+        chunks.push(bytes[start..cut].to_vec());
+        start = cut;
+    }
+    chunks.push(bytes[start..].to_vec());
+    chunks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    proptest! {
+        #[test]
+        fn test_chunked_roundtrip(
+            (text, chunks) in unicode_text(16).prop_flat_map(|t| (Just(t.clone()), chunked(t.into_bytes())))
+        ) {
+            prop_assert_eq!(chunks.concat(), text.into_bytes());
+        }
+
+        #[test]
+        fn test_interleave_keeps_order(
+            merged in interleave(vec![vec![1, 2, 3], vec![10, 20], vec![]])
+        ) {
+            prop_assert_eq!(merged.len(), 5);
+            let ones: Vec<i32> = merged.iter().copied().filter(|n| *n < 10).collect();
+            let tens: Vec<i32> = merged.iter().copied().filter(|n| *n >= 10).collect();
+            prop_assert_eq!(ones, vec![1, 2, 3]);
+            prop_assert_eq!(tens, vec![10, 20]);
+        }
+    }
+}
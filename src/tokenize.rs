@@ -0,0 +1,247 @@
+//! Token counting utilities.
+//!
+//! Exact counts come from provider endpoints where available (Anthropic
+//! `messages/count_tokens`, Gemini `countTokens`). OpenAI models are counted
+//! locally with their tiktoken BPE via [`count_tokens`]. Everything else falls back
+//! to a character-based estimate, which is good enough for context-window budgeting.
+
+use async_trait::async_trait;
+use rmcp::model::Tool;
+use tiktoken_rs::tokenizer::{get_tokenizer, Tokenizer};
+use tiktoken_rs::CoreBPE;
+use tracing::warn;
+
+use crate::client::{Client, ClientError};
+use crate::model::{MediaType, Message, Part};
+
+/// Approximate number of characters per token for English text.
+pub(crate) const CHARS_PER_TOKEN: usize = 4;
+
+/// Fixed per-message overhead for role markers and separators.
+const MESSAGE_OVERHEAD: usize = 4;
+
+/// Rough token cost of a single image input.
+const IMAGE_TOKENS: usize = 1000;
+
+/// Tokens per message in the OpenAI chat format (`<|start|>{role}\n{content}<|end|>\n`),
+/// excluding the content.
+const CHAT_MESSAGE_OVERHEAD: usize = 4;
+
+/// Tokens priming every reply (`<|start|>assistant<|message|>`).
+const CHAT_REPLY_PRIMING: usize = 3;
+
+/// Estimate the token count of a text.
+pub fn estimate_text_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(CHARS_PER_TOKEN)
+}
+
+/// Estimate the token count of a single part.
+pub fn estimate_part_tokens(part: &Part) -> usize {
+    part_tokens(part, &estimate_text_tokens)
+}
+
+fn part_tokens(part: &Part, text_tokens: &dyn Fn(&str) -> usize) -> usize {
+    match part {
+        Part::Text { content, .. } => text_tokens(content),
+        Part::Reasoning { content, .. } => text_tokens(content),
+        Part::FunctionCall {
+            name, arguments, ..
+        } => text_tokens(name) + text_tokens(&arguments.to_string()),
+        Part::FunctionResponse {
+            name,
+            response,
+            parts,
+            ..
+        } => {
+            text_tokens(name)
+                + text_tokens(&response.to_string())
+                + parts
+                    .iter()
+                    .map(|p| part_tokens(p, text_tokens))
+                    .sum::<usize>()
+        }
+        Part::Media {
+            media_type, data, ..
+        } => match media_type {
+            MediaType::Image => IMAGE_TOKENS,
+            // Base64 encodes 3 bytes in 4 characters.
+            _ => (data.len() * 3 / 4).div_ceil(CHARS_PER_TOKEN),
+        },
+        Part::ComputerCall { action, .. } => {
+            text_tokens(&serde_json::to_string(action).unwrap_or_default())
+        }
+        Part::ComputerResult { .. } => IMAGE_TOKENS,
+        Part::HostedToolCall { call, .. } => {
+            text_tokens(&serde_json::to_string(call).unwrap_or_default())
+        }
+        // Citations are not sent back to providers.
+        Part::Citation { .. } => 0,
+    }
+}
+
+/// Estimate the token count of a conversation and its tool definitions.
+pub fn estimate_tokens(messages: &[Message], tools: &[Tool]) -> usize {
+    let message_tokens: usize = messages
+        .iter()
+        .map(|m| MESSAGE_OVERHEAD + m.parts().iter().map(estimate_part_tokens).sum::<usize>())
+        .sum();
+
+    let tool_tokens: usize = tools
+        .iter()
+        .map(|t| estimate_text_tokens(&serde_json::to_string(t).unwrap_or_default()))
+        .sum();
+
+    message_tokens + tool_tokens
+}
+
+/// Count the tokens of a conversation for the given model.
+///
+/// Models with a known tiktoken encoding (the OpenAI families, optionally
+/// prefixed with a router namespace like `openai/gpt-4o`) are tokenized exactly,
+/// including the chat format overhead. Other models, such as Claude and Gemini,
+/// have no public tokenizer and are estimated; use
+/// [`TokenCounter::count_tokens`] for API-backed counts.
+pub fn count_tokens(messages: &[Message], model: &str) -> usize {
+    match bpe_for_model(model) {
+        Some(bpe) => {
+            let text_tokens = |text: &str| bpe.encode_with_special_tokens(text).len();
+            let message_tokens: usize = messages
+                .iter()
+                .map(|m| {
+                    CHAT_MESSAGE_OVERHEAD
+                        + m.parts()
+                            .iter()
+                            .map(|p| part_tokens(p, &text_tokens))
+                            .sum::<usize>()
+                })
+                .sum();
+            message_tokens + CHAT_REPLY_PRIMING
+        }
+        None => estimate_tokens(messages, &[]),
+    }
+}
+
+/// Count the tokens of tool definitions for the given model.
+fn count_tool_tokens(tools: &[Tool], model: &str) -> usize {
+    let bpe = bpe_for_model(model);
+    tools
+        .iter()
+        .map(|t| {
+            let json = serde_json::to_string(t).unwrap_or_default();
+            match bpe {
+                Some(bpe) => bpe.encode_with_special_tokens(&json).len(),
+                None => estimate_text_tokens(&json),
+            }
+        })
+        .sum()
+}
+
+/// Look up the BPE encoding of a model.
+fn bpe_for_model(model: &str) -> Option<&'static CoreBPE> {
+    let model = model.rsplit('/').next().unwrap_or(model);
+    let tokenizer = get_tokenizer(model).or_else(|| {
+        ["gpt-5", "o3", "o4"]
+            .iter()
+            .any(|prefix| model.starts_with(prefix))
+            .then_some(Tokenizer::O200kBase)
+    })?;
+
+    Some(match tokenizer {
+        Tokenizer::O200kBase => tiktoken_rs::o200k_base_singleton(),
+        Tokenizer::Cl100kBase => tiktoken_rs::cl100k_base_singleton(),
+        Tokenizer::P50kBase => tiktoken_rs::p50k_base_singleton(),
+        Tokenizer::P50kEdit => tiktoken_rs::p50k_edit_singleton(),
+        Tokenizer::R50kBase | Tokenizer::Gpt2 => tiktoken_rs::r50k_base_singleton(),
+    })
+}
+
+/// Trait for clients that can count the tokens of a prompt.
+#[async_trait]
+pub trait TokenCounter: Client {
+    /// Count prompt tokens using the provider API.
+    ///
+    /// Returns `Ok(None)` if the provider has no token counting endpoint.
+    async fn count_tokens_exact(
+        &self,
+        _messages: &[Message],
+        _tools: &[Tool],
+    ) -> Result<Option<usize>, ClientError> {
+        Ok(None)
+    }
+
+    /// Count prompt tokens, preferring exact provider counts and falling back to
+    /// local counting with [`count_tokens`](fn@count_tokens).
+    async fn count_tokens(&self, messages: &[Message], tools: &[Tool]) -> usize {
+        let local = || {
+            let model = &self.model_options().model;
+            count_tokens(messages, model) + count_tool_tokens(tools, model)
+        };
+        match self.count_tokens_exact(messages, tools).await {
+            Ok(Some(count)) => count,
+            Ok(None) => local(),
+            Err(e) => {
+                warn!(
+                    "Exact token counting failed, falling back to estimate: {}",
+                    e
+                );
+                local()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimate_tokens() {
+        let messages = vec![
+            Message::User(vec![Part::Text {
+                content: "Hello, how are you?".to_string(),
+                finished: true,
+            }]),
+            Message::Assistant(vec![Part::Text {
+                content: "Fine".to_string(),
+                finished: true,
+            }]),
+        ];
+
+        // 19 chars -> 5 tokens, 4 chars -> 1 token, plus 2 * overhead.
+        assert_eq!(
+            estimate_tokens(&messages, &[]),
+            5 + 1 + 2 * MESSAGE_OVERHEAD
+        );
+    }
+
+    #[test]
+    fn test_estimate_media_tokens() {
+        let image = Part::Media {
+            media_type: MediaType::Image,
+            data: "aGVsbG8=".to_string(),
+            mime_type: "image/png".to_string(),
+            uri: None,
+            finished: true,
+        };
+        assert_eq!(estimate_part_tokens(&image), IMAGE_TOKENS);
+    }
+
+    #[test]
+    fn test_count_tokens_bpe() {
+        let messages = vec![Message::User(vec![Part::Text {
+            content: "Hello, how are you?".to_string(),
+            finished: true,
+        }])];
+
+        // "Hello", ",", " how", " are", " you", "?"
+        let expected = 6 + CHAT_MESSAGE_OVERHEAD + CHAT_REPLY_PRIMING;
+        assert_eq!(count_tokens(&messages, "gpt-4o"), expected);
+        assert_eq!(count_tokens(&messages, "gpt-4"), expected);
+        assert_eq!(count_tokens(&messages, "openai/gpt-4o-mini"), expected);
+
+        assert_eq!(
+            count_tokens(&messages, "claude-sonnet-4-5"),
+            estimate_tokens(&messages, &[])
+        );
+    }
+}
@@ -2,7 +2,8 @@
 
 use async_trait::async_trait;
 pub use rmcp::model::Tool;
-use serde_json::Value;
+use serde_json::{Map, Value};
+use std::sync::Arc;
 
 /// Error type for tool execution.
 #[derive(Debug, thiserror::Error)]
@@ -20,3 +21,74 @@ pub trait ToolService: Send + Sync {
     /// Execute a tool.
     async fn call_tool(&self, name: String, args: Value) -> Result<Value, ToolError>;
 }
+
+/// Return a copy of a JSON value with the keys of every object sorted.
+///
+/// `serde_json` keeps insertion order when its `preserve_order` feature is enabled
+/// anywhere in the dependency graph, so sorted output cannot be taken for granted.
+pub fn canonicalize(value: &Value) -> Value {
+    match value {
+        Value::Object(map) => {
+            let mut entries: Vec<_> = map.iter().collect();
+            entries.sort_by_key(|(key, _)| *key);
+            Value::Object(
+                entries
+                    .into_iter()
+                    .map(|(k, v)| (k.clone(), canonicalize(v)))
+                    .collect::<Map<_, _>>(),
+            )
+        }
+        Value::Array(items) => Value::Array(items.iter().map(canonicalize).collect()),
+        other => other.clone(),
+    }
+}
+
+/// Put tool definitions into a canonical form: sorted by name, with sorted schema keys.
+///
+/// Request builders apply this so that the same set of tools always serializes to
+/// the same bytes, which keeps provider prompt caches warm and snapshots stable.
+pub fn canonical_tools(mut tools: Vec<Tool>) -> Vec<Tool> {
+    tools.sort_by(|a, b| a.name.cmp(&b.name));
+    for tool in &mut tools {
+        if let Value::Object(schema) = canonicalize(&Value::Object((*tool.input_schema).clone())) {
+            tool.input_schema = Arc::new(schema);
+        }
+        if let Some(schema) = &tool.output_schema {
+            if let Value::Object(schema) = canonicalize(&Value::Object((**schema).clone())) {
+                tool.output_schema = Some(Arc::new(schema));
+            }
+        }
+    }
+    tools
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_canonical_tools() {
+        let schema = |value: Value| value.as_object().unwrap().clone();
+        let tools = vec![
+            Tool::new(
+                "search",
+                "Search",
+                schema(json!({
+                    "type": "object",
+                    "properties": { "query": { "type": "string", "description": "q" } },
+                })),
+            ),
+            Tool::new("add", "Add", schema(json!({ "type": "object" }))),
+        ];
+
+        let canonical = canonical_tools(tools);
+
+        assert_eq!(canonical[0].name, "add");
+        assert_eq!(canonical[1].name, "search");
+        assert_eq!(
+            serde_json::to_string(&*canonical[1].input_schema).unwrap(),
+            r#"{"properties":{"query":{"description":"q","type":"string"}},"type":"object"}"#
+        );
+    }
+}
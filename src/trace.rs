@@ -0,0 +1,238 @@
+//! W3C trace context propagation.
+//!
+//! Outgoing provider requests carry `traceparent`, `tracestate` and `baggage`
+//! headers for the current [`TraceContext`], so gateway and provider logs can be
+//! correlated with application traces. MCP tool calls carry the same fields in
+//! their `_meta`, since the MCP transport sends requests from its own task.
+//!
+//! The current context is resolved in order from:
+//!
+//! 1. An explicit scope set with [`TraceContext::scope`].
+//! 2. The propagator installed with [`set_propagator`], while a `tracing` span is
+//!    active. Tracing bridges such as OpenTelemetry use this to expose the trace
+//!    of the current span.
+//!
+//! ```ignore
+//! use unia::trace::TraceContext;
+//!
+//! let context = TraceContext::parse(incoming_traceparent).unwrap_or_else(TraceContext::new_root);
+//! let response = context.scope(agent.chat(messages)).await?;
+//! ```
+
+use rand::Rng;
+use reqwest::RequestBuilder;
+use serde_json::{Map, Value};
+use std::future::Future;
+use std::sync::OnceLock;
+
+/// Header carrying the trace id, parent span id and trace flags.
+pub const TRACEPARENT: &str = "traceparent";
+/// Header carrying vendor-specific trace state.
+pub const TRACESTATE: &str = "tracestate";
+/// Header carrying application-defined key-value pairs.
+pub const BAGGAGE: &str = "baggage";
+
+type Propagator = Box<dyn Fn() -> Option<TraceContext> + Send + Sync>;
+
+static PROPAGATOR: OnceLock<Propagator> = OnceLock::new();
+
+tokio::task_local! {
+    static CURRENT: TraceContext;
+}
+
+/// A W3C trace context.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceContext {
+    /// 32 lowercase hex digits identifying the trace.
+    pub trace_id: String,
+    /// 16 lowercase hex digits identifying the calling span.
+    pub parent_id: String,
+    /// Trace flags; bit 0 marks the trace as sampled.
+    pub flags: u8,
+    /// Value of the `tracestate` header, if any.
+    pub tracestate: Option<String>,
+    /// Value of the `baggage` header, if any.
+    pub baggage: Option<String>,
+}
+
+impl TraceContext {
+    /// Start a new sampled trace with random ids.
+    pub fn new_root() -> Self {
+        let mut rng = rand::rng();
+        Self {
+            trace_id: format!("{:032x}", rng.random_range(1..=u128::MAX)),
+            parent_id: format!("{:016x}", rng.random_range(1..=u64::MAX)),
+            flags: 1,
+            tracestate: None,
+            baggage: None,
+        }
+    }
+
+    /// Parse a `traceparent` header value.
+    ///
+    /// Returns `None` for malformed values and for the all-zero ids the
+    /// specification marks as invalid.
+    pub fn parse(traceparent: &str) -> Option<Self> {
+        let mut fields = traceparent.trim().split('-');
+        let version = fields.next()?;
+        let trace_id = fields.next()?;
+        let parent_id = fields.next()?;
+        let flags = fields.next()?;
+
+        let is_hex = |s: &str, len: usize| {
+            s.len() == len && s.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+        };
+        if !is_hex(version, 2) || version == "ff" || (version == "00" && fields.next().is_some()) {
+            return None;
+        }
+        if !is_hex(trace_id, 32) || !is_hex(parent_id, 16) || !is_hex(flags, 2) {
+            return None;
+        }
+        if trace_id.bytes().all(|b| b == b'0') || parent_id.bytes().all(|b| b == b'0') {
+            return None;
+        }
+
+        Some(Self {
+            trace_id: trace_id.to_string(),
+            parent_id: parent_id.to_string(),
+            flags: u8::from_str_radix(flags, 16).ok()?,
+            tracestate: None,
+            baggage: None,
+        })
+    }
+
+    /// Set the `tracestate` value.
+    pub fn with_tracestate(mut self, tracestate: impl Into<String>) -> Self {
+        self.tracestate = Some(tracestate.into());
+        self
+    }
+
+    /// Set the `baggage` value.
+    pub fn with_baggage(mut self, baggage: impl Into<String>) -> Self {
+        self.baggage = Some(baggage.into());
+        self
+    }
+
+    /// Whether the trace is sampled.
+    pub fn sampled(&self) -> bool {
+        self.flags & 1 == 1
+    }
+
+    /// Format the `traceparent` header value.
+    pub fn traceparent(&self) -> String {
+        format!("00-{}-{}-{:02x}", self.trace_id, self.parent_id, self.flags)
+    }
+
+    /// Run a future with this context as the current trace context.
+    pub async fn scope<F: Future>(self, future: F) -> F::Output {
+        CURRENT.scope(self, future).await
+    }
+
+    /// The current trace context, if any.
+    pub fn current() -> Option<Self> {
+        if let Ok(context) = CURRENT.try_with(Clone::clone) {
+            return Some(context);
+        }
+        if tracing::Span::current().is_none() {
+            return None;
+        }
+        PROPAGATOR.get().and_then(|propagator| propagator())
+    }
+
+    /// Header names and values for this context.
+    pub fn headers(&self) -> Vec<(&'static str, String)> {
+        let mut headers = vec![(TRACEPARENT, self.traceparent())];
+        if let Some(tracestate) = &self.tracestate {
+            headers.push((TRACESTATE, tracestate.clone()));
+        }
+        if let Some(baggage) = &self.baggage {
+            headers.push((BAGGAGE, baggage.clone()));
+        }
+        headers
+    }
+}
+
+/// Install the function resolving the trace context of the active `tracing` span.
+///
+/// Only one propagator can be installed. Returns `false` if one already was.
+pub fn set_propagator<F>(propagator: F) -> bool
+where
+    F: Fn() -> Option<TraceContext> + Send + Sync + 'static,
+{
+    PROPAGATOR.set(Box::new(propagator)).is_ok()
+}
+
+/// Add the headers of the current trace context to a request.
+pub fn inject(mut request: RequestBuilder) -> RequestBuilder {
+    if let Some(context) = TraceContext::current() {
+        for (name, value) in context.headers() {
+            request = request.header(name, value);
+        }
+    }
+    request
+}
+
+/// The current trace context as MCP `_meta` fields.
+pub(crate) fn current_meta() -> Option<Map<String, Value>> {
+    let context = TraceContext::current()?;
+    Some(
+        context
+            .headers()
+            .into_iter()
+            .map(|(name, value)| (name.to_string(), Value::String(value)))
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_traceparent() {
+        let context =
+            TraceContext::parse("00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01").unwrap();
+        assert_eq!(context.trace_id, "4bf92f3577b34da6a3ce929d0e0e4736");
+        assert_eq!(context.parent_id, "00f067aa0ba902b7");
+        assert!(context.sampled());
+        assert_eq!(
+            context.traceparent(),
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01"
+        );
+
+        for invalid in [
+            "00-00000000000000000000000000000000-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-0000000000000000-01",
+            "00-4BF92F3577B34DA6A3CE929D0E0E4736-00f067aa0ba902b7-01",
+            "ff-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01-extra",
+        ] {
+            assert!(TraceContext::parse(invalid).is_none(), "{}", invalid);
+        }
+
+        let root = TraceContext::new_root();
+        assert_eq!(TraceContext::parse(&root.traceparent()), Some(root));
+    }
+
+    #[tokio::test]
+    async fn test_scoped_context_is_injected() {
+        assert!(TraceContext::current().is_none());
+
+        let context = TraceContext::new_root()
+            .with_tracestate("vendor=value")
+            .with_baggage("user=42");
+        let expected = context.clone();
+
+        let request = context
+            .scope(async { inject(reqwest::Client::new().get("http://localhost")) })
+            .await
+            .build()
+            .unwrap();
+
+        let headers = request.headers();
+        assert_eq!(headers[TRACEPARENT], expected.traceparent().as_str());
+        assert_eq!(headers[TRACESTATE], "vendor=value");
+        assert_eq!(headers[BAGGAGE], "user=42");
+    }
+}
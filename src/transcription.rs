@@ -0,0 +1,147 @@
+//! Speech-to-text API support.
+//!
+//! Like embeddings, transcription reuses the regular provider clients: create a
+//! client with a transcription model (e.g. `OpenAI::create(key, "gpt-4o-transcribe".into())`
+//! or `Groq::create(key, "whisper-large-v3-turbo".into())`) and call
+//! [`TranscriptionClient::transcribe`] on it:
+//!
+//! ```ignore
+//! use unia::transcription::{TranscriptionClient, TranscriptionOptions};
+//!
+//! let audio = std::fs::read("meeting.mp3")?;
+//! let options = TranscriptionOptions::new().with_language("en");
+//! let transcription = client.transcribe(audio, "audio/mpeg", options).await?;
+//! println!("{}", transcription.text);
+//! ```
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_with::skip_serializing_none;
+
+use crate::client::ClientError;
+
+/// Trait for providers that can transcribe audio.
+#[async_trait]
+pub trait TranscriptionClient: Send + Sync {
+    /// Transcribe audio in the given format, such as `audio/mpeg` or `audio/wav`.
+    async fn transcribe(
+        &self,
+        audio: Vec<u8>,
+        mime_type: &str,
+        options: TranscriptionOptions,
+    ) -> Result<Transcription, ClientError>;
+}
+
+/// Options for a transcription. Unset values use the provider defaults.
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TranscriptionOptions {
+    /// Model to use instead of the client's model.
+    pub model: Option<String>,
+    /// Language of the audio as an ISO-639-1 code, which improves accuracy.
+    pub language: Option<String>,
+    /// Text guiding the style of the transcript or spelling unusual words.
+    pub prompt: Option<String>,
+    pub temperature: Option<f32>,
+    /// Return the transcript split into timed segments. Not every model
+    /// supports it.
+    pub timestamps: Option<bool>,
+}
+
+impl TranscriptionOptions {
+    /// Create options with the provider defaults.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the model to use instead of the client's model.
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.model = Some(model.into());
+        self
+    }
+
+    /// Set the language of the audio.
+    pub fn with_language(mut self, language: impl Into<String>) -> Self {
+        self.language = Some(language.into());
+        self
+    }
+
+    /// Set the prompt guiding the transcript.
+    pub fn with_prompt(mut self, prompt: impl Into<String>) -> Self {
+        self.prompt = Some(prompt.into());
+        self
+    }
+
+    /// Set the sampling temperature.
+    pub fn with_temperature(mut self, temperature: f32) -> Self {
+        self.temperature = Some(temperature);
+        self
+    }
+
+    /// Request timed segments.
+    pub fn with_timestamps(mut self, timestamps: bool) -> Self {
+        self.timestamps = Some(timestamps);
+        self
+    }
+}
+
+/// Result of a transcription.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Transcription {
+    /// Full transcript.
+    pub text: String,
+    /// Detected language, if reported.
+    pub language: Option<String>,
+    /// Duration of the audio in seconds, if reported.
+    pub duration: Option<f64>,
+    /// Timed segments, when requested with
+    /// [`TranscriptionOptions::timestamps`].
+    #[serde(default)]
+    pub segments: Vec<TranscriptionSegment>,
+}
+
+/// A timed part of a transcript.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TranscriptionSegment {
+    /// Start time in seconds.
+    pub start: f64,
+    /// End time in seconds.
+    pub end: f64,
+    pub text: String,
+}
+
+/// File name for uploaded audio. Speech-to-text endpoints detect the format
+/// from the extension.
+pub(crate) fn audio_file_name(mime_type: &str) -> String {
+    let subtype = mime_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .rsplit('/')
+        .next()
+        .unwrap_or_default()
+        .trim();
+    let extension = match subtype {
+        "mpeg" | "mp3" | "mpga" => "mp3",
+        "wav" | "wave" | "x-wav" | "vnd.wave" => "wav",
+        "mp4" | "m4a" | "x-m4a" => "m4a",
+        "ogg" | "opus" => "ogg",
+        "x-flac" | "flac" => "flac",
+        "" => "bin",
+        other => other,
+    };
+    format!("audio.{}", extension)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_audio_file_name() {
+        assert_eq!(audio_file_name("audio/mpeg"), "audio.mp3");
+        assert_eq!(audio_file_name("audio/x-wav"), "audio.wav");
+        assert_eq!(audio_file_name("audio/webm;codecs=opus"), "audio.webm");
+        assert_eq!(audio_file_name("audio/ogg"), "audio.ogg");
+    }
+}
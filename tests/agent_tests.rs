@@ -1,52 +1,113 @@
 use async_trait::async_trait;
-use rmcp::model::Tool;
+use futures::{StreamExt, TryStreamExt};
+use rmcp::model::{GetPromptResult, Prompt, ReadResourceResult, Resource, Tool};
+use serde_json::{json, Value};
 use std::sync::{Arc, Mutex};
-use unia::agent::Agent;
-use unia::client::{Client, ClientError};
+use std::time::Duration;
+use unia::agent::{
+    Agent, AgentError, AgentEvent, AgentHooks, OnMaxIterations, ToolResultPolicy,
+    TruncationStrategy,
+};
+use unia::client::ClientError;
+use unia::cost::{CostTracker, ModelPricing, PriceTable};
+use unia::mcp::{
+    ListChanged, ListChanges, MCPError, MCPServer, MCPTimeouts, MultiMCPServer, Servable, Served,
+    ToolFilter,
+};
+use unia::memory::Conversation;
 use unia::model::{FinishReason, Message, Part, Response, Usage};
-use unia::options::{ModelOptions, TransportOptions};
+use unia::moderation::{SafetyPolicy, SafetyStage, SafetyVerdict};
+use unia::stream::StreamDelta;
+use unia::testing::mock::stream_deltas;
+use unia::testing::MockClient;
 
-#[derive(Clone)]
-struct MockClient {
-    responses: Arc<Mutex<Vec<Response>>>,
-    requests: Arc<Mutex<Vec<Vec<Message>>>>,
-}
+/// MCP server with a single `echo` tool returning its arguments, after sleeping
+/// for `delay_ms` milliseconds if given.
+struct EchoServer;
 
-impl MockClient {
-    fn new(responses: Vec<Response>) -> Self {
-        Self {
-            responses: Arc::new(Mutex::new(responses)),
-            requests: Arc::new(Mutex::new(Vec::new())),
+#[async_trait]
+impl MCPServer for EchoServer {
+    async fn list_tools(&self) -> Result<Vec<Served<Tool>>, MCPError> {
+        let schema = json!({ "type": "object" }).as_object().unwrap().clone();
+        Ok(vec![
+            Tool::new("echo", "Echo the arguments", schema).served(None)
+        ])
+    }
+
+    async fn call_tool(
+        &self,
+        name: String,
+        args: Value,
+        _server_id: Option<String>,
+    ) -> Result<Part, MCPError> {
+        if name != "echo" {
+            return Err(MCPError::ToolNotFound(name));
         }
+        if let Some(delay) = args.get("delay_ms").and_then(Value::as_u64) {
+            tokio::time::sleep(std::time::Duration::from_millis(delay)).await;
+        }
+        Ok(Part::FunctionResponse {
+            id: None,
+            name,
+            response: args,
+            parts: vec![],
+            finished: true,
+        })
     }
-}
 
-#[async_trait]
-impl Client for MockClient {
-    type ModelProvider = ();
+    async fn list_prompts(&self) -> Result<Vec<Served<Prompt>>, MCPError> {
+        Ok(vec![])
+    }
 
-    async fn request(
+    async fn get_prompt(
         &self,
-        messages: Vec<Message>,
-        _tools: Vec<Tool>,
-    ) -> Result<Response, ClientError> {
-        self.requests.lock().unwrap().push(messages);
-        let mut responses = self.responses.lock().unwrap();
-        if !responses.is_empty() {
-            Ok(responses.remove(0))
-        } else {
-            Err(ClientError::ProviderError(
-                "No more mock responses".to_string(),
-            ))
-        }
+        prompt: &Served<Prompt>,
+        _args: Option<serde_json::Map<String, Value>>,
+    ) -> Result<Served<GetPromptResult>, MCPError> {
+        Err(MCPError::PromptNotFound(prompt.value.name.clone()))
     }
 
-    fn model_options(&self) -> &ModelOptions<Self::ModelProvider> {
-        unimplemented!()
+    async fn list_resources(&self) -> Result<Vec<Served<Resource>>, MCPError> {
+        Ok(vec![])
     }
 
-    fn transport_options(&self) -> &TransportOptions {
-        unimplemented!()
+    async fn read_resource(
+        &self,
+        resource: &Served<Resource>,
+    ) -> Result<Served<ReadResourceResult>, MCPError> {
+        Err(MCPError::ResourceNotFound(resource.value.uri.clone()))
+    }
+}
+
+fn tool_call(name: &str, arguments: Value) -> Response {
+    Response {
+        data: vec![Message::Assistant(vec![Part::FunctionCall {
+            id: Some("call_1".to_string()),
+            name: name.to_string(),
+            arguments,
+            signature: None,
+            raw_arguments: None,
+            finished: true,
+        }])],
+        usage: Usage::default(),
+        finish: FinishReason::ToolCalls,
+        alternatives: Vec::new(),
+        model: None,
+        iterations: Vec::new(),
+    }
+}
+
+fn text_reply(text: &str) -> Response {
+    Response {
+        data: vec![Message::Assistant(vec![Part::Text {
+            content: text.to_string(),
+            finished: true,
+        }])],
+        usage: Usage::default(),
+        finish: FinishReason::Stop,
+        alternatives: Vec::new(),
+        model: None,
+        iterations: Vec::new(),
     }
 }
 
@@ -59,9 +120,12 @@ async fn test_agent_simple_chat() {
         }])],
         usage: Usage::default(),
         finish: FinishReason::Stop,
+        alternatives: Vec::new(),
+        model: None,
+        iterations: Vec::new(),
     };
 
-    let client = MockClient::new(vec![expected_response]);
+    let client = MockClient::with_responses(vec![expected_response]);
     let agent = Agent::new(client);
 
     let messages = vec![Message::User(vec![Part::Text {
@@ -82,3 +146,1162 @@ async fn test_agent_simple_chat() {
         panic!("Expected assistant message");
     }
 }
+
+#[tokio::test]
+async fn test_agent_chat_in_conversation() {
+    let reply = |text: &str| Response {
+        data: vec![Message::Assistant(vec![Part::Text {
+            content: text.to_string(),
+            finished: true,
+        }])],
+        usage: Usage::default(),
+        finish: FinishReason::Stop,
+        alternatives: Vec::new(),
+        model: None,
+        iterations: Vec::new(),
+    };
+    let user = |text: &str| {
+        Message::User(vec![Part::Text {
+            content: text.to_string(),
+            finished: true,
+        }])
+    };
+
+    let client = MockClient::with_responses(vec![reply("Hello"), reply("Fine")]);
+    let agent = Agent::new(client.clone());
+    let mut conversation = Conversation::new();
+
+    agent.chat_in(&mut conversation, user("Hi")).await.unwrap();
+    agent
+        .chat_in(&mut conversation, user("How are you?"))
+        .await
+        .unwrap();
+
+    assert_eq!(conversation.len(), 4);
+    assert_eq!(client.requests()[1].messages.len(), 3);
+
+    // A failed request leaves the conversation untouched.
+    assert!(agent.chat_in(&mut conversation, user("?")).await.is_err());
+    assert_eq!(conversation.len(), 4);
+}
+
+#[derive(Default)]
+struct RecordingHooks {
+    events: Arc<Mutex<Vec<String>>>,
+}
+
+#[async_trait]
+impl AgentHooks for RecordingHooks {
+    async fn on_iteration(&self, iteration: usize) {
+        self.events
+            .lock()
+            .unwrap()
+            .push(format!("iteration {}", iteration));
+    }
+
+    async fn on_llm_request(&self, messages: &[Message], _tools: &[Tool]) {
+        self.events
+            .lock()
+            .unwrap()
+            .push(format!("request {}", messages.len()));
+    }
+
+    async fn on_llm_response(&self, response: &Response) {
+        self.events
+            .lock()
+            .unwrap()
+            .push(format!("response {:?}", response.finish));
+    }
+
+    async fn on_tool_call(&self, name: &str, arguments: &mut Value) {
+        arguments["audited"] = json!(true);
+        self.events.lock().unwrap().push(format!("call {}", name));
+    }
+
+    async fn on_tool_result(&self, name: &str, result: &Part) {
+        if let Part::FunctionResponse { response, .. } = result {
+            self.events
+                .lock()
+                .unwrap()
+                .push(format!("result {} {}", name, response));
+        }
+    }
+
+    fn on_abort(&self, partial: &Response) {
+        self.events.lock().unwrap().push(format!(
+            "abort {:?} after {} messages",
+            partial.finish,
+            partial.data.len()
+        ));
+    }
+}
+
+#[tokio::test]
+async fn test_agent_hooks() {
+    let client = MockClient::with_responses(vec![
+        tool_call("echo", json!({ "text": "hi" })),
+        text_reply("Done"),
+    ]);
+    let hooks = RecordingHooks::default();
+    let events = hooks.events.clone();
+    let agent = Agent::new(client).with_server(EchoServer).with_hooks(hooks);
+
+    let messages = vec![Message::User(vec![Part::Text {
+        content: "Echo hi".to_string(),
+        finished: true,
+    }])];
+    agent.chat(messages).await.unwrap();
+
+    assert_eq!(
+        *events.lock().unwrap(),
+        vec![
+            "iteration 0",
+            "request 1",
+            "response ToolCalls",
+            "call echo",
+            r#"result echo {"audited":true,"text":"hi"}"#,
+            "iteration 1",
+            "request 3",
+            "response Stop",
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_agent_dry_run() {
+    let client = MockClient::with_responses(vec![
+        tool_call("echo", json!({ "text": "hi" })),
+        text_reply("Done"),
+    ]);
+    let hooks = RecordingHooks::default();
+    let events = hooks.events.clone();
+    let agent = Agent::new(client).with_server(EchoServer).with_hooks(hooks);
+
+    let messages = vec![Message::User(vec![Part::Text {
+        content: "Echo hi".to_string(),
+        finished: true,
+    }])];
+    let preview = agent
+        .dry_run(
+            messages,
+            |name: &str, _arguments: &Value| json!({ "stub": name }),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(preview.steps.len(), 1);
+    let step = &preview.steps[0];
+    assert_eq!(step.iteration, 0);
+    assert!(matches!(
+        &step.calls[..],
+        [Part::FunctionCall { name, arguments, .. }] if name == "echo" && *arguments == json!({ "text": "hi" })
+    ));
+    assert!(matches!(
+        &step.results[..],
+        [Part::FunctionResponse { id: Some(id), response, .. }]
+            if id == "call_1" && *response == json!({ "stub": "echo" })
+    ));
+    assert_eq!(preview.response.data.len(), 3);
+    assert_eq!(
+        preview.response.data.last().unwrap().content().as_deref(),
+        Some("Done")
+    );
+    assert!(!events.lock().unwrap().iter().any(|e| e.starts_with("call")));
+}
+
+/// Redacts tool results and answers mentioning secrets, and rejects user input
+/// mentioning them.
+struct SecretPolicy;
+
+#[async_trait]
+impl SafetyPolicy for SecretPolicy {
+    async fn screen(
+        &self,
+        message: &Message,
+        stage: SafetyStage,
+    ) -> Result<SafetyVerdict, ClientError> {
+        if !serde_json::to_string(message).unwrap().contains("secret") {
+            return Ok(SafetyVerdict::Allow);
+        }
+        let redacted = |part: &Part| match part {
+            Part::FunctionCall { .. } => part.clone(),
+            Part::FunctionResponse { id, name, .. } => Part::FunctionResponse {
+                id: id.clone(),
+                name: name.clone(),
+                response: json!("[redacted]"),
+                parts: vec![],
+                finished: true,
+            },
+            _ => Part::Text {
+                content: "[redacted]".to_string(),
+                finished: true,
+            },
+        };
+        let tool_result = message
+            .parts()
+            .iter()
+            .any(|p| matches!(p, Part::FunctionResponse { .. }));
+        Ok(match (stage, message) {
+            (SafetyStage::Input, _) if !tool_result => {
+                SafetyVerdict::Reject("mentions a secret".to_string())
+            }
+            (_, Message::User(parts)) => {
+                SafetyVerdict::Redact(Message::User(parts.iter().map(redacted).collect()))
+            }
+            (_, Message::Assistant(parts)) => {
+                SafetyVerdict::Redact(Message::Assistant(parts.iter().map(redacted).collect()))
+            }
+        })
+    }
+}
+
+#[tokio::test]
+async fn test_agent_safety_policy() {
+    let user = |text: &str| {
+        vec![Message::User(vec![Part::Text {
+            content: text.to_string(),
+            finished: true,
+        }])]
+    };
+
+    let client = MockClient::with_responses(vec![text_reply("Never")]);
+    let agent = Agent::new(client).with_safety_policy(SecretPolicy);
+    let error = agent.chat(user("Tell me the secret")).await.unwrap_err();
+    assert!(matches!(error.source, ClientError::PolicyViolation(_)));
+    assert!(error.partial.data.is_empty());
+
+    let client = MockClient::with_responses(vec![
+        tool_call("echo", json!({ "text": "the secret" })),
+        text_reply("The secret is 42"),
+    ]);
+    let agent = Agent::new(client)
+        .with_server(EchoServer)
+        .with_safety_policy(SecretPolicy);
+    let response = agent.chat(user("Echo something")).await.unwrap();
+    assert_eq!(response.data.len(), 3);
+    assert!(matches!(
+        &response.data[1].parts()[0],
+        Part::FunctionResponse { response, .. } if *response == json!("[redacted]")
+    ));
+    assert_eq!(response.data[2].content().as_deref(), Some("[redacted]"));
+}
+
+#[tokio::test]
+async fn test_agent_prices_served_model() {
+    let mut reply = text_reply("Hi");
+    reply.model = Some("snapshot-2025".to_string());
+    reply.usage.prompt_tokens = Some(1_000_000);
+    let prices = PriceTable::empty().with_price("snapshot", ModelPricing::new(2.0, 0.0));
+    let tracker = Arc::new(CostTracker::with_prices(prices));
+    let agent =
+        Agent::new(MockClient::with_responses(vec![reply])).with_cost_tracker(tracker.clone());
+
+    let response = agent
+        .chat(vec![Message::User(vec![Part::Text {
+            content: "Hello".to_string(),
+            finished: true,
+        }])])
+        .await
+        .unwrap();
+    assert_eq!(response.model.as_deref(), Some("snapshot-2025"));
+    assert_eq!(tracker.total().cost, 2.0);
+}
+
+#[tokio::test]
+async fn test_agent_iteration_usage() {
+    let reply = |mut response: Response, model: &str, tokens: u32| {
+        response.model = Some(model.to_string());
+        response.usage.prompt_tokens = Some(tokens);
+        response
+    };
+    let client = MockClient::with_responses(vec![
+        reply(tool_call("echo", json!({ "text": "a" })), "small", 10),
+        reply(tool_call("echo", json!({ "text": "b" })), "large", 20),
+        reply(text_reply("Done"), "small", 30),
+    ]);
+    let agent = Agent::new(client).with_server(EchoServer);
+
+    let response = agent.chat(vec![]).await.unwrap();
+    let tokens: Vec<_> = response
+        .iterations
+        .iter()
+        .map(|iteration| iteration.usage.prompt_tokens)
+        .collect();
+    assert_eq!(tokens, vec![Some(10), Some(20), Some(30)]);
+    assert_eq!(response.usage.prompt_tokens, Some(60));
+    assert_eq!(
+        response.iterations.iter().map(|i| &i.usage).sum::<Usage>(),
+        response.usage
+    );
+
+    let by_model = response.usage_by_model("default");
+    assert_eq!(by_model.len(), 2);
+    assert_eq!(by_model["small"].prompt_tokens, Some(40));
+    assert_eq!(by_model["large"].prompt_tokens, Some(20));
+}
+
+#[tokio::test]
+async fn test_agent_tool_result_policy() {
+    let text = "x".repeat(100) + "end";
+    let result = |response: &Response| match &response.data[1].parts()[0] {
+        Part::FunctionResponse { response, .. } => response.clone(),
+        _ => panic!("expected a tool result"),
+    };
+
+    let client = MockClient::with_responses(vec![
+        tool_call("echo", json!({ "text": text })),
+        text_reply("Done"),
+    ]);
+    let agent = Agent::new(client)
+        .with_server(EchoServer)
+        .with_tool_result_policy(
+            ToolResultPolicy::new()
+                .with_max_bytes(1000)
+                .with_max_tokens(5)
+                .with_strategy(TruncationStrategy::Tail),
+        );
+    let response = agent.chat(vec![]).await.unwrap();
+    assert_eq!(
+        result(&response),
+        json!({ "content": "xxxxxxxxxxxxxxxend\"}", "note": "Truncated to the last 20 of 114 bytes" })
+    );
+
+    let mut summary = text_reply("A hundred x, then end.");
+    summary.usage.completion_tokens = Some(7);
+    let client = MockClient::with_responses(vec![
+        tool_call("echo", json!({ "text": text })),
+        summary,
+        text_reply("Done"),
+    ]);
+    let tracker = Arc::new(CostTracker::new());
+    let hooks = RecordingHooks::default();
+    let events = hooks.events.clone();
+    let agent = Agent::new(client.clone())
+        .with_server(EchoServer)
+        .with_hooks(hooks)
+        .with_cost_tracker(tracker.clone())
+        .with_tool_result_policy(
+            ToolResultPolicy::new()
+                .with_max_bytes(50)
+                .with_strategy(TruncationStrategy::Summarize),
+        );
+    let response = agent.chat(vec![]).await.unwrap();
+    assert_eq!(result(&response)["summary"], "A hundred x, then end.");
+    assert_eq!(client.requests().len(), 3);
+    // The summary is requested like a turn and counts towards the run.
+    assert_eq!(response.usage.completion_tokens, Some(7));
+    assert_eq!(tracker.total().requests, 3);
+    let requests = events
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|e| e.starts_with("request"))
+        .count();
+    assert_eq!(requests, 3);
+
+    // Small results are kept as they are.
+    let client = MockClient::with_responses(vec![
+        tool_call("echo", json!({ "text": "hi" })),
+        text_reply("Done"),
+    ]);
+    let agent = Agent::new(client)
+        .with_server(EchoServer)
+        .with_tool_result_policy(ToolResultPolicy::new().with_max_bytes(50));
+    let response = agent.chat(vec![]).await.unwrap();
+    assert_eq!(result(&response), json!({ "text": "hi" }));
+}
+
+/// Echo server whose calls wait until `barrier` is reached by as many calls
+/// as it was created for, failing if that takes too long.
+struct BarrierServer {
+    barrier: Arc<tokio::sync::Barrier>,
+}
+
+#[async_trait]
+impl MCPServer for BarrierServer {
+    async fn list_tools(&self) -> Result<Vec<Served<Tool>>, MCPError> {
+        EchoServer.list_tools().await
+    }
+
+    async fn call_tool(
+        &self,
+        name: String,
+        args: Value,
+        server_id: Option<String>,
+    ) -> Result<Part, MCPError> {
+        let timeout = std::time::Duration::from_secs(5);
+        tokio::time::timeout(timeout, self.barrier.wait())
+            .await
+            .map_err(|_| MCPError::Timeout {
+                operation: "call_tool",
+                timeout,
+            })?;
+        EchoServer.call_tool(name, args, server_id).await
+    }
+
+    async fn list_prompts(&self) -> Result<Vec<Served<Prompt>>, MCPError> {
+        Ok(vec![])
+    }
+
+    async fn get_prompt(
+        &self,
+        prompt: &Served<Prompt>,
+        _args: Option<serde_json::Map<String, Value>>,
+    ) -> Result<Served<GetPromptResult>, MCPError> {
+        Err(MCPError::PromptNotFound(prompt.value.name.clone()))
+    }
+
+    async fn list_resources(&self) -> Result<Vec<Served<Resource>>, MCPError> {
+        Ok(vec![])
+    }
+
+    async fn read_resource(
+        &self,
+        resource: &Served<Resource>,
+    ) -> Result<Served<ReadResourceResult>, MCPError> {
+        Err(MCPError::ResourceNotFound(resource.value.uri.clone()))
+    }
+}
+
+#[tokio::test]
+async fn test_agent_parallel_tool_calls() {
+    let call = |id: &str, delay: u64| Part::FunctionCall {
+        id: Some(id.to_string()),
+        name: "echo".to_string(),
+        arguments: json!({ "delay_ms": delay }),
+        signature: None,
+        raw_arguments: None,
+        finished: true,
+    };
+    let calls = Response {
+        data: vec![Message::Assistant(vec![
+            call("a", 200),
+            call("b", 100),
+            call("c", 200),
+        ])],
+        usage: Usage::default(),
+        finish: FinishReason::ToolCalls,
+        alternatives: Vec::new(),
+        model: None,
+        iterations: Vec::new(),
+    };
+    let client = MockClient::with_responses(vec![calls, text_reply("Done")]);
+    // Every call waits for the other two, so the run only succeeds if all
+    // three are in flight at once.
+    let agent = Agent::new(client).with_server(BarrierServer {
+        barrier: Arc::new(tokio::sync::Barrier::new(3)),
+    });
+
+    let response = agent.chat(vec![]).await.unwrap();
+
+    let results: Vec<_> = response
+        .data
+        .iter()
+        .flat_map(|m| m.parts())
+        .filter_map(|p| match p {
+            Part::FunctionResponse { id, response, .. } => Some((id.clone(), response.clone())),
+            _ => None,
+        })
+        .collect();
+    assert!(results
+        .iter()
+        .all(|(_, response)| response.get("error").is_none()));
+    let ids: Vec<_> = results.into_iter().filter_map(|(id, _)| id).collect();
+    assert_eq!(ids, vec!["a", "b", "c"]);
+}
+
+#[tokio::test]
+async fn test_agent_timeout_cancels_tool_calls() {
+    let client = MockClient::with_responses(vec![
+        tool_call("echo", json!({ "delay_ms": 5000 })),
+        text_reply("Done"),
+    ]);
+    let agent = Agent::new(client)
+        .with_server(EchoServer)
+        .with_timeout(std::time::Duration::from_millis(100));
+
+    let started = std::time::Instant::now();
+    let result = agent.chat(vec![]).await;
+
+    assert!(matches!(
+        result,
+        Err(AgentError {
+            source: ClientError::DeadlineExceeded,
+            ..
+        })
+    ));
+    assert!(started.elapsed() < std::time::Duration::from_secs(1));
+}
+
+#[tokio::test]
+async fn test_agent_error_keeps_partial_response() {
+    // The client runs out of responses after the tool call.
+    let client = MockClient::with_responses(vec![tool_call("echo", json!({ "text": "hi" }))]);
+    let agent = Agent::new(client).with_server(EchoServer);
+
+    let error = agent.chat(vec![]).await.unwrap_err();
+
+    assert!(matches!(error.source, ClientError::ProviderError(_)));
+    assert_eq!(error.partial.finish, FinishReason::Error);
+    assert_eq!(error.partial.data.len(), 2);
+    assert!(matches!(
+        error.partial.data[1].parts()[0],
+        Part::FunctionResponse { .. }
+    ));
+}
+
+#[tokio::test]
+async fn test_agent_max_iterations_returns_partial_transcript() {
+    let script = || {
+        vec![
+            tool_call("echo", json!({ "text": "one" })),
+            tool_call("echo", json!({ "text": "two" })),
+            text_reply("Done"),
+        ]
+    };
+    let assert_partial = |response: &Response| {
+        assert_eq!(response.finish, FinishReason::MaxIterations);
+        assert_eq!(response.iterations.len(), 2);
+        assert_eq!(response.data.len(), 4);
+        assert!(matches!(
+            &response.data[2].parts()[..],
+            [Part::FunctionCall { arguments, .. }] if *arguments == json!({ "text": "two" })
+        ));
+        assert!(matches!(
+            &response.data[3].parts()[..],
+            [Part::FunctionResponse { name, .. }] if name == "echo"
+        ));
+    };
+
+    let agent = Agent::new(MockClient::with_responses(script()))
+        .with_server(EchoServer)
+        .with_max_iterations(2);
+    assert_partial(&agent.chat(vec![]).await.unwrap());
+
+    let agent = Agent::new(MockClient::with_responses(script()))
+        .with_server(EchoServer)
+        .with_max_iterations(2);
+    let responses: Vec<Response> = agent.chat_stream(vec![]).try_collect().await.unwrap();
+    assert_partial(responses.last().unwrap());
+}
+
+#[tokio::test]
+async fn test_agent_max_iterations_error() {
+    let client = MockClient::with_responses(vec![
+        tool_call("echo", json!({})),
+        tool_call("echo", json!({})),
+        text_reply("Done"),
+    ]);
+    let agent = Agent::new(client)
+        .with_server(EchoServer)
+        .with_max_iterations(1)
+        .on_max_iterations(OnMaxIterations::Error);
+
+    let error = agent.chat(vec![]).await.unwrap_err();
+    assert!(matches!(error.source, ClientError::Config(_)));
+    // The transcript up to the limit is kept on the error.
+    assert_eq!(error.partial.finish, FinishReason::Error);
+    assert_eq!(error.partial.data.len(), 2);
+}
+
+#[tokio::test]
+async fn test_agent_on_max_iterations_final_answer() {
+    let looping = || {
+        MockClient::with_responses(vec![
+            tool_call("echo", json!({})),
+            tool_call("echo", json!({})),
+            text_reply("Done"),
+        ])
+    };
+
+    let client = looping();
+    let agent = Agent::new(client.clone())
+        .with_server(EchoServer)
+        .with_max_iterations(2)
+        .on_max_iterations(OnMaxIterations::FinalAnswer);
+    let response = agent.chat(vec![]).await.unwrap();
+    assert_eq!(response.finish, FinishReason::Stop);
+    assert_eq!(
+        response.data.last().unwrap().content().as_deref(),
+        Some("Done")
+    );
+    // The wrap-up instruction is sent but not part of the transcript.
+    assert_eq!(response.data.len(), 5);
+    let last_request = client.requests().pop().unwrap().messages;
+    assert!(last_request
+        .last()
+        .unwrap()
+        .content()
+        .unwrap()
+        .contains("Answer now"));
+}
+
+#[tokio::test]
+async fn test_agent_forced_final_answer() {
+    let client = MockClient::with_responses(vec![tool_call("echo", json!({})), text_reply("Done")]);
+    let agent = Agent::new(client.clone())
+        .with_server(EchoServer)
+        .with_max_iterations(2)
+        .with_forced_final_answer(true);
+
+    let response = agent.chat(vec![]).await.unwrap();
+    assert_eq!(response.finish, FinishReason::Stop);
+    assert_eq!(response.data.len(), 3);
+    assert_eq!(response.iterations.len(), 2);
+
+    let requests = client.requests();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[0].messages.len(), 0);
+    // The last permitted request goes without tools, ending with the instruction.
+    assert!(requests[1].tools.is_empty());
+    assert_eq!(requests[1].messages.len(), 3);
+    assert!(requests[1].messages[2]
+        .content()
+        .unwrap()
+        .contains("Answer now"));
+}
+
+#[tokio::test]
+async fn test_agent_mcp_call_timeout() {
+    let client = MockClient::with_responses(vec![
+        tool_call("echo", json!({ "delay_ms": 5000 })),
+        text_reply("Done"),
+    ]);
+    let agent = Agent::new(client)
+        .with_server(EchoServer)
+        .with_mcp_timeouts(MCPTimeouts {
+            call_tool: Some(std::time::Duration::from_millis(50)),
+            ..MCPTimeouts::default()
+        });
+
+    let response = agent.chat(vec![]).await.unwrap();
+
+    // The hung call is reported to the model, which then answers.
+    match &response.data[1].parts()[0] {
+        Part::FunctionResponse { response, .. } => {
+            assert!(response["error"].as_str().unwrap().contains("timed out"))
+        }
+        other => panic!("Expected function response, got {:?}", other),
+    }
+    assert_eq!(response.data[2].content().as_deref(), Some("Done"));
+}
+
+#[tokio::test]
+async fn test_agent_chat_with_cancellation() {
+    let client = MockClient::with_responses(vec![
+        tool_call("echo", json!({ "delay_ms": 5000 })),
+        text_reply("Done"),
+    ]);
+    let agent = Agent::new(client).with_server(EchoServer);
+
+    let cancel = tokio_util::sync::CancellationToken::new();
+    let trigger = cancel.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        trigger.cancel();
+    });
+
+    let result = agent.chat_with_cancellation(vec![], cancel).await;
+    assert!(matches!(
+        result,
+        Err(AgentError {
+            source: ClientError::StreamCancelled,
+            ..
+        })
+    ));
+}
+
+#[tokio::test]
+async fn test_agent_title() {
+    let user = Message::User(vec![Part::Text {
+        content: "How do I bake sourdough bread?".to_string(),
+        finished: true,
+    }]);
+
+    let client = MockClient::with_responses(vec![text_reply("\"Baking Sourdough Bread.\"")]);
+    let agent = Agent::new(client.clone());
+
+    assert_eq!(
+        agent.title(std::slice::from_ref(&user)).await.unwrap(),
+        "Baking Sourdough Bread"
+    );
+    // The second call is served from the cache.
+    assert_eq!(
+        agent.title(&[user]).await.unwrap(),
+        "Baking Sourdough Bread"
+    );
+    assert_eq!(client.requests().len(), 1);
+    assert!(client.requests()[0].messages[0]
+        .content()
+        .unwrap()
+        .contains("User: How do I bake sourdough bread?"));
+
+    assert!(agent.title(&[]).await.is_err());
+}
+
+#[tokio::test]
+async fn test_agent_eager_tool_execution() {
+    let call = |id: &str, arguments: Value| Part::FunctionCall {
+        id: Some(id.to_string()),
+        name: "echo".to_string(),
+        arguments,
+        signature: None,
+        raw_arguments: None,
+        finished: true,
+    };
+    // The arguments of `b` stream in many fragments, while `a` could already run.
+    let mut calls = tool_call("echo", json!({}));
+    calls.data = vec![Message::Assistant(vec![
+        call("a", json!({ "delay_ms": 300 })),
+        call("b", json!({ "text": "x".repeat(100) })),
+    ])];
+    let delay = Duration::from_millis(20);
+    let streamed =
+        delay * (stream_deltas(&calls).len() + stream_deltas(&text_reply("Done")).len()) as u32;
+
+    let run = |eager: bool| {
+        let client = MockClient::with_responses([calls.clone(), text_reply("Done")])
+            .with_stream_delay(delay);
+        async move {
+            let agent = Agent::new(client)
+                .with_server(EchoServer)
+                .with_eager_tool_execution(eager);
+            let started = std::time::Instant::now();
+            let responses: Vec<Response> = agent.chat_stream(vec![]).try_collect().await.unwrap();
+            (started.elapsed(), responses)
+        }
+    };
+
+    let (sequential, _) = run(false).await;
+    assert!(sequential >= streamed + Duration::from_millis(300));
+
+    let (elapsed, responses) = run(true).await;
+    assert!(elapsed < streamed + Duration::from_millis(200));
+
+    let ids: Vec<_> = responses
+        .last()
+        .unwrap()
+        .data
+        .iter()
+        .flat_map(|m| m.parts())
+        .filter_map(|p| match p {
+            Part::FunctionResponse { id, .. } => id.clone(),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(ids, vec!["a", "b"]);
+}
+
+#[tokio::test]
+async fn test_agent_stream_dropped_early() {
+    let client = MockClient::with_responses([
+        tool_call("echo", json!({ "delay_ms": 5000 })),
+        text_reply("Done"),
+    ]);
+    let hooks = RecordingHooks::default();
+    let events = hooks.events.clone();
+    let agent = Agent::new(client).with_server(EchoServer).with_hooks(hooks);
+    let cancel = tokio_util::sync::CancellationToken::new();
+
+    let mut stream = agent.chat_stream_with_cancellation(vec![], cancel.clone());
+    // Snapshots of the model turn, up to the finished tool call.
+    while stream.next().await.unwrap().unwrap().finish != FinishReason::ToolCalls {}
+    // The tool call is still running.
+    let next = tokio::time::timeout(std::time::Duration::from_millis(50), stream.next()).await;
+    assert!(next.is_err());
+    drop(stream);
+
+    assert_eq!(
+        events.lock().unwrap().last().unwrap(),
+        "abort Error after 1 messages"
+    );
+    assert!(!cancel.is_cancelled());
+
+    // Streams that ran to completion are not reported.
+    events.lock().unwrap().clear();
+    let responses: Vec<Response> = agent.chat_stream(vec![]).try_collect().await.unwrap();
+    assert_eq!(responses.last().unwrap().finish, FinishReason::Stop);
+    assert!(!events
+        .lock()
+        .unwrap()
+        .iter()
+        .any(|e| e.starts_with("abort")));
+}
+
+/// MCP server whose `install` tool adds an `echo` tool and announces the change.
+struct InstallingServer {
+    installed: Mutex<bool>,
+    changes: tokio::sync::broadcast::Sender<ListChanged>,
+}
+
+#[async_trait]
+impl MCPServer for InstallingServer {
+    async fn list_tools(&self) -> Result<Vec<Served<Tool>>, MCPError> {
+        let schema = json!({ "type": "object" }).as_object().unwrap().clone();
+        let mut tools = vec![Tool::new(
+            "install",
+            "Install the echo tool",
+            schema.clone(),
+        )];
+        if *self.installed.lock().unwrap() {
+            tools.push(Tool::new("echo", "Echo the arguments", schema));
+        }
+        Ok(tools.into_iter().map(|t| t.served(None)).collect())
+    }
+
+    async fn call_tool(
+        &self,
+        name: String,
+        _args: Value,
+        _server_id: Option<String>,
+    ) -> Result<Part, MCPError> {
+        *self.installed.lock().unwrap() = true;
+        self.changes.send(ListChanged::Tools).unwrap();
+        Ok(Part::FunctionResponse {
+            id: None,
+            name,
+            response: json!({ "installed": "echo" }),
+            parts: vec![],
+            finished: true,
+        })
+    }
+
+    async fn list_prompts(&self) -> Result<Vec<Served<Prompt>>, MCPError> {
+        Ok(vec![])
+    }
+
+    async fn get_prompt(
+        &self,
+        prompt: &Served<Prompt>,
+        _args: Option<serde_json::Map<String, Value>>,
+    ) -> Result<Served<GetPromptResult>, MCPError> {
+        Err(MCPError::PromptNotFound(prompt.value.name.clone()))
+    }
+
+    async fn list_resources(&self) -> Result<Vec<Served<Resource>>, MCPError> {
+        Ok(vec![])
+    }
+
+    async fn read_resource(
+        &self,
+        resource: &Served<Resource>,
+    ) -> Result<Served<ReadResourceResult>, MCPError> {
+        Err(MCPError::ResourceNotFound(resource.value.uri.clone()))
+    }
+
+    fn subscribe(&self) -> ListChanges {
+        ListChanges::new(self.changes.subscribe())
+    }
+}
+
+/// Hooks recording the tool names of every model request.
+#[derive(Default)]
+struct ToolRecorder {
+    requests: Arc<Mutex<Vec<Vec<String>>>>,
+}
+
+#[async_trait]
+impl AgentHooks for ToolRecorder {
+    async fn on_llm_request(&self, _messages: &[Message], tools: &[Tool]) {
+        let names = tools.iter().map(|t| t.name.to_string()).collect();
+        self.requests.lock().unwrap().push(names);
+    }
+}
+
+#[tokio::test]
+async fn test_agent_refreshes_changed_tools() {
+    let client = MockClient::with_responses(vec![
+        tool_call("install", json!({})),
+        text_reply("Installed"),
+    ]);
+    let hooks = ToolRecorder::default();
+    let requests = hooks.requests.clone();
+    let agent = Agent::new(client)
+        .with_server(InstallingServer {
+            installed: Mutex::new(false),
+            changes: tokio::sync::broadcast::channel(4).0,
+        })
+        .with_hooks(hooks);
+
+    agent.chat(vec![]).await.unwrap();
+
+    assert_eq!(
+        *requests.lock().unwrap(),
+        vec![vec!["install"], vec!["install", "echo"]]
+    );
+}
+
+/// Echo server counting its tool calls.
+#[derive(Default)]
+struct CountingServer {
+    calls: Arc<Mutex<usize>>,
+}
+
+#[async_trait]
+impl MCPServer for CountingServer {
+    async fn list_tools(&self) -> Result<Vec<Served<Tool>>, MCPError> {
+        EchoServer.list_tools().await
+    }
+
+    async fn call_tool(
+        &self,
+        name: String,
+        args: Value,
+        server_id: Option<String>,
+    ) -> Result<Part, MCPError> {
+        *self.calls.lock().unwrap() += 1;
+        EchoServer.call_tool(name, args, server_id).await
+    }
+
+    async fn list_prompts(&self) -> Result<Vec<Served<Prompt>>, MCPError> {
+        Ok(vec![])
+    }
+
+    async fn get_prompt(
+        &self,
+        prompt: &Served<Prompt>,
+        _args: Option<serde_json::Map<String, Value>>,
+    ) -> Result<Served<GetPromptResult>, MCPError> {
+        Err(MCPError::PromptNotFound(prompt.value.name.clone()))
+    }
+
+    async fn list_resources(&self) -> Result<Vec<Served<Resource>>, MCPError> {
+        Ok(vec![])
+    }
+
+    async fn read_resource(
+        &self,
+        resource: &Served<Resource>,
+    ) -> Result<Served<ReadResourceResult>, MCPError> {
+        Err(MCPError::ResourceNotFound(resource.value.uri.clone()))
+    }
+}
+
+#[tokio::test]
+async fn test_agent_tool_cache() {
+    let script = || {
+        vec![
+            tool_call("echo", json!({ "a": 1, "b": [{ "x": 1, "y": 2 }] })),
+            tool_call("echo", json!({ "b": [{ "y": 2, "x": 1 }], "a": 1 })),
+            tool_call("echo", json!({ "a": 2 })),
+            text_reply("Done"),
+        ]
+    };
+    let server = CountingServer::default();
+    let calls = server.calls.clone();
+    let agent = Agent::new(MockClient::with_responses([script(), script()].concat()))
+        .with_server(server)
+        .with_tool_cache(std::time::Duration::from_secs(60));
+
+    let response = agent.chat(vec![]).await.unwrap();
+    assert_eq!(*calls.lock().unwrap(), 2);
+    let results: Vec<&Value> = response
+        .data
+        .iter()
+        .flat_map(|m| m.parts())
+        .filter_map(|part| match part {
+            Part::FunctionResponse { id, response, .. } => {
+                assert_eq!(id.as_deref(), Some("call_1"));
+                Some(response)
+            }
+            _ => None,
+        })
+        .collect();
+    assert_eq!(results[0], results[1]);
+
+    // Results are not shared between runs outside a conversation.
+    agent.chat(vec![]).await.unwrap();
+    assert_eq!(*calls.lock().unwrap(), 4);
+}
+
+#[tokio::test]
+async fn test_agent_multi_server_namespacing() {
+    let server = CountingServer::default();
+    let calls = server.calls.clone();
+    let multi = MultiMCPServer::new()
+        .add_filtered_server(EchoServer, ToolFilter::new().with_prefix("first"))
+        .add_filtered_server(server, ToolFilter::new().with_prefix("second"))
+        .add_filtered_server(EchoServer, ToolFilter::new().with_deny("*"));
+    let names: Vec<String> = multi
+        .list_tools()
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|t| t.value.name.to_string())
+        .collect();
+    assert_eq!(names, vec!["first__echo", "second__echo"]);
+
+    let client = MockClient::with_responses(vec![
+        tool_call("second__echo", json!({ "text": "hi" })),
+        text_reply("Done"),
+    ]);
+    let agent = Agent::new(client).with_server(multi);
+    let response = agent.chat(vec![]).await.unwrap();
+    assert_eq!(*calls.lock().unwrap(), 1);
+    assert!(response.data.iter().flat_map(|m| m.parts()).any(|part| {
+        matches!(part, Part::FunctionResponse { name, response, .. }
+            if name == "second__echo" && response["text"] == "hi")
+    }));
+
+    // Tools over the limit are neither listed nor callable.
+    let server = CountingServer::default();
+    let calls = server.calls.clone();
+    let multi = MultiMCPServer::new()
+        .add_filtered_server(EchoServer, ToolFilter::new().with_prefix("first"))
+        .add_filtered_server(server, ToolFilter::new().with_prefix("second"))
+        .with_max_tools(1);
+    assert_eq!(multi.list_tools().await.unwrap().len(), 1);
+    assert!(matches!(
+        multi
+            .call_tool("second__echo".to_string(), json!({}), None)
+            .await,
+        Err(MCPError::ToolNotFound(_))
+    ));
+    assert!(multi
+        .call_tool("first__echo".to_string(), json!({}), None)
+        .await
+        .is_ok());
+    let client = MockClient::with_responses(vec![
+        tool_call("second__echo", json!({ "text": "hi" })),
+        text_reply("Done"),
+    ]);
+    let response = Agent::new(client)
+        .with_server(multi)
+        .chat(vec![])
+        .await
+        .unwrap();
+    assert_eq!(*calls.lock().unwrap(), 0);
+    assert!(response.data.iter().flat_map(|m| m.parts()).any(|part| {
+        matches!(part, Part::FunctionResponse { name, response, .. }
+            if name == "second__echo" && response["error"].as_str().unwrap().contains("not found"))
+    }));
+
+    let multi =
+        MultiMCPServer::new().add_filtered_server(EchoServer, ToolFilter::new().with_deny("echo"));
+    assert!(matches!(
+        multi.call_tool("echo".to_string(), json!({}), None).await,
+        Err(MCPError::ToolNotFound(_))
+    ));
+}
+
+#[tokio::test]
+async fn test_agent_chat_events() {
+    let call = |id: &str, delay: u64| Part::FunctionCall {
+        id: Some(id.to_string()),
+        name: "echo".to_string(),
+        arguments: json!({ "delay_ms": delay }),
+        signature: None,
+        raw_arguments: None,
+        finished: true,
+    };
+    let mut calls = tool_call("echo", json!({}));
+    calls.data = vec![Message::Assistant(vec![call("slow", 200), call("fast", 0)])];
+    // The reply is streamed word by word.
+    let client = MockClient::with_responses([calls, text_reply("Done for now")]);
+    let agent = Agent::new(client).with_server(EchoServer);
+
+    let events: Vec<AgentEvent> = agent.chat_events(vec![]).try_collect().await.unwrap();
+    let mut kinds = Vec::new();
+    let mut text = String::new();
+    for event in &events {
+        match event {
+            AgentEvent::IterationStart { iteration } => kinds.push(format!("start {}", iteration)),
+            AgentEvent::LlmDelta {
+                delta: StreamDelta::TextDelta { text: t, .. },
+                iteration,
+            } => {
+                assert_eq!(*iteration, 1);
+                text.push_str(t);
+            }
+            AgentEvent::LlmDelta { .. } => {}
+            AgentEvent::ToolCallStarted { id, .. } => {
+                kinds.push(format!("call {}", id.as_deref().unwrap()))
+            }
+            AgentEvent::ToolCallFinished { id, result, .. } => {
+                assert!(matches!(result, Part::FunctionResponse { .. }));
+                kinds.push(format!("result {}", id.as_deref().unwrap()));
+            }
+            AgentEvent::Finished { response, .. } => {
+                kinds.push(format!("finished {:?}", response.finish))
+            }
+        }
+    }
+    assert_eq!(
+        kinds,
+        vec![
+            "start 0",
+            "call slow",
+            "call fast",
+            // Each result is reported as soon as its call returns.
+            "result fast",
+            "result slow",
+            "start 1",
+            "finished Stop"
+        ]
+    );
+    assert_eq!(text, "Done for now");
+}
+
+/// Rejects every tool result.
+struct RejectToolResults;
+
+#[async_trait]
+impl SafetyPolicy for RejectToolResults {
+    async fn screen(
+        &self,
+        message: &Message,
+        _stage: SafetyStage,
+    ) -> Result<SafetyVerdict, ClientError> {
+        let tool_result = message
+            .parts()
+            .iter()
+            .any(|p| matches!(p, Part::FunctionResponse { .. }));
+        Ok(if tool_result {
+            SafetyVerdict::Reject("tool results are off limits".to_string())
+        } else {
+            SafetyVerdict::Allow
+        })
+    }
+}
+
+#[tokio::test]
+async fn test_agent_chat_events_rejected_tool_result() {
+    let client = MockClient::with_responses([tool_call("echo", json!({})), text_reply("Done")]);
+    let agent = Agent::new(client)
+        .with_server(EchoServer)
+        .with_safety_policy(RejectToolResults);
+
+    let events: Vec<_> = agent.chat_events(vec![]).collect().await;
+    let finished = events.iter().find_map(|event| match event {
+        Ok(AgentEvent::ToolCallFinished { result, .. }) => Some(result),
+        _ => None,
+    });
+    assert!(matches!(
+        finished,
+        Some(Part::FunctionResponse { response, .. })
+            if response["error"].as_str().unwrap().contains("off limits")
+    ));
+    assert!(matches!(
+        events.last(),
+        Some(Err(ClientError::PolicyViolation(_)))
+    ));
+}
+
+#[tokio::test]
+async fn test_agent_chat_events_iteration_start_precedes_request() {
+    // The second request fails before the model sends anything.
+    let client = MockClient::with_responses([tool_call("echo", json!({}))]);
+    let agent = Agent::new(client).with_server(EchoServer);
+
+    let mut events = agent.chat_events(vec![]);
+    let mut starts = Vec::new();
+    let error = loop {
+        match events.next().await.unwrap() {
+            Ok(AgentEvent::IterationStart { iteration }) => starts.push(iteration),
+            Ok(_) => {}
+            Err(e) => break e,
+        }
+    };
+    assert_eq!(starts, vec![0, 1]);
+    assert!(error.to_string().contains("No more mock responses"));
+}
@@ -1,6 +1,6 @@
-use unia::client::Client;
+use unia::client::{BoxedClient, Client};
 use unia::model::{Message, Part, Role};
-use unia::providers::{OpenAI, Provider};
+use unia::providers::{Anthropic, Groq, OpenAI, Provider};
 
 #[test]
 fn test_client_creation() {
@@ -8,6 +8,47 @@ fn test_client_creation() {
     assert_eq!(client.model_options().model, "gpt-5");
 }
 
+#[test]
+fn test_client_capabilities() {
+    let gpt = OpenAI::create("test-key".to_string(), "gpt-4o-mini".to_string()).capabilities();
+    assert!(gpt.supports_tools && gpt.supports_vision && gpt.supports_json_mode);
+    assert_eq!(gpt.max_context_tokens, Some(128_000));
+
+    let o1 = OpenAI::create("test-key".to_string(), "o1-mini".to_string()).capabilities();
+    assert!(!o1.supports_vision && !o1.supports_system_prompt);
+
+    let claude =
+        Anthropic::create("test-key".to_string(), "claude-sonnet-4-5".to_string()).capabilities();
+    assert!(claude.supports_streaming && !claude.supports_json_mode);
+
+    let groq = Groq::create("test-key".to_string(), "llama-3.3-70b".to_string()).capabilities();
+    assert!(groq.supports_tools && !groq.supports_vision);
+    assert_eq!(groq.max_context_tokens, None);
+}
+
+#[test]
+fn test_boxed_clients() {
+    let clients = [
+        BoxedClient::new(OpenAI::create("test-key".to_string(), "gpt-5".to_string())),
+        BoxedClient::new(Anthropic::create(
+            "test-key".to_string(),
+            "claude-sonnet-4-5".to_string(),
+        )),
+    ];
+    let providers: Vec<(&str, &str)> = clients
+        .iter()
+        .map(|c| (c.provider(), c.model_options().model.as_str()))
+        .collect();
+    assert_eq!(
+        providers,
+        [("openai", "gpt-5"), ("anthropic", "claude-sonnet-4-5")]
+    );
+    assert!(!clients[1].capabilities().supports_json_mode);
+
+    let agents: Vec<unia::Agent> = clients.into_iter().map(unia::Agent::new).collect();
+    assert_eq!(agents.len(), 2);
+}
+
 #[test]
 fn test_message_construction() {
     let msg = Message::User(vec![Part::Text {
@@ -1,5 +1,5 @@
 use std::time::Duration;
-use unia::options::{ModelOptions, TransportOptions};
+use unia::options::{ModelOptions, RetryPolicy, TransportOptions};
 use unia::providers::OpenAIModel;
 
 #[test]
@@ -14,6 +14,7 @@ fn test_transport_options_builder() {
             timeout,
             proxy,
             headers,
+            ..
         } => {
             assert_eq!(timeout, Some(Duration::from_secs(30)));
             assert_eq!(proxy, Some("http://proxy.example.com".to_string()));
@@ -43,3 +44,32 @@ fn test_model_options_custom() {
     assert_eq!(options.temperature, Some(0.7));
     assert_eq!(options.max_tokens, Some(100));
 }
+
+#[test]
+fn test_retry_policy_backoff() {
+    let policy = RetryPolicy::new()
+        .with_base_delay(Duration::from_millis(100))
+        .with_max_delay(Duration::from_millis(350))
+        .with_jitter(false);
+
+    assert_eq!(policy.backoff(1), Duration::from_millis(100));
+    assert_eq!(policy.backoff(2), Duration::from_millis(200));
+    assert_eq!(policy.backoff(3), Duration::from_millis(350));
+    assert!(policy.should_retry_status(429));
+    assert!(!policy.should_retry_status(400));
+
+    let options = TransportOptions::new().with_retry(policy.clone());
+    assert_eq!(options.retry_policy(), Some(&policy));
+}
+
+#[test]
+fn test_transport_options_user_agent() {
+    let options = TransportOptions::new();
+    assert_eq!(
+        options.user_agent(),
+        format!("unia/{}", env!("CARGO_PKG_VERSION"))
+    );
+
+    let options = options.with_user_agent("my-app/1.0");
+    assert_eq!(options.user_agent(), "my-app/1.0");
+}